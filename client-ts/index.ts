@@ -1,17 +1,47 @@
 import * as lz4 from 'lz4js';
 
+export interface BFastTensor {
+    dtype: string;
+    shape: number[];
+    data: Int8Array | Uint8Array | Int16Array | Uint16Array | Int32Array | Uint32Array |
+        BigInt64Array | BigUint64Array | Float32Array | Float64Array;
+}
+
+// Value the producer kept outside the frame (blob_store); fetch it by token
+export interface BFastBlobRef {
+    blobRef: string;
+}
+
+// Value of a Python extension type (b_fast.register_ext): its ID and bytes
+export interface BFastExt {
+    extId: number;
+    data: Uint8Array;
+}
+
+// Field lists of schemas referenced by ID (header flag 0x08)
+const schemaRegistry = new Map<number, string[]>();
+
+// Codec ID in the "BC" envelope of large frames LZ4-compressed in chunks
+const CODEC_LZ4_CHUNKS = 4;
+
 interface BFastHeader {
     magic: number;
     flags: number;
     version: number;
     stringTableCount: number;
+    // Bytes after the header, recorded from format version 2 on
+    length?: number;
     stringTable: string[];
+    // Record layout fingerprint of a root list written by the batch path
+    fingerprint?: bigint;
 }
 
 class BFastParser {
     private view: DataView;
     private offset: number = 0;
     private header: BFastHeader;
+    // Containers in post-order, for frames written with structural sharing
+    private refs: any[] = [];
 
     constructor(view: DataView) {
         this.view = view;
@@ -32,9 +62,47 @@ class BFastParser {
         const version = this.view.getUint8(3);
         const stringTableCount = this.view.getUint16(4, true);
 
-        this.offset = 6;
+        // Version 2 headers add the u32 length of the rest of the frame
+        let length: number | undefined;
+        if (version === 1) {
+            this.offset = 6;
+        } else if (version === 2) {
+            if (this.view.byteLength < 10) {
+                throw new BFastError('Buffer too small for B-FAST header');
+            }
+            length = this.view.getUint32(6, true);
+            this.offset = 10;
+        } else {
+            throw new BFastError(`Unsupported B-FAST format version ${version}`);
+        }
         const stringTable: string[] = [];
 
+        // Schema frames: key IDs below the field count name schema fields
+        if ((flags & 0x08) !== 0) {
+            if (this.offset + 4 > this.view.byteLength) {
+                throw new BFastError('Buffer too small for B-FAST schema ID');
+            }
+            const schemaId = this.view.getUint32(this.offset, true);
+            const fields = schemaRegistry.get(schemaId);
+            if (fields === undefined) {
+                throw new BFastError(`Unknown schema ID ${schemaId}; register it with BFastDecoder.registerSchema()`);
+            }
+            stringTable.push(...fields);
+            this.offset += 4;
+        }
+
+        // Metadata section (header flag 0x80): a length-prefixed nested frame
+        if ((flags & 0x80) !== 0) {
+            if (this.offset + 4 > this.view.byteLength) {
+                throw new BFastError('Buffer too small for B-FAST metadata section');
+            }
+            const length = this.view.getUint32(this.offset, true);
+            this.offset += 4 + length;
+            if (this.offset > this.view.byteLength) {
+                throw new BFastError('Metadata section extends beyond buffer');
+            }
+        }
+
         // Parse string table
         for (let i = 0; i < stringTableCount; i++) {
             if (this.offset >= this.view.byteLength) {
@@ -51,7 +119,14 @@ class BFastParser {
             this.offset += length;
         }
 
-        return { magic, flags, version, stringTableCount, stringTable };
+        // Fingerprint (tag 0x62) in front of the root value
+        let fingerprint: bigint | undefined;
+        if (this.offset + 9 <= this.view.byteLength && this.view.getUint8(this.offset) === 0x62) {
+            fingerprint = this.view.getBigUint64(this.offset + 1, true);
+            this.offset += 9;
+        }
+
+        return { magic, flags, version, stringTableCount, length, stringTable, fingerprint };
     }
 
     parse(): any {
@@ -66,7 +141,234 @@ class BFastParser {
 
     private parseValue(): any {
         this.checkBounds(1);
+        const tag = this.view.getUint8(this.offset);
+        const value = this.parseTagged();
+        if ((this.header.flags & 0x04) !== 0 && (tag === 0x60 || tag === 0x70 || tag === 0x71 || tag === 0x72)) {
+            this.refs.push(value);
+        }
+        return value;
+    }
+
+    // Regular [keyId][value] object entries up to the 0x7F terminator
+    private parseEntries(obj: any): any {
+        while (this.offset < this.view.byteLength && this.view.getUint8(this.offset) !== 0x7F) {
+            this.checkBounds(4);
+            const keyId = this.view.getUint32(this.offset, true);
+            this.offset += 4;
+
+            if (keyId >= this.header.stringTable.length) {
+                throw new BFastError(`Invalid string table index: ${keyId}`);
+            }
+
+            const key = this.header.stringTable[keyId];
+            const value = this.parseValue();
+            obj[key] = value;
+        }
+
+        if (this.offset >= this.view.byteLength) {
+            throw new BFastError('Object not properly terminated');
+        }
+
+        this.offset++; // Skip 0x7F
+        return obj;
+    }
+
+    // [rows u32][columns u16], then per column [keyId u32][encoding u8][values]
+    private parseColumnar(): any[] {
+        this.checkBounds(6);
+        const rowCount = this.view.getUint32(this.offset, true);
+        const columns = this.view.getUint16(this.offset + 4, true);
+        this.offset += 6;
+
+        const rows: any[] = [];
+        for (let i = 0; i < rowCount; i++) {
+            rows.push({});
+        }
+        for (let c = 0; c < columns; c++) {
+            this.checkBounds(5);
+            const keyId = this.view.getUint32(this.offset, true);
+            const encoding = this.view.getUint8(this.offset + 4);
+            this.offset += 5;
+            if (keyId >= this.header.stringTable.length) {
+                throw new BFastError(`Invalid string table index: ${keyId}`);
+            }
+            const key = this.header.stringTable[keyId];
+
+            if (encoding === 0) {
+                for (const row of rows) {
+                    row[key] = this.parseValue();
+                }
+            } else if (encoding === 1) {
+                // Run-length encoded: [runs u32] then ([length u32][value])*
+                this.checkBounds(4);
+                const runs = this.view.getUint32(this.offset, true);
+                this.offset += 4;
+                let filled = 0;
+                for (let r = 0; r < runs; r++) {
+                    this.checkBounds(4);
+                    const length = this.view.getUint32(this.offset, true);
+                    this.offset += 4;
+                    const value = this.parseValue();
+                    if (filled + length > rowCount) {
+                        throw new BFastError('Run-length column exceeds the row count');
+                    }
+                    for (let i = filled; i < filled + length; i++) {
+                        rows[i][key] = value;
+                    }
+                    filled += length;
+                }
+                if (filled !== rowCount) {
+                    throw new BFastError('Run-length column does not cover every row');
+                }
+            } else if (encoding === 5) {
+                // Dictionary: [entries u32][code width u8], the entries, then
+                // one little-endian code per row
+                this.checkBounds(5);
+                const entryCount = this.view.getUint32(this.offset, true);
+                const width = this.view.getUint8(this.offset + 4);
+                this.offset += 5;
+                if (width !== 1 && width !== 2 && width !== 4) {
+                    throw new BFastError(`Invalid dictionary code width: ${width}`);
+                }
+                const entries: any[] = [];
+                for (let e = 0; e < entryCount; e++) {
+                    entries.push(this.parseValue());
+                }
+                this.checkBounds(rowCount * width);
+                rows.forEach((row, i) => {
+                    const at = this.offset + i * width;
+                    const code = width === 1 ? this.view.getUint8(at)
+                        : width === 2 ? this.view.getUint16(at, true)
+                        : this.view.getUint32(at, true);
+                    if (code >= entries.length) {
+                        throw new BFastError('Dictionary code out of range');
+                    }
+                    row[key] = entries[code];
+                });
+                this.offset += rowCount * width;
+            } else if (encoding === 2) {
+                const bits = this.parseBits(rowCount);
+                rows.forEach((row, i) => { row[key] = bits[i]; });
+            } else if (encoding === 4) {
+                const floats = this.parseXorFloats(rowCount);
+                rows.forEach((row, i) => { row[key] = floats[i]; });
+            } else if (encoding === 3) {
+                // Delta-of-delta: [length u32] then zigzag varints
+                this.checkBounds(4);
+                const length = this.view.getUint32(this.offset, true);
+                this.offset += 4;
+                this.checkBounds(length);
+                const end = this.offset + length;
+                let previous = 0n;
+                let delta = 0n;
+                rows.forEach((row, i) => {
+                    const encoded = this.readZigzag(end);
+                    let value = encoded;
+                    if (i === 1) value = BigInt.asIntN(64, previous + encoded);
+                    if (i > 1) value = BigInt.asIntN(64, previous + delta + encoded);
+                    if (i > 0) delta = BigInt.asIntN(64, value - previous);
+                    previous = value;
+                    row[key] = Number(value);
+                });
+                if (this.offset !== end) {
+                    throw new BFastError('Delta-encoded column length mismatch');
+                }
+            } else {
+                throw new BFastError(`Unknown column encoding: ${encoding}`);
+            }
+        }
+        return rows;
+    }
+
+    // Gorilla XOR floats: [length u32] then a bit stream, MSB first
+    private parseXorFloats(count: number): number[] {
+        this.checkBounds(4);
+        const length = this.view.getUint32(this.offset, true);
+        this.offset += 4;
+        this.checkBounds(length);
+        const start = this.offset;
+        let position = 0;
+        const read = (bits: number): bigint => {
+            if (position + bits > length * 8) {
+                throw new BFastError('XOR-encoded column is truncated');
+            }
+            let value = 0n;
+            for (let i = 0; i < bits; i++, position++) {
+                const byte = this.view.getUint8(start + (position >> 3));
+                value = (value << 1n) | BigInt((byte >> (7 - (position & 7))) & 1);
+            }
+            return value;
+        };
+
+        const scratch = new DataView(new ArrayBuffer(8));
+        const floats: number[] = [];
+        let previous = 0n;
+        let leading = -1;
+        let trailing = 0;
+        for (let i = 0; i < count; i++) {
+            if (i === 0) {
+                previous = read(64);
+            } else if (read(1) === 1n) {
+                if (read(1) === 1n) {
+                    leading = Number(read(5));
+                    const width = Number(read(6)) || 64;
+                    if (leading + width > 64) {
+                        throw new BFastError('Malformed XOR-encoded column');
+                    }
+                    trailing = 64 - leading - width;
+                }
+                if (leading < 0) {
+                    throw new BFastError('Malformed XOR-encoded column');
+                }
+                previous ^= read(64 - leading - trailing) << BigInt(trailing);
+            }
+            scratch.setBigUint64(0, previous);
+            floats.push(scratch.getFloat64(0));
+        }
+        if (Math.ceil(position / 8) !== length) {
+            throw new BFastError('Malformed XOR-encoded column');
+        }
+        this.offset += length;
+        return floats;
+    }
+
+    private readZigzag(end: number): bigint {
+        let value = 0n;
+        for (let shift = 0n; shift < 64n && this.offset < end; shift += 7n) {
+            const byte = this.view.getUint8(this.offset++);
+            value |= BigInt(byte & 0x7F) << shift;
+            if ((byte & 0x80) === 0) {
+                return BigInt.asIntN(64, (value >> 1n) ^ -(value & 1n));
+            }
+        }
+        throw new BFastError('Malformed varint in delta-encoded column');
+    }
+
+    // `count` booleans packed 8 per byte, least significant bit first
+    private parseBits(count: number): boolean[] {
+        const length = Math.ceil(count / 8);
+        this.checkBounds(length);
+        const bits: boolean[] = [];
+        for (let i = 0; i < count; i++) {
+            bits.push((this.view.getUint8(this.offset + (i >> 3)) & (1 << (i & 7))) !== 0);
+        }
+        this.offset += length;
+        return bits;
+    }
+
+    private parseTagged(): any {
         const tag = this.view.getUint8(this.offset++);
+
+        // Back-reference to an earlier container (shared, not copied)
+        if (tag === 0xA0 && (this.header.flags & 0x04) !== 0) {
+            this.checkBounds(4);
+            const index = this.view.getUint32(this.offset, true);
+            this.offset += 4;
+            if (index >= this.refs.length) {
+                throw new BFastError(`Invalid back-reference index: ${index}`);
+            }
+            return this.refs[index];
+        }
         
         // Null
         if (tag === 0x10) return null;
@@ -83,8 +385,27 @@ class BFastParser {
             return Number(value);
         }
         
-        // Small integers (bit-packed)
+        // Small integers (bit-packed): 0 to 15, and -16 to -1 from 0xB0
         if ((tag & 0xF0) === 0x30) return tag & 0x0F;
+        if ((tag & 0xF0) === 0xB0) return (tag & 0x0F) - 16;
+
+        // Integers sized by value (0xC1, 0xC2, 0xC4: 1, 2 or 4 bytes)
+        if (tag === 0xC1) {
+            this.checkBounds(1);
+            return this.view.getInt8(this.offset++);
+        }
+        if (tag === 0xC2) {
+            this.checkBounds(2);
+            const value = this.view.getInt16(this.offset, true);
+            this.offset += 2;
+            return value;
+        }
+        if (tag === 0xC4) {
+            this.checkBounds(4);
+            const value = this.view.getInt32(this.offset, true);
+            this.offset += 4;
+            return value;
+        }
         
         // Float64
         if (tag === 0x40) {
@@ -93,6 +414,14 @@ class BFastParser {
             this.offset += 8;
             return value;
         }
+
+        // Float32 (float32 encoders)
+        if (tag === 0x41) {
+            this.checkBounds(4);
+            const value = this.view.getFloat32(this.offset, true);
+            this.offset += 4;
+            return value;
+        }
         
         // Raw string
         if (tag === 0x50) {
@@ -117,30 +446,115 @@ class BFastParser {
             return array;
         }
         
+        // Bit-packed booleans
+        if (tag === 0x92) {
+            this.checkBounds(4);
+            const count = this.view.getUint32(this.offset, true);
+            this.offset += 4;
+            return this.parseBits(count);
+        }
+
+        // array.array - type code + little-endian items
+        if (tag === 0x93) {
+            this.checkBounds(5);
+            const code = String.fromCharCode(this.view.getUint8(this.offset));
+            const length = this.view.getUint32(this.offset + 1, true);
+            this.offset += 5;
+            this.checkBounds(length);
+            // Copy so the typed array is aligned regardless of the frame offset
+            const start = this.view.byteOffset + this.offset;
+            const raw = this.view.buffer.slice(start, start + length);
+            this.offset += length;
+            return decodeTypedArray(code, raw);
+        }
+
+        // range - start, stop, step; expanded to its values
+        if (tag === 0x94) {
+            const start = Number(this.parseValue());
+            const stop = Number(this.parseValue());
+            const step = Number(this.parseValue());
+            const values: number[] = [];
+            for (let i = start; step > 0 ? i < stop : i > stop; i += step) {
+                values.push(i);
+            }
+            return values;
+        }
+
+        // Value kept outside the frame - its token, left for the caller to fetch
+        if (tag === 0x95) {
+            this.checkBounds(4);
+            const length = this.view.getUint32(this.offset, true);
+            this.offset += 4;
+            this.checkBounds(length);
+            const bytes = new Uint8Array(this.view.buffer, this.view.byteOffset + this.offset, length);
+            this.offset += length;
+            const ref: BFastBlobRef = { blobRef: new TextDecoder().decode(bytes) };
+            return ref;
+        }
+
+        // Records stored column by column
+        if (tag === 0x61) {
+            return this.parseColumnar();
+        }
+
         // Object start
         if (tag === 0x70) {
+            return this.parseEntries({});
+        }
+
+        // Object with integer keys
+        if (tag === 0x72) {
+            this.checkBounds(4);
+            const count = this.view.getUint32(this.offset, true);
+            this.offset += 4;
             const obj: any = {};
-            while (this.offset < this.view.byteLength && this.view.getUint8(this.offset) !== 0x7F) {
-                this.checkBounds(4);
-                const keyId = this.view.getUint32(this.offset, true);
-                this.offset += 4;
-                
+            for (let i = 0; i < count; i++) {
+                const key = this.parseValue();
+                obj[String(key)] = this.parseValue();
+            }
+            return obj;
+        }
+
+        // Named sections: an index of (key ID, length) pairs, then the values
+        if (tag === 0x73) {
+            this.checkBounds(4);
+            const count = this.view.getUint32(this.offset, true);
+            this.offset += 4;
+            this.checkBounds(count * 8);
+            const index = this.offset;
+            this.offset += count * 8;
+            const obj: any = {};
+            for (let i = 0; i < count; i++) {
+                const keyId = this.view.getUint32(index + i * 8, true);
                 if (keyId >= this.header.stringTable.length) {
                     throw new BFastError(`Invalid string table index: ${keyId}`);
                 }
-                
-                const key = this.header.stringTable[keyId];
-                const value = this.parseValue();
-                obj[key] = value;
+                obj[this.header.stringTable[keyId]] = this.parseValue();
             }
-            
-            if (this.offset >= this.view.byteLength) {
-                throw new BFastError('Object not properly terminated');
-            }
-            
-            this.offset++; // Skip 0x7F
             return obj;
         }
+
+        // Object with a presence bitmap for its leading fields (absent = null)
+        if (tag === 0x71) {
+            this.checkBounds(6);
+            const firstId = this.view.getUint32(this.offset, true);
+            const count = this.view.getUint16(this.offset + 4, true);
+            this.offset += 6;
+            if (firstId + count > this.header.stringTable.length) {
+                throw new BFastError(`Invalid string table index: ${firstId + count - 1}`);
+            }
+            const bitmapLength = Math.ceil(count / 8);
+            this.checkBounds(bitmapLength);
+            const bitmapStart = this.offset;
+            this.offset += bitmapLength;
+
+            const obj: any = {};
+            for (let i = 0; i < count; i++) {
+                const present = (this.view.getUint8(bitmapStart + (i >> 3)) & (1 << (i & 7))) !== 0;
+                obj[this.header.stringTable[firstId + i]] = present ? this.parseValue() : null;
+            }
+            return this.parseEntries(obj);
+        }
         
         // Bytes
         if (tag === 0x80) {
@@ -168,11 +582,51 @@ class BFastParser {
             return Array.from(array);
         }
         
-        // DateTime (0xD1) - ISO 8601 string
+        // Typed tensor (0x91) - DLPack dtype code/bits + shape
+        if (tag === 0x91) {
+            this.checkBounds(3);
+            const code = this.view.getUint8(this.offset);
+            const bits = this.view.getUint8(this.offset + 1);
+            const ndim = this.view.getUint8(this.offset + 2);
+            this.offset += 3;
+            this.checkBounds(ndim * 4 + 4);
+            const shape: number[] = [];
+            for (let i = 0; i < ndim; i++) {
+                shape.push(this.view.getUint32(this.offset, true));
+                this.offset += 4;
+            }
+            const length = this.view.getUint32(this.offset, true);
+            this.offset += 4;
+            this.checkBounds(length);
+            // Copy so the typed array is aligned regardless of the frame offset
+            const start = this.view.byteOffset + this.offset;
+            const raw = this.view.buffer.slice(start, start + length);
+            this.offset += length;
+            return decodeTensor(code, bits, shape, raw);
+        }
+
+        // DateTime (0xD1) - ISO 8601 string, or epoch nanoseconds and UTC offset
         if (tag === 0xD1) {
             this.checkBounds(4);
             const length = this.view.getUint32(this.offset, true);
             this.offset += 4;
+            if (length === 0xFFFFFFFF) {
+                this.checkBounds(12);
+                const nanos = this.view.getBigInt64(this.offset, true);
+                const offset = this.view.getInt32(this.offset + 8, true);
+                this.offset += 12;
+                const millis = nanos / 1000000n - (nanos % 1000000n < 0n ? 1n : 0n);
+                const instant = new Date(Number(millis));
+                if (offset !== -0x80000000) {
+                    return instant;
+                }
+                // Naive datetimes are wall-clock times, read as local like ISO strings
+                return new Date(
+                    instant.getUTCFullYear(), instant.getUTCMonth(), instant.getUTCDate(),
+                    instant.getUTCHours(), instant.getUTCMinutes(), instant.getUTCSeconds(),
+                    instant.getUTCMilliseconds(),
+                );
+            }
             this.checkBounds(length);
             const bytes = new Uint8Array(this.view.buffer, this.view.byteOffset + this.offset, length);
             this.offset += length;
@@ -203,6 +657,36 @@ class BFastParser {
             return new TextDecoder().decode(bytes);
         }
         
+        // Date (0xD7) - i32 days since 1970-01-01, as UTC midnight like ISO dates
+        if (tag === 0xD7) {
+            this.checkBounds(4);
+            const days = this.view.getInt32(this.offset, true);
+            this.offset += 4;
+            return new Date(days * 86400000);
+        }
+
+        // Time (0xD8) - u64 nanoseconds since midnight, returned as an ISO string
+        if (tag === 0xD8) {
+            this.checkBounds(8);
+            const nanos = this.view.getBigUint64(this.offset, true);
+            this.offset += 8;
+            const seconds = Number(nanos / 1000000000n);
+            const fraction = Number(nanos % 1000000000n);
+            const pad = (value: number, width = 2) => String(value).padStart(width, '0');
+            let time = `${pad(Math.floor(seconds / 3600))}:${pad(Math.floor(seconds / 60) % 60)}:${pad(seconds % 60)}`;
+            if (fraction % 1000 !== 0) time += `.${pad(fraction, 9)}`;
+            else if (fraction !== 0) time += `.${pad(fraction / 1000, 6)}`;
+            return time;
+        }
+
+        // Timedelta (0xD6) - i64 nanoseconds, returned as milliseconds
+        if (tag === 0xD6) {
+            this.checkBounds(8);
+            const nanos = this.view.getBigInt64(this.offset, true);
+            this.offset += 8;
+            return Number(nanos) / 1e6;
+        }
+
         // UUID (0xD4) - hex string
         if (tag === 0xD4) {
             this.checkBounds(4);
@@ -227,11 +711,69 @@ class BFastParser {
             const decimalString = new TextDecoder().decode(bytes);
             return parseFloat(decimalString);
         }
+
+        // Custom object (0xD9) - class name, then the value its __bfast__ returned
+        if (tag === 0xD9) {
+            this.checkBounds(4);
+            const length = this.view.getUint32(this.offset, true);
+            this.offset += 4;
+            this.checkBounds(length);
+            this.offset += length;
+            return this.parseValue();
+        }
+
+        // Extension type (0xE0) - ID and raw bytes, left for the caller to decode
+        if (tag === 0xE0) {
+            this.checkBounds(5);
+            const extId = this.view.getUint8(this.offset);
+            const length = this.view.getUint32(this.offset + 1, true);
+            this.offset += 5;
+            this.checkBounds(length);
+            const data = new Uint8Array(this.view.buffer, this.view.byteOffset + this.offset, length).slice();
+            this.offset += length;
+            const ext: BFastExt = { extId, data };
+            return ext;
+        }
         
         throw new BFastError(`Unknown tag: 0x${tag.toString(16).padStart(2, '0')}`);
     }
 }
 
+function decodeTypedArray(code: string, raw: ArrayBuffer): BFastTensor['data'] {
+    switch (code) {
+        case 'b': return new Int8Array(raw);
+        case 'B': return new Uint8Array(raw);
+        case 'h': return new Int16Array(raw);
+        case 'H': return new Uint16Array(raw);
+        case 'i': return new Int32Array(raw);
+        case 'I': return new Uint32Array(raw);
+        case 'q': return new BigInt64Array(raw);
+        case 'Q': return new BigUint64Array(raw);
+        case 'f': return new Float32Array(raw);
+        case 'd': return new Float64Array(raw);
+        default: throw new BFastError(`Unsupported typed array code: ${code}`);
+    }
+}
+
+function decodeTensor(code: number, bits: number, shape: number[], raw: ArrayBuffer): BFastTensor {
+    const key = `${code}:${bits}`;
+    switch (key) {
+        case '0:8': return { dtype: 'int8', shape, data: new Int8Array(raw) };
+        case '0:16': return { dtype: 'int16', shape, data: new Int16Array(raw) };
+        case '0:32': return { dtype: 'int32', shape, data: new Int32Array(raw) };
+        case '0:64': return { dtype: 'int64', shape, data: new BigInt64Array(raw) };
+        case '1:8': return { dtype: 'uint8', shape, data: new Uint8Array(raw) };
+        case '1:16': return { dtype: 'uint16', shape, data: new Uint16Array(raw) };
+        case '1:32': return { dtype: 'uint32', shape, data: new Uint32Array(raw) };
+        case '1:64': return { dtype: 'uint64', shape, data: new BigUint64Array(raw) };
+        case '2:32': return { dtype: 'float32', shape, data: new Float32Array(raw) };
+        case '2:64': return { dtype: 'float64', shape, data: new Float64Array(raw) };
+        case '6:8': return { dtype: 'bool', shape, data: new Uint8Array(raw) };
+        default:
+            throw new BFastError(`Unsupported tensor dtype (code=${code}, bits=${bits})`);
+    }
+}
+
 function decompressBlockLz4(compressedData: Uint8Array): Uint8Array {
     if (compressedData.length < 4) {
         throw new BFastError('Compressed block too small');
@@ -252,7 +794,52 @@ function decompressBlockLz4(compressedData: Uint8Array): Uint8Array {
     return dst;
 }
 
+/** Decompress large frames, LZ4-compressed in parallel as
+ * [u32 size][u32 chunk count] then [u32 length][LZ4 block] per chunk. */
+function decompressChunksLz4(data: Uint8Array): Uint8Array {
+    const view = new DataView(data.buffer, data.byteOffset, data.byteLength);
+    const uncompressedSize = view.getUint32(0, true);
+    const chunksCount = view.getUint32(4, true);
+
+    let offset = 8;
+    const decompressedChunks: Uint8Array[] = [];
+    for (let i = 0; i < chunksCount; i++) {
+        if (offset + 4 > data.length) {
+            throw new BFastError('Unexpected end of data in parallel compression chunk headers');
+        }
+        const chunkLen = view.getUint32(offset, true);
+        offset += 4;
+        if (offset + chunkLen > data.length) {
+            throw new BFastError('Unexpected end of data in parallel compression chunk data');
+        }
+        const chunkData = new Uint8Array(
+            data.buffer.slice(data.byteOffset + offset, data.byteOffset + offset + chunkLen)
+        );
+        offset += chunkLen;
+
+        decompressedChunks.push(decompressBlockLz4(chunkData));
+    }
+
+    // Concatenate chunks
+    const result = new Uint8Array(uncompressedSize);
+    let writeOffset = 0;
+    for (const chunk of decompressedChunks) {
+        result.set(chunk, writeOffset);
+        writeOffset += chunk.length;
+    }
+    return result;
+}
+
 export class BFastDecoder {
+    /**
+     * Register the field list of a schema so frames referencing its ID decode
+     * @param id - Schema ID (BFastSchema.id on the Python side)
+     * @param fields - Field names in schema order
+     */
+    static registerSchema(id: number, fields: string[]): void {
+        schemaRegistry.set(id, [...fields]);
+    }
+
     /**
      * Decode B-FAST binary data to JavaScript objects
      * @param buffer - ArrayBuffer or Uint8Array containing B-FAST data
@@ -261,50 +848,53 @@ export class BFastDecoder {
     static decode(buffer: ArrayBuffer | Uint8Array): any {
         let data = buffer instanceof Uint8Array ? buffer : new Uint8Array(buffer);
 
+        // Compressed output of format version 2 sits behind a header of its
+        // own: the compressed flag, the length of what follows and the size
+        // of the inflated frame
+        let inflatedSize: number | undefined;
+        if (data.length >= 14 && data[0] === 0x42 && data[1] === 0x46 && (data[2] & 0x01) && data[3] === 2) {
+            const view = new DataView(data.buffer, data.byteOffset, data.byteLength);
+            const length = view.getUint32(6, true);
+            if (length < 4 || data.length < 10 + length) {
+                throw new BFastError('Truncated compressed frame');
+            }
+            inflatedSize = view.getUint32(10, true);
+            data = data.subarray(14, 10 + length);
+        }
+
+        // Zstandard frames (from b_fast.recompress) need a zstd decoder
+        if (data.length >= 4 && data[0] === 0x28 && data[1] === 0xb5 && data[2] === 0x2f && data[3] === 0xfd) {
+            throw new BFastError('Zstandard-compressed payloads are not supported; recompress with compression="lz4"');
+        }
+        // Standard LZ4 frames (compression="lz4-frame")
+        if (data.length >= 4 && data[0] === 0x04 && data[1] === 0x22 && data[2] === 0x4d && data[3] === 0x18) {
+            data = lz4.decompress(data);
+        }
+        // Chunked LZ4 and the other codecs wrap their output in a "BC" envelope
+        if (data.length >= 4 && data[0] === 0x42 && data[1] === 0x43 && data[3] === 0xff) {
+            if (data[2] !== CODEC_LZ4_CHUNKS) {
+                throw new BFastError(`Payloads compressed with codec ${data[2]} are not supported; recompress with compression="lz4"`);
+            }
+            data = decompressChunksLz4(data.subarray(4));
+        }
+
         // Auto-detect LZ4 compression (if doesn't start with 'BF' magic)
         if (data.length >= 2 && (data[0] !== 0x42 || data[1] !== 0x46)) {
             try {
                 // Try single-chunk decompression first
                 data = decompressBlockLz4(data);
             } catch (error) {
-                // Fall back to parallel chunk decompression
+                // Fall back to the chunks older encoders wrote without an envelope
                 try {
-                    const view = new DataView(data.buffer, data.byteOffset, data.byteLength);
-                    const uncompressedSize = view.getUint32(0, true);
-                    const chunksCount = view.getUint32(4, true);
-
-                    let offset = 8;
-                    const decompressedChunks: Uint8Array[] = [];
-                    for (let i = 0; i < chunksCount; i++) {
-                        if (offset + 4 > data.length) {
-                            throw new BFastError('Unexpected end of data in parallel compression chunk headers');
-                        }
-                        const chunkLen = view.getUint32(offset, true);
-                        offset += 4;
-                        if (offset + chunkLen > data.length) {
-                            throw new BFastError('Unexpected end of data in parallel compression chunk data');
-                        }
-                        const chunkData = new Uint8Array(
-                            data.buffer.slice(data.byteOffset + offset, data.byteOffset + offset + chunkLen)
-                        );
-                        offset += chunkLen;
-
-                        decompressedChunks.push(decompressBlockLz4(chunkData));
-                    }
-
-                    // Concatenate chunks
-                    const result = new Uint8Array(uncompressedSize);
-                    let writeOffset = 0;
-                    for (const chunk of decompressedChunks) {
-                        result.set(chunk, writeOffset);
-                        writeOffset += chunk.length;
-                    }
-                    data = result;
+                    data = decompressChunksLz4(data);
                 } catch (parallelError) {
                     throw new BFastError(`LZ4 decompression failed (single: ${error}, parallel: ${parallelError})`);
                 }
             }
         }
+        if (inflatedSize !== undefined && data.length !== inflatedSize) {
+            throw new BFastError(`Compressed frame inflated to ${data.length} bytes, but its header records ${inflatedSize}`);
+        }
 
         const view = new DataView(data.buffer, data.byteOffset, data.byteLength);
         return new BFastParser(view).parse();
@@ -4,8 +4,115 @@ B-FAST: Binary Fast Adaptive Serialization Transfer
 Ultra-fast binary serialization library with Rust backend.
 """
 
-from ._b_fast import BFast, BFastError
-from .integration import BFastResponse
+from ._b_fast import (
+    FORMAT_VERSION,
+    TAGS,
+    BFast,
+    BFastBatchEncoder,
+    BFastError,
+    BFastF32,
+    BFastRaw,
+    BFastReader,
+    BFastSchema,
+    BFastWriter,
+    LazyColumn,
+    LazyList,
+    PendingFrame,
+    SizeReport,
+    aggregate,
+    apply_patch,
+    concat,
+    count,
+    decode_range,
+    diff,
+    dump_tokens,
+    encode_async,
+    explain,
+    filter,
+    fingerprint,
+    frame_length,
+    from_json_bytes,
+    from_protobuf,
+    get,
+    hash,
+    is_expired,
+    may_match,
+    metadata,
+    recompress,
+    register_ext,
+    register_schema,
+    register_schema_resolver,
+    set_trace_hook,
+    slice,
+    spec,
+    stats,
+    to_json,
+    to_protobuf,
+)
+from .integration import MEDIA_TYPE, BFastCodec, BFastResponse
 
 __version__ = "1.3.0"
-__all__ = ["BFast", "BFastError", "BFastResponse"]
+__all__ = [
+    "FORMAT_VERSION",
+    "TAGS",
+    "BFast",
+    "BFastBatchEncoder",
+    "BFastCodec",
+    "BFastError",
+    "BFastF32",
+    "BFastRaw",
+    "BFastReader",
+    "BFastResponse",
+    "BFastSchema",
+    "BFastWriter",
+    "LazyColumn",
+    "LazyList",
+    "MEDIA_TYPE",
+    "PendingFrame",
+    "SizeReport",
+    "aggregate",
+    "apply_patch",
+    "concat",
+    "count",
+    "decode_range",
+    "diff",
+    "dump_tokens",
+    "encode_async",
+    "explain",
+    "filter",
+    "fingerprint",
+    "frame_length",
+    "from_json_bytes",
+    "from_protobuf",
+    "get",
+    "hash",
+    "is_expired",
+    "may_match",
+    "metadata",
+    "recompress",
+    "register_ext",
+    "register_schema",
+    "register_schema_resolver",
+    "set_trace_hook",
+    "slice",
+    "spec",
+    "stats",
+    "to_json",
+    "to_protobuf",
+]
+
+# Only in builds with the "redis" Cargo feature
+try:
+    from ._b_fast import decode_keyed, encode_keyed
+except ImportError:
+    pass
+else:
+    __all__ += ["decode_keyed", "encode_keyed"]
+
+# Only in builds with the "parquet" Cargo feature
+try:
+    from ._b_fast import to_parquet
+except ImportError:
+    pass
+else:
+    __all__ += ["to_parquet"]
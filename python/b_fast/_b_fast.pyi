@@ -1,10 +1,154 @@
-from typing import Any
+import asyncio
+import os
+from types import TracebackType
+from typing import (
+    Any,
+    Callable,
+    Dict,
+    Iterable,
+    Iterator,
+    List,
+    Literal,
+    Optional,
+    Protocol,
+    Tuple,
+    Type,
+    Union,
+)
+
+class SupportsWrite(Protocol):
+    def write(self, data: bytes, /) -> Any: ...
+
+class SupportsRead(Protocol):
+    def read(self, size: int, /) -> bytes: ...
 
 class BFast:
-    """Ultra-fast binary serializer with Rust backend."""
+    """
+    Ultra-fast binary serializer with Rust backend.
+
+    Subclasses may define encoding hooks, which encode_packed, encode_to,
+    encode_deferred and encode_packed_split call on the instance:
 
-    def __init__(self) -> None:
-        """Initialize B-FAST encoder with empty string table."""
+    - ``on_value(value)``: every value about to be written; the value
+      returned is written in its place
+    - ``on_key(key)``: every dict or object key; the key returned is written
+      (as its str) in its place
+    - ``on_unknown_type(obj)``: objects with no encoding of their own, which
+      are otherwise written as ``str(obj)``; the value returned is written
+      instead (returning ``obj`` keeps the fallback)
+
+    With hooks, lists of objects skip the record batch path, and
+    ``columnar`` and ``pack_bools`` do not apply.
+    """
+
+    def __init__(
+        self,
+        *,
+        dedup: bool = False,
+        schema: Optional[BFastSchema] = None,
+        validate: bool = False,
+        null_bitmap: bool = False,
+        columnar: bool = False,
+        flatten: bool = False,
+        column_stats: bool = False,
+        pack_bools: bool = False,
+        computed_fields: bool = False,
+        reveal_secrets: bool = False,
+        decimal_as_float: bool = False,
+        datetime_nanos: bool = False,
+        float32: bool = False,
+        batch_threshold: int = 8,
+        blob_store: Optional[Callable[[Union[str, bytes]], str]] = None,
+        blob_threshold: int = 65_536,
+        compression: Literal[
+            "lz4", "lz4-frame", "zstd", "snappy", "brotli", "none"
+        ] = "lz4",
+        compression_level: Optional[int] = None,
+        parallel_threshold: int = 1_000_000,
+        chunk_size: int = 262_144,
+        max_buffer_bytes: Optional[int] = None,
+        shrink_factor: Optional[int] = 8,
+        duplicate_keys: Optional[Literal["error", "first", "last"]] = None,
+        non_finite: Literal["allow", "null", "raise"] = "allow",
+    ) -> None:
+        """
+        Initialize B-FAST encoder with empty string table.
+
+        Args:
+            dedup: Replace repeated lists/dicts with back-references to their
+                first occurrence (structural sharing)
+            schema: Write the schema's 4-byte ID instead of its field names;
+                the schema is registered for decoding in this process
+            validate: Raise ValueError when a record (the root value or an
+                item of a root list) has a field the schema does not list,
+                lacks a field without a default, or holds a value of another
+                type than the field's annotation; requires ``schema``
+            null_bitmap: Mark None fields of each record in a presence bitmap
+                instead of writing their keys and null tags (sparse records)
+            columnar: Write lists of records with the same keys column by
+                column; columns of repeated values are run-length encoded,
+                boolean columns are bit-packed, sorted integer columns are
+                delta encoded, float columns are XOR (Gorilla) encoded and
+                string columns with few distinct values (countries,
+                statuses) are dictionary encoded, each when that is smaller
+            flatten: Write the fields of Pydantic models and dataclasses
+                nested in a record as fields of the record named
+                ``"address.city"`` (one level deep), before coercing and
+                validating; requires ``schema`` or ``columnar``
+            column_stats: Add the count, null count, min and max of each
+                record field to the frame statistics, for ``may_match`` and
+                ``aggregate`` to read; requires ``columnar``
+            pack_bools: Bit-pack lists of booleans, 8 per byte
+            computed_fields: Also write the ``@computed_field`` properties of
+                Pydantic models after their stored fields, as ``model_dump``
+                does; plain properties are never called
+            reveal_secrets: Write the value of Pydantic ``SecretStr`` and
+                ``SecretBytes`` fields; by default they are written as
+                ``"**********"``
+            decimal_as_float: Write ``Decimal`` values as floats; encoding
+                raises ``ValueError`` for a Decimal the float would round
+            datetime_nanos: Write datetimes and numpy datetime64 values as
+                epoch nanoseconds and their UTC offset instead of ISO
+                strings, as pandas Timestamps always are; values outside
+                1677-2262 raise ``OverflowError``
+            float32: Write floats (NumPy float64 arrays included) as 4-byte
+                f32 values, which decode as the nearest float; values beyond
+                the f32 range stay f64
+            batch_threshold: Lists with more items than this, all objects of
+                one class with the same fields, are written by a faster path
+                that inspects the first record only once
+            blob_store: Called with every str or bytes value longer than
+                blob_threshold bytes; it stores the value elsewhere (S3, disk)
+                and returns a str token, which is encoded in its place.
+                Decoding such frames needs a matching ``blob_resolver``
+            blob_threshold: Size in bytes (UTF-8 for str) above which values
+                go to blob_store
+            compression: Codec of frames encoded with ``compress=True``;
+                "lz4-frame" writes the standard LZ4 frame format that the
+                ``lz4`` tool reads; anything but "lz4" and "lz4-frame" needs
+                a Python decoder (see recompress). "brotli" is only
+                available in builds with the ``brotli`` feature
+            compression_level: Zstandard level (default 9) or brotli level
+                (0-11, default 11)
+            parallel_threshold: LZ4 frames of at least this many bytes are
+                split into chunks compressed on all cores
+            chunk_size: Size in bytes of those chunks; frames under two
+                chunks are compressed whole
+            max_buffer_bytes: Fail with ValueError instead of growing the
+                encode buffer past this many bytes, so one huge payload
+                cannot leave a long-lived encoder holding that much memory
+            shrink_factor: After a compressed frame, trim the encode buffer
+                back to the average frame size when it holds more than this
+                many times that; None keeps the largest buffer ever needed
+            duplicate_keys: How to write dict keys that give the same text
+                (``1`` and ``"1"``, or keys an ``on_key`` hook merges):
+                "error" raises ValueError, "first" keeps the first value and
+                "last" the last one, at the first key's position. None writes
+                every key as it comes
+            non_finite: What to write for NaN and infinite floats: "allow"
+                writes them as floats, "null" as None and "raise" raises
+                ValueError. Typed arrays and tensors keep their raw values
+        """
         ...
 
     def encode(self, data: Any) -> bytes:
@@ -19,29 +163,389 @@ class BFast:
         """
         ...
 
-    def encode_packed(self, data: Any, *, compress: bool = False) -> bytes:
+    def encode_packed(
+        self,
+        data: Any,
+        *,
+        compress: bool = False,
+        metadata: Optional[Dict[str, Any]] = None,
+        stats: Optional[List[str]] = None,
+        ttl: Optional[float] = None,
+        sections: bool = False,
+        release_gil: bool = False,
+    ) -> bytes:
         """
         Encode data to B-FAST binary format with optional LZ4 compression.
 
+        Objects defining ``__bfast__(self)`` are written as the structure it
+        returns, tagged with their class name so that decoding can rebuild
+        them through the ``__bfast_restore__`` classmethod. Otherwise, objects
+        whose class overrides ``__getstate__`` are written as their pickle
+        state instead of their ``__dict__``.
+
+        Pydantic model fields declared with ``Field(exclude=True)`` are left
+        out, as ``model_dump`` does.
+
+        pandas Timestamps are written as epoch nanoseconds. A pandas Series or
+        Index is written as its values only: the index and name of a Series
+        are dropped, so encode ``series.to_dict()`` to keep them.
+
         Args:
             data: Any serializable Python object
-            compress: Enable LZ4 compression for large payloads
+            compress: Enable LZ4 compression for large payloads; frames it
+                does not shrink are returned uncompressed
+            metadata: Dict written to the frame header (producer, trace ID,
+                ...), read back with ``b_fast.metadata()`` without decoding
+                the payload
+            stats: Fields to summarise in the frame header: the record
+                count (items of a root list), the count by type name and the
+                count, min, max and sum of the numeric values of each field
+                listed, read back with ``b_fast.stats()``; ``[]`` records
+                the counts only
+            ttl: Seconds the frame stays fresh: sets ``expires_at`` in the
+                metadata dict to now plus ``ttl``, checked with
+                ``b_fast.is_expired()``
+            sections: Write the dict ``data`` as named sections behind an
+                index, so ``b_fast.get(encoded, name)`` decodes one section
+                without walking the others (not with dedup)
+            release_gil: Copy ``data`` into Rust values first, then lay out
+                and compress the frame with the GIL released, so other
+                threads run meanwhile. Only data made of exact None, bool,
+                int, float, str, bytes, list, tuple and str-keyed dict values
+                is encoded this way, and only without dedup, null_bitmap,
+                columnar, pack_bools, validate, a blob store or hooks;
+                anything else is encoded as usual. The bytes are the same either way
 
         Returns:
             Binary data in B-FAST format (optionally compressed)
         """
         ...
 
-    def decode_packed(self, bytes: bytes, *, decompress: bool = True) -> Any:
+    def decode_packed(
+        self,
+        bytes: bytes,
+        *,
+        decompress: bool = True,
+        shared_refs: bool = False,
+        schema: Optional[BFastSchema] = None,
+        lazy: bool = False,
+        immutable: bool = False,
+        allowed_classes: Optional[Iterable[type]] = None,
+        ranges_as_lists: bool = False,
+        blob_resolver: Optional[Callable[[str], Any]] = None,
+        fingerprint: Optional[int] = None,
+        max_keys: int = 4096,
+        max_key_length: int = 128,
+        max_depth: int = 64,
+        parse_float: Literal["float", "decimal"] = "float",
+        duplicate_keys: Optional[Literal["error", "first", "last"]] = None,
+        aliases: Optional[Dict[str, str]] = None,
+    ) -> Any:
         """
         Decode B-FAST binary data to Python objects.
 
+        Dict keys are shared across calls on the same instance: a key seen in
+        an earlier frame comes back as the same str object, so frames with
+        the same fields create no new key strings.
+
         Args:
             bytes: bytes or bytearray containing B-FAST data (optionally compressed)
-            decompress: Decompress B-FAST data if compressed, otherwise parse directly
+            decompress: Decompress B-FAST data if compressed, otherwise parse directly;
+                LZ4 frames ("lz4-frame") are decompressed as they are read
+            shared_refs: Return the same object for every back-reference written by
+                a dedup encoder instead of an independent copy
+            schema: Reader schema; records (the root dict or the dicts of a
+                root list) are evolved to its fields: old names are mapped
+                through its aliases, unknown fields are dropped and missing
+                fields get their defaults
+            lazy: Return a root list as a LazyList that decodes elements on
+                first access (other payloads, and frames encoded with dedup,
+                are decoded as usual)
+            immutable: Decode lists as tuples and dicts as read-only
+                ``types.MappingProxyType`` views, so decoded objects can be
+                shared without defensive copies
+            allowed_classes: Classes decoding may instantiate (all by default).
+                Values of other classes come back as plain data: temporal
+                values, UUIDs and Decimals as strings (pandas Timedeltas as
+                integer nanoseconds), ``array.array`` values as lists and tensors as
+                ``{"dtype", "shape", "data"}`` dicts.
+                Objects encoded through ``__bfast__`` or ``__getstate__`` are
+                rebuilt (with ``__bfast_restore__``, or ``__setstate__`` like
+                pickle) only when their class is listed here, and otherwise
+                decode as the value that method returned. Records of tagged
+                union lists are rebuilt as their Pydantic model (without
+                validation) or dataclass the same way
+            ranges_as_lists: Expand encoded ``range`` values into lists of their
+                values instead of restoring ``range`` objects
+            blob_resolver: Called with the token of each value the encoder's
+                blob_store kept outside the frame; returns the value. Frames
+                with such tokens raise ValueError without it
+            fingerprint: Expected record layout fingerprint (see
+                ``b_fast.fingerprint``); frames carrying another one raise
+                ValueError before anything is decoded
+            max_keys: Most string table entries a frame may declare; checked
+                before the table is read
+            max_key_length: Most bytes in one string table entry
+            max_depth: Most levels of nesting, counting the innermost values
+                (at most 128)
+            parse_float: "decimal" decodes floats as ``decimal.Decimal`` of
+                their shortest repr (``0.1`` as ``Decimal("0.1")``), like
+                encoded Decimals; floats stay floats when ``allowed_classes``
+                leaves out Decimal
+            duplicate_keys: How to decode an object that repeats a key:
+                "error" raises ValueError, "first" keeps the first value and
+                "last" (like None, the default) the last one
+            aliases: Maps key names written in the frame to the names they
+                are decoded under, e.g. ``{"cid": "customer_id"}``, so
+                payloads written before a field was renamed still decode;
+                applies to every dict key and record field, before a
+                ``schema``
 
         Returns:
             Decoded Python object
+
+        Raises:
+            ValueError: The frame is malformed, exceeds max_keys or
+                max_key_length, or has another fingerprint, or parse_float
+                is neither "float" nor "decimal", or repeats a key with
+                duplicate_keys="error"
+            RecursionError: The frame nests deeper than max_depth
+        """
+        ...
+
+    def encode_packed_split(
+        self,
+        records: Iterable[Any],
+        max_frame_bytes: int = 1_048_576,
+        *,
+        compress: bool = False,
+    ) -> List[bytes]:
+        """
+        Encode records as a list of frames that each stay under a size cap.
+
+        Records are never cut in two: a frame is closed when the next record
+        would push it over ``max_frame_bytes``, and that record starts the
+        next frame with a string table of its own. Each frame decodes to a
+        list of records on its own, for transports with message size limits
+        such as Kafka and SQS.
+
+        Args:
+            records: Iterable of serializable records
+            max_frame_bytes: Largest frame to produce, measured before
+                compression (a frame is only kept compressed if that is smaller)
+            compress: Enable LZ4 compression for each frame
+
+        Returns:
+            The frames, in record order; empty for no records
+
+        Raises:
+            ValueError: If a single record does not fit in max_frame_bytes
+        """
+        ...
+
+    def encode_deferred(
+        self,
+        data: Any,
+        *,
+        compress: bool = True,
+        metadata: Optional[Dict[str, Any]] = None,
+        stats: Optional[List[str]] = None,
+        ttl: Optional[float] = None,
+        sections: bool = False,
+    ) -> "PendingFrame":
+        """
+        Encode data now and compress it on a background thread pool.
+
+        Serialization happens before this returns, so ``data`` may be changed
+        right away; only the compression is left running. Useful when the
+        bytes are only needed later, e.g. when a socket becomes writable.
+
+        Args:
+            data: Any serializable Python object
+            compress: Enable LZ4 compression for large payloads
+            metadata: Dict written to the frame header, as for encode_packed
+            stats: Fields to summarise in the frame header, as for
+                encode_packed
+            ttl: Seconds the frame stays fresh, as for encode_packed
+            sections: Write the dict ``data`` as named sections, as for
+                encode_packed
+
+        Returns:
+            Handle whose ``result()`` gives the same bytes as encode_packed
+        """
+        ...
+
+    def encode_columns(
+        self, columns: Dict[str, Any], *, compress: bool = False
+    ) -> bytes:
+        """
+        Encode a dict of columns as a columnar list of records.
+
+        The rows are never built: numeric NumPy arrays are read straight from
+        their buffers, other columns (lists, object arrays) value by value.
+        ``decode_packed`` returns the list of records; ``decode_columns``
+        returns the columns.
+
+        Args:
+            columns: Column name to a one-dimensional array or sequence; every
+                column has the same length
+            compress: Enable LZ4 compression for large payloads
+
+        Returns:
+            Encoded bytes
+
+        Raises:
+            ValueError: The columns differ in length or an array is not
+                one-dimensional
+        """
+        ...
+
+    def decode_columns(
+        self,
+        data: bytes,
+        *,
+        decompress: bool = True,
+        arrays: bool = True,
+        lazy: bool = False,
+        max_keys: int = 4096,
+        max_key_length: int = 128,
+        max_depth: int = 64,
+    ) -> Dict[str, Any]:
+        """
+        Decode a columnar list of records into a dict of columns.
+
+        Lists of objects written by the batch path decode the same way, one
+        column per field. Columns of only ints or only floats are read into
+        a typed buffer without building a Python object per value.
+
+        Args:
+            data: Bytes from encode_columns, from an encoder with
+                ``columnar=True`` or of a list of objects of one class
+            decompress: Whether the data may be LZ4 compressed
+            arrays: Return int, float and bool columns as NumPy arrays
+                (int64, float64, bool); other columns are always lists
+            lazy: Without ``arrays``, return int and float columns as
+                LazyColumns that build Python numbers on access
+            max_keys, max_key_length, max_depth: Limits checked while
+                decoding, as in decode_packed
+
+        Returns:
+            Column name to column values
+
+        Raises:
+            ValueError: The payload is neither columnar nor a batch-written
+                list, or its records do not share their fields
+            ImportError: ``arrays`` is set and NumPy is not installed
+        """
+        ...
+
+    def encode_to(
+        self,
+        data: Any,
+        fp: SupportsWrite,
+        *,
+        compress: bool = False,
+        metadata: Optional[Dict[str, Any]] = None,
+        stats: Optional[List[str]] = None,
+        ttl: Optional[float] = None,
+        sections: bool = False,
+    ) -> int:
+        """
+        Encode data and write it to a file-like object in 64 KiB chunks.
+
+        Args:
+            data: Any serializable Python object
+            fp: Any object with a write() method (file, BytesIO, gzip file,
+                socket makefile). write() must return the number of bytes
+                it took; a None return raises BlockingIOError
+            compress: Enable LZ4 compression for large payloads
+            metadata: Dict written to the frame header, as for encode_packed
+            stats: Fields to summarise in the frame header, as for
+                encode_packed
+            ttl: Seconds the frame stays fresh, as for encode_packed
+            sections: Write the dict ``data`` as named sections, as for
+                encode_packed
+
+        Returns:
+            Number of bytes written
+        """
+        ...
+
+    def decode_mmap(
+        self,
+        path: Union[str, "os.PathLike[str]"],
+        *,
+        copy: bool = False,
+        max_keys: int = 4096,
+        max_key_length: int = 128,
+        max_depth: int = 64,
+    ) -> Any:
+        """
+        Decode a B-FAST file through a read-only memory map.
+
+        A list is returned as a LazyList over the map, so only the offsets of
+        its elements are read up front and each element decodes on first access.
+
+        Args:
+            path: Path to an encoded file
+            copy: Return bytes copies instead of memoryviews into the map
+            max_keys, max_key_length, max_depth: Limits checked while
+                decoding, as in decode_packed; elements of a LazyList are
+                checked when they decode
+
+        Returns:
+            LazyList for a list, otherwise the decoded Python object; bytes
+            values are memoryviews into the mapped file unless copy=True
+            (compressed files are always inflated into memory)
+        """
+        ...
+
+    def decode_from(
+        self,
+        fp: SupportsRead,
+        *,
+        decompress: bool = True,
+        max_keys: int = 4096,
+        max_key_length: int = 128,
+        max_depth: int = 64,
+    ) -> Any:
+        """
+        Read B-FAST data from a file-like object and decode it.
+
+        Args:
+            fp: Any object with readinto() or read() (file, BytesIO, gzip file,
+                socket makefile)
+            decompress: Decompress B-FAST data if compressed, otherwise parse directly;
+                LZ4 frames ("lz4-frame") are decompressed as they are read
+            max_keys, max_key_length, max_depth: Limits checked while
+                decoding, as in decode_packed
+
+        Returns:
+            Decoded Python object
+        """
+        ...
+
+    def memory_usage(self) -> Dict[str, int]:
+        """
+        Report the memory the encoder keeps between calls.
+
+        Returns:
+            Bytes held by "work_buffer", "string_table", "key_cache" and
+            "dedup_spans", their "total", and the "string_table_entries"
+            count
+        """
+        ...
+
+    def shrink_to_fit(self) -> int:
+        """
+        Release the buffer capacity kept from earlier payloads.
+
+        An encoder keeps the largest buffer it ever needed, so a long-lived
+        encoder that wrote one huge payload can call this afterwards. Keys
+        already in the string table are kept.
+
+        Returns:
+            The number of bytes released
         """
         ...
 
@@ -59,6 +563,992 @@ class BFast:
         """
         ...
 
+class BFastWriter:
+    """Append-only writer for bfast-lines containers (length-prefixed frames)."""
+
+    def __init__(
+        self,
+        target: Union[str, "os.PathLike[str]", SupportsWrite],
+        *,
+        compress: bool = False,
+        log: bool = False,
+        fsync_every: int = 0,
+    ) -> None:
+        """
+        Args:
+            target: Path (opened in append mode) or file-like object with write()
+            compress: Enable LZ4 compression for each frame
+            log: Write checksummed log records; reopening a log path drops a torn
+                final record and raises ``ValueError`` for a corrupt record
+                followed by others, leaving the file unchanged
+            fsync_every: fsync the file after every N frames (0 disables)
+        """
+        ...
+
+    def append(self, obj: Any) -> int:
+        """Encode obj as one independently decodable frame; returns bytes written."""
+        ...
+
+    def extend(self, objs: Iterable[Any]) -> int:
+        """Append one frame per object; returns bytes written."""
+        ...
+
+    @property
+    def frames(self) -> int:
+        """Number of frames written by this writer."""
+        ...
+
+    @property
+    def recovered_bytes(self) -> int:
+        """Bytes of a torn log tail truncated when the writer was opened."""
+        ...
+
+    def sync(self) -> None:
+        """Flush and fsync the underlying file."""
+        ...
+
+    def flush(self) -> None: ...
+    def close(self) -> None: ...
+    def __enter__(self) -> "BFastWriter": ...
+    def __exit__(
+        self,
+        exc_type: Optional[Type[BaseException]],
+        exc_value: Optional[BaseException],
+        traceback: Optional[TracebackType],
+    ) -> bool: ...
+
+class BFastReader:
+    """Iterates over the frames of a bfast-lines container."""
+
+    def __init__(
+        self,
+        source: Union[str, "os.PathLike[str]", SupportsRead],
+        *,
+        decompress: bool = True,
+        log: bool = False,
+        position: Optional[int] = None,
+        max_keys: int = 4096,
+        max_key_length: int = 128,
+        max_depth: int = 64,
+    ) -> None:
+        """
+        Args:
+            source: Path or file-like object with read()
+            decompress: Decompress frames if compressed, otherwise parse directly
+            log: Read checksummed log records, stopping cleanly at a torn tail;
+                a corrupt record with others behind it raises ValueError
+            position: Seek to this value of position() before reading, to resume
+                an earlier reader
+            max_keys, max_key_length, max_depth: Limits every frame is
+                decoded with, as in decode_packed
+        """
+        ...
+
+    def __iter__(self) -> Iterator[Any]: ...
+    def __next__(self) -> Any: ...
+    def read_many(self, count: Optional[int] = None) -> List[Any]:
+        """
+        Decode the next ``count`` documents, or all remaining ones.
+
+        Checksums and decompression run on all cores with the GIL released;
+        only building the Python objects happens on the calling thread. An
+        empty list means the container is exhausted.
+
+        A record that fails to decode raises, as from ``__next__``: the
+        documents before it are returned first and it raises on the next
+        call, after which the records behind it are read as usual.
+        ``position()`` stays at the first record not returned.
+        """
+        ...
+
+    @property
+    def frames(self) -> int:
+        """Number of frames decoded so far."""
+        ...
+
+    def decode_next(self) -> Any:
+        """Decode the next document; raises EOFError when no complete frame is left."""
+        ...
+
+    def position(self) -> int:
+        """
+        Byte offset in the source past every byte read so far, which is the
+        next frame after a successful read.
+
+        Persist it and pass it as ``position`` to a new reader to continue
+        where this one stopped. Without ``position``, offsets start from the
+        source's tell(), or 0 when it cannot tell.
+        """
+        ...
+
+    def remaining(self) -> int:
+        """Bytes from position() to the current end of a seekable source."""
+        ...
+
+    @property
+    def truncated(self) -> bool:
+        """True when log iteration stopped at a torn or corrupt record."""
+        ...
+
+    def close(self) -> None: ...
+    def __enter__(self) -> "BFastReader": ...
+    def __exit__(
+        self,
+        exc_type: Optional[Type[BaseException]],
+        exc_value: Optional[BaseException],
+        traceback: Optional[TracebackType],
+    ) -> bool: ...
+
+class BFastBatchEncoder:
+    """Encodes a list of records one record at a time."""
+
+    def __init__(self, *, compress: bool = False) -> None:
+        """
+        Args:
+            compress: Enable LZ4 compression of the finished frame
+        """
+        ...
+
+    def add(self, record: Any) -> None:
+        """
+        Encode record and append it to the batch.
+
+        Keys share one string table across the batch. A record that fails to
+        encode is dropped and the batch stays usable.
+        """
+        ...
+
+    def extend(self, records: Iterable[Any]) -> int:
+        """Add every record of an iterable; returns the batch's record count."""
+        ...
+
+    def finish(self) -> bytes:
+        """
+        Return the frame of the list of added records.
+
+        The bytes decode like ``encode_packed`` of the same list. The encoder
+        cannot be used afterwards.
+        """
+        ...
+
+    def __len__(self) -> int: ...
+
+def diff(old: Any, new: Any) -> bytes:
+    """
+    Compute a compact binary patch between the encodings of two objects.
+
+    The patch is a B-FAST frame holding a list of [op, path, value] operations.
+
+    Args:
+        old: Object the receiver already has
+        new: Updated object
+
+    Returns:
+        Patch bytes for apply_patch()
+    """
+    ...
+
+def apply_patch(encoded: bytes, patch: bytes) -> bytes:
+    """
+    Apply a patch from diff() to an encoded payload without re-encoding it.
+
+    Args:
+        encoded: B-FAST bytes of the old object (compressed or not)
+        patch: Output of diff()
+
+    Returns:
+        Updated B-FAST bytes, compressed if the input was
+
+    Raises:
+        ValueError: If the patch does not match the payload structure
+    """
+    ...
+
+def encode_async(
+    data: Any, *, compress: bool = False, encoder: Optional[BFast] = None
+) -> "asyncio.Future[bytes]":
+    """
+    Encode data for use inside a running event loop.
+
+    The object is walked on the calling thread, which needs the GIL either
+    way; compression runs on the shared thread pool with the GIL released,
+    and the loop keeps serving other tasks meanwhile. There is no
+    ``decode_async``: decoding builds Python objects throughout, so it would
+    hold the GIL, and block the loop, on any thread.
+
+    Args:
+        data: Any serializable Python object
+        compress: Enable LZ4 compression for large payloads
+        encoder: Encoder whose options and hooks to use
+
+    Returns:
+        A future resolving to the same bytes as ``encode_packed``
+    """
+    ...
+
+def encode_keyed(data: Any, *, compress: bool = False) -> Tuple[str, bytes]:
+    """
+    Encode data for a content-addressed cache entry.
+
+    Only available in builds with the ``redis`` feature. The payload is the
+    canonical encoding, and the key suffix is ``hash(data).hex()``, so equal
+    values map to one key whatever their dict order or compression.
+
+    Args:
+        data: Any serializable Python object
+        compress: Enable LZ4 compression for large payloads
+
+    Returns:
+        ``(key_suffix, payload)``; store the payload under ``prefix + key_suffix``
+    """
+    ...
+
+def decode_keyed(key: str, data: bytes) -> Any:
+    """
+    Decode a payload from ``encode_keyed`` after checking it against its key.
+
+    Only available in builds with the ``redis`` feature.
+
+    Args:
+        key: Cache key the payload was read from, ending with its key suffix
+        data: The payload
+
+    Returns:
+        The decoded object
+
+    Raises:
+        ValueError: The payload's hash does not match the key
+    """
+    ...
+
+def to_parquet(data: bytes, path: Union[str, "os.PathLike[str]"]) -> int:
+    """
+    Write an encoded list of records to a Parquet file without decoding it.
+
+    Only available in builds with the ``parquet`` feature. Every field becomes
+    a nullable column typed from its values: int, float (ints and floats
+    together become float), bool or str. Records without a field get a null.
+
+    Args:
+        data: B-FAST bytes of a list of dicts or models (compressed or not;
+            not encoded with dedup, null_bitmap or columnar)
+        path: Output file, overwritten if it exists
+
+    Returns:
+        Number of rows written
+
+    Raises:
+        ValueError: A field holds nested values or mixes types
+    """
+    ...
+
+def to_protobuf(
+    data: bytes, descriptor: bytes, message: str
+) -> Union[bytes, List[bytes]]:
+    """
+    Re-encode records as protobuf messages described at runtime.
+
+    Fields map by name; enums are written from their numbers and map fields
+    from dicts. None values and missing keys are left out of the message.
+
+    Args:
+        data: B-FAST bytes of a dict, or of a list of dicts
+        descriptor: Serialized ``FileDescriptorSet``, as written by
+            ``protoc --include_imports --descriptor_set_out``
+        message: Full name of the message type, e.g. ``"orders.v1.Order"``
+
+    Returns:
+        The message bytes, or a list of them when ``data`` holds a list
+
+    Raises:
+        ValueError: The message type is unknown or a key is not one of its
+            fields
+        TypeError: A value does not fit its field's type
+    """
+    ...
+
+def from_json_bytes(json_bytes: bytes, *, compress: bool = False) -> bytes:
+    """
+    Encode a JSON document without creating Python objects.
+
+    The document is parsed in Rust and laid out with the GIL released; the
+    bytes are those ``BFast().encode_packed(json.loads(json_bytes), compress)``
+    returns. A key repeated in an object keeps its last value, like
+    ``json.loads``, and integers beyond the 64-bit range become floats.
+
+    Args:
+        json_bytes: UTF-8 JSON document
+        compress: Enable LZ4 compression
+
+    Returns:
+        B-FAST bytes of the document
+
+    Raises:
+        ValueError: The document is not valid JSON (``NaN`` and ``Infinity``
+            included) or holds a number beyond the float range
+    """
+    ...
+
+def to_json(data: bytes, *, fields: Optional[Iterable[str]] = None) -> bytes:
+    """
+    Transcode a payload to compact UTF-8 JSON without creating Python objects.
+
+    Values come out as ``BFast().decode_packed(data, allowed_classes=[])``
+    returns them: temporal values, UUIDs and Decimals as strings, pandas
+    Timedeltas as integer nanoseconds, ``array.array`` values and ranges as lists and
+    objects encoded through ``__bfast__`` or ``__getstate__`` as their state.
+    NaN and infinities, which JSON lacks, are written as ``null``.
+
+    Args:
+        data: B-FAST encoded bytes
+        fields: Fields to keep in the root record, or in every record of a
+            root list; ``"customer.name"`` keeps ``name`` of the nested
+            ``customer`` record, and a field named ``"customer.name"``
+            (as ``flatten=True`` writes) too. Fields a record lacks are
+            left out
+
+    Returns:
+        UTF-8 JSON bytes
+
+    Raises:
+        ValueError: The frame is malformed, was encoded with dedup,
+            null_bitmap, columnar or pack_bools, or holds a value with no
+            JSON form (bytes, tensors, extension values, external blobs)
+    """
+    ...
+
+def from_protobuf(
+    data: Union[bytes, Iterable[bytes]],
+    descriptor: bytes,
+    message: str,
+    *,
+    compress: bool = False,
+) -> bytes:
+    """
+    Encode protobuf messages as B-FAST records.
+
+    Every field of the message type is present in the record; fields absent
+    from the message get their proto3 default (None for messages). Unknown
+    field numbers are skipped.
+
+    Args:
+        data: One message, or an iterable of messages for a list of records
+        descriptor: Serialized ``FileDescriptorSet``
+        message: Full name of the message type
+        compress: Enable LZ4 compression
+
+    Returns:
+        B-FAST bytes of the record, or of the list of records
+
+    Raises:
+        ValueError: The message type is unknown or a message is malformed
+    """
+    ...
+
+def get(data: bytes, path: str, default: Any = None) -> Any:
+    """
+    Extract one value from encoded bytes without decoding the rest.
+
+    Args:
+        data: B-FAST bytes (compressed or not)
+        path: Keys separated by dots with list indices in brackets,
+            e.g. "orders[3].customer.id"; "" selects the whole payload
+        default: Returned when the path does not exist
+
+    Returns:
+        The decoded value at path
+    """
+    ...
+
+def metadata(data: bytes) -> Optional[Dict[str, Any]]:
+    """
+    Read the metadata dict of a frame without decoding its payload.
+
+    Args:
+        data: B-FAST bytes (compressed or not)
+
+    Returns:
+        The dict passed as ``metadata=`` when encoding, or None
+    """
+    ...
+
+def stats(data: bytes) -> Optional[Dict[str, Any]]:
+    """
+    Read the statistics of a frame without decoding its payload.
+
+    Args:
+        data: B-FAST bytes (compressed or not)
+
+    Returns:
+        ``{"records": ..., "types": {...}, "fields": {...}}`` for frames
+        encoded with ``stats=``, with ``"columns"`` added by
+        ``column_stats=True``, or None
+    """
+    ...
+
+def may_match(data: bytes, field: str, op: str, value: Any) -> bool:
+    """
+    Check from the column statistics of a frame whether any of its records
+    may hold ``field op value``, without decoding its payload.
+
+    Args:
+        data: B-FAST bytes (compressed or not)
+        field: Record field, as named in the records
+        op: "==", "!=", "<", "<=", ">" or ">="
+        value: Operand; with None, "==" and "!=" test for None fields
+
+    Returns:
+        False when no record can match; True when one may, or the frame was
+        not encoded with ``column_stats=True``
+    """
+    ...
+
+def is_expired(data: bytes, now: Optional[Any] = None) -> bool:
+    """
+    Check the ``expires_at`` metadata field of a frame without decoding its
+    payload.
+
+    ``expires_at`` is epoch seconds or a datetime (naive ones are taken as
+    UTC), as set by ``ttl=`` or passed in ``metadata=``.
+
+    Args:
+        data: B-FAST bytes (compressed or not)
+        now: Time to compare with, epoch seconds or a datetime; the current
+            time by default
+
+    Returns:
+        True when ``expires_at`` is at or before ``now``; False for frames
+        without one
+    """
+    ...
+
+def fingerprint(data: bytes) -> Optional[int]:
+    """
+    Read the record layout fingerprint of a frame without decoding it.
+
+    Lists of records of one class written by the batch path carry a 64-bit
+    hash of their field names and value types, so consumers can notice a
+    producer whose records changed shape before decoding them.
+
+    Args:
+        data: B-FAST bytes (compressed or not)
+
+    Returns:
+        The fingerprint, or None for frames without one
+    """
+    ...
+
+def frame_length(header: bytes) -> int:
+    """
+    Size of the frame that starts with ``header``, from its header alone.
+
+    Frames record the length of what follows their 10-byte header, so a
+    reader taking frames off a socket or pipe reads 10 bytes, then the rest.
+    Compressed frames have a header of their own in front of the compressed
+    bytes, recording their length and the size of the frame once inflated.
+
+    Args:
+        header: At least the first 10 bytes of a frame, compressed or not
+
+    Returns:
+        The frame's size in bytes, header included
+
+    Raises:
+        ValueError: If ``header`` is too short or from a version 1 frame
+            (which has no length field)
+    """
+    ...
+
+def aggregate(
+    data: bytes,
+    field: Optional[str] = None,
+    op: Literal["sum", "count", "min", "max"] = "sum",
+) -> Union[int, float, None]:
+    """
+    Aggregate a field over an encoded list without building Python objects.
+
+    Missing and null fields are ignored. Frames encoded with
+    ``column_stats=True`` answer "count", "min" and "max" of a record field
+    from their column statistics. Frames encoded with dedup, null_bitmap,
+    columnar or pack_bools are decoded first.
+
+    Args:
+        data: B-FAST bytes of a list (compressed or not)
+        field: Path of the value inside each element (same syntax as get());
+            None aggregates the elements themselves
+        op: "sum", "count", "min" or "max"
+
+    Returns:
+        The aggregate; min/max of no values is None
+    """
+    ...
+
+def filter(
+    data: bytes,
+    field: str,
+    op: Literal["==", "!=", "<", "<=", ">", ">="],
+    value: Union[None, bool, int, float, str],
+    *,
+    decode: bool = False,
+) -> Union[bytes, List[Any]]:
+    """
+    Keep the elements of an encoded list whose field matches a predicate,
+    reading the fields from the encoded bytes.
+
+    Comparisons follow Python for None, bools, numbers and strings; fields of
+    other types only match ``!=``, and orderings never match across types.
+    Elements without the field are dropped. Frames whose column statistics
+    rule the predicate out give an empty list without being scanned; frames
+    encoded with dedup, null_bitmap, columnar or pack_bools are decoded to be
+    filtered.
+
+    Args:
+        data: B-FAST bytes of a list (compressed or not)
+        field: Path of the value inside each element (same syntax as get())
+        op: "==", "!=", "<", "<=", ">" or ">="
+        value: Operand; with None, "==" and "!=" test for None fields
+        decode: Return the decoded elements instead of a frame
+
+    Returns:
+        A frame of the matching elements, compressed when ``data`` was and
+        keeping its metadata, or their list with ``decode=True``
+    """
+    ...
+
+def concat(blobs: Iterable[bytes], *, compress: Optional[bool] = None) -> bytes:
+    """
+    Merge frames whose payloads are lists without decoding them.
+
+    String tables are merged and object key IDs remapped at the byte level.
+    Metadata sections of the inputs are not carried over.
+
+    Args:
+        blobs: B-FAST bytes of lists (compressed or not)
+        compress: Compress the result; None compresses if any input was
+
+    Returns:
+        One frame holding the elements of every input, in order
+    """
+    ...
+
+def slice(
+    data: bytes, start: Optional[int] = None, stop: Optional[int] = None
+) -> bytes:
+    """
+    Cut elements start:stop out of an encoded list without decoding it.
+
+    Bounds follow Python slice semantics. The string table of the result only
+    keeps the keys used by the selected elements.
+
+    Args:
+        data: B-FAST bytes of a list (compressed or not)
+        start: First element (negative counts from the end)
+        stop: End of the range, exclusive
+
+    Returns:
+        A valid frame, compressed if the input was
+    """
+    ...
+
+def count(data: bytes) -> int:
+    """
+    Count the elements of an encoded list without decoding them.
+
+    Args:
+        data: B-FAST bytes of a list (compressed or not)
+
+    Returns:
+        The number of elements, read from the list header
+
+    Raises:
+        ValueError: If the payload is not a list
+    """
+    ...
+
+def decode_range(
+    data: bytes, start: Optional[int] = None, stop: Optional[int] = None
+) -> List[Any]:
+    """
+    Decode elements start:stop of an encoded list.
+
+    Elements before ``start`` are skipped by their encoded lengths and those
+    from ``stop`` on are not read, so a page costs about as much as its own
+    elements. Frames encoded with dedup, null_bitmap, columnar or pack_bools
+    are decoded in full and sliced.
+
+    Args:
+        data: B-FAST bytes of a list (compressed or not)
+        start: First element (negative counts from the end)
+        stop: End of the range, exclusive
+
+    Returns:
+        The decoded elements, as ``decode_packed(data)[start:stop]``
+
+    Raises:
+        ValueError: If the payload is not a list
+    """
+    ...
+
+def recompress(
+    data: bytes,
+    compression: Literal[
+        "zstd", "lz4", "lz4-frame", "snappy", "brotli", "none"
+    ] = "zstd",
+    level: Optional[int] = None,
+) -> bytes:
+    """
+    Recompress an encoded payload without re-encoding it.
+
+    The header, string table and payload bytes are kept as they are; only the
+    compression wrapper changes. Zstandard, snappy and brotli payloads decode
+    with every Python API but not with the TypeScript client; brotli needs a
+    build with the ``brotli`` feature.
+
+    Args:
+        data: B-FAST bytes (compressed or not)
+        compression: "zstd", "lz4", "lz4-frame", "snappy", "brotli" or "none"
+        level: Zstandard level (default 9) or brotli level (0-11, default
+            11); ignored by the other codecs
+
+    Returns:
+        The recompressed payload
+    """
+    ...
+
+def hash(obj: Any) -> bytes:
+    """
+    Stable 128-bit content hash of an object.
+
+    Hashes the canonical encoding (sorted dict keys and set elements) with
+    XXH3-128, so equal values hash the same across runs and processes.
+
+    Args:
+        obj: Any serializable Python object (dicts, Pydantic models, ...)
+
+    Returns:
+        16-byte digest
+    """
+    ...
+
+def dump_tokens(data: bytes) -> Dict[str, Any]:
+    """
+    Disassemble an encoded payload for debugging.
+
+    Every value of the payload becomes an ``(offset, tag, length, preview)``
+    tuple; the tuple of a list, object or columnar list is followed by a list
+    holding the tokens of its children. Bytes that cannot be followed end the
+    listing with an ``"error"`` token instead of raising.
+
+    Args:
+        data: Encoded payload (compressed or not)
+
+    Returns:
+        Dict with "header" (version, length, compressed, flags, schema_id,
+        metadata_size, string_count), "strings" (``(offset, text)`` per
+        string table entry, offset None for schema fields) and "tokens".
+        Offsets refer to the decompressed frame.
+
+    Raises:
+        ValueError: If the header or string table is invalid
+    """
+    ...
+
+FORMAT_VERSION: int
+"""Version byte written in the header of every frame."""
+
+TAGS: Dict[int, str]
+"""Tag byte to the name of the value type it introduces."""
+
+def spec() -> Dict[str, Any]:
+    """
+    Describe the wire format this build reads and writes.
+
+    Returns:
+        Dict with "version", "magic", "byte_order", "header" (``name`` and
+        ``layout`` per field, in order), "flags" (name to bit), "tags" (tag
+        byte to ``name`` and ``layout`` of the bytes after it),
+        "reserved_tags", "column_encodings" and "codecs" (ID to name)
+    """
+    ...
+
+def explain(obj: Any) -> SizeReport:
+    """
+    Show where the bytes of a payload go.
+
+    Bytes are charged to the top-level fields (summed over the records of a
+    root list of records, or per column of a columnar list) and to type
+    categories: strings, numbers, booleans, nulls, bytes, temporal, other,
+    keys (string table and key IDs) and overhead (header, container and
+    column framing). ``print()`` the report for a table.
+
+    Args:
+        obj: Encoded payload (bytes), or any object to encode with default
+            options first
+
+    Returns:
+        SizeReport with the totals
+
+    Raises:
+        ValueError: If the payload is malformed
+    """
+    ...
+
+class PendingFrame:
+    """Frame returned by ``BFast.encode_deferred`` while it is compressed."""
+
+    def done(self) -> bool:
+        """Whether the bytes are ready."""
+        ...
+
+    def result(self, timeout: Optional[float] = None) -> bytes:
+        """
+        Wait for the encoded bytes.
+
+        Args:
+            timeout: Seconds to wait; None waits until they are ready
+
+        Returns:
+            The encoded bytes
+
+        Raises:
+            TimeoutError: The bytes were not ready in time
+        """
+        ...
+
+class LazyList:
+    """
+    Read-only sequence over an encoded list, returned by
+    ``decode_packed(..., lazy=True)`` and ``decode_mmap``.
+
+    Supports len(), indexing (negative indices included), slicing and
+    iteration. Elements are decoded on first access and cached, so repeated
+    access returns the same object.
+    """
+
+    def __len__(self) -> int: ...
+    def __getitem__(self, key: Union[int, slice]) -> Any: ...
+    def __iter__(self) -> Iterator[Any]: ...
+    @property
+    def decoded(self) -> int:
+        """Number of elements decoded so far."""
+        ...
+
+    def to_list(self) -> List[Any]:
+        """Decode every element into a regular list."""
+        ...
+
+class LazyColumn:
+    """
+    Read-only sequence over an int or float column, returned by
+    ``decode_columns(..., arrays=False, lazy=True)``.
+
+    Supports len(), indexing (negative indices included), slicing and
+    iteration. The values stay in a typed buffer; each access builds a new
+    Python int or float.
+    """
+
+    def __len__(self) -> int: ...
+    def __getitem__(self, key: Union[int, slice]) -> Any: ...
+    def __iter__(self) -> Iterator[Any]: ...
+    @property
+    def dtype(self) -> str:
+        """NumPy dtype of the values: "int64" or "float64"."""
+        ...
+
+    def to_list(self) -> List[Any]:
+        """Every value in a regular list."""
+        ...
+
+    def to_numpy(self) -> Any:
+        """
+        The values as a NumPy array (a copy of the buffer).
+
+        Raises:
+            ImportError: NumPy is not installed
+        """
+        ...
+
+class SizeReport:
+    """Bytes of an encoded payload by top-level field and type category."""
+
+    @property
+    def total(self) -> int:
+        """Size of the uncompressed frame."""
+        ...
+
+    @property
+    def compressed(self) -> Optional[int]:
+        """Size of the payload as given, if it was compressed."""
+        ...
+
+    @property
+    def fields(self) -> Dict[str, int]:
+        """Bytes per top-level field, largest first."""
+        ...
+
+    @property
+    def categories(self) -> Dict[str, int]:
+        """Bytes per type category, largest first."""
+        ...
+
+class BFastSchema:
+    """Field list referenced from encoded frames by a 4-byte ID."""
+
+    def __init__(
+        self,
+        source: Union[type, Iterable[str]],
+        *,
+        id: Optional[int] = None,
+        name: Optional[str] = None,
+        defaults: Optional[dict[str, Any]] = None,
+        aliases: Optional[dict[str, str]] = None,
+        coerce: Optional[
+            dict[
+                str,
+                Union[
+                    Literal["uuid", "datetime", "date", "decimal"],
+                    Tuple[Literal["decimal"], int],
+                ],
+            ]
+        ] = None,
+    ) -> None:
+        """
+        Build a schema from a Pydantic model class, a dataclass, a TypedDict
+        or field names.
+
+        The type annotations of a model's fields are kept for encoders created
+        with ``validate=True``.
+
+        Args:
+            source: Model class, dataclass, TypedDict or iterable of field
+                names; the keys of a TypedDict that are not required may be
+                missing from records
+            id: Schema ID (default: derived from the field names)
+            name: Display name (default: the class name)
+            defaults: Values for fields missing from older payloads, on top of
+                the defaults declared on the model
+            aliases: Maps a field to the name older payloads wrote it under
+            coerce: Maps a field to the type its values are converted to by
+                encoders using the schema and by ``decode_packed(schema=...)``:
+                "uuid" and "datetime" or "date" (ISO 8601) from str,
+                "decimal" from int, float or str, and ``("decimal", scale)``
+                also quantized to ``scale`` places. Other values pass through
+
+        Raises:
+            ValueError: If a field name appears twice, a default, alias or
+                coercion names an unknown field, or a coercion is unknown
+        """
+        ...
+
+    @property
+    def id(self) -> int: ...
+    @property
+    def name(self) -> Optional[str]: ...
+    @property
+    def fields(self) -> list[str]: ...
+
+class BFastF32:
+    """
+    Field hint: write the floats of the field as 4-byte f32 values.
+
+    Used as ``Annotated[float, BFastF32()]`` on a Pydantic model or dataclass
+    field, like ``BFast(float32=True)`` for that field only (lists and other
+    values of the field included); floats beyond the f32 range stay f64.
+    """
+
+    def __init__(self) -> None: ...
+
+class BFastRaw:
+    """
+    Field hint: keep the field's str and bytes values in the frame.
+
+    Used as ``Annotated[bytes, BFastRaw()]`` on a Pydantic model or dataclass
+    field, so an encoder's ``blob_store`` never receives them.
+    """
+
+    def __init__(self) -> None: ...
+
+def register_schema(schema: BFastSchema) -> None:
+    """
+    Make a schema resolvable when decoding frames that reference its ID.
+
+    Encoders register their schema automatically; decoding processes that
+    never encode with it have to register it explicitly.
+
+    Args:
+        schema: The schema to register
+
+    Raises:
+        ValueError: If another schema is already registered under the same ID
+    """
+    ...
+
+def register_schema_resolver(
+    resolver: Optional[Callable[[int], Union[BFastSchema, Iterable[str], None]]],
+    *,
+    cache_size: int = 256,
+) -> None:
+    """
+    Resolve schema IDs that are not registered locally with a callback.
+
+    The resolver is called with the schema ID (e.g. to fetch it from a central
+    registry over HTTP) and returns a BFastSchema, a list of field names, or
+    None for an unknown ID. Answers are kept in an LRU cache; the local
+    registry is always checked first.
+
+    Args:
+        resolver: Callback, or None to remove the current resolver
+        cache_size: Number of resolved schemas to keep
+
+    Raises:
+        ValueError: If cache_size is 0
+    """
+    ...
+
+def register_ext(
+    ext_id: int,
+    cls: type,
+    encode: Callable[[Any], bytes],
+    decode: Callable[[bytes], Any],
+) -> None:
+    """
+    Encode instances of a class as an extension type with its own bytes.
+
+    Like msgpack's ext types: instances of ``cls`` are written as the ID and
+    the bytes ``encode(obj)`` returns, and decoding calls ``decode(bytes)``.
+    The registry is process-wide, so decoding processes register the same
+    ID. Built-in types keep their own encoding; registered classes are
+    checked before ``__bfast__``.
+
+    Args:
+        ext_id: Extension type ID, 0-255
+        cls: Class whose instances (subclasses included) use the extension
+        encode: Turns an instance into bytes
+        decode: Turns the bytes back into a value
+
+    Raises:
+        ValueError: If ext_id is out of range
+    """
+    ...
+
+def set_trace_hook(hook: Optional[Callable[[str, int, int], Any]]) -> None:
+    """
+    Time the stages of every encode with a callback.
+
+    After each stage the hook is called as ``hook(stage, duration_ns, nbytes)``,
+    where stage is "serialize", "string_table", "compress" or "pybytes" and
+    nbytes is the size of what the stage produced. Exceptions raised by the
+    hook propagate out of the encode call. Builds with the ``tracing`` Cargo
+    feature also emit the stages as ``tracing`` spans.
+
+    Args:
+        hook: Callback, or None to remove the current hook
+
+    Raises:
+        TypeError: If hook is not callable
+    """
+    ...
+
 class BFastError(Exception):
     """Base exception for B-FAST operations."""
 
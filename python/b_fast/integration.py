@@ -1,4 +1,14 @@
-from typing import Any
+import struct
+import threading
+from typing import Any, Optional
+
+from ._b_fast import BFast
+
+# Content type of encoded payloads, for HTTP headers and gRPC metadata
+MEDIA_TYPE = "application/x-bfast"
+
+# Confluent wire format prefix: magic byte 0, then the big-endian schema ID
+_CONFLUENT_PREFIX = struct.Struct(">BI")
 
 try:
     from fastapi import Response
@@ -13,8 +23,64 @@ except ImportError:
     FASTAPI_AVAILABLE = False
 
 
+class BFastCodec:
+    """Encode/decode callables for transports that take a serializer pair.
+
+    ``codec.encode`` and ``codec.decode`` fit grpcio's ``request_serializer``
+    / ``response_deserializer`` (and the server-side equivalents) as well as
+    Starlette's ``Response.render``. Each thread encodes with its own
+    encoder, so one codec can be shared by a whole server.
+
+    With ``schema_id``, messages carry the Confluent wire prefix (magic byte
+    0 and the 4-byte schema registry ID) in front of the frame, so they pass
+    through Kafka tooling that expects it; ``decode`` strips the prefix and
+    rejects messages with another ID. Both methods also accept the
+    serialization context confluent-kafka passes as a second argument.
+    """
+
+    media_type = MEDIA_TYPE
+
+    def __init__(
+        self,
+        *,
+        compress: bool = True,
+        schema_id: Optional[int] = None,
+        **decode_options: Any,
+    ):
+        if schema_id is not None and not 0 <= schema_id <= 0xFFFFFFFF:
+            raise ValueError(f"schema_id must fit in 32 bits, got {schema_id}")
+        self.compress = compress
+        self.schema_id = schema_id
+        self.decode_options = decode_options
+        self._local = threading.local()
+        self._prefix = b""
+        if schema_id is not None:
+            self._prefix = _CONFLUENT_PREFIX.pack(0, schema_id)
+
+    def encode(self, obj: Any, ctx: Any = None) -> bytes:
+        encoder = getattr(self._local, "encoder", None)
+        if encoder is None:
+            encoder = self._local.encoder = BFast()
+        return self._prefix + encoder.encode_packed(obj, compress=self.compress)
+
+    def decode(self, data: bytes, ctx: Any = None) -> Any:
+        if self.schema_id is not None:
+            data = self._strip_prefix(data)
+        return BFast().decode_packed(data, **self.decode_options)
+
+    def _strip_prefix(self, data: bytes) -> bytes:
+        if len(data) < _CONFLUENT_PREFIX.size or data[0] != 0:
+            raise ValueError("Message does not start with the Confluent magic byte")
+        _, schema_id = _CONFLUENT_PREFIX.unpack_from(data)
+        if schema_id != self.schema_id:
+            raise ValueError(
+                f"Message has schema ID {schema_id}, expected {self.schema_id}"
+            )
+        return data[_CONFLUENT_PREFIX.size :]
+
+
 class BFastResponse(Response):
-    media_type = "application/x-bfast"
+    media_type = MEDIA_TYPE
 
     def __init__(self, content: Any, **kwargs):
         if not FASTAPI_AVAILABLE:
@@ -22,9 +88,6 @@ class BFastResponse(Response):
                 "FastAPI is required to use BFastResponse. Install it with 'pip install bfast-py[fastapi]'."
             )
 
-        # Delayed import to avoid circular dependency
-        from ._b_fast import BFast
-
         self.encoder = BFast()
         super().__init__(content=content, **kwargs)
 
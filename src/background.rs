@@ -0,0 +1,126 @@
+// Encoding work moved off the calling thread.
+//
+// `BFast.encode_deferred` walks the object on the calling thread and leaves
+// only the compression to the rayon pool, behind a `PendingFrame` handle.
+//
+// `encode_async` does the same for a running event loop and waits for the
+// frame on the loop's executor, so awaiting code never blocks on the
+// compression. Walking the object needs the GIL, so it runs on the calling
+// thread as it would anywhere else. There is no decode_async for the same
+// reason: decoding builds Python objects the whole way through and holds the
+// GIL as long as it runs, so another thread would block the loop just the
+// same.
+
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+
+use crate::compression::{self, Codec};
+use crate::BFast;
+
+/// Encode `obj` with its compression on the rayon pool; returns an asyncio
+/// future of the bytes.
+#[pyfunction]
+#[pyo3(signature = (obj, *, compress = false, encoder = None))]
+pub fn encode_async(
+    py: Python,
+    obj: &PyAny,
+    compress: bool,
+    encoder: Option<Py<BFast>>,
+) -> PyResult<PyObject> {
+    let event_loop = py.import("asyncio")?.call_method0("get_running_loop")?;
+    let encoder = match encoder {
+        Some(encoder) => encoder,
+        None => Py::new(py, BFast::new())?,
+    };
+    let encoder = encoder.try_borrow_mut(py)?;
+    match BFast::encode_deferred(encoder, obj, compress, None, None, None, false) {
+        Ok(pending) => {
+            let result = Py::new(py, pending)?.getattr(py, "result")?;
+            Ok(event_loop
+                .call_method1("run_in_executor", (py.None(), result))?
+                .into())
+        }
+        // Raised on await, like a failed compression
+        Err(err) => {
+            let future = event_loop.call_method0("create_future")?;
+            future.call_method1("set_exception", (err.into_value(py),))?;
+            Ok(future.into())
+        }
+    }
+}
+
+type CompressResult = Result<Vec<u8>, String>;
+
+/// A frame being compressed on the rayon pool.
+#[allow(non_local_definitions)]
+#[pyclass]
+pub struct PendingFrame {
+    slot: Arc<(Mutex<Option<CompressResult>>, Condvar)>,
+    /// The bytes once `result` has collected them.
+    bytes: Option<PyObject>,
+}
+
+impl PendingFrame {
+    /// Start compressing `frame` with `codec` (when `compress` is set and it
+    /// is worth it).
+    pub(crate) fn spawn(mut frame: Vec<u8>, compress: bool, codec: Arc<dyn Codec>) -> Self {
+        let slot = Arc::new((Mutex::new(None), Condvar::new()));
+        let filled = Arc::clone(&slot);
+        rayon::spawn(move || {
+            let frame = if compress && frame.len() > 256 {
+                compression::compress_or_store(&*codec, &mut frame)
+            } else {
+                Ok(frame)
+            };
+            *filled.0.lock().unwrap() = Some(frame);
+            filled.1.notify_all();
+        });
+        PendingFrame { slot, bytes: None }
+    }
+}
+
+#[allow(non_local_definitions)]
+#[pymethods]
+impl PendingFrame {
+    /// Whether the frame is ready.
+    fn done(&self) -> bool {
+        self.bytes.is_some() || self.slot.0.lock().unwrap().is_some()
+    }
+
+    /// The encoded bytes, waiting up to `timeout` seconds (forever if None).
+    #[pyo3(signature = (timeout = None))]
+    fn result(&mut self, py: Python, timeout: Option<f64>) -> PyResult<PyObject> {
+        if let Some(bytes) = &self.bytes {
+            return Ok(bytes.clone_ref(py));
+        }
+        let timeout =
+            timeout.and_then(|seconds| Duration::try_from_secs_f64(seconds.max(0.0)).ok());
+        let slot = Arc::clone(&self.slot);
+        let frame = py.allow_threads(move || {
+            let (lock, ready) = &*slot;
+            let guard = lock.lock().unwrap();
+            let mut guard = match timeout {
+                Some(timeout) => {
+                    ready
+                        .wait_timeout_while(guard, timeout, |frame| frame.is_none())
+                        .unwrap()
+                        .0
+                }
+                None => ready.wait_while(guard, |frame| frame.is_none()).unwrap(),
+            };
+            guard.take()
+        });
+        let Some(frame) = frame else {
+            return Err(PyErr::new::<pyo3::exceptions::PyTimeoutError, _>(
+                "Frame not ready within the timeout",
+            ));
+        };
+        let frame = frame.map_err(PyErr::new::<pyo3::exceptions::PyValueError, _>)?;
+        let bytes: PyObject = PyBytes::new(py, &frame).into();
+        self.bytes = Some(bytes.clone_ref(py));
+        Ok(bytes)
+    }
+}
@@ -0,0 +1,68 @@
+// Content-addressed payloads for key-value caches such as Redis.
+//
+// `encode_keyed` writes the canonical frame and keys it by the same digest
+// `hash` returns, so identical values share one cache entry whatever their
+// dict order or compression. `decode_keyed` digests the frame again before
+// decoding and refuses payloads that do not match the key they were read
+// from.
+
+use std::mem;
+
+use pyo3::prelude::*;
+use pyo3::types::{PyAny, PyBytes, PyTuple};
+use xxhash_rust::xxh3::Xxh3;
+
+use crate::compression::compress_frame;
+use crate::scan::unpack;
+use crate::{decode_frame, BFast, DecodeOptions, FLAG_COMPRESSED};
+
+/// Lowercase hex of the XXH3-128 digest of an uncompressed frame, taken as
+/// if the frame had been encoded without compression.
+fn key_suffix(frame: &[u8]) -> String {
+    let mut hasher = Xxh3::new();
+    if frame.len() > 2 {
+        hasher.update(&frame[..2]);
+        hasher.update(&[frame[2] & !FLAG_COMPRESSED]);
+        hasher.update(&frame[3..]);
+    } else {
+        hasher.update(frame);
+    }
+    format!("{:032x}", hasher.digest128())
+}
+
+/// Encode `obj` canonically, returning `(key suffix, bytes)`.
+#[pyfunction]
+#[pyo3(signature = (obj, *, compress = false))]
+pub fn encode_keyed(py: Python, obj: &PyAny, compress: bool) -> PyResult<PyObject> {
+    let mut encoder = BFast {
+        canonical: true,
+        ..BFast::new()
+    };
+    encoder.write_frame(obj, None, None, false)?;
+    let frame = mem::take(&mut encoder.work_buffer);
+    let (suffix, data) = py
+        .allow_threads(|| {
+            let suffix = key_suffix(&frame);
+            if compress && frame.len() > 256 {
+                compress_frame(frame).map(|data| (suffix, data))
+            } else {
+                Ok((suffix, frame))
+            }
+        })
+        .map_err(PyErr::new::<pyo3::exceptions::PyValueError, _>)?;
+    Ok(PyTuple::new(py, [suffix.into_py(py), PyBytes::new(py, &data).into()]).into())
+}
+
+/// Decode bytes stored under `key`, whose suffix must be their content hash.
+#[pyfunction]
+pub fn decode_keyed(py: Python, key: &str, data: &[u8]) -> PyResult<PyObject> {
+    let (frame, _) = unpack(data).map_err(PyErr::new::<pyo3::exceptions::PyValueError, _>)?;
+    let suffix = py.allow_threads(|| key_suffix(&frame));
+    if !key.ends_with(&suffix) {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+            "Payload hash {} does not match key {:?}",
+            suffix, key
+        )));
+    }
+    decode_frame(py, &frame, None, DecodeOptions::default())
+}
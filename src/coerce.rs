@@ -0,0 +1,212 @@
+// Per-field type coercions declared on a schema.
+//
+// `BFastSchema(Order, coerce={"id": "uuid", "total": ("decimal", 2)})` turns
+// the values of those fields into the declared type wherever records meet
+// the schema: before an encoder with the schema writes them (and before
+// `validate=True` checks them), and after `decode_packed(schema=...)` evolves
+// them. Values already of the type are kept, except Decimals, which are
+// quantized to the scale when one is given:
+//
+//   "uuid"              str -> uuid.UUID
+//   "datetime"          ISO 8601 str -> datetime.datetime
+//   "date"              ISO 8601 str -> datetime.date
+//   "decimal"           int, float or str -> decimal.Decimal
+//   ("decimal", scale)  the same, quantized to `scale` decimal places
+//
+// Records are dicts or objects; an object whose fields change is written as
+// the dict of its fields. None and values of other types pass through.
+
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyFloat, PyList, PyLong, PyString, PyTuple};
+
+use crate::schema::SchemaDef;
+use crate::BFast;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) enum Coercion {
+    Uuid,
+    Datetime,
+    Date,
+    Decimal(Option<u32>),
+}
+
+fn coerce_error(message: String) -> PyErr {
+    PyErr::new::<pyo3::exceptions::PyValueError, _>(message)
+}
+
+/// The coercion described by `spec`: a name, or `("decimal", scale)`.
+pub(crate) fn parse(field: &str, spec: &PyAny) -> PyResult<Coercion> {
+    if let Ok((name, scale)) = spec.extract::<(&str, u32)>() {
+        if name == "decimal" {
+            return Ok(Coercion::Decimal(Some(scale)));
+        }
+    }
+    match spec.extract::<&str>() {
+        Ok("uuid") => Ok(Coercion::Uuid),
+        Ok("datetime") => Ok(Coercion::Datetime),
+        Ok("date") => Ok(Coercion::Date),
+        Ok("decimal") => Ok(Coercion::Decimal(None)),
+        _ => Err(coerce_error(format!(
+            "Unknown coercion {} for field {:?}; expected \"uuid\", \"datetime\", \
+             \"date\", \"decimal\" or (\"decimal\", scale)",
+            spec.repr()?,
+            field
+        ))),
+    }
+}
+
+impl Coercion {
+    fn name(self) -> String {
+        match self {
+            Coercion::Uuid => "uuid".to_string(),
+            Coercion::Datetime => "datetime".to_string(),
+            Coercion::Date => "date".to_string(),
+            Coercion::Decimal(None) => "decimal".to_string(),
+            Coercion::Decimal(Some(scale)) => format!("decimal with scale {}", scale),
+        }
+    }
+
+    /// `value` converted, or None when it is left as it is.
+    fn apply(self, value: &PyAny) -> PyResult<Option<&PyAny>> {
+        let py = value.py();
+        match self {
+            Coercion::Uuid if value.is_instance_of::<PyString>() => {
+                let uuid = py.import("uuid")?.getattr("UUID")?;
+                uuid.call1((value,)).map(Some)
+            }
+            Coercion::Datetime | Coercion::Date if value.is_instance_of::<PyString>() => {
+                let class = match self {
+                    Coercion::Datetime => "datetime",
+                    _ => "date",
+                };
+                let class = py.import("datetime")?.getattr(class)?;
+                class.call_method1("fromisoformat", (value,)).map(Some)
+            }
+            Coercion::Decimal(scale) => {
+                let class = py.import("decimal")?.getattr("Decimal")?;
+                let decimal = if value.is_instance(class)? {
+                    value
+                } else if value.is_instance_of::<pyo3::types::PyBool>() {
+                    return Ok(None);
+                } else if value.is_instance_of::<PyLong>() || value.is_instance_of::<PyString>() {
+                    class.call1((value,))?
+                } else if value.is_instance_of::<PyFloat>() {
+                    // The shortest repr, not the binary expansion
+                    class.call1((value.str()?,))?
+                } else {
+                    return Ok(None);
+                };
+                let Some(scale) = scale else {
+                    return Ok((!decimal.is(value)).then_some(decimal));
+                };
+                let exponent = class.call1((format!("1e-{}", scale),))?;
+                decimal.call_method1("quantize", (exponent,)).map(Some)
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
+/// The value of `field` after `coercion`, or None when it is left as it is.
+fn coerce_value<'py>(
+    field: &str,
+    coercion: Coercion,
+    value: &'py PyAny,
+) -> PyResult<Option<&'py PyAny>> {
+    coercion.apply(value).map_err(|e| {
+        coerce_error(format!(
+            "Cannot coerce field '{}' to {}: {}",
+            field,
+            coercion.name(),
+            e
+        ))
+    })
+}
+
+/// A copy of the record `fields` with the coercions of `def` applied; None
+/// when no value changes.
+fn coerce_fields<'py>(fields: &'py PyDict, def: &SchemaDef) -> PyResult<Option<&'py PyDict>> {
+    let mut coerced: Option<&PyDict> = None;
+    for &(field, coercion) in &def.coercions {
+        let Some(value) = fields.get_item(field)? else {
+            continue;
+        };
+        if let Some(converted) = coerce_value(field, coercion, value)? {
+            let target = match coerced {
+                Some(target) => target,
+                None => *coerced.insert(fields.copy()?),
+            };
+            target.set_item(field, converted)?;
+        }
+    }
+    Ok(coerced)
+}
+
+/// Apply the coercions of `def` to a decoded record in place.
+pub(crate) fn coerce_decoded(record: &PyDict, def: &SchemaDef) -> PyResult<()> {
+    for &(field, coercion) in &def.coercions {
+        let Some(value) = record.get_item(field)? else {
+            continue;
+        };
+        if let Some(converted) = coerce_value(field, coercion, value)? {
+            record.set_item(field, converted)?;
+        }
+    }
+    Ok(())
+}
+
+/// `obj` with `f` applied to its records: the root value, or the items of a
+/// root list or tuple, which come back as a list when any record changes.
+pub(crate) fn map_records<'py>(
+    obj: &'py PyAny,
+    mut f: impl FnMut(&'py PyAny) -> PyResult<&'py PyAny>,
+) -> PyResult<&'py PyAny> {
+    let items: Vec<&PyAny> = if let Ok(list) = obj.downcast::<PyList>() {
+        list.iter().collect()
+    } else if let Ok(tuple) = obj.downcast::<PyTuple>() {
+        tuple.iter().collect()
+    } else {
+        return f(obj);
+    };
+    let mut changed = false;
+    let mut records = Vec::with_capacity(items.len());
+    for item in items {
+        let record = f(item)?;
+        changed |= !record.is(item);
+        records.push(record);
+    }
+    match changed {
+        true => Ok(PyList::new(obj.py(), records).into()),
+        false => Ok(obj),
+    }
+}
+
+impl BFast {
+    /// `obj` with the coercions of the encoder's schema applied to its
+    /// records (the root value or the items of a root list), or `obj` itself
+    /// when nothing changes.
+    pub(crate) fn coerce_root<'py>(&self, obj: &'py PyAny) -> PyResult<&'py PyAny> {
+        let Some(def) = self.schema.as_ref().filter(|def| !def.coercions.is_empty()) else {
+            return Ok(obj);
+        };
+        map_records(obj, |record| self.coerce_record(record, def))
+    }
+
+    /// Like `coerce_root` for one record.
+    pub(crate) fn coerce_record<'py>(
+        &self,
+        record: &'py PyAny,
+        def: &SchemaDef,
+    ) -> PyResult<&'py PyAny> {
+        if def.coercions.is_empty() {
+            return Ok(record);
+        }
+        let Some(fields) = self.record_fields(record)? else {
+            return Ok(record);
+        };
+        Ok(match coerce_fields(fields, def)? {
+            Some(coerced) => coerced.into(),
+            None => record,
+        })
+    }
+}
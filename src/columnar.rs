@@ -0,0 +1,1165 @@
+// Columnar layout for lists of records.
+//
+// A list whose elements are records with the same keys in the same order is
+// written column by column under TAG_COLUMNAR:
+//
+//   [row count (u32)][column count (u16)]
+//   per column: [key ID (u32)][encoding (u8)][encoded values]
+//
+// Each column picks its encoding from a sample of its first rows, so nearly
+// constant columns (status flags, tenant IDs) collapse into a few runs,
+// sorted integers (IDs, timestamps) shrink to small deltas, slowly moving
+// floats (sensor readings) keep only the bits that change and strings from a
+// small set (countries, statuses) are written once, with a code per row.
+//
+// `BFast.encode_columns` writes the same layout from a dict of columns, taking
+// numeric NumPy arrays straight from their buffers, and `decode_columns`
+// reads it back column by column. It also reads lists written by the batch
+// path, whose records all share the layout their fingerprint names, by
+// collecting every record's fields into columns.
+//
+// Columns holding only ints or only floats (of any width) are read into a
+// typed buffer without building a Python object per value. That buffer
+// becomes the NumPy array as is, or backs a `LazyColumn` that builds Python
+// numbers only for the rows accessed.
+
+use ahash::{AHashMap, AHashSet};
+use numpy::{PyArray1, PyReadonlyArray1};
+use pyo3::prelude::*;
+use pyo3::types::{PyBool, PyDict, PyFloat, PyList, PyLong, PySlice, PyString};
+
+use crate::hints;
+use crate::scan::{
+    float_value, float_width, int_value, int_width, read_u32, root_fingerprint, write_int,
+    TAG_FLOAT32,
+};
+use crate::{
+    bool_values, pack_bits, read_string_table, BFast, BFastParser, DecodeLimits, DecodeOptions,
+    TAG_BLOB_REF, TAG_BOOL_ARRAY, TAG_COLUMNAR, TAG_CUSTOM, TAG_EXT, TAG_INT_OBJECT,
+    TAG_NULL_BITMAP, TAG_RANGE, TAG_REF, TAG_TENSOR, TAG_TYPED_ARRAY,
+};
+
+/// Every row's value, tagged as usual.
+pub(crate) const COL_PLAIN: u8 = 0;
+/// `[run count (u32)]` then `([run length (u32)][value])*`.
+pub(crate) const COL_RLE: u8 = 1;
+/// Booleans packed 8 per byte, least significant bit first.
+pub(crate) const COL_BOOL: u8 = 2;
+/// Integers as zigzag varints of their delta-of-delta (the first value, then
+/// the first delta, then the change of each delta), preceded by the byte
+/// length of the varints (u32). Arithmetic wraps, so every i64 round-trips.
+pub(crate) const COL_DELTA: u8 = 3;
+/// Floats XORed with their predecessor (Gorilla): the first value's 64 bits,
+/// then per value `0` (unchanged), `10` and the bits inside the previous
+/// window, or `11`, the leading zeros (5 bits), the window width (6 bits,
+/// 0 = 64) and the bits inside it. Bits are packed MSB first after the byte
+/// length of the stream (u32).
+pub(crate) const COL_XOR: u8 = 4;
+/// Strings (and None) from a small set: `[entry count (u32)][code width
+/// (u8: 1, 2 or 4)]`, the distinct values tagged as usual, then each row's
+/// index into them as a little-endian code of that width.
+pub(crate) const COL_DICT: u8 = 5;
+
+/// Rows inspected when choosing a column encoding.
+const SAMPLE_ROWS: usize = 1024;
+
+/// Sampled rows per distinct value from which a dictionary is tried.
+const DICT_ROWS_PER_VALUE: usize = 4;
+
+/// Values decoded from these tags are immutable, so one decoded object can be
+/// shared by every row of a run.
+fn is_scalar(tag: u8) -> bool {
+    !matches!(
+        tag,
+        0x60 | TAG_COLUMNAR
+            | 0x70
+            | TAG_INT_OBJECT
+            | TAG_NULL_BITMAP
+            | 0x90
+            | TAG_TENSOR
+            | TAG_BOOL_ARRAY
+            | TAG_TYPED_ARRAY
+            | TAG_RANGE
+            | TAG_BLOB_REF
+            | TAG_REF
+            | TAG_CUSTOM
+            | TAG_EXT
+    )
+}
+
+/// Runs of byte-identical values: `(first value index, run length)`.
+fn runs(data: &[u8], bounds: &[(usize, usize)]) -> Vec<(usize, u32)> {
+    let value = |index: usize| &data[bounds[index].0..bounds[index].1];
+    let mut runs: Vec<(usize, u32)> = Vec::new();
+    for index in 0..bounds.len() {
+        match runs.last_mut() {
+            Some((first, length)) if value(*first) == value(index) => *length += 1,
+            _ => runs.push((index, 1)),
+        }
+    }
+    runs
+}
+
+/// Number of distinct byte strings among the values.
+fn distinct(data: &[u8], bounds: &[(usize, usize)]) -> usize {
+    let values = bounds.iter().map(|&(s, e)| &data[s..e]);
+    values.collect::<AHashSet<_>>().len()
+}
+
+/// `COL_DICT` values, from the plain encoding of every row; None when there
+/// are too many distinct values for a code.
+fn dictionary(plain: &[u8], bounds: &[(usize, usize)]) -> Option<Vec<u8>> {
+    let mut index: AHashMap<&[u8], u32> = AHashMap::new();
+    let mut entries = Vec::new();
+    let mut codes = Vec::with_capacity(bounds.len());
+    for &(s, e) in bounds {
+        let value = &plain[s..e];
+        let next = u32::try_from(index.len()).ok()?;
+        let code = *index.entry(value).or_insert_with(|| {
+            entries.extend_from_slice(value);
+            next
+        });
+        codes.push(code);
+    }
+    let width: usize = match index.len() {
+        0..=0x100 => 1,
+        0x101..=0x1_0000 => 2,
+        _ => 4,
+    };
+    let mut out = Vec::with_capacity(5 + entries.len() + codes.len() * width);
+    out.extend_from_slice(&(index.len() as u32).to_le_bytes());
+    out.push(width as u8);
+    out.extend_from_slice(&entries);
+    for code in codes {
+        out.extend_from_slice(&code.to_le_bytes()[..width]);
+    }
+    Some(out)
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn zigzag(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+fn unzigzag(value: u64) -> i64 {
+    (value >> 1) as i64 ^ -((value & 1) as i64)
+}
+
+/// Whether the sampled rows are sorted (either way), which is when
+/// delta-of-delta pays off.
+fn sorted_sample(ints: &[i64]) -> bool {
+    let sample = &ints[..ints.len().min(SAMPLE_ROWS)];
+    sample.windows(2).all(|w| w[0] <= w[1]) || sample.windows(2).all(|w| w[0] >= w[1])
+}
+
+/// The column as i64s when it holds only ints and its sampled rows are
+/// sorted.
+fn monotonic_ints(values: &[&PyAny]) -> PyResult<Option<Vec<i64>>> {
+    let mut ints = Vec::with_capacity(values.len());
+    for value in values {
+        if !value.is_instance_of::<PyLong>() || value.is_instance_of::<PyBool>() {
+            return Ok(None);
+        }
+        match value.extract::<i64>() {
+            Ok(int) => ints.push(int),
+            Err(_) => return Ok(None),
+        }
+        if ints.len() == SAMPLE_ROWS.min(values.len()) && !sorted_sample(&ints) {
+            return Ok(None);
+        }
+    }
+    Ok(Some(ints))
+}
+
+/// A column read natively from a one-dimensional NumPy array.
+enum NativeColumn {
+    Ints(Vec<i64>),
+    Floats(Vec<f64>),
+    Bools(Vec<bool>),
+}
+
+/// The values of a NumPy bool, int or float array (uint64 excepted, which may
+/// not fit an i64); `None` for other dtypes and for other objects.
+fn native_column(column: &PyAny) -> PyResult<Option<NativeColumn>> {
+    if !column.hasattr("__array_interface__")? || !column.hasattr("dtype")? {
+        return Ok(None);
+    }
+    let dtype = column.getattr("dtype")?;
+    let kind = dtype.getattr("kind")?.extract::<String>()?;
+    let itemsize = dtype.getattr("itemsize")?.extract::<usize>()?;
+    let target = match kind.as_str() {
+        "b" => "bool",
+        "i" => "int64",
+        "u" if itemsize < 8 => "int64",
+        "f" => "float64",
+        _ => return Ok(None),
+    };
+    let ndim = column.getattr("ndim")?.extract::<usize>()?;
+    if ndim != 1 {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+            "Columns must be one-dimensional, got {} dimensions",
+            ndim
+        )));
+    }
+    let array = column.call_method1("astype", (target,))?;
+    Ok(Some(match target {
+        "bool" => {
+            let array = array.extract::<PyReadonlyArray1<bool>>()?;
+            NativeColumn::Bools(array.as_array().iter().copied().collect())
+        }
+        "int64" => {
+            let array = array.extract::<PyReadonlyArray1<i64>>()?;
+            NativeColumn::Ints(array.as_array().iter().copied().collect())
+        }
+        _ => {
+            let array = array.extract::<PyReadonlyArray1<f64>>()?;
+            NativeColumn::Floats(array.as_array().iter().copied().collect())
+        }
+    }))
+}
+
+/// A decoded column, typed when its encoding says so.
+pub(crate) enum ColumnValues {
+    Ints(Vec<i64>),
+    Floats(Vec<f64>),
+    Bools(Vec<bool>),
+    Objects(Vec<PyObject>),
+}
+
+impl ColumnValues {
+    /// Plain and run-length columns of only ints, only floats or only bools,
+    /// typed like the natively encoded ones.
+    fn narrow(self, py: Python) -> PyResult<Self> {
+        let ColumnValues::Objects(objects) = self else {
+            return Ok(self);
+        };
+        let values = objects.iter().map(|o| o.as_ref(py)).collect::<Vec<_>>();
+        if values.is_empty() {
+            return Ok(ColumnValues::Objects(objects));
+        }
+        if let Some(bits) = bool_values(values.iter().copied())? {
+            return Ok(ColumnValues::Bools(bits));
+        }
+        if let Some(floats) = float_values(&values)? {
+            return Ok(ColumnValues::Floats(floats));
+        }
+        let mut ints = Vec::with_capacity(values.len());
+        for value in &values {
+            if !value.is_instance_of::<PyLong>() || value.is_instance_of::<PyBool>() {
+                return Ok(ColumnValues::Objects(objects));
+            }
+            match value.extract::<i64>() {
+                Ok(int) => ints.push(int),
+                Err(_) => return Ok(ColumnValues::Objects(objects)),
+            }
+        }
+        Ok(ColumnValues::Ints(ints))
+    }
+
+    /// The value of every row, as Python objects.
+    fn into_objects(self, py: Python) -> Vec<PyObject> {
+        match self {
+            ColumnValues::Ints(ints) => ints.into_iter().map(|v| v.into_py(py)).collect(),
+            ColumnValues::Floats(floats) => floats.into_iter().map(|v| v.into_py(py)).collect(),
+            ColumnValues::Bools(bits) => bits.into_iter().map(|v| v.into_py(py)).collect(),
+            ColumnValues::Objects(objects) => objects,
+        }
+    }
+
+    /// A NumPy array for typed columns, a list otherwise.
+    fn into_array(self, py: Python) -> PyObject {
+        match self {
+            ColumnValues::Ints(ints) => PyArray1::from_vec(py, ints).into_py(py),
+            ColumnValues::Floats(floats) => PyArray1::from_vec(py, floats).into_py(py),
+            ColumnValues::Bools(bits) => PyArray1::from_vec(py, bits).into_py(py),
+            ColumnValues::Objects(objects) => PyList::new(py, objects).into(),
+        }
+    }
+
+    /// The rows so far as Python objects, so that the column can take a
+    /// value of any type.
+    fn widen(&mut self, py: Python) -> &mut Vec<PyObject> {
+        if !matches!(self, ColumnValues::Objects(_)) {
+            let values = std::mem::replace(self, ColumnValues::Objects(Vec::new()));
+            *self = ColumnValues::Objects(values.into_objects(py));
+        }
+        match self {
+            ColumnValues::Objects(objects) => objects,
+            _ => unreachable!(),
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            ColumnValues::Ints(ints) => ints.len(),
+            ColumnValues::Floats(floats) => floats.len(),
+            ColumnValues::Bools(bits) => bits.len(),
+            ColumnValues::Objects(objects) => objects.len(),
+        }
+    }
+
+    fn get(&self, py: Python, index: usize) -> PyObject {
+        match self {
+            ColumnValues::Ints(ints) => ints[index].into_py(py),
+            ColumnValues::Floats(floats) => floats[index].into_py(py),
+            ColumnValues::Bools(bits) => bits[index].into_py(py),
+            ColumnValues::Objects(objects) => objects[index].clone_ref(py),
+        }
+    }
+}
+
+/// A number read from the frame without building a Python object.
+enum Number {
+    Int(i64),
+    Float(f64),
+}
+
+/// The column as f64s when every value is a float.
+fn float_values(values: &[&PyAny]) -> PyResult<Option<Vec<f64>>> {
+    let mut floats = Vec::with_capacity(values.len());
+    for value in values {
+        if !value.is_instance_of::<PyFloat>() {
+            return Ok(None);
+        }
+        floats.push(value.extract::<f64>()?);
+    }
+    Ok(Some(floats))
+}
+
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    pending: u128,
+    bits: u32,
+}
+
+impl BitWriter {
+    /// Append the low `count` bits of `value`, most significant first.
+    fn write(&mut self, value: u64, count: u32) {
+        if count == 0 {
+            return;
+        }
+        let value = if count == 64 {
+            value
+        } else {
+            value & ((1 << count) - 1)
+        };
+        self.pending = (self.pending << count) | value as u128;
+        self.bits += count;
+        while self.bits >= 8 {
+            self.bits -= 8;
+            self.bytes.push((self.pending >> self.bits) as u8);
+        }
+        self.pending &= (1 << self.bits) - 1;
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.bits > 0 {
+            self.bytes.push((self.pending << (8 - self.bits)) as u8);
+        }
+        self.bytes
+    }
+}
+
+struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl BitReader<'_> {
+    fn read(&mut self, count: u32) -> PyResult<u64> {
+        if self.position + count as usize > self.data.len() * 8 {
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+                "XOR-encoded column is truncated",
+            ));
+        }
+        let mut value = 0u64;
+        let mut remaining = count;
+        while remaining > 0 {
+            let used = (self.position % 8) as u32;
+            let take = remaining.min(8 - used);
+            let byte = self.data[self.position / 8] as u64;
+            let chunk = (byte >> (8 - used - take)) & ((1 << take) - 1);
+            value = if take == 64 {
+                chunk
+            } else {
+                (value << take) | chunk
+            };
+            self.position += take as usize;
+            remaining -= take;
+        }
+        Ok(value)
+    }
+}
+
+fn xor_encode(values: &[f64]) -> Vec<u8> {
+    let mut out = BitWriter::default();
+    let mut previous = values[0].to_bits();
+    out.write(previous, 64);
+    // No window until the first changed value
+    let mut window: Option<(u32, u32)> = None;
+    for value in &values[1..] {
+        let bits = value.to_bits();
+        let xor = bits ^ previous;
+        previous = bits;
+        if xor == 0 {
+            out.write(0, 1);
+            continue;
+        }
+        let leading = xor.leading_zeros().min(31);
+        let trailing = xor.trailing_zeros();
+        match window {
+            Some((lead, trail)) if leading >= lead && trailing >= trail => {
+                out.write(0b10, 2);
+                out.write(xor >> trail, 64 - lead - trail);
+            }
+            _ => {
+                let width = 64 - leading - trailing;
+                out.write(0b11, 2);
+                out.write(leading as u64, 5);
+                out.write((width & 63) as u64, 6);
+                out.write(xor >> trailing, width);
+                window = Some((leading, trailing));
+            }
+        }
+    }
+    out.finish()
+}
+
+impl BFast {
+    /// Field mapping of a list element that can become a columnar row.
+    pub(crate) fn record_dict<'py>(&self, item: &'py PyAny) -> PyResult<Option<&'py PyDict>> {
+        if let Ok(dict) = item.downcast::<PyDict>() {
+            return Ok(Some(dict));
+        }
+        // Pydantic models and dataclasses; other objects with a __dict__ (enums,
+        // arbitrary classes) keep their regular encoding
+        let cls = item.get_type();
+        for attr in ["model_fields", "__fields__", "__dataclass_fields__"] {
+            if cls.hasattr(attr)? {
+                return Ok(self.model_dict(item).ok());
+            }
+        }
+        Ok(None)
+    }
+
+    /// Write `list` in columnar form when its elements are records sharing the
+    /// same keys in the same order. Returns `false`, with nothing written,
+    /// when the list does not qualify.
+    pub(crate) fn serialize_columnar(&mut self, list: &PyList) -> PyResult<bool> {
+        if list.len() < 2 || list.len() > u32::MAX as usize {
+            return Ok(false);
+        }
+        let mut keys: Vec<String> = Vec::new();
+        let mut rows: Vec<Vec<&PyAny>> = Vec::with_capacity(list.len());
+        for item in list.iter() {
+            let Some(dict) = self.record_dict(item)? else {
+                return Ok(false);
+            };
+            if rows.is_empty() {
+                for key in dict.keys() {
+                    // Integer keys keep their type outside the columnar layout
+                    let Ok(key) = key.downcast::<PyString>() else {
+                        return Ok(false);
+                    };
+                    keys.push(key.to_str()?.to_owned());
+                }
+                if keys.is_empty() || keys.len() > u16::MAX as usize {
+                    return Ok(false);
+                }
+            } else if dict.len() != keys.len() {
+                return Ok(false);
+            }
+            let mut values = Vec::with_capacity(keys.len());
+            for ((key, value), expected) in dict.iter().zip(&keys) {
+                match key.downcast::<PyString>() {
+                    Ok(key) if key.to_str()? == expected => {}
+                    _ => return Ok(false),
+                }
+                values.push(value);
+            }
+            rows.push(values);
+        }
+
+        let hints = hints::for_fields(list.get_item(0)?.get_type(), &keys)?;
+        self.work_buffer.push(TAG_COLUMNAR);
+        self.work_buffer
+            .extend_from_slice(&(rows.len() as u32).to_le_bytes());
+        self.work_buffer
+            .extend_from_slice(&(keys.len() as u16).to_le_bytes());
+        for (column, key) in keys.iter().enumerate() {
+            let id = self.get_or_create_string_id_fast(key);
+            self.work_buffer.extend_from_slice(&id.to_le_bytes());
+            let values = rows.iter().map(|row| row[column]).collect::<Vec<_>>();
+            self.with_hints(hints[column], |encoder| encoder.write_column(&values))?;
+        }
+        Ok(true)
+    }
+
+    /// Write a dict of equal-length columns as a columnar list of records.
+    pub(crate) fn serialize_columns(&mut self, columns: &PyDict) -> PyResult<()> {
+        if columns.is_empty() || columns.len() > u16::MAX as usize {
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+                "encode_columns expects between 1 and 65535 columns",
+            ));
+        }
+        let mut rows = None;
+        let mut prepared = Vec::with_capacity(columns.len());
+        for (key, column) in columns.iter() {
+            let key = key.downcast::<PyString>().map_err(|_| {
+                PyErr::new::<pyo3::exceptions::PyTypeError, _>("Column names must be strings")
+            })?;
+            let len = column.len()?;
+            if len > u32::MAX as usize || *rows.get_or_insert(len) != len {
+                return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                    "Column {:?} has {} rows, expected {}",
+                    key.to_str()?,
+                    len,
+                    rows.unwrap_or(len)
+                )));
+            }
+            prepared.push((key.to_str()?, column, native_column(column)?));
+        }
+
+        let rows = rows.unwrap_or(0);
+        self.work_buffer.push(TAG_COLUMNAR);
+        self.work_buffer
+            .extend_from_slice(&(rows as u32).to_le_bytes());
+        self.work_buffer
+            .extend_from_slice(&(prepared.len() as u16).to_le_bytes());
+        for (key, column, native) in prepared {
+            let id = self.get_or_create_string_id_fast(key);
+            self.work_buffer.extend_from_slice(&id.to_le_bytes());
+            if rows == 0 {
+                self.work_buffer.push(COL_PLAIN);
+                continue;
+            }
+            match native {
+                Some(NativeColumn::Bools(bits)) => {
+                    self.work_buffer.push(COL_BOOL);
+                    pack_bits(&mut self.work_buffer, &bits);
+                }
+                Some(NativeColumn::Ints(ints)) if sorted_sample(&ints) => self.write_delta(&ints),
+                Some(NativeColumn::Ints(ints)) => {
+                    self.work_buffer.push(COL_PLAIN);
+                    for int in ints {
+                        write_int(&mut self.work_buffer, int);
+                    }
+                }
+                Some(NativeColumn::Floats(floats)) => self.write_floats(&floats)?,
+                None => self.write_column(&column.iter()?.collect::<PyResult<Vec<_>>>()?)?,
+            }
+        }
+        Ok(())
+    }
+
+    /// `COL_DELTA` values.
+    fn write_delta(&mut self, ints: &[i64]) {
+        self.work_buffer.push(COL_DELTA);
+        let length_pos = self.work_buffer.len();
+        self.work_buffer.extend_from_slice(&[0; 4]);
+        let (mut previous, mut delta) = (0i64, 0i64);
+        for (index, &int) in ints.iter().enumerate() {
+            let encoded = match index {
+                0 => int,
+                1 => int.wrapping_sub(previous),
+                _ => int.wrapping_sub(previous).wrapping_sub(delta),
+            };
+            write_varint(&mut self.work_buffer, zigzag(encoded));
+            if index > 0 {
+                delta = int.wrapping_sub(previous);
+            }
+            previous = int;
+        }
+        let length = (self.work_buffer.len() - length_pos - 4) as u32;
+        self.work_buffer[length_pos..length_pos + 4].copy_from_slice(&length.to_le_bytes());
+    }
+
+    /// A non-empty float column as `COL_XOR`, or plain when that is smaller.
+    fn write_floats(&mut self, floats: &[f64]) -> PyResult<()> {
+        if !self.floats_as_is(floats)? {
+            self.work_buffer.push(COL_PLAIN);
+            for &float in floats {
+                self.write_float(float)?;
+            }
+            return Ok(());
+        }
+        let packed = xor_encode(floats);
+        if 4 + packed.len() < floats.len() * 9 {
+            self.work_buffer.push(COL_XOR);
+            self.work_buffer
+                .extend_from_slice(&(packed.len() as u32).to_le_bytes());
+            self.work_buffer.extend_from_slice(&packed);
+        } else {
+            self.work_buffer.push(COL_PLAIN);
+            for float in floats {
+                self.work_buffer.push(0x40);
+                self.work_buffer.extend_from_slice(&float.to_le_bytes());
+            }
+        }
+        Ok(())
+    }
+
+    fn write_column(&mut self, values: &[&PyAny]) -> PyResult<()> {
+        // Bit-packed unless the column is so constant that runs are smaller
+        if let Some(bits) = bool_values(values.iter().copied())? {
+            let runs = 1 + bits.windows(2).filter(|w| w[0] != w[1]).count();
+            if 4 + runs * 5 >= bits.len().div_ceil(8) {
+                self.work_buffer.push(COL_BOOL);
+                pack_bits(&mut self.work_buffer, &bits);
+                return Ok(());
+            }
+        }
+
+        if let Some(ints) = monotonic_ints(values)? {
+            self.write_delta(&ints);
+            return Ok(());
+        }
+
+        let encoding_pos = self.work_buffer.len();
+        self.write_values(values)?;
+
+        // Float columns keep whichever of XOR and plain/RLE is smaller (NaN
+        // and infinities the non_finite policy wrote as None stay plain)
+        if let Some(floats) = float_values(values)? {
+            if self.floats_as_is(&floats)? {
+                let packed = xor_encode(&floats);
+                if 5 + packed.len() < self.work_buffer.len() - encoding_pos {
+                    self.work_buffer.truncate(encoding_pos);
+                    self.work_buffer.push(COL_XOR);
+                    self.work_buffer
+                        .extend_from_slice(&(packed.len() as u32).to_le_bytes());
+                    self.work_buffer.extend_from_slice(&packed);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Every value tagged as usual, then run-length encoded if that pays off.
+    fn write_values(&mut self, values: &[&PyAny]) -> PyResult<()> {
+        let encoding_pos = self.work_buffer.len();
+        self.work_buffer.push(COL_PLAIN);
+        let start = self.work_buffer.len();
+        let mut bounds = Vec::with_capacity(values.len());
+        for value in values {
+            let value_start = self.work_buffer.len();
+            self.serialize_any_optimized(value)?;
+            bounds.push((value_start, self.work_buffer.len()));
+        }
+
+        // Only scalar columns are rewritten, so no container (and no dedup
+        // span) moves
+        if !bounds.iter().all(|&(s, _)| is_scalar(self.work_buffer[s])) {
+            return Ok(());
+        }
+        let sample = &bounds[..bounds.len().min(SAMPLE_ROWS)];
+        let rle = runs(&self.work_buffer, sample).len() * 2 <= sample.len();
+        let strings = bounds
+            .iter()
+            .all(|&(s, _)| matches!(self.work_buffer[s], 0x50 | 0x10));
+        let low_cardinality =
+            strings && distinct(&self.work_buffer, sample) * DICT_ROWS_PER_VALUE <= sample.len();
+        if !rle && !low_cardinality {
+            return Ok(());
+        }
+
+        let plain = self.work_buffer.split_off(start);
+        let bounds = bounds
+            .into_iter()
+            .map(|(s, e)| (s - start, e - start))
+            .collect::<Vec<_>>();
+        if rle {
+            let runs = runs(&plain, &bounds);
+            self.work_buffer[encoding_pos] = COL_RLE;
+            self.work_buffer
+                .extend_from_slice(&(runs.len() as u32).to_le_bytes());
+            for (first, length) in runs {
+                let (s, e) = bounds[first];
+                self.work_buffer.extend_from_slice(&length.to_le_bytes());
+                self.work_buffer.extend_from_slice(&plain[s..e]);
+            }
+        } else {
+            self.work_buffer.extend_from_slice(&plain);
+        }
+        // Kept only when smaller than the runs or plain values
+        if let Some(encoded) = low_cardinality
+            .then(|| dictionary(&plain, &bounds))
+            .flatten()
+        {
+            if encoded.len() < self.work_buffer.len() - start {
+                self.work_buffer.truncate(encoding_pos);
+                self.work_buffer.push(COL_DICT);
+                self.work_buffer.extend_from_slice(&encoded);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'py> BFastParser<'_, 'py> {
+    fn read_u32(&mut self) -> PyResult<u32> {
+        let value = read_u32(self.data, self.offset)
+            .map_err(PyErr::new::<pyo3::exceptions::PyValueError, _>)?;
+        self.offset += 4;
+        Ok(value)
+    }
+
+    fn read_varint(&mut self, end: usize) -> PyResult<u64> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            if self.offset >= end {
+                break;
+            }
+            let byte = self.data[self.offset];
+            self.offset += 1;
+            value |= ((byte & 0x7F) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            "Malformed varint in delta-encoded column",
+        ))
+    }
+
+    fn parse_xor(&mut self, row_count: usize) -> PyResult<Vec<f64>> {
+        let length = self.read_u32()? as usize;
+        self.check_bounds(length)?;
+        let mut reader = BitReader {
+            data: &self.data[self.offset..self.offset + length],
+            position: 0,
+        };
+        let malformed =
+            || PyErr::new::<pyo3::exceptions::PyValueError, _>("Malformed XOR-encoded column");
+        let mut floats = Vec::with_capacity(row_count);
+        let mut previous = 0u64;
+        let mut window: Option<(u32, u32)> = None;
+        for index in 0..row_count {
+            if index == 0 {
+                previous = reader.read(64)?;
+            } else if reader.read(1)? == 1 {
+                if reader.read(1)? == 1 {
+                    let leading = reader.read(5)? as u32;
+                    let width = match reader.read(6)? as u32 {
+                        0 => 64,
+                        width => width,
+                    };
+                    if leading + width > 64 {
+                        return Err(malformed());
+                    }
+                    window = Some((leading, 64 - leading - width));
+                }
+                let (leading, trailing) = window.ok_or_else(malformed)?;
+                previous ^= reader.read(64 - leading - trailing)? << trailing;
+            }
+            floats.push(f64::from_bits(previous));
+        }
+        if reader.position.div_ceil(8) != length {
+            return Err(malformed());
+        }
+        self.offset += length;
+        Ok(floats)
+    }
+
+    fn parse_delta(&mut self, row_count: usize) -> PyResult<Vec<i64>> {
+        let length = self.read_u32()? as usize;
+        self.check_bounds(length)?;
+        let end = self.offset + length;
+        let mut ints = Vec::with_capacity(row_count);
+        let (mut previous, mut delta) = (0i64, 0i64);
+        for index in 0..row_count {
+            let encoded = unzigzag(self.read_varint(end)?);
+            let int = match index {
+                0 => encoded,
+                1 => previous.wrapping_add(encoded),
+                _ => previous.wrapping_add(delta).wrapping_add(encoded),
+            };
+            if index > 0 {
+                delta = int.wrapping_sub(previous);
+            }
+            previous = int;
+            ints.push(int);
+        }
+        if self.offset != end {
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+                "Delta-encoded column length mismatch",
+            ));
+        }
+        Ok(ints)
+    }
+
+    /// The int or f64 at `offset`, or None (leaving `offset` there) for any
+    /// other value.
+    fn read_number(&mut self) -> Option<Number> {
+        let tag = *self.data.get(self.offset)?;
+        let number = match tag {
+            0x40 | TAG_FLOAT32 => {
+                let width = float_width(tag)?;
+                let bytes = self.data.get(self.offset + 1..self.offset + 1 + width)?;
+                self.offset += width;
+                Number::Float(float_value(bytes))
+            }
+            t => {
+                let width = int_width(t)?;
+                let bytes = self.data.get(self.offset + 1..self.offset + 1 + width)?;
+                self.offset += width;
+                Number::Int(int_value(t, bytes))
+            }
+        };
+        self.offset += 1;
+        Some(number)
+    }
+
+    /// An empty column typed after the value at `offset`.
+    fn column_for(&self, row_count: usize) -> ColumnValues {
+        let capacity = row_count.min(self.data.len() - self.offset);
+        match self.data.get(self.offset) {
+            Some(&t) if float_width(t).is_some() => {
+                ColumnValues::Floats(Vec::with_capacity(capacity))
+            }
+            Some(&t) if int_width(t).is_some() => ColumnValues::Ints(Vec::with_capacity(capacity)),
+            _ => ColumnValues::Objects(Vec::with_capacity(capacity)),
+        }
+    }
+
+    /// Append the value at `offset` to `values`, read straight into a typed
+    /// column when it fits one.
+    fn push_value(&mut self, values: &mut ColumnValues) -> PyResult<()> {
+        let start = self.offset;
+        match (self.read_number(), &mut *values) {
+            (Some(Number::Int(int)), ColumnValues::Ints(ints)) => ints.push(int),
+            (Some(Number::Float(float)), ColumnValues::Floats(floats)) => floats.push(float),
+            _ => {
+                self.offset = start;
+                let value = self.parse()?;
+                values.widen(self.py).push(value);
+            }
+        }
+        Ok(())
+    }
+
+    /// One column of `row_count` values, from its encoding byte on.
+    fn parse_column(&mut self, row_count: usize) -> PyResult<ColumnValues> {
+        self.check_bounds(1)?;
+        let encoding = self.data[self.offset];
+        self.offset += 1;
+
+        Ok(match encoding {
+            COL_PLAIN => {
+                let mut values = self.column_for(row_count);
+                for _ in 0..row_count {
+                    self.push_value(&mut values)?;
+                }
+                values
+            }
+            COL_BOOL => ColumnValues::Bools(self.parse_bits(row_count)?),
+            COL_XOR => ColumnValues::Floats(self.parse_xor(row_count)?),
+            COL_DELTA => ColumnValues::Ints(self.parse_delta(row_count)?),
+            COL_RLE => {
+                let run_count = self.read_u32()?;
+                let mut values = Vec::with_capacity(row_count);
+                for _ in 0..run_count {
+                    let length = self.read_u32()? as usize;
+                    let value = self.parse()?;
+                    if length > row_count - values.len() {
+                        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+                            "Run-length column exceeds the row count",
+                        ));
+                    }
+                    values.extend((0..length).map(|_| value.clone_ref(self.py)));
+                }
+                if values.len() != row_count {
+                    return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+                        "Run-length column does not cover every row",
+                    ));
+                }
+                ColumnValues::Objects(values)
+            }
+            COL_DICT => {
+                let entry_count = self.read_u32()? as usize;
+                self.check_bounds(1)?;
+                let width = self.data[self.offset] as usize;
+                self.offset += 1;
+                if !matches!(width, 1 | 2 | 4) {
+                    return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                        "Invalid dictionary code width: {}",
+                        width
+                    )));
+                }
+                let mut entries = Vec::with_capacity(entry_count.min(row_count));
+                for _ in 0..entry_count {
+                    entries.push(self.parse()?);
+                }
+                self.check_bounds(row_count * width)?;
+                let mut values = Vec::with_capacity(row_count);
+                for code in self.data[self.offset..self.offset + row_count * width].chunks(width) {
+                    let mut bytes = [0; 4];
+                    bytes[..width].copy_from_slice(code);
+                    let entry =
+                        entries
+                            .get(u32::from_le_bytes(bytes) as usize)
+                            .ok_or_else(|| {
+                                PyErr::new::<pyo3::exceptions::PyValueError, _>(
+                                    "Dictionary code out of range",
+                                )
+                            })?;
+                    values.push(entry.clone_ref(self.py));
+                }
+                self.offset += row_count * width;
+                ColumnValues::Objects(values)
+            }
+            other => {
+                return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                    "Unknown column encoding: {}",
+                    other
+                )))
+            }
+        })
+    }
+
+    /// The row count and the named columns after TAG_COLUMNAR.
+    fn parse_columns(&mut self) -> PyResult<(usize, Vec<(&'py PyString, ColumnValues)>)> {
+        let row_count = self.read_u32()? as usize;
+        self.check_bounds(2)?;
+        let count = u16::from_le_bytes([self.data[self.offset], self.data[self.offset + 1]]);
+        self.offset += 2;
+
+        let mut columns = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            let key_id = self.read_u32()? as usize;
+            let key = self.key(key_id)?;
+            columns.push((key, self.parse_column(row_count)?));
+        }
+        Ok((row_count, columns))
+    }
+
+    /// The fields of a list of records written by the batch path, from the
+    /// record count after 0x60 on, as columns.
+    fn parse_record_columns(&mut self) -> PyResult<Vec<(&'py PyString, ColumnValues)>> {
+        let mismatch = || {
+            PyErr::new::<pyo3::exceptions::PyValueError, _>(
+                "Records must share their fields to decode as columns",
+            )
+        };
+        let row_count = self.read_u32()? as usize;
+        let mut columns: Vec<(&'py PyString, ColumnValues)> = Vec::new();
+        for row in 0..row_count {
+            self.check_bounds(1)?;
+            let mut field = 0;
+            if self.data[self.offset] == 0x70 {
+                self.offset += 1;
+                loop {
+                    self.check_bounds(1)?;
+                    if self.data[self.offset] == 0x7F {
+                        self.offset += 1;
+                        break;
+                    }
+                    let id = self.read_u32()? as usize;
+                    let key = self.key(id)?;
+                    if row == 0 {
+                        columns.push((key, self.column_for(row_count)));
+                    }
+                    match columns.get_mut(field) {
+                        Some((name, values)) if name.is(key) => self.push_value(values)?,
+                        _ => return Err(mismatch()),
+                    }
+                    field += 1;
+                }
+            } else {
+                // Records with a null bitmap go through the generic parser
+                let record = self.parse()?;
+                let record = record.into_ref(self.py).downcast::<PyDict>()?;
+                for (key, value) in record {
+                    let key = key.downcast::<PyString>()?;
+                    if row == 0 {
+                        columns.push((key, ColumnValues::Objects(Vec::new())));
+                    }
+                    match columns.get_mut(field) {
+                        Some((name, values)) if name.eq(key)? => {
+                            values.widen(self.py).push(value.into())
+                        }
+                        _ => return Err(mismatch()),
+                    }
+                    field += 1;
+                }
+            }
+            if field != columns.len() {
+                return Err(mismatch());
+            }
+        }
+        Ok(columns)
+    }
+
+    pub(crate) fn parse_columnar(&mut self) -> PyResult<PyObject> {
+        let (row_count, columns) = self.parse_columns()?;
+        let rows: Vec<&PyDict> = (0..row_count).map(|_| PyDict::new(self.py)).collect();
+        for (key, values) in columns {
+            for (row, value) in rows.iter().zip(values.into_objects(self.py)) {
+                // Float columns are read without going through `parse`
+                let value = match value.downcast::<PyFloat>(self.py) {
+                    Ok(float) if self.decimal_floats => self.float(float.value())?,
+                    _ => value,
+                };
+                self.insert(row, key, value)?;
+            }
+        }
+        let rows = rows
+            .into_iter()
+            .map(|row| self.mapping(row))
+            .collect::<PyResult<Vec<_>>>()?;
+        Ok(self.sequence(rows))
+    }
+}
+
+/// The columns of an uncompressed frame whose payload is columnar, or a list
+/// written by the batch path, as a dict.
+pub(crate) fn decode_columns(
+    py: Python,
+    data: &[u8],
+    arrays: bool,
+    lazy: bool,
+    limits: DecodeLimits,
+) -> PyResult<PyObject> {
+    if arrays {
+        // NumPy arrays cannot be built without it
+        py.import("numpy")?;
+    }
+    let options = DecodeOptions::limited(limits);
+    let (string_table, offset) = read_string_table(data, &options.limits)?;
+    let (fingerprint, offset) =
+        root_fingerprint(data, offset).map_err(PyErr::new::<pyo3::exceptions::PyValueError, _>)?;
+    let mut parser = BFastParser::new(py, data, &string_table, None, &options)?;
+    parser.offset = offset;
+    parser.check_bounds(1)?;
+    parser.offset += 1;
+    let columns = match (data[offset], fingerprint) {
+        (TAG_COLUMNAR, _) => parser.parse_columns()?.1,
+        (0x60, Some(_)) => parser.parse_record_columns()?,
+        _ => {
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+                "decode_columns expects a frame written by encode_columns, with \
+                 columnar=True or by the batch path",
+            ))
+        }
+    };
+    let dict = PyDict::new(py);
+    for (key, values) in columns {
+        let values = match values {
+            values if arrays => values.narrow(py)?.into_array(py),
+            values @ (ColumnValues::Ints(_) | ColumnValues::Floats(_)) if lazy => {
+                Py::new(py, LazyColumn { values })?.into_py(py)
+            }
+            values => PyList::new(py, values.into_objects(py)).into(),
+        };
+        dict.set_item(key, values)?;
+    }
+    Ok(dict.into())
+}
+
+/// Sequence over an int or float column that builds Python numbers only for
+/// the rows accessed.
+#[allow(non_local_definitions)]
+#[pyclass(sequence)]
+pub struct LazyColumn {
+    values: ColumnValues,
+}
+
+#[allow(non_local_definitions)]
+#[pymethods]
+impl LazyColumn {
+    fn __len__(&self) -> usize {
+        self.values.len()
+    }
+
+    fn __getitem__(&self, py: Python, key: &PyAny) -> PyResult<PyObject> {
+        let len = self.values.len();
+        if let Ok(slice) = key.downcast::<PySlice>() {
+            let indices = slice.indices(len as _)?;
+            let values = (0..indices.slicelength).map(|n| {
+                self.values
+                    .get(py, (indices.start + n * indices.step) as usize)
+            });
+            return Ok(PyList::new(py, values).into());
+        }
+        let index = key.extract::<isize>()?;
+        let resolved = if index < 0 {
+            index + len as isize
+        } else {
+            index
+        };
+        if !(0..len as isize).contains(&resolved) {
+            return Err(PyErr::new::<pyo3::exceptions::PyIndexError, _>(
+                "LazyColumn index out of range",
+            ));
+        }
+        Ok(self.values.get(py, resolved as usize))
+    }
+
+    fn __iter__(slf: PyRef<Self>) -> LazyColumnIter {
+        LazyColumnIter {
+            column: slf.into(),
+            index: 0,
+        }
+    }
+
+    /// NumPy dtype of the column: "int64" or "float64".
+    #[getter]
+    fn dtype(&self) -> &'static str {
+        match self.values {
+            ColumnValues::Ints(_) => "int64",
+            _ => "float64",
+        }
+    }
+
+    /// Every value in a regular list.
+    fn to_list(&self, py: Python) -> PyObject {
+        let values = (0..self.values.len()).map(|index| self.values.get(py, index));
+        PyList::new(py, values).into()
+    }
+
+    /// The column as a NumPy array (a copy of its buffer).
+    fn to_numpy(&self, py: Python) -> PyResult<PyObject> {
+        py.import("numpy")?;
+        Ok(match &self.values {
+            ColumnValues::Ints(ints) => PyArray1::from_vec(py, ints.clone()).into_py(py),
+            ColumnValues::Floats(floats) => PyArray1::from_vec(py, floats.clone()).into_py(py),
+            _ => unreachable!(),
+        })
+    }
+
+    fn __repr__(&self) -> String {
+        format!("LazyColumn({}, len={})", self.dtype(), self.values.len())
+    }
+}
+
+#[allow(non_local_definitions)]
+#[pyclass]
+pub struct LazyColumnIter {
+    column: Py<LazyColumn>,
+    index: usize,
+}
+
+#[allow(non_local_definitions)]
+#[pymethods]
+impl LazyColumnIter {
+    fn __iter__(slf: PyRef<Self>) -> PyRef<Self> {
+        slf
+    }
+
+    fn __next__(&mut self, py: Python) -> Option<PyObject> {
+        let column = self.column.borrow(py);
+        if self.index >= column.values.len() {
+            return None;
+        }
+        self.index += 1;
+        Some(column.values.get(py, self.index - 1))
+    }
+}
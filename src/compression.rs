@@ -0,0 +1,799 @@
+// Compression codecs for whole frames.
+//
+// Every codec implements `Codec`; encoders hold one and call it on finished
+// frames, so a new codec only needs an implementation and a name in `codec`.
+//
+// Compressed output starts with a frame header of its own, so a reader can
+// split a stream of frames without inflating them: "BF", the compressed flag
+// alone, the format version, a zero string count and the u32 length of what
+// follows, which is the u32 size of the inflated frame and then the codec's
+// output. The frame inside never has the compressed flag set.
+//
+// A size-prepended LZ4 block is what encode_packed produces by default, and
+// zstd frames are recognised by their magic number, so both are written as
+// they always were. Output of the other codecs goes into an envelope
+// recording the codec ID: ["BC"][codec ID][0xFF] then the compressed bytes.
+// Read as the u32 size prefix of an LZ4 block, that last 0xFF would mean more
+// than 4 GiB, so the two layouts never collide. Version 1 encoders wrote the
+// codec output bare, which still decodes.
+//
+// Large frames LZ4 splits into chunks compressed in parallel are enveloped
+// too (CODEC_LZ4_CHUNKS), as [u32 size][u32 chunk count] then each chunk as
+// [u32 length][size-prepended block]. Older encoders wrote that layout bare;
+// it is still read when the data does not decode as one block.
+//
+// "lz4-frame" writes the standard LZ4 frame format instead, which the lz4
+// command line tool and other LZ4 libraries read, and which decode_from
+// inflates while it reads the stream. Its magic number (04 22 4D 18) would
+// be the size prefix of a 389 MiB block, so data starting with it that does
+// not decode as an LZ4 frame is still tried as a block.
+//
+// "brotli" (the `brotli` feature) trades encode time for the smallest
+// output, for cold payloads served through CDNs. Builds without the feature
+// still recognise its codec ID and say what is missing.
+//
+// Encoders keep a frame the codec does not shrink (random bytes, media,
+// already compressed blobs) as it is, so it costs no extra header and decodes
+// without a decompression pass.
+
+use std::io::{self, Read, Write};
+use std::mem;
+use std::sync::Arc;
+
+use lz4_flex::frame::{FrameDecoder, FrameEncoder, FrameInfo};
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+use rayon::prelude::*;
+
+use crate::scan::unpack;
+use crate::{FLAG_COMPRESSED, FORMAT_VERSION, HEADER_SIZE};
+
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xB5, 0x2F, 0xFD];
+const ZSTD_DEFAULT_LEVEL: i32 = 9;
+#[cfg(feature = "brotli")]
+const BROTLI_DEFAULT_LEVEL: i32 = 11;
+#[cfg(feature = "brotli")]
+const BROTLI_WINDOW_BITS: i32 = 22;
+const LZ4_FRAME_MAGIC: [u8; 4] = [0x04, 0x22, 0x4D, 0x18];
+const ENVELOPE_MAGIC: [u8; 2] = *b"BC";
+const ENVELOPE_SIZE: usize = 4;
+/// Header of a compressed frame, then the inflated frame's size.
+const PACKED_HEADER_SIZE: usize = HEADER_SIZE + 4;
+
+// Codec IDs recorded in the envelope
+pub(crate) const CODEC_NONE: u8 = 0;
+pub(crate) const CODEC_LZ4: u8 = 1;
+pub(crate) const CODEC_ZSTD: u8 = 2;
+pub(crate) const CODEC_SNAPPY: u8 = 3;
+pub(crate) const CODEC_LZ4_CHUNKS: u8 = 4;
+pub(crate) const CODEC_LZ4_FRAME: u8 = 5;
+pub(crate) const CODEC_BROTLI: u8 = 6;
+
+pub(crate) trait Codec: Send + Sync {
+    /// ID written in the envelope.
+    fn id(&self) -> u8;
+
+    fn compress(&self, frame: &[u8]) -> Result<Vec<u8>, String>;
+
+    fn decompress(&self, data: &[u8]) -> Result<Vec<u8>, String>;
+
+    /// Whether the output identifies itself, so it needs no envelope.
+    fn self_describing(&self) -> bool {
+        false
+    }
+}
+
+/// Frames stored as they are.
+struct Identity;
+
+impl Codec for Identity {
+    fn id(&self) -> u8 {
+        CODEC_NONE
+    }
+
+    fn compress(&self, frame: &[u8]) -> Result<Vec<u8>, String> {
+        Ok(frame.to_vec())
+    }
+
+    fn decompress(&self, data: &[u8]) -> Result<Vec<u8>, String> {
+        Ok(data.to_vec())
+    }
+
+    // The frame's own "BF" magic
+    fn self_describing(&self) -> bool {
+        true
+    }
+}
+
+/// How LZ4 splits large frames into chunks compressed in parallel. The best
+/// values depend on the core count: more, smaller chunks keep a big machine
+/// busy, while on a few cores they only add per-chunk overhead.
+#[derive(Clone, Copy)]
+pub(crate) struct Chunking {
+    /// Frames at least this large are split
+    pub(crate) threshold: usize,
+    pub(crate) chunk_size: usize,
+}
+
+impl Default for Chunking {
+    fn default() -> Self {
+        Chunking {
+            threshold: 1_000_000,
+            chunk_size: 256 * 1024,
+        }
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct Lz4 {
+    pub(crate) chunking: Chunking,
+}
+
+impl Codec for Lz4 {
+    fn id(&self) -> u8 {
+        CODEC_LZ4
+    }
+
+    fn compress(&self, frame: &[u8]) -> Result<Vec<u8>, String> {
+        Ok(compress_lz4(frame, self.chunking))
+    }
+
+    fn decompress(&self, data: &[u8]) -> Result<Vec<u8>, String> {
+        decompress_lz4(data)
+    }
+
+    // Anything without another signature is read as LZ4
+    fn self_describing(&self) -> bool {
+        true
+    }
+}
+
+/// LZ4 blocks of fixed-size chunks of the frame, which compress and
+/// decompress in parallel.
+struct Lz4Chunks {
+    chunk_size: usize,
+}
+
+impl Codec for Lz4Chunks {
+    fn id(&self) -> u8 {
+        CODEC_LZ4_CHUNKS
+    }
+
+    fn compress(&self, frame: &[u8]) -> Result<Vec<u8>, String> {
+        Ok(compress_chunks(frame, self.chunk_size))
+    }
+
+    fn decompress(&self, data: &[u8]) -> Result<Vec<u8>, String> {
+        decompress_chunks(data)
+    }
+}
+
+struct Zstd {
+    level: i32,
+}
+
+impl Codec for Zstd {
+    fn id(&self) -> u8 {
+        CODEC_ZSTD
+    }
+
+    fn compress(&self, frame: &[u8]) -> Result<Vec<u8>, String> {
+        zstd::bulk::compress(frame, self.level)
+            .map_err(|e| format!("zstd compression failed: {}", e))
+    }
+
+    fn decompress(&self, data: &[u8]) -> Result<Vec<u8>, String> {
+        zstd::stream::decode_all(data).map_err(|e| format!("zstd decompression failed: {}", e))
+    }
+
+    fn self_describing(&self) -> bool {
+        true
+    }
+}
+
+/// The standard LZ4 frame format, with the content size and checksum.
+struct Lz4Frame;
+
+impl Codec for Lz4Frame {
+    fn id(&self) -> u8 {
+        CODEC_LZ4_FRAME
+    }
+
+    fn compress(&self, frame: &[u8]) -> Result<Vec<u8>, String> {
+        let info = FrameInfo::new()
+            .content_size(Some(frame.len() as u64))
+            .content_checksum(true);
+        let mut encoder =
+            FrameEncoder::with_frame_info(info, Vec::with_capacity(frame.len() / 2 + 64));
+        encoder
+            .write_all(frame)
+            .map_err(|e| format!("LZ4 frame compression failed: {}", e))?;
+        encoder
+            .finish()
+            .map_err(|e| format!("LZ4 frame compression failed: {}", e))
+    }
+
+    fn decompress(&self, data: &[u8]) -> Result<Vec<u8>, String> {
+        let mut frame = Vec::with_capacity(data.len() * 2);
+        FrameDecoder::new(data)
+            .read_to_end(&mut frame)
+            .map_err(|e| format!("LZ4 frame decompression failed: {}", e))?;
+        Ok(frame)
+    }
+
+    fn self_describing(&self) -> bool {
+        true
+    }
+}
+
+/// Raw snappy blocks, as Hadoop and Spark tooling reads them.
+struct Snappy;
+
+impl Codec for Snappy {
+    fn id(&self) -> u8 {
+        CODEC_SNAPPY
+    }
+
+    fn compress(&self, frame: &[u8]) -> Result<Vec<u8>, String> {
+        Ok(snappy_compress(frame))
+    }
+
+    fn decompress(&self, data: &[u8]) -> Result<Vec<u8>, String> {
+        snappy_decompress(data)
+    }
+}
+
+/// Brotli streams at `level` 0-11 (the `brotli` feature).
+#[cfg(feature = "brotli")]
+struct Brotli {
+    level: i32,
+}
+
+#[cfg(feature = "brotli")]
+impl Codec for Brotli {
+    fn id(&self) -> u8 {
+        CODEC_BROTLI
+    }
+
+    fn compress(&self, frame: &[u8]) -> Result<Vec<u8>, String> {
+        let params = brotli::enc::BrotliEncoderParams {
+            quality: self.level,
+            lgwin: BROTLI_WINDOW_BITS,
+            size_hint: frame.len(),
+            ..Default::default()
+        };
+        let mut out = Vec::with_capacity(frame.len() / 4 + 64);
+        brotli::BrotliCompress(&mut &frame[..], &mut out, &params)
+            .map_err(|e| format!("brotli compression failed: {}", e))?;
+        Ok(out)
+    }
+
+    fn decompress(&self, data: &[u8]) -> Result<Vec<u8>, String> {
+        let mut frame = Vec::with_capacity(data.len() * 4);
+        brotli::BrotliDecompress(&mut &data[..], &mut frame)
+            .map_err(|e| format!("brotli decompression failed: {}", e))?;
+        Ok(frame)
+    }
+}
+
+#[cfg(feature = "brotli")]
+fn brotli_codec(level: Option<i32>) -> Result<Arc<dyn Codec>, String> {
+    let level = level.unwrap_or(BROTLI_DEFAULT_LEVEL);
+    if !(0..=11).contains(&level) {
+        return Err(format!("Invalid brotli level: {}", level));
+    }
+    Ok(Arc::new(Brotli { level }))
+}
+
+#[cfg(not(feature = "brotli"))]
+fn brotli_codec(_level: Option<i32>) -> Result<Arc<dyn Codec>, String> {
+    Err("brotli compression needs a build with the brotli feature".to_string())
+}
+
+/// The codec called `name`, with `level` for the codecs that take one.
+pub(crate) fn codec(
+    name: &str,
+    level: Option<i32>,
+    chunking: Chunking,
+) -> Result<Arc<dyn Codec>, String> {
+    match name {
+        "none" => Ok(Arc::new(Identity)),
+        "lz4" => Ok(Arc::new(Lz4 { chunking })),
+        "zstd" => {
+            let level = level.unwrap_or(ZSTD_DEFAULT_LEVEL);
+            if !zstd::compression_level_range().contains(&level) {
+                return Err(format!("Invalid zstd level: {}", level));
+            }
+            Ok(Arc::new(Zstd { level }))
+        }
+        "snappy" => Ok(Arc::new(Snappy)),
+        "lz4-frame" => Ok(Arc::new(Lz4Frame)),
+        "brotli" => brotli_codec(level),
+        _ => Err(format!(
+            "Unknown compression {:?}; expected zstd, lz4, lz4-frame, snappy, brotli or none",
+            name
+        )),
+    }
+}
+
+fn codec_by_id(id: u8) -> Result<Arc<dyn Codec>, String> {
+    match id {
+        CODEC_NONE => Ok(Arc::new(Identity)),
+        CODEC_LZ4 => Ok(Arc::new(Lz4::default())),
+        CODEC_ZSTD => Ok(Arc::new(Zstd {
+            level: ZSTD_DEFAULT_LEVEL,
+        })),
+        CODEC_SNAPPY => Ok(Arc::new(Snappy)),
+        CODEC_LZ4_CHUNKS => Ok(Arc::new(Lz4Chunks {
+            chunk_size: Chunking::default().chunk_size,
+        })),
+        CODEC_LZ4_FRAME => Ok(Arc::new(Lz4Frame)),
+        CODEC_BROTLI => brotli_codec(None),
+        _ => Err(format!("Unknown codec ID {} in compressed frame", id)),
+    }
+}
+
+/// Compress a whole frame with `codec`, in an envelope unless its output
+/// identifies itself, behind the header of a compressed frame.
+pub(crate) fn compress(codec: &dyn Codec, frame: &[u8]) -> Result<Vec<u8>, String> {
+    let output = codec.compress(frame)?;
+    match codec.id() {
+        // Still the frame itself
+        CODEC_NONE => Ok(output),
+        _ if codec.self_describing() => packed(frame.len(), &output),
+        id => packed(frame.len(), &envelope(id, &output)),
+    }
+}
+
+/// `frame` compressed with `codec`, or taken as it is when that is no
+/// smaller.
+pub(crate) fn compress_or_store(codec: &dyn Codec, frame: &mut Vec<u8>) -> Result<Vec<u8>, String> {
+    let compressed = compress(codec, frame)?;
+    Ok(smaller_or_stored(compressed, frame))
+}
+
+fn smaller_or_stored(compressed: Vec<u8>, frame: &mut Vec<u8>) -> Vec<u8> {
+    if compressed.len() < frame.len() {
+        return compressed;
+    }
+    mem::take(frame)
+}
+
+/// `output`, the codec's output for a `frame_len`-byte frame, behind the
+/// header of a compressed frame.
+fn packed(frame_len: usize, output: &[u8]) -> Result<Vec<u8>, String> {
+    let too_large = |_| "Frame larger than 4 GiB".to_string();
+    let size = u32::try_from(frame_len).map_err(too_large)?;
+    let length = u32::try_from(4 + output.len()).map_err(too_large)?;
+    let mut packed = Vec::with_capacity(PACKED_HEADER_SIZE + output.len());
+    packed.extend_from_slice(b"BF");
+    packed.extend_from_slice(&[FLAG_COMPRESSED, FORMAT_VERSION, 0, 0]);
+    packed.extend_from_slice(&length.to_le_bytes());
+    packed.extend_from_slice(&size.to_le_bytes());
+    packed.extend_from_slice(output);
+    Ok(packed)
+}
+
+/// Whether `data` starts with the header of a compressed frame.
+pub(crate) fn is_packed(data: &[u8]) -> bool {
+    data.len() >= HEADER_SIZE
+        && data.starts_with(b"BF")
+        && data[2] & FLAG_COMPRESSED != 0
+        && data[3] == FORMAT_VERSION
+}
+
+/// The frame the compressed frame `data` starts with, inflated.
+pub(crate) fn unpack_frame(data: &[u8]) -> Result<Vec<u8>, String> {
+    let length = u32::from_le_bytes(data[6..10].try_into().unwrap()) as usize;
+    if length < 4 || data.len() < HEADER_SIZE + length {
+        return Err("Truncated compressed frame".to_string());
+    }
+    let frame = decompress(&data[PACKED_HEADER_SIZE..HEADER_SIZE + length])?;
+    check_inflated_size(&frame, data)?;
+    Ok(frame)
+}
+
+/// Fail unless `frame` has the size the compressed frame header `head`
+/// records.
+fn check_inflated_size(frame: &[u8], head: &[u8]) -> Result<(), String> {
+    let size = u32::from_le_bytes(head[10..14].try_into().unwrap()) as usize;
+    if frame.len() != size {
+        return Err(format!(
+            "Compressed frame inflated to {} bytes, but its header records {}",
+            frame.len(),
+            size
+        ));
+    }
+    Ok(())
+}
+
+fn envelope(id: u8, compressed: &[u8]) -> Vec<u8> {
+    let mut output = Vec::with_capacity(ENVELOPE_SIZE + compressed.len());
+    output.extend_from_slice(&ENVELOPE_MAGIC);
+    output.extend_from_slice(&[id, 0xFF]);
+    output.extend_from_slice(compressed);
+    output
+}
+
+/// Decompress codec output, picking the codec from the envelope or the
+/// data's own signature.
+pub(crate) fn decompress(data: &[u8]) -> Result<Vec<u8>, String> {
+    if is_enveloped(data) {
+        return codec_by_id(data[2])?.decompress(&data[ENVELOPE_SIZE..]);
+    }
+    if data.starts_with(&ZSTD_MAGIC) {
+        return Zstd {
+            level: ZSTD_DEFAULT_LEVEL,
+        }
+        .decompress(data);
+    }
+    if is_lz4_frame(data) {
+        if let Ok(frame) = Lz4Frame.decompress(data) {
+            return Ok(frame);
+        }
+    }
+    decompress_lz4(data)
+}
+
+/// Whether `data` starts like an LZ4 frame.
+fn is_lz4_frame(data: &[u8]) -> bool {
+    data.starts_with(&LZ4_FRAME_MAGIC)
+}
+
+/// Where the LZ4 frame `head`, the first bytes of a stream, holds starts:
+/// past the header of a compressed frame, or at once for output of version 1
+/// encoders.
+pub(crate) fn lz4_frame_start(head: &[u8]) -> Option<usize> {
+    if is_packed(head) && is_lz4_frame(&head[PACKED_HEADER_SIZE..]) {
+        return Some(PACKED_HEADER_SIZE);
+    }
+    is_lz4_frame(head).then_some(0)
+}
+
+/// The first bytes of `fp`, enough to tell an LZ4 frame by.
+pub(crate) fn read_head(fp: &PyAny) -> PyResult<Vec<u8>> {
+    let size = PACKED_HEADER_SIZE + LZ4_FRAME_MAGIC.len();
+    let mut head = Vec::with_capacity(size);
+    PyReader { fp }
+        .take(size as u64)
+        .read_to_end(&mut head)
+        .map_err(|e| reader_error(e, "Reading the stream failed"))?;
+    Ok(head)
+}
+
+/// Inflate the LZ4 frame `fp` holds, starting at `start` in `head`, the
+/// bytes already read, and pulling the rest from `fp` as the decoder needs
+/// it.
+pub(crate) fn read_lz4_frame(fp: &PyAny, head: &[u8], start: usize) -> PyResult<Vec<u8>> {
+    let mut frame = Vec::new();
+    FrameDecoder::new(head[start..].chain(PyReader { fp }))
+        .read_to_end(&mut frame)
+        .map_err(|e| reader_error(e, "LZ4 frame decompression failed"))?;
+    if start > 0 {
+        check_inflated_size(&frame, head)
+            .map_err(PyErr::new::<pyo3::exceptions::PyValueError, _>)?;
+    }
+    Ok(frame)
+}
+
+/// The Python exception a `PyReader` read raised, or a ValueError for any
+/// other failure.
+fn reader_error(error: io::Error, context: &str) -> PyErr {
+    let message = format!("{}: {}", context, error);
+    match error.into_inner().map(|inner| inner.downcast::<PyErr>()) {
+        Some(Ok(err)) => *err,
+        _ => PyErr::new::<pyo3::exceptions::PyValueError, _>(message),
+    }
+}
+
+/// A Python file object read through `io::Read`; errors it raises come back
+/// out of the reader's caller unchanged.
+struct PyReader<'py> {
+    fp: &'py PyAny,
+}
+
+impl Read for PyReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut read = || -> PyResult<usize> {
+            let result = self.fp.call_method1("read", (buf.len(),))?;
+            if result.is_none() {
+                return Err(PyErr::new::<pyo3::exceptions::PyBlockingIOError, _>(
+                    "Source returned no data; non-blocking streams are not supported",
+                ));
+            }
+            let bytes = result.extract::<&[u8]>()?;
+            let n = bytes.len().min(buf.len());
+            buf[..n].copy_from_slice(&bytes[..n]);
+            Ok(n)
+        };
+        read().map_err(io::Error::other)
+    }
+}
+
+#[inline]
+fn is_enveloped(data: &[u8]) -> bool {
+    data.len() >= ENVELOPE_SIZE && data.starts_with(&ENVELOPE_MAGIC) && data[3] == 0xFF
+}
+
+/// LZ4-compress a whole frame, splitting large ones into parallel chunks;
+/// frames it does not shrink are stored.
+pub(crate) fn compress_frame(mut frame: Vec<u8>) -> Result<Vec<u8>, String> {
+    let compressed = packed(frame.len(), &compress_lz4(&frame, Chunking::default()))?;
+    Ok(smaller_or_stored(compressed, &mut frame))
+}
+
+fn compress_lz4(data: &[u8], chunking: Chunking) -> Vec<u8> {
+    // Frames under two chunks gain nothing from splitting
+    if data.len() >= chunking.threshold && data.len() >= chunking.chunk_size.saturating_mul(2) {
+        envelope(
+            CODEC_LZ4_CHUNKS,
+            &compress_chunks(data, chunking.chunk_size),
+        )
+    } else {
+        lz4_flex::compress_prepend_size(data)
+    }
+}
+
+fn compress_chunks(data: &[u8], chunk_size: usize) -> Vec<u8> {
+    let total_size = data.len();
+
+    let chunks: Vec<Vec<u8>> = data
+        .par_chunks(chunk_size)
+        .map(lz4_flex::compress_prepend_size)
+        .collect();
+
+    let mut result = Vec::with_capacity(total_size / 2);
+    result.extend_from_slice(&(total_size as u32).to_le_bytes());
+    result.extend_from_slice(&(chunks.len() as u32).to_le_bytes());
+
+    for chunk in &chunks {
+        result.extend_from_slice(&(chunk.len() as u32).to_le_bytes());
+        result.extend_from_slice(chunk);
+    }
+
+    result
+}
+
+fn decompress_lz4(data: &[u8]) -> Result<Vec<u8>, String> {
+    if data.len() < 8 {
+        return Err("Buffer too small for compressed B-FAST data".to_string());
+    }
+
+    // Try single-chunk decompression first
+    if let Ok(decompressed) = lz4_flex::decompress_size_prepended(data) {
+        return Ok(decompressed);
+    }
+
+    // Fall back to the chunks older encoders wrote without an envelope
+    decompress_chunks(data)
+}
+
+fn decompress_chunks(data: &[u8]) -> Result<Vec<u8>, String> {
+    if data.len() < 8 {
+        return Err("Buffer too small for compressed B-FAST data".to_string());
+    }
+
+    let uncompressed_size = u32::from_le_bytes(data[0..4].try_into().unwrap()) as usize;
+    let chunks_count = u32::from_le_bytes(data[4..8].try_into().unwrap()) as usize;
+
+    let max_possible_chunks = (data.len() - 8) / 4;
+    if chunks_count > max_possible_chunks {
+        return Err("Invalid chunks count in parallel compression header".to_string());
+    }
+
+    let mut offset = 8;
+    let mut chunk_slices = Vec::with_capacity(chunks_count);
+
+    for _ in 0..chunks_count {
+        if offset + 4 > data.len() {
+            return Err("Unexpected end of data in parallel compression chunk headers".to_string());
+        }
+        let chunk_len = u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap()) as usize;
+        offset += 4;
+        if offset + chunk_len > data.len() {
+            return Err("Unexpected end of data in parallel compression chunk data".to_string());
+        }
+        chunk_slices.push(&data[offset..offset + chunk_len]);
+        offset += chunk_len;
+    }
+
+    let decompressed_chunks: Result<Vec<Vec<u8>>, _> = chunk_slices
+        .into_par_iter()
+        .map(lz4_flex::decompress_size_prepended)
+        .collect();
+
+    let decompressed_chunks =
+        decompressed_chunks.map_err(|e| format!("LZ4 chunk decompression failed: {}", e))?;
+    let result = decompressed_chunks.concat();
+    if result.len() != uncompressed_size {
+        return Err(format!(
+            "Decompressed size mismatch: expected {}, got {}",
+            uncompressed_size,
+            result.len()
+        ));
+    }
+    Ok(result)
+}
+
+// Snappy block format: the uncompressed length as a varint, then elements
+// whose tag's low two bits pick a literal (0) or a copy with a 1-, 2- or
+// 4-byte offset (1, 2, 3).
+
+const SNAPPY_HASH_BITS: u32 = 14;
+const SNAPPY_MIN_MATCH: usize = 4;
+
+fn snappy_compress(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len() / 2 + 16);
+    let mut length = data.len() as u64;
+    while length >= 0x80 {
+        out.push(length as u8 | 0x80);
+        length >>= 7;
+    }
+    out.push(length as u8);
+
+    let mut table = vec![0usize; 1 << SNAPPY_HASH_BITS];
+    let hash = |at: usize| {
+        let word = u32::from_le_bytes(data[at..at + 4].try_into().unwrap());
+        (word.wrapping_mul(0x1E35_A7BD) >> (32 - SNAPPY_HASH_BITS)) as usize
+    };
+    let (mut literal_start, mut at) = (0, 0);
+    while at + SNAPPY_MIN_MATCH <= data.len() {
+        let slot = hash(at);
+        // Table entries hold position + 1, so 0 means empty
+        let candidate = table[slot].checked_sub(1);
+        table[slot] = at + 1;
+        let Some(candidate) = candidate.filter(|&c| {
+            at - c <= u32::MAX as usize
+                && data[c..c + SNAPPY_MIN_MATCH] == data[at..at + SNAPPY_MIN_MATCH]
+        }) else {
+            at += 1;
+            continue;
+        };
+        let mut end = at + SNAPPY_MIN_MATCH;
+        while end < data.len() && data[end] == data[candidate + end - at] {
+            end += 1;
+        }
+        snappy_literal(&mut out, &data[literal_start..at]);
+        snappy_copy(&mut out, at - candidate, end - at);
+        at = end;
+        literal_start = end;
+    }
+    snappy_literal(&mut out, &data[literal_start..]);
+    out
+}
+
+fn snappy_literal(out: &mut Vec<u8>, literal: &[u8]) {
+    if literal.is_empty() {
+        return;
+    }
+    let n = literal.len() - 1;
+    if n < 60 {
+        out.push((n as u8) << 2);
+    } else {
+        let bytes = (n as u32).to_le_bytes();
+        let width = 4 - (n as u32).leading_zeros() as usize / 8;
+        out.push(((59 + width) as u8) << 2);
+        out.extend_from_slice(&bytes[..width]);
+    }
+    out.extend_from_slice(literal);
+}
+
+fn snappy_copy(out: &mut Vec<u8>, offset: usize, mut length: usize) {
+    while length > 0 {
+        // Copies hold at most 64 bytes; never leave fewer than 4 behind
+        let chunk = match length {
+            65..=67 => 60,
+            _ => length.min(64),
+        };
+        if (4..12).contains(&chunk) && offset < 2048 {
+            out.push(1 | ((chunk - 4) << 2) as u8 | ((offset >> 8) << 5) as u8);
+            out.push(offset as u8);
+        } else if offset <= u16::MAX as usize {
+            out.push(2 | ((chunk - 1) << 2) as u8);
+            out.extend_from_slice(&(offset as u16).to_le_bytes());
+        } else {
+            out.push(3 | ((chunk - 1) << 2) as u8);
+            out.extend_from_slice(&(offset as u32).to_le_bytes());
+        }
+        length -= chunk;
+    }
+}
+
+fn snappy_decompress(data: &[u8]) -> Result<Vec<u8>, String> {
+    let truncated = || "Truncated snappy data".to_string();
+    let (mut length, mut at) = (0u64, 0);
+    loop {
+        let byte = *data.get(at).ok_or_else(truncated)?;
+        if at == 10 {
+            return Err("Invalid snappy length".to_string());
+        }
+        length |= ((byte & 0x7F) as u64) << (7 * at);
+        at += 1;
+        if byte & 0x80 == 0 {
+            break;
+        }
+    }
+    let length = length as usize;
+    // Each input byte expands to at most 64 output bytes
+    let mut out = Vec::with_capacity(length.min(data.len().saturating_mul(64)));
+
+    while at < data.len() {
+        let tag = data[at];
+        at += 1;
+        let (offset, copy_length) = match tag & 3 {
+            0 => {
+                let mut n = (tag >> 2) as usize;
+                if n >= 60 {
+                    let width = n - 59;
+                    let bytes = data.get(at..at + width).ok_or_else(truncated)?;
+                    n = bytes
+                        .iter()
+                        .rev()
+                        .fold(0, |n, &byte| n << 8 | byte as usize);
+                    at += width;
+                }
+                let literal = data.get(at..at + n + 1).ok_or_else(truncated)?;
+                out.extend_from_slice(literal);
+                at += n + 1;
+                continue;
+            }
+            1 => {
+                let low = *data.get(at).ok_or_else(truncated)? as usize;
+                at += 1;
+                ((tag as usize >> 5) << 8 | low, 4 + (tag as usize >> 2 & 7))
+            }
+            2 => {
+                let bytes = data.get(at..at + 2).ok_or_else(truncated)?;
+                at += 2;
+                (
+                    u16::from_le_bytes(bytes.try_into().unwrap()) as usize,
+                    1 + (tag >> 2) as usize,
+                )
+            }
+            _ => {
+                let bytes = data.get(at..at + 4).ok_or_else(truncated)?;
+                at += 4;
+                (
+                    u32::from_le_bytes(bytes.try_into().unwrap()) as usize,
+                    1 + (tag >> 2) as usize,
+                )
+            }
+        };
+        if offset == 0 || offset > out.len() {
+            return Err(format!("Invalid snappy copy offset {}", offset));
+        }
+        // Overlapping copies repeat the bytes they have just written
+        let start = out.len() - offset;
+        for i in 0..copy_length {
+            out.push(out[start + i]);
+        }
+    }
+    if out.len() != length {
+        return Err(format!(
+            "Decompressed size mismatch: expected {}, got {}",
+            length,
+            out.len()
+        ));
+    }
+    Ok(out)
+}
+
+/// Decompress `data` and compress the same frame bytes with other settings.
+#[pyfunction]
+#[pyo3(signature = (data, compression = "zstd", level = None))]
+pub fn recompress(
+    py: Python,
+    data: &[u8],
+    compression: &str,
+    level: Option<i32>,
+) -> PyResult<PyObject> {
+    let codec = codec(compression, level, Chunking::default())
+        .map_err(PyErr::new::<pyo3::exceptions::PyValueError, _>)?;
+    let output = py
+        .allow_threads(|| {
+            let (frame, _) = unpack(data)?;
+            compress(&*codec, &frame)
+        })
+        .map_err(PyErr::new::<pyo3::exceptions::PyValueError, _>)?;
+    Ok(PyBytes::new(py, &output).into())
+}
@@ -0,0 +1,631 @@
+// bfast-lines: an append-friendly stream of independently decodable frames.
+// Each record is [u32 LE frame length][frame], so files can be appended to,
+// concatenated, and read back one document at a time.
+//
+// Log mode adds a CRC32 of the frame: [u32 length][u32 crc32][frame]. A torn
+// or corrupt final record ends iteration cleanly instead of raising, and a
+// writer opened on an existing log truncates such a tail before appending. A
+// corrupt record with others behind it is not a torn write, so readers and
+// the writer raise instead of dropping them.
+
+use std::borrow::Cow;
+use std::collections::VecDeque;
+
+use pyo3::prelude::*;
+use pyo3::types::{PyAny, PyBytes, PyString};
+use rayon::prelude::*;
+
+use crate::{
+    decode_bytes, decode_frame, decompress_packed, write_chunked, BFast, DecodeLimits,
+    DecodeOptions, DEFAULT_MAX_DEPTH, DEFAULT_MAX_KEYS, DEFAULT_MAX_KEY_LENGTH,
+};
+
+/// Whether the `length` bytes at the current position of `file` hash to
+/// `checksum`; they are read a block at a time.
+fn record_matches(file: &mut std::fs::File, length: u64, checksum: u32) -> std::io::Result<bool> {
+    use std::io::Read;
+
+    let mut hasher = crc32fast::Hasher::new();
+    let mut block = vec![0u8; 64 * 1024];
+    let mut left = length;
+    while left > 0 {
+        let take = left.min(block.len() as u64) as usize;
+        file.read_exact(&mut block[..take])?;
+        hasher.update(&block[..take]);
+        left -= take as u64;
+    }
+    Ok(hasher.finalize() == checksum)
+}
+
+/// Drop a torn final record from an existing log file: one cut short, or
+/// failing its checksum with nothing behind it. Returns the bytes removed.
+/// A corrupt record followed by others raises, leaving the file as it is.
+fn recover_log_file(path: &str) -> PyResult<u64> {
+    use std::io::Read;
+
+    let mut file = match std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(path)
+    {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e.into()),
+    };
+    let size = file.metadata()?.len();
+    let mut offset = 0u64;
+    while offset < size {
+        let start = offset + 8;
+        if start > size {
+            break;
+        }
+        let mut header = [0u8; 8];
+        file.read_exact(&mut header)?;
+        let length = u32::from_le_bytes(header[..4].try_into().unwrap()) as u64;
+        let checksum = u32::from_le_bytes(header[4..].try_into().unwrap());
+        if start + length > size {
+            break;
+        }
+        if !record_matches(&mut file, length, checksum)? {
+            if start + length == size {
+                break;
+            }
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                "Corrupt log record at byte {} is followed by more records; \
+                 the file was left unchanged",
+                offset
+            )));
+        }
+        offset = start + length;
+    }
+    if offset < size {
+        file.set_len(offset)?;
+        file.sync_all()?;
+    }
+    Ok(size - offset)
+}
+
+/// Open `target` when it is a path, otherwise use it as a file-like object.
+pub(crate) fn open_target(py: Python, target: &PyAny, mode: &str) -> PyResult<(PyObject, bool)> {
+    if target.is_instance_of::<PyString>() || target.hasattr("__fspath__")? {
+        let fp = py
+            .import("builtins")?
+            .call_method1("open", (target, mode))?;
+        Ok((fp.into(), true))
+    } else {
+        Ok((target.into(), false))
+    }
+}
+
+/// Read exactly `size` bytes. Returns `None` on a clean EOF before the first
+/// byte and the partial data otherwise, so callers can tell torn records apart.
+pub(crate) fn read_exact(fp: &PyAny, size: usize) -> PyResult<Option<Vec<u8>>> {
+    // `size` comes from the stream, so the buffer grows as data arrives
+    let mut data = Vec::with_capacity(size.min(1 << 20));
+    while data.len() < size {
+        let chunk = fp.call_method1("read", (size - data.len(),))?;
+        if chunk.is_none() {
+            return Err(PyErr::new::<pyo3::exceptions::PyBlockingIOError, _>(
+                "Source returned no data; non-blocking streams are not supported",
+            ));
+        }
+        let bytes = chunk.downcast::<PyBytes>()?.as_bytes();
+        if bytes.is_empty() {
+            break;
+        }
+        data.extend_from_slice(bytes);
+    }
+    if data.is_empty() && size > 0 {
+        return Ok(None);
+    }
+    Ok(Some(data))
+}
+
+#[allow(non_local_definitions)]
+#[pyclass]
+pub struct BFastWriter {
+    fp: PyObject,
+    owns_fp: bool,
+    encoder: BFast,
+    compress: bool,
+    frames: usize,
+    closed: bool,
+    log: bool,
+    fsync_every: usize,
+    unsynced: usize,
+    recovered_bytes: u64,
+}
+
+#[allow(non_local_definitions)]
+#[pymethods]
+impl BFastWriter {
+    #[new]
+    #[pyo3(signature = (target, *, compress = false, log = false, fsync_every = 0))]
+    fn new(
+        py: Python,
+        target: &PyAny,
+        compress: bool,
+        log: bool,
+        fsync_every: usize,
+    ) -> PyResult<Self> {
+        let mut recovered_bytes = 0;
+        if log && (target.is_instance_of::<PyString>() || target.hasattr("__fspath__")?) {
+            let path = py
+                .import("os")?
+                .call_method1("fspath", (target,))?
+                .extract::<String>()?;
+            recovered_bytes = recover_log_file(&path)?;
+        }
+
+        let (fp, owns_fp) = open_target(py, target, "ab")?;
+        Ok(BFastWriter {
+            fp,
+            owns_fp,
+            encoder: BFast {
+                checksum: log,
+                ..BFast::new()
+            },
+            compress,
+            frames: 0,
+            closed: false,
+            log,
+            fsync_every,
+            unsynced: 0,
+            recovered_bytes,
+        })
+    }
+
+    /// Encode `obj` as one frame and append it. Returns the bytes written.
+    fn append(&mut self, py: Python, obj: &PyAny) -> PyResult<usize> {
+        if self.closed {
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+                "I/O operation on closed BFastWriter",
+            ));
+        }
+        let frame = self.encoder.encode_to_vec(obj, self.compress)?;
+        let length = u32::try_from(frame.len())
+            .map_err(|_| PyErr::new::<pyo3::exceptions::PyValueError, _>("Frame exceeds 4 GiB"))?;
+
+        let fp = self.fp.as_ref(py);
+        let mut prefix = Vec::with_capacity(8);
+        prefix.extend_from_slice(&length.to_le_bytes());
+        if self.log {
+            let checksum = self
+                .encoder
+                .frame_crc
+                .unwrap_or_else(|| crc32fast::hash(&frame));
+            prefix.extend_from_slice(&checksum.to_le_bytes());
+        }
+        write_chunked(fp, &prefix)?;
+        write_chunked(fp, &frame)?;
+        self.frames += 1;
+
+        if self.fsync_every > 0 {
+            self.unsynced += 1;
+            if self.unsynced >= self.fsync_every {
+                self.sync(py)?;
+            }
+        }
+        Ok(prefix.len() + frame.len())
+    }
+
+    /// Flush and fsync the underlying file descriptor.
+    fn sync(&mut self, py: Python) -> PyResult<()> {
+        self.flush(py)?;
+        let fp = self.fp.as_ref(py);
+        if fp.hasattr("fileno")? {
+            py.import("os")?
+                .call_method1("fsync", (fp.call_method0("fileno")?,))?;
+        }
+        self.unsynced = 0;
+        Ok(())
+    }
+
+    /// Bytes of a torn tail removed when the log was reopened.
+    #[getter]
+    fn recovered_bytes(&self) -> u64 {
+        self.recovered_bytes
+    }
+
+    /// Append every object of an iterable, one frame each.
+    fn extend(&mut self, py: Python, objs: &PyAny) -> PyResult<usize> {
+        let mut written = 0;
+        for obj in objs.iter()? {
+            written += self.append(py, obj?)?;
+        }
+        Ok(written)
+    }
+
+    #[getter]
+    fn frames(&self) -> usize {
+        self.frames
+    }
+
+    fn flush(&self, py: Python) -> PyResult<()> {
+        let fp = self.fp.as_ref(py);
+        if fp.hasattr("flush")? {
+            fp.call_method0("flush")?;
+        }
+        Ok(())
+    }
+
+    fn close(&mut self, py: Python) -> PyResult<()> {
+        if self.closed {
+            return Ok(());
+        }
+        if self.unsynced > 0 {
+            self.sync(py)?;
+        } else {
+            self.flush(py)?;
+        }
+        if self.owns_fp {
+            self.fp.as_ref(py).call_method0("close")?;
+        }
+        self.closed = true;
+        Ok(())
+    }
+
+    fn __enter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __exit__(
+        &mut self,
+        py: Python,
+        _exc_type: &PyAny,
+        _exc_value: &PyAny,
+        _traceback: &PyAny,
+    ) -> PyResult<bool> {
+        self.close(py)?;
+        Ok(false)
+    }
+}
+
+#[allow(non_local_definitions)]
+#[pyclass]
+pub struct BFastReader {
+    fp: PyObject,
+    owns_fp: bool,
+    decompress: bool,
+    frames: usize,
+    log: bool,
+    truncated: bool,
+    /// Byte offset in the source past everything read so far, less the
+    /// records in `pending`.
+    position: u64,
+    limits: DecodeLimits,
+    /// Records read from the source but not returned yet, in order.
+    pending: VecDeque<Record>,
+    /// First byte of the next record, read by `at_end`.
+    peeked: Option<u8>,
+}
+
+/// A record taken from the source.
+struct Record {
+    frame: Vec<u8>,
+    /// Stored checksum (0 outside log mode).
+    checksum: u32,
+    /// Bytes the record takes in the source, prefix included.
+    size: u64,
+}
+
+impl BFastReader {
+    /// Length of the record prefix: frame length, plus a checksum in log mode.
+    fn prefix_len(&self) -> usize {
+        if self.log {
+            8
+        } else {
+            4
+        }
+    }
+
+    // Plain containers treat a damaged record as an error; logs expect a torn
+    // tail after a crash and simply stop there
+    fn torn<T>(&mut self, message: String) -> PyResult<Option<T>> {
+        if self.log {
+            self.truncated = true;
+            return Ok(None);
+        }
+        Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(message))
+    }
+
+    /// The next record.
+    fn read_record(&mut self, py: Python) -> PyResult<Option<Record>> {
+        if self.truncated {
+            return Ok(None);
+        }
+        if let Some(record) = self.pending.pop_front() {
+            self.position += record.size;
+            return Ok(Some(record));
+        }
+        let prefix_len = self.prefix_len();
+        let header = match self.peeked.take() {
+            Some(byte) => {
+                let rest = read_exact(self.fp.as_ref(py), prefix_len - 1)?;
+                [vec![byte], rest.unwrap_or_default()].concat()
+            }
+            None => match read_exact(self.fp.as_ref(py), prefix_len)? {
+                Some(header) => header,
+                None => return Ok(None),
+            },
+        };
+        let fp = self.fp.as_ref(py);
+        self.position += header.len() as u64;
+        if header.len() < prefix_len {
+            return self.torn(format!(
+                "Truncated length prefix after frame {}",
+                self.frames
+            ));
+        }
+        let length = u32::from_le_bytes(header[..4].try_into().unwrap()) as usize;
+
+        let frame = read_exact(fp, length)?.unwrap_or_default();
+        self.position += frame.len() as u64;
+        if frame.len() < length {
+            return self.torn(format!(
+                "Truncated frame {}: expected {} bytes, got {}",
+                self.frames,
+                length,
+                frame.len()
+            ));
+        }
+        let checksum = match self.log {
+            true => u32::from_le_bytes(header[4..8].try_into().unwrap()),
+            false => 0,
+        };
+        Ok(Some(Record {
+            size: (prefix_len + length) as u64,
+            frame,
+            checksum,
+        }))
+    }
+
+    /// Whether nothing follows the records read so far.
+    fn at_end(&mut self, py: Python) -> PyResult<bool> {
+        if !self.pending.is_empty() || self.peeked.is_some() {
+            return Ok(false);
+        }
+        let next = read_exact(self.fp.as_ref(py), 1)?;
+        self.peeked = next.map(|byte| byte[0]);
+        Ok(self.peeked.is_none())
+    }
+
+    /// The error for a log record failing its checksum with `followed` set
+    /// when other records come after it, or None for a torn final record,
+    /// which ends the log.
+    fn checksum_error(&mut self, followed: bool) -> Option<PyErr> {
+        if !followed {
+            self.truncated = true;
+            return None;
+        }
+        Some(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+            "Checksum mismatch in frame {}, which is followed by more records",
+            self.frames
+        )))
+    }
+
+    /// Put `records` back in front of the ones still to read.
+    fn unread(&mut self, records: Vec<Record>) {
+        for record in records.into_iter().rev() {
+            self.position -= record.size;
+            self.pending.push_front(record);
+        }
+    }
+}
+
+/// Why a record read by `read_many` cannot be decoded.
+enum RecordError {
+    Checksum,
+    Invalid(String),
+}
+
+#[allow(non_local_definitions)]
+#[pymethods]
+impl BFastReader {
+    #[new]
+    #[pyo3(signature = (
+        source,
+        *,
+        decompress = true,
+        log = false,
+        position = None,
+        max_keys = DEFAULT_MAX_KEYS,
+        max_key_length = DEFAULT_MAX_KEY_LENGTH,
+        max_depth = DEFAULT_MAX_DEPTH
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        py: Python,
+        source: &PyAny,
+        decompress: bool,
+        log: bool,
+        position: Option<u64>,
+        max_keys: usize,
+        max_key_length: usize,
+        max_depth: usize,
+    ) -> PyResult<Self> {
+        let limits = DecodeLimits::new(max_keys, max_key_length, max_depth)?;
+        let (fp, owns_fp) = open_target(py, source, "rb")?;
+        // Resuming from a saved position() skips straight to that record
+        let position = match position {
+            Some(position) => {
+                fp.as_ref(py).call_method1("seek", (position,))?;
+                position
+            }
+            // Offsets are counted in the source, which may not start at 0
+            None => match fp.as_ref(py).call_method0("tell") {
+                Ok(offset) => offset.extract()?,
+                Err(_) => 0,
+            },
+        };
+        Ok(BFastReader {
+            fp,
+            owns_fp,
+            decompress,
+            frames: 0,
+            log,
+            truncated: false,
+            position,
+            limits,
+            pending: VecDeque::new(),
+            peeked: None,
+        })
+    }
+
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __next__(&mut self, py: Python) -> PyResult<Option<PyObject>> {
+        let Some(Record {
+            frame, checksum, ..
+        }) = self.read_record(py)?
+        else {
+            return Ok(None);
+        };
+        if self.log && crc32fast::hash(&frame) != checksum {
+            let followed = !self.at_end(py)?;
+            return match self.checksum_error(followed) {
+                Some(err) => Err(err),
+                None => Ok(None),
+            };
+        }
+
+        self.frames += 1;
+        let options = DecodeOptions::limited(self.limits.clone());
+        decode_bytes(py, &frame, self.decompress, options).map(Some)
+    }
+
+    /// Decode the next `count` documents (all remaining ones when None).
+    /// Checksums and decompression run on the rayon pool without the GIL;
+    /// only building the Python objects is left to the calling thread.
+    #[pyo3(signature = (count = None))]
+    fn read_many(&mut self, py: Python, count: Option<usize>) -> PyResult<Vec<PyObject>> {
+        let mut records = Vec::new();
+        while count.is_none_or(|count| records.len() < count) {
+            match self.read_record(py)? {
+                Some(record) => records.push(record),
+                None => break,
+            }
+        }
+
+        let (log, decompress) = (self.log, self.decompress);
+        let frames: Vec<Result<Cow<[u8]>, RecordError>> = py.allow_threads(|| {
+            records
+                .par_iter()
+                .map(|record| {
+                    let frame = &record.frame;
+                    if log && crc32fast::hash(frame) != record.checksum {
+                        return Err(RecordError::Checksum);
+                    }
+                    match decompress {
+                        true => decompress_packed(frame).map_err(RecordError::Invalid),
+                        false => Ok(Cow::Borrowed(&frame[..])),
+                    }
+                })
+                .collect()
+        });
+
+        let mut documents = Vec::with_capacity(frames.len());
+        // Index of the first record not used up
+        let mut unread = records.len();
+        let mut error = None;
+        for (index, frame) in frames.into_iter().enumerate() {
+            let frame = match frame {
+                Ok(frame) => frame,
+                Err(RecordError::Checksum) => {
+                    let followed = index + 1 < records.len() || !self.at_end(py)?;
+                    error = self.checksum_error(followed);
+                    unread = index + usize::from(error.is_none());
+                    break;
+                }
+                Err(RecordError::Invalid(message)) => {
+                    error = Some(PyErr::new::<pyo3::exceptions::PyValueError, _>(message));
+                    unread = index;
+                    break;
+                }
+            };
+            let options = DecodeOptions::limited(self.limits.clone());
+            match decode_frame(py, &frame, None, options) {
+                Ok(document) => documents.push(document),
+                Err(err) => {
+                    error = Some(err);
+                    unread = index;
+                    break;
+                }
+            }
+            self.frames += 1;
+        }
+        // The documents before a bad record are returned and the record
+        // raises on the next call, as it would from __next__; the records
+        // behind it stay to be read
+        if error.is_some() && documents.is_empty() {
+            unread += 1;
+        }
+        self.unread(records.split_off(unread));
+        match error {
+            Some(err) if documents.is_empty() => Err(err),
+            _ => Ok(documents),
+        }
+    }
+
+    /// Decode the next document, raising EOFError once no complete record is left.
+    fn decode_next(&mut self, py: Python) -> PyResult<PyObject> {
+        self.__next__(py)?.ok_or_else(|| {
+            PyErr::new::<pyo3::exceptions::PyEOFError, _>(format!(
+                "No frame left after frame {}",
+                self.frames
+            ))
+        })
+    }
+
+    /// Byte offset in the source past the records read so far; pass it as
+    /// `position` to resume there.
+    fn position(&self) -> u64 {
+        self.position
+    }
+
+    /// Bytes from the next record to the current end of the source, which
+    /// must be seekable.
+    fn remaining(&self, py: Python) -> PyResult<u64> {
+        let fp = self.fp.as_ref(py);
+        let current = fp.call_method0("tell")?;
+        let end: u64 = fp.call_method1("seek", (0, 2))?.extract()?;
+        fp.call_method1("seek", (current,))?;
+        Ok(end.saturating_sub(self.position))
+    }
+
+    /// True when log-mode iteration stopped at a torn or corrupt record.
+    #[getter]
+    fn truncated(&self) -> bool {
+        self.truncated
+    }
+
+    #[getter]
+    fn frames(&self) -> usize {
+        self.frames
+    }
+
+    fn close(&mut self, py: Python) -> PyResult<()> {
+        if self.owns_fp {
+            self.fp.as_ref(py).call_method0("close")?;
+            self.owns_fp = false;
+        }
+        Ok(())
+    }
+
+    fn __enter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __exit__(
+        &mut self,
+        py: Python,
+        _exc_type: &PyAny,
+        _exc_value: &PyAny,
+        _traceback: &PyAny,
+    ) -> PyResult<bool> {
+        self.close(py)?;
+        Ok(false)
+    }
+}
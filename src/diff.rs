@@ -0,0 +1,358 @@
+// Structural diff and patch for encoded payloads.
+//
+// A patch is itself a B-FAST frame whose payload is a list of operations,
+// each a 3-element list [op, path, value]. Paths are lists of object keys
+// (strings) and list indices (ints). Values are ordinary encoded values, so
+// applying a patch splices bytes into the target frame and re-interns keys in
+// its string table instead of re-encoding the document.
+
+use pyo3::prelude::*;
+use pyo3::types::{PyAny, PyBytes};
+
+use crate::scan::{
+    format_path, parse_frame, unpack, write_int, Frame, PathItem, ScanResult, StringTable,
+};
+use crate::{BFast, TAG_SECTIONS};
+
+/// Replace the value at path (or add a missing object key).
+const OP_SET: i64 = 0;
+/// Remove an object key or list element.
+const OP_DELETE: i64 = 1;
+/// Append the value to the list at path.
+const OP_APPEND: i64 = 2;
+/// Keep only the first `value` elements of the list at path.
+const OP_TRUNCATE: i64 = 3;
+
+struct PatchWriter {
+    table: StringTable,
+    payload: Vec<u8>,
+    ops: u32,
+}
+
+impl PatchWriter {
+    fn op(&mut self, code: i64, path: &[PathItem]) {
+        self.ops += 1;
+        self.payload.push(0x60);
+        self.payload.extend_from_slice(&3u32.to_le_bytes());
+        write_int(&mut self.payload, code);
+        self.payload.push(0x60);
+        self.payload
+            .extend_from_slice(&(path.len() as u32).to_le_bytes());
+        for item in path {
+            match item {
+                PathItem::Key(key) => {
+                    self.payload.push(0x50);
+                    self.payload
+                        .extend_from_slice(&(key.len() as u32).to_le_bytes());
+                    self.payload.extend_from_slice(key.as_bytes());
+                }
+                PathItem::Index(index) => write_int(&mut self.payload, *index as i64),
+            }
+        }
+    }
+
+    fn value(&mut self, frame: &Frame, offset: usize) -> ScanResult<()> {
+        frame.copy_value(offset, &mut self.table, &mut self.payload)?;
+        Ok(())
+    }
+
+    fn finish(self) -> ScanResult<Vec<u8>> {
+        let mut payload = Vec::with_capacity(5 + self.payload.len());
+        payload.push(0x60);
+        payload.extend_from_slice(&self.ops.to_le_bytes());
+        payload.extend_from_slice(&self.payload);
+        self.table.build_frame(&payload, 0, None, false)
+    }
+}
+
+fn diff_value<'a>(
+    old: &Frame<'a>,
+    o: usize,
+    new: &Frame<'a>,
+    n: usize,
+    path: &mut Vec<PathItem<'a>>,
+    out: &mut PatchWriter,
+) -> ScanResult<()> {
+    match (old.tag(o)?, new.tag(n)?) {
+        (0x70, 0x70) => {
+            let old_entries = old.object_entries(o)?;
+            let new_entries = new.object_entries(n)?;
+            for (key, _, _) in &old_entries {
+                if !new_entries.iter().any(|(k, _, _)| k == key) {
+                    path.push(PathItem::Key(key));
+                    out.op(OP_DELETE, path);
+                    out.payload.push(0x10);
+                    path.pop();
+                }
+            }
+            for (key, _, value) in &new_entries {
+                path.push(PathItem::Key(key));
+                match old_entries.iter().rev().find(|(k, _, _)| k == key) {
+                    Some((_, _, old_value)) => diff_value(old, *old_value, new, *value, path, out)?,
+                    None => {
+                        out.op(OP_SET, path);
+                        out.value(new, *value)?;
+                    }
+                }
+                path.pop();
+            }
+        }
+        (0x60, 0x60) => {
+            let old_items = old.list_items(o)?;
+            let new_items = new.list_items(n)?;
+            for (index, (a, b)) in old_items.iter().zip(&new_items).enumerate() {
+                path.push(PathItem::Index(index));
+                diff_value(old, *a, new, *b, path, out)?;
+                path.pop();
+            }
+            for item in new_items.iter().skip(old_items.len()) {
+                out.op(OP_APPEND, path);
+                out.value(new, *item)?;
+            }
+            if new_items.len() < old_items.len() {
+                out.op(OP_TRUNCATE, path);
+                write_int(&mut out.payload, new_items.len() as i64);
+            }
+        }
+        _ => {
+            if !old.value_eq(o, new, n)? {
+                out.op(OP_SET, path);
+                out.value(new, n)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn diff_frames(old: &[u8], new: &[u8]) -> ScanResult<Vec<u8>> {
+    let old = parse_frame(old)?;
+    let new = parse_frame(new)?;
+    let mut out = PatchWriter {
+        table: StringTable::default(),
+        payload: Vec::new(),
+        ops: 0,
+    };
+    diff_value(
+        &old,
+        old.payload,
+        &new,
+        new.payload,
+        &mut Vec::new(),
+        &mut out,
+    )?;
+    out.finish()
+}
+
+/// A splice of the target payload computed for one operation.
+struct Edit<'p> {
+    start: usize,
+    end: usize,
+    /// New element count to store at the given list header offset.
+    count: Option<(usize, u32)>,
+    /// Key to intern and write before the value (object insertions).
+    key: Option<&'p str>,
+    value: bool,
+}
+
+impl Edit<'_> {
+    fn replace(start: usize, end: usize) -> Self {
+        Edit {
+            start,
+            end,
+            count: None,
+            key: None,
+            value: true,
+        }
+    }
+
+    fn remove(start: usize, end: usize) -> Self {
+        Edit {
+            value: false,
+            ..Edit::replace(start, end)
+        }
+    }
+}
+
+fn plan_edit<'p>(
+    target: &Frame,
+    code: i64,
+    path: &[PathItem<'p>],
+    patch: &Frame,
+    value: usize,
+) -> ScanResult<Edit<'p>> {
+    let not_found = || format!("Patch path not found: {}", format_path(path));
+    let mismatch = || {
+        format!(
+            "Patch path does not match the encoded structure: {}",
+            format_path(path)
+        )
+    };
+
+    match code {
+        OP_APPEND | OP_TRUNCATE => {
+            let list = target.resolve(0, path)?.ok_or_else(not_found)?;
+            if target.tag(list)? != 0x60 {
+                return Err(mismatch());
+            }
+            let count = target.u32_at(list + 1)?;
+            let end = target.skip(list)?;
+            if code == OP_APPEND {
+                return Ok(Edit {
+                    count: Some((list + 1, count + 1)),
+                    ..Edit::replace(end, end)
+                });
+            }
+            let keep = patch.int_at(value)?;
+            if keep < 0 {
+                return Err(mismatch());
+            }
+            if keep as usize >= count as usize {
+                return Ok(Edit::remove(end, end));
+            }
+            let start = target.list_items(list)?[keep as usize];
+            Ok(Edit {
+                count: Some((list + 1, keep as u32)),
+                ..Edit::remove(start, end)
+            })
+        }
+        OP_SET | OP_DELETE => {
+            let Some((last, parents)) = path.split_last() else {
+                if code == OP_DELETE {
+                    return Err("Cannot delete the root value".to_string());
+                }
+                return Ok(Edit::replace(0, target.data.len()));
+            };
+            let parent = target.resolve(0, parents)?.ok_or_else(not_found)?;
+            match (target.tag(parent)?, last) {
+                (0x70, PathItem::Key(key)) => {
+                    let entry = target
+                        .object_entries(parent)?
+                        .into_iter()
+                        .rev()
+                        .find(|(k, _, _)| k == key);
+                    match entry {
+                        Some((_, _, v)) if code == OP_SET => Ok(Edit::replace(v, target.skip(v)?)),
+                        Some((_, k, v)) => Ok(Edit::remove(k, target.skip(v)?)),
+                        None if code == OP_SET => {
+                            // New keys go right before the 0x7F terminator
+                            let end = target.skip(parent)? - 1;
+                            Ok(Edit {
+                                key: Some(*key),
+                                ..Edit::replace(end, end)
+                            })
+                        }
+                        None => Err(not_found()),
+                    }
+                }
+                (0x60, PathItem::Index(_)) => {
+                    let item = target.child(parent, last)?.ok_or_else(not_found)?;
+                    let end = target.skip(item)?;
+                    if code == OP_SET {
+                        return Ok(Edit::replace(item, end));
+                    }
+                    let count = target.u32_at(parent + 1)?;
+                    Ok(Edit {
+                        count: Some((parent + 1, count - 1)),
+                        ..Edit::remove(item, end)
+                    })
+                }
+                _ => Err(mismatch()),
+            }
+        }
+        _ => Err(format!("Unknown patch operation: {}", code)),
+    }
+}
+
+fn read_path<'p>(patch: &Frame<'p>, offset: usize) -> ScanResult<Vec<PathItem<'p>>> {
+    if patch.tag(offset)? != 0x60 {
+        return Err("Malformed patch: path must be a list".to_string());
+    }
+    let mut path = Vec::new();
+    for item in patch.list_items(offset)? {
+        if patch.tag(item)? == 0x50 {
+            let length = patch.u32_at(item + 1)? as usize;
+            let bytes = patch
+                .data
+                .get(item + 5..item + 5 + length)
+                .ok_or_else(|| "Unexpected end of buffer during parsing".to_string())?;
+            let key = std::str::from_utf8(bytes)
+                .map_err(|e| format!("Invalid UTF-8 in patch path: {}", e))?;
+            path.push(PathItem::Key(key));
+        } else {
+            let index = patch.int_at(item)?;
+            if index < 0 {
+                return Err(format!("Negative index in patch path: {}", index));
+            }
+            path.push(PathItem::Index(index as usize));
+        }
+    }
+    Ok(path)
+}
+
+fn apply_frames(encoded: &[u8], patch: &[u8]) -> ScanResult<Vec<u8>> {
+    let (data, compressed) = unpack(encoded)?;
+    let target = parse_frame(&data)?;
+    target.require_plain("apply_patch")?;
+    // Edits inside a section would leave the section index stale
+    if target.tag(target.payload)? == TAG_SECTIONS {
+        return Err("apply_patch does not support frames encoded with sections".to_string());
+    }
+    let (patch_data, _) = unpack(patch)?;
+    let patch = parse_frame(&patch_data)?;
+
+    let mut table = StringTable::from_frame(&target);
+    let mut payload = target.data[target.payload..].to_vec();
+
+    if patch.tag(patch.payload)? != 0x60 {
+        return Err("Malformed patch: expected a list of operations".to_string());
+    }
+    for op in patch.list_items(patch.payload)? {
+        if patch.tag(op)? != 0x60 || patch.u32_at(op + 1)? != 3 {
+            return Err("Malformed patch: operations are [op, path, value]".to_string());
+        }
+        let fields = patch.list_items(op)?;
+        let code = patch.int_at(fields[0])?;
+        let path = read_path(&patch, fields[1])?;
+
+        let edit = plan_edit(
+            &Frame::view(&payload, &table.strings),
+            code,
+            &path,
+            &patch,
+            fields[2],
+        )?;
+
+        let mut replacement = Vec::new();
+        if let Some(key) = edit.key {
+            replacement.extend_from_slice(&table.intern(key)?.to_le_bytes());
+        }
+        if edit.value {
+            patch.copy_value(fields[2], &mut table, &mut replacement)?;
+        }
+        if let Some((at, count)) = edit.count {
+            payload[at..at + 4].copy_from_slice(&count.to_le_bytes());
+        }
+        payload.splice(edit.start..edit.end, replacement);
+    }
+
+    table.build_frame(&payload, target.flags, target.metadata, compressed)
+}
+
+/// Compute a binary patch that turns the encoding of `old` into that of `new`.
+#[pyfunction]
+pub fn diff(py: Python, old: &PyAny, new: &PyAny) -> PyResult<PyObject> {
+    let mut encoder = BFast::new();
+    let old_data = encoder.encode_to_vec(old, false)?;
+    let new_data = encoder.encode_to_vec(new, false)?;
+    let patch = diff_frames(&old_data, &new_data)
+        .map_err(PyErr::new::<pyo3::exceptions::PyValueError, _>)?;
+    Ok(PyBytes::new(py, &patch).into())
+}
+
+/// Apply a patch from `diff` to an encoded payload, returning the new encoding.
+#[pyfunction]
+pub fn apply_patch(py: Python, encoded: &[u8], patch: &[u8]) -> PyResult<PyObject> {
+    let updated =
+        apply_frames(encoded, patch).map_err(PyErr::new::<pyo3::exceptions::PyValueError, _>)?;
+    Ok(PyBytes::new(py, &updated).into())
+}
@@ -0,0 +1,24 @@
+// Content hashing over the canonical encoding.
+//
+// Objects are encoded by a fresh encoder in canonical mode (sorted dict keys
+// and set elements, no batch fast path), so equal values always produce the
+// same frame, and key IDs depend only on the content. The frame is hashed
+// with XXH3-128 and dropped.
+
+use pyo3::prelude::*;
+use pyo3::types::{PyAny, PyBytes};
+use xxhash_rust::xxh3::xxh3_128;
+
+use crate::BFast;
+
+/// Stable 128-bit digest of `obj` (16 bytes, big-endian XXH3-128).
+#[pyfunction]
+pub fn hash(py: Python, obj: &PyAny) -> PyResult<PyObject> {
+    let mut encoder = BFast {
+        canonical: true,
+        ..BFast::new()
+    };
+    let frame = encoder.encode_to_vec(obj, false)?;
+    let digest = py.allow_threads(|| xxh3_128(&frame));
+    Ok(PyBytes::new(py, &digest.to_be_bytes()).into())
+}
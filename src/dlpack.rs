@@ -0,0 +1,202 @@
+// DLPack tensor import (PyTorch, TensorFlow, JAX, CuPy, NumPy >= 1.22)
+//
+// Objects exposing __dlpack__ hand us a "dltensor" capsule wrapping a
+// DLManagedTensor. We copy the host buffer out (honouring strides), rename
+// the capsule to "used_dltensor" as the protocol requires and release the
+// producer's memory through its deleter.
+
+use pyo3::ffi;
+use pyo3::prelude::*;
+use pyo3::types::PyCapsule;
+use std::ffi::c_void;
+
+const DL_CPU: i32 = 1;
+
+// DLDataTypeCode values we know how to map to NumPy dtypes
+pub const DL_INT: u8 = 0;
+pub const DL_UINT: u8 = 1;
+pub const DL_FLOAT: u8 = 2;
+pub const DL_COMPLEX: u8 = 5;
+pub const DL_BOOL: u8 = 6;
+
+#[repr(C)]
+struct DLDevice {
+    device_type: i32,
+    device_id: i32,
+}
+
+#[repr(C)]
+struct DLDataType {
+    code: u8,
+    bits: u8,
+    lanes: u16,
+}
+
+#[repr(C)]
+struct DLTensor {
+    data: *mut c_void,
+    device: DLDevice,
+    ndim: i32,
+    dtype: DLDataType,
+    shape: *mut i64,
+    strides: *mut i64,
+    byte_offset: u64,
+}
+
+#[repr(C)]
+struct DLManagedTensor {
+    dl_tensor: DLTensor,
+    manager_ctx: *mut c_void,
+    deleter: Option<unsafe extern "C" fn(*mut DLManagedTensor)>,
+}
+
+/// Host copy of a tensor, laid out C-contiguous.
+pub struct HostTensor {
+    pub code: u8,
+    pub bits: u8,
+    pub shape: Vec<u32>,
+    pub data: Vec<u8>,
+}
+
+#[inline(always)]
+pub fn is_dlpack(val: &PyAny) -> PyResult<bool> {
+    Ok(val.hasattr("__dlpack__")? && val.hasattr("__dlpack_device__")?)
+}
+
+/// NumPy dtype string for a DLPack dtype, `None` when NumPy has no equivalent
+/// (bfloat16, opaque handles, vector lanes).
+pub fn numpy_dtype(code: u8, bits: u8) -> Option<String> {
+    let size = bits / 8;
+    match code {
+        DL_INT if matches!(bits, 8 | 16 | 32 | 64) => Some(format!("<i{}", size)),
+        DL_UINT if matches!(bits, 8 | 16 | 32 | 64) => Some(format!("<u{}", size)),
+        DL_FLOAT if matches!(bits, 16 | 32 | 64) => Some(format!("<f{}", size)),
+        DL_COMPLEX if matches!(bits, 64 | 128) => Some(format!("<c{}", size)),
+        DL_BOOL if bits == 8 => Some("?".to_string()),
+        _ => None,
+    }
+}
+
+pub fn to_host(val: &PyAny) -> PyResult<HostTensor> {
+    let mut obj = val;
+
+    // Device-to-host: let the framework move the tensor before exporting
+    let (device_type, _): (i32, i32) = obj.call_method0("__dlpack_device__")?.extract()?;
+    if device_type != DL_CPU {
+        if obj.hasattr("cpu")? {
+            obj = obj.call_method0("cpu")?;
+        } else {
+            return Err(PyErr::new::<pyo3::exceptions::PyTypeError, _>(format!(
+                "DLPack tensor lives on device type {}; move it to host memory before encoding",
+                device_type
+            )));
+        }
+    }
+
+    let capsule = obj.call_method0("__dlpack__")?.downcast::<PyCapsule>()?;
+    match capsule.name()? {
+        Some(name) if name.to_bytes() == b"dltensor" => {}
+        _ => {
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+                "__dlpack__ did not return an unconsumed 'dltensor' capsule",
+            ))
+        }
+    }
+
+    let managed = capsule.pointer() as *mut DLManagedTensor;
+    if managed.is_null() {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            "DLPack capsule holds a null tensor",
+        ));
+    }
+
+    // Claim ownership so the capsule destructor does not free it as well
+    unsafe {
+        if ffi::PyCapsule_SetName(capsule.as_ptr(), c"used_dltensor".as_ptr()) != 0 {
+            return Err(PyErr::fetch(val.py()));
+        }
+    }
+
+    let result = unsafe { copy_tensor(&(*managed).dl_tensor) };
+
+    unsafe {
+        if let Some(deleter) = (*managed).deleter {
+            deleter(managed);
+        }
+    }
+
+    result
+}
+
+unsafe fn copy_tensor(t: &DLTensor) -> PyResult<HostTensor> {
+    if t.device.device_type != DL_CPU {
+        return Err(PyErr::new::<pyo3::exceptions::PyTypeError, _>(format!(
+            "DLPack tensor exported from device {}:{} is not host-accessible",
+            t.device.device_type, t.device.device_id
+        )));
+    }
+    if t.dtype.lanes != 1 || numpy_dtype(t.dtype.code, t.dtype.bits).is_none() {
+        return Err(PyErr::new::<pyo3::exceptions::PyTypeError, _>(format!(
+            "Unsupported DLPack dtype (code={}, bits={}, lanes={})",
+            t.dtype.code, t.dtype.bits, t.dtype.lanes
+        )));
+    }
+
+    let ndim = t.ndim.max(0) as usize;
+    let shape: Vec<i64> = if ndim == 0 {
+        Vec::new()
+    } else {
+        std::slice::from_raw_parts(t.shape, ndim).to_vec()
+    };
+    let mut dims = Vec::with_capacity(ndim);
+    for &d in &shape {
+        dims.push(u32::try_from(d).map_err(|_| {
+            PyErr::new::<pyo3::exceptions::PyValueError, _>("Tensor dimension out of range")
+        })?);
+    }
+
+    let item_size = (t.dtype.bits / 8) as usize;
+    let count: usize = shape.iter().map(|&d| d as usize).product();
+    let base = (t.data as *const u8).add(t.byte_offset as usize);
+    let mut data = Vec::with_capacity(count * item_size);
+
+    // Compact row-major strides for comparison (in elements)
+    let mut contiguous = vec![1i64; ndim];
+    for i in (0..ndim.saturating_sub(1)).rev() {
+        contiguous[i] = contiguous[i + 1] * shape[i + 1];
+    }
+    let strides: Vec<i64> = if t.strides.is_null() {
+        contiguous.clone()
+    } else {
+        std::slice::from_raw_parts(t.strides, ndim).to_vec()
+    };
+
+    if count == 0 {
+        // nothing to copy
+    } else if strides == contiguous {
+        data.extend_from_slice(std::slice::from_raw_parts(base, count * item_size));
+    } else {
+        // Strided view: walk every index in row-major order
+        let mut index = vec![0i64; ndim];
+        for _ in 0..count {
+            let elem: i64 = index.iter().zip(&strides).map(|(i, s)| i * s).sum();
+            let src = base.offset(elem as isize * item_size as isize);
+            data.extend_from_slice(std::slice::from_raw_parts(src, item_size));
+
+            for axis in (0..ndim).rev() {
+                index[axis] += 1;
+                if index[axis] < shape[axis] {
+                    break;
+                }
+                index[axis] = 0;
+            }
+        }
+    }
+
+    Ok(HostTensor {
+        code: t.dtype.code,
+        bits: t.dtype.bits,
+        shape: dims,
+        data,
+    })
+}
@@ -0,0 +1,514 @@
+// Human-readable listing of an encoded frame.
+//
+// Meant for debugging producers that disagree about the wire format: every
+// value becomes an `(offset, tag, length, preview)` tuple, and a container's
+// tuple is followed by a list holding the tokens of its children. A stream
+// that cannot be followed ends in an "error" token instead of raising, so the
+// listing shows how far the bytes made sense.
+
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList};
+
+use crate::scan::{float_width, header_size, int_width, parse_frame, unpack, Frame, ScanResult};
+use crate::{
+    columnar, spec, temporal, FLAG_SCHEMA, MAX_RECURSION_DEPTH, TAG_BLOB_REF, TAG_BOOL_ARRAY,
+    TAG_COLUMNAR, TAG_CUSTOM, TAG_DATE, TAG_DATETIME, TAG_DATE_DAYS, TAG_DECIMAL, TAG_EXT,
+    TAG_INT_OBJECT, TAG_NULL_BITMAP, TAG_RANGE, TAG_REF, TAG_SECTIONS, TAG_TENSOR, TAG_TIME,
+    TAG_TIMEDELTA, TAG_TIME_NANOS, TAG_TYPED_ARRAY, TAG_UUID,
+};
+
+/// Characters of a string shown before it is cut off.
+const PREVIEW_CHARS: usize = 40;
+
+pub(crate) struct Token {
+    pub offset: usize,
+    pub tag: &'static str,
+    pub length: usize,
+    pub preview: String,
+    /// Object key (or column) the token names.
+    pub key: Option<String>,
+    pub children: Option<Vec<Token>>,
+}
+
+impl Token {
+    fn leaf(offset: usize, tag: &'static str, length: usize, preview: String) -> Self {
+        Token {
+            offset,
+            tag,
+            length,
+            preview,
+            key: None,
+            children: None,
+        }
+    }
+
+    fn named(offset: usize, tag: &'static str, length: usize, preview: String, key: &str) -> Self {
+        Token {
+            key: Some(key.to_string()),
+            ..Token::leaf(offset, tag, length, preview)
+        }
+    }
+}
+
+/// Name of the value type a tag byte introduces.
+pub(crate) fn tag_name(tag: u8) -> &'static str {
+    spec::tag(tag).map_or("unknown", |(name, _)| name)
+}
+
+fn quote(bytes: &[u8]) -> String {
+    let text = String::from_utf8_lossy(bytes);
+    match text.char_indices().nth(PREVIEW_CHARS) {
+        Some((cut, _)) => format!("{:?}...", &text[..cut]),
+        None => format!("{:?}", text),
+    }
+}
+
+fn u16_at(frame: &Frame, offset: usize) -> ScanResult<usize> {
+    frame
+        .data
+        .get(offset..offset + 2)
+        .map(|b| u16::from_le_bytes([b[0], b[1]]) as usize)
+        .ok_or_else(|| "Unexpected end of buffer during parsing".to_string())
+}
+
+struct Walker<'f, 'a> {
+    frame: &'f Frame<'a>,
+}
+
+impl Walker<'_, '_> {
+    /// Append the tokens of the value at `offset` to `out` and return its end,
+    /// or `None` once the stream could not be followed.
+    fn value(&self, offset: usize, depth: usize, out: &mut Vec<Token>) -> Option<usize> {
+        let result = if depth > MAX_RECURSION_DEPTH {
+            Err("Maximum recursion depth exceeded during B-FAST scan".to_string())
+        } else {
+            self.token(offset, depth, out)
+        };
+        result.unwrap_or_else(|message| {
+            out.push(Token::leaf(offset, "error", 0, message));
+            None
+        })
+    }
+
+    /// `Ok(None)` when a child failed and its error token is already written.
+    fn token(
+        &self,
+        offset: usize,
+        depth: usize,
+        out: &mut Vec<Token>,
+    ) -> ScanResult<Option<usize>> {
+        let frame = self.frame;
+        let tag = frame.tag(offset)?;
+        let mut children = Vec::new();
+        let (preview, end) = match tag {
+            0x60 => {
+                let count = frame.u32_at(offset + 1)?;
+                let mut cursor = Some(offset + 5);
+                for _ in 0..count {
+                    cursor = cursor.and_then(|c| self.value(c, depth + 1, &mut children));
+                }
+                (format!("{} items", count), cursor)
+            }
+            0x70 | TAG_NULL_BITMAP => self.object(offset, depth, &mut children)?,
+            TAG_COLUMNAR => self.columns(offset, depth, &mut children)?,
+            TAG_INT_OBJECT => {
+                let count = frame.u32_at(offset + 1)?;
+                let mut cursor = Some(offset + 5);
+                for _ in 0..count {
+                    cursor = match cursor {
+                        Some(c) => {
+                            let value = frame.skip(c)?;
+                            let key = frame.int_at(c)?.to_string();
+                            children.push(Token::named(c, "key", value - c, key.clone(), &key));
+                            self.value(value, depth + 1, &mut children)
+                        }
+                        None => None,
+                    };
+                }
+                (format!("{} entries", count), cursor)
+            }
+            TAG_SECTIONS => {
+                let sections = frame.sections(offset)?;
+                children.push(Token::leaf(
+                    offset + 5,
+                    "index",
+                    sections.len() * 8,
+                    format!("{} entries", sections.len()),
+                ));
+                let mut cursor = Some(offset + 5 + sections.len() * 8);
+                for (key, value) in &sections {
+                    // Like bitmap fields, sections are named at their value
+                    children.push(Token::named(
+                        *value,
+                        "section",
+                        0,
+                        format!("{:?}", key),
+                        key,
+                    ));
+                    cursor = cursor.and_then(|_| self.value(*value, depth + 1, &mut children));
+                }
+                (format!("{} sections", sections.len()), cursor)
+            }
+            TAG_CUSTOM => {
+                let value = offset + 5 + frame.u32_at(offset + 1)? as usize;
+                let name = frame
+                    .data
+                    .get(offset + 5..value)
+                    .ok_or("Unexpected end of buffer during parsing")?;
+                let end = self.value(value, depth + 1, &mut children);
+                (quote(name), end)
+            }
+            _ => {
+                let end = frame.skip(offset)?;
+                out.push(Token::leaf(
+                    offset,
+                    tag_name(tag),
+                    end - offset,
+                    self.preview(offset, end)?,
+                ));
+                return Ok(Some(end));
+            }
+        };
+        out.push(Token {
+            offset,
+            tag: tag_name(tag),
+            length: end.map_or(0, |end| end - offset),
+            preview,
+            key: None,
+            children: Some(children),
+        });
+        Ok(end)
+    }
+
+    fn object(
+        &self,
+        offset: usize,
+        depth: usize,
+        children: &mut Vec<Token>,
+    ) -> ScanResult<(String, Option<usize>)> {
+        let frame = self.frame;
+        let mut fields = 0;
+        let mut cursor = offset + 1;
+        if frame.tag(offset)? == TAG_NULL_BITMAP {
+            let first = frame.u32_at(offset + 1)?;
+            let count = u16_at(frame, offset + 5)?;
+            let bitmap = frame
+                .data
+                .get(offset + 7..offset + 7 + count.div_ceil(8))
+                .ok_or("Unexpected end of buffer during parsing")?;
+            let present = (0..count)
+                .filter(|i| bitmap[i / 8] & (1 << (i % 8)) != 0)
+                .collect::<Vec<_>>();
+            children.push(Token::leaf(
+                offset + 7,
+                "bitmap",
+                bitmap.len(),
+                format!("{} present, {} null", present.len(), count - present.len()),
+            ));
+            fields += count;
+            cursor = offset + 7 + bitmap.len();
+            for i in present {
+                // Bitmap fields have no key bytes; name them at their value
+                let key = frame.key(first + i as u32)?;
+                children.push(Token::named(cursor, "field", 0, format!("{:?}", key), key));
+                match self.value(cursor, depth + 1, children) {
+                    Some(end) => cursor = end,
+                    None => return Ok((format!("{} fields", fields), None)),
+                }
+            }
+        }
+        while frame.tag(cursor)? != 0x7F {
+            let id = frame.u32_at(cursor)?;
+            let key = frame.key(id)?;
+            children.push(Token::named(
+                cursor,
+                "key",
+                4,
+                format!("#{} {:?}", id, key),
+                key,
+            ));
+            fields += 1;
+            match self.value(cursor + 4, depth + 1, children) {
+                Some(end) => cursor = end,
+                None => return Ok((format!("{} fields", fields), None)),
+            }
+        }
+        Ok((format!("{} fields", fields), Some(cursor + 1)))
+    }
+
+    fn columns(
+        &self,
+        offset: usize,
+        depth: usize,
+        children: &mut Vec<Token>,
+    ) -> ScanResult<(String, Option<usize>)> {
+        let frame = self.frame;
+        let rows = frame.u32_at(offset + 1)? as usize;
+        let columns = u16_at(frame, offset + 5)?;
+        let preview = format!("{} rows, {} columns", rows, columns);
+        let mut cursor = offset + 7;
+        for _ in 0..columns {
+            let start = cursor;
+            let key = frame.key(frame.u32_at(cursor)?)?;
+            let encoding = frame.tag(cursor + 4)?;
+            cursor += 5;
+            let mut values = Vec::new();
+            let (name, end) = match encoding {
+                columnar::COL_PLAIN => {
+                    let mut end = Some(cursor);
+                    for _ in 0..rows {
+                        end = end.and_then(|c| self.value(c, depth + 1, &mut values));
+                    }
+                    ("plain", end)
+                }
+                columnar::COL_RLE => {
+                    let runs = frame.u32_at(cursor)?;
+                    let mut end = Some(cursor + 4);
+                    for _ in 0..runs {
+                        end = end.and_then(|c| {
+                            let length = frame.u32_at(c).ok()?;
+                            values.push(Token::leaf(c, "run", 4, format!("x{}", length)));
+                            self.value(c + 4, depth + 1, &mut values)
+                        });
+                    }
+                    ("rle", end)
+                }
+                columnar::COL_DICT => {
+                    let entries = frame.u32_at(cursor)?;
+                    let width = frame.tag(cursor + 4)? as usize;
+                    let mut end = Some(cursor + 5);
+                    for _ in 0..entries {
+                        end = end.and_then(|c| self.value(c, depth + 1, &mut values));
+                    }
+                    let end = end.map(|codes| {
+                        let length = rows * width;
+                        values.push(Token::leaf(
+                            codes,
+                            "codes",
+                            length,
+                            format!("{} x u{}", rows, width * 8),
+                        ));
+                        codes + length
+                    });
+                    ("dict", end)
+                }
+                columnar::COL_BOOL => {
+                    let length = rows.div_ceil(8);
+                    values.push(Token::leaf(
+                        cursor,
+                        "bits",
+                        length,
+                        format!("{} bools", rows),
+                    ));
+                    ("bool", Some(cursor + length))
+                }
+                columnar::COL_DELTA | columnar::COL_XOR => {
+                    let length = 4 + frame.u32_at(cursor)? as usize;
+                    let (name, kind) = if encoding == columnar::COL_DELTA {
+                        ("delta", "ints")
+                    } else {
+                        ("xor", "floats")
+                    };
+                    values.push(Token::leaf(
+                        cursor,
+                        "stream",
+                        length,
+                        format!("{} {}", rows, kind),
+                    ));
+                    (name, Some(cursor + length))
+                }
+                e => return Err(format!("Unknown column encoding: {}", e)),
+            };
+            if end.is_some_and(|end| end > frame.data.len()) {
+                return Err("Unexpected end of buffer during parsing".to_string());
+            }
+            children.push(Token {
+                offset: start,
+                tag: "column",
+                length: end.map_or(0, |end| end - start),
+                preview: format!("{:?} ({})", key, name),
+                key: Some(key.to_string()),
+                children: Some(values),
+            });
+            match end {
+                Some(end) => cursor = end,
+                None => return Ok((preview, None)),
+            }
+        }
+        Ok((preview, Some(cursor)))
+    }
+
+    fn preview(&self, offset: usize, end: usize) -> ScanResult<String> {
+        let frame = self.frame;
+        let body = &frame.data[offset + 1..end];
+        Ok(match frame.tag(offset)? {
+            0x10 => "None".to_string(),
+            0x20 => "False".to_string(),
+            0x21 => "True".to_string(),
+            t if float_width(t).is_some() => format!("{:?}", frame.float_at(offset)?),
+            t if int_width(t).is_some() => frame.int_at(offset)?.to_string(),
+            TAG_DATETIME if body[..4] == temporal::DATETIME_NANOS.to_le_bytes() => {
+                let nanos = i64::from_le_bytes(body[4..12].try_into().unwrap());
+                let offset = i32::from_le_bytes(body[12..16].try_into().unwrap());
+                format!("{:?}", temporal::iso_from_nanos(nanos, offset))
+            }
+            0x50 | TAG_DATETIME | TAG_DATE | TAG_TIME | TAG_DECIMAL | TAG_BLOB_REF => {
+                quote(&body[4..])
+            }
+            TAG_UUID => match std::str::from_utf8(&body[4..]) {
+                Ok(text) => format!("{:?}", text),
+                Err(_) => body[4..].iter().map(|b| format!("{:02x}", b)).collect(),
+            },
+            TAG_TIMEDELTA => format!("{} ns", i64::from_le_bytes(body.try_into().unwrap())),
+            TAG_DATE_DAYS => format!(
+                "{:?}",
+                temporal::date_iso(i32::from_le_bytes(body.try_into().unwrap()) as i64)
+            ),
+            TAG_TIME_NANOS => format!(
+                "{:?}",
+                temporal::time_iso(u64::from_le_bytes(body.try_into().unwrap()))
+            ),
+            0x80 => format!("{} bytes", body.len() - 4),
+            0x90 => format!("{} floats", frame.u32_at(offset + 1)?),
+            TAG_BOOL_ARRAY => format!("{} bools", frame.u32_at(offset + 1)?),
+            TAG_TYPED_ARRAY => format!("{} bytes of {:?}", body.len() - 5, body[0] as char),
+            TAG_EXT => format!("type {}, {} bytes", body[0], body.len() - 5),
+            TAG_REF => format!("-> #{}", frame.u32_at(offset + 1)?),
+            TAG_RANGE => {
+                let start = frame.int_at(offset + 1)?;
+                let stop_at = frame.skip(offset + 1)?;
+                let stop = frame.int_at(stop_at)?;
+                let step = frame.int_at(frame.skip(stop_at)?)?;
+                format!("range({}, {}, {})", start, stop, step)
+            }
+            TAG_TENSOR => {
+                let shape = body[3..3 + body[2] as usize * 4]
+                    .chunks(4)
+                    .map(|d| u32::from_le_bytes(d.try_into().unwrap()).to_string())
+                    .collect::<Vec<_>>();
+                format!(
+                    "dtype {}/{}, shape ({})",
+                    body[0],
+                    body[1],
+                    shape.join(", ")
+                )
+            }
+            _ => String::new(),
+        })
+    }
+}
+
+pub(crate) struct Listing {
+    pub compressed: bool,
+    pub flags: u8,
+    pub version: u8,
+    /// Bytes after the header, from version 2 on
+    pub length: Option<u32>,
+    pub schema_id: Option<u32>,
+    pub metadata_size: Option<usize>,
+    pub strings: Vec<(Option<usize>, String)>,
+    pub tokens: Vec<Token>,
+}
+
+pub(crate) fn list_frame(data: &[u8]) -> ScanResult<Listing> {
+    let (data, compressed) = unpack(data)?;
+    let frame = parse_frame(&data)?;
+
+    // Schema keys come first and have no bytes in the frame
+    let mut strings = Vec::with_capacity(frame.strings.len());
+    let mut schema_id = None;
+    let mut offset = header_size(&data)?;
+    let length = match offset {
+        6 => None,
+        _ => Some(frame.u32_at(6)?),
+    };
+    if frame.flags & FLAG_SCHEMA != 0 {
+        schema_id = Some(frame.u32_at(offset)?);
+        offset += 4;
+        let table = u16_at(&frame, 4)?;
+        let schema_fields = frame.strings.len() - table;
+        strings.extend(
+            frame.strings[..schema_fields]
+                .iter()
+                .map(|s| (None, s.to_string())),
+        );
+    }
+    if let Some(metadata) = frame.metadata {
+        offset += 4 + metadata.len() + frame.stats.map_or(0, |stats| stats.len());
+    }
+    for s in &frame.strings[strings.len()..] {
+        strings.push((Some(offset), s.to_string()));
+        offset += 1 + s.len();
+    }
+
+    let mut tokens = Vec::new();
+    if let Some(fingerprint) = frame.fingerprint {
+        tokens.push(Token::leaf(
+            frame.payload - 9,
+            "fingerprint",
+            9,
+            format!("{:#018x}", fingerprint),
+        ));
+    }
+    let walker = Walker { frame: &frame };
+    if let Some(end) = walker.value(frame.payload, 0, &mut tokens) {
+        if end < data.len() {
+            tokens.push(Token::leaf(
+                end,
+                "trailing",
+                data.len() - end,
+                format!("{} bytes", data.len() - end),
+            ));
+        }
+    }
+    Ok(Listing {
+        compressed,
+        flags: frame.flags,
+        version: data[3],
+        length,
+        schema_id,
+        metadata_size: frame.metadata.map(|metadata| metadata.len()),
+        strings,
+        tokens,
+    })
+}
+
+fn tokens_to_py<'py>(py: Python<'py>, tokens: Vec<Token>) -> PyResult<&'py PyList> {
+    let list = PyList::empty(py);
+    for token in tokens {
+        list.append((token.offset, token.tag, token.length, token.preview))?;
+        if let Some(children) = token.children {
+            list.append(tokens_to_py(py, children)?)?;
+        }
+    }
+    Ok(list)
+}
+
+/// Listing of an encoded frame: header fields, string table entries and the
+/// token tree of its payload. Offsets refer to the decompressed frame.
+#[pyfunction]
+pub fn dump_tokens(py: Python, data: &[u8]) -> PyResult<PyObject> {
+    let listing = py
+        .allow_threads(|| list_frame(data))
+        .map_err(PyErr::new::<pyo3::exceptions::PyValueError, _>)?;
+
+    let header = PyDict::new(py);
+    header.set_item("version", listing.version)?;
+    header.set_item("length", listing.length)?;
+    header.set_item("compressed", listing.compressed)?;
+    let flags = spec::FLAGS
+        .iter()
+        .filter(|(flag, _)| listing.flags & flag != 0)
+        .map(|(_, name)| *name)
+        .collect::<Vec<_>>();
+    header.set_item("flags", flags)?;
+    header.set_item("schema_id", listing.schema_id)?;
+    header.set_item("metadata_size", listing.metadata_size)?;
+    header.set_item("string_count", listing.strings.len())?;
+
+    let result = PyDict::new(py);
+    result.set_item("header", header)?;
+    result.set_item("strings", listing.strings)?;
+    result.set_item("tokens", tokens_to_py(py, listing.tokens)?)?;
+    Ok(result.into())
+}
@@ -0,0 +1,81 @@
+// What to do with dict keys that occur twice.
+//
+// Python dicts cannot hold the same key twice, but an encoded object can: keys
+// that differ as objects may write the same text (1 and "1", or two keys an
+// on_key hook maps to one name), and a crafted frame may repeat a key ID. By
+// default the encoder writes such keys as they come and the decoder keeps the
+// last value. With a policy, both directions resolve them the same way:
+//
+//   "error"  raise ValueError
+//   "first"  keep the first value
+//   "last"   keep the last value, at the first key's position (like dict
+//            updates)
+//
+// Encoders with a policy collect a dict's key strings before writing it, so
+// the written object never repeats a key.
+
+use ahash::AHashMap;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+use crate::BFastParser;
+
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) enum DuplicateKeys {
+    Error,
+    First,
+    Last,
+}
+
+/// The policy called `name`; None keeps the unchecked behaviour.
+pub(crate) fn policy(name: Option<&str>) -> PyResult<Option<DuplicateKeys>> {
+    match name {
+        None => Ok(None),
+        Some("error") => Ok(Some(DuplicateKeys::Error)),
+        Some("first") => Ok(Some(DuplicateKeys::First)),
+        Some("last") => Ok(Some(DuplicateKeys::Last)),
+        Some(name) => Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+            "Unknown duplicate_keys policy {:?}; expected error, first or last",
+            name
+        ))),
+    }
+}
+
+fn duplicate_error(key: impl std::fmt::Display) -> PyErr {
+    PyErr::new::<pyo3::exceptions::PyValueError, _>(format!("Duplicate key {}", key))
+}
+
+/// `entries` with repeated keys resolved under `policy`.
+pub(crate) fn resolve<V>(
+    entries: Vec<(String, V)>,
+    policy: DuplicateKeys,
+) -> PyResult<Vec<(String, V)>> {
+    let mut positions: AHashMap<String, usize> = AHashMap::with_capacity(entries.len());
+    let mut resolved: Vec<(String, V)> = Vec::with_capacity(entries.len());
+    for (key, value) in entries {
+        match positions.get(&key) {
+            None => {
+                positions.insert(key.clone(), resolved.len());
+                resolved.push((key, value));
+            }
+            Some(&position) => match policy {
+                DuplicateKeys::Error => return Err(duplicate_error(format!("'{}'", key))),
+                DuplicateKeys::First => {}
+                DuplicateKeys::Last => resolved[position].1 = value,
+            },
+        }
+    }
+    Ok(resolved)
+}
+
+impl<'a, 'py> BFastParser<'a, 'py> {
+    /// Add `key: value` to the dict being decoded, under the duplicate key
+    /// policy.
+    pub(crate) fn insert(&self, dict: &PyDict, key: &PyAny, value: PyObject) -> PyResult<()> {
+        match self.duplicate_keys {
+            Some(DuplicateKeys::Error) if dict.contains(key)? => Err(duplicate_error(key.repr()?)),
+            Some(DuplicateKeys::First) if dict.contains(key)? => Ok(()),
+            _ => dict.set_item(key, value),
+        }
+    }
+}
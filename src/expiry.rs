@@ -0,0 +1,105 @@
+// Expiry times for cached frames.
+//
+// A frame expires at the `expires_at` field of its metadata dict: epoch
+// seconds (int or float) or a datetime, naive ones taken as UTC. Encoding
+// with `ttl=` sets it to that many seconds from now. `b_fast.is_expired`
+// reads only the metadata section, so cache middlewares can evict stale
+// payloads without decoding them.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+use crate::scan::{parse_frame, unpack};
+use crate::{decode_frame, DecodeOptions};
+
+const EXPIRES_AT: &str = "expires_at";
+
+fn value_error(message: String) -> PyErr {
+    PyErr::new::<pyo3::exceptions::PyValueError, _>(message)
+}
+
+fn epoch_now() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0.0, |now| now.as_secs_f64())
+}
+
+/// `metadata` with `expires_at` set `ttl` seconds from now, when given.
+pub(crate) fn with_ttl<'py>(
+    py: Python<'py>,
+    metadata: Option<&'py PyDict>,
+    ttl: Option<f64>,
+) -> PyResult<Option<&'py PyDict>> {
+    let Some(ttl) = ttl else {
+        return Ok(metadata);
+    };
+    if !(ttl.is_finite() && ttl >= 0.0) {
+        return Err(value_error(format!(
+            "ttl must be a non-negative number of seconds, got {}",
+            ttl
+        )));
+    }
+    let metadata = match metadata {
+        Some(metadata) => metadata.copy()?,
+        None => PyDict::new(py),
+    };
+    metadata.set_item(EXPIRES_AT, epoch_now() + ttl)?;
+    Ok(Some(metadata))
+}
+
+/// Epoch seconds of `value`, the `expires_at` field or `now` (`name`).
+fn epoch_seconds(name: &str, value: &PyAny) -> PyResult<f64> {
+    if value.get_type().name()? == "datetime" {
+        let value = match value.getattr("tzinfo")?.is_none() {
+            true => {
+                let utc = value
+                    .py()
+                    .import("datetime")?
+                    .getattr("timezone")?
+                    .getattr("utc")?;
+                let kwargs = PyDict::new(value.py());
+                kwargs.set_item("tzinfo", utc)?;
+                value.call_method("replace", (), Some(kwargs))?
+            }
+            false => value,
+        };
+        return value.call_method0("timestamp")?.extract();
+    }
+    value.extract::<f64>().map_err(|_| {
+        PyErr::new::<pyo3::exceptions::PyTypeError, _>(format!(
+            "{} must be epoch seconds or a datetime, not {}",
+            name,
+            value.get_type().name().unwrap_or("?")
+        ))
+    })
+}
+
+/// Whether the frame's `expires_at` is at or before `now` (epoch seconds or
+/// a datetime; the current time by default). Frames without one never
+/// expire. The payload is not decoded.
+#[pyfunction]
+#[pyo3(signature = (data, now = None))]
+pub fn is_expired(py: Python, data: &[u8], now: Option<&PyAny>) -> PyResult<bool> {
+    let (frame_data, _) = unpack(data).map_err(value_error)?;
+    let frame = parse_frame(&frame_data).map_err(value_error)?;
+    let Some(metadata) = frame.metadata else {
+        return Ok(false);
+    };
+    let metadata = decode_frame(py, metadata, None, DecodeOptions::default())?;
+    let Ok(metadata) = metadata.downcast::<PyDict>(py) else {
+        return Ok(false);
+    };
+    let Some(expires_at) = metadata.get_item(EXPIRES_AT)? else {
+        return Ok(false);
+    };
+    if expires_at.is_none() {
+        return Ok(false);
+    }
+    let now = match now {
+        Some(now) => epoch_seconds("now", now)?,
+        None => epoch_now(),
+    };
+    Ok(now >= epoch_seconds(EXPIRES_AT, expires_at)?)
+}
@@ -0,0 +1,231 @@
+// Size attribution for encoded payloads.
+//
+// Built on the token listing of dump.rs: every byte of the frame is charged
+// to one type category (strings, numbers, keys, structural overhead, ...),
+// and the bytes of each top-level field (summed over the records of a root
+// list) are added up, so users can see what makes a payload big.
+
+use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyDict};
+
+use crate::dump::{list_frame, Token};
+use crate::scan::ScanResult;
+use crate::BFast;
+
+/// Category of the bytes of a leaf token.
+fn category(tag: &str) -> &'static str {
+    match tag {
+        "str" => "strings",
+        "int" | "int64" | "float" | "range" => "numbers",
+        "float_array" | "typed_array" | "tensor" | "stream" => "numbers",
+        "bool" | "bool_array" | "bits" => "booleans",
+        "null" => "nulls",
+        "bytes" => "bytes",
+        "date" | "time" | "datetime" | "timedelta" => "temporal",
+        "uuid" | "decimal" => "other",
+        "key" => "keys",
+        // Container headers, run lengths, bitmaps, back-references
+        _ => "overhead",
+    }
+}
+
+/// Add `bytes` to the entry `name`, keeping first-seen order.
+fn charge(totals: &mut Vec<(String, usize)>, name: &str, bytes: usize) {
+    match totals.iter_mut().find(|(n, _)| n == name) {
+        Some((_, total)) => *total += bytes,
+        None => totals.push((name.to_string(), bytes)),
+    }
+}
+
+fn first_error(tokens: &[Token]) -> Option<&str> {
+    tokens.iter().find_map(|token| match &token.children {
+        _ if token.tag == "error" => Some(token.preview.as_str()),
+        Some(children) => first_error(children),
+        None => None,
+    })
+}
+
+fn by_category(tokens: &[Token], totals: &mut Vec<(String, usize)>) {
+    for token in tokens {
+        match &token.children {
+            Some(children) => {
+                let inner: usize = children.iter().map(|child| child.length).sum();
+                charge(totals, "overhead", token.length - inner);
+                by_category(children, totals);
+            }
+            None => charge(totals, category(token.tag), token.length),
+        }
+    }
+}
+
+/// Charge the entries of a record (key ID and value) to their key.
+fn record_fields(entries: &[Token], totals: &mut Vec<(String, usize)>) {
+    let mut current = None;
+    for token in entries {
+        if token.key.is_some() {
+            current = token.key.as_deref();
+        }
+        if let Some(key) = current {
+            charge(totals, key, token.length);
+        }
+    }
+}
+
+fn by_field(root: &Token, totals: &mut Vec<(String, usize)>) {
+    let Some(children) = &root.children else {
+        return;
+    };
+    match root.tag {
+        "object" | "bitmap_object" => record_fields(children, totals),
+        "list" => {
+            for item in children {
+                if let ("object" | "bitmap_object", Some(entries)) = (item.tag, &item.children) {
+                    record_fields(entries, totals);
+                }
+            }
+        }
+        "columnar" => {
+            for column in children {
+                if let Some(key) = &column.key {
+                    charge(totals, key, column.length);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+fn sorted(mut totals: Vec<(String, usize)>) -> Vec<(String, usize)> {
+    totals.sort_by_key(|&(_, bytes)| std::cmp::Reverse(bytes));
+    totals
+}
+
+fn report(data: &[u8]) -> ScanResult<SizeReport> {
+    let listing = list_frame(data)?;
+    if let Some(message) = first_error(&listing.tokens) {
+        return Err(message.to_string());
+    }
+    let payload = listing.tokens.first().map_or(0, |root| root.offset);
+    let table: usize = listing
+        .strings
+        .iter()
+        .filter(|(offset, _)| offset.is_some())
+        .map(|(_, s)| 1 + s.len())
+        .sum();
+
+    let mut categories = Vec::new();
+    charge(&mut categories, "overhead", payload - table);
+    charge(&mut categories, "keys", table);
+    by_category(&listing.tokens, &mut categories);
+    categories.retain(|(_, bytes)| *bytes > 0);
+
+    let mut fields = Vec::new();
+    if let Some(root) = listing.tokens.first() {
+        by_field(root, &mut fields);
+    }
+
+    let total = categories.iter().map(|(_, bytes)| bytes).sum();
+    Ok(SizeReport {
+        total,
+        compressed: listing.compressed.then_some(data.len()),
+        fields: sorted(fields),
+        categories: sorted(categories),
+    })
+}
+
+fn table(title: &str, rows: &[(String, usize)], total: usize) -> String {
+    let width = rows
+        .iter()
+        .map(|(name, _)| name.chars().count())
+        .chain([title.len()])
+        .max()
+        .unwrap_or(0);
+    let mut out = format!("{:<width$}  {:>10}  {:>6}\n", title, "bytes", "share");
+    for (name, bytes) in rows {
+        let share = *bytes as f64 * 100.0 / total.max(1) as f64;
+        out.push_str(&format!(
+            "{:<width$}  {:>10}  {:>5.1}%\n",
+            name, bytes, share
+        ));
+    }
+    out
+}
+
+/// Bytes of an encoded payload by top-level field and by type category.
+#[allow(non_local_definitions)]
+#[pyclass]
+pub struct SizeReport {
+    /// Size of the uncompressed frame.
+    #[pyo3(get)]
+    total: usize,
+    /// Size of the payload as given, if it was compressed.
+    #[pyo3(get)]
+    compressed: Option<usize>,
+    fields: Vec<(String, usize)>,
+    categories: Vec<(String, usize)>,
+}
+
+#[allow(non_local_definitions)]
+#[pymethods]
+impl SizeReport {
+    /// Bytes per top-level field (summed over the records of a root list),
+    /// largest first.
+    #[getter]
+    fn fields(&self, py: Python) -> PyResult<PyObject> {
+        let fields = PyDict::new(py);
+        for (name, bytes) in &self.fields {
+            fields.set_item(name, bytes)?;
+        }
+        Ok(fields.into())
+    }
+
+    /// Bytes per type category, largest first.
+    #[getter]
+    fn categories(&self, py: Python) -> PyResult<PyObject> {
+        let categories = PyDict::new(py);
+        for (name, bytes) in &self.categories {
+            categories.set_item(name, bytes)?;
+        }
+        Ok(categories.into())
+    }
+
+    fn __str__(&self) -> String {
+        let mut out = format!("{} bytes", self.total);
+        if let Some(compressed) = self.compressed {
+            out.push_str(&format!(" ({} compressed)", compressed));
+        }
+        out.push_str("\n\n");
+        if !self.fields.is_empty() {
+            out.push_str(&table("field", &self.fields, self.total));
+            out.push('\n');
+        }
+        out.push_str(&table("type", &self.categories, self.total));
+        out.trim_end().to_string()
+    }
+
+    fn __repr__(&self) -> String {
+        match self.compressed {
+            Some(compressed) => format!(
+                "SizeReport(total={}, compressed={})",
+                self.total, compressed
+            ),
+            None => format!("SizeReport(total={})", self.total),
+        }
+    }
+}
+
+/// Attribute the bytes of an encoded payload, or of `obj` encoded with
+/// default options, to its top-level fields and to type categories.
+#[pyfunction]
+pub fn explain(py: Python, obj: &PyAny) -> PyResult<SizeReport> {
+    let encoded;
+    let data = match obj.downcast::<PyBytes>() {
+        Ok(bytes) => bytes.as_bytes(),
+        Err(_) => {
+            encoded = BFast::new().encode_to_vec(obj, false)?;
+            &encoded
+        }
+    };
+    py.allow_threads(|| report(data))
+        .map_err(PyErr::new::<pyo3::exceptions::PyValueError, _>)
+}
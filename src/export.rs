@@ -0,0 +1,264 @@
+// Parquet export of encoded lists of records (the `parquet` feature).
+//
+// The records are read straight from the tag stream: every field becomes an
+// optional column, typed from its non-null values (ints and floats together
+// widen to DOUBLE), and records without a field get a null there. Nested
+// values have no column type and are rejected.
+
+use std::fs::{self, File};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use ahash::AHashMap;
+use parquet::basic::{Compression, ConvertedType, LogicalType, Repetition, Type as PhysicalType};
+use parquet::data_type::{BoolType, ByteArray, ByteArrayType, DoubleType, Int32Type, Int64Type};
+use parquet::file::properties::WriterProperties;
+use parquet::file::writer::SerializedFileWriter;
+use parquet::schema::types::Type;
+use pyo3::prelude::*;
+
+use crate::dump::tag_name;
+use crate::scan::{float_width, int_width, parse_frame, unpack, Frame, ScanResult};
+
+#[derive(Clone, Copy)]
+enum Scalar<'a> {
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    Str(&'a [u8]),
+}
+
+struct Column<'a> {
+    name: &'a str,
+    values: Vec<Option<Scalar<'a>>>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Kind {
+    Null,
+    Int,
+    Float,
+    Bool,
+    Str,
+}
+
+impl Kind {
+    fn name(self) -> &'static str {
+        match self {
+            Kind::Null => "null",
+            Kind::Int => "int",
+            Kind::Float => "float",
+            Kind::Bool => "bool",
+            Kind::Str => "str",
+        }
+    }
+}
+
+impl Column<'_> {
+    /// Type shared by the non-null values.
+    fn kind(&self) -> ScanResult<Kind> {
+        let mut kind = Kind::Null;
+        for value in self.values.iter().flatten() {
+            let next = match value {
+                Scalar::Int(_) => Kind::Int,
+                Scalar::Float(_) => Kind::Float,
+                Scalar::Bool(_) => Kind::Bool,
+                Scalar::Str(_) => Kind::Str,
+            };
+            kind = match (kind, next) {
+                (Kind::Null, next) => next,
+                (Kind::Int, Kind::Float) | (Kind::Float, Kind::Int) => Kind::Float,
+                (kind, next) if kind == next => kind,
+                (kind, next) => {
+                    return Err(format!(
+                        "Field {:?} mixes {} and {} values",
+                        self.name,
+                        kind.name(),
+                        next.name()
+                    ))
+                }
+            };
+        }
+        Ok(kind)
+    }
+
+    /// Definition levels: 1 for a value, 0 for a null.
+    fn levels(&self) -> Vec<i16> {
+        self.values.iter().map(|v| v.is_some() as i16).collect()
+    }
+}
+
+fn scalar_at<'a>(frame: &Frame<'a>, offset: usize) -> ScanResult<Option<Scalar<'a>>> {
+    let tag = frame.tag(offset)?;
+    Ok(Some(match tag {
+        0x10 => return Ok(None),
+        0x20 | 0x21 => Scalar::Bool(tag == 0x21),
+        t if float_width(t).is_some() => Scalar::Float(frame.float_at(offset)?),
+        0x50 => Scalar::Str(
+            frame
+                .data
+                .get(offset + 5..frame.skip(offset)?)
+                .ok_or("Unexpected end of buffer during parsing")?,
+        ),
+        t if int_width(t).is_some() => Scalar::Int(frame.int_at(offset)?),
+        _ => return Err(tag_name(tag).to_string()),
+    }))
+}
+
+/// The fields of every record of the list payload, as columns.
+fn columns<'a>(frame: &Frame<'a>) -> ScanResult<(usize, Vec<Column<'a>>)> {
+    frame.require_plain("to_parquet")?;
+    if frame.tag(frame.payload)? != 0x60 {
+        return Err("to_parquet expects a payload encoded from a list of records".to_string());
+    }
+    let records = frame.list_items(frame.payload)?;
+    let mut columns: Vec<Column> = Vec::new();
+    let mut by_name: AHashMap<&str, usize> = AHashMap::new();
+    for (row, &record) in records.iter().enumerate() {
+        if frame.tag(record)? != 0x70 {
+            return Err(format!(
+                "Element {} is a {}, not a record",
+                row,
+                tag_name(frame.tag(record)?)
+            ));
+        }
+        for (name, _, value) in frame.object_entries(record)? {
+            let value = scalar_at(frame, value).map_err(|tag| {
+                format!(
+                    "Field {:?} of element {} is a {}, not a scalar",
+                    name, row, tag
+                )
+            })?;
+            let index = *by_name.entry(name).or_insert_with(|| {
+                columns.push(Column {
+                    name,
+                    values: Vec::with_capacity(records.len()),
+                });
+                columns.len() - 1
+            });
+            // Pads the rows without this field; a repeated key drops the
+            // earlier value, as decoding does
+            let values = &mut columns[index].values;
+            values.resize(row, None);
+            values.push(value);
+        }
+    }
+    for column in &mut columns {
+        column.values.resize(records.len(), None);
+    }
+    Ok((records.len(), columns))
+}
+
+fn parquet_error(error: parquet::errors::ParquetError) -> String {
+    format!("Parquet write failed: {}", error)
+}
+
+fn write_parquet(data: &[u8], file: File) -> ScanResult<usize> {
+    let (frame_data, _) = unpack(data)?;
+    let frame = parse_frame(&frame_data)?;
+    let (rows, columns) = columns(&frame)?;
+    let kinds = columns
+        .iter()
+        .map(Column::kind)
+        .collect::<ScanResult<Vec<_>>>()?;
+
+    let mut fields = Vec::with_capacity(columns.len());
+    for (column, kind) in columns.iter().zip(&kinds) {
+        let physical = match kind {
+            Kind::Null => PhysicalType::INT32,
+            Kind::Int => PhysicalType::INT64,
+            Kind::Float => PhysicalType::DOUBLE,
+            Kind::Bool => PhysicalType::BOOLEAN,
+            Kind::Str => PhysicalType::BYTE_ARRAY,
+        };
+        let mut field = Type::primitive_type_builder(column.name, physical)
+            .with_repetition(Repetition::OPTIONAL);
+        field = match kind {
+            Kind::Str => field.with_converted_type(ConvertedType::UTF8),
+            Kind::Null => field.with_logical_type(Some(LogicalType::Unknown)),
+            _ => field,
+        };
+        fields.push(Arc::new(field.build().map_err(parquet_error)?));
+    }
+    let schema = Type::group_type_builder("schema")
+        .with_fields(fields)
+        .build()
+        .map_err(parquet_error)?;
+    let properties = WriterProperties::builder()
+        .set_compression(Compression::SNAPPY)
+        .build();
+
+    let mut writer = SerializedFileWriter::new(file, Arc::new(schema), Arc::new(properties))
+        .map_err(parquet_error)?;
+    let mut row_group = writer.next_row_group().map_err(parquet_error)?;
+    let mut index = 0;
+    while let Some(mut column_writer) = row_group.next_column().map_err(parquet_error)? {
+        let column = &columns[index];
+        let levels = column.levels();
+        let present = column.values.iter().flatten();
+        match kinds[index] {
+            Kind::Null => column_writer
+                .typed::<Int32Type>()
+                .write_batch(&[], Some(&levels), None),
+            Kind::Int => column_writer.typed::<Int64Type>().write_batch(
+                &present
+                    .map(|v| match v {
+                        Scalar::Int(i) => *i,
+                        _ => unreachable!(),
+                    })
+                    .collect::<Vec<_>>(),
+                Some(&levels),
+                None,
+            ),
+            Kind::Float => column_writer.typed::<DoubleType>().write_batch(
+                &present
+                    .map(|v| match v {
+                        Scalar::Int(i) => *i as f64,
+                        Scalar::Float(f) => *f,
+                        _ => unreachable!(),
+                    })
+                    .collect::<Vec<_>>(),
+                Some(&levels),
+                None,
+            ),
+            Kind::Bool => column_writer.typed::<BoolType>().write_batch(
+                &present
+                    .map(|v| match v {
+                        Scalar::Bool(b) => *b,
+                        _ => unreachable!(),
+                    })
+                    .collect::<Vec<_>>(),
+                Some(&levels),
+                None,
+            ),
+            Kind::Str => column_writer.typed::<ByteArrayType>().write_batch(
+                &present
+                    .map(|v| match v {
+                        Scalar::Str(s) => ByteArray::from(s.to_vec()),
+                        _ => unreachable!(),
+                    })
+                    .collect::<Vec<_>>(),
+                Some(&levels),
+                None,
+            ),
+        }
+        .map_err(parquet_error)?;
+        column_writer.close().map_err(parquet_error)?;
+        index += 1;
+    }
+    row_group.close().map_err(parquet_error)?;
+    writer.close().map_err(parquet_error)?;
+    Ok(rows)
+}
+
+/// Write an encoded list of records to a Parquet file; returns the row count.
+#[pyfunction]
+pub fn to_parquet(py: Python, data: &[u8], path: PathBuf) -> PyResult<usize> {
+    let file = File::create(&path)?;
+    py.allow_threads(|| write_parquet(data, file))
+        .map_err(|message| {
+            // Leave no truncated file behind
+            let _ = fs::remove_file(&path);
+            PyErr::new::<pyo3::exceptions::PyValueError, _>(message)
+        })
+}
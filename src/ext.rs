@@ -0,0 +1,102 @@
+// Extension types: user-defined binary encodings, like msgpack's ext.
+//
+// `register_ext(ext_id, cls, encode, decode)` makes the encoder write
+// instances of `cls` as TAG_EXT: [ext ID (u8)][length (u32)] then the bytes
+// `encode(obj)` returned. Decoders look the ID up in the same process-wide
+// registry and call `decode(data)`. Only classes the encoder has no native
+// form for reach the registry; they are checked before `__bfast__`.
+//
+// Tags 0xE0-0xEF are set aside for extensions; 0xE0 is the only layout used
+// so far.
+
+use std::sync::{Arc, RwLock};
+
+use pyo3::prelude::*;
+use pyo3::types::{PyAny, PyBytes, PyType};
+
+use crate::{BFast, TAG_EXT};
+
+struct Extension {
+    id: u8,
+    class: Py<PyType>,
+    encode: PyObject,
+    decode: PyObject,
+}
+
+static EXTENSIONS: RwLock<Vec<Arc<Extension>>> = RwLock::new(Vec::new());
+
+fn by_class(val: &PyAny) -> PyResult<Option<Arc<Extension>>> {
+    // isinstance may run Python code, so the lock is not held meanwhile
+    let extensions = EXTENSIONS.read().unwrap().clone();
+    for ext in extensions {
+        if val.is_instance(ext.class.as_ref(val.py()))? {
+            return Ok(Some(ext));
+        }
+    }
+    Ok(None)
+}
+
+/// Rebuild the value of extension `id` from its bytes.
+pub(crate) fn decode(py: Python, id: u8, data: &[u8]) -> PyResult<PyObject> {
+    let ext = EXTENSIONS
+        .read()
+        .unwrap()
+        .iter()
+        .find(|ext| ext.id == id)
+        .cloned();
+    let Some(ext) = ext else {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+            "Frame holds extension type {}; register it with register_ext to decode it",
+            id
+        )));
+    };
+    ext.decode.call1(py, (PyBytes::new(py, data),))
+}
+
+impl BFast {
+    /// Write `val` as an extension value if its class is registered. Returns
+    /// whether it did.
+    pub(crate) fn serialize_ext(&mut self, val: &PyAny) -> PyResult<bool> {
+        let Some(ext) = by_class(val)? else {
+            return Ok(false);
+        };
+        let py = val.py();
+        let data = ext.encode.call1(py, (val,))?;
+        let data = data.as_ref(py).downcast::<PyBytes>().map_err(|_| {
+            PyErr::new::<pyo3::exceptions::PyTypeError, _>(format!(
+                "encode of extension type {} must return bytes, got {}",
+                ext.id,
+                data.as_ref(py).get_type().name().unwrap_or("?")
+            ))
+        })?;
+        let data = data.as_bytes();
+        self.work_buffer.push(TAG_EXT);
+        self.work_buffer.push(ext.id);
+        self.work_buffer
+            .extend_from_slice(&(data.len() as u32).to_le_bytes());
+        self.work_buffer.extend_from_slice(data);
+        Ok(true)
+    }
+}
+
+/// Encode instances of `cls` as extension type `ext_id` (0-255) through
+/// `encode(obj) -> bytes`, and decode them with `decode(bytes)`. Registering
+/// an ID or class again replaces its earlier registration.
+#[pyfunction]
+pub fn register_ext(ext_id: i64, cls: &PyType, encode: PyObject, decode: PyObject) -> PyResult<()> {
+    let id = u8::try_from(ext_id).map_err(|_| {
+        PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+            "ext_id must be between 0 and 255, got {}",
+            ext_id
+        ))
+    })?;
+    let mut extensions = EXTENSIONS.write().unwrap();
+    extensions.retain(|ext| ext.id != id && !ext.class.as_ref(cls.py()).is(cls));
+    extensions.push(Arc::new(Extension {
+        id,
+        class: cls.into(),
+        encode,
+        decode,
+    }));
+    Ok(())
+}
@@ -0,0 +1,240 @@
+// Predicate filters over encoded lists.
+//
+// `b_fast.filter(data, "status", "==", "active")` keeps the elements of an
+// encoded list whose field compares as asked. Fields are read from the tag
+// stream and matching elements are copied as-is into a new frame, as slice
+// does, so nothing is decoded. Frames with a pruning index (column_stats)
+// that rules the predicate out give an empty list without being scanned.
+//
+// Comparisons follow Python for None, bools, numbers and strings; values of
+// other types only match `!=`, and orderings never match across types.
+// Frames encoded with dedup, null_bitmap, columnar or pack_bools have no
+// per-element bytes, so they are decoded, filtered, and written again as
+// plain frames.
+
+use std::cmp::Ordering;
+
+use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyDict, PyFloat, PyList, PyLong};
+
+use crate::scan::{
+    float_width, int_width, parse_frame, parse_path, unpack, Frame, PathItem, ScanResult,
+    StringTable,
+};
+use crate::stats::{self, Bound, Op, Operand};
+use crate::{decode_frame, BFast, DecodeOptions};
+
+fn value_error(message: String) -> PyErr {
+    PyErr::new::<pyo3::exceptions::PyValueError, _>(message)
+}
+
+/// Whether a field holding `found` satisfies `op operand`. None only equals
+/// None, and values that do not compare only match `!=`.
+fn holds(found: Operand, op: Op, operand: Operand) -> bool {
+    let ordering = match (found, operand) {
+        (Operand::Null, Operand::Null) if matches!(op, Op::Eq | Op::Ne) => Some(Ordering::Equal),
+        (Operand::Bound(found), Operand::Bound(operand)) => found.compare(operand),
+        _ => None,
+    };
+    match ordering {
+        Some(ordering) => op.holds(ordering),
+        None => op == Op::Ne,
+    }
+}
+
+/// The operand `value`; only None, bools, numbers and strings compare.
+fn operand(value: &PyAny) -> PyResult<Operand<'_>> {
+    if let Ok(float) = value.downcast::<PyFloat>() {
+        // NaN is kept, and equals nothing
+        return Ok(Operand::Bound(Bound::Float(float.value())));
+    }
+    match Operand::of(value)? {
+        Operand::Other => Err(value_error(format!(
+            "filter compares None, bools, ints, floats and strings, not {}",
+            match value.is_instance_of::<PyLong>() {
+                true => "ints beyond 64 bits".to_string(),
+                false => value.get_type().name()?.to_string(),
+            }
+        ))),
+        operand => Ok(operand),
+    }
+}
+
+/// The value at `offset` of `frame`, as an operand.
+fn operand_at<'a>(frame: &Frame<'a>, offset: usize) -> ScanResult<Operand<'a>> {
+    Ok(match frame.tag(offset)? {
+        0x10 => Operand::Null,
+        0x20 => Operand::Bound(Bound::Int(0)),
+        0x21 => Operand::Bound(Bound::Int(1)),
+        t if int_width(t).is_some() => Operand::Bound(Bound::Int(frame.int_at(offset)?)),
+        t if float_width(t).is_some() => Operand::Bound(Bound::Float(frame.float_at(offset)?)),
+        0x50 => {
+            let length = frame.u32_at(offset + 1)? as usize;
+            let bytes = frame
+                .data
+                .get(offset + 5..offset + 5 + length)
+                .ok_or_else(|| "Unexpected end of buffer during parsing".to_string())?;
+            let text = std::str::from_utf8(bytes)
+                .map_err(|e| format!("Invalid UTF-8 in string: {}", e))?;
+            Operand::Bound(Bound::Str(text))
+        }
+        _ => Operand::Other,
+    })
+}
+
+/// The decoded value `value`, as an operand.
+fn operand_of(value: &PyAny) -> PyResult<Operand<'_>> {
+    if let Ok(float) = value.downcast::<PyFloat>() {
+        return Ok(Operand::Bound(Bound::Float(float.value())));
+    }
+    Operand::of(value)
+}
+
+/// False when the pruning index of `frame` rules the predicate out.
+fn may_match(frame: &Frame, path: &[PathItem], op: Op, operand: Operand) -> ScanResult<bool> {
+    let &[PathItem::Key(field)] = path else {
+        return Ok(true);
+    };
+    Ok(stats::column(frame.stats, field)?.is_none_or(|column| column.may_match(op, operand)))
+}
+
+/// The frame of a list of the `count` elements written in `items`, keeping
+/// the metadata of `frame`.
+fn list_frame(
+    frame: &Frame,
+    table: &StringTable,
+    count: u32,
+    items: &[u8],
+    compress: bool,
+) -> ScanResult<Vec<u8>> {
+    let mut payload = Vec::with_capacity(5 + items.len());
+    payload.push(0x60);
+    payload.extend_from_slice(&count.to_le_bytes());
+    payload.extend_from_slice(items);
+    // An empty list needs none of the layout flags
+    let flags = if count == 0 { 0 } else { frame.flags };
+    table.build_frame(&payload, flags, frame.metadata, compress)
+}
+
+enum Filtered {
+    Frame(Vec<u8>),
+    // The frame has no per-element bytes to copy
+    Decode,
+}
+
+fn filter_frame(
+    data: &[u8],
+    path: &[PathItem],
+    op: Op,
+    operand: Operand,
+    compress: bool,
+) -> ScanResult<Filtered> {
+    let (data, compressed) = unpack(data)?;
+    let frame = parse_frame(&data)?;
+    let compress = compress && compressed;
+    if !matches!(frame.tag(frame.payload)?, 0x60 | crate::TAG_COLUMNAR) {
+        return Err("filter expects payloads encoded from a list".to_string());
+    }
+    let mut table = StringTable::default();
+    if !may_match(&frame, path, op, operand)? {
+        return Ok(Filtered::Frame(list_frame(
+            &frame,
+            &table,
+            0,
+            &[],
+            compress,
+        )?));
+    }
+    if frame.require_plain("filter").is_err() || frame.tag(frame.payload)? != 0x60 {
+        return Ok(Filtered::Decode);
+    }
+
+    let mut items = Vec::new();
+    let mut count = 0u32;
+    let mut cursor = frame.payload + 5;
+    for _ in 0..frame.u32_at(frame.payload + 1)? {
+        let end = frame.skip(cursor)?;
+        if let Some(offset) = frame.resolve(cursor, path)? {
+            if holds(operand_at(&frame, offset)?, op, operand) {
+                frame.copy_value(cursor, &mut table, &mut items)?;
+                count += 1;
+            }
+        }
+        cursor = end;
+    }
+    Ok(Filtered::Frame(list_frame(
+        &frame, &table, count, &items, compress,
+    )?))
+}
+
+/// The value at `path` inside a decoded element.
+fn resolve<'py>(mut value: &'py PyAny, path: &[PathItem]) -> PyResult<Option<&'py PyAny>> {
+    for item in path {
+        let next = match item {
+            PathItem::Key(key) => match value.downcast::<PyDict>() {
+                Ok(dict) => dict.get_item(key)?,
+                Err(_) => None,
+            },
+            PathItem::Index(index) => match value.downcast::<PyList>() {
+                Ok(list) if *index < list.len() => Some(list.get_item(*index)?),
+                _ => None,
+            },
+        };
+        match next {
+            Some(next) => value = next,
+            None => return Ok(None),
+        }
+    }
+    Ok(Some(value))
+}
+
+/// Keep the elements of an encoded list whose `field` compares with `value`
+/// as `op` asks; elements without the field are dropped. Returns a new frame,
+/// compressed when the input was, or with `decode` the decoded elements.
+#[pyfunction]
+#[pyo3(signature = (data, field, op, value, *, decode = false))]
+pub fn filter(
+    py: Python,
+    data: &[u8],
+    field: &str,
+    op: &str,
+    value: &PyAny,
+    decode: bool,
+) -> PyResult<PyObject> {
+    let op = Op::parse(op)?;
+    let operand = operand(value)?;
+    let path = parse_path(field).map_err(value_error)?;
+    let filtered = py
+        .allow_threads(|| filter_frame(data, &path, op, operand, !decode))
+        .map_err(value_error)?;
+    if let Filtered::Frame(frame) = filtered {
+        return match decode {
+            true => decode_frame(py, &frame, None, DecodeOptions::default()),
+            false => Ok(PyBytes::new(py, &frame).into()),
+        };
+    }
+
+    let (frame_data, compressed) = unpack(data).map_err(value_error)?;
+    let list = decode_frame(py, &frame_data, None, DecodeOptions::default())?;
+    let list = list.downcast::<PyList>(py)?;
+    let kept = PyList::empty(py);
+    for element in list {
+        if let Some(found) = resolve(element, &path)? {
+            if holds(operand_of(found)?, op, operand) {
+                kept.append(element)?;
+            }
+        }
+    }
+    if decode {
+        return Ok(kept.into());
+    }
+    let metadata = match parse_frame(&frame_data).map_err(value_error)?.metadata {
+        Some(metadata) => Some(decode_frame(py, metadata, None, DecodeOptions::default())?),
+        None => None,
+    };
+    let metadata = metadata
+        .as_ref()
+        .and_then(|m| m.downcast::<PyDict>(py).ok());
+    let frame = BFast::new().encode_frame(kept, compressed, metadata, None, false)?;
+    Ok(PyBytes::new(py, &frame).into())
+}
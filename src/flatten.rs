@@ -0,0 +1,60 @@
+// Flat records for analytics consumers.
+//
+// `BFast(flatten=True)` writes the fields of a model nested in a record as
+// fields of the record, named with a dot: `Order(id=1, address=Address(
+// city="Recife"))` is written as `{"id": 1, "address.city": "Recife"}`.
+// Records are the root value or the items of a root list; only one level is
+// flattened, so models nested deeper are written as objects. Values that are
+// Pydantic models or dataclasses are flattened, dicts are left as they are.
+//
+// Flattening happens before schema coercions and validation, which therefore
+// see the dotted names, and before the columnar layout, which gets a column
+// per nested field. Decoding returns the flat records.
+
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+use crate::{coerce, validate, BFast};
+
+impl BFast {
+    /// `obj` with the nested models of its records flattened, or `obj`
+    /// itself when the encoder does not flatten or nothing changes.
+    pub(crate) fn flatten_root<'py>(&self, obj: &'py PyAny) -> PyResult<&'py PyAny> {
+        if !self.flatten {
+            return Ok(obj);
+        }
+        coerce::map_records(obj, |record| self.flatten_record(record))
+    }
+
+    /// Like `flatten_root` for one record.
+    pub(crate) fn flatten_record<'py>(&self, record: &'py PyAny) -> PyResult<&'py PyAny> {
+        let Some(fields) = self.record_fields(record)? else {
+            return Ok(record);
+        };
+        let nested = |value: &PyAny| {
+            !value.is_instance_of::<pyo3::types::PyType>()
+                && validate::declares_fields(value.get_type())
+        };
+        if !fields.values().iter().any(nested) {
+            // An object without nested models is still written as an object
+            return Ok(record);
+        }
+        let flat = PyDict::new(record.py());
+        for (key, value) in fields {
+            let Some(inner) = nested(value)
+                .then(|| self.record_fields(value))
+                .transpose()?
+                .flatten()
+            else {
+                flat.set_item(key, value)?;
+                continue;
+            };
+            let prefix = key.str()?.to_string_lossy();
+            for (name, inner_value) in inner {
+                let name = format!("{}.{}", prefix, name.str()?.to_string_lossy());
+                flat.set_item(name, inner_value)?;
+            }
+        }
+        Ok(flat.into())
+    }
+}
@@ -0,0 +1,205 @@
+// Per-field encoding hints declared next to the model.
+//
+// A field annotated with `Annotated[float, BFastF32()]` is written as if the
+// encoder had `float32=True`, and one annotated with `Annotated[bytes,
+// BFastRaw()]` is always kept in the frame, even by an encoder with a blob
+// store. The hints apply to the whole value of the field, so
+// `Annotated[list[float], BFastF32()]` narrows every float of the list.
+//
+// Hints are read from the type hints of Pydantic models and dataclasses
+// (`Annotated` needs Python 3.9) once per class, and apply wherever their
+// instances are written: as objects, by the batch path and in columnar
+// lists (from the class of the first record).
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyType};
+
+use crate::{validate, BFast};
+
+/// Write the field's floats as f32 (TAG_FLOAT32) when they stay finite.
+#[allow(non_local_definitions)]
+#[pyclass(frozen)]
+pub struct BFastF32;
+
+#[allow(non_local_definitions)]
+#[pymethods]
+impl BFastF32 {
+    #[new]
+    fn new() -> Self {
+        BFastF32
+    }
+
+    fn __repr__(&self) -> &'static str {
+        "BFastF32()"
+    }
+}
+
+/// Keep the field's str and bytes values in the frame instead of handing
+/// them to the encoder's blob store.
+#[allow(non_local_definitions)]
+#[pyclass(frozen)]
+pub struct BFastRaw;
+
+#[allow(non_local_definitions)]
+#[pymethods]
+impl BFastRaw {
+    #[new]
+    fn new() -> Self {
+        BFastRaw
+    }
+
+    fn __repr__(&self) -> &'static str {
+        "BFastRaw()"
+    }
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub(crate) struct FieldHints {
+    f32: bool,
+    raw: bool,
+}
+
+impl FieldHints {
+    pub(crate) fn is_empty(self) -> bool {
+        self == FieldHints::default()
+    }
+}
+
+/// Hinted fields of a class, by name.
+pub(crate) type ClassHints = Arc<Vec<(String, FieldHints)>>;
+
+// Keyed by the address of the class, which the entry keeps alive
+static HINTS: Mutex<BTreeMap<usize, (Py<PyType>, ClassHints)>> = Mutex::new(BTreeMap::new());
+
+/// The hinted fields of `class`; empty for classes without any.
+pub(crate) fn of(class: &PyType) -> PyResult<ClassHints> {
+    let key = class.as_ptr() as usize;
+    if let Some((_, hints)) = HINTS.lock().unwrap().get(&key) {
+        return Ok(hints.clone());
+    }
+    // Reading the annotations runs Python code, so the lock is not held
+    let hints = Arc::new(read_hints(class)?);
+    HINTS
+        .lock()
+        .unwrap()
+        .insert(key, (class.into(), hints.clone()));
+    Ok(hints)
+}
+
+/// The hints of each of `field_names` of `class`.
+pub(crate) fn for_fields(class: &PyType, field_names: &[String]) -> PyResult<Vec<FieldHints>> {
+    let hints = of(class)?;
+    Ok(field_names
+        .iter()
+        .map(|name| lookup(&hints, name))
+        .collect())
+}
+
+fn lookup(hints: &ClassHints, name: &str) -> FieldHints {
+    hints
+        .iter()
+        .find(|(field, _)| field == name)
+        .map_or_else(FieldHints::default, |&(_, hints)| hints)
+}
+
+fn read_hints(class: &PyType) -> PyResult<Vec<(String, FieldHints)>> {
+    let py = class.py();
+    let typing = py.import("typing")?;
+    let Ok(annotated) = typing.getattr("Annotated") else {
+        return Ok(Vec::new());
+    };
+    if !validate::declares_fields(class) {
+        return Ok(Vec::new());
+    }
+    let kwargs = PyDict::new(py);
+    kwargs.set_item("include_extras", true)?;
+    // Forward references that do not resolve leave the fields without hints
+    let Ok(annotations) = typing.call_method("get_type_hints", (class,), Some(kwargs)) else {
+        return Ok(Vec::new());
+    };
+    let mut fields = Vec::new();
+    for (name, hint) in annotations.downcast::<PyDict>()? {
+        if !typing.call_method1("get_origin", (hint,))?.is(annotated) {
+            continue;
+        }
+        let mut hints = FieldHints::default();
+        for marker in hint.getattr("__metadata__")?.iter()? {
+            let marker = marker?;
+            hints.f32 |= marker.is_instance_of::<BFastF32>();
+            hints.raw |= marker.is_instance_of::<BFastRaw>();
+        }
+        if !hints.is_empty() {
+            fields.push((name.extract()?, hints));
+        }
+    }
+    Ok(fields)
+}
+
+impl BFast {
+    /// Run `write` with the encoder set up as `hints` asks.
+    pub(crate) fn with_hints<T>(
+        &mut self,
+        hints: FieldHints,
+        write: impl FnOnce(&mut Self) -> PyResult<T>,
+    ) -> PyResult<T> {
+        if hints.is_empty() {
+            return write(self);
+        }
+        let float32 = self.float32;
+        self.float32 |= hints.f32;
+        let blob_store = match hints.raw {
+            true => self.blob_store.take(),
+            false => None,
+        };
+        let result = write(self);
+        self.float32 = float32;
+        if hints.raw {
+            self.blob_store = blob_store;
+        }
+        result
+    }
+
+    /// Write an object of a class with hinted fields, like serialize_object.
+    pub(crate) fn serialize_hinted_object(
+        &mut self,
+        dict: &PyDict,
+        hints: &ClassHints,
+    ) -> PyResult<()> {
+        let mut hinted = Vec::new();
+        for (name, field_hints) in hints.iter() {
+            if dict.contains(name)? {
+                hinted.push((self.get_or_create_string_id_fast(name), *field_hints));
+            }
+        }
+        let hints_of = |id: u32| {
+            hinted
+                .iter()
+                .find(|(hinted_id, _)| *hinted_id == id)
+                .map_or_else(FieldHints::default, |&(_, hints)| hints)
+        };
+        let entries = self.object_entries(dict)?;
+        if self.null_bitmap {
+            let entries = entries
+                .into_iter()
+                .map(|(id, v)| (id, Some(v)))
+                .collect::<Vec<_>>();
+            return self.write_bitmap_object(&entries, |encoder, i, value| {
+                encoder.with_hints(hints_of(entries[i].0), |encoder| {
+                    encoder.serialize_any_optimized(value)
+                })
+            });
+        }
+        self.work_buffer.push(0x70);
+        for (id, value) in entries {
+            self.work_buffer.extend_from_slice(&id.to_le_bytes());
+            self.with_hints(hints_of(id), |encoder| {
+                encoder.serialize_any_optimized(value)
+            })?;
+        }
+        self.work_buffer.push(0x7F);
+        Ok(())
+    }
+}
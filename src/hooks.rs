@@ -0,0 +1,124 @@
+// Encoding hooks for Python subclasses of BFast.
+//
+// A subclass may define any of:
+//
+//   on_value(self, value)        every value about to be written; the value
+//                                returned is written in its place
+//   on_key(self, key)            every dict or object key; the key returned
+//                                is written (as its str) in its place
+//   on_unknown_type(self, obj)   objects the encoder has no form for, which
+//                                would otherwise be written as str(obj); the
+//                                value returned is written instead
+//
+// Whether the class defines any is looked up once, when the encoder is
+// created. The encode methods then bind the hooks to the instance for the
+// duration of the call only, so the encoder never holds a reference to itself
+// between calls. While hooks are bound, the record batch path, the columnar
+// layout and bool packing are skipped: they write values without visiting
+// them one by one.
+
+use pyo3::prelude::*;
+use pyo3::types::PyType;
+
+use crate::BFast;
+
+const HOOK_NAMES: [&str; 3] = ["on_value", "on_key", "on_unknown_type"];
+
+/// Hooks bound to the instance being encoded with.
+pub(crate) struct Hooks {
+    on_value: Option<PyObject>,
+    on_key: Option<PyObject>,
+    on_unknown_type: Option<PyObject>,
+}
+
+/// Whether `cls` defines any of the hooks.
+pub(crate) fn defines_hooks(cls: &PyType) -> PyResult<bool> {
+    for name in HOOK_NAMES {
+        if cls.hasattr(name)? {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Run `encode` on the encoder behind `slf`, with the hooks its class
+/// defines bound to it.
+pub(crate) fn with_hooks<R>(
+    mut slf: PyRefMut<'_, BFast>,
+    encode: impl FnOnce(&mut BFast) -> PyResult<R>,
+) -> PyResult<R> {
+    if !slf.hooked {
+        return encode(&mut slf);
+    }
+    let py = slf.py();
+    let this = (&slf).into_py(py);
+    let this = this.as_ref(py);
+    let bind = |name: &str| -> PyResult<Option<PyObject>> {
+        match this.hasattr(name)? {
+            true => Ok(Some(this.getattr(name)?.into())),
+            false => Ok(None),
+        }
+    };
+    slf.hooks = Some(Hooks {
+        on_value: bind("on_value")?,
+        on_key: bind("on_key")?,
+        on_unknown_type: bind("on_unknown_type")?,
+    });
+    let result = encode(&mut slf);
+    slf.hooks = None;
+    result
+}
+
+impl BFast {
+    /// `val` as passed through the subclass's `on_value`.
+    #[inline]
+    pub(crate) fn hook_value<'p>(&self, val: &'p PyAny) -> PyResult<&'p PyAny> {
+        match self
+            .hooks
+            .as_ref()
+            .and_then(|hooks| hooks.on_value.as_ref())
+        {
+            Some(on_value) => Ok(on_value.call1(val.py(), (val,))?.into_ref(val.py())),
+            None => Ok(val),
+        }
+    }
+
+    /// `key` as passed through the subclass's `on_key`.
+    #[inline]
+    pub(crate) fn hook_key<'p>(&self, key: &'p PyAny) -> PyResult<&'p PyAny> {
+        match self.hooks.as_ref().and_then(|hooks| hooks.on_key.as_ref()) {
+            Some(on_key) => Ok(on_key.call1(key.py(), (key,))?.into_ref(key.py())),
+            None => Ok(key),
+        }
+    }
+
+    /// Write the replacement the subclass's `on_unknown_type` returns for
+    /// `val`. Returns whether it did; without the hook, or when the hook
+    /// returns `val` itself, the encoder's own fallback applies.
+    pub(crate) fn serialize_unknown(&mut self, val: &PyAny) -> PyResult<bool> {
+        let Some(hook) = self
+            .hooks
+            .as_ref()
+            .and_then(|hooks| hooks.on_unknown_type.as_ref())
+        else {
+            return Ok(false);
+        };
+        let py = val.py();
+        let replacement = hook.call1(py, (val,))?.into_ref(py);
+        if replacement.is(val) {
+            return Ok(false);
+        }
+        // A hook that keeps returning new unknown objects ends here
+        self.check_recursion_depth()?;
+        self.serialize_any_optimized(replacement)?;
+        self.decrease_recursion_depth();
+        Ok(true)
+    }
+
+    /// Whether hooks are bound, which rules out the writers that do not
+    /// visit every value.
+    #[inline]
+    pub(crate) fn hooks_bound(&self) -> bool {
+        self.hooks.is_some()
+    }
+}
@@ -0,0 +1,201 @@
+// Incremental encoding of a list of records.
+//
+// `BFastBatchEncoder` writes each record into the payload as soon as `add`
+// receives it, with every key going to one shared string table, so rows
+// streamed from a database cursor never have to be collected into a list
+// first. `finish` puts the header and string table in front of the records
+// and returns a frame that decodes to the list of everything added.
+//
+// `BFast.encode_packed_split` builds on the same steps to cut a list into
+// frames under a size cap, starting a new frame (and string table) whenever
+// the next record would not fit.
+
+use std::mem;
+
+use pyo3::prelude::*;
+use pyo3::types::{PyAny, PyBytes};
+
+use crate::compression;
+use crate::{BFast, HEADER_SIZE};
+
+/// List tag and record count in front of the records.
+const LIST_HEADER_SIZE: usize = 5;
+
+impl BFast {
+    /// Encode `record` at the end of `work_buffer`. A record that fails to
+    /// encode leaves neither bytes nor keys behind.
+    fn append_record(&mut self, record: &PyAny) -> PyResult<()> {
+        let record = match self.flatten {
+            true => self.flatten_record(record)?,
+            false => record,
+        };
+        let record = match &self.schema {
+            Some(def) => self.coerce_record(record, def)?,
+            None => record,
+        };
+        self.validate_added(record)?;
+        let (start, next_id) = (self.work_buffer.len(), self.next_id);
+        if let Err(err) = self.serialize_any_optimized(record) {
+            self.rewind(start, next_id);
+            return Err(err);
+        }
+        Ok(())
+    }
+
+    /// Turn the `count` records in `work_buffer` into the uncompressed frame
+    /// of their list.
+    fn write_records_frame(&mut self, py: Python, count: u32) -> PyResult<()> {
+        let records = mem::take(&mut self.work_buffer);
+        self.write_frame_with(py, None, 0, |encoder, _| {
+            encoder.work_buffer.push(0x60);
+            encoder.work_buffer.extend_from_slice(&count.to_le_bytes());
+            encoder.work_buffer.extend_from_slice(&records);
+            Ok(())
+        })
+    }
+
+    /// Bytes the string table entries with IDs from `id` take in a frame.
+    fn string_table_size_from(&self, id: u32) -> usize {
+        self.string_table
+            .iter()
+            .filter(|(_, &entry)| entry >= id)
+            .map(|(string, _)| string.len() + 1)
+            .sum()
+    }
+
+    /// Encode `records` as a list of frames of at most `max_frame_bytes`
+    /// each (before compression), keeping every record whole.
+    pub(crate) fn encode_split(
+        &mut self,
+        py: Python,
+        records: &PyAny,
+        max_frame_bytes: usize,
+        compress: bool,
+    ) -> PyResult<Vec<Vec<u8>>> {
+        // Keys from earlier calls stay in every frame, as with encode_packed
+        let first_id = self.next_id;
+        let schema_fields = self.schema_field_count() as u32;
+        let fixed = HEADER_SIZE + if self.schema.is_some() { 4 } else { 0 } + LIST_HEADER_SIZE;
+        let mut frames = Vec::new();
+
+        self.work_buffer.clear();
+        self.recursion_depth = 0;
+        let mut table_size = self.string_table_size_from(schema_fields);
+        let mut count = 0u32;
+        for (index, record) in records.iter()?.enumerate() {
+            let record = record?;
+            let (start, next_id) = (self.work_buffer.len(), self.next_id);
+            self.append_record(record)?;
+            let mut record_table_size = table_size + self.string_table_size_from(next_id);
+            let mut frame_size = fixed + record_table_size + self.work_buffer.len();
+
+            // Close the frame without this record and start the next with it
+            if frame_size > max_frame_bytes && count > 0 {
+                self.rewind(start, next_id);
+                frames.push(self.take_split_frame(py, compress, count)?);
+                self.rewind(0, first_id);
+                table_size = self.string_table_size_from(schema_fields);
+                count = 0;
+                let next_id = self.next_id;
+                self.append_record(record)?;
+                record_table_size = table_size + self.string_table_size_from(next_id);
+                frame_size = fixed + record_table_size + self.work_buffer.len();
+            }
+            if frame_size > max_frame_bytes {
+                return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                    "Record {} needs a {}-byte frame, over max_frame_bytes={}",
+                    index, frame_size, max_frame_bytes
+                )));
+            }
+            table_size = record_table_size;
+            count += 1;
+        }
+        if count > 0 {
+            frames.push(self.take_split_frame(py, compress, count)?);
+        }
+        Ok(frames)
+    }
+
+    /// The frame of the records in `work_buffer`, compressed only when that
+    /// makes it smaller, so it stays within the size it was cut to.
+    fn take_split_frame(&mut self, py: Python, compress: bool, count: u32) -> PyResult<Vec<u8>> {
+        self.write_records_frame(py, count)?;
+        let mut frame = mem::take(&mut self.work_buffer);
+        if compress && frame.len() > 256 {
+            return compression::compress_or_store(&*self.codec, &mut frame)
+                .map_err(PyErr::new::<pyo3::exceptions::PyValueError, _>);
+        }
+        Ok(frame)
+    }
+}
+
+#[allow(non_local_definitions)]
+#[pyclass]
+pub struct BFastBatchEncoder {
+    /// Holds the records added so far.
+    encoder: BFast,
+    records: usize,
+    compress: bool,
+    finished: bool,
+}
+
+impl BFastBatchEncoder {
+    fn check_open(&self) -> PyResult<()> {
+        if self.finished {
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+                "BFastBatchEncoder is already finished",
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[allow(non_local_definitions)]
+#[pymethods]
+impl BFastBatchEncoder {
+    #[new]
+    #[pyo3(signature = (*, compress = false))]
+    fn new(compress: bool) -> Self {
+        BFastBatchEncoder {
+            encoder: BFast::new(),
+            records: 0,
+            compress,
+            finished: false,
+        }
+    }
+
+    /// Encode one record and append it to the batch.
+    fn add(&mut self, record: &PyAny) -> PyResult<()> {
+        self.check_open()?;
+        if self.records == u32::MAX as usize {
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+                "Batch already holds the maximum number of records",
+            ));
+        }
+        self.encoder.append_record(record)?;
+        self.records += 1;
+        Ok(())
+    }
+
+    /// Append every record of an iterable. Returns the batch's record count.
+    fn extend(&mut self, records: &PyAny) -> PyResult<usize> {
+        for record in records.iter()? {
+            self.add(record?)?;
+        }
+        Ok(self.records)
+    }
+
+    fn __len__(&self) -> usize {
+        self.records
+    }
+
+    /// The frame of the list of added records; the encoder cannot be used
+    /// afterwards.
+    fn finish(&mut self, py: Python) -> PyResult<PyObject> {
+        self.check_open()?;
+        self.finished = true;
+        self.encoder.write_records_frame(py, self.records as u32)?;
+        let frame = self.encoder.take_frame(py, self.compress)?;
+        Ok(PyBytes::new(py, &frame).into())
+    }
+}
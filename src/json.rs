@@ -0,0 +1,410 @@
+// JSON ingestion without Python objects.
+//
+// `from_json_bytes(data)` parses JSON straight into the plain Rust values the
+// release_gil path lays out (see prescan.rs), with the GIL released for both
+// steps, and returns the frame `encode_packed(json.loads(data))` would. Object
+// keys keep their order; a repeated key keeps its first position and last
+// value, as json.loads does. Integers beyond the i64 range become floats, as
+// the encoder writes such Python ints.
+//
+// `to_json(data, fields=...)` goes the other way without Python objects
+// either: the tag stream is written out as compact JSON, with the values
+// `decode_packed(data, allowed_classes=[])` would return (temporal values,
+// UUIDs and Decimals as strings, pandas Timedeltas as integer nanoseconds,
+// custom objects as their state). `fields` keeps only the listed fields of the root
+// record, or of every record of a root list; `"customer.name"` selects into a
+// nested record, as well as a field named that way.
+
+use std::fmt;
+
+use ahash::AHashMap;
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+use serde::de::{Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
+
+use crate::dump::tag_name;
+use crate::prescan::Value;
+use crate::scan::{float_width, int_width, parse_frame, read_u32, unpack, Frame, ScanResult};
+use crate::{
+    temporal, trace, typed_array_itemsize, BFast, MAX_RECURSION_DEPTH, TAG_BOOL_ARRAY, TAG_CUSTOM,
+    TAG_DATE, TAG_DATETIME, TAG_DATE_DAYS, TAG_DECIMAL, TAG_INT_OBJECT, TAG_RANGE, TAG_SECTIONS,
+    TAG_TIME, TAG_TIMEDELTA, TAG_TIME_NANOS, TAG_TYPED_ARRAY, TAG_UUID,
+};
+
+/// Entries from which repeated keys are looked up in an index.
+const INDEXED_OBJECT_LEN: usize = 32;
+
+impl<'de> Deserialize<'de> for Value {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Value, D::Error> {
+        deserializer.deserialize_any(ValueVisitor)
+    }
+}
+
+struct ValueVisitor;
+
+impl<'de> Visitor<'de> for ValueVisitor {
+    type Value = Value;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a JSON value")
+    }
+
+    fn visit_unit<E>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_bool<E>(self, b: bool) -> Result<Value, E> {
+        Ok(Value::Bool(b))
+    }
+
+    fn visit_i64<E>(self, n: i64) -> Result<Value, E> {
+        Ok(Value::Int(n))
+    }
+
+    fn visit_u64<E>(self, n: u64) -> Result<Value, E> {
+        Ok(match i64::try_from(n) {
+            Ok(n) => Value::Int(n),
+            Err(_) => Value::Float(n as f64),
+        })
+    }
+
+    fn visit_f64<E>(self, f: f64) -> Result<Value, E> {
+        Ok(Value::Float(f))
+    }
+
+    fn visit_str<E>(self, s: &str) -> Result<Value, E> {
+        Ok(Value::Str(s.to_owned()))
+    }
+
+    fn visit_string<E>(self, s: String) -> Result<Value, E> {
+        Ok(Value::Str(s))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Value, A::Error> {
+        let mut items = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(item) = seq.next_element()? {
+            items.push(item);
+        }
+        Ok(Value::List(items))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Value, A::Error> {
+        let mut entries: Vec<(String, Value)> = Vec::new();
+        // Small objects look repeated keys up by scanning
+        let mut index: Option<AHashMap<String, usize>> = None;
+        while let Some((key, value)) = map.next_entry::<String, Value>()? {
+            let position = match &index {
+                Some(index) => index.get(&key).copied(),
+                None => entries.iter().position(|(existing, _)| *existing == key),
+            };
+            if let Some(position) = position {
+                entries[position].1 = value;
+                continue;
+            }
+            if let Some(index) = &mut index {
+                index.insert(key.clone(), entries.len());
+            }
+            entries.push((key, value));
+            if index.is_none() && entries.len() == INDEXED_OBJECT_LEN {
+                index = Some(
+                    entries
+                        .iter()
+                        .enumerate()
+                        .map(|(position, (key, _))| (key.clone(), position))
+                        .collect(),
+                );
+            }
+        }
+        Ok(Value::Object(entries))
+    }
+}
+
+/// B-FAST encoding of a JSON document, parsed without creating Python
+/// objects.
+#[pyfunction]
+#[pyo3(signature = (json_bytes, *, compress = false))]
+pub fn from_json_bytes(py: Python, json_bytes: &[u8], compress: bool) -> PyResult<PyObject> {
+    let mut encoder = BFast::new();
+    let span = trace::span("released");
+    let frame = py.allow_threads(|| {
+        let value: Value = serde_json::from_slice(json_bytes).map_err(|e| {
+            PyErr::new::<pyo3::exceptions::PyValueError, _>(format!("Invalid JSON: {}", e))
+        })?;
+        encoder.value_frame(&value, compress, None)
+    })?;
+    span.finish(py, frame.len())?;
+    Ok(PyBytes::new(py, &frame).into())
+}
+
+struct JsonWriter<'f, 'a> {
+    frame: &'f Frame<'a>,
+    out: Vec<u8>,
+}
+
+impl<'a> JsonWriter<'_, 'a> {
+    /// Write the value at `offset`, keeping only `fields` of its records.
+    fn value(&mut self, offset: usize, fields: Option<&[&str]>, depth: usize) -> ScanResult<()> {
+        if depth > MAX_RECURSION_DEPTH {
+            return Err("Maximum recursion depth exceeded during B-FAST scan".to_string());
+        }
+        let frame = self.frame;
+        let tag = frame.tag(offset)?;
+        match tag {
+            0x10 => self.out.extend_from_slice(b"null"),
+            0x20 => self.out.extend_from_slice(b"false"),
+            0x21 => self.out.extend_from_slice(b"true"),
+            t if int_width(t).is_some() => self.int(frame.int_at(offset)?),
+            t if float_width(t).is_some() => self.float(frame.float_at(offset)?)?,
+            0x50 | TAG_DATE | TAG_TIME | TAG_DECIMAL => {
+                let text = self.text(offset)?;
+                self.string(text)?
+            }
+            TAG_DATETIME if frame.u32_at(offset + 1)? == temporal::DATETIME_NANOS => {
+                let fixed = self.fixed(offset + 5, temporal::DATETIME_NANOS_LEN)?;
+                let nanos = i64::from_le_bytes(fixed[..8].try_into().unwrap());
+                let utc_offset = i32::from_le_bytes(fixed[8..].try_into().unwrap());
+                self.string(&temporal::iso_from_nanos(nanos, utc_offset))?
+            }
+            TAG_DATETIME => {
+                let text = self.text(offset)?;
+                self.string(text)?
+            }
+            TAG_DATE_DAYS => {
+                let days = i32::from_le_bytes(self.fixed(offset + 1, 4)?.try_into().unwrap());
+                self.string(&temporal::date_iso(days as i64))?
+            }
+            TAG_TIME_NANOS => {
+                let nanos = u64::from_le_bytes(self.fixed(offset + 1, 8)?.try_into().unwrap());
+                self.string(&temporal::time_iso(nanos))?
+            }
+            TAG_TIMEDELTA => self.int(i64::from_le_bytes(
+                self.fixed(offset + 1, 8)?.try_into().unwrap(),
+            )),
+            TAG_UUID => {
+                let hex = self.text(offset)?;
+                match hex.len() == 32 && hex.is_ascii() {
+                    true => self.string(
+                        &[(0, 8), (8, 12), (12, 16), (16, 20), (20, 32)]
+                            .map(|(start, end)| &hex[start..end])
+                            .join("-"),
+                    )?,
+                    false => self.string(hex)?,
+                }
+            }
+            0x60 => {
+                self.out.push(b'[');
+                for (i, item) in frame.list_items(offset)?.into_iter().enumerate() {
+                    if i > 0 {
+                        self.out.push(b',');
+                    }
+                    self.value(item, fields, depth + 1)?;
+                }
+                self.out.push(b']');
+            }
+            0x90 => {
+                let count = frame.u32_at(offset + 1)? as usize;
+                let data = self.fixed(offset + 5, count * 8)?;
+                self.array(data.chunks_exact(8), |writer, item| {
+                    writer.float(f64::from_le_bytes(item.try_into().unwrap()))
+                })?
+            }
+            TAG_BOOL_ARRAY => {
+                let count = frame.u32_at(offset + 1)? as usize;
+                let bits = self.fixed(offset + 5, count.div_ceil(8))?;
+                self.array(0..count, |writer, i| {
+                    let bit = bits[i / 8] >> (i % 8) & 1 == 1;
+                    writer
+                        .out
+                        .extend_from_slice(if bit { b"true" } else { b"false" });
+                    Ok(())
+                })?
+            }
+            TAG_TYPED_ARRAY => self.typed_array(offset)?,
+            TAG_RANGE => {
+                let start = frame.int_at(offset + 1)?;
+                let stop_at = frame.skip(offset + 1)?;
+                let stop = frame.int_at(stop_at)?;
+                let step = frame.int_at(frame.skip(stop_at)?)?;
+                if step == 0 {
+                    return Err("Range step must not be zero".to_string());
+                }
+                let values = std::iter::successors(Some(start), |value| value.checked_add(step))
+                    .take_while(|&value| if step > 0 { value < stop } else { value > stop });
+                self.array(values, |writer, value| {
+                    writer.int(value);
+                    Ok(())
+                })?
+            }
+            0x70 => {
+                let entries = frame
+                    .object_entries(offset)?
+                    .into_iter()
+                    .map(|(key, _, value)| (key, value))
+                    .collect::<Vec<_>>();
+                self.object(&entries, fields, depth)?
+            }
+            TAG_SECTIONS => self.object(&frame.sections(offset)?, fields, depth)?,
+            // Keys are written as strings, as json.dumps does
+            TAG_INT_OBJECT => {
+                let entries = frame
+                    .int_entries(offset)?
+                    .into_iter()
+                    .map(|(key, value)| (key.to_string(), value))
+                    .collect::<Vec<_>>();
+                self.object(&entries, fields, depth)?
+            }
+            TAG_CUSTOM => {
+                let state = offset + 5 + frame.u32_at(offset + 1)? as usize;
+                self.value(state, fields, depth + 1)?
+            }
+            _ => {
+                return Err(format!(
+                    "{} values have no JSON form (at offset {})",
+                    tag_name(tag),
+                    offset
+                ))
+            }
+        }
+        Ok(())
+    }
+
+    /// Write the entries of a record, keeping only `fields` when given.
+    fn object<K: AsRef<str>>(
+        &mut self,
+        entries: &[(K, usize)],
+        fields: Option<&[&str]>,
+        depth: usize,
+    ) -> ScanResult<()> {
+        self.out.push(b'{');
+        let mut first = true;
+        for (key, value) in entries {
+            let key = key.as_ref();
+            let nested: Vec<&str>;
+            let selected = match fields {
+                Some(fields) if !fields.contains(&key) => {
+                    nested = fields
+                        .iter()
+                        .filter_map(|field| field.strip_prefix(key)?.strip_prefix('.'))
+                        .collect();
+                    if nested.is_empty() {
+                        continue;
+                    }
+                    Some(nested.as_slice())
+                }
+                _ => None,
+            };
+            if !first {
+                self.out.push(b',');
+            }
+            first = false;
+            self.string(key)?;
+            self.out.push(b':');
+            self.value(*value, selected, depth + 1)?;
+        }
+        self.out.push(b'}');
+        Ok(())
+    }
+
+    fn typed_array(&mut self, offset: usize) -> ScanResult<()> {
+        let code = self.frame.tag(offset + 1)?;
+        let length = read_u32(self.frame.data, offset + 2)? as usize;
+        let data = self.fixed(offset + 6, length)?;
+        let itemsize = typed_array_itemsize(code)
+            .ok_or_else(|| format!("Unsupported typed array code: {:?}", code as char))?;
+        if !length.is_multiple_of(itemsize) {
+            return Err("Typed array length is not a multiple of its item size".to_string());
+        }
+        self.array(data.chunks_exact(itemsize), |writer, item| {
+            match code {
+                b'f' => return writer.float(f32::from_le_bytes(item.try_into().unwrap()) as f64),
+                b'd' => return writer.float(f64::from_le_bytes(item.try_into().unwrap())),
+                b'Q' => {
+                    let value = u64::from_le_bytes(item.try_into().unwrap());
+                    writer.out.extend_from_slice(value.to_string().as_bytes());
+                    return Ok(());
+                }
+                _ => {}
+            }
+            writer.int(match code {
+                b'b' => item[0] as i8 as i64,
+                b'B' => item[0] as i64,
+                b'h' => i16::from_le_bytes(item.try_into().unwrap()) as i64,
+                b'H' => u16::from_le_bytes(item.try_into().unwrap()) as i64,
+                b'i' => i32::from_le_bytes(item.try_into().unwrap()) as i64,
+                b'I' => u32::from_le_bytes(item.try_into().unwrap()) as i64,
+                _ => i64::from_le_bytes(item.try_into().unwrap()),
+            });
+            Ok(())
+        })
+    }
+
+    fn array<T>(
+        &mut self,
+        items: impl IntoIterator<Item = T>,
+        mut write: impl FnMut(&mut Self, T) -> ScanResult<()>,
+    ) -> ScanResult<()> {
+        self.out.push(b'[');
+        for (i, item) in items.into_iter().enumerate() {
+            if i > 0 {
+                self.out.push(b',');
+            }
+            write(self, item)?;
+        }
+        self.out.push(b']');
+        Ok(())
+    }
+
+    /// The `length` bytes at `offset`.
+    fn fixed(&self, offset: usize, length: usize) -> ScanResult<&'a [u8]> {
+        self.frame
+            .data
+            .get(offset..offset + length)
+            .ok_or_else(|| "Unexpected end of buffer during parsing".to_string())
+    }
+
+    /// The UTF-8 text of a length-prefixed value.
+    fn text(&self, offset: usize) -> ScanResult<&'a str> {
+        let length = self.frame.u32_at(offset + 1)? as usize;
+        std::str::from_utf8(self.fixed(offset + 5, length)?)
+            .map_err(|e| format!("Invalid UTF-8 in string: {}", e))
+    }
+
+    fn int(&mut self, value: i64) {
+        self.out.extend_from_slice(value.to_string().as_bytes());
+    }
+
+    /// JSON has no NaN or infinities; like serde_json, they become null.
+    fn float(&mut self, value: f64) -> ScanResult<()> {
+        serde_json::to_writer(&mut self.out, &value).map_err(|e| e.to_string())
+    }
+
+    fn string(&mut self, value: &str) -> ScanResult<()> {
+        serde_json::to_writer(&mut self.out, value).map_err(|e| e.to_string())
+    }
+}
+
+fn frame_json(data: &[u8], fields: Option<&[&str]>) -> ScanResult<Vec<u8>> {
+    let (frame_data, _) = unpack(data)?;
+    let frame = parse_frame(&frame_data)?;
+    frame.require_plain("to_json")?;
+    let mut writer = JsonWriter {
+        frame: &frame,
+        out: Vec::with_capacity(frame_data.len() * 2),
+    };
+    writer.value(frame.payload, fields, 0)?;
+    Ok(writer.out)
+}
+
+/// Compact UTF-8 JSON of an encoded payload, written without creating
+/// Python objects; `fields` keeps only those fields of its records.
+#[pyfunction]
+#[pyo3(signature = (data, *, fields = None))]
+pub fn to_json(py: Python, data: &[u8], fields: Option<Vec<String>>) -> PyResult<PyObject> {
+    let fields = fields
+        .as_ref()
+        .map(|fields| fields.iter().map(String::as_str).collect::<Vec<_>>());
+    let json = py
+        .allow_threads(|| frame_json(data, fields.as_deref()))
+        .map_err(PyErr::new::<pyo3::exceptions::PyValueError, _>)?;
+    Ok(PyBytes::new(py, &json).into())
+}
@@ -0,0 +1,39 @@
+// Key objects shared by the frames one encoder decodes.
+//
+// A parser interns each string table entry the first time a frame uses it,
+// which still builds a str per key per frame before the interpreter's table
+// hands back the existing one. `BFast.decode_packed` instead looks keys up in
+// a map the instance keeps across calls, so a stream of frames with the same
+// fields gets every key as the same str object and builds none after the
+// first frame. The map stops taking new keys once it holds MAX_KEYS of them;
+// keys past that are interned per frame as before.
+
+use std::sync::Mutex;
+
+use ahash::AHashMap;
+use pyo3::prelude::*;
+use pyo3::types::PyString;
+
+/// Keys kept before the map stops growing.
+const MAX_KEYS: usize = 4096;
+
+/// Decoded key objects by their text.
+#[derive(Default)]
+pub(crate) struct KeyCache {
+    keys: Mutex<AHashMap<String, Py<PyString>>>,
+}
+
+impl KeyCache {
+    /// The str object for `key`, created on first use.
+    pub(crate) fn get<'py>(&self, py: Python<'py>, key: &str) -> &'py PyString {
+        let mut keys = self.keys.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(cached) = keys.get(key) {
+            return cached.clone_ref(py).into_ref(py);
+        }
+        let interned = PyString::intern(py, key);
+        if keys.len() < MAX_KEYS {
+            keys.insert(key.to_owned(), interned.into());
+        }
+        interned
+    }
+}
@@ -0,0 +1,235 @@
+// Lazily decoded root lists.
+//
+// `decode_packed(..., lazy=True)` keeps the (decompressed) frame and the
+// offsets of the root list's elements, found by walking the tag stream
+// without the GIL. Elements are decoded the first time they are accessed and
+// cached, so a handler that pages through a huge response only pays for the
+// rows it touches. `decode_mmap` builds the same list over a file mapping,
+// so opening a large snapshot only walks the offsets of its elements.
+
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList, PySlice};
+
+use crate::scan::{parse_frame, unpack, ScanResult};
+use crate::{
+    check_fingerprint, decode_frame, freeze, schema, BFastParser, DecodeOptions, FLAG_SHARED_REFS,
+};
+
+/// The frame a LazyList decodes from: a copy (inflated when it was
+/// compressed), or a read-only mapping of the file holding it.
+pub(crate) enum Source {
+    Owned(Vec<u8>),
+    Mapped(memmap2::Mmap),
+}
+
+impl std::ops::Deref for Source {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            Source::Owned(data) => data,
+            Source::Mapped(map) => map,
+        }
+    }
+}
+
+struct RootList {
+    strings: Vec<String>,
+    items: Vec<usize>,
+    fingerprint: Option<u64>,
+}
+
+/// The root list of the frame `data`, or `None` when the payload is not a
+/// list whose elements can be decoded on their own.
+fn root_list(data: &[u8]) -> ScanResult<Option<RootList>> {
+    let frame = parse_frame(data)?;
+    // Back-references point at containers anywhere in the frame
+    if frame.flags & FLAG_SHARED_REFS != 0 || frame.tag(frame.payload)? != 0x60 {
+        return Ok(None);
+    }
+    let items = frame.list_items(frame.payload)?;
+    let strings = frame.strings.iter().map(|s| s.to_string()).collect();
+    let fingerprint = frame.fingerprint;
+    Ok(Some(RootList {
+        strings,
+        items,
+        fingerprint,
+    }))
+}
+
+/// Decode `bytes`, returning a LazyList when the root is a list.
+pub(crate) fn decode_lazy(
+    py: Python,
+    bytes: &[u8],
+    decompress: bool,
+    options: DecodeOptions,
+) -> PyResult<PyObject> {
+    let data = py
+        .allow_threads(|| match decompress {
+            true => unpack(bytes).map(|(data, _)| data.into_owned()),
+            false => Ok(bytes.to_vec()),
+        })
+        .map_err(PyErr::new::<pyo3::exceptions::PyValueError, _>)?;
+    lazy_list(py, Source::Owned(data), None, options)
+}
+
+/// A LazyList over the frame in `source`, or its decoded value when the root
+/// is not a list. With `view`, a memoryview over the same bytes, bytes values
+/// come back as slices of it.
+pub(crate) fn lazy_list(
+    py: Python,
+    source: Source,
+    view: Option<PyObject>,
+    options: DecodeOptions,
+) -> PyResult<PyObject> {
+    let root = py
+        .allow_threads(|| root_list(&source))
+        .map_err(PyErr::new::<pyo3::exceptions::PyValueError, _>)?;
+    let Some(root) = root else {
+        return decode_frame(py, &source, view.as_ref().map(|v| v.as_ref(py)), options);
+    };
+    options.limits.check_keys(&root.strings)?;
+    check_fingerprint(root.fingerprint, options.fingerprint)?;
+    let cache = (0..root.items.len()).map(|_| None).collect();
+    let list = LazyList {
+        data: source,
+        view,
+        strings: root.strings,
+        items: root.items,
+        cache,
+        options,
+    };
+    Ok(Py::new(py, list)?.into_py(py))
+}
+
+/// Sequence over an encoded list that decodes elements on first access.
+#[allow(non_local_definitions)]
+#[pyclass(sequence)]
+pub struct LazyList {
+    data: Source,
+    // Memoryview over `data` that bytes values are sliced from
+    view: Option<PyObject>,
+    strings: Vec<String>,
+    /// Offset of every element in `data`.
+    items: Vec<usize>,
+    cache: Vec<Option<PyObject>>,
+    options: DecodeOptions,
+}
+
+impl LazyList {
+    /// Decode the uncached elements among `indices` with one parser.
+    fn load(&mut self, py: Python, indices: &[usize]) -> PyResult<()> {
+        let missing = indices
+            .iter()
+            .copied()
+            .filter(|&i| self.cache[i].is_none())
+            .collect::<Vec<_>>();
+        if missing.is_empty() {
+            return Ok(());
+        }
+        let view = self.view.as_ref().map(|view| view.as_ref(py));
+        let mut parser = BFastParser::new(py, &self.data, &self.strings, view, &self.options)?;
+        for index in missing {
+            parser.offset = self.items[index];
+            let mut value = parser.parse()?;
+            if let Some(def) = &self.options.schema {
+                if let Ok(record) = value.as_ref(py).downcast::<PyDict>() {
+                    value = schema::evolve_record(py, record, def)?.into();
+                }
+                if self.options.immutable {
+                    let proxy = py.import("types")?.getattr("MappingProxyType")?;
+                    value = freeze(py, value.as_ref(py), proxy)?;
+                }
+            }
+            self.cache[index] = Some(value);
+        }
+        Ok(())
+    }
+
+    fn get(&mut self, py: Python, index: usize) -> PyResult<PyObject> {
+        self.load(py, &[index])?;
+        Ok(self.cache[index].as_ref().unwrap().clone_ref(py))
+    }
+}
+
+#[allow(non_local_definitions)]
+#[pymethods]
+impl LazyList {
+    fn __len__(&self) -> usize {
+        self.items.len()
+    }
+
+    fn __getitem__(&mut self, py: Python, key: &PyAny) -> PyResult<PyObject> {
+        if let Ok(slice) = key.downcast::<PySlice>() {
+            let indices = slice.indices(self.items.len() as _)?;
+            let selected = (0..indices.slicelength)
+                .map(|n| (indices.start + n * indices.step) as usize)
+                .collect::<Vec<_>>();
+            self.load(py, &selected)?;
+            let values = selected
+                .iter()
+                .map(|&i| self.cache[i].as_ref().unwrap().clone_ref(py));
+            return Ok(PyList::new(py, values).into());
+        }
+        let index = key.extract::<isize>()?;
+        let len = self.items.len() as isize;
+        let resolved = if index < 0 { index + len } else { index };
+        if !(0..len).contains(&resolved) {
+            return Err(PyErr::new::<pyo3::exceptions::PyIndexError, _>(
+                "LazyList index out of range",
+            ));
+        }
+        self.get(py, resolved as usize)
+    }
+
+    fn __iter__(slf: PyRef<Self>) -> LazyListIter {
+        LazyListIter {
+            list: slf.into(),
+            index: 0,
+        }
+    }
+
+    /// Number of elements decoded so far.
+    #[getter]
+    fn decoded(&self) -> usize {
+        self.cache.iter().filter(|value| value.is_some()).count()
+    }
+
+    /// Decode every element into a regular list.
+    #[pyo3(name = "to_list")]
+    fn materialize(&mut self, py: Python) -> PyResult<PyObject> {
+        self.__getitem__(py, PySlice::full(py))
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "LazyList(len={}, decoded={})",
+            self.items.len(),
+            self.decoded()
+        )
+    }
+}
+
+#[allow(non_local_definitions)]
+#[pyclass]
+pub struct LazyListIter {
+    list: Py<LazyList>,
+    index: usize,
+}
+
+#[allow(non_local_definitions)]
+#[pymethods]
+impl LazyListIter {
+    fn __iter__(slf: PyRef<Self>) -> PyRef<Self> {
+        slf
+    }
+
+    fn __next__(&mut self, py: Python) -> PyResult<Option<PyObject>> {
+        let mut list = self.list.borrow_mut(py);
+        if self.index >= list.items.len() {
+            return Ok(None);
+        }
+        self.index += 1;
+        list.get(py, self.index - 1).map(Some)
+    }
+}
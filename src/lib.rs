@@ -1,23 +1,116 @@
 #![allow(non_local_definitions)]
 
 use ahash::{AHashMap, AHasher};
-use lz4_flex::compress_prepend_size;
 use numpy::PyReadonlyArrayDyn;
 use pyo3::prelude::*;
-use pyo3::types::{PyAny, PyBytes, PyDict, PyFrozenSet, PyList, PySet, PyString, PyTuple};
-use rayon::prelude::*;
+use pyo3::sync::GILOnceCell;
+use pyo3::types::{
+    PyAny, PyBool, PyByteArray, PyBytes, PyDict, PyDictItems, PyDictKeys, PyDictValues,
+    PyFrozenSet, PyList, PySet, PySlice, PyString, PyTuple, PyType,
+};
 use std::borrow::Cow;
+use std::collections::HashMap;
 use std::hash::{Hash, Hasher};
 use std::mem;
 use std::ptr;
-
+use std::sync::Arc;
+use xxhash_rust::xxh3::xxh3_64;
+
+mod background;
+#[cfg(feature = "redis")]
+mod cache;
+mod coerce;
+mod columnar;
+mod compression;
+mod container;
+mod diff;
+mod digest;
+mod dlpack;
+mod dump;
+mod duplicates;
 mod errors;
+mod expiry;
+mod explain;
+#[cfg(feature = "parquet")]
+mod export;
+mod ext;
+mod filter;
+mod flatten;
+mod hints;
+mod hooks;
+mod incremental;
+mod json;
+mod keys;
+mod lazy;
+mod memory;
+mod nonfinite;
+mod plan;
+mod prescan;
+mod protobuf;
+mod query;
+mod scan;
+mod schema;
+mod spec;
+mod splice;
+mod stats;
+mod temporal;
+mod trace;
+mod unions;
+mod validate;
 
 // Performance tuning constants
 const CACHE_LINE_SIZE: usize = 64;
-const PARALLEL_COMPRESSION_THRESHOLD: usize = 1_000_000;
 const INITIAL_BUFFER_SIZE: usize = 4096;
 const MAX_RECURSION_DEPTH: usize = 128;
+// Decode limits when none are passed: far more than regular frames use, far
+// less than a hostile header can declare
+const DEFAULT_MAX_KEYS: usize = 4096;
+const DEFAULT_MAX_KEY_LENGTH: usize = 128;
+const DEFAULT_MAX_DEPTH: usize = 64;
+const IO_CHUNK_SIZE: usize = 64 * 1024;
+// Records sampled to pick the value writer of each field in a batch
+const BATCH_SAMPLE_ROWS: usize = 16;
+
+// Header version byte; see spec.rs for the whole format. Version 2 added the
+// u32 length of the rest of the frame after the string count, so readers of
+// a stream know how many bytes to take; version 1 frames still decode.
+const FORMAT_VERSION: u8 = 0x02;
+const HEADER_SIZE: usize = 10;
+
+// Header flags (bit 1 is reserved for endianness by the spec)
+const FLAG_COMPRESSED: u8 = 0x01;
+const FLAG_SHARED_REFS: u8 = 0x04;
+// A u32 schema ID follows the header; see schema.rs
+const FLAG_SCHEMA: u8 = 0x08;
+// Objects may use the null-bitmap form (TAG_NULL_BITMAP)
+const FLAG_NULL_BITMAP: u8 = 0x10;
+// Lists of records may use the columnar layout (TAG_COLUMNAR)
+const FLAG_COLUMNAR: u8 = 0x20;
+// Lists of booleans may be bit-packed (TAG_BOOL_ARRAY)
+const FLAG_PACKED_BOOLS: u8 = 0x40;
+// A metadata section follows the header (and schema ID): [length (u32)] then
+// a complete uncompressed frame holding the metadata dict
+const FLAG_METADATA: u8 = 0x80;
+
+// Back-reference to an earlier container, by post-order index (u32)
+const TAG_REF: u8 = 0xA0;
+
+// Object whose leading fields have consecutive key IDs: [first key ID (u32)]
+// [field count (u16)][presence bitmap], the values of the present fields, then
+// regular ([key ID][value])* entries up to 0x7F. Absent fields decode as None.
+const TAG_NULL_BITMAP: u8 = 0x71;
+
+// List of records stored column by column; see columnar.rs
+const TAG_COLUMNAR: u8 = 0x61;
+
+// Dict whose keys are all integers: [count (u32)] then ([key][value])*, keys
+// written as integer values instead of string table IDs
+const TAG_INT_OBJECT: u8 = 0x72;
+
+// Dict of separately addressable sections, written by `sections=True`:
+// [count (u32)] then an index of ([key ID (u32)][value length (u32)])*, then
+// the values back to back
+const TAG_SECTIONS: u8 = 0x73;
 
 // Type tags with metadata preservation
 const TAG_DATETIME: u8 = 0xD1;
@@ -25,9 +118,50 @@ const TAG_DATE: u8 = 0xD2;
 const TAG_TIME: u8 = 0xD3;
 const TAG_UUID: u8 = 0xD4;
 const TAG_DECIMAL: u8 = 0xD5;
+const TAG_TIMEDELTA: u8 = 0xD6;
+// date as days since 1970-01-01 (i32) and naive time as nanoseconds since
+// midnight (u64); see temporal.rs
+const TAG_DATE_DAYS: u8 = 0xD7;
+const TAG_TIME_NANOS: u8 = 0xD8;
+
+// Typed n-dimensional array: dtype code/bits, shape, raw little-endian data
+const TAG_TENSOR: u8 = 0x91;
+
+// List of booleans: [count (u32)] then 8 per byte, least significant bit first
+const TAG_BOOL_ARRAY: u8 = 0x92;
+
+// array.array: [type code (b/B/h/H/i/I/q/Q/f/d)][byte length (u32)] then the
+// little-endian items. Codes have fixed widths (1/2/4/8 bytes, 'l' is mapped
+// by item size) so frames decode the same on every platform.
+const TAG_TYPED_ARRAY: u8 = 0x93;
+
+// range: start, stop and step as integer values
+const TAG_RANGE: u8 = 0x94;
+
+// str or bytes value kept outside the frame by the encoder's blob_store:
+// [token length (u32)] then the UTF-8 token its blob_resolver looks up
+const TAG_BLOB_REF: u8 = 0x95;
+
+// Object serialized through __bfast__ or __getstate__: [name length (u32)]
+// ["module.qualname"] then the value the method returned
+const TAG_CUSTOM: u8 = 0xD9;
+
+// Value of a registered extension type: [ext ID (u8)][length (u32)] then the
+// bytes its encoder returned; 0xE1-0xEF are reserved for extensions too
+const TAG_EXT: u8 = 0xE0;
+
+// Fingerprint of the record layout of a list written by the batch path
+// (field names and value types, u64), in front of the root list it describes
+const TAG_FINGERPRINT: u8 = 0x62;
+
+// Written in place of Pydantic secrets, as model_dump(mode="json") does
+const SECRET_MASK: &str = "**********";
+
+// Writes one value into the encoder's buffer
+type ValueWriter = fn(&mut BFast, &PyAny) -> PyResult<()>;
 
 #[allow(non_local_definitions)]
-#[pyclass]
+#[pyclass(subclass)]
 pub struct BFast {
     string_table: AHashMap<String, u32>,
     next_id: u32,
@@ -35,13 +169,539 @@ pub struct BFast {
     key_cache: [Option<(u32, u32)>; 64],
     cache_index: usize,
     recursion_depth: usize,
+    // Structural sharing: (start, end) of every container written, in post-order
+    dedup: bool,
+    dedup_spans: Vec<(usize, usize)>,
+    // Sorted dict keys and set elements, so equal values encode identically
+    canonical: bool,
+    // Registered schema whose fields take the first string table IDs
+    schema: Option<Arc<schema::SchemaDef>>,
+    // Check records against the schema before writing them; see validate.rs
+    validate: bool,
+    // Mark None fields in a per-record bitmap instead of writing them
+    null_bitmap: bool,
+    // Write lists of uniform records column by column
+    columnar: bool,
+    // Write the fields of nested models as dotted record fields; see flatten.rs
+    flatten: bool,
+    // Add a per-column pruning index to the statistics; see stats.rs
+    column_stats: bool,
+    // Bit-pack lists of booleans
+    pack_bools: bool,
+    // Add @computed_field values of Pydantic models after their stored fields
+    computed_fields: bool,
+    // Write the value of Pydantic secrets instead of their masked form
+    reveal_secrets: bool,
+    // Write Decimals as floats when that loses no precision
+    decimal_as_float: bool,
+    // Write datetimes as epoch nanoseconds instead of ISO strings
+    datetime_nanos: bool,
+    // Write floats as f32 (TAG_FLOAT32) when that keeps them finite
+    float32: bool,
+    // Lists longer than this try the per-class record batch path
+    batch_threshold: usize,
+    // Compute the CRC32 of each frame while it is assembled
+    checksum: bool,
+    // CRC32 of the last frame, when `checksum` is set
+    frame_crc: Option<u32>,
+    // Called with str and bytes values over `blob_threshold` bytes; the token
+    // it returns is written in their place (TAG_BLOB_REF)
+    blob_store: Option<PyObject>,
+    blob_threshold: usize,
+    // Codec of frames encoded with compress=True
+    codec: Arc<dyn compression::Codec>,
+    // Largest work buffer an encode may need; see memory.rs
+    max_buffer_bytes: Option<usize>,
+    // Keep at most this many times the average frame size between calls
+    shrink_factor: Option<usize>,
+    // Rolling average size of the frames written (before compression)
+    average_frame: usize,
+    // Resolve dict keys that write the same text; see duplicates.rs
+    duplicate_keys: Option<duplicates::DuplicateKeys>,
+    // What NaN and infinite floats are written as; see nonfinite.rs
+    non_finite: nonfinite::NonFinite,
+    // The class is a subclass defining encoding hooks; see hooks.rs
+    hooked: bool,
+    // Those hooks, bound to the instance during an encode call
+    hooks: Option<hooks::Hooks>,
+    // Key objects of the frames decode_packed has read; see keys.rs
+    decoded_keys: Arc<keys::KeyCache>,
 }
 
 #[allow(non_local_definitions)]
 #[pymethods]
 impl BFast {
     #[new]
-    fn new() -> Self {
+    #[classmethod]
+    #[pyo3(signature = (
+        *,
+        dedup = false,
+        schema = None,
+        validate = false,
+        null_bitmap = false,
+        columnar = false,
+        flatten = false,
+        column_stats = false,
+        pack_bools = false,
+        computed_fields = false,
+        reveal_secrets = false,
+        decimal_as_float = false,
+        datetime_nanos = false,
+        float32 = false,
+        batch_threshold = 8,
+        blob_store = None,
+        blob_threshold = 65_536,
+        compression = "lz4",
+        compression_level = None,
+        parallel_threshold = 1_000_000,
+        chunk_size = 262_144,
+        max_buffer_bytes = None,
+        shrink_factor = Some(8),
+        duplicate_keys = None,
+        non_finite = "allow"
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    fn py_new(
+        cls: &PyType,
+        dedup: bool,
+        schema: Option<PyRef<schema::BFastSchema>>,
+        validate: bool,
+        null_bitmap: bool,
+        columnar: bool,
+        flatten: bool,
+        column_stats: bool,
+        pack_bools: bool,
+        computed_fields: bool,
+        reveal_secrets: bool,
+        decimal_as_float: bool,
+        datetime_nanos: bool,
+        float32: bool,
+        batch_threshold: usize,
+        blob_store: Option<PyObject>,
+        blob_threshold: usize,
+        compression: &str,
+        compression_level: Option<i32>,
+        parallel_threshold: usize,
+        chunk_size: usize,
+        max_buffer_bytes: Option<usize>,
+        shrink_factor: Option<usize>,
+        duplicate_keys: Option<&str>,
+        non_finite: &str,
+    ) -> PyResult<Self> {
+        if shrink_factor == Some(0) {
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+                "shrink_factor must be at least 1",
+            ));
+        }
+        if chunk_size == 0 {
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+                "chunk_size must be at least 1",
+            ));
+        }
+        if validate && schema.is_none() {
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+                "validate requires a schema",
+            ));
+        }
+        if flatten && schema.is_none() && !columnar {
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+                "flatten requires a schema or columnar=True",
+            ));
+        }
+        if column_stats && !columnar {
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+                "column_stats requires columnar=True",
+            ));
+        }
+        let chunking = compression::Chunking {
+            threshold: parallel_threshold,
+            chunk_size,
+        };
+        let codec = compression::codec(compression, compression_level, chunking)
+            .map_err(PyErr::new::<pyo3::exceptions::PyValueError, _>)?;
+        let mut encoder = BFast {
+            dedup,
+            validate,
+            null_bitmap,
+            columnar,
+            flatten,
+            column_stats,
+            pack_bools,
+            computed_fields,
+            reveal_secrets,
+            decimal_as_float,
+            datetime_nanos,
+            float32,
+            batch_threshold,
+            blob_store,
+            blob_threshold,
+            codec,
+            max_buffer_bytes,
+            shrink_factor,
+            duplicate_keys: duplicates::policy(duplicate_keys)?,
+            non_finite: nonfinite::policy(non_finite)?,
+            hooked: hooks::defines_hooks(cls)?,
+            ..BFast::new()
+        };
+        if let Some(schema) = schema {
+            schema::register(&schema.def)?;
+            for (id, field) in schema.def.fields.iter().enumerate() {
+                encoder.string_table.insert(field.to_string(), id as u32);
+            }
+            encoder.next_id = schema.def.fields.len() as u32;
+            encoder.schema = Some(schema.def.clone());
+        }
+        Ok(encoder)
+    }
+
+    #[pyo3(signature = (
+        obj,
+        compress,
+        *,
+        metadata = None,
+        stats = None,
+        ttl = None,
+        sections = false,
+        release_gil = false
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    pub fn encode_packed(
+        slf: PyRefMut<'_, Self>,
+        obj: &PyAny,
+        compress: bool,
+        metadata: Option<&PyDict>,
+        stats: Option<Vec<String>>,
+        ttl: Option<f64>,
+        sections: bool,
+        release_gil: bool,
+    ) -> PyResult<PyObject> {
+        let metadata = expiry::with_ttl(obj.py(), metadata, ttl)?;
+        let final_data = hooks::with_hooks(slf, |encoder| {
+            if release_gil && !sections && stats.is_none() {
+                if let Some(frame) = encoder.encode_released(obj, compress, metadata)? {
+                    return Ok(frame);
+                }
+            }
+            encoder.encode_frame(obj, compress, metadata, stats.as_deref(), sections)
+        })?;
+        let span = trace::span("pybytes");
+        let bytes = PyBytes::new(obj.py(), &final_data);
+        span.finish(obj.py(), final_data.len())?;
+        Ok(bytes.into())
+    }
+
+    /// Encode a list of records as frames of at most `max_frame_bytes` each
+    /// (before compression), for transports with a message size limit.
+    #[pyo3(signature = (records, max_frame_bytes = 1_048_576, *, compress = false))]
+    pub fn encode_packed_split(
+        slf: PyRefMut<'_, Self>,
+        py: Python,
+        records: &PyAny,
+        max_frame_bytes: usize,
+        compress: bool,
+    ) -> PyResult<Vec<PyObject>> {
+        let frames = hooks::with_hooks(slf, |encoder| {
+            encoder.encode_split(py, records, max_frame_bytes, compress)
+        })?;
+        Ok(frames
+            .iter()
+            .map(|frame| PyBytes::new(py, frame).into())
+            .collect())
+    }
+
+    /// Serialize now and compress on the rayon pool, returning a handle.
+    #[pyo3(signature = (
+        obj,
+        *,
+        compress = true,
+        metadata = None,
+        stats = None,
+        ttl = None,
+        sections = false
+    ))]
+    pub fn encode_deferred(
+        slf: PyRefMut<'_, Self>,
+        obj: &PyAny,
+        compress: bool,
+        metadata: Option<&PyDict>,
+        stats: Option<Vec<String>>,
+        ttl: Option<f64>,
+        sections: bool,
+    ) -> PyResult<background::PendingFrame> {
+        let metadata = expiry::with_ttl(obj.py(), metadata, ttl)?;
+        hooks::with_hooks(slf, |encoder| {
+            encoder.write_frame(obj, metadata, stats.as_deref(), sections)?;
+            let frame = mem::take(&mut encoder.work_buffer);
+            Ok(background::PendingFrame::spawn(
+                frame,
+                compress,
+                Arc::clone(&encoder.codec),
+            ))
+        })
+    }
+
+    /// Encode a dict of equal-length columns (NumPy arrays or sequences) as a
+    /// columnar list of records, without building the rows.
+    #[pyo3(signature = (columns, *, compress = false))]
+    pub fn encode_columns(&mut self, columns: &PyAny, compress: bool) -> PyResult<PyObject> {
+        let py = columns.py();
+        let columns = columns.downcast::<PyDict>().map_err(|_| {
+            PyErr::new::<pyo3::exceptions::PyTypeError, _>(format!(
+                "encode_columns expects a dict of columns, got {}",
+                columns.get_type().name().unwrap_or("?")
+            ))
+        })?;
+        self.write_frame_with(py, None, FLAG_COLUMNAR, |encoder, _| {
+            encoder.serialize_columns(columns)
+        })?;
+        let frame = self.take_frame(py, compress)?;
+        Ok(PyBytes::new(py, &frame).into())
+    }
+
+    /// Decode a columnar list of records, or one written by the batch path,
+    /// into a dict of columns: NumPy arrays for int, float and bool columns
+    /// with `arrays`, lists otherwise (`LazyColumn`s for int and float columns
+    /// with `lazy`).
+    #[pyo3(signature = (
+        bytes,
+        *,
+        decompress = true,
+        arrays = true,
+        lazy = false,
+        max_keys = DEFAULT_MAX_KEYS,
+        max_key_length = DEFAULT_MAX_KEY_LENGTH,
+        max_depth = DEFAULT_MAX_DEPTH
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    pub fn decode_columns(
+        &self,
+        py: Python,
+        bytes: &[u8],
+        decompress: bool,
+        arrays: bool,
+        lazy: bool,
+        max_keys: usize,
+        max_key_length: usize,
+        max_depth: usize,
+    ) -> PyResult<PyObject> {
+        let limits = DecodeLimits::new(max_keys, max_key_length, max_depth)?;
+        let data = if decompress {
+            decompress_packed(bytes).map_err(PyErr::new::<pyo3::exceptions::PyValueError, _>)?
+        } else {
+            Cow::Borrowed(bytes)
+        };
+        columnar::decode_columns(py, &data, arrays, lazy, limits)
+    }
+
+    /// Encode into any object with a `write()` method, in fixed-size chunks.
+    #[pyo3(signature = (
+        obj,
+        fp,
+        *,
+        compress = false,
+        metadata = None,
+        stats = None,
+        ttl = None,
+        sections = false
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    pub fn encode_to(
+        slf: PyRefMut<'_, Self>,
+        obj: &PyAny,
+        fp: &PyAny,
+        compress: bool,
+        metadata: Option<&PyDict>,
+        stats: Option<Vec<String>>,
+        ttl: Option<f64>,
+        sections: bool,
+    ) -> PyResult<usize> {
+        let metadata = expiry::with_ttl(obj.py(), metadata, ttl)?;
+        let final_data = hooks::with_hooks(slf, |encoder| {
+            encoder.encode_frame(obj, compress, metadata, stats.as_deref(), sections)
+        })?;
+        write_chunked(fp, &final_data)?;
+        Ok(final_data.len())
+    }
+
+    #[pyo3(signature = (
+        bytes,
+        *,
+        decompress = true,
+        shared_refs = false,
+        schema = None,
+        lazy = false,
+        immutable = false,
+        allowed_classes = None,
+        ranges_as_lists = false,
+        blob_resolver = None,
+        fingerprint = None,
+        max_keys = DEFAULT_MAX_KEYS,
+        max_key_length = DEFAULT_MAX_KEY_LENGTH,
+        max_depth = DEFAULT_MAX_DEPTH,
+        parse_float = "float",
+        duplicate_keys = None,
+        aliases = None
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    pub fn decode_packed(
+        &self,
+        py: Python,
+        bytes: &[u8],
+        decompress: bool,
+        shared_refs: bool,
+        schema: Option<PyRef<schema::BFastSchema>>,
+        lazy: bool,
+        immutable: bool,
+        allowed_classes: Option<&PyAny>,
+        ranges_as_lists: bool,
+        blob_resolver: Option<PyObject>,
+        fingerprint: Option<u64>,
+        max_keys: usize,
+        max_key_length: usize,
+        max_depth: usize,
+        parse_float: &str,
+        duplicate_keys: Option<&str>,
+        aliases: Option<HashMap<String, String>>,
+    ) -> PyResult<PyObject> {
+        let limits = DecodeLimits::new(max_keys, max_key_length, max_depth)?;
+        let decimal_floats = match parse_float {
+            "float" => false,
+            "decimal" => true,
+            _ => {
+                return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+                    "parse_float must be \"float\" or \"decimal\"",
+                ))
+            }
+        };
+        let options = DecodeOptions {
+            shared_refs,
+            schema: schema.map(|schema| schema.def.clone()),
+            immutable,
+            allowed_classes: allowed_classes.map(class_allowlist).transpose()?,
+            ranges_as_lists,
+            blob_resolver,
+            limits,
+            fingerprint,
+            key_cache: Some(self.decoded_keys.clone()),
+            decimal_floats,
+            duplicate_keys: duplicates::policy(duplicate_keys)?,
+            aliases: aliases.map(Arc::new),
+        };
+        if lazy {
+            return lazy::decode_lazy(py, bytes, decompress, options);
+        }
+        decode_bytes(py, bytes, decompress, options)
+    }
+
+    /// Decode a file through a read-only memory map. A list comes back as a
+    /// LazyList over the map that decodes elements on first access. Unless
+    /// `copy` is set, bytes values are memoryviews into the map.
+    #[pyo3(signature = (
+        path,
+        *,
+        copy = false,
+        max_keys = DEFAULT_MAX_KEYS,
+        max_key_length = DEFAULT_MAX_KEY_LENGTH,
+        max_depth = DEFAULT_MAX_DEPTH
+    ))]
+    pub fn decode_mmap(
+        &self,
+        py: Python,
+        path: &PyAny,
+        copy: bool,
+        max_keys: usize,
+        max_key_length: usize,
+        max_depth: usize,
+    ) -> PyResult<PyObject> {
+        let options =
+            DecodeOptions::limited(DecodeLimits::new(max_keys, max_key_length, max_depth)?);
+        let path_str = py
+            .import("os")?
+            .call_method1("fspath", (path,))?
+            .extract::<String>()?;
+        let file = std::fs::File::open(&path_str)?;
+        if file.metadata()?.len() == 0 {
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+                "Cannot decode an empty file",
+            ));
+        }
+        let map = unsafe { memmap2::Mmap::map(&file)? };
+
+        // Compressed frames have to be inflated, so there is nothing to view
+        if map.len() < 2 || &map[0..2] != b"BF" || compression::is_packed(&map) {
+            return lazy::decode_lazy(py, &map, true, options);
+        }
+        if copy {
+            return lazy::lazy_list(py, lazy::Source::Mapped(map), None, options);
+        }
+
+        // Map the file a second time from Python so views keep it alive
+        let mmap_module = py.import("mmap")?;
+        let fp = py
+            .import("builtins")?
+            .call_method1("open", (path_str.as_str(), "rb"))?;
+        let kwargs = PyDict::new(py);
+        kwargs.set_item("access", mmap_module.getattr("ACCESS_READ")?)?;
+        let py_map = mmap_module
+            .getattr("mmap")?
+            .call((fp.call_method0("fileno")?, 0), Some(kwargs));
+        fp.call_method0("close")?;
+        let view = py
+            .import("builtins")?
+            .getattr("memoryview")?
+            .call1((py_map?,))?;
+
+        lazy::lazy_list(py, lazy::Source::Mapped(map), Some(view.into()), options)
+    }
+
+    /// Decode from any object with `readinto()` or `read()` (files, sockets, gzip).
+    #[pyo3(signature = (
+        fp,
+        *,
+        decompress = true,
+        max_keys = DEFAULT_MAX_KEYS,
+        max_key_length = DEFAULT_MAX_KEY_LENGTH,
+        max_depth = DEFAULT_MAX_DEPTH
+    ))]
+    pub fn decode_from(
+        &self,
+        py: Python,
+        fp: &PyAny,
+        decompress: bool,
+        max_keys: usize,
+        max_key_length: usize,
+        max_depth: usize,
+    ) -> PyResult<PyObject> {
+        let options =
+            DecodeOptions::limited(DecodeLimits::new(max_keys, max_key_length, max_depth)?);
+        let mut data = Vec::new();
+        if decompress && fp.hasattr("read")? {
+            // LZ4 frames are inflated as they are read, without holding the
+            // whole compressed stream first
+            data = compression::read_head(fp)?;
+            if let Some(start) = compression::lz4_frame_start(&data) {
+                let frame = compression::read_lz4_frame(fp, &data, start)?;
+                return decode_bytes(py, &frame, false, options);
+            }
+        }
+        data.extend_from_slice(&read_chunked(py, fp)?);
+        decode_bytes(py, &data, decompress, options)
+    }
+
+    /// Bytes the encoder holds between calls, per part and in total.
+    pub fn memory_usage<'py>(&self, py: Python<'py>) -> PyResult<&'py PyDict> {
+        self.memory_report(py)
+    }
+
+    /// Give back buffer capacity left over from earlier payloads; returns
+    /// the number of bytes released.
+    pub fn shrink_to_fit(&mut self) -> usize {
+        self.release_memory()
+    }
+}
+
+impl BFast {
+    pub(crate) fn new() -> Self {
         BFast {
             string_table: AHashMap::with_capacity(1024),
             next_id: 0,
@@ -49,12 +709,100 @@ impl BFast {
             key_cache: [None; 64],
             cache_index: 0,
             recursion_depth: 0,
+            dedup: false,
+            dedup_spans: Vec::new(),
+            canonical: false,
+            schema: None,
+            validate: false,
+            null_bitmap: false,
+            columnar: false,
+            flatten: false,
+            column_stats: false,
+            pack_bools: false,
+            computed_fields: false,
+            reveal_secrets: false,
+            decimal_as_float: false,
+            datetime_nanos: false,
+            float32: false,
+            batch_threshold: 8,
+            checksum: false,
+            frame_crc: None,
+            blob_store: None,
+            blob_threshold: 0,
+            codec: Arc::new(compression::Lz4::default()),
+            max_buffer_bytes: None,
+            shrink_factor: Some(8),
+            average_frame: 0,
+            duplicate_keys: None,
+            non_finite: nonfinite::NonFinite::Allow,
+            hooked: false,
+            hooks: None,
+            decoded_keys: Arc::default(),
         }
     }
 
-    pub fn encode_packed(&mut self, obj: &PyAny, compress: bool) -> PyResult<PyObject> {
-        self.work_buffer.clear();
-        self.recursion_depth = 0;
+    fn encode_to_vec(&mut self, obj: &PyAny, compress: bool) -> PyResult<Vec<u8>> {
+        self.encode_frame(obj, compress, None, None, false)
+    }
+
+    /// Like `encode_to_vec`, with an optional metadata section in the header
+    /// (holding the statistics of `stats` fields too, see stats.rs) and, with
+    /// `sections`, the dict `obj` written as named sections.
+    fn encode_frame(
+        &mut self,
+        obj: &PyAny,
+        compress: bool,
+        metadata: Option<&PyDict>,
+        stats: Option<&[String]>,
+        sections: bool,
+    ) -> PyResult<Vec<u8>> {
+        self.write_frame(obj, metadata, stats, sections)?;
+        self.take_frame(obj.py(), compress)
+    }
+
+    /// The frame in `work_buffer`, compressed when asked and worth it.
+    fn take_frame(&mut self, py: Python, compress: bool) -> PyResult<Vec<u8>> {
+        self.settle_buffer(self.work_buffer.len());
+        if !(compress && self.work_buffer.len() > 256) {
+            return Ok(mem::take(&mut self.work_buffer));
+        }
+        let span = trace::span("compress");
+        let compressed = self.compress_frame()?;
+        span.finish(py, compressed.len())?;
+        self.release_frame();
+        Ok(compressed)
+    }
+
+    /// The frame in `work_buffer`, compressed with the encoder's codec, or
+    /// taken as it is when that does not make it smaller.
+    fn compress_frame(&mut self) -> PyResult<Vec<u8>> {
+        let packed = compression::compress_or_store(&*self.codec, &mut self.work_buffer)
+            .map_err(PyErr::new::<pyo3::exceptions::PyValueError, _>)?;
+        if self.checksum {
+            // Compression writes new bytes, so the output is hashed
+            self.frame_crc = Some(crc32fast::hash(&packed));
+        }
+        Ok(packed)
+    }
+
+    /// Write the uncompressed frame into `work_buffer`.
+    fn write_frame(
+        &mut self,
+        obj: &PyAny,
+        metadata: Option<&PyDict>,
+        stats: Option<&[String]>,
+        sections: bool,
+    ) -> PyResult<()> {
+        let obj = self.flatten_root(obj)?;
+        let obj = self.coerce_root(obj)?;
+        self.validate_root(obj)?;
+        // The metadata gets a string table of its own, so it can be read
+        // without the payload's
+        let columns = match self.column_stats {
+            true => Some(self.column_stats(obj)?),
+            false => None,
+        };
+        let metadata = stats::metadata_section(obj, metadata, stats, columns)?;
 
         // CACHE-ALIGNED pre-allocation
         let estimated_size = if let Ok(list) = obj.downcast::<PyList>() {
@@ -64,177 +812,206 @@ impl BFast {
             8192
         };
 
+        let estimated_size = estimated_size.min(self.max_buffer_bytes.unwrap_or(usize::MAX));
         if self.work_buffer.capacity() < estimated_size {
             self.work_buffer.reserve(estimated_size);
         }
 
-        // Reserve space for header
-        let header_pos = self.work_buffer.len();
-        self.work_buffer.extend_from_slice(&[0u8; 6]);
+        self.write_frame_with(obj.py(), metadata, 0, |encoder, string_table_pos| {
+            if sections {
+                encoder.serialize_sections(obj)
+            } else {
+                encoder.serialize_root(obj, true)?;
+                if encoder.dedup {
+                    encoder.apply_dedup(string_table_pos);
+                }
+                Ok(())
+            }
+        })
+    }
 
-        // Write string table placeholder (will be filled later)
-        let string_table_pos = self.work_buffer.len();
+    /// Write into `work_buffer` the frame around the payload `write` appends
+    /// (it gets the payload's start offset), with `flags` added to the
+    /// header's.
+    fn write_frame_with(
+        &mut self,
+        py: Python,
+        metadata: Option<Vec<u8>>,
+        flags: u8,
+        write: impl FnOnce(&mut Self, usize) -> PyResult<()>,
+    ) -> PyResult<()> {
+        let string_table_pos = self.begin_frame();
+
+        let span = trace::span("serialize");
+        let written = write(self, string_table_pos);
+        self.bound_buffer(written)?;
+        span.finish(py, self.work_buffer.len() - string_table_pos)?;
+
+        let span = trace::span("string_table");
+        let finished = self
+            .finish_frame(string_table_pos, metadata, flags)
+            .and_then(|_| self.check_buffer_limit(0));
+        self.bound_buffer(finished)?;
+        span.finish(py, self.work_buffer.len())
+    }
 
-        // SIMD batch processing for lists
-        if let Ok(list) = obj.downcast::<PyList>() {
-            if list.len() > 8 {
-                if let Ok(()) = self.serialize_pydantic_simd_batch(list) {
-                    // Insert string table after header, before payload
-                    let payload = self.work_buffer.split_off(string_table_pos);
-                    self.write_string_table_vectorized()?;
-                    self.work_buffer.extend_from_slice(&payload);
-                    self.write_header_simd(header_pos, compress);
-
-                    let final_data = if compress && self.work_buffer.len() > 256 {
-                        if self.work_buffer.len() >= PARALLEL_COMPRESSION_THRESHOLD {
-                            self.compress_parallel()
-                        } else {
-                            compress_prepend_size(&self.work_buffer)
-                        }
-                    } else {
-                        mem::take(&mut self.work_buffer)
-                    };
+    /// Clear `work_buffer` for a new frame and reserve its header; returns
+    /// where the payload starts.
+    fn begin_frame(&mut self) -> usize {
+        self.work_buffer.clear();
+        self.recursion_depth = 0;
+        self.dedup_spans.clear();
+        self.frame_crc = None;
 
-                    return Ok(PyBytes::new(obj.py(), &final_data).into());
-                }
-            }
-        }
+        // Reserve space for header
+        self.work_buffer.extend_from_slice(&[0u8; HEADER_SIZE]);
 
-        self.serialize_any_optimized(obj)?;
+        // Write string table placeholder (will be filled later)
+        self.work_buffer.len()
+    }
+
+    /// Complete the frame whose payload starts at `string_table_pos`: insert
+    /// the schema ID, metadata and string table in front of it and fill in
+    /// the header.
+    fn finish_frame(
+        &mut self,
+        string_table_pos: usize,
+        metadata: Option<Vec<u8>>,
+        flags: u8,
+    ) -> PyResult<()> {
+        let header_pos = 0;
 
         // Insert string table after header, before payload
         let payload = self.work_buffer.split_off(string_table_pos);
+        if let Some(schema) = &self.schema {
+            self.work_buffer.extend_from_slice(&schema.id.to_le_bytes());
+        }
+        if let Some(metadata) = &metadata {
+            self.work_buffer
+                .extend_from_slice(&(metadata.len() as u32).to_le_bytes());
+            self.work_buffer.extend_from_slice(metadata);
+        }
         self.write_string_table_vectorized()?;
-        self.work_buffer.extend_from_slice(&payload);
-        self.write_header_simd(header_pos, compress);
-
-        let final_data = if compress && self.work_buffer.len() > 256 {
-            if self.work_buffer.len() >= PARALLEL_COMPRESSION_THRESHOLD {
-                self.compress_parallel()
-            } else {
-                compress_prepend_size(&self.work_buffer)
+        let head_len = self.work_buffer.len();
+        let mut payload_crc = self.checksum.then(crc32fast::Hasher::new);
+        match &mut payload_crc {
+            // Hashed while it moves back behind the string table, so the
+            // checksum takes no extra pass over the payload
+            Some(hasher) => {
+                for chunk in payload.chunks(IO_CHUNK_SIZE) {
+                    hasher.update(chunk);
+                    self.work_buffer.extend_from_slice(chunk);
+                }
             }
-        } else {
-            mem::take(&mut self.work_buffer)
-        };
-
-        Ok(PyBytes::new(obj.py(), &final_data).into())
+            None => self.work_buffer.extend_from_slice(&payload),
+        }
+        self.write_header_simd(header_pos)?;
+        if metadata.is_some() {
+            self.work_buffer[header_pos + 2] |= FLAG_METADATA;
+        }
+        self.work_buffer[header_pos + 2] |= flags;
+        if let Some(payload_crc) = payload_crc {
+            let mut crc = crc32fast::Hasher::new();
+            crc.update(&self.work_buffer[..head_len]);
+            crc.combine(&payload_crc);
+            self.frame_crc = Some(crc.finalize());
+        }
+        Ok(())
     }
 
-    #[pyo3(signature = (bytes, *, decompress = true))]
-    pub fn decode_packed(&self, py: Python, bytes: &[u8], decompress: bool) -> PyResult<PyObject> {
-        let decompressed_data = if decompress {
-            decompress_packed(bytes)
-                .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(e))?
-        } else {
-            Cow::Borrowed(bytes)
-        };
-
-        if decompressed_data.len() < 6 {
-            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
-                "Decompressed buffer too small for B-FAST header",
-            ));
+    /// Write a payload value, lists through the batch path when they can,
+    /// after the fingerprint of their records when `fingerprint` is set.
+    fn serialize_root(&mut self, obj: &PyAny, fingerprint: bool) -> PyResult<()> {
+        // SIMD batch processing for lists (records are not numbered for dedup
+        // and keep field order, which canonical mode cannot)
+        if let Ok(list) = obj.downcast::<PyList>() {
+            if !self.dedup
+                && !self.canonical
+                && !self.columnar
+                && self.blob_store.is_none()
+                && !self.hooks_bound()
+            {
+                // A list the batch path cannot take is written again from
+                // scratch, without the keys the attempt added
+                let start = self.work_buffer.len();
+                let next_id = self.next_id;
+                if list.len() > self.batch_threshold {
+                    if self
+                        .serialize_pydantic_simd_batch(list, fingerprint)
+                        .is_ok()
+                    {
+                        return Ok(());
+                    }
+                    self.rewind(start, next_id);
+                }
+                // Tagged unions at any length, so records keep their class
+                if list.len() > 1 {
+                    if self.serialize_union_batch(list).is_ok() {
+                        return Ok(());
+                    }
+                    self.rewind(start, next_id);
+                }
+            }
         }
+        self.serialize_any_optimized(obj)
+    }
 
-        let magic = &decompressed_data[0..2];
-        if magic != b"BF" {
+    /// Write the entries of a dict as sections (TAG_SECTIONS), each value
+    /// encoded like a payload of its own.
+    fn serialize_sections(&mut self, obj: &PyAny) -> PyResult<()> {
+        let dict = obj.downcast::<PyDict>().map_err(|_| {
+            PyErr::new::<pyo3::exceptions::PyTypeError, _>(
+                "sections=True requires a dict of named objects",
+            )
+        })?;
+        // Shared containers would point across section boundaries
+        if self.dedup {
             return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
-                "Invalid B-FAST magic number",
+                "sections=True cannot be combined with dedup",
             ));
         }
-
-        let string_table_count =
-            u16::from_le_bytes(decompressed_data[4..6].try_into().unwrap()) as usize;
-
-        let mut offset = 6;
-        let mut string_table = Vec::with_capacity(string_table_count);
-        for _ in 0..string_table_count {
-            if offset >= decompressed_data.len() {
-                return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
-                    "Unexpected end of buffer in string table",
-                ));
-            }
-            let length = decompressed_data[offset] as usize;
-            offset += 1;
-            if offset + length > decompressed_data.len() {
-                return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
-                    "String extends beyond buffer in string table",
-                ));
-            }
-            let string_bytes = &decompressed_data[offset..offset + length];
-            let string_val = std::str::from_utf8(string_bytes)
-                .map_err(|e| {
-                    PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
-                        "Invalid UTF-8 in string table: {}",
-                        e
-                    ))
-                })?
-                .to_string();
-            string_table.push(string_val);
-            offset += length;
+        let entries = self.object_entries(dict)?;
+        self.work_buffer.push(TAG_SECTIONS);
+        self.work_buffer
+            .extend_from_slice(&(entries.len() as u32).to_le_bytes());
+        let index = self.work_buffer.len();
+        self.work_buffer.resize(index + entries.len() * 8, 0);
+        for (i, (id, value)) in entries.into_iter().enumerate() {
+            let start = self.work_buffer.len();
+            self.serialize_root(value, false)?;
+            let length = u32::try_from(self.work_buffer.len() - start).map_err(|_| {
+                PyErr::new::<pyo3::exceptions::PyOverflowError, _>("Section larger than 4 GiB")
+            })?;
+            let entry = index + i * 8;
+            self.work_buffer[entry..entry + 4].copy_from_slice(&id.to_le_bytes());
+            self.work_buffer[entry + 4..entry + 8].copy_from_slice(&length.to_le_bytes());
         }
-
-        let datetime_module = py.import("datetime")?;
-        let datetime_class = datetime_module.getattr("datetime")?;
-        let date_class = datetime_module.getattr("date")?;
-        let time_class = datetime_module.getattr("time")?;
-
-        let uuid_module = py.import("uuid")?;
-        let uuid_class = uuid_module.getattr("UUID")?;
-
-        let decimal_module = py.import("decimal")?;
-        let decimal_class = decimal_module.getattr("Decimal")?;
-
-        let mut parser = BFastParser {
-            py,
-            data: &decompressed_data,
-            offset,
-            string_table: &string_table,
-            datetime_class,
-            date_class,
-            time_class,
-            uuid_class,
-            decimal_class,
-            recursion_depth: 0,
-        };
-
-        parser.parse()
+        Ok(())
     }
-}
-
-impl BFast {
-    fn compress_parallel(&self) -> Vec<u8> {
-        const CHUNK_SIZE: usize = 256 * 1024;
-
-        let data = &self.work_buffer;
-        let total_size = data.len();
-
-        if total_size < CHUNK_SIZE * 2 {
-            return compress_prepend_size(data);
-        }
-
-        let chunks: Vec<Vec<u8>> = data
-            .par_chunks(CHUNK_SIZE)
-            .map(|chunk| compress_prepend_size(chunk))
-            .collect();
 
-        let mut result = Vec::with_capacity(total_size / 2);
-        result.extend_from_slice(&(total_size as u32).to_le_bytes());
-        result.extend_from_slice(&(chunks.len() as u32).to_le_bytes());
-
-        for chunk in &chunks {
-            result.extend_from_slice(&(chunk.len() as u32).to_le_bytes());
-            result.extend_from_slice(chunk);
+    /// Drop everything written after `len` and the string table entries
+    /// created since the table held `next_id` strings.
+    fn rewind(&mut self, len: usize, next_id: u32) {
+        self.work_buffer.truncate(len);
+        self.recursion_depth = 0;
+        if self.next_id > next_id {
+            self.string_table.retain(|_, id| *id < next_id);
+            for entry in self.key_cache.iter_mut() {
+                if matches!(entry, Some((_, id)) if *id >= next_id) {
+                    *entry = None;
+                }
+            }
+            self.next_id = next_id;
         }
-
-        result
     }
 
     #[inline(always)]
     fn ensure_buffer_capacity(&mut self, additional: usize) {
         let required = self.work_buffer.len() + additional;
         if required > self.work_buffer.capacity() {
-            let new_cap = (self.work_buffer.capacity() * 2).max(required);
+            let new_cap = (self.work_buffer.capacity() * 2)
+                .min(self.max_buffer_bytes.unwrap_or(usize::MAX))
+                .max(required);
             self.work_buffer.reserve(new_cap - self.work_buffer.len());
         }
     }
@@ -256,7 +1033,7 @@ impl BFast {
     }
 
     #[inline(always)]
-    fn serialize_pydantic_simd_batch(&mut self, list: &PyList) -> PyResult<()> {
+    fn serialize_pydantic_simd_batch(&mut self, list: &PyList, fingerprint: bool) -> PyResult<()> {
         let len = list.len();
         if len == 0 {
             self.work_buffer.push(0x60);
@@ -267,14 +1044,17 @@ impl BFast {
         self.check_recursion_depth()?;
 
         let first_item = list.get_item(0)?;
-        if !first_item.hasattr("__dict__")? {
+        let first_type = first_item.get_type();
+        if !first_item.hasattr("__dict__")?
+            || !list.iter().all(|item| item.get_type().is(first_type))
+        {
             self.decrease_recursion_depth();
             return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
-                "Not Pydantic",
+                "Not a list of records of one class",
             ));
         }
 
-        let dict = first_item.getattr("__dict__")?.downcast::<PyDict>()?;
+        let dict = self.model_dict(first_item)?;
         let field_names: Vec<String> = dict.keys().iter().map(|k| k.to_string()).collect();
 
         let field_ids: Vec<u32> = field_names
@@ -282,75 +1062,169 @@ impl BFast {
             .map(|name| self.get_or_create_string_id_fast(name))
             .collect();
 
-        // Auto-detect: check if first object has complex types
-        let use_fast_mode = self.detect_simple_types(&dict, &field_names)?;
+        // Auto-detect: pick a fast or complex writer per field
+        let writers = self.field_writers(list, &field_names)?;
+        let hints = hints::for_fields(first_type, &field_names)?;
 
-        self.ensure_buffer_capacity(5 + len * 50);
+        self.ensure_buffer_capacity(14 + len * 50);
+        if fingerprint {
+            let fingerprint = self.batch_fingerprint(list, &field_names)?;
+            self.work_buffer.push(TAG_FINGERPRINT);
+            self.work_buffer
+                .extend_from_slice(&fingerprint.to_le_bytes());
+        }
         self.work_buffer.push(0x60);
         self.work_buffer
             .extend_from_slice(&(len as u32).to_le_bytes());
 
-        // Choose serialization path based on type detection
-        if use_fast_mode {
-            // Fast path: simple types only (int, str, float, bool)
-            for item in list.iter() {
-                self.serialize_pydantic_fast(item, &field_names, &field_ids)?;
-            }
-        } else {
-            // Complex path: handles datetime, UUID, Decimal, etc.
-            for item in list.iter() {
-                self.serialize_pydantic_complex(item, &field_names, &field_ids)?;
-            }
+        for item in list.iter() {
+            self.check_buffer_limit(0)?;
+            self.serialize_batch_record(item, &field_names, &field_ids, &writers, &hints)?;
         }
 
         self.decrease_recursion_depth();
         Ok(())
     }
 
-    #[inline(always)]
-    fn detect_simple_types(&self, dict: &PyDict, field_names: &[String]) -> PyResult<bool> {
-        // Check first object's field types
-        for field_name in field_names {
-            if let Some(value) = dict.get_item(field_name)? {
+    /// The fields written for an object: its `__dict__` without the fields of a
+    /// Pydantic model declared with `Field(exclude=True)`, plus the values of
+    /// `@computed_field` properties when the encoder includes them.
+    fn model_dict<'py>(&self, obj: &'py PyAny) -> PyResult<&'py PyDict> {
+        let dict = obj.getattr("__dict__")?.downcast::<PyDict>()?;
+        let class = obj.get_type();
+        let mut fields = dict;
+        if let Ok(model_fields) = class.getattr("model_fields") {
+            for (name, info) in model_fields.downcast::<PyDict>()?.iter() {
+                if info.getattr("exclude")?.is_true()? && fields.contains(name)? {
+                    if fields.is(dict) {
+                        fields = dict.copy()?;
+                    }
+                    fields.del_item(name)?;
+                }
+            }
+        }
+        if self.computed_fields {
+            if let Ok(computed) = class.getattr("model_computed_fields") {
+                for name in computed.downcast::<PyDict>()?.keys() {
+                    if fields.is(dict) {
+                        fields = dict.copy()?;
+                    }
+                    fields.set_item(name, obj.getattr(name.downcast::<PyString>()?)?)?;
+                }
+            }
+        }
+        Ok(fields)
+    }
+
+    /// Field values of a record in a batch, in the order of the first record's
+    /// fields; fails when the record has other fields.
+    fn batch_record<'py>(
+        &self,
+        obj: &'py PyAny,
+        field_names: &[String],
+    ) -> PyResult<Vec<&'py PyAny>> {
+        let mismatch = || {
+            PyErr::new::<pyo3::exceptions::PyValueError, _>(
+                "Record fields differ from the first record",
+            )
+        };
+        let dict = self.model_dict(obj)?;
+        if dict.len() != field_names.len() {
+            return Err(mismatch());
+        }
+        field_names
+            .iter()
+            .map(|name| dict.get_item(name)?.ok_or_else(mismatch))
+            .collect()
+    }
+
+    /// Writer for each batch field: the simple-type writer when every value of
+    /// the field in the sampled records is None, a bool, an int, a str or a
+    /// float, and at least one is not None; the complex writer otherwise.
+    fn field_writers(&self, list: &PyList, field_names: &[String]) -> PyResult<Vec<ValueWriter>> {
+        let mut simple = vec![false; field_names.len()];
+        let mut complex = vec![false; field_names.len()];
+        for item in list.iter().take(BATCH_SAMPLE_ROWS) {
+            for (i, value) in self
+                .batch_record(item, field_names)?
+                .into_iter()
+                .enumerate()
+            {
                 if value.is_none() {
                     continue;
                 }
+                if value.is_instance_of::<PyBool>()
+                    || value.is_instance_of::<pyo3::types::PyLong>()
+                    || value.is_instance_of::<PyString>()
+                    || value.is_instance_of::<pyo3::types::PyFloat>()
+                {
+                    simple[i] = true;
+                } else {
+                    complex[i] = true;
+                }
+            }
+        }
+        Ok(simple
+            .into_iter()
+            .zip(complex)
+            .map(|(simple, complex)| -> ValueWriter {
+                if simple && !complex {
+                    Self::serialize_value_fast
+                } else {
+                    Self::serialize_value_ultra_fast
+                }
+            })
+            .collect())
+    }
 
-                // Check for complex types
-                if let Ok(type_name) = value.get_type().name() {
-                    match type_name {
-                        "datetime" | "date" | "time" | "UUID" | "Decimal" => {
-                            return Ok(false); // Use complex mode
-                        }
-                        _ => {}
-                    }
+    /// Fingerprint of the records of a batch: their field names and, per
+    /// field, the type of the first sampled value that is not None.
+    fn batch_fingerprint(&self, list: &PyList, field_names: &[String]) -> PyResult<u64> {
+        let mut types = vec![None; field_names.len()];
+        for item in list.iter().take(BATCH_SAMPLE_ROWS) {
+            let values = self.batch_record(item, field_names)?;
+            for (found, value) in types.iter_mut().zip(values) {
+                if found.is_none() && !value.is_none() {
+                    *found = Some(value.get_type().name()?);
                 }
             }
         }
-        Ok(true) // Use fast mode
+        let mut layout = Vec::new();
+        for (name, found) in field_names.iter().zip(types) {
+            layout.extend_from_slice(name.as_bytes());
+            layout.push(0);
+            layout.extend_from_slice(found.unwrap_or("NoneType").as_bytes());
+            layout.push(0);
+        }
+        Ok(xxh3_64(&layout))
     }
 
     #[inline(always)]
-    fn serialize_pydantic_fast(
+    fn serialize_batch_record(
         &mut self,
         obj: &PyAny,
         field_names: &[String],
         field_ids: &[u32],
+        writers: &[ValueWriter],
+        hints: &[hints::FieldHints],
     ) -> PyResult<()> {
+        let values = self.batch_record(obj, field_names)?;
+        if self.null_bitmap {
+            let entries: Vec<_> = field_ids
+                .iter()
+                .zip(&values)
+                .map(|(&id, &value)| (id, Some(value)))
+                .collect();
+            return self.write_bitmap_object(&entries, |encoder, i, value| {
+                encoder.with_hints(hints[i], |encoder| writers[i](encoder, value))
+            });
+        }
         self.work_buffer.push(0x70);
 
-        let dict = obj.getattr("__dict__")?.downcast::<PyDict>()?;
-
-        // Fast path: direct iteration for simple types
-        for (i, field_name) in field_names.iter().enumerate() {
-            self.work_buffer
-                .extend_from_slice(&field_ids[i].to_le_bytes());
-
-            if let Some(value) = dict.get_item(field_name)? {
-                self.serialize_value_fast(value)?;
-            } else {
-                self.work_buffer.push(0x10);
-            }
+        for (((&id, value), write), &hints) in field_ids.iter().zip(values).zip(writers).zip(hints)
+        {
+            self.work_buffer.extend_from_slice(&id.to_le_bytes());
+            self.with_hints(hints, |encoder| write(encoder, value))?;
         }
 
         self.work_buffer.push(0x7F);
@@ -373,23 +1247,12 @@ impl BFast {
 
         if val.is_instance_of::<pyo3::types::PyLong>() {
             if let Ok(n) = val.extract::<i32>() {
-                if n >= 0 && n <= 7 {
-                    self.work_buffer.push(0x30 | (n as u8));
-                    return Ok(());
-                }
-                self.work_buffer.push(0x38);
-                self.work_buffer
-                    .extend_from_slice(&(n as i64).to_le_bytes());
+                scan::write_int(&mut self.work_buffer, n as i64);
                 return Ok(());
             }
 
             if let Ok(n) = val.extract::<i64>() {
-                if n >= 0 && n <= 7 {
-                    self.work_buffer.push(0x30 | (n as u8));
-                } else {
-                    self.work_buffer.push(0x38);
-                    self.work_buffer.extend_from_slice(&n.to_le_bytes());
-                }
+                scan::write_int(&mut self.work_buffer, n);
                 return Ok(());
             }
         }
@@ -407,42 +1270,11 @@ impl BFast {
         }
 
         if val.is_instance_of::<pyo3::types::PyFloat>() {
-            let f = val.extract::<f64>()?;
-            self.work_buffer.push(0x40);
-            self.work_buffer.extend_from_slice(&f.to_le_bytes());
-            return Ok(());
-        }
-
-        // Fallback
-        self.work_buffer.push(0x10);
-        Ok(())
-    }
-
-    #[inline(always)]
-    fn serialize_pydantic_complex(
-        &mut self,
-        obj: &PyAny,
-        field_names: &[String],
-        field_ids: &[u32],
-    ) -> PyResult<()> {
-        // Complex path: handles all types including datetime, UUID, Decimal
-        self.work_buffer.push(0x70);
-
-        let dict = obj.getattr("__dict__")?.downcast::<PyDict>()?;
-
-        for (i, field_name) in field_names.iter().enumerate() {
-            self.work_buffer
-                .extend_from_slice(&field_ids[i].to_le_bytes());
-
-            if let Some(value) = dict.get_item(field_name)? {
-                self.serialize_value_ultra_fast(value)?;
-            } else {
-                self.work_buffer.push(0x10);
-            }
+            return self.write_float(val.extract::<f64>()?);
         }
 
-        self.work_buffer.push(0x7F);
-        Ok(())
+        // A value of another type than the field's sampled ones
+        self.serialize_value_ultra_fast(val)
     }
 
     #[inline(always)]
@@ -465,23 +1297,12 @@ impl BFast {
         // Int check (most common for IDs)
         if val.is_instance_of::<pyo3::types::PyLong>() {
             if let Ok(n) = val.extract::<i32>() {
-                if n >= 0 && n <= 7 {
-                    self.work_buffer.push(0x30 | (n as u8));
-                    return Ok(());
-                }
-                self.work_buffer.push(0x38);
-                self.work_buffer
-                    .extend_from_slice(&(n as i64).to_le_bytes());
+                scan::write_int(&mut self.work_buffer, n as i64);
                 return Ok(());
             }
 
             if let Ok(n) = val.extract::<i64>() {
-                if n >= 0 && n <= 7 {
-                    self.work_buffer.push(0x30 | (n as u8));
-                } else {
-                    self.work_buffer.push(0x38);
-                    self.work_buffer.extend_from_slice(&n.to_le_bytes());
-                }
+                scan::write_int(&mut self.work_buffer, n);
                 return Ok(());
             }
         }
@@ -501,15 +1322,18 @@ impl BFast {
 
         // Float check
         if val.is_instance_of::<pyo3::types::PyFloat>() {
-            let f = val.extract::<f64>()?;
-            self.work_buffer.push(0x40);
-            self.work_buffer.extend_from_slice(&f.to_le_bytes());
-            return Ok(());
+            return self.write_float(val.extract::<f64>()?);
         }
 
         // Special types (Decimal, UUID, datetime, etc.)
         if let Ok(type_name) = val.get_type().name() {
             match type_name {
+                "Decimal" if self.decimal_as_float => return self.serialize_decimal_float(val),
+                "datetime" if self.datetime_nanos => {
+                    return self.serialize_datetime_nanos(val, type_name)
+                }
+                "date" => return self.serialize_date(val),
+                "time" if val.getattr("tzinfo")?.is_none() => return self.serialize_time(val),
                 "Decimal" => {
                     let dec_str = val.str()?.extract::<String>()?;
                     self.work_buffer.push(TAG_DECIMAL);
@@ -528,11 +1352,10 @@ impl BFast {
                     self.work_buffer.extend_from_slice(bytes);
                     return Ok(());
                 }
-                "datetime" | "date" | "time" => {
+                "datetime" | "time" => {
                     let iso_str = val.call_method0("isoformat")?.extract::<String>()?;
                     let tag = match type_name {
                         "datetime" => TAG_DATETIME,
-                        "date" => TAG_DATE,
                         "time" => TAG_TIME,
                         _ => 0x50,
                     };
@@ -582,6 +1405,175 @@ impl BFast {
         self.serialize_any_optimized(val)
     }
 
+    /// Write a Decimal as an f64, unless the float reads back as a different
+    /// number (shortest round-trip digits compared to the Decimal).
+    fn serialize_decimal_float(&mut self, val: &PyAny) -> PyResult<()> {
+        let text = val.str()?;
+        let float = text.to_str()?.parse::<f64>().ok().filter(|float| {
+            float.is_nan()
+                || val
+                    .get_type()
+                    .call1((float.to_string(),))
+                    .and_then(|back| back.eq(val))
+                    .unwrap_or(false)
+        });
+        let Some(float) = float else {
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                "Decimal('{}') cannot be encoded as a float without losing precision",
+                text
+            )));
+        };
+        self.write_float(float)
+    }
+
+    /// Write `float` as an f64, or as an f32 for `float32` encoders unless
+    /// it would overflow to infinity.
+    #[inline]
+    fn write_float_value(&mut self, float: f64) {
+        let narrow = float as f32;
+        if self.float32 && (narrow.is_finite() || !float.is_finite()) {
+            self.work_buffer.push(scan::TAG_FLOAT32);
+            self.work_buffer.extend_from_slice(&narrow.to_le_bytes());
+        } else {
+            self.work_buffer.push(0x40);
+            self.work_buffer.extend_from_slice(&float.to_le_bytes());
+        }
+    }
+
+    /// Write a datetime, pandas Timestamp or numpy datetime64 as epoch
+    /// nanoseconds and its UTC offset (see temporal.rs).
+    fn serialize_datetime_nanos(&mut self, val: &PyAny, type_name: &str) -> PyResult<()> {
+        let out_of_range = || {
+            PyErr::new::<pyo3::exceptions::PyOverflowError, _>(
+                "datetime out of range for nanosecond encoding",
+            )
+        };
+        let (nanos, offset) = if type_name == "datetime64" {
+            // NaT is the minimum int64 in every unit
+            if val.call_method1("astype", ("int64",))?.extract::<i64>()? == i64::MIN {
+                self.work_buffer.push(0x10);
+                return Ok(());
+            }
+            let exact = val.call_method1("astype", ("datetime64[ns]",))?;
+            if !exact.eq(val)? {
+                return Err(out_of_range());
+            }
+            let nanos = exact.call_method1("astype", ("int64",))?.extract::<i64>()?;
+            (nanos, temporal::NAIVE)
+        } else {
+            let field = |name| val.getattr(name)?.extract::<i64>();
+            let days = temporal::days_from_civil(
+                field("year")?,
+                field("month")? as u32,
+                field("day")? as u32,
+            );
+            let seconds = field("hour")? * 3_600 + field("minute")? * 60 + field("second")?;
+            let mut fraction = field("microsecond")? * 1_000;
+            if type_name == "Timestamp" {
+                fraction += field("nanosecond")?;
+            }
+            let offset = match val.call_method0("utcoffset")? {
+                offset if offset.is_none() => temporal::NAIVE,
+                offset => {
+                    if offset.getattr("microseconds")?.extract::<i64>()? != 0 {
+                        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+                            "UTC offsets with microseconds cannot be encoded as nanoseconds",
+                        ));
+                    }
+                    offset.getattr("days")?.extract::<i32>()? * 86_400
+                        + offset.getattr("seconds")?.extract::<i32>()?
+                }
+            };
+            let utc_seconds = if offset == temporal::NAIVE {
+                seconds
+            } else {
+                seconds - offset as i64
+            };
+            let nanos = days
+                .checked_mul(86_400_000_000_000)
+                .and_then(|n| n.checked_add(utc_seconds * 1_000_000_000))
+                .and_then(|n| n.checked_add(fraction))
+                .ok_or_else(out_of_range)?;
+            (nanos, offset)
+        };
+        self.work_buffer.push(TAG_DATETIME);
+        self.work_buffer
+            .extend_from_slice(&temporal::DATETIME_NANOS.to_le_bytes());
+        self.work_buffer.extend_from_slice(&nanos.to_le_bytes());
+        self.work_buffer.extend_from_slice(&offset.to_le_bytes());
+        Ok(())
+    }
+
+    fn serialize_date(&mut self, val: &PyAny) -> PyResult<()> {
+        let field = |name| val.getattr(name)?.extract::<u32>();
+        let days = temporal::days_from_civil(field("year")? as i64, field("month")?, field("day")?);
+        self.work_buffer.push(TAG_DATE_DAYS);
+        self.work_buffer
+            .extend_from_slice(&(days as i32).to_le_bytes());
+        Ok(())
+    }
+
+    fn serialize_time(&mut self, val: &PyAny) -> PyResult<()> {
+        let field = |name| val.getattr(name)?.extract::<u64>();
+        let seconds = field("hour")? * 3_600 + field("minute")? * 60 + field("second")?;
+        let nanos = seconds * 1_000_000_000 + field("microsecond")? * 1_000;
+        self.work_buffer.push(TAG_TIME_NANOS);
+        self.work_buffer.extend_from_slice(&nanos.to_le_bytes());
+        Ok(())
+    }
+
+    fn serialize_pandas(&mut self, val: &PyAny, type_name: &str) -> PyResult<bool> {
+        match type_name {
+            "NaTType" => {
+                self.work_buffer.push(0x10);
+                Ok(true)
+            }
+            "Timestamp" => {
+                // Epoch nanoseconds keep the full precision. Timestamps that
+                // form cannot hold (a coarser unit outside 1677-2262, a UTC
+                // offset with microseconds) have no nanoseconds to lose and
+                // stay ISO strings
+                match self.serialize_datetime_nanos(val, type_name) {
+                    Err(err)
+                        if err.is_instance_of::<pyo3::exceptions::PyOverflowError>(val.py())
+                            || err.is_instance_of::<pyo3::exceptions::PyValueError>(val.py()) => {}
+                    result => return result.map(|_| true),
+                }
+                let iso_str = val.call_method0("isoformat")?.extract::<String>()?;
+                self.work_buffer.push(TAG_DATETIME);
+                let bytes = iso_str.as_bytes();
+                self.work_buffer
+                    .extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+                self.work_buffer.extend_from_slice(bytes);
+                Ok(true)
+            }
+            "Timedelta" => {
+                let nanos = val.getattr("value")?.extract::<i64>()?;
+                self.work_buffer.push(TAG_TIMEDELTA);
+                self.work_buffer.extend_from_slice(&nanos.to_le_bytes());
+                Ok(true)
+            }
+            "Series" | "Index" | "RangeIndex" | "DatetimeIndex" | "TimedeltaIndex"
+            | "CategoricalIndex" => {
+                let module = val.get_type().getattr("__module__")?;
+                if !module.extract::<&str>()?.starts_with("pandas") {
+                    return Ok(false);
+                }
+                // Numeric columns keep their dtype through the array tags,
+                // everything else becomes a list of (pandas-aware) values
+                let kind = val.getattr("dtype")?.getattr("kind")?.extract::<String>()?;
+                if matches!(kind.as_str(), "b" | "i" | "u" | "f" | "c") {
+                    self.serialize_any_optimized(val.call_method0("to_numpy")?)?;
+                } else {
+                    let values = val.call_method0("tolist")?;
+                    self.serialize_any_optimized(values)?;
+                }
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
     #[inline(always)]
     fn get_or_create_string_id_fast(&mut self, key_str: &str) -> u32 {
         let mut hasher = AHasher::default();
@@ -614,15 +1606,36 @@ impl BFast {
     }
 
     #[inline(always)]
-    fn write_header_simd(&mut self, pos: usize, compress: bool) {
+    fn write_header_simd(&mut self, pos: usize) -> PyResult<()> {
+        let length = u32::try_from(self.work_buffer.len() - pos - HEADER_SIZE).map_err(|_| {
+            PyErr::new::<pyo3::exceptions::PyOverflowError, _>("Frame larger than 4 GiB")
+        })?;
         unsafe {
             let header = self.work_buffer.as_mut_ptr().add(pos);
             ptr::write_unaligned(header as *mut u16, u16::from_le_bytes(*b"BF"));
-            *header.add(2) = if compress { 0x01 } else { 0x00 };
-            *header.add(3) = 0x01;
-            let count = self.string_table.len() as u16;
+            let mut flags = 0x00;
+            if self.dedup {
+                flags |= FLAG_SHARED_REFS;
+            }
+            if self.schema.is_some() {
+                flags |= FLAG_SCHEMA;
+            }
+            if self.null_bitmap {
+                flags |= FLAG_NULL_BITMAP;
+            }
+            if self.columnar {
+                flags |= FLAG_COLUMNAR;
+            }
+            if self.pack_bools {
+                flags |= FLAG_PACKED_BOOLS;
+            }
+            *header.add(2) = flags;
+            *header.add(3) = FORMAT_VERSION;
+            let count = (self.string_table.len() - self.schema_field_count()) as u16;
             ptr::write_unaligned(header.add(4) as *mut u16, count.to_le());
+            ptr::write_unaligned(header.add(6) as *mut u32, length.to_le());
         }
+        Ok(())
     }
 
     #[inline(always)]
@@ -635,7 +1648,13 @@ impl BFast {
         let aligned_size = (total_size + CACHE_LINE_SIZE - 1) & !(CACHE_LINE_SIZE - 1);
         self.work_buffer.reserve(aligned_size);
 
-        let mut sorted: Vec<_> = self.string_table.iter().collect();
+        // Schema fields are resolved from the registry, not written out
+        let base = self.schema_field_count() as u32;
+        let mut sorted: Vec<_> = self
+            .string_table
+            .iter()
+            .filter(|(_, &id)| id >= base)
+            .collect();
         sorted.sort_unstable_by_key(|(_, &id)| id);
 
         for (string, _) in sorted {
@@ -646,8 +1665,325 @@ impl BFast {
         Ok(())
     }
 
+    #[inline(always)]
+    fn schema_field_count(&self) -> usize {
+        self.schema.as_ref().map_or(0, |schema| schema.fields.len())
+    }
+
+    #[inline(always)]
+    fn container_mark(&self) -> usize {
+        self.work_buffer.len()
+    }
+
+    #[inline(always)]
+    fn finish_container(&mut self, start: usize) {
+        if self.dedup {
+            self.dedup_spans.push((start, self.work_buffer.len()));
+        }
+    }
+
+    // Replace repeated subtrees in the payload with back-references. Spans
+    // were recorded in post-order; walking them by start offset (pre-order)
+    // lets the largest repeat win and skips everything nested inside it.
+    fn apply_dedup(&mut self, payload_start: usize) {
+        let mut spans = mem::take(&mut self.dedup_spans);
+        let post_order = spans.clone();
+        spans.sort_unstable_by_key(|&(start, _)| start);
+
+        let mut first_seen: AHashMap<u64, Vec<(usize, usize)>> = AHashMap::new();
+        let mut replaced: Vec<(usize, usize, usize)> = Vec::new();
+        let mut skip_until = 0;
+        for &(start, end) in &spans {
+            // A reference costs 5 bytes, so only larger subtrees are worth sharing
+            if start < skip_until || end - start <= 5 {
+                continue;
+            }
+            let encoded = &self.work_buffer[start..end];
+            let mut hasher = AHasher::default();
+            encoded.hash(&mut hasher);
+            let candidates = first_seen.entry(hasher.finish()).or_default();
+
+            let target = candidates
+                .iter()
+                .find(|&&(s, e)| self.work_buffer[s..e] == *encoded)
+                .map(|&(s, _)| s);
+            match target {
+                Some(target) => {
+                    replaced.push((start, end, target));
+                    skip_until = end;
+                }
+                None => candidates.push((start, end)),
+            }
+        }
+        if replaced.is_empty() {
+            return;
+        }
+
+        // Post-order indices of the containers the decoder will actually see
+        let mut indices: AHashMap<usize, u32> = AHashMap::new();
+        let mut kept = 0u32;
+        // Already in start order since spans were visited by start offset
+        let sorted_replaced = replaced;
+        for &(start, _) in &post_order {
+            let hidden = sorted_replaced
+                .binary_search_by(|&(s, e, _)| {
+                    if e <= start {
+                        std::cmp::Ordering::Less
+                    } else if s > start {
+                        std::cmp::Ordering::Greater
+                    } else {
+                        std::cmp::Ordering::Equal
+                    }
+                })
+                .is_ok();
+            if !hidden {
+                indices.insert(start, kept);
+                kept += 1;
+            }
+        }
+
+        let mut output = Vec::with_capacity(self.work_buffer.len());
+        output.extend_from_slice(&self.work_buffer[..payload_start]);
+        let mut cursor = payload_start;
+        for &(start, end, target) in &sorted_replaced {
+            output.extend_from_slice(&self.work_buffer[cursor..start]);
+            output.push(TAG_REF);
+            output.extend_from_slice(&indices[&target].to_le_bytes());
+            cursor = end;
+        }
+        output.extend_from_slice(&self.work_buffer[cursor..]);
+        self.work_buffer = output;
+    }
+
+    fn serialize_object(&mut self, dict: &PyDict) -> PyResult<()> {
+        if self.null_bitmap {
+            let entries = self
+                .object_entries(dict)?
+                .into_iter()
+                .map(|(id, v)| (id, Some(v)))
+                .collect::<Vec<_>>();
+            return self.write_bitmap_object(&entries, |encoder, _, value| {
+                encoder.serialize_any_optimized(value)
+            });
+        }
+        self.work_buffer.push(0x70);
+        self.serialize_dict_entries(dict)?;
+        self.work_buffer.push(0x7F);
+        Ok(())
+    }
+
+    /// Write a dict whose keys are all integers, sorted by key in canonical
+    /// mode.
+    fn serialize_int_keyed(&mut self, dict: &PyDict, keys: Vec<i64>) -> PyResult<()> {
+        let mut entries = keys.into_iter().zip(dict.values()).collect::<Vec<_>>();
+        if self.canonical {
+            entries.sort_by_key(|&(key, _)| key);
+        }
+        self.work_buffer.push(TAG_INT_OBJECT);
+        self.work_buffer
+            .extend_from_slice(&(entries.len() as u32).to_le_bytes());
+        for (key, value) in entries {
+            scan::write_int(&mut self.work_buffer, key);
+            self.serialize_any_optimized(value)?;
+        }
+        Ok(())
+    }
+
+    /// Key IDs and values of `dict`, sorted by key in canonical mode.
+    fn object_entries<'p>(&mut self, dict: &'p PyDict) -> PyResult<Vec<(u32, &'p PyAny)>> {
+        let mut entries = dict
+            .iter()
+            .map(|(k, v)| Ok((self.hook_key(k)?.str()?.to_str()?.to_owned(), v)))
+            .collect::<PyResult<Vec<(String, &PyAny)>>>()?;
+        if let Some(policy) = self.duplicate_keys {
+            entries = duplicates::resolve(entries, policy)?;
+        }
+        if self.canonical {
+            entries.sort_by(|a, b| a.0.cmp(&b.0));
+        }
+        Ok(entries
+            .into_iter()
+            .map(|(key, v)| (self.get_or_create_string_id_fast(&key), v))
+            .collect())
+    }
+
+    /// Write an object in null-bitmap form: the leading run of entries with
+    /// consecutive key IDs becomes a presence bitmap followed by the values of
+    /// the non-None fields; the remaining entries are written as usual.
+    fn write_bitmap_object(
+        &mut self,
+        entries: &[(u32, Option<&PyAny>)],
+        write_value: impl Fn(&mut Self, usize, &PyAny) -> PyResult<()>,
+    ) -> PyResult<()> {
+        let Some(&(first_id, _)) = entries.first() else {
+            self.work_buffer.extend_from_slice(&[0x70, 0x7F]);
+            return Ok(());
+        };
+        let run = entries
+            .iter()
+            .zip(first_id..)
+            .take(u16::MAX as usize)
+            .take_while(|((id, _), expected)| id == expected)
+            .count();
+
+        self.work_buffer.push(TAG_NULL_BITMAP);
+        self.work_buffer.extend_from_slice(&first_id.to_le_bytes());
+        self.work_buffer
+            .extend_from_slice(&(run as u16).to_le_bytes());
+        let bitmap_pos = self.work_buffer.len();
+        self.work_buffer.resize(bitmap_pos + run.div_ceil(8), 0);
+        for (i, (_, value)) in entries[..run].iter().enumerate() {
+            if let Some(value) = value.filter(|v| !v.is_none()) {
+                self.work_buffer[bitmap_pos + i / 8] |= 1 << (i % 8);
+                write_value(self, i, value)?;
+            }
+        }
+        for (i, (id, value)) in entries.iter().enumerate().skip(run) {
+            self.work_buffer.extend_from_slice(&id.to_le_bytes());
+            match value {
+                Some(value) => write_value(self, i, value)?,
+                None => self.work_buffer.push(0x10),
+            }
+        }
+        self.work_buffer.push(0x7F);
+        Ok(())
+    }
+
+    fn serialize_dict_entries(&mut self, dict: &PyDict) -> PyResult<()> {
+        if self.canonical || self.duplicate_keys.is_some() {
+            for (id, v) in self.object_entries(dict)? {
+                self.work_buffer.extend_from_slice(&id.to_le_bytes());
+                self.serialize_any_optimized(v)?;
+            }
+            return Ok(());
+        }
+
+        for (k, v) in dict.iter() {
+            let k = self.hook_key(k)?;
+            let key_str = if let Ok(py_str) = k.downcast::<PyString>() {
+                py_str.to_str()?
+            } else {
+                &k.to_string()
+            };
+
+            let id = self.get_or_create_string_id_fast(key_str);
+            self.work_buffer.extend_from_slice(&id.to_le_bytes());
+            self.serialize_any_optimized(v)?;
+        }
+        Ok(())
+    }
+
+    fn serialize_typed_array(&mut self, array: &PyAny) -> PyResult<()> {
+        let typecode = array.getattr("typecode")?.extract::<char>()?;
+        let itemsize = array.getattr("itemsize")?.extract::<usize>()?;
+        let Some(code) = typed_array_code(typecode, itemsize) else {
+            // 'u' / 'w' arrays hold text
+            return self.serialize_any_optimized(array.call_method0("tounicode")?);
+        };
+        // abi3 builds have no buffer API, so the items are copied once
+        let raw = array.call_method0("tobytes")?;
+        let raw = raw.downcast::<PyBytes>()?.as_bytes();
+        self.check_buffer_limit(6 + raw.len())?;
+        self.work_buffer.push(TAG_TYPED_ARRAY);
+        self.work_buffer.push(code);
+        self.work_buffer
+            .extend_from_slice(&(raw.len() as u32).to_le_bytes());
+        self.work_buffer.extend_from_slice(raw);
+        Ok(())
+    }
+
+    fn serialize_tensor(&mut self, tensor: dlpack::HostTensor) -> PyResult<()> {
+        let rank = u8::try_from(tensor.shape.len()).map_err(|_| {
+            PyErr::new::<pyo3::exceptions::PyOverflowError, _>("Tensor has more than 255 dims")
+        })?;
+        let length = u32::try_from(tensor.data.len()).map_err(|_| {
+            PyErr::new::<pyo3::exceptions::PyOverflowError, _>("Tensor larger than 4 GiB")
+        })?;
+        let size = 8 + tensor.shape.len() * 4 + tensor.data.len();
+        self.check_buffer_limit(size)?;
+        self.ensure_buffer_capacity(size);
+        self.work_buffer.push(TAG_TENSOR);
+        self.work_buffer.push(tensor.code);
+        self.work_buffer.push(tensor.bits);
+        self.work_buffer.push(rank);
+        for dim in &tensor.shape {
+            self.work_buffer.extend_from_slice(&dim.to_le_bytes());
+        }
+        self.work_buffer.extend_from_slice(&length.to_le_bytes());
+        self.work_buffer.extend_from_slice(&tensor.data);
+        Ok(())
+    }
+
+    fn serialize_custom(&mut self, val: &PyAny, state: &PyAny) -> PyResult<()> {
+        let class = val.get_type();
+        let name = format!(
+            "{}.{}",
+            class.getattr("__module__")?,
+            class.getattr("__qualname__")?
+        );
+        self.work_buffer.push(TAG_CUSTOM);
+        self.work_buffer
+            .extend_from_slice(&(name.len() as u32).to_le_bytes());
+        self.work_buffer.extend_from_slice(name.as_bytes());
+
+        // The state may be another object that defines __bfast__
+        self.check_recursion_depth()?;
+        let result = self.serialize_any_optimized(state);
+        self.decrease_recursion_depth();
+        result
+    }
+
+    /// Hand a str or bytes value of `len` bytes to the blob store when it is
+    /// over the threshold and write the returned token. Returns whether it did.
+    fn store_blob(&mut self, val: &PyAny, len: usize) -> PyResult<bool> {
+        let py = val.py();
+        let token = match &self.blob_store {
+            Some(store) if len > self.blob_threshold => store.call1(py, (val,))?,
+            _ => return Ok(false),
+        };
+        let token = token.as_ref(py).downcast::<PyString>().map_err(|_| {
+            PyErr::new::<pyo3::exceptions::PyTypeError, _>(format!(
+                "blob_store must return a str token, got {}",
+                token.as_ref(py).get_type().name().unwrap_or("?")
+            ))
+        })?;
+        let bytes = token.to_str()?.as_bytes();
+        self.work_buffer.push(TAG_BLOB_REF);
+        self.work_buffer
+            .extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+        self.work_buffer.extend_from_slice(bytes);
+        Ok(true)
+    }
+
+    // Set iteration order is arbitrary (string hashes are randomized per
+    // process), so canonical mode sorts the encoded elements
+    fn serialize_unordered<'p>(&mut self, items: impl Iterator<Item = &'p PyAny>) -> PyResult<()> {
+        if !self.canonical {
+            for item in items {
+                self.serialize_any_optimized(item)?;
+            }
+            return Ok(());
+        }
+
+        let start = self.work_buffer.len();
+        let mut bounds = Vec::new();
+        for item in items {
+            let item_start = self.work_buffer.len() - start;
+            self.serialize_any_optimized(item)?;
+            bounds.push(item_start..self.work_buffer.len() - start);
+        }
+        let encoded = self.work_buffer.split_off(start);
+        bounds.sort_by(|a, b| encoded[a.clone()].cmp(&encoded[b.clone()]));
+        for range in bounds {
+            self.work_buffer.extend_from_slice(&encoded[range]);
+        }
+        Ok(())
+    }
+
     #[inline(always)]
     fn serialize_any_optimized(&mut self, val: &PyAny) -> PyResult<()> {
+        self.check_buffer_limit(0)?;
+        let val = self.hook_value(val)?;
         if val.is_none() {
             self.work_buffer.push(0x10);
             return Ok(());
@@ -661,6 +1997,52 @@ impl BFast {
         // Check special types BEFORE basic types (Decimal can be extracted as f64)
         // Decimal
         if let Ok(type_name) = val.get_type().name() {
+            if self.datetime_nanos && matches!(type_name, "datetime" | "Timestamp" | "datetime64") {
+                return self.serialize_datetime_nanos(val, type_name);
+            }
+
+            // pandas scalars and columns (Timestamp has isoformat, Series has __dict__)
+            if self.serialize_pandas(val, type_name)? {
+                return Ok(());
+            }
+
+            // Pydantic secrets hold their value in __dict__, which must not leak
+            if matches!(type_name, "SecretStr" | "SecretBytes")
+                && val.hasattr("get_secret_value")?
+            {
+                if self.reveal_secrets {
+                    return self.serialize_any_optimized(val.call_method0("get_secret_value")?);
+                }
+                return self.serialize_any_optimized(PyString::new(val.py(), SECRET_MASK));
+            }
+
+            if type_name == "range" {
+                let bound = |name| val.getattr(name).and_then(|v| v.extract::<i64>());
+                // Bounds beyond i64 fall through to the string fallback
+                if let (Ok(start), Ok(stop), Ok(step)) =
+                    (bound("start"), bound("stop"), bound("step"))
+                {
+                    self.work_buffer.push(TAG_RANGE);
+                    for value in [start, stop, step] {
+                        scan::write_int(&mut self.work_buffer, value);
+                    }
+                    return Ok(());
+                }
+            }
+
+            if type_name == "date" {
+                return self.serialize_date(val);
+            }
+
+            // Aware times keep their offset in the ISO form below
+            if type_name == "time" && val.getattr("tzinfo")?.is_none() {
+                return self.serialize_time(val);
+            }
+
+            if type_name == "Decimal" && self.decimal_as_float {
+                return self.serialize_decimal_float(val);
+            }
+
             if type_name == "Decimal" {
                 let dec_str = val.str()?.extract::<String>()?;
                 self.work_buffer.push(TAG_DECIMAL);
@@ -708,24 +2090,21 @@ impl BFast {
         }
 
         if let Ok(n) = val.extract::<i64>() {
-            if n >= 0 && n <= 7 {
-                self.work_buffer.push(0x30 | (n as u8));
-            } else {
-                self.work_buffer.push(0x38);
-                self.work_buffer.extend_from_slice(&n.to_le_bytes());
-            }
+            scan::write_int(&mut self.work_buffer, n);
             return Ok(());
         }
 
         if let Ok(f) = val.extract::<f64>() {
-            self.work_buffer.push(0x40);
-            self.work_buffer.extend_from_slice(&f.to_le_bytes());
-            return Ok(());
+            return self.write_float(f);
         }
 
         if let Ok(py_str) = val.downcast::<PyString>() {
-            self.work_buffer.push(0x50);
             let str_data = py_str.to_str()?;
+            if self.store_blob(val, str_data.len())? {
+                return Ok(());
+            }
+            self.check_buffer_limit(5 + str_data.len())?;
+            self.work_buffer.push(0x50);
             let bytes = str_data.as_bytes();
             self.work_buffer
                 .extend_from_slice(&(bytes.len() as u32).to_le_bytes());
@@ -735,6 +2114,10 @@ impl BFast {
 
         // bytes / bytearray (check before collections)
         if let Ok(py_bytes) = val.extract::<&[u8]>() {
+            if self.store_blob(val, py_bytes.len())? {
+                return Ok(());
+            }
+            self.check_buffer_limit(5 + py_bytes.len())?;
             self.work_buffer.push(0x80);
             self.work_buffer
                 .extend_from_slice(&(py_bytes.len() as u32).to_le_bytes());
@@ -743,6 +2126,19 @@ impl BFast {
         }
 
         if let Ok(list) = val.downcast::<PyList>() {
+            if self.columnar && !self.hooks_bound() && self.serialize_columnar(list)? {
+                return Ok(());
+            }
+            if self.pack_bools && !self.hooks_bound() && !list.is_empty() {
+                if let Some(bits) = bool_values(list.iter())? {
+                    self.work_buffer.push(TAG_BOOL_ARRAY);
+                    self.work_buffer
+                        .extend_from_slice(&(bits.len() as u32).to_le_bytes());
+                    pack_bits(&mut self.work_buffer, &bits);
+                    return Ok(());
+                }
+            }
+            let mark = self.container_mark();
             self.work_buffer.push(0x60);
             let len = list.len();
             self.work_buffer
@@ -751,11 +2147,13 @@ impl BFast {
             for item in list.iter() {
                 self.serialize_any_optimized(item)?;
             }
+            self.finish_container(mark);
             return Ok(());
         }
 
         // tuple (serialize as list)
         if let Ok(tuple) = val.downcast::<PyTuple>() {
+            let mark = self.container_mark();
             self.work_buffer.push(0x60);
             let len = tuple.len();
             self.work_buffer
@@ -764,37 +2162,51 @@ impl BFast {
             for item in tuple.iter() {
                 self.serialize_any_optimized(item)?;
             }
+            self.finish_container(mark);
             return Ok(());
         }
 
         // set / frozenset (serialize as list)
         if let Ok(set) = val.downcast::<PySet>() {
+            let mark = self.container_mark();
             self.work_buffer.push(0x60);
             let len = set.len();
             self.work_buffer
                 .extend_from_slice(&(len as u32).to_le_bytes());
 
-            for item in set.iter() {
-                self.serialize_any_optimized(item)?;
-            }
+            self.serialize_unordered(set.iter())?;
+            self.finish_container(mark);
             return Ok(());
         }
 
         if let Ok(frozenset) = val.downcast::<PyFrozenSet>() {
+            let mark = self.container_mark();
             self.work_buffer.push(0x60);
             let len = frozenset.len();
             self.work_buffer
                 .extend_from_slice(&(len as u32).to_le_bytes());
 
-            for item in frozenset.iter() {
-                self.serialize_any_optimized(item)?;
-            }
+            self.serialize_unordered(frozenset.iter())?;
+            self.finish_container(mark);
             return Ok(());
         }
 
         if let Ok(array) = val.extract::<PyReadonlyArrayDyn<f64>>() {
-            self.work_buffer.push(0x90);
             let raw_data = array.as_slice()?;
+            if !self.floats_as_is(raw_data)? {
+                // Decoders number every list as a back-reference target
+                let mark = self.container_mark();
+                self.work_buffer.push(0x60);
+                self.work_buffer
+                    .extend_from_slice(&(raw_data.len() as u32).to_le_bytes());
+                for &float in raw_data {
+                    self.write_float(float)?;
+                }
+                self.finish_container(mark);
+                return Ok(());
+            }
+            self.check_buffer_limit(5 + raw_data.len() * 8)?;
+            self.work_buffer.push(0x90);
             self.work_buffer
                 .extend_from_slice(&(raw_data.len() as u32).to_le_bytes());
 
@@ -805,25 +2217,56 @@ impl BFast {
             return Ok(());
         }
 
+        // DLPack tensors (PyTorch, TensorFlow, JAX, non-f64 NumPy arrays)
+        if dlpack::is_dlpack(val)? {
+            return self.serialize_tensor(dlpack::to_host(val)?);
+        }
+
         // Check for dict or __dict__ (Pydantic models)
         if let Ok(dict) = val.downcast::<PyDict>() {
-            self.work_buffer.push(0x70);
+            let mark = self.container_mark();
+            match int_keys(dict)? {
+                Some(keys) => self.serialize_int_keyed(dict, keys)?,
+                None => self.serialize_object(dict)?,
+            }
+            self.finish_container(mark);
+            return Ok(());
+        }
 
-            for (k, v) in dict.iter() {
-                let key_str = if let Ok(py_str) = k.downcast::<PyString>() {
-                    py_str.to_str()?
-                } else {
-                    &k.to_string()
-                };
+        // dict views and deque (serialize as list; items as [key, value])
+        if val.downcast::<PyDictKeys>().is_ok()
+            || val.downcast::<PyDictValues>().is_ok()
+            || val.downcast::<PyDictItems>().is_ok()
+            || val.is_instance(deque_class(val.py())?)?
+        {
+            let mark = self.container_mark();
+            self.work_buffer.push(0x60);
+            let len = val.len()?;
+            self.work_buffer
+                .extend_from_slice(&(len as u32).to_le_bytes());
 
-                let id = self.get_or_create_string_id_fast(key_str);
-                self.work_buffer.extend_from_slice(&id.to_le_bytes());
-                self.serialize_any_optimized(v)?;
+            for item in val.iter()? {
+                self.serialize_any_optimized(item?)?;
             }
+            self.finish_container(mark);
+            return Ok(());
+        }
+
+        // array.array (typed buffer)
+        if val.is_instance(array_class(val.py())?)? {
+            return self.serialize_typed_array(val);
+        }
 
-            self.work_buffer.push(0x7F);
+        // Registered extension types, then the __bfast__ protocol - both
+        // BEFORE Enum and __dict__
+        if self.serialize_ext(val)? {
             return Ok(());
         }
+        if !val.is_instance_of::<PyType>() {
+            if let Ok(method) = val.getattr("__bfast__") {
+                return self.serialize_custom(val, method.call0()?);
+            }
+        }
 
         // Enum (extract value) - check BEFORE __dict__
         if val.hasattr("value")? && val.hasattr("name")? {
@@ -841,106 +2284,603 @@ impl BFast {
             }
         }
 
+        // Pickle state protocol - check BEFORE __dict__
+        if let Some(state) = pickle_state(val)? {
+            return self.serialize_custom(val, state);
+        }
+
         // Try __dict__ for Pydantic models
         if let Ok(dict_attr) = val.getattr("__dict__") {
-            if let Ok(dict) = dict_attr.downcast::<PyDict>() {
-                self.work_buffer.push(0x70);
+            if dict_attr.downcast::<PyDict>().is_ok() {
+                let dict = self.model_dict(val)?;
+                let hints = hints::of(val.get_type())?;
+                let mark = self.container_mark();
+                match hints.is_empty() {
+                    true => self.serialize_object(dict)?,
+                    false => self.serialize_hinted_object(dict, &hints)?,
+                }
+                self.finish_container(mark);
+                return Ok(());
+            }
+        }
 
-                for (k, v) in dict.iter() {
-                    let key_str = if let Ok(py_str) = k.downcast::<PyString>() {
-                        py_str.to_str()?
-                    } else {
-                        &k.to_string()
-                    };
+        if self.serialize_unknown(val)? {
+            return Ok(());
+        }
+
+        // Fallback: convert to string
+        let str_repr = val.str()?.extract::<String>()?;
+        self.work_buffer.push(0x50);
+        let bytes = str_repr.as_bytes();
+        self.work_buffer
+            .extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+        self.work_buffer.extend_from_slice(bytes);
+        Ok(())
+    }
+}
+
+#[pymodule]
+fn _b_fast(_py: Python, m: &PyModule) -> PyResult<()> {
+    m.add_class::<BFast>()?;
+    m.add_class::<container::BFastWriter>()?;
+    m.add_class::<container::BFastReader>()?;
+    m.add_class::<incremental::BFastBatchEncoder>()?;
+    m.add_class::<schema::BFastSchema>()?;
+    m.add_class::<hints::BFastF32>()?;
+    m.add_class::<hints::BFastRaw>()?;
+    m.add_class::<explain::SizeReport>()?;
+    m.add_class::<lazy::LazyList>()?;
+    m.add_class::<columnar::LazyColumn>()?;
+    m.add_class::<background::PendingFrame>()?;
+    m.add_function(wrap_pyfunction!(background::encode_async, m)?)?;
+    #[cfg(feature = "redis")]
+    {
+        m.add_function(wrap_pyfunction!(cache::encode_keyed, m)?)?;
+        m.add_function(wrap_pyfunction!(cache::decode_keyed, m)?)?;
+    }
+    m.add_function(wrap_pyfunction!(compression::recompress, m)?)?;
+    m.add_function(wrap_pyfunction!(diff::diff, m)?)?;
+    m.add_function(wrap_pyfunction!(diff::apply_patch, m)?)?;
+    m.add_function(wrap_pyfunction!(digest::hash, m)?)?;
+    m.add_function(wrap_pyfunction!(dump::dump_tokens, m)?)?;
+    m.add_function(wrap_pyfunction!(explain::explain, m)?)?;
+    m.add_function(wrap_pyfunction!(json::from_json_bytes, m)?)?;
+    m.add_function(wrap_pyfunction!(json::to_json, m)?)?;
+    m.add_function(wrap_pyfunction!(expiry::is_expired, m)?)?;
+    #[cfg(feature = "parquet")]
+    m.add_function(wrap_pyfunction!(export::to_parquet, m)?)?;
+    m.add_function(wrap_pyfunction!(protobuf::to_protobuf, m)?)?;
+    m.add_function(wrap_pyfunction!(protobuf::from_protobuf, m)?)?;
+    m.add_function(wrap_pyfunction!(query::get, m)?)?;
+    m.add_function(wrap_pyfunction!(query::aggregate, m)?)?;
+    m.add_function(wrap_pyfunction!(filter::filter, m)?)?;
+    m.add_function(wrap_pyfunction!(query::metadata, m)?)?;
+    m.add_function(wrap_pyfunction!(query::fingerprint, m)?)?;
+    m.add_function(wrap_pyfunction!(query::frame_length, m)?)?;
+    m.add_function(wrap_pyfunction!(query::count, m)?)?;
+    m.add_function(wrap_pyfunction!(query::decode_range, m)?)?;
+    m.add_function(wrap_pyfunction!(schema::register_schema, m)?)?;
+    m.add_function(wrap_pyfunction!(schema::register_schema_resolver, m)?)?;
+    m.add_function(wrap_pyfunction!(ext::register_ext, m)?)?;
+    m.add_function(wrap_pyfunction!(spec::spec, m)?)?;
+    m.add_function(wrap_pyfunction!(splice::concat, m)?)?;
+    m.add_function(wrap_pyfunction!(splice::slice, m)?)?;
+    m.add_function(wrap_pyfunction!(stats::stats, m)?)?;
+    m.add_function(wrap_pyfunction!(stats::may_match, m)?)?;
+    m.add_function(wrap_pyfunction!(trace::set_trace_hook, m)?)?;
+    m.add(
+        "BFastError",
+        _py.get_type::<pyo3::exceptions::PyValueError>(),
+    )?;
+    m.add("FORMAT_VERSION", FORMAT_VERSION)?;
+    m.add("TAGS", spec::tag_names(_py)?)?;
+    Ok(())
+}
+
+fn decode_bytes(
+    py: Python,
+    bytes: &[u8],
+    decompress: bool,
+    options: DecodeOptions,
+) -> PyResult<PyObject> {
+    let decompressed_data = if decompress {
+        decompress_packed(bytes).map_err(PyErr::new::<pyo3::exceptions::PyValueError, _>)?
+    } else {
+        Cow::Borrowed(bytes)
+    };
+
+    decode_frame(py, &decompressed_data, None, options)
+}
+
+/// Decode-time switches shared by every decode entry point.
+#[derive(Clone, Default)]
+pub(crate) struct DecodeOptions {
+    /// Resolve back-references to the same object instead of a fresh copy.
+    pub shared_refs: bool,
+    /// Reader schema the decoded records are evolved to.
+    pub schema: Option<Arc<schema::SchemaDef>>,
+    /// Decode lists as tuples and dicts as read-only mapping proxies.
+    pub immutable: bool,
+    /// Classes decoding may instantiate (`None` allows all); values of other
+    /// classes come back as plain data.
+    pub allowed_classes: Option<Arc<Vec<PyObject>>>,
+    /// Expand ranges into lists of their values.
+    pub ranges_as_lists: bool,
+    /// Called with the token of each value kept outside the frame.
+    pub blob_resolver: Option<PyObject>,
+    /// Caps on the string table and nesting depth.
+    pub limits: DecodeLimits,
+    /// Record layout fingerprint frames must carry, when they carry one.
+    pub fingerprint: Option<u64>,
+    /// Key objects kept from earlier frames, reused instead of new ones.
+    pub key_cache: Option<Arc<keys::KeyCache>>,
+    /// Decode floats as decimal.Decimal.
+    pub decimal_floats: bool,
+    /// What to do with keys an object repeats; None keeps the last value.
+    pub duplicate_keys: Option<duplicates::DuplicateKeys>,
+    /// Names keys are decoded under instead of the ones in the frame.
+    pub aliases: Option<Arc<HashMap<String, String>>>,
+}
+
+impl DecodeOptions {
+    /// The default options, with `limits`.
+    pub(crate) fn limited(limits: DecodeLimits) -> Self {
+        DecodeOptions {
+            limits,
+            ..DecodeOptions::default()
+        }
+    }
+}
+
+/// Bounds checked while decoding, so a hostile frame fails before it makes
+/// the decoder allocate or recurse for it.
+#[derive(Clone)]
+pub(crate) struct DecodeLimits {
+    /// String table entries a frame may declare.
+    pub max_keys: usize,
+    /// Bytes in one string table entry.
+    pub max_key_length: usize,
+    /// Levels of nested values.
+    pub max_depth: usize,
+}
+
+impl Default for DecodeLimits {
+    fn default() -> Self {
+        DecodeLimits {
+            max_keys: DEFAULT_MAX_KEYS,
+            max_key_length: DEFAULT_MAX_KEY_LENGTH,
+            max_depth: DEFAULT_MAX_DEPTH,
+        }
+    }
+}
+
+impl DecodeLimits {
+    /// The limits passed to a decode entry point.
+    pub(crate) fn new(max_keys: usize, max_key_length: usize, max_depth: usize) -> PyResult<Self> {
+        if max_depth > MAX_RECURSION_DEPTH {
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                "max_depth cannot exceed {}",
+                MAX_RECURSION_DEPTH
+            )));
+        }
+        Ok(DecodeLimits {
+            max_keys,
+            max_key_length,
+            max_depth,
+        })
+    }
+
+    fn check_key_count(&self, count: usize) -> PyResult<()> {
+        if count > self.max_keys {
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                "String table has {} entries, more than max_keys ({})",
+                count, self.max_keys
+            )));
+        }
+        Ok(())
+    }
+
+    fn check_key_length(&self, index: usize, length: usize) -> PyResult<()> {
+        if length > self.max_key_length {
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                "String table entry {} is {} bytes, more than max_key_length ({})",
+                index, length, self.max_key_length
+            )));
+        }
+        Ok(())
+    }
+
+    /// Check a string table read by the scanner.
+    pub(crate) fn check_keys(&self, keys: &[impl AsRef<str>]) -> PyResult<()> {
+        self.check_key_count(keys.len())?;
+        for (index, key) in keys.iter().enumerate() {
+            self.check_key_length(index, key.as_ref().len())?;
+        }
+        Ok(())
+    }
+}
+
+fn deque_class(py: Python<'_>) -> PyResult<&PyAny> {
+    static DEQUE: GILOnceCell<PyObject> = GILOnceCell::new();
+    cached_class(py, &DEQUE, "collections", "deque")
+}
+
+fn array_class(py: Python<'_>) -> PyResult<&PyAny> {
+    static ARRAY: GILOnceCell<PyObject> = GILOnceCell::new();
+    cached_class(py, &ARRAY, "array", "array")
+}
+
+/// `module.name`, imported on first use and kept in `cell` from then on.
+fn cached_class<'py>(
+    py: Python<'py>,
+    cell: &'static GILOnceCell<PyObject>,
+    module: &str,
+    name: &str,
+) -> PyResult<&'py PyAny> {
+    let class = cell.get_or_try_init(py, || {
+        Ok::<_, PyErr>(py.import(module)?.getattr(name)?.into())
+    })?;
+    Ok(class.as_ref(py))
+}
+
+fn class_allowlist(classes: &PyAny) -> PyResult<Arc<Vec<PyObject>>> {
+    let mut allowed = Vec::new();
+    for class in classes.iter()? {
+        let class = class?;
+        if !class.is_instance_of::<PyType>() {
+            return Err(PyErr::new::<pyo3::exceptions::PyTypeError, _>(format!(
+                "allowed_classes must contain classes, got {}",
+                class.get_type().name()?
+            )));
+        }
+        allowed.push(class.into());
+    }
+    Ok(Arc::new(allowed))
+}
+
+/// Read-only copy of a decoded value: lists become tuples and dicts become
+/// mapping proxies, recursively. `proxy` is `types.MappingProxyType`.
+pub(crate) fn freeze(py: Python, value: &PyAny, proxy: &PyAny) -> PyResult<PyObject> {
+    if let Ok(list) = value.downcast::<PyList>() {
+        let items = list
+            .iter()
+            .map(|item| freeze(py, item, proxy))
+            .collect::<PyResult<Vec<_>>>()?;
+        return Ok(PyTuple::new(py, items).into());
+    }
+    if let Ok(dict) = value.downcast::<PyDict>() {
+        let frozen = PyDict::new(py);
+        for (key, item) in dict {
+            frozen.set_item(key, freeze(py, item, proxy)?)?;
+        }
+        return Ok(proxy.call1((frozen,))?.into());
+    }
+    Ok(value.into())
+}
+
+/// Parse an uncompressed frame. With `blob_view` set (a memoryview over the
+/// same bytes), bytes values are returned as zero-copy slices of it.
+fn decode_frame(
+    py: Python,
+    data: &[u8],
+    blob_view: Option<&PyAny>,
+    options: DecodeOptions,
+) -> PyResult<PyObject> {
+    decode_frame_at(py, data, blob_view, options, None)
+}
+
+/// Like `decode_frame`, but decode only the value starting at `root` when given.
+fn decode_frame_at(
+    py: Python,
+    data: &[u8],
+    blob_view: Option<&PyAny>,
+    options: DecodeOptions,
+    root: Option<usize>,
+) -> PyResult<PyObject> {
+    let (string_table, offset) = read_string_table(data, &options.limits)?;
+    let (fingerprint, offset) = scan::root_fingerprint(data, offset)
+        .map_err(PyErr::new::<pyo3::exceptions::PyValueError, _>)?;
+    check_fingerprint(fingerprint, options.fingerprint)?;
+    let mut parser = BFastParser::new(py, data, &string_table, blob_view, &options)?;
+    parser.offset = root.unwrap_or(offset);
+
+    let mut value = match (fingerprint, root) {
+        (Some(fingerprint), None) => parser.parse_planned(fingerprint)?,
+        _ => parser.parse()?,
+    };
+    if let Some(schema) = &options.schema {
+        if root.is_none() {
+            value = schema::evolve(py, value, schema)?;
+        }
+        // The parser left the containers mutable for the schema to rewrite
+        if options.immutable {
+            let proxy = py.import("types")?.getattr("MappingProxyType")?;
+            value = freeze(py, value.as_ref(py), proxy)?;
+        }
+    }
+    Ok(value)
+}
+
+/// The `count` values stored back to back from `offset` on, decoded by one
+/// parser.
+fn decode_values_at(
+    py: Python,
+    data: &[u8],
+    options: DecodeOptions,
+    offset: usize,
+    count: usize,
+) -> PyResult<Vec<PyObject>> {
+    let (string_table, _) = read_string_table(data, &options.limits)?;
+    let mut parser = BFastParser::new(py, data, &string_table, None, &options)?;
+    parser.offset = offset;
+    (0..count).map(|_| parser.parse()).collect()
+}
+
+/// Fail when a frame's record layout fingerprint is not the `expected` one.
+fn check_fingerprint(fingerprint: Option<u64>, expected: Option<u64>) -> PyResult<()> {
+    match (fingerprint, expected) {
+        (Some(found), Some(expected)) if found != expected => {
+            Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                "Frame records have layout fingerprint {:#018x}, expected {:#018x}",
+                found, expected
+            )))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// The string table of a frame (schema fields first) and the payload offset.
+fn read_string_table(data: &[u8], limits: &DecodeLimits) -> PyResult<(Vec<String>, usize)> {
+    if data.len() < 6 {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            "Decompressed buffer too small for B-FAST header",
+        ));
+    }
+
+    let magic = &data[0..2];
+    if magic != b"BF" {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            "Invalid B-FAST magic number",
+        ));
+    }
+
+    let string_table_count = u16::from_le_bytes(data[4..6].try_into().unwrap()) as usize;
+    limits.check_key_count(string_table_count)?;
+
+    let mut offset =
+        scan::header_size(data).map_err(PyErr::new::<pyo3::exceptions::PyValueError, _>)?;
+    // Every entry takes at least its length byte
+    let mut string_table = Vec::with_capacity(string_table_count.min(data.len() - offset));
+    if data[2] & FLAG_SCHEMA != 0 {
+        let schema =
+            schema::frame_schema(data).map_err(PyErr::new::<pyo3::exceptions::PyValueError, _>)?;
+        string_table.extend(schema.fields.iter().map(|f| f.to_string()));
+        offset += 4;
+    }
+    if data[2] & FLAG_METADATA != 0 {
+        offset = scan::metadata_section(data, offset)
+            .map_err(PyErr::new::<pyo3::exceptions::PyValueError, _>)?
+            .1;
+    }
+    for index in 0..string_table_count {
+        if offset >= data.len() {
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+                "Unexpected end of buffer in string table",
+            ));
+        }
+        let length = data[offset] as usize;
+        limits.check_key_length(index, length)?;
+        offset += 1;
+        if offset + length > data.len() {
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+                "String extends beyond buffer in string table",
+            ));
+        }
+        let string_bytes = &data[offset..offset + length];
+        let string_val = std::str::from_utf8(string_bytes)
+            .map_err(|e| {
+                PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                    "Invalid UTF-8 in string table: {}",
+                    e
+                ))
+            })?
+            .to_string();
+        string_table.push(string_val);
+        offset += length;
+    }
+    Ok((string_table, offset))
+}
+
+/// The keys of a non-empty dict whose keys are all ints (not bools) that fit
+/// in an i64.
+fn int_keys(dict: &PyDict) -> PyResult<Option<Vec<i64>>> {
+    if dict.is_empty() {
+        return Ok(None);
+    }
+    let mut keys = Vec::with_capacity(dict.len());
+    for key in dict.keys() {
+        if !key.is_instance_of::<pyo3::types::PyLong>() || key.is_instance_of::<PyBool>() {
+            return Ok(None);
+        }
+        match key.extract::<i64>() {
+            Ok(key) => keys.push(key),
+            Err(_) => return Ok(None),
+        }
+    }
+    Ok(Some(keys))
+}
+
+/// Fixed-width type code of an array.array, or `None` for text arrays.
+fn typed_array_code(typecode: char, itemsize: usize) -> Option<u8> {
+    let signed = match itemsize {
+        1 => b'b',
+        2 => b'h',
+        4 => b'i',
+        8 => b'q',
+        _ => return None,
+    };
+    match typecode {
+        'b' | 'h' | 'i' | 'l' | 'q' => Some(signed),
+        'B' | 'H' | 'I' | 'L' | 'Q' => Some(signed.to_ascii_uppercase()),
+        'f' | 'd' => Some(typecode as u8),
+        _ => None,
+    }
+}
+
+/// Item size of a typed array code.
+pub(crate) fn typed_array_itemsize(code: u8) -> Option<usize> {
+    match code {
+        b'b' | b'B' => Some(1),
+        b'h' | b'H' => Some(2),
+        b'i' | b'I' | b'f' => Some(4),
+        b'q' | b'Q' | b'd' => Some(8),
+        _ => None,
+    }
+}
+
+/// Rebuild an instance of `class` from the value its `__bfast__` or
+/// `__getstate__` returned, the way pickle does for the latter. Pydantic
+/// models, written as their fields, are rebuilt without validation.
+fn restore(class: &PyAny, state: &PyAny) -> PyResult<PyObject> {
+    if class.hasattr("__bfast__")? {
+        return Ok(class.call_method1("__bfast_restore__", (state,))?.into());
+    }
+    if let Ok(fields) = state.downcast::<PyDict>() {
+        for (marker, constructor) in [
+            ("__pydantic_fields__", "model_construct"),
+            ("__fields__", "construct"),
+        ] {
+            if class.hasattr(marker)? && class.hasattr(constructor)? {
+                return Ok(class.call_method(constructor, (), Some(fields))?.into());
+            }
+        }
+    }
+    let obj = class.call_method1("__new__", (class,))?;
+    if obj.hasattr("__setstate__")? {
+        obj.call_method1("__setstate__", (state,))?;
+    } else {
+        obj.getattr("__dict__")?.call_method1("update", (state,))?;
+    }
+    Ok(obj.into())
+}
+
+/// The `__getstate__()` of an object whose class overrides it, so pickle-aware
+/// classes keep caches and locks out of the payload. Pydantic models keep
+/// their field encoding.
+fn pickle_state(val: &PyAny) -> PyResult<Option<&PyAny>> {
+    let class = val.get_type();
+    let Ok(method) = class.getattr("__getstate__") else {
+        return Ok(None);
+    };
+    // object.__getstate__ (Python 3.11+) returns the plain __dict__
+    let default = val.py().get_type::<PyAny>().getattr("__getstate__").ok();
+    if default.is_some_and(|default| method.is(default))
+        || class.hasattr("__pydantic_fields__")?
+        || class.hasattr("__fields__")?
+    {
+        return Ok(None);
+    }
+    Ok(Some(val.call_method0("__getstate__")?))
+}
+
+/// The values as booleans if every one of them is a `bool`.
+fn bool_values<'p>(values: impl Iterator<Item = &'p PyAny>) -> PyResult<Option<Vec<bool>>> {
+    let mut bits = Vec::with_capacity(values.size_hint().0);
+    for value in values {
+        match value.downcast::<PyBool>() {
+            Ok(b) => bits.push(b.is_true()),
+            Err(_) => return Ok(None),
+        }
+    }
+    Ok(Some(bits))
+}
 
-                    let id = self.get_or_create_string_id_fast(key_str);
-                    self.work_buffer.extend_from_slice(&id.to_le_bytes());
-                    self.serialize_any_optimized(v)?;
+/// Append `bits` 8 per byte, least significant bit first.
+fn pack_bits(out: &mut Vec<u8>, bits: &[bool]) {
+    out.extend(bits.chunks(8).map(|chunk| {
+        chunk
+            .iter()
+            .enumerate()
+            .fold(0u8, |byte, (i, &bit)| byte | (bit as u8) << i)
+    }));
+}
+
+fn write_chunked(fp: &PyAny, data: &[u8]) -> PyResult<()> {
+    let py = fp.py();
+    for chunk in data.chunks(IO_CHUNK_SIZE) {
+        let mut written = 0;
+        // Raw streams may accept fewer bytes than offered
+        while written < chunk.len() {
+            let result = fp.call_method1("write", (PyBytes::new(py, &chunk[written..]),))?;
+            // A non-blocking raw stream returns None when it took nothing
+            if result.is_none() {
+                return Err(PyErr::new::<pyo3::exceptions::PyBlockingIOError, _>(
+                    "Sink accepted no data; non-blocking streams are not supported",
+                ));
+            }
+            match result.extract::<i64>()? {
+                n if n > 0 => written += n as usize,
+                n => {
+                    return Err(PyErr::new::<pyo3::exceptions::PyOSError, _>(format!(
+                        "Sink write() returned {} with {} bytes left to write",
+                        n,
+                        chunk.len() - written
+                    )))
                 }
+            }
+        }
+    }
+    Ok(())
+}
 
-                self.work_buffer.push(0x7F);
-                return Ok(());
+fn read_chunked(py: Python, fp: &PyAny) -> PyResult<Vec<u8>> {
+    let mut data = Vec::with_capacity(INITIAL_BUFFER_SIZE);
+
+    if fp.hasattr("readinto")? {
+        let chunk = PyByteArray::new(py, &[0u8; IO_CHUNK_SIZE]);
+        loop {
+            let result = fp.call_method1("readinto", (chunk,))?;
+            if result.is_none() {
+                return Err(PyErr::new::<pyo3::exceptions::PyBlockingIOError, _>(
+                    "Source returned no data; non-blocking streams are not supported",
+                ));
+            }
+            let n = result.extract::<usize>()?;
+            if n == 0 {
+                break;
+            }
+            data.extend_from_slice(unsafe { &chunk.as_bytes()[..n] });
+        }
+    } else {
+        loop {
+            let result = fp.call_method1("read", (IO_CHUNK_SIZE,))?;
+            if result.is_none() {
+                return Err(PyErr::new::<pyo3::exceptions::PyBlockingIOError, _>(
+                    "Source returned no data; non-blocking streams are not supported",
+                ));
             }
+            let bytes = result.extract::<&[u8]>()?;
+            if bytes.is_empty() {
+                break;
+            }
+            data.extend_from_slice(bytes);
         }
-
-        // Fallback: convert to string
-        let str_repr = val.str()?.extract::<String>()?;
-        self.work_buffer.push(0x50);
-        let bytes = str_repr.as_bytes();
-        self.work_buffer
-            .extend_from_slice(&(bytes.len() as u32).to_le_bytes());
-        self.work_buffer.extend_from_slice(bytes);
-        Ok(())
     }
-}
 
-#[pymodule]
-fn _b_fast(_py: Python, m: &PyModule) -> PyResult<()> {
-    m.add_class::<BFast>()?;
-    m.add(
-        "BFastError",
-        _py.get_type::<pyo3::exceptions::PyValueError>(),
-    )?;
-    Ok(())
+    Ok(data)
 }
 
 fn decompress_packed(data: &[u8]) -> Result<Cow<'_, [u8]>, String> {
     if data.len() < 2 {
         return Err("Buffer too small for B-FAST payload".to_string());
     }
+    if compression::is_packed(data) {
+        return compression::unpack_frame(data).map(Cow::Owned);
+    }
     if &data[0..2] == b"BF" {
         return Ok(Cow::Borrowed(data));
     }
-    if data.len() < 8 {
-        return Err("Buffer too small for compressed B-FAST data".to_string());
-    }
-
-    // Try single-chunk decompression first
-    if let Ok(decompressed) = lz4_flex::decompress_size_prepended(data) {
-        return Ok(Cow::Owned(decompressed));
-    }
-
-    // Fall back to parallel chunk decompression
-    let uncompressed_size = u32::from_le_bytes(data[0..4].try_into().unwrap()) as usize;
-    let chunks_count = u32::from_le_bytes(data[4..8].try_into().unwrap()) as usize;
-
-    let max_possible_chunks = (data.len() - 8) / 4;
-    if chunks_count > max_possible_chunks {
-        return Err("Invalid chunks count in parallel compression header".to_string());
-    }
-
-    let mut offset = 8;
-    let mut chunk_slices = Vec::with_capacity(chunks_count);
-
-    for _ in 0..chunks_count {
-        if offset + 4 > data.len() {
-            return Err("Unexpected end of data in parallel compression chunk headers".to_string());
-        }
-        let chunk_len = u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap()) as usize;
-        offset += 4;
-        if offset + chunk_len > data.len() {
-            return Err("Unexpected end of data in parallel compression chunk data".to_string());
-        }
-        chunk_slices.push(&data[offset..offset + chunk_len]);
-        offset += chunk_len;
-    }
-
-    let decompressed_chunks: Result<Vec<Vec<u8>>, _> = chunk_slices
-        .into_par_iter()
-        .map(|chunk_data| lz4_flex::decompress_size_prepended(chunk_data))
-        .collect();
-
-    let decompressed_chunks =
-        decompressed_chunks.map_err(|e| format!("LZ4 chunk decompression failed: {}", e))?;
-    let result = decompressed_chunks.concat();
-    if result.len() != uncompressed_size {
-        return Err(format!(
-            "Decompressed size mismatch: expected {}, got {}",
-            uncompressed_size,
-            result.len()
-        ));
-    }
-    Ok(Cow::Owned(result))
+    compression::decompress(data).map(Cow::Owned)
 }
 
 struct BFastParser<'a, 'py> {
@@ -948,15 +2888,207 @@ struct BFastParser<'a, 'py> {
     data: &'a [u8],
     offset: usize,
     string_table: &'a [String],
+    // Interned key objects, created on first use and shared by every record
+    keys: Vec<Option<&'py PyString>>,
     datetime_class: &'py PyAny,
     date_class: &'py PyAny,
     time_class: &'py PyAny,
     uuid_class: &'py PyAny,
     decimal_class: &'py PyAny,
     recursion_depth: usize,
+    blob_view: Option<&'py PyAny>,
+    shared_refs: bool,
+    // Frames written with dedup number their containers in post-order
+    track_refs: bool,
+    refs: Vec<(usize, PyObject)>,
+    replaying: usize,
+    // MappingProxyType when decoding immutable structures
+    frozen: Option<&'py PyAny>,
+    allowed_classes: Option<Arc<Vec<PyObject>>>,
+    ranges_as_lists: bool,
+    blob_resolver: Option<PyObject>,
+    max_depth: usize,
+    key_cache: Option<Arc<keys::KeyCache>>,
+    // Floats come back as Decimals
+    decimal_floats: bool,
+    duplicate_keys: Option<duplicates::DuplicateKeys>,
+    // Wire key names mapped to the names handed to the caller
+    aliases: Option<Arc<HashMap<String, String>>>,
 }
 
 impl<'a, 'py> BFastParser<'a, 'py> {
+    /// Parser over a frame whose string table is already read. Set `offset`
+    /// to the value to decode before calling `parse`.
+    fn new(
+        py: Python<'py>,
+        data: &'a [u8],
+        string_table: &'a [String],
+        blob_view: Option<&'py PyAny>,
+        options: &DecodeOptions,
+    ) -> PyResult<Self> {
+        let datetime_module = py.import("datetime")?;
+        let uuid_module = py.import("uuid")?;
+        let decimal_module = py.import("decimal")?;
+        let frozen = match options.immutable && options.schema.is_none() {
+            true => Some(py.import("types")?.getattr("MappingProxyType")?),
+            false => None,
+        };
+        Ok(BFastParser {
+            py,
+            data,
+            offset: 0,
+            string_table,
+            keys: vec![None; string_table.len()],
+            datetime_class: datetime_module.getattr("datetime")?,
+            date_class: datetime_module.getattr("date")?,
+            time_class: datetime_module.getattr("time")?,
+            uuid_class: uuid_module.getattr("UUID")?,
+            decimal_class: decimal_module.getattr("Decimal")?,
+            recursion_depth: 0,
+            blob_view,
+            shared_refs: options.shared_refs,
+            track_refs: data[2] & FLAG_SHARED_REFS != 0,
+            refs: Vec::new(),
+            replaying: 0,
+            frozen,
+            allowed_classes: options.allowed_classes.clone(),
+            ranges_as_lists: options.ranges_as_lists,
+            blob_resolver: options.blob_resolver.clone(),
+            max_depth: options.limits.max_depth,
+            key_cache: options.key_cache.clone(),
+            decimal_floats: options.decimal_floats,
+            duplicate_keys: options.duplicate_keys,
+            aliases: options.aliases.clone(),
+        })
+    }
+
+    /// A decoded float, as a Decimal of its shortest repr when decoding
+    /// floats as Decimals.
+    fn float(&self, val: f64) -> PyResult<PyObject> {
+        if !self.decimal_floats || !self.allows(self.decimal_class) {
+            return Ok(val.into_py(self.py));
+        }
+        Ok(self.decimal_class.call1((format!("{:?}", val),))?.into())
+    }
+
+    /// Whether decoding may instantiate `class`.
+    fn allows(&self, class: &PyAny) -> bool {
+        match &self.allowed_classes {
+            Some(allowed) => allowed.iter().any(|c| c.as_ptr() == class.as_ptr()),
+            None => true,
+        }
+    }
+
+    /// Whether decoding may instantiate `module.name`, without importing
+    /// the module when every class is allowed.
+    fn allows_class(&self, module: &str, name: &str) -> bool {
+        self.allowed_classes.is_none()
+            || self
+                .py
+                .import(module)
+                .and_then(|module| module.getattr(name))
+                .is_ok_and(|class| self.allows(class))
+    }
+
+    /// The allowed class named `module.qualname` in a custom object. Classes
+    /// are never imported by name, so nothing outside `allowed_classes` is
+    /// restored.
+    fn restorer(&self, name: &str) -> PyResult<Option<&'py PyAny>> {
+        let Some(allowed) = &self.allowed_classes else {
+            return Ok(None);
+        };
+        for class in allowed.iter() {
+            let class = class.clone_ref(self.py).into_ref(self.py);
+            let qualified = format!(
+                "{}.{}",
+                class.getattr("__module__")?,
+                class.getattr("__qualname__")?
+            );
+            // State written by __getstate__ can always be restored
+            let restorable = !class.hasattr("__bfast__")? || class.hasattr("__bfast_restore__")?;
+            if qualified == name && restorable {
+                return Ok(Some(class));
+            }
+        }
+        Ok(None)
+    }
+
+    /// An ISO 8601 value decoded as `class`, or left a string when the class
+    /// is not allowed.
+    fn parse_iso(&self, class: &PyAny, iso_str: &str) -> PyResult<PyObject> {
+        if !self.allows(class) {
+            return Ok(PyString::new(self.py, iso_str).into());
+        }
+        Ok(class.call_method1("fromisoformat", (iso_str,))?.into())
+    }
+
+    /// A decoded list: a tuple when decoding immutable structures.
+    fn sequence<T, U>(&self, items: impl IntoIterator<Item = T, IntoIter = U>) -> PyObject
+    where
+        T: ToPyObject,
+        U: ExactSizeIterator<Item = T>,
+    {
+        match self.frozen {
+            Some(_) => PyTuple::new(self.py, items).into(),
+            None => PyList::new(self.py, items).into(),
+        }
+    }
+
+    /// A decoded dict: a read-only proxy when decoding immutable structures.
+    fn mapping(&self, dict: &PyDict) -> PyResult<PyObject> {
+        match self.frozen {
+            Some(proxy) => Ok(proxy.call1((dict,))?.into()),
+            None => Ok(dict.into()),
+        }
+    }
+
+    /// The interned string for key `id` of the string table, under its alias
+    /// when it has one.
+    fn key(&mut self, id: usize) -> PyResult<&'py PyString> {
+        let Some(slot) = self.keys.get_mut(id) else {
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                "Invalid string table index: {}",
+                id
+            )));
+        };
+        Ok(*slot.get_or_insert_with(|| {
+            let name = &self.string_table[id];
+            let name = match &self.aliases {
+                Some(aliases) => aliases.get(name).unwrap_or(name),
+                None => name,
+            };
+            match &self.key_cache {
+                Some(cache) => cache.get(self.py, name),
+                None => PyString::intern(self.py, name),
+            }
+        }))
+    }
+
+    /// The int tagged `tag`, whose tag byte was already read; None when
+    /// `tag` is not an int.
+    fn parse_int(&mut self, tag: u8) -> PyResult<Option<i64>> {
+        let Some(width) = scan::int_width(tag) else {
+            return Ok(None);
+        };
+        self.check_bounds(width)?;
+        let value = scan::int_value(tag, &self.data[self.offset..self.offset + width]);
+        self.offset += width;
+        Ok(Some(value))
+    }
+
+    /// A str built straight from UTF-8 bytes of the frame. CPython's decoder
+    /// validates while it copies, so the bytes are read once; invalid input
+    /// raises UnicodeDecodeError (a ValueError).
+    fn utf8_string(&self, bytes: &[u8]) -> PyResult<PyObject> {
+        unsafe {
+            let ptr = pyo3::ffi::PyUnicode_FromStringAndSize(
+                bytes.as_ptr() as *const std::os::raw::c_char,
+                bytes.len() as pyo3::ffi::Py_ssize_t,
+            );
+            PyObject::from_owned_ptr_or_err(self.py, ptr)
+        }
+    }
+
     fn check_bounds(&self, size: usize) -> PyResult<()> {
         if self.offset + size > self.data.len() {
             return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
@@ -966,9 +3098,29 @@ impl<'a, 'py> BFastParser<'a, 'py> {
         Ok(())
     }
 
+    fn datetime_from_iso(&self, iso_str: &str) -> PyResult<PyObject> {
+        // Sub-microsecond fractions (pandas Timestamps) need pandas to survive;
+        // without it they stay an ISO string rather than lose the extra digits
+        if let Some(dot) = iso_str.find('.') {
+            let frac_len = iso_str[dot + 1..]
+                .bytes()
+                .take_while(|b| b.is_ascii_digit())
+                .count();
+            if frac_len > 6 {
+                if let Ok(pandas) = self.py.import("pandas") {
+                    if self.allows_class("pandas", "Timestamp") {
+                        return Ok(pandas.getattr("Timestamp")?.call1((iso_str,))?.into());
+                    }
+                }
+                return Ok(PyString::new(self.py, iso_str).into());
+            }
+        }
+        self.parse_iso(self.datetime_class, iso_str)
+    }
+
     fn parse(&mut self) -> PyResult<PyObject> {
         self.recursion_depth += 1;
-        if self.recursion_depth > MAX_RECURSION_DEPTH {
+        if self.recursion_depth > self.max_depth {
             return Err(PyErr::new::<pyo3::exceptions::PyRecursionError, _>(
                 "Maximum recursion depth exceeded during B-FAST decoding",
             ));
@@ -978,12 +3130,87 @@ impl<'a, 'py> BFastParser<'a, 'py> {
         let tag = self.data[self.offset];
         self.offset += 1;
 
+        let start = self.offset - 1;
         let result = self.parse_tag(tag);
 
+        if self.track_refs
+            && self.replaying == 0
+            && matches!(tag, 0x60 | 0x70 | TAG_NULL_BITMAP | TAG_INT_OBJECT)
+        {
+            if let Ok(obj) = &result {
+                self.refs.push((start, obj.clone_ref(self.py)));
+            }
+        }
+
         self.recursion_depth -= 1;
         result
     }
 
+    fn parse_ref(&mut self) -> PyResult<PyObject> {
+        self.check_bounds(4)?;
+        let index = u32::from_le_bytes(self.data[self.offset..self.offset + 4].try_into().unwrap())
+            as usize;
+        self.offset += 4;
+
+        let (start, obj) = match self.refs.get(index) {
+            Some((start, obj)) => (*start, obj.clone_ref(self.py)),
+            None => {
+                return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                    "Invalid back-reference index: {}",
+                    index
+                )))
+            }
+        };
+        // Immutable values are safe to share
+        if self.shared_refs || self.frozen.is_some() {
+            return Ok(obj);
+        }
+
+        // Re-parse the referenced bytes for an independent copy
+        let resume = self.offset;
+        self.offset = start;
+        self.replaying += 1;
+        let result = self.parse();
+        self.replaying -= 1;
+        self.offset = resume;
+        result
+    }
+
+    /// `count` bit-packed booleans starting at the current offset.
+    fn parse_bits(&mut self, count: usize) -> PyResult<Vec<bool>> {
+        let length = count.div_ceil(8);
+        self.check_bounds(length)?;
+        let bytes = &self.data[self.offset..self.offset + length];
+        self.offset += length;
+        Ok((0..count)
+            .map(|i| bytes[i / 8] & (1 << (i % 8)) != 0)
+            .collect())
+    }
+
+    /// Regular `([key ID][value])* 0x7F` object entries, added to `dict`.
+    fn parse_entries(&mut self, dict: &PyDict) -> PyResult<()> {
+        while self.offset < self.data.len() && self.data[self.offset] != 0x7F {
+            self.check_bounds(4)?;
+            let key_id =
+                u32::from_le_bytes(self.data[self.offset..self.offset + 4].try_into().unwrap())
+                    as usize;
+            self.offset += 4;
+
+            let key = self.key(key_id)?;
+            let value = self.parse()?;
+            self.insert(dict, key, value)?;
+        }
+
+        if self.offset >= self.data.len() {
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+                "Object not properly terminated",
+            ));
+        }
+
+        self.offset += 1; // Skip 0x7F
+        Ok(())
+    }
+
     fn parse_tag(&mut self, tag: u8) -> PyResult<PyObject> {
         // Null
         if tag == 0x10 {
@@ -998,28 +3225,17 @@ impl<'a, 'py> BFastParser<'a, 'py> {
             return Ok(true.into_py(self.py));
         }
 
-        // Int64
-        if tag == 0x38 {
-            self.check_bounds(8)?;
-            let val =
-                i64::from_le_bytes(self.data[self.offset..self.offset + 8].try_into().unwrap());
-            self.offset += 8;
-            return Ok(val.into_py(self.py));
-        }
-
-        // Small integers (bit-packed)
-        if (tag & 0xF0) == 0x30 {
-            let val = (tag & 0x0F) as i64;
+        // Integers, packed in the tag or in 1, 2, 4 or 8 bytes
+        if let Some(val) = self.parse_int(tag)? {
             return Ok(val.into_py(self.py));
         }
 
-        // Float64
-        if tag == 0x40 {
-            self.check_bounds(8)?;
-            let val =
-                f64::from_le_bytes(self.data[self.offset..self.offset + 8].try_into().unwrap());
-            self.offset += 8;
-            return Ok(val.into_py(self.py));
+        // Float64, or Float32 from float32 encoders
+        if let Some(width) = scan::float_width(tag) {
+            self.check_bounds(width)?;
+            let val = scan::float_value(&self.data[self.offset..self.offset + width]);
+            self.offset += width;
+            return self.float(val);
         }
 
         // Raw string
@@ -1032,13 +3248,7 @@ impl<'a, 'py> BFastParser<'a, 'py> {
             self.check_bounds(length)?;
             let str_bytes = &self.data[self.offset..self.offset + length];
             self.offset += length;
-            let val = std::str::from_utf8(str_bytes).map_err(|e| {
-                PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
-                    "Invalid UTF-8 in string: {}",
-                    e
-                ))
-            })?;
-            return Ok(PyString::new(self.py, val).into());
+            return self.utf8_string(str_bytes);
         }
 
         // List/Array
@@ -1054,39 +3264,56 @@ impl<'a, 'py> BFastParser<'a, 'py> {
             for _ in 0..length {
                 list.push(self.parse()?);
             }
-            return Ok(PyList::new(self.py, list).into());
+            return Ok(self.sequence(list));
+        }
+
+        // Records stored column by column
+        if tag == TAG_COLUMNAR {
+            return self.parse_columnar();
         }
 
         // Object start
         if tag == 0x70 {
             let dict = PyDict::new(self.py);
-            while self.offset < self.data.len() && self.data[self.offset] != 0x7F {
-                self.check_bounds(4)?;
-                let key_id =
-                    u32::from_le_bytes(self.data[self.offset..self.offset + 4].try_into().unwrap())
-                        as usize;
-                self.offset += 4;
-
-                if key_id >= self.string_table.len() {
-                    return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
-                        "Invalid string table index: {}",
-                        key_id
-                    )));
-                }
+            self.parse_entries(dict)?;
+            return self.mapping(dict);
+        }
 
-                let key = &self.string_table[key_id];
-                let value = self.parse()?;
-                dict.set_item(key, value)?;
+        // Object with a presence bitmap for its leading fields
+        if tag == TAG_NULL_BITMAP {
+            self.check_bounds(6)?;
+            let first_id =
+                u32::from_le_bytes(self.data[self.offset..self.offset + 4].try_into().unwrap())
+                    as usize;
+            let count = u16::from_le_bytes(
+                self.data[self.offset + 4..self.offset + 6]
+                    .try_into()
+                    .unwrap(),
+            ) as usize;
+            self.offset += 6;
+            if first_id + count > self.string_table.len() {
+                return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                    "Invalid string table index: {}",
+                    first_id + count - 1
+                )));
             }
+            let bitmap_len = count.div_ceil(8);
+            self.check_bounds(bitmap_len)?;
+            let bitmap_start = self.offset;
+            self.offset += bitmap_len;
 
-            if self.offset >= self.data.len() {
-                return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
-                    "Object not properly terminated",
-                ));
+            let dict = PyDict::new(self.py);
+            for i in 0..count {
+                let key = self.key(first_id + i)?;
+                if self.data[bitmap_start + i / 8] & (1 << (i % 8)) != 0 {
+                    let value = self.parse()?;
+                    self.insert(dict, key, value)?;
+                } else {
+                    self.insert(dict, key, self.py.None())?;
+                }
             }
-
-            self.offset += 1; // Skip 0x7F
-            return Ok(dict.into());
+            self.parse_entries(dict)?;
+            return self.mapping(dict);
         }
 
         // Bytes
@@ -1097,11 +3324,140 @@ impl<'a, 'py> BFastParser<'a, 'py> {
                     as usize;
             self.offset += 4;
             self.check_bounds(length)?;
+            if let Some(view) = self.blob_view {
+                let slice = view.get_item(PySlice::new(
+                    self.py,
+                    self.offset as isize,
+                    (self.offset + length) as isize,
+                    1,
+                ))?;
+                self.offset += length;
+                return Ok(slice.into());
+            }
             let bytes_val = &self.data[self.offset..self.offset + length];
             self.offset += length;
             return Ok(PyBytes::new(self.py, bytes_val).into());
         }
 
+        // Bit-packed booleans
+        if tag == TAG_BOOL_ARRAY {
+            self.check_bounds(4)?;
+            let count =
+                u32::from_le_bytes(self.data[self.offset..self.offset + 4].try_into().unwrap())
+                    as usize;
+            self.offset += 4;
+            let bits = self.parse_bits(count)?;
+            return Ok(self.sequence(bits));
+        }
+
+        // array.array (0x93) - decoded as an array of the same type code
+        if tag == TAG_TYPED_ARRAY {
+            self.check_bounds(5)?;
+            let code = self.data[self.offset];
+            let length = u32::from_le_bytes(
+                self.data[self.offset + 1..self.offset + 5]
+                    .try_into()
+                    .unwrap(),
+            ) as usize;
+            self.offset += 5;
+            self.check_bounds(length)?;
+            let itemsize = typed_array_itemsize(code).ok_or_else(|| {
+                PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                    "Unsupported typed array code: {:?}",
+                    code as char
+                ))
+            })?;
+            if !length.is_multiple_of(itemsize) {
+                return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+                    "Typed array length is not a multiple of its item size",
+                ));
+            }
+            let raw = PyBytes::new(self.py, &self.data[self.offset..self.offset + length]);
+            self.offset += length;
+            let class = array_class(self.py)?;
+            let array = class.call1(((code as char).to_string(), raw))?;
+            if self.frozen.is_some() {
+                return Ok(self.py.get_type::<PyTuple>().call1((array,))?.into());
+            }
+            if !self.allows(class) {
+                return Ok(array.call_method0("tolist")?.into());
+            }
+            return Ok(array.into());
+        }
+
+        // Integer-keyed dict (0x72)
+        if tag == TAG_INT_OBJECT {
+            self.check_bounds(4)?;
+            let count =
+                u32::from_le_bytes(self.data[self.offset..self.offset + 4].try_into().unwrap())
+                    as usize;
+            self.offset += 4;
+            let dict = PyDict::new(self.py);
+            for _ in 0..count {
+                let key = self.parse()?;
+                let value = self.parse()?;
+                self.insert(dict, key.as_ref(self.py), value)?;
+            }
+            return self.mapping(dict);
+        }
+
+        // Sections (0x73): the index gives each value's key and length
+        if tag == TAG_SECTIONS {
+            self.check_bounds(4)?;
+            let count =
+                u32::from_le_bytes(self.data[self.offset..self.offset + 4].try_into().unwrap())
+                    as usize;
+            self.offset += 4;
+            self.check_bounds(count * 8)?;
+            let index = self.offset;
+            self.offset += count * 8;
+            let dict = PyDict::new(self.py);
+            for i in 0..count {
+                let entry = &self.data[index + i * 8..index + i * 8 + 8];
+                let id = u32::from_le_bytes(entry[..4].try_into().unwrap()) as usize;
+                let length = u32::from_le_bytes(entry[4..].try_into().unwrap()) as usize;
+                let key = self.key(id)?;
+                let start = self.offset;
+                let value = self.parse()?;
+                if self.offset - start != length {
+                    return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+                        "Section length does not match its index",
+                    ));
+                }
+                self.insert(dict, key, value)?;
+            }
+            return self.mapping(dict);
+        }
+
+        // range (0x94) - start, stop, step
+        if tag == TAG_RANGE {
+            let mut bounds = [0i64; 3];
+            for bound in &mut bounds {
+                self.check_bounds(1)?;
+                let int_tag = self.data[self.offset];
+                self.offset += 1;
+                *bound = match self.parse_int(int_tag)? {
+                    Some(value) => value,
+                    None => {
+                        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                            "Invalid range bound tag: 0x{:02x}",
+                            int_tag
+                        )))
+                    }
+                };
+            }
+            let range = self
+                .py
+                .import("builtins")?
+                .getattr("range")?
+                .call1((bounds[0], bounds[1], bounds[2]))?;
+            if self.ranges_as_lists {
+                let values = range.iter()?.collect::<PyResult<Vec<_>>>()?;
+                return Ok(self.sequence(values));
+            }
+            return Ok(range.into());
+        }
+
         // NumPy Array (f64)
         if tag == 0x90 {
             self.check_bounds(4)?;
@@ -1116,19 +3472,86 @@ impl<'a, 'py> BFastParser<'a, 'py> {
             for _ in 0..length {
                 let val =
                     f64::from_le_bytes(self.data[self.offset..self.offset + 8].try_into().unwrap());
-                list.push(val.into_py(self.py));
+                list.push(self.float(val)?);
                 self.offset += 8;
             }
-            return Ok(PyList::new(self.py, list).into());
+            return Ok(self.sequence(list));
+        }
+
+        // Back-reference (0xA0) to an earlier container
+        if tag == TAG_REF && self.track_refs {
+            return self.parse_ref();
+        }
+
+        // Typed tensor (0x91) - decoded as a NumPy array
+        if tag == TAG_TENSOR {
+            self.check_bounds(3)?;
+            let code = self.data[self.offset];
+            let bits = self.data[self.offset + 1];
+            let ndim = self.data[self.offset + 2] as usize;
+            self.offset += 3;
+
+            self.check_bounds(ndim * 4 + 4)?;
+            let mut shape = Vec::with_capacity(ndim);
+            for _ in 0..ndim {
+                let dim =
+                    u32::from_le_bytes(self.data[self.offset..self.offset + 4].try_into().unwrap())
+                        as usize;
+                shape.push(dim);
+                self.offset += 4;
+            }
+            let length =
+                u32::from_le_bytes(self.data[self.offset..self.offset + 4].try_into().unwrap())
+                    as usize;
+            self.offset += 4;
+            self.check_bounds(length)?;
+
+            let dtype = dlpack::numpy_dtype(code, bits).ok_or_else(|| {
+                PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                    "Unsupported tensor dtype (code={}, bits={})",
+                    code, bits
+                ))
+            })?;
+            let expected = shape
+                .iter()
+                .try_fold((bits / 8) as usize, |acc, &d| acc.checked_mul(d));
+            if expected != Some(length) {
+                return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+                    "Tensor data length does not match its shape",
+                ));
+            }
+
+            let raw = PyBytes::new(self.py, &self.data[self.offset..self.offset + length]);
+            self.offset += length;
+            if !self.allows_class("numpy", "ndarray") {
+                let tensor = PyDict::new(self.py);
+                tensor.set_item("dtype", dtype)?;
+                tensor.set_item("shape", shape)?;
+                tensor.set_item("data", raw)?;
+                return Ok(tensor.into());
+            }
+            let numpy = self.py.import("numpy")?;
+            let array = numpy
+                .call_method1("frombuffer", (raw, dtype))?
+                .call_method1("reshape", (PyTuple::new(self.py, shape),))?;
+            return Ok(array.into());
         }
 
-        // DateTime (0xD1) - ISO 8601 string
+        // DateTime (0xD1) - ISO 8601 string, or epoch nanoseconds (temporal.rs)
         if tag == TAG_DATETIME {
             self.check_bounds(4)?;
             let length =
                 u32::from_le_bytes(self.data[self.offset..self.offset + 4].try_into().unwrap())
                     as usize;
             self.offset += 4;
+            if length == temporal::DATETIME_NANOS as usize {
+                self.check_bounds(temporal::DATETIME_NANOS_LEN)?;
+                let at = self.offset;
+                let nanos = i64::from_le_bytes(self.data[at..at + 8].try_into().unwrap());
+                let offset = i32::from_le_bytes(self.data[at + 8..at + 12].try_into().unwrap());
+                self.offset += temporal::DATETIME_NANOS_LEN;
+                return self.datetime_from_iso(&temporal::iso_from_nanos(nanos, offset));
+            }
             self.check_bounds(length)?;
             let str_bytes = &self.data[self.offset..self.offset + length];
             self.offset += length;
@@ -1138,10 +3561,7 @@ impl<'a, 'py> BFastParser<'a, 'py> {
                     e
                 ))
             })?;
-            let obj = self
-                .datetime_class
-                .call_method1("fromisoformat", (iso_str,))?;
-            return Ok(obj.into());
+            return self.datetime_from_iso(iso_str);
         }
 
         // Date (0xD2) - ISO 8601 date string
@@ -1160,8 +3580,7 @@ impl<'a, 'py> BFastParser<'a, 'py> {
                     e
                 ))
             })?;
-            let obj = self.date_class.call_method1("fromisoformat", (iso_str,))?;
-            return Ok(obj.into());
+            return self.parse_iso(self.date_class, iso_str);
         }
 
         // Time (0xD3) - ISO 8601 time string
@@ -1180,7 +3599,57 @@ impl<'a, 'py> BFastParser<'a, 'py> {
                     e
                 ))
             })?;
-            let obj = self.time_class.call_method1("fromisoformat", (iso_str,))?;
+            return self.parse_iso(self.time_class, iso_str);
+        }
+
+        // Date (0xD7) - i32 days since 1970-01-01
+        if tag == TAG_DATE_DAYS {
+            self.check_bounds(4)?;
+            let days =
+                i32::from_le_bytes(self.data[self.offset..self.offset + 4].try_into().unwrap());
+            self.offset += 4;
+            if !self.allows(self.date_class) {
+                return Ok(PyString::new(self.py, &temporal::date_iso(days as i64)).into());
+            }
+            let (year, month, day) = temporal::civil_from_days(days as i64);
+            return Ok(self.date_class.call1((year, month, day))?.into());
+        }
+
+        // Time (0xD8) - u64 nanoseconds since midnight
+        if tag == TAG_TIME_NANOS {
+            self.check_bounds(8)?;
+            let nanos =
+                u64::from_le_bytes(self.data[self.offset..self.offset + 8].try_into().unwrap());
+            self.offset += 8;
+            if !self.allows(self.time_class) {
+                return Ok(PyString::new(self.py, &temporal::time_iso(nanos)).into());
+            }
+            let seconds = nanos / 1_000_000_000;
+            let micros = nanos % 1_000_000_000 / 1_000;
+            let time = (seconds / 3_600, seconds / 60 % 60, seconds % 60, micros);
+            return Ok(self.time_class.call1(time)?.into());
+        }
+
+        // Timedelta (0xD6) - i64 nanoseconds
+        if tag == TAG_TIMEDELTA {
+            self.check_bounds(8)?;
+            let nanos =
+                i64::from_le_bytes(self.data[self.offset..self.offset + 8].try_into().unwrap());
+            self.offset += 8;
+            if nanos % 1_000 != 0 {
+                if let Ok(pandas) = self.py.import("pandas") {
+                    if self.allows_class("pandas", "Timedelta") {
+                        return Ok(pandas.getattr("Timedelta")?.call1((nanos,))?.into());
+                    }
+                }
+            }
+            let timedelta_class = self.py.import("datetime")?.getattr("timedelta")?;
+            if !self.allows(timedelta_class) {
+                return Ok(nanos.into_py(self.py));
+            }
+            let kwargs = PyDict::new(self.py);
+            kwargs.set_item("microseconds", nanos / 1_000)?;
+            let obj = timedelta_class.call((), Some(kwargs))?;
             return Ok(obj.into());
         }
 
@@ -1200,10 +3669,80 @@ impl<'a, 'py> BFastParser<'a, 'py> {
                     e
                 ))
             })?;
+            if !self.allows(self.uuid_class) {
+                // Canonical str(UUID) form
+                let canonical = match hex_str.len() == 32 && hex_str.is_ascii() {
+                    true => [(0, 8), (8, 12), (12, 16), (16, 20), (20, 32)]
+                        .map(|(start, end)| &hex_str[start..end])
+                        .join("-"),
+                    false => hex_str.to_string(),
+                };
+                return Ok(PyString::new(self.py, &canonical).into());
+            }
             let obj = self.uuid_class.call1((hex_str,))?;
             return Ok(obj.into());
         }
 
+        // Custom object (0xD9) - restored only for allowed classes
+        if tag == TAG_CUSTOM {
+            self.check_bounds(4)?;
+            let length =
+                u32::from_le_bytes(self.data[self.offset..self.offset + 4].try_into().unwrap())
+                    as usize;
+            self.offset += 4;
+            self.check_bounds(length)?;
+            let name = std::str::from_utf8(&self.data[self.offset..self.offset + length]).map_err(
+                |e| {
+                    PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                        "Invalid UTF-8 in class name: {}",
+                        e
+                    ))
+                },
+            )?;
+            self.offset += length;
+            let class = self.restorer(name)?;
+            let value = self.parse()?;
+            return match class {
+                Some(class) => restore(class, value.as_ref(self.py)),
+                None => Ok(value),
+            };
+        }
+
+        // Extension type (0xE0), rebuilt by its registered decoder
+        if tag == TAG_EXT {
+            self.check_bounds(5)?;
+            let id = self.data[self.offset];
+            let length = u32::from_le_bytes(
+                self.data[self.offset + 1..self.offset + 5]
+                    .try_into()
+                    .unwrap(),
+            ) as usize;
+            self.offset += 5;
+            self.check_bounds(length)?;
+            let data = &self.data[self.offset..self.offset + length];
+            self.offset += length;
+            return ext::decode(self.py, id, data);
+        }
+
+        // Value kept outside the frame (0x95), fetched by its token
+        if tag == TAG_BLOB_REF {
+            self.check_bounds(4)?;
+            let length =
+                u32::from_le_bytes(self.data[self.offset..self.offset + 4].try_into().unwrap())
+                    as usize;
+            self.offset += 4;
+            self.check_bounds(length)?;
+            let token = self.utf8_string(&self.data[self.offset..self.offset + length])?;
+            self.offset += length;
+            let Some(resolver) = &self.blob_resolver else {
+                return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                    "Frame references the external blob {}; pass blob_resolver to decode it",
+                    token.as_ref(self.py).repr()?
+                )));
+            };
+            return resolver.call1(self.py, (token,));
+        }
+
         // Decimal (0xD5)
         if tag == TAG_DECIMAL {
             self.check_bounds(4)?;
@@ -1220,6 +3759,9 @@ impl<'a, 'py> BFastParser<'a, 'py> {
                     e
                 ))
             })?;
+            if !self.allows(self.decimal_class) {
+                return Ok(PyString::new(self.py, dec_str).into());
+            }
             let obj = self.decimal_class.call1((dec_str,))?;
             return Ok(obj.into());
         }
@@ -0,0 +1,126 @@
+// Memory held by an encoder between calls.
+//
+// Encoders keep their work buffer, string table and dedup spans from one
+// call to the next, so a long-lived encoder that once wrote a huge payload
+// keeps the capacity it needed. `BFast.memory_usage()` reports what is held
+// and `BFast.shrink_to_fit()` gives back what the next call does not need.
+//
+// With `max_buffer_bytes`, an encode whose buffer would grow past the cap
+// fails instead. The check runs before each value and before str, bytes,
+// arrays and tensors are copied in bulk, so the buffer overshoots the cap by
+// at most one scalar; a failed encode then drops the buffer, and a successful
+// one keeps no more capacity than the cap.
+//
+// Frames that get compressed leave their buffer behind for the next call.
+// Unless `shrink_factor` is None, the encoder keeps a rolling average of its
+// frame sizes and, after such a frame, trims a buffer holding more than
+// `shrink_factor` times that average back to the average, so memory returns
+// to its usual level a call or two after an occasional large payload.
+
+use std::mem;
+
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+use crate::{BFast, INITIAL_BUFFER_SIZE};
+
+/// Share of the newest frame in the rolling average of frame sizes.
+const AVERAGE_WINDOW: usize = 8;
+
+impl BFast {
+    /// Bytes held by each part of the encoder, as `memory_usage()` reports
+    /// them.
+    fn held_bytes(&self) -> [(&'static str, usize); 4] {
+        let table = self.string_table.capacity() * mem::size_of::<(String, u32)>()
+            + self
+                .string_table
+                .keys()
+                .map(|key| key.capacity())
+                .sum::<usize>();
+        [
+            ("work_buffer", self.work_buffer.capacity()),
+            ("string_table", table),
+            ("key_cache", mem::size_of_val(&self.key_cache)),
+            (
+                "dedup_spans",
+                self.dedup_spans.capacity() * mem::size_of::<(usize, usize)>(),
+            ),
+        ]
+    }
+
+    pub(crate) fn memory_report<'py>(&self, py: Python<'py>) -> PyResult<&'py PyDict> {
+        let report = PyDict::new(py);
+        let held = self.held_bytes();
+        for (part, bytes) in held {
+            report.set_item(part, bytes)?;
+        }
+        report.set_item("string_table_entries", self.string_table.len())?;
+        report.set_item("total", held.iter().map(|(_, bytes)| bytes).sum::<usize>())?;
+        Ok(report)
+    }
+
+    /// Fail when `additional` more bytes would take the work buffer past
+    /// `max_buffer_bytes`.
+    #[inline]
+    pub(crate) fn check_buffer_limit(&self, additional: usize) -> PyResult<()> {
+        match self.max_buffer_bytes {
+            Some(max) if self.work_buffer.len() + additional > max => {
+                Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                    "Payload needs more than max_buffer_bytes={} bytes to encode",
+                    max
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Pass on `result` after bringing the work buffer back within
+    /// `max_buffer_bytes`: dropped when the encode failed, trimmed to the
+    /// cap when it succeeded.
+    pub(crate) fn bound_buffer<T>(&mut self, result: PyResult<T>) -> PyResult<T> {
+        if let Some(max) = self.max_buffer_bytes {
+            match result {
+                Ok(_) => self.work_buffer.shrink_to(max),
+                Err(_) => {
+                    self.work_buffer.clear();
+                    self.work_buffer.shrink_to(INITIAL_BUFFER_SIZE);
+                }
+            }
+        }
+        result
+    }
+
+    /// Count a frame of `len` bytes in the rolling average frame size.
+    pub(crate) fn settle_buffer(&mut self, len: usize) {
+        self.average_frame = match self.average_frame {
+            0 => len,
+            average => (average * (AVERAGE_WINDOW - 1) + len) / AVERAGE_WINDOW,
+        };
+    }
+
+    /// Empty the work buffer once its frame has been copied out, trimming
+    /// it to the average frame size when it holds more than `shrink_factor`
+    /// times that.
+    pub(crate) fn release_frame(&mut self) {
+        self.work_buffer.clear();
+        if let Some(factor) = self.shrink_factor {
+            // Buffers of a few pages are left alone
+            let usual = self.average_frame.max(INITIAL_BUFFER_SIZE);
+            if self.work_buffer.capacity() / factor > usual {
+                self.work_buffer.shrink_to(usual);
+            }
+        }
+    }
+
+    /// Release the capacity the next call does not need; returns the bytes
+    /// released. Keys stay in the string table, as they do between calls.
+    pub(crate) fn release_memory(&mut self) -> usize {
+        let before: usize = self.held_bytes().iter().map(|(_, bytes)| bytes).sum();
+        self.work_buffer.clear();
+        self.work_buffer.shrink_to(INITIAL_BUFFER_SIZE);
+        self.dedup_spans = Vec::new();
+        self.string_table.shrink_to_fit();
+        let after: usize = self.held_bytes().iter().map(|(_, bytes)| bytes).sum();
+        before.saturating_sub(after)
+    }
+}
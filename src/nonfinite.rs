@@ -0,0 +1,76 @@
+// What the encoder writes for NaN and infinite floats.
+//
+// They are valid f64s and are written as such by default, but JSON has no
+// form for them and NaN never compares equal to itself. `non_finite="null"`
+// writes them as None instead and `non_finite="raise"` refuses them. The
+// policy covers float values wherever they are written: the generic and fast
+// paths, record batches, columns and NumPy float arrays. Typed arrays and
+// tensors keep their raw bytes.
+
+use pyo3::prelude::*;
+
+use crate::BFast;
+
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) enum NonFinite {
+    Allow,
+    Null,
+    Raise,
+}
+
+/// The policy called `name`.
+pub(crate) fn policy(name: &str) -> PyResult<NonFinite> {
+    match name {
+        "allow" => Ok(NonFinite::Allow),
+        "null" => Ok(NonFinite::Null),
+        "raise" => Ok(NonFinite::Raise),
+        _ => Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+            "Unknown non_finite policy {:?}; expected allow, null or raise",
+            name
+        ))),
+    }
+}
+
+fn non_finite_error(float: f64) -> PyErr {
+    PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+        "Cannot encode the float {} with non_finite=\"raise\"",
+        float
+    ))
+}
+
+impl BFast {
+    /// Write `float`, or what the policy makes of it when it is NaN or
+    /// infinite.
+    #[inline]
+    pub(crate) fn write_float(&mut self, float: f64) -> PyResult<()> {
+        if !float.is_finite() {
+            match self.non_finite {
+                NonFinite::Allow => {}
+                NonFinite::Null => {
+                    self.work_buffer.push(0x10);
+                    return Ok(());
+                }
+                NonFinite::Raise => return Err(non_finite_error(float)),
+            }
+        }
+        self.write_float_value(float);
+        Ok(())
+    }
+
+    /// Whether `floats` can be written bit for bit, as float columns and
+    /// arrays are; false when some have to be written as None instead, or
+    /// all as f32.
+    pub(crate) fn floats_as_is(&self, floats: &[f64]) -> PyResult<bool> {
+        if self.float32 {
+            return Ok(false);
+        }
+        if self.non_finite == NonFinite::Allow {
+            return Ok(true);
+        }
+        match floats.iter().find(|float| !float.is_finite()) {
+            None => Ok(true),
+            Some(&float) if self.non_finite == NonFinite::Raise => Err(non_finite_error(float)),
+            Some(_) => Ok(false),
+        }
+    }
+}
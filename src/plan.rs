@@ -0,0 +1,227 @@
+// Decode plans for lists written by the batch path.
+//
+// Such lists follow the record layout fingerprint (TAG_FINGERPRINT) of their
+// records, which all have the same fields in the same order. The first time
+// a fingerprint is decoded, the layout of its first record becomes a plan:
+// the field names as interned str objects, and which fields held a scalar
+// (None, bool, int, float or str). Records matching the plan are rebuilt
+// field by field with the planned keys, scalars decoded inline instead of
+// going through the tag dispatch; a record that does not match is decoded
+// the generic way. Plans are kept process-wide per fingerprint, so later
+// frames of the same shape start from the first one's plan.
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyString};
+
+use crate::scan::{float_value, float_width, int_value, int_width, Frame, TAG_FLOAT32};
+use crate::BFastParser;
+
+/// Plans kept before the cache starts over.
+const MAX_PLANS: usize = 256;
+
+struct DecodePlan {
+    keys: Vec<Py<PyString>>,
+    /// Whether each field held a scalar in the record the plan was made from.
+    scalar: Vec<bool>,
+}
+
+static PLANS: Mutex<BTreeMap<u64, Arc<DecodePlan>>> = Mutex::new(BTreeMap::new());
+
+#[inline]
+fn is_scalar(tag: u8) -> bool {
+    matches!(tag, 0x10 | 0x20 | 0x21 | 0x40 | TAG_FLOAT32 | 0x50) || int_width(tag).is_some()
+}
+
+impl<'a, 'py> BFastParser<'a, 'py> {
+    /// Parse the root value, decoding the records of a list with the plan
+    /// for `fingerprint`.
+    pub(crate) fn parse_planned(&mut self, fingerprint: u64) -> PyResult<PyObject> {
+        // Back-references count containers, which planned records skip
+        if self.track_refs || self.data.get(self.offset) != Some(&0x60) {
+            return self.parse();
+        }
+        let start = self.offset;
+        self.offset += 1;
+        self.check_bounds(4)?;
+        let length = u32::from_le_bytes(self.data[self.offset..self.offset + 4].try_into().unwrap())
+            as usize;
+        self.offset += 4;
+        if length == 0 {
+            self.offset = start;
+            return self.parse();
+        }
+        let Some((plan, ids)) = self.plan_for(fingerprint)? else {
+            self.offset = start;
+            return self.parse();
+        };
+
+        // The list and its records count as levels, like in `parse`
+        self.recursion_depth += 2;
+        if self.recursion_depth + 1 > self.max_depth {
+            self.recursion_depth -= 2;
+            self.offset = start;
+            return self.parse();
+        }
+        let max_elements = self.data.len() - self.offset;
+        let mut records = Vec::with_capacity(length.min(max_elements));
+        for _ in 0..length {
+            let record = match self.parse_planned_record(&plan, &ids)? {
+                Some(record) => record,
+                None => {
+                    self.recursion_depth -= 1;
+                    let record = self.parse();
+                    self.recursion_depth += 1;
+                    record?
+                }
+            };
+            records.push(record);
+        }
+        self.recursion_depth -= 2;
+        Ok(self.sequence(records))
+    }
+
+    /// The plan for `fingerprint` (made from the record at `offset` when
+    /// there is none yet) and the frame's key IDs of its fields, or None when
+    /// the plan does not fit this frame.
+    fn plan_for(&self, fingerprint: u64) -> PyResult<Option<(Arc<DecodePlan>, Vec<u32>)>> {
+        let cached = PLANS.lock().unwrap().get(&fingerprint).cloned();
+        let plan = match cached {
+            Some(plan) => plan,
+            None => {
+                let Some(plan) = self.make_plan()? else {
+                    return Ok(None);
+                };
+                let plan = Arc::new(plan);
+                let mut plans = PLANS.lock().unwrap();
+                if plans.len() >= MAX_PLANS {
+                    plans.clear();
+                }
+                plans.insert(fingerprint, plan.clone());
+                plan
+            }
+        };
+        let mut ids = Vec::with_capacity(plan.keys.len());
+        for key in &plan.keys {
+            let key = key.as_ref(self.py).to_str()?;
+            match self.string_table.iter().position(|entry| entry == key) {
+                Some(id) => ids.push(id as u32),
+                None => return Ok(None),
+            }
+        }
+        Ok(Some((plan, ids)))
+    }
+
+    /// A plan from the layout of the record at `offset`, if it is an object.
+    fn make_plan(&self) -> PyResult<Option<DecodePlan>> {
+        if self.data.get(self.offset) != Some(&0x70) {
+            return Ok(None);
+        }
+        let frame = Frame::view(self.data, self.string_table);
+        let mut cursor = self.offset + 1;
+        let (mut keys, mut scalar) = (Vec::new(), Vec::new());
+        while let Some(&tag) = self.data.get(cursor) {
+            if tag == 0x7F {
+                return Ok(Some(DecodePlan { keys, scalar }));
+            }
+            let Ok(id) = frame.u32_at(cursor) else {
+                break;
+            };
+            let Some(name) = self.string_table.get(id as usize) else {
+                break;
+            };
+            keys.push(PyString::intern(self.py, name).into());
+            scalar.push(self.data.get(cursor + 4).is_some_and(|&tag| is_scalar(tag)));
+            match frame.skip(cursor + 4) {
+                Ok(end) => cursor = end,
+                Err(_) => break,
+            }
+        }
+        // Malformed records are left for the generic parser to report
+        Ok(None)
+    }
+
+    /// Decode the record at `offset` with `plan`, or return None (and leave
+    /// `offset` there) when the record does not follow it.
+    fn parse_planned_record(
+        &mut self,
+        plan: &DecodePlan,
+        ids: &[u32],
+    ) -> PyResult<Option<PyObject>> {
+        let start = self.offset;
+        if self.data.get(start) != Some(&0x70) {
+            return Ok(None);
+        }
+        self.offset += 1;
+        let dict = PyDict::new(self.py);
+        for ((key, &scalar), &id) in plan.keys.iter().zip(&plan.scalar).zip(ids) {
+            if self.offset + 5 > self.data.len()
+                || self.data[self.offset..self.offset + 4] != id.to_le_bytes()
+            {
+                self.offset = start;
+                return Ok(None);
+            }
+            self.offset += 4;
+            let value = match scalar {
+                true => self.parse_scalar()?,
+                false => None,
+            };
+            let value = match value {
+                Some(value) => value,
+                None => self.parse()?,
+            };
+            self.insert(dict, key.as_ref(self.py), value)?;
+        }
+        if self.data.get(self.offset) != Some(&0x7F) {
+            self.offset = start;
+            return Ok(None);
+        }
+        self.offset += 1;
+        self.mapping(dict).map(Some)
+    }
+
+    /// Decode the value at `offset` inline when it is a scalar `parse` would
+    /// return as is; None leaves it for `parse`.
+    fn parse_scalar(&mut self) -> PyResult<Option<PyObject>> {
+        let tag = self.data[self.offset];
+        let value = match tag {
+            0x10 => self.py.None(),
+            0x20 => false.into_py(self.py),
+            0x21 => true.into_py(self.py),
+            0x40 | TAG_FLOAT32 => {
+                let width = float_width(tag).unwrap();
+                let Some(bytes) = self.data.get(self.offset + 1..self.offset + 1 + width) else {
+                    return Ok(None);
+                };
+                self.offset += width;
+                self.float(float_value(bytes))?
+            }
+            0x50 => {
+                let Some(length) = self.data.get(self.offset + 1..self.offset + 5) else {
+                    return Ok(None);
+                };
+                let length = u32::from_le_bytes(length.try_into().unwrap()) as usize;
+                let Some(bytes) = self.data.get(self.offset + 5..self.offset + 5 + length) else {
+                    return Ok(None);
+                };
+                let value = self.utf8_string(bytes)?;
+                self.offset += 4 + length;
+                value
+            }
+            t => {
+                let Some(width) = int_width(t) else {
+                    return Ok(None);
+                };
+                let Some(bytes) = self.data.get(self.offset + 1..self.offset + 1 + width) else {
+                    return Ok(None);
+                };
+                self.offset += width;
+                int_value(t, bytes).into_py(self.py)
+            }
+        };
+        self.offset += 1;
+        Ok(Some(value))
+    }
+}
@@ -0,0 +1,186 @@
+// Encoding with the GIL released.
+//
+// `encode_packed(..., release_gil=True)` splits the encode in two phases.
+// With the GIL held, the object is copied into a tree of plain Rust values;
+// this only succeeds for exact None, bool, int (within i64), float, str,
+// bytes, list, tuple and str-keyed dict values, so nothing in it can run
+// Python code when read. Then, with the GIL released, the tree is laid out,
+// its keys interned, the string table written and the frame compressed,
+// letting other Python threads run meanwhile. The bytes are the same as the
+// regular path writes.
+//
+// Objects holding anything else, and encoders whose options need to look at
+// the Python objects (dedup, canonical order, null bitmaps, the columnar
+// layout, bool packing, blob stores and hooks) or can fail on a value (a
+// non_finite policy), take the regular path.
+
+use pyo3::prelude::*;
+use pyo3::types::{PyBool, PyBytes, PyDict, PyFloat, PyList, PyLong, PyString, PyTuple};
+
+use crate::nonfinite::NonFinite;
+use crate::{scan, trace, BFast, MAX_RECURSION_DEPTH};
+
+/// A value copied out of its Python object, or parsed from JSON (json.rs).
+pub(crate) enum Value {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Str(String),
+    Bytes(Vec<u8>),
+    List(Vec<Value>),
+    Object(Vec<(String, Value)>),
+}
+
+impl Value {
+    /// Copy `obj`, or return None when it holds a value of another type (or
+    /// one the regular path would reject, which then reports it).
+    fn extract(obj: &PyAny, depth: usize) -> Option<Value> {
+        if depth > MAX_RECURSION_DEPTH {
+            return None;
+        }
+        if obj.is_none() {
+            return Some(Value::Null);
+        }
+        if let Ok(b) = obj.downcast_exact::<PyBool>() {
+            return Some(Value::Bool(b.is_true()));
+        }
+        if obj.is_exact_instance_of::<PyLong>() {
+            return obj.extract().ok().map(Value::Int);
+        }
+        if let Ok(f) = obj.downcast_exact::<PyFloat>() {
+            return Some(Value::Float(f.value()));
+        }
+        if let Ok(s) = obj.downcast_exact::<PyString>() {
+            return s.to_str().ok().map(|s| Value::Str(s.to_owned()));
+        }
+        if let Ok(b) = obj.downcast_exact::<PyBytes>() {
+            return Some(Value::Bytes(b.as_bytes().to_vec()));
+        }
+        let items = |items: &mut dyn Iterator<Item = &PyAny>| {
+            items
+                .map(|item| Value::extract(item, depth + 1))
+                .collect::<Option<Vec<_>>>()
+                .map(Value::List)
+        };
+        if let Ok(list) = obj.downcast_exact::<PyList>() {
+            return items(&mut list.iter());
+        }
+        if let Ok(tuple) = obj.downcast_exact::<PyTuple>() {
+            return items(&mut tuple.iter());
+        }
+        if let Ok(dict) = obj.downcast_exact::<PyDict>() {
+            // Int keys make an int-keyed object and other keys go through
+            // str(), both left to the regular path
+            let mut entries = Vec::with_capacity(dict.len());
+            for (key, value) in dict.iter() {
+                let key = key.downcast_exact::<PyString>().ok()?.to_str().ok()?;
+                entries.push((key.to_owned(), Value::extract(value, depth + 1)?));
+            }
+            return Some(Value::Object(entries));
+        }
+        None
+    }
+}
+
+impl BFast {
+    /// Encode `obj` with the layout and compression done without the GIL,
+    /// or return None when it has to take the regular path.
+    pub(crate) fn encode_released(
+        &mut self,
+        obj: &PyAny,
+        compress: bool,
+        metadata: Option<&PyDict>,
+    ) -> PyResult<Option<Vec<u8>>> {
+        if self.dedup
+            || self.canonical
+            || self.null_bitmap
+            || self.columnar
+            || self.pack_bools
+            || self.blob_store.is_some()
+            || self.hooks_bound()
+            || self.non_finite != NonFinite::Allow
+            || self.validate
+        {
+            return Ok(None);
+        }
+        let py = obj.py();
+        let Some(value) = Value::extract(obj, 0) else {
+            return Ok(None);
+        };
+        let metadata = metadata
+            .map(|metadata| BFast::new().encode_to_vec(metadata, false))
+            .transpose()?;
+
+        let span = trace::span("released");
+        let frame = py.allow_threads(|| self.value_frame(&value, compress, metadata))?;
+        span.finish(py, frame.len())?;
+        Ok(Some(frame))
+    }
+
+    /// The frame of `value`; needs no GIL.
+    pub(crate) fn value_frame(
+        &mut self,
+        value: &Value,
+        compress: bool,
+        metadata: Option<Vec<u8>>,
+    ) -> PyResult<Vec<u8>> {
+        let string_table_pos = self.begin_frame();
+        let written = self.write_value(value);
+        self.bound_buffer(written)?;
+        let finished = self
+            .finish_frame(string_table_pos, metadata, 0)
+            .and_then(|_| self.check_buffer_limit(0));
+        self.bound_buffer(finished)?;
+        self.settle_buffer(self.work_buffer.len());
+        match compress && self.work_buffer.len() > 256 {
+            true => {
+                let compressed = self.compress_frame();
+                self.release_frame();
+                compressed
+            }
+            false => Ok(std::mem::take(&mut self.work_buffer)),
+        }
+    }
+
+    /// Write `value` the way `serialize_any_optimized` writes the object it
+    /// was copied from, within `max_buffer_bytes` the same way.
+    fn write_value(&mut self, value: &Value) -> PyResult<()> {
+        self.check_buffer_limit(0)?;
+        match value {
+            Value::Null => self.work_buffer.push(0x10),
+            Value::Bool(b) => self.work_buffer.push(if *b { 0x21 } else { 0x20 }),
+            Value::Int(n) => scan::write_int(&mut self.work_buffer, *n),
+            Value::Float(f) => self.write_float_value(*f),
+            Value::Str(s) => self.write_sized(0x50, s.as_bytes())?,
+            Value::Bytes(b) => self.write_sized(0x80, b)?,
+            Value::List(items) => {
+                self.work_buffer.push(0x60);
+                self.work_buffer
+                    .extend_from_slice(&(items.len() as u32).to_le_bytes());
+                for item in items {
+                    self.write_value(item)?;
+                }
+            }
+            Value::Object(entries) => {
+                self.work_buffer.push(0x70);
+                for (key, value) in entries {
+                    let id = self.get_or_create_string_id_fast(key);
+                    self.work_buffer.extend_from_slice(&id.to_le_bytes());
+                    self.write_value(value)?;
+                }
+                self.work_buffer.push(0x7F);
+            }
+        }
+        Ok(())
+    }
+
+    fn write_sized(&mut self, tag: u8, bytes: &[u8]) -> PyResult<()> {
+        self.check_buffer_limit(5 + bytes.len())?;
+        self.work_buffer.push(tag);
+        self.work_buffer
+            .extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+        self.work_buffer.extend_from_slice(bytes);
+        Ok(())
+    }
+}
@@ -0,0 +1,627 @@
+// Protobuf bridge driven by a runtime descriptor.
+//
+// `to_protobuf` maps the records of a frame onto a message type taken from a
+// serialized FileDescriptorSet (`protoc --include_imports
+// --descriptor_set_out`), and `from_protobuf` reads such messages back into
+// a frame. Only what the wire format needs is read from the descriptors:
+// scalar, string and bytes fields, enums (as their numbers), nested and
+// repeated messages, packed repeated scalars and map fields.
+
+use ahash::AHashMap;
+use pyo3::prelude::*;
+use pyo3::types::{PyBool, PyBytes, PyDict, PyList, PyString};
+
+use crate::scan::ScanResult;
+use crate::{decode_bytes, BFast, DecodeOptions, MAX_RECURSION_DEPTH};
+
+// FieldDescriptorProto.Type values
+const TYPE_DOUBLE: u64 = 1;
+const TYPE_FLOAT: u64 = 2;
+const TYPE_INT64: u64 = 3;
+const TYPE_UINT64: u64 = 4;
+const TYPE_INT32: u64 = 5;
+const TYPE_FIXED64: u64 = 6;
+const TYPE_FIXED32: u64 = 7;
+const TYPE_BOOL: u64 = 8;
+const TYPE_STRING: u64 = 9;
+const TYPE_GROUP: u64 = 10;
+const TYPE_MESSAGE: u64 = 11;
+const TYPE_BYTES: u64 = 12;
+const TYPE_UINT32: u64 = 13;
+const TYPE_ENUM: u64 = 14;
+const TYPE_SFIXED32: u64 = 15;
+const TYPE_SFIXED64: u64 = 16;
+const TYPE_SINT32: u64 = 17;
+const TYPE_SINT64: u64 = 18;
+
+const LABEL_REPEATED: u64 = 3;
+
+const WIRE_VARINT: u8 = 0;
+const WIRE_FIXED64: u8 = 1;
+const WIRE_LEN: u8 = 2;
+const WIRE_FIXED32: u8 = 5;
+
+fn value_error(message: String) -> PyErr {
+    PyErr::new::<pyo3::exceptions::PyValueError, _>(message)
+}
+
+/// One field value as read off the wire.
+enum Wire<'a> {
+    Varint(u64),
+    Fixed64(u64),
+    Len(&'a [u8]),
+    Fixed32(u32),
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Reader { data, pos: 0 }
+    }
+
+    fn varint(&mut self) -> ScanResult<u64> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = *self.data.get(self.pos).ok_or("Truncated protobuf varint")?;
+            self.pos += 1;
+            value |= ((byte & 0x7F) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err("Protobuf varint longer than 10 bytes".to_string())
+    }
+
+    fn take(&mut self, len: usize) -> ScanResult<&'a [u8]> {
+        let bytes = self
+            .data
+            .get(self.pos..self.pos.saturating_add(len))
+            .ok_or("Truncated protobuf field")?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn fixed32(&mut self) -> ScanResult<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn fixed64(&mut self) -> ScanResult<u64> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    /// The next `(field number, value)`, or `None` at the end.
+    fn field(&mut self) -> ScanResult<Option<(u64, Wire<'a>)>> {
+        if self.pos == self.data.len() {
+            return Ok(None);
+        }
+        let key = self.varint()?;
+        let value = match (key & 7) as u8 {
+            WIRE_VARINT => Wire::Varint(self.varint()?),
+            WIRE_FIXED64 => Wire::Fixed64(self.fixed64()?),
+            WIRE_LEN => {
+                let len = self.varint()? as usize;
+                Wire::Len(self.take(len)?)
+            }
+            WIRE_FIXED32 => Wire::Fixed32(self.fixed32()?),
+            wire => return Err(format!("Unsupported protobuf wire type {}", wire)),
+        };
+        Ok(Some((key >> 3, value)))
+    }
+}
+
+fn text(bytes: &[u8]) -> ScanResult<String> {
+    String::from_utf8(bytes.to_vec())
+        .map_err(|_| "Invalid UTF-8 in protobuf descriptor".to_string())
+}
+
+struct Field {
+    name: String,
+    number: u64,
+    kind: u64,
+    repeated: bool,
+    /// Full name of the message type, for message fields.
+    type_name: String,
+}
+
+impl Field {
+    /// Scalars whose repeated values may be packed into one LEN record.
+    fn packable(&self) -> bool {
+        !matches!(
+            self.kind,
+            TYPE_STRING | TYPE_BYTES | TYPE_MESSAGE | TYPE_GROUP
+        )
+    }
+
+    fn wire_type(&self) -> u8 {
+        match self.kind {
+            TYPE_DOUBLE | TYPE_FIXED64 | TYPE_SFIXED64 => WIRE_FIXED64,
+            TYPE_FLOAT | TYPE_FIXED32 | TYPE_SFIXED32 => WIRE_FIXED32,
+            TYPE_STRING | TYPE_BYTES | TYPE_MESSAGE => WIRE_LEN,
+            _ => WIRE_VARINT,
+        }
+    }
+}
+
+#[derive(Default)]
+struct Message {
+    fields: Vec<Field>,
+    /// Synthesized `key`/`value` entry type of a map field.
+    map_entry: bool,
+}
+
+/// Message types of a FileDescriptorSet, by full name.
+#[derive(Default)]
+struct Descriptors {
+    messages: AHashMap<String, Message>,
+}
+
+impl Descriptors {
+    fn parse(data: &[u8]) -> ScanResult<Self> {
+        let mut descriptors = Descriptors::default();
+        let mut set = Reader::new(data);
+        while let Some((number, value)) = set.field()? {
+            if let (1, Wire::Len(file)) = (number, value) {
+                descriptors.file(file)?;
+            }
+        }
+        Ok(descriptors)
+    }
+
+    fn file(&mut self, data: &[u8]) -> ScanResult<()> {
+        let mut package = String::new();
+        let mut messages = Vec::new();
+        let mut file = Reader::new(data);
+        while let Some((number, value)) = file.field()? {
+            match (number, value) {
+                (2, Wire::Len(name)) => package = text(name)?,
+                (4, Wire::Len(message)) => messages.push(message),
+                _ => {}
+            }
+        }
+        for message in messages {
+            self.message(message, &package)?;
+        }
+        Ok(())
+    }
+
+    fn message(&mut self, data: &[u8], scope: &str) -> ScanResult<()> {
+        let mut name = String::new();
+        let mut message = Message::default();
+        let mut nested = Vec::new();
+        let mut reader = Reader::new(data);
+        while let Some((number, value)) = reader.field()? {
+            match (number, value) {
+                (1, Wire::Len(bytes)) => name = text(bytes)?,
+                (2, Wire::Len(field)) => message.fields.push(Self::field(field)?),
+                (3, Wire::Len(bytes)) => nested.push(bytes),
+                (7, Wire::Len(options)) => {
+                    let mut options = Reader::new(options);
+                    while let Some((number, value)) = options.field()? {
+                        if let (7, Wire::Varint(flag)) = (number, value) {
+                            message.map_entry = flag != 0;
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        let full_name = if scope.is_empty() {
+            name
+        } else {
+            format!("{}.{}", scope, name)
+        };
+        for bytes in nested {
+            self.message(bytes, &full_name)?;
+        }
+        self.messages.insert(full_name, message);
+        Ok(())
+    }
+
+    fn field(data: &[u8]) -> ScanResult<Field> {
+        let mut field = Field {
+            name: String::new(),
+            number: 0,
+            kind: 0,
+            repeated: false,
+            type_name: String::new(),
+        };
+        let mut reader = Reader::new(data);
+        while let Some((number, value)) = reader.field()? {
+            match (number, value) {
+                (1, Wire::Len(name)) => field.name = text(name)?,
+                (3, Wire::Varint(number)) => field.number = number,
+                (4, Wire::Varint(label)) => field.repeated = label == LABEL_REPEATED,
+                (5, Wire::Varint(kind)) => field.kind = kind,
+                (6, Wire::Len(name)) => {
+                    field.type_name = text(name)?.trim_start_matches('.').into()
+                }
+                _ => {}
+            }
+        }
+        if field.kind == TYPE_GROUP {
+            return Err(format!(
+                "Field {:?} is a group, which is not supported",
+                field.name
+            ));
+        }
+        Ok(field)
+    }
+
+    fn get(&self, name: &str) -> ScanResult<&Message> {
+        self.messages
+            .get(name.trim_start_matches('.'))
+            .ok_or_else(|| format!("Message type {:?} is not in the descriptor", name))
+    }
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn write_key(out: &mut Vec<u8>, number: u64, wire: u8) {
+    write_varint(out, number << 3 | wire as u64);
+}
+
+fn write_len(out: &mut Vec<u8>, number: u64, bytes: &[u8]) {
+    write_key(out, number, WIRE_LEN);
+    write_varint(out, bytes.len() as u64);
+    out.extend_from_slice(bytes);
+}
+
+struct Encoder<'d> {
+    descriptors: &'d Descriptors,
+}
+
+impl Encoder<'_> {
+    fn message(&self, message: &Message, obj: &PyAny, depth: usize) -> PyResult<Vec<u8>> {
+        if depth > MAX_RECURSION_DEPTH {
+            return Err(PyErr::new::<pyo3::exceptions::PyRecursionError, _>(
+                "Maximum recursion depth exceeded",
+            ));
+        }
+        let dict = obj.downcast::<PyDict>().map_err(|_| {
+            PyErr::new::<pyo3::exceptions::PyTypeError, _>(format!(
+                "Expected a dict for a protobuf message, got {}",
+                obj.get_type().name().unwrap_or("?")
+            ))
+        })?;
+        for key in dict.keys() {
+            let key = key.str()?.to_str()?;
+            if !message.fields.iter().any(|field| field.name == key) {
+                return Err(value_error(format!(
+                    "Field {:?} is not in the message type",
+                    key
+                )));
+            }
+        }
+        let mut out = Vec::new();
+        for field in &message.fields {
+            let Some(value) = dict.get_item(&field.name)? else {
+                continue;
+            };
+            if value.is_none() {
+                continue;
+            }
+            if !field.repeated {
+                self.field(field, value, &mut out, depth)?;
+                continue;
+            }
+            let entry = match field.kind {
+                TYPE_MESSAGE => Some(
+                    self.descriptors
+                        .get(&field.type_name)
+                        .map_err(value_error)?,
+                ),
+                _ => None,
+            };
+            if let (Some(entry), Ok(map)) = (entry, value.downcast::<PyDict>()) {
+                if entry.map_entry {
+                    for (key, item) in map.iter() {
+                        let pair = PyDict::new(value.py());
+                        pair.set_item("key", key)?;
+                        pair.set_item("value", item)?;
+                        write_len(
+                            &mut out,
+                            field.number,
+                            &self.message(entry, pair, depth + 1)?,
+                        );
+                    }
+                    continue;
+                }
+            }
+            if value.is_instance_of::<PyString>() || value.is_instance_of::<PyBytes>() {
+                return Err(PyErr::new::<pyo3::exceptions::PyTypeError, _>(format!(
+                    "Repeated field {:?} expects a list",
+                    field.name
+                )));
+            }
+            if field.packable() {
+                let mut packed = Vec::new();
+                for item in value.iter()? {
+                    self.scalar(field, item?, &mut packed)?;
+                }
+                write_len(&mut out, field.number, &packed);
+            } else {
+                for item in value.iter()? {
+                    self.field(field, item?, &mut out, depth)?;
+                }
+            }
+        }
+        Ok(out)
+    }
+
+    /// Write one value of `field` with its key.
+    fn field(&self, field: &Field, value: &PyAny, out: &mut Vec<u8>, depth: usize) -> PyResult<()> {
+        match field.kind {
+            TYPE_MESSAGE => {
+                let message = self
+                    .descriptors
+                    .get(&field.type_name)
+                    .map_err(value_error)?;
+                write_len(out, field.number, &self.message(message, value, depth + 1)?);
+            }
+            TYPE_STRING => {
+                let text = value.downcast::<PyString>()?.to_str()?;
+                write_len(out, field.number, text.as_bytes());
+            }
+            TYPE_BYTES => write_len(out, field.number, value.downcast::<PyBytes>()?.as_bytes()),
+            _ => {
+                write_key(out, field.number, field.wire_type());
+                self.scalar(field, value, out)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Write a numeric or bool value without its key.
+    fn scalar(&self, field: &Field, value: &PyAny, out: &mut Vec<u8>) -> PyResult<()> {
+        let overflow = |_| {
+            PyErr::new::<pyo3::exceptions::PyOverflowError, _>(format!(
+                "Value out of range for field {:?}",
+                field.name
+            ))
+        };
+        match field.kind {
+            TYPE_DOUBLE => out.extend_from_slice(&value.extract::<f64>()?.to_le_bytes()),
+            TYPE_FLOAT => out.extend_from_slice(&(value.extract::<f64>()? as f32).to_le_bytes()),
+            TYPE_BOOL => out.push(value.downcast::<PyBool>()?.is_true() as u8),
+            TYPE_INT64 => write_varint(out, value.extract::<i64>()? as u64),
+            TYPE_INT32 | TYPE_ENUM => {
+                let value = value.extract::<i32>().map_err(overflow)?;
+                // Negative 32-bit values are sign-extended to 10 bytes
+                write_varint(out, value as i64 as u64);
+            }
+            TYPE_UINT64 => write_varint(out, value.extract::<u64>().map_err(overflow)?),
+            TYPE_UINT32 => write_varint(out, value.extract::<u32>().map_err(overflow)? as u64),
+            TYPE_SINT32 => {
+                let value = value.extract::<i32>().map_err(overflow)?;
+                write_varint(out, ((value << 1) ^ (value >> 31)) as u32 as u64);
+            }
+            TYPE_SINT64 => {
+                let value = value.extract::<i64>().map_err(overflow)?;
+                write_varint(out, ((value << 1) ^ (value >> 63)) as u64);
+            }
+            TYPE_FIXED32 => {
+                out.extend_from_slice(&value.extract::<u32>().map_err(overflow)?.to_le_bytes())
+            }
+            TYPE_SFIXED32 => {
+                out.extend_from_slice(&value.extract::<i32>().map_err(overflow)?.to_le_bytes())
+            }
+            TYPE_FIXED64 => {
+                out.extend_from_slice(&value.extract::<u64>().map_err(overflow)?.to_le_bytes())
+            }
+            TYPE_SFIXED64 => {
+                out.extend_from_slice(&value.extract::<i64>().map_err(overflow)?.to_le_bytes())
+            }
+            kind => {
+                return Err(value_error(format!(
+                    "Field {:?} has unsupported type {}",
+                    field.name, kind
+                )))
+            }
+        }
+        Ok(())
+    }
+}
+
+struct Decoder<'d, 'py> {
+    py: Python<'py>,
+    descriptors: &'d Descriptors,
+}
+
+impl<'py> Decoder<'_, 'py> {
+    /// The message as a dict holding every field, absent ones at their
+    /// proto3 default (`None` for messages).
+    fn message(&self, message: &Message, data: &[u8], depth: usize) -> PyResult<&'py PyDict> {
+        if depth > MAX_RECURSION_DEPTH {
+            return Err(PyErr::new::<pyo3::exceptions::PyRecursionError, _>(
+                "Maximum recursion depth exceeded",
+            ));
+        }
+        let dict = PyDict::new(self.py);
+        for field in &message.fields {
+            dict.set_item(&field.name, self.default(field)?)?;
+        }
+        let mut reader = Reader::new(data);
+        while let Some((number, value)) = reader.field().map_err(value_error)? {
+            // Unknown fields are skipped, as protobuf parsers do
+            let Some(field) = message.fields.iter().find(|f| f.number == number) else {
+                continue;
+            };
+            if !field.repeated {
+                dict.set_item(&field.name, self.value(field, value, depth)?)?;
+                continue;
+            }
+            let target = dict
+                .get_item(&field.name)?
+                .ok_or_else(|| self.missing("message", &field.name))?;
+            if let Ok(map) = target.downcast::<PyDict>() {
+                let Wire::Len(bytes) = value else {
+                    return Err(self.mismatch(field));
+                };
+                let entry = self
+                    .descriptors
+                    .get(&field.type_name)
+                    .map_err(value_error)?;
+                let pair = self.message(entry, bytes, depth + 1)?;
+                let key = pair.get_item("key")?.ok_or_else(|| {
+                    self.missing(&format!("map entry {:?}", field.type_name), "key")
+                })?;
+                let value = pair.get_item("value")?.ok_or_else(|| {
+                    self.missing(&format!("map entry {:?}", field.type_name), "value")
+                })?;
+                map.set_item(key, value)?;
+                continue;
+            }
+            let list = target.downcast::<PyList>()?;
+            match value {
+                Wire::Len(bytes) if field.packable() => {
+                    let mut packed = Reader::new(bytes);
+                    while packed.pos < bytes.len() {
+                        let item = match field.wire_type() {
+                            WIRE_FIXED64 => Wire::Fixed64(packed.fixed64().map_err(value_error)?),
+                            WIRE_FIXED32 => Wire::Fixed32(packed.fixed32().map_err(value_error)?),
+                            _ => Wire::Varint(packed.varint().map_err(value_error)?),
+                        };
+                        list.append(self.value(field, item, depth)?)?;
+                    }
+                }
+                value => list.append(self.value(field, value, depth)?)?,
+            }
+        }
+        Ok(dict)
+    }
+
+    fn default(&self, field: &Field) -> PyResult<PyObject> {
+        let py = self.py;
+        if field.repeated {
+            let map_entry = field.kind == TYPE_MESSAGE
+                && self
+                    .descriptors
+                    .get(&field.type_name)
+                    .map_err(value_error)?
+                    .map_entry;
+            return Ok(match map_entry {
+                true => PyDict::new(py).into(),
+                false => PyList::empty(py).into(),
+            });
+        }
+        Ok(match field.kind {
+            TYPE_MESSAGE => py.None(),
+            TYPE_STRING => "".into_py(py),
+            TYPE_BYTES => PyBytes::new(py, b"").into(),
+            TYPE_BOOL => false.into_py(py),
+            TYPE_DOUBLE | TYPE_FLOAT => 0.0.into_py(py),
+            _ => 0.into_py(py),
+        })
+    }
+
+    fn missing(&self, message: &str, field: &str) -> PyErr {
+        value_error(format!("Decoded {} has no field {:?}", message, field))
+    }
+
+    fn mismatch(&self, field: &Field) -> PyErr {
+        value_error(format!(
+            "Wire type does not match the type of field {:?}",
+            field.name
+        ))
+    }
+
+    fn value(&self, field: &Field, value: Wire, depth: usize) -> PyResult<PyObject> {
+        let py = self.py;
+        Ok(match (field.kind, value) {
+            (TYPE_MESSAGE, Wire::Len(bytes)) => {
+                let message = self
+                    .descriptors
+                    .get(&field.type_name)
+                    .map_err(value_error)?;
+                self.message(message, bytes, depth + 1)?.into()
+            }
+            (TYPE_STRING, Wire::Len(bytes)) => std::str::from_utf8(bytes)
+                .map_err(|_| value_error(format!("Invalid UTF-8 in field {:?}", field.name)))?
+                .into_py(py),
+            (TYPE_BYTES, Wire::Len(bytes)) => PyBytes::new(py, bytes).into(),
+            (TYPE_DOUBLE, Wire::Fixed64(bits)) => f64::from_bits(bits).into_py(py),
+            (TYPE_FLOAT, Wire::Fixed32(bits)) => (f32::from_bits(bits) as f64).into_py(py),
+            (TYPE_BOOL, Wire::Varint(v)) => (v != 0).into_py(py),
+            (TYPE_INT64, Wire::Varint(v)) => (v as i64).into_py(py),
+            (TYPE_INT32 | TYPE_ENUM, Wire::Varint(v)) => (v as i32).into_py(py),
+            (TYPE_UINT64, Wire::Varint(v)) => v.into_py(py),
+            (TYPE_UINT32, Wire::Varint(v)) => (v as u32).into_py(py),
+            (TYPE_SINT32 | TYPE_SINT64, Wire::Varint(v)) => {
+                ((v >> 1) as i64 ^ -((v & 1) as i64)).into_py(py)
+            }
+            (TYPE_FIXED32, Wire::Fixed32(v)) => v.into_py(py),
+            (TYPE_SFIXED32, Wire::Fixed32(v)) => (v as i32).into_py(py),
+            (TYPE_FIXED64, Wire::Fixed64(v)) => v.into_py(py),
+            (TYPE_SFIXED64, Wire::Fixed64(v)) => (v as i64).into_py(py),
+            _ => return Err(self.mismatch(field)),
+        })
+    }
+}
+
+/// Protobuf encoding of the record in `data`, or a list of encodings when
+/// `data` holds a list of records.
+#[pyfunction]
+pub fn to_protobuf(
+    py: Python,
+    data: &[u8],
+    descriptor: &[u8],
+    message: &str,
+) -> PyResult<PyObject> {
+    let descriptors = Descriptors::parse(descriptor).map_err(value_error)?;
+    let message = descriptors.get(message).map_err(value_error)?;
+    let encoder = Encoder {
+        descriptors: &descriptors,
+    };
+    let value = decode_bytes(py, data, true, DecodeOptions::default())?;
+    let value = value.as_ref(py);
+    if let Ok(list) = value.downcast::<PyList>() {
+        let messages = list
+            .iter()
+            .map(|record| Ok(PyBytes::new(py, &encoder.message(message, record, 0)?)))
+            .collect::<PyResult<Vec<_>>>()?;
+        return Ok(PyList::new(py, messages).into());
+    }
+    Ok(PyBytes::new(py, &encoder.message(message, value, 0)?).into())
+}
+
+/// B-FAST encoding of one protobuf message, or of a list of records when
+/// given a list of messages.
+#[pyfunction]
+#[pyo3(signature = (data, descriptor, message, *, compress = false))]
+pub fn from_protobuf(
+    py: Python,
+    data: &PyAny,
+    descriptor: &[u8],
+    message: &str,
+    compress: bool,
+) -> PyResult<PyObject> {
+    let descriptors = Descriptors::parse(descriptor).map_err(value_error)?;
+    let message = descriptors.get(message).map_err(value_error)?;
+    let decoder = Decoder {
+        py,
+        descriptors: &descriptors,
+    };
+    let records: &PyAny = match data.downcast::<PyBytes>() {
+        Ok(bytes) => decoder.message(message, bytes.as_bytes(), 0)?,
+        Err(_) => {
+            let records = PyList::empty(py);
+            for item in data.iter()? {
+                let bytes = item?.downcast::<PyBytes>()?.as_bytes();
+                records.append(decoder.message(message, bytes, 0)?)?;
+            }
+            records
+        }
+    };
+    let frame = BFast::new().encode_to_vec(records, compress)?;
+    Ok(PyBytes::new(py, &frame).into())
+}
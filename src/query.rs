@@ -0,0 +1,363 @@
+// Queries that run directly on encoded bytes.
+//
+// The tag stream is walked with the scan helpers and only the values that
+// are actually requested get turned into Python objects.
+
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList};
+
+use crate::scan::{
+    float_width, header_size, parse_frame, parse_path, read_u32, unpack, Frame, PathItem,
+    ScanResult,
+};
+use crate::splice::slice_bounds;
+use crate::stats::{self, Bound};
+use crate::{
+    decode_frame, decode_frame_at, decode_values_at, BFast, DecodeOptions, FLAG_COLUMNAR,
+    FLAG_NULL_BITMAP, FLAG_PACKED_BOOLS, FLAG_SHARED_REFS, HEADER_SIZE, TAG_COLUMNAR,
+};
+
+fn value_error(message: String) -> PyErr {
+    PyErr::new::<pyo3::exceptions::PyValueError, _>(message)
+}
+
+/// Decode the metadata dict of a frame encoded with `metadata=`, or return
+/// None; the payload is not decoded.
+#[pyfunction]
+pub fn metadata(py: Python, data: &[u8]) -> PyResult<PyObject> {
+    let (frame_data, _) = unpack(data).map_err(value_error)?;
+    let frame = parse_frame(&frame_data).map_err(value_error)?;
+    match frame.metadata {
+        Some(metadata) => decode_frame(py, metadata, None, DecodeOptions::default()),
+        None => Ok(py.None()),
+    }
+}
+
+/// The record layout fingerprint of a frame whose root list was written by
+/// the batch path, or None.
+#[pyfunction]
+pub fn fingerprint(data: &[u8]) -> PyResult<Option<u64>> {
+    let (frame_data, _) = unpack(data).map_err(value_error)?;
+    let frame = parse_frame(&frame_data).map_err(value_error)?;
+    Ok(frame.fingerprint)
+}
+
+/// Size of the frame `header` starts with, compressed or not, from its first
+/// 10 bytes, so a reader knows how much more of a stream to take.
+#[pyfunction]
+pub fn frame_length(header: &[u8]) -> PyResult<usize> {
+    if header_size(header).map_err(value_error)? != HEADER_SIZE {
+        return Err(value_error(
+            "Version 1 frames do not record their length".to_string(),
+        ));
+    }
+    let length = read_u32(header, 6).map_err(value_error)? as usize;
+    Ok(HEADER_SIZE + length)
+}
+
+/// Element count of the list a frame holds, from the count in front of its
+/// elements; they are not decoded.
+#[pyfunction]
+pub fn count(data: &[u8]) -> PyResult<usize> {
+    let (frame_data, _) = unpack(data).map_err(value_error)?;
+    let frame = parse_frame(&frame_data).map_err(value_error)?;
+    match frame.tag(frame.payload).map_err(value_error)? {
+        // Columnar records start with their row count too
+        0x60 | TAG_COLUMNAR => Ok(frame.u32_at(frame.payload + 1).map_err(value_error)? as usize),
+        _ => Err(value_error(
+            "count expects payloads encoded from a list".to_string(),
+        )),
+    }
+}
+
+/// Decode elements `start:stop` of an encoded list (Python slice semantics).
+/// Earlier elements are skipped over by their lengths and later ones are never
+/// read, except in frames encoded with dedup, null_bitmap, columnar or
+/// pack_bools, which are decoded in full and sliced.
+#[pyfunction]
+#[pyo3(signature = (data, start = None, stop = None))]
+pub fn decode_range(
+    py: Python,
+    data: &[u8],
+    start: Option<isize>,
+    stop: Option<isize>,
+) -> PyResult<PyObject> {
+    let (frame_data, _) = unpack(data).map_err(value_error)?;
+    let frame = parse_frame(&frame_data).map_err(value_error)?;
+    let tag = frame.tag(frame.payload).map_err(value_error)?;
+    if !matches!(tag, 0x60 | TAG_COLUMNAR) {
+        return Err(value_error(
+            "decode_range expects payloads encoded from a list".to_string(),
+        ));
+    }
+
+    let layouts = FLAG_SHARED_REFS | FLAG_NULL_BITMAP | FLAG_COLUMNAR | FLAG_PACKED_BOOLS;
+    if frame.flags & layouts != 0 || tag == TAG_COLUMNAR {
+        // As in `get`, elements of these frames cannot be decoded on their
+        // own, so the list is decoded in full
+        let list = decode_frame(py, &frame_data, None, DecodeOptions::default())?;
+        let list = list.downcast::<PyList>(py)?;
+        let (start, stop) = slice_bounds(start, stop, list.len());
+        return Ok(list.get_slice(start, stop).into());
+    }
+
+    let count = frame.u32_at(frame.payload + 1).map_err(value_error)? as usize;
+    let (start, stop) = slice_bounds(start, stop, count);
+    let mut cursor = frame.payload + 5;
+    for _ in 0..start {
+        cursor = frame.skip(cursor).map_err(value_error)?;
+    }
+    let items = decode_values_at(
+        py,
+        &frame_data,
+        DecodeOptions::default(),
+        cursor,
+        stop - start,
+    )?;
+    Ok(PyList::new(py, items).into())
+}
+
+/// Return the value at `path` (e.g. `"orders[3].customer.id"`), or `default`
+/// when the path does not exist.
+#[pyfunction]
+#[pyo3(signature = (data, path, default = None))]
+pub fn get(py: Python, data: &[u8], path: &str, default: Option<PyObject>) -> PyResult<PyObject> {
+    let default = default.unwrap_or_else(|| py.None());
+    let (frame_data, _) = unpack(data).map_err(value_error)?;
+    let frame = parse_frame(&frame_data).map_err(value_error)?;
+    let items = parse_path(path).map_err(value_error)?;
+
+    let layouts = FLAG_SHARED_REFS | FLAG_NULL_BITMAP | FLAG_COLUMNAR | FLAG_PACKED_BOOLS;
+    if frame.flags & layouts != 0 {
+        // Back-references are numbered from the start of the payload, and
+        // bitmap-null fields, columnar records and packed booleans have no
+        // bytes of their own, so these frames are decoded in full
+        let mut obj = decode_frame(py, &frame_data, None, DecodeOptions::default())?.into_ref(py);
+        for item in &items {
+            let next = match item {
+                PathItem::Key(key) => match obj.downcast::<PyDict>() {
+                    Ok(dict) => dict.get_item(key)?,
+                    Err(_) => None,
+                },
+                PathItem::Index(index) => match obj.downcast::<PyList>() {
+                    Ok(list) if *index < list.len() => Some(list.get_item(*index)?),
+                    _ => None,
+                },
+            };
+            match next {
+                Some(value) => obj = value,
+                None => return Ok(default),
+            }
+        }
+        return Ok(obj.into());
+    }
+
+    match frame.resolve(frame.payload, &items).map_err(value_error)? {
+        Some(offset) => decode_frame_at(
+            py,
+            &frame_data,
+            None,
+            DecodeOptions::default(),
+            Some(offset),
+        ),
+        None => Ok(default),
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Number {
+    Int(i64),
+    Float(f64),
+}
+
+impl Number {
+    fn as_f64(self) -> f64 {
+        match self {
+            Number::Int(i) => i as f64,
+            Number::Float(f) => f,
+        }
+    }
+
+    fn less_than(self, other: Number) -> bool {
+        match (self, other) {
+            (Number::Int(a), Number::Int(b)) => a < b,
+            (a, b) => a.as_f64() < b.as_f64(),
+        }
+    }
+}
+
+impl IntoPy<PyObject> for Number {
+    fn into_py(self, py: Python) -> PyObject {
+        match self {
+            Number::Int(i) => i.into_py(py),
+            Number::Float(f) => f.into_py(py),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Accumulator {
+    count: u64,
+    int_sum: i128,
+    float_sum: f64,
+    has_float: bool,
+    min: Option<Number>,
+    max: Option<Number>,
+}
+
+impl Accumulator {
+    fn add(&mut self, value: Number) {
+        self.count += 1;
+        match value {
+            Number::Int(i) => self.int_sum += i as i128,
+            Number::Float(f) => {
+                self.float_sum += f;
+                self.has_float = true;
+            }
+        }
+        match self.min {
+            Some(min) if !value.less_than(min) => {}
+            _ => self.min = Some(value),
+        }
+        match self.max {
+            Some(max) if !max.less_than(value) => {}
+            _ => self.max = Some(value),
+        }
+    }
+}
+
+/// Numeric value at `offset`; `None` for null. Booleans count as 0/1 like in Python.
+fn number_at(frame: &Frame, offset: usize) -> ScanResult<Option<Number>> {
+    match frame.tag(offset)? {
+        0x10 => Ok(None),
+        0x20 => Ok(Some(Number::Int(0))),
+        0x21 => Ok(Some(Number::Int(1))),
+        t if float_width(t).is_some() => frame.float_at(offset).map(|f| Some(Number::Float(f))),
+        _ => frame.int_at(offset).map(|i| Some(Number::Int(i))),
+    }
+}
+
+/// The count, min or max of `field` from the pruning index of a frame
+/// encoded with `column_stats=True`, when it holds the answer.
+fn aggregate_index(frame: &Frame, field: &str, op: &str) -> ScanResult<Option<Accumulator>> {
+    let Some(column) = stats::column(frame.stats, field)? else {
+        return Ok(None);
+    };
+    let number = |bound| match bound {
+        Bound::Int(i) => Some(Number::Int(i)),
+        Bound::Float(f) => Some(Number::Float(f)),
+        Bound::Str(_) => None,
+    };
+    let mut acc = Accumulator {
+        count: column.count - column.nulls,
+        ..Accumulator::default()
+    };
+    if op == "count" {
+        return Ok(Some(acc));
+    }
+    match column.bounds {
+        Some((min, max)) => match (number(min), number(max)) {
+            (Some(min), Some(max)) => (acc.min, acc.max) = (Some(min), Some(max)),
+            _ => return Ok(None),
+        },
+        // Without bounds, only a column of nulls is known to have no min
+        None if acc.count > 0 => return Ok(None),
+        None => {}
+    }
+    Ok(Some(acc))
+}
+
+/// Aggregate over the elements of `data`, or `None` when its elements have no
+/// bytes of their own to read.
+fn aggregate_list(data: &[u8], field: Option<&str>, op: &str) -> ScanResult<Option<Accumulator>> {
+    let (frame_data, _) = unpack(data)?;
+    let frame = parse_frame(&frame_data)?;
+    let path = parse_path(field.unwrap_or(""))?;
+    if let (&[PathItem::Key(key)], "count" | "min" | "max") = (path.as_slice(), op) {
+        if let Some(acc) = aggregate_index(&frame, key, op)? {
+            return Ok(Some(acc));
+        }
+    }
+    if frame.require_plain("aggregate").is_err() || frame.tag(frame.payload)? == TAG_COLUMNAR {
+        return Ok(None);
+    }
+    aggregate_frame(&frame, &path, field, op).map(Some)
+}
+
+fn aggregate_frame(
+    frame: &Frame,
+    path: &[PathItem],
+    field: Option<&str>,
+    op: &str,
+) -> ScanResult<Accumulator> {
+    let numeric = op != "count";
+    if frame.tag(frame.payload)? != 0x60 {
+        return Err("aggregate expects a payload encoded from a list".to_string());
+    }
+
+    let mut acc = Accumulator::default();
+    let count = frame.u32_at(frame.payload + 1)?;
+    let mut cursor = frame.payload + 5;
+    for index in 0..count {
+        if let Some(offset) = frame.resolve(cursor, path)? {
+            if !numeric {
+                if frame.tag(offset)? != 0x10 {
+                    acc.count += 1;
+                }
+            } else if let Some(value) = number_at(frame, offset).map_err(|_| {
+                format!(
+                    "Element {} has a non-numeric {:?}",
+                    index,
+                    field.unwrap_or("value")
+                )
+            })? {
+                acc.add(value);
+            }
+        }
+        cursor = frame.skip(cursor)?;
+    }
+    Ok(acc)
+}
+
+/// Aggregate `field` over the elements of an encoded list without decoding it.
+/// `op` is one of "sum", "count", "min" or "max"; missing and null fields are
+/// ignored. Frames with a pruning index answer count, min and max of their
+/// columns from it; frames whose elements have no bytes of their own are
+/// decoded first.
+#[pyfunction]
+#[pyo3(signature = (data, field = None, op = "sum"))]
+pub fn aggregate(py: Python, data: &[u8], field: Option<&str>, op: &str) -> PyResult<PyObject> {
+    if !matches!(op, "sum" | "count" | "min" | "max") {
+        return Err(value_error(format!(
+            "Unknown aggregate op {:?}; expected sum, count, min or max",
+            op
+        )));
+    }
+    let acc = match py
+        .allow_threads(|| aggregate_list(data, field, op))
+        .map_err(value_error)?
+    {
+        Some(acc) => acc,
+        None => {
+            // Frames encoded with dedup, null_bitmap, columnar or pack_bools
+            // are decoded and written again as plain frames
+            let (frame_data, _) = unpack(data).map_err(value_error)?;
+            let value = decode_frame(py, &frame_data, None, DecodeOptions::default())?;
+            let plain = BFast::new().encode_frame(value.as_ref(py), false, None, None, false)?;
+            py.allow_threads(|| {
+                let frame = parse_frame(&plain)?;
+                let path = parse_path(field.unwrap_or(""))?;
+                aggregate_frame(&frame, &path, field, op)
+            })
+            .map_err(value_error)?
+        }
+    };
+
+    Ok(match op {
+        "count" => acc.count.into_py(py),
+        "sum" if acc.has_float => (acc.float_sum + acc.int_sum as f64).into_py(py),
+        "sum" => acc.int_sum.into_py(py),
+        "min" => acc.min.into_py(py),
+        _ => acc.max.into_py(py),
+    })
+}
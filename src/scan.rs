@@ -0,0 +1,877 @@
+// Byte-level access to encoded frames.
+//
+// Walks the tag stream without creating Python objects: header and string
+// table parsing, skipping values, and copying values between frames while
+// remapping object key IDs into another string table. Tools that query or
+// rewrite encoded payloads in place build on these helpers.
+
+use ahash::AHashMap;
+use std::borrow::Cow;
+
+use crate::compression::compress_frame;
+use crate::{
+    columnar, decompress_packed, schema, temporal, FLAG_COLUMNAR, FLAG_COMPRESSED, FLAG_METADATA,
+    FLAG_NULL_BITMAP, FLAG_PACKED_BOOLS, FLAG_SCHEMA, FLAG_SHARED_REFS, FORMAT_VERSION,
+    HEADER_SIZE, MAX_RECURSION_DEPTH, TAG_BLOB_REF, TAG_BOOL_ARRAY, TAG_COLUMNAR, TAG_CUSTOM,
+    TAG_DATE, TAG_DATETIME, TAG_DATE_DAYS, TAG_DECIMAL, TAG_EXT, TAG_FINGERPRINT, TAG_INT_OBJECT,
+    TAG_NULL_BITMAP, TAG_RANGE, TAG_REF, TAG_SECTIONS, TAG_TENSOR, TAG_TIME, TAG_TIMEDELTA,
+    TAG_TIME_NANOS, TAG_TYPED_ARRAY, TAG_UUID,
+};
+
+pub(crate) type ScanResult<T> = Result<T, String>;
+
+/// One step of a path into a value: an object key or a list index.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum PathItem<'a> {
+    Key(&'a str),
+    Index(usize),
+}
+
+/// Render a path as `orders[3].customer.id`.
+pub(crate) fn format_path(path: &[PathItem]) -> String {
+    let mut out = String::new();
+    for item in path {
+        match item {
+            PathItem::Key(key) => {
+                if !out.is_empty() {
+                    out.push('.');
+                }
+                out.push_str(key);
+            }
+            PathItem::Index(index) => out.push_str(&format!("[{}]", index)),
+        }
+    }
+    out
+}
+
+/// Parse `orders[3].customer.id` (or `[0].id` for a root list) into path items.
+pub(crate) fn parse_path(path: &str) -> ScanResult<Vec<PathItem<'_>>> {
+    let mut items = Vec::new();
+    if path.is_empty() {
+        return Ok(items);
+    }
+    for (n, segment) in path.split('.').enumerate() {
+        let (key, mut rest) = match segment.find('[') {
+            Some(i) => segment.split_at(i),
+            None => (segment, ""),
+        };
+        if key.is_empty() && (n > 0 || rest.is_empty()) {
+            return Err(format!("Empty key in path: {:?}", path));
+        }
+        if !key.is_empty() {
+            items.push(PathItem::Key(key));
+        }
+        while !rest.is_empty() {
+            let close = rest
+                .find(']')
+                .filter(|_| rest.starts_with('['))
+                .ok_or_else(|| format!("Malformed index in path: {:?}", path))?;
+            let index = rest[1..close]
+                .parse::<usize>()
+                .map_err(|_| format!("Invalid list index {:?} in path", &rest[1..close]))?;
+            items.push(PathItem::Index(index));
+            rest = &rest[close + 1..];
+        }
+    }
+    Ok(items)
+}
+
+/// A parsed, uncompressed frame.
+pub(crate) struct Frame<'a> {
+    pub data: &'a [u8],
+    pub flags: u8,
+    pub strings: Vec<&'a str>,
+    /// The metadata section's frame, with FLAG_METADATA.
+    pub metadata: Option<&'a [u8]>,
+    /// The statistics frame behind it, if any; see stats.rs.
+    pub stats: Option<&'a [u8]>,
+    /// Record layout fingerprint in front of the root value.
+    pub fingerprint: Option<u64>,
+    /// Offset of the root value.
+    pub payload: usize,
+}
+
+/// Inflate `data` if needed. The flag tells whether the input was compressed.
+pub(crate) fn unpack(data: &[u8]) -> ScanResult<(Cow<'_, [u8]>, bool)> {
+    let unpacked = decompress_packed(data)?;
+    let compressed = matches!(unpacked, Cow::Owned(_));
+    Ok((unpacked, compressed))
+}
+
+/// Size of the header of the (decompressed) frame `data`: 6 bytes in
+/// version 1 frames, which have no length field, and `HEADER_SIZE` from
+/// version 2 on. The length is not checked against `data`, so tools can
+/// still walk what a truncated frame holds.
+pub(crate) fn header_size(data: &[u8]) -> ScanResult<usize> {
+    if data.len() < 6 {
+        return Err("Buffer too small for B-FAST header".to_string());
+    }
+    if &data[0..2] != b"BF" {
+        return Err("Invalid B-FAST magic number".to_string());
+    }
+    match data[3] {
+        0x01 => Ok(6),
+        FORMAT_VERSION if data.len() < HEADER_SIZE => {
+            Err("Buffer too small for B-FAST header".to_string())
+        }
+        FORMAT_VERSION => Ok(HEADER_SIZE),
+        version => Err(format!("Unsupported B-FAST format version {}", version)),
+    }
+}
+
+pub(crate) fn parse_frame(data: &[u8]) -> ScanResult<Frame<'_>> {
+    let mut offset = header_size(data)?;
+    let count = u16::from_le_bytes(data[4..6].try_into().unwrap()) as usize;
+
+    let mut strings = Vec::with_capacity(count);
+    if data[2] & FLAG_SCHEMA != 0 {
+        strings.extend(schema::frame_schema(data)?.fields.iter().copied());
+        offset += 4;
+    }
+    let (mut metadata, mut stats) = (None, None);
+    if data[2] & FLAG_METADATA != 0 {
+        let (section, end) = metadata_section(data, offset)?;
+        let (frame, trailer) = split_metadata(section)?;
+        (metadata, stats) = (Some(frame), trailer);
+        offset = end;
+    }
+    for _ in 0..count {
+        let length = *data
+            .get(offset)
+            .ok_or("Unexpected end of buffer in string table")? as usize;
+        offset += 1;
+        let bytes = data
+            .get(offset..offset + length)
+            .ok_or("String extends beyond buffer in string table")?;
+        strings.push(
+            std::str::from_utf8(bytes)
+                .map_err(|e| format!("Invalid UTF-8 in string table: {}", e))?,
+        );
+        offset += length;
+    }
+
+    let (fingerprint, payload) = root_fingerprint(data, offset)?;
+    Ok(Frame {
+        data,
+        flags: data[2],
+        strings,
+        metadata,
+        stats,
+        fingerprint,
+        payload,
+    })
+}
+
+/// The record layout fingerprint written in front of the root value at
+/// `offset`, if any, and the offset of the root value.
+pub(crate) fn root_fingerprint(data: &[u8], offset: usize) -> ScanResult<(Option<u64>, usize)> {
+    if data.get(offset) != Some(&TAG_FINGERPRINT) {
+        return Ok((None, offset));
+    }
+    let bytes = data
+        .get(offset + 1..offset + 9)
+        .ok_or("Unexpected end of buffer in fingerprint")?;
+    let fingerprint = u64::from_le_bytes(bytes.try_into().unwrap());
+    Ok((Some(fingerprint), offset + 9))
+}
+
+/// The metadata frame starting at `offset` and the offset after it.
+pub(crate) fn metadata_section(data: &[u8], offset: usize) -> ScanResult<(&[u8], usize)> {
+    let start = offset + 4;
+    let end = start + read_u32(data, offset)? as usize;
+    let section = data
+        .get(start..end)
+        .ok_or("Metadata section extends beyond buffer")?;
+    Ok((section, end))
+}
+
+/// Split a metadata section into the metadata frame and the statistics frame
+/// written behind it, if any.
+fn split_metadata(section: &[u8]) -> ScanResult<(&[u8], Option<&[u8]>)> {
+    if header_size(section)? != HEADER_SIZE {
+        return Ok((section, None));
+    }
+    let end = HEADER_SIZE + read_u32(section, 6)? as usize;
+    if end > section.len() {
+        return Err("Metadata frame extends beyond its section".to_string());
+    }
+    let stats = (end < section.len()).then(|| &section[end..]);
+    Ok((&section[..end], stats))
+}
+
+impl<'a> Frame<'a> {
+    /// View a bare payload under an owned string table.
+    pub fn view(payload: &'a [u8], strings: &'a [String]) -> Self {
+        Frame {
+            data: payload,
+            flags: 0,
+            strings: strings.iter().map(|s| s.as_str()).collect(),
+            metadata: None,
+            stats: None,
+            fingerprint: None,
+            payload: 0,
+        }
+    }
+
+    /// Reject frames with back-references, whose indices break when spliced.
+    pub fn require_plain(&self, operation: &str) -> ScanResult<()> {
+        if self.flags & FLAG_SHARED_REFS != 0 {
+            return Err(format!(
+                "{} does not support frames encoded with dedup",
+                operation
+            ));
+        }
+        // Null fields have no bytes to point at or splice
+        if self.flags & FLAG_NULL_BITMAP != 0 {
+            return Err(format!(
+                "{} does not support frames encoded with null_bitmap",
+                operation
+            ));
+        }
+        // Records are spread over columns
+        if self.flags & FLAG_COLUMNAR != 0 {
+            return Err(format!(
+                "{} does not support frames encoded with columnar",
+                operation
+            ));
+        }
+        // Packed booleans have no byte of their own
+        if self.flags & FLAG_PACKED_BOOLS != 0 {
+            return Err(format!(
+                "{} does not support frames encoded with pack_bools",
+                operation
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn tag(&self, offset: usize) -> ScanResult<u8> {
+        self.data
+            .get(offset)
+            .copied()
+            .ok_or_else(|| "Unexpected end of buffer during parsing".to_string())
+    }
+
+    pub fn u32_at(&self, offset: usize) -> ScanResult<u32> {
+        read_u32(self.data, offset)
+    }
+
+    /// The bytes `start..end` of the frame.
+    pub fn bytes(&self, start: usize, end: usize) -> ScanResult<&'a [u8]> {
+        self.data
+            .get(start..end)
+            .ok_or_else(|| "Unexpected end of buffer during parsing".to_string())
+    }
+
+    pub fn key(&self, id: u32) -> ScanResult<&'a str> {
+        self.strings
+            .get(id as usize)
+            .copied()
+            .ok_or_else(|| format!("Invalid string table index: {}", id))
+    }
+
+    /// End offset of the value starting at `offset`.
+    pub fn skip(&self, offset: usize) -> ScanResult<usize> {
+        skip_value(self.data, offset, 0)
+    }
+
+    /// Offsets of the elements of the list at `offset`.
+    pub fn list_items(&self, offset: usize) -> ScanResult<Vec<usize>> {
+        let count = self.u32_at(offset + 1)? as usize;
+        let mut items = Vec::with_capacity(count.min(self.data.len()));
+        let mut cursor = offset + 5;
+        for _ in 0..count {
+            items.push(cursor);
+            cursor = self.skip(cursor)?;
+        }
+        Ok(items)
+    }
+
+    /// `(key, key offset, value offset)` for every entry of the object at `offset`.
+    pub fn object_entries(&self, offset: usize) -> ScanResult<Vec<(&'a str, usize, usize)>> {
+        let mut entries = Vec::new();
+        let mut cursor = offset + 1;
+        while self.tag(cursor)? != 0x7F {
+            let key = self.key(self.u32_at(cursor)?)?;
+            entries.push((key, cursor, cursor + 4));
+            cursor = self.skip(cursor + 4)?;
+        }
+        Ok(entries)
+    }
+
+    /// `(key, value offset)` of every entry of an integer-keyed dict.
+    pub fn int_entries(&self, offset: usize) -> ScanResult<Vec<(i64, usize)>> {
+        let count = self.u32_at(offset + 1)?;
+        let mut entries = Vec::new();
+        let mut cursor = offset + 5;
+        for _ in 0..count {
+            let value = self.skip(cursor)?;
+            entries.push((self.int_at(cursor)?, value));
+            cursor = self.skip(value)?;
+        }
+        Ok(entries)
+    }
+
+    /// `(key, value offset)` of every section, found through the index.
+    pub fn sections(&self, offset: usize) -> ScanResult<Vec<(&'a str, usize)>> {
+        let count = self.u32_at(offset + 1)? as usize;
+        let mut sections = Vec::with_capacity(count.min(self.data.len()));
+        let mut cursor = offset + 5 + count * 8;
+        for entry in (offset + 5..offset + 5 + count * 8).step_by(8) {
+            sections.push((self.key(self.u32_at(entry)?)?, cursor));
+            cursor += self.u32_at(entry + 4)? as usize;
+        }
+        Ok(sections)
+    }
+
+    /// Offset of the child selected by `item` in the container at `offset`.
+    pub fn child(&self, offset: usize, item: &PathItem) -> ScanResult<Option<usize>> {
+        match (self.tag(offset)?, item) {
+            (0x70, PathItem::Key(key)) => Ok(self
+                .object_entries(offset)?
+                .into_iter()
+                .rev()
+                .find(|(k, _, _)| k == key)
+                .map(|(_, _, value)| value)),
+            (TAG_INT_OBJECT, PathItem::Key(key)) => match key.parse::<i64>() {
+                Ok(key) => self.int_child(offset, key),
+                Err(_) => Ok(None),
+            },
+            (TAG_INT_OBJECT, PathItem::Index(key)) => self.int_child(offset, *key as i64),
+            (TAG_SECTIONS, PathItem::Key(key)) => Ok(self
+                .sections(offset)?
+                .into_iter()
+                .rev()
+                .find(|(k, _)| k == key)
+                .map(|(_, value)| value)),
+            (0x60, PathItem::Index(index)) => {
+                if *index >= self.u32_at(offset + 1)? as usize {
+                    return Ok(None);
+                }
+                let mut cursor = offset + 5;
+                for _ in 0..*index {
+                    cursor = self.skip(cursor)?;
+                }
+                Ok(Some(cursor))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    fn int_child(&self, offset: usize, key: i64) -> ScanResult<Option<usize>> {
+        Ok(self
+            .int_entries(offset)?
+            .into_iter()
+            .rev()
+            .find(|&(k, _)| k == key)
+            .map(|(_, value)| value))
+    }
+
+    /// Offset of the value at `path` below `offset`, `None` if it does not exist.
+    pub fn resolve(&self, offset: usize, path: &[PathItem]) -> ScanResult<Option<usize>> {
+        let mut cursor = offset;
+        for item in path {
+            match self.child(cursor, item)? {
+                Some(next) => cursor = next,
+                None => return Ok(None),
+            }
+        }
+        Ok(Some(cursor))
+    }
+
+    /// Float stored at `offset`, f64 or f32.
+    pub fn float_at(&self, offset: usize) -> ScanResult<f64> {
+        let tag = self.tag(offset)?;
+        let width =
+            float_width(tag).ok_or_else(|| format!("Expected a float, found tag 0x{:02x}", tag))?;
+        self.data
+            .get(offset + 1..offset + 1 + width)
+            .map(float_value)
+            .ok_or_else(|| "Unexpected end of buffer during parsing".to_string())
+    }
+
+    /// Integer stored at `offset`, in any of its widths.
+    pub fn int_at(&self, offset: usize) -> ScanResult<i64> {
+        let tag = self.tag(offset)?;
+        let width = int_width(tag)
+            .ok_or_else(|| format!("Expected an integer, found tag 0x{:02x}", tag))?;
+        self.data
+            .get(offset + 1..offset + 1 + width)
+            .map(|bytes| int_value(tag, bytes))
+            .ok_or_else(|| "Unexpected end of buffer during parsing".to_string())
+    }
+
+    /// Copy the value at `offset` into `out`, re-interning object keys in `table`.
+    pub fn copy_value(
+        &self,
+        offset: usize,
+        table: &mut StringTable,
+        out: &mut Vec<u8>,
+    ) -> ScanResult<usize> {
+        self.copy_inner(offset, table, out, 0)
+    }
+
+    fn copy_inner(
+        &self,
+        offset: usize,
+        table: &mut StringTable,
+        out: &mut Vec<u8>,
+        depth: usize,
+    ) -> ScanResult<usize> {
+        if depth > MAX_RECURSION_DEPTH {
+            return Err("Maximum recursion depth exceeded during B-FAST scan".to_string());
+        }
+        match self.tag(offset)? {
+            0x60 => {
+                let count = self.u32_at(offset + 1)?;
+                out.extend_from_slice(self.bytes(offset, offset + 5)?);
+                let mut cursor = offset + 5;
+                for _ in 0..count {
+                    cursor = self.copy_inner(cursor, table, out, depth + 1)?;
+                }
+                Ok(cursor)
+            }
+            0x70 => {
+                out.push(0x70);
+                let mut cursor = offset + 1;
+                while self.tag(cursor)? != 0x7F {
+                    let id = table.intern(self.key(self.u32_at(cursor)?)?)?;
+                    out.extend_from_slice(&id.to_le_bytes());
+                    cursor = self.copy_inner(cursor + 4, table, out, depth + 1)?;
+                }
+                out.push(0x7F);
+                Ok(cursor + 1)
+            }
+            TAG_INT_OBJECT => {
+                let count = self.u32_at(offset + 1)?;
+                out.extend_from_slice(self.bytes(offset, offset + 5)?);
+                let mut cursor = offset + 5;
+                for _ in 0..count {
+                    let value = self.skip(cursor)?;
+                    out.extend_from_slice(self.bytes(cursor, value)?);
+                    cursor = self.copy_inner(value, table, out, depth + 1)?;
+                }
+                Ok(cursor)
+            }
+            TAG_SECTIONS => {
+                // The index is filled in as the values are copied
+                let sections = self.sections(offset)?;
+                out.extend_from_slice(self.bytes(offset, offset + 5)?);
+                let index = out.len();
+                out.resize(index + sections.len() * 8, 0);
+                let mut cursor = offset + 5 + sections.len() * 8;
+                for (i, (key, value)) in sections.into_iter().enumerate() {
+                    let start = out.len();
+                    cursor = self.copy_inner(value, table, out, depth + 1)?;
+                    let length = (out.len() - start) as u32;
+                    let entry = index + i * 8;
+                    out[entry..entry + 4].copy_from_slice(&table.intern(key)?.to_le_bytes());
+                    out[entry + 4..entry + 8].copy_from_slice(&length.to_le_bytes());
+                }
+                Ok(cursor)
+            }
+            TAG_CUSTOM => {
+                let value = offset + 5 + self.u32_at(offset + 1)? as usize;
+                out.extend_from_slice(self.bytes(offset, value)?);
+                self.copy_inner(value, table, out, depth + 1)
+            }
+            _ => {
+                let end = self.skip(offset)?;
+                out.extend_from_slice(self.bytes(offset, end)?);
+                Ok(end)
+            }
+        }
+    }
+
+    /// Structural equality of two values that may live in different frames.
+    pub fn value_eq(&self, offset: usize, other: &Frame, other_offset: usize) -> ScanResult<bool> {
+        let tag = self.tag(offset)?;
+        if tag != other.tag(other_offset)? {
+            return Ok(false);
+        }
+        match tag {
+            0x60 => {
+                let (a, b) = (self.list_items(offset)?, other.list_items(other_offset)?);
+                if a.len() != b.len() {
+                    return Ok(false);
+                }
+                for (x, y) in a.into_iter().zip(b) {
+                    if !self.value_eq(x, other, y)? {
+                        return Ok(false);
+                    }
+                }
+                Ok(true)
+            }
+            TAG_INT_OBJECT => {
+                let a = self.int_entries(offset)?;
+                let b = other.int_entries(other_offset)?;
+                if a.len() != b.len() {
+                    return Ok(false);
+                }
+                for ((ka, va), (kb, vb)) in a.into_iter().zip(b) {
+                    if ka != kb || !self.value_eq(va, other, vb)? {
+                        return Ok(false);
+                    }
+                }
+                Ok(true)
+            }
+            TAG_CUSTOM => {
+                let value = offset + 5 + self.u32_at(offset + 1)? as usize;
+                let other_value = other_offset + 5 + other.u32_at(other_offset + 1)? as usize;
+                Ok(
+                    self.data.get(offset..value) == other.data.get(other_offset..other_value)
+                        && self.value_eq(value, other, other_value)?,
+                )
+            }
+            TAG_SECTIONS => {
+                let a = self.sections(offset)?;
+                let b = other.sections(other_offset)?;
+                if a.len() != b.len() {
+                    return Ok(false);
+                }
+                for ((ka, va), (kb, vb)) in a.into_iter().zip(b) {
+                    if ka != kb || !self.value_eq(va, other, vb)? {
+                        return Ok(false);
+                    }
+                }
+                Ok(true)
+            }
+            0x70 => {
+                let a = self.object_entries(offset)?;
+                let b = other.object_entries(other_offset)?;
+                if a.len() != b.len() {
+                    return Ok(false);
+                }
+                for ((ka, _, va), (kb, _, vb)) in a.into_iter().zip(b) {
+                    if ka != kb || !self.value_eq(va, other, vb)? {
+                        return Ok(false);
+                    }
+                }
+                Ok(true)
+            }
+            _ => {
+                let end = self.skip(offset)?;
+                let other_end = other.skip(other_offset)?;
+                Ok(self.data[offset..end] == other.data[other_offset..other_end])
+            }
+        }
+    }
+}
+
+#[inline]
+pub(crate) fn read_u32(data: &[u8], offset: usize) -> ScanResult<u32> {
+    data.get(offset..offset + 4)
+        .map(|b| u32::from_le_bytes(b.try_into().unwrap()))
+        .ok_or_else(|| "Unexpected end of buffer during parsing".to_string())
+}
+
+fn skip_value(data: &[u8], offset: usize, depth: usize) -> ScanResult<usize> {
+    if depth > MAX_RECURSION_DEPTH {
+        return Err("Maximum recursion depth exceeded during B-FAST scan".to_string());
+    }
+    let tag = *data
+        .get(offset)
+        .ok_or("Unexpected end of buffer during parsing")?;
+    let end = match tag {
+        0x10 | 0x20 | 0x21 => offset + 1,
+        0x38 | 0x40 | TAG_TIMEDELTA | TAG_TIME_NANOS => offset + 9,
+        TAG_DATE_DAYS | TAG_INT32 | TAG_FLOAT32 => offset + 5,
+        TAG_INT8 => offset + 2,
+        TAG_INT16 => offset + 3,
+        t if is_small_int(t) => offset + 1,
+        TAG_DATETIME if read_u32(data, offset + 1)? == temporal::DATETIME_NANOS => {
+            offset + 5 + temporal::DATETIME_NANOS_LEN
+        }
+        0x50 | 0x80 | TAG_DATETIME | TAG_DATE | TAG_TIME | TAG_UUID | TAG_DECIMAL
+        | TAG_BLOB_REF => offset + 5 + read_u32(data, offset + 1)? as usize,
+        0x90 => offset + 5 + read_u32(data, offset + 1)? as usize * 8,
+        TAG_BOOL_ARRAY => offset + 5 + (read_u32(data, offset + 1)? as usize).div_ceil(8),
+        TAG_TYPED_ARRAY | TAG_EXT => offset + 6 + read_u32(data, offset + 2)? as usize,
+        TAG_SECTIONS => {
+            let count = read_u32(data, offset + 1)? as usize;
+            let mut cursor = offset + 5 + count * 8;
+            for entry in (offset + 5..cursor).step_by(8) {
+                cursor += read_u32(data, entry + 4)? as usize;
+            }
+            cursor
+        }
+        TAG_INT_OBJECT => {
+            let mut cursor = offset + 5;
+            for _ in 0..read_u32(data, offset + 1)? {
+                cursor = skip_value(data, cursor, depth + 1)?;
+                cursor = skip_value(data, cursor, depth + 1)?;
+            }
+            cursor
+        }
+        TAG_RANGE => {
+            let mut cursor = offset + 1;
+            for _ in 0..3 {
+                cursor = skip_value(data, cursor, depth + 1)?;
+            }
+            cursor
+        }
+        TAG_REF => offset + 5,
+        TAG_CUSTOM => {
+            let value = offset + 5 + read_u32(data, offset + 1)? as usize;
+            skip_value(data, value, depth + 1)?
+        }
+        TAG_TENSOR => {
+            let ndim = *data
+                .get(offset + 3)
+                .ok_or("Unexpected end of buffer during parsing")? as usize;
+            let length_at = offset + 4 + ndim * 4;
+            length_at + 4 + read_u32(data, length_at)? as usize
+        }
+        0x60 => {
+            let count = read_u32(data, offset + 1)?;
+            let mut cursor = offset + 5;
+            for _ in 0..count {
+                cursor = skip_value(data, cursor, depth + 1)?;
+            }
+            cursor
+        }
+        TAG_COLUMNAR => {
+            let rows = read_u32(data, offset + 1)?;
+            let columns = data
+                .get(offset + 5..offset + 7)
+                .map(|b| u16::from_le_bytes(b.try_into().unwrap()))
+                .ok_or("Unexpected end of buffer during parsing")?;
+            let mut cursor = offset + 7;
+            for _ in 0..columns {
+                let encoding = *data
+                    .get(cursor + 4)
+                    .ok_or("Unexpected end of buffer during parsing")?;
+                cursor += 5;
+                match encoding {
+                    columnar::COL_PLAIN => {
+                        for _ in 0..rows {
+                            cursor = skip_value(data, cursor, depth + 1)?;
+                        }
+                    }
+                    columnar::COL_BOOL => cursor += (rows as usize).div_ceil(8),
+                    columnar::COL_DELTA | columnar::COL_XOR => {
+                        cursor += 4 + read_u32(data, cursor)? as usize
+                    }
+                    columnar::COL_RLE => {
+                        let runs = read_u32(data, cursor)?;
+                        cursor += 4;
+                        for _ in 0..runs {
+                            cursor = skip_value(data, cursor + 4, depth + 1)?;
+                        }
+                    }
+                    columnar::COL_DICT => {
+                        let entries = read_u32(data, cursor)?;
+                        let width = *data
+                            .get(cursor + 4)
+                            .ok_or("Unexpected end of buffer during parsing")?;
+                        cursor += 5;
+                        for _ in 0..entries {
+                            cursor = skip_value(data, cursor, depth + 1)?;
+                        }
+                        cursor += rows as usize * width as usize;
+                    }
+                    e => return Err(format!("Unknown column encoding: {}", e)),
+                }
+            }
+            cursor
+        }
+        0x70 | TAG_NULL_BITMAP => {
+            let mut cursor = offset + 1;
+            if tag == TAG_NULL_BITMAP {
+                let count = data
+                    .get(offset + 5..offset + 7)
+                    .map(|b| u16::from_le_bytes(b.try_into().unwrap()) as usize)
+                    .ok_or("Unexpected end of buffer during parsing")?;
+                let bitmap = data
+                    .get(offset + 7..offset + 7 + count.div_ceil(8))
+                    .ok_or("Unexpected end of buffer during parsing")?;
+                cursor = offset + 7 + bitmap.len();
+                for _ in 0..bitmap.iter().map(|b| b.count_ones()).sum::<u32>() {
+                    cursor = skip_value(data, cursor, depth + 1)?;
+                }
+            }
+            loop {
+                match data.get(cursor) {
+                    Some(0x7F) => break cursor + 1,
+                    Some(_) => cursor = skip_value(data, cursor + 4, depth + 1)?,
+                    None => return Err("Object not properly terminated".to_string()),
+                }
+            }
+        }
+        t => return Err(format!("Unknown tag: 0x{:02x}", t)),
+    };
+    if end > data.len() {
+        return Err("Unexpected end of buffer during parsing".to_string());
+    }
+    Ok(end)
+}
+
+/// First of the tags holding -16 to -1 in their low nibble.
+pub(crate) const TAG_NEGATIVE_INT: u8 = 0xB0;
+
+/// Whether `tag` is a small int, which carries its value in the low nibble:
+/// 0x30-0x3F (0x38 excepted) hold 0 to 15, 0xB0-0xBF hold -16 to -1.
+#[inline]
+pub(crate) fn is_small_int(tag: u8) -> bool {
+    (tag & 0xF0 == 0x30 && tag != 0x38) || tag & 0xF0 == TAG_NEGATIVE_INT
+}
+
+/// Value of the small int `tag`.
+#[inline]
+pub(crate) fn small_int(tag: u8) -> i64 {
+    match tag & 0xF0 {
+        TAG_NEGATIVE_INT => (tag & 0x0F) as i64 - 16,
+        _ => (tag & 0x0F) as i64,
+    }
+}
+
+/// Tags of ints stored in 1, 2 and 4 bytes; the low nibble is the width.
+pub(crate) const TAG_INT8: u8 = 0xC1;
+pub(crate) const TAG_INT16: u8 = 0xC2;
+pub(crate) const TAG_INT32: u8 = 0xC4;
+
+/// Bytes following the int tag `tag`: none for small ints, 1, 2, 4 or 8 for
+/// the others; None when `tag` is not an int.
+#[inline]
+pub(crate) fn int_width(tag: u8) -> Option<usize> {
+    match tag {
+        0x38 => Some(8),
+        TAG_INT8 | TAG_INT16 | TAG_INT32 => Some((tag & 0x0F) as usize),
+        t if is_small_int(t) => Some(0),
+        _ => None,
+    }
+}
+
+/// Value of the int tagged `tag`, from the `int_width(tag)` bytes after it.
+#[inline]
+pub(crate) fn int_value(tag: u8, bytes: &[u8]) -> i64 {
+    match bytes.len() {
+        0 => small_int(tag),
+        1 => bytes[0] as i8 as i64,
+        2 => i16::from_le_bytes(bytes.try_into().unwrap()) as i64,
+        4 => i32::from_le_bytes(bytes.try_into().unwrap()) as i64,
+        _ => i64::from_le_bytes(bytes.try_into().unwrap()),
+    }
+}
+
+/// Tag of floats stored as f32, written by `float32` encoders.
+pub(crate) const TAG_FLOAT32: u8 = 0x41;
+
+/// Bytes following the float tag `tag` (8 for f64, 4 for f32); None when
+/// `tag` is not a float.
+#[inline]
+pub(crate) fn float_width(tag: u8) -> Option<usize> {
+    match tag {
+        0x40 => Some(8),
+        TAG_FLOAT32 => Some(4),
+        _ => None,
+    }
+}
+
+/// Value of a float from the `float_width` bytes after its tag.
+#[inline]
+pub(crate) fn float_value(bytes: &[u8]) -> f64 {
+    match bytes.len() {
+        4 => f32::from_le_bytes(bytes.try_into().unwrap()) as f64,
+        _ => f64::from_le_bytes(bytes.try_into().unwrap()),
+    }
+}
+
+/// Append an integer the way the encoder does: packed in the tag when small,
+/// otherwise in the fewest of 1, 2, 4 or 8 bytes that hold it.
+pub(crate) fn write_int(out: &mut Vec<u8>, value: i64) {
+    if (0..16).contains(&value) && value != 8 {
+        out.push(0x30 | value as u8);
+    } else if (-16..0).contains(&value) {
+        out.push(TAG_NEGATIVE_INT | (value + 16) as u8);
+    } else if let Ok(value) = i8::try_from(value) {
+        out.extend_from_slice(&[TAG_INT8, value as u8]);
+    } else if let Ok(value) = i16::try_from(value) {
+        out.push(TAG_INT16);
+        out.extend_from_slice(&value.to_le_bytes());
+    } else if let Ok(value) = i32::try_from(value) {
+        out.push(TAG_INT32);
+        out.extend_from_slice(&value.to_le_bytes());
+    } else {
+        out.push(0x38);
+        out.extend_from_slice(&value.to_le_bytes());
+    }
+}
+
+/// String table for a frame being assembled.
+#[derive(Default)]
+pub(crate) struct StringTable {
+    pub strings: Vec<String>,
+    index: AHashMap<String, u32>,
+}
+
+impl StringTable {
+    pub fn from_frame(frame: &Frame) -> Self {
+        let mut table = StringTable::default();
+        for s in &frame.strings {
+            // Ids must stay stable, so duplicates keep their first slot
+            let id = table.strings.len() as u32;
+            table.index.entry(s.to_string()).or_insert(id);
+            table.strings.push(s.to_string());
+        }
+        table
+    }
+
+    pub fn intern(&mut self, key: &str) -> ScanResult<u32> {
+        if let Some(&id) = self.index.get(key) {
+            return Ok(id);
+        }
+        if key.len() > u8::MAX as usize {
+            return Err(format!("Key exceeds 255 bytes: {:?}", key));
+        }
+        if self.strings.len() >= u16::MAX as usize {
+            return Err("String table exceeds 65535 entries".to_string());
+        }
+        let id = self.strings.len() as u32;
+        self.index.insert(key.to_string(), id);
+        self.strings.push(key.to_string());
+        Ok(id)
+    }
+
+    /// Assemble header, metadata, string table and payload, compressing when
+    /// asked.
+    pub fn build_frame(
+        &self,
+        payload: &[u8],
+        flags: u8,
+        metadata: Option<&[u8]>,
+        compress: bool,
+    ) -> ScanResult<Vec<u8>> {
+        let table_size: usize = self.strings.iter().map(|s| s.len() + 1).sum();
+        let metadata_size = metadata.map_or(0, |m| 4 + m.len());
+        let mut frame =
+            Vec::with_capacity(HEADER_SIZE + metadata_size + table_size + payload.len());
+        frame.extend_from_slice(b"BF");
+        // Every key is written out, so the result never references a schema
+        let mut flags = flags & !(FLAG_COMPRESSED | FLAG_SCHEMA | FLAG_METADATA);
+        if metadata.is_some() {
+            flags |= FLAG_METADATA;
+        }
+        frame.push(flags);
+        frame.push(FORMAT_VERSION);
+        frame.extend_from_slice(&(self.strings.len() as u16).to_le_bytes());
+        let length = u32::try_from(metadata_size + table_size + payload.len())
+            .map_err(|_| "Frame larger than 4 GiB".to_string())?;
+        frame.extend_from_slice(&length.to_le_bytes());
+        if let Some(metadata) = metadata {
+            frame.extend_from_slice(&(metadata.len() as u32).to_le_bytes());
+            frame.extend_from_slice(metadata);
+        }
+        for s in &self.strings {
+            frame.push(s.len() as u8);
+            frame.extend_from_slice(s.as_bytes());
+        }
+        frame.extend_from_slice(payload);
+
+        if compress && frame.len() > 256 {
+            compress_frame(frame)
+        } else {
+            Ok(frame)
+        }
+    }
+}
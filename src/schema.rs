@@ -0,0 +1,487 @@
+// Schemas: field lists registered once and referenced by a 4-byte ID.
+//
+// A frame encoded with a schema sets FLAG_SCHEMA and stores the schema ID
+// right after the header instead of spelling out every key. Key IDs below the
+// schema's field count name its fields; the inline string table only carries
+// keys outside the schema, numbered from there on. Decoders resolve the ID
+// from a process-wide registry, then from a user-supplied resolver (for
+// centrally managed schemas) whose answers are kept in an LRU cache.
+//
+// Decoding with a reader schema evolves records written with an older one:
+// old names are mapped through aliases, fields the reader does not know are
+// dropped and missing fields are filled from defaults.
+
+use crate::{coerce, scan, validate};
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList, PySet, PyString};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::sync::{Arc, Mutex, RwLock};
+use xxhash_rust::xxh3::xxh3_64;
+
+pub(crate) struct SchemaDef {
+    pub id: u32,
+    pub name: Option<String>,
+    pub fields: Vec<&'static str>,
+    /// Values for fields missing from records written with another schema.
+    pub defaults: Vec<(&'static str, PyObject)>,
+    /// Old field name -> current field name.
+    pub aliases: HashMap<String, &'static str>,
+    /// Classes field values are checked against; see validate.rs.
+    pub types: HashMap<String, validate::FieldType>,
+    /// Conversions applied to field values; see coerce.rs.
+    pub coercions: Vec<(&'static str, coerce::Coercion)>,
+    /// Fields records may leave out without a default (the keys of a
+    /// TypedDict that are not required).
+    pub optional: Vec<&'static str>,
+}
+
+static REGISTRY: RwLock<BTreeMap<u32, Arc<SchemaDef>>> = RwLock::new(BTreeMap::new());
+
+// Field names live as long as the process so byte-level frames can borrow
+// them like string table entries; each distinct name is stored once
+static FIELD_NAMES: Mutex<BTreeSet<&'static str>> = Mutex::new(BTreeSet::new());
+
+fn intern_field(name: &str) -> &'static str {
+    let mut names = FIELD_NAMES.lock().unwrap();
+    if let Some(&existing) = names.get(name) {
+        return existing;
+    }
+    let leaked: &'static str = Box::leak(name.to_owned().into_boxed_str());
+    names.insert(leaked);
+    leaked
+}
+
+pub(crate) fn lookup(id: u32) -> Option<Arc<SchemaDef>> {
+    REGISTRY.read().unwrap().get(&id).cloned()
+}
+
+struct Resolver {
+    // Shared so it can be called without holding the resolver lock
+    fetch: Arc<PyObject>,
+    capacity: usize,
+    cache: HashMap<u32, Arc<SchemaDef>>,
+    // Least recently used first
+    order: VecDeque<u32>,
+}
+
+impl Resolver {
+    fn get(&mut self, id: u32) -> Option<Arc<SchemaDef>> {
+        let def = self.cache.get(&id)?.clone();
+        self.order.retain(|&cached| cached != id);
+        self.order.push_back(id);
+        Some(def)
+    }
+
+    fn insert(&mut self, def: Arc<SchemaDef>) {
+        if self.cache.insert(def.id, def.clone()).is_some() {
+            self.order.retain(|&cached| cached != def.id);
+        } else if self.cache.len() > self.capacity {
+            if let Some(evicted) = self.order.pop_front() {
+                self.cache.remove(&evicted);
+            }
+        }
+        self.order.push_back(def.id);
+    }
+}
+
+static RESOLVER: Mutex<Option<Resolver>> = Mutex::new(None);
+
+/// Ask the registered resolver for schema `id`, going through its cache.
+fn resolve(id: u32) -> Result<Option<Arc<SchemaDef>>, String> {
+    let fetch = {
+        let mut resolver = RESOLVER.lock().unwrap();
+        let Some(resolver) = resolver.as_mut() else {
+            return Ok(None);
+        };
+        if let Some(def) = resolver.get(id) {
+            return Ok(Some(def));
+        }
+        resolver.fetch.clone()
+    };
+
+    let def = Python::with_gil(|py| -> PyResult<Option<Arc<SchemaDef>>> {
+        let result = fetch.call1(py, (id,))?.into_ref(py);
+        if result.is_none() {
+            return Ok(None);
+        }
+        if let Ok(schema) = result.extract::<PyRef<BFastSchema>>() {
+            if schema.def.id != id {
+                return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                    "resolver returned schema ID {}",
+                    schema.def.id
+                )));
+            }
+            return Ok(Some(schema.def.clone()));
+        }
+        Ok(Some(Arc::new(SchemaDef {
+            id,
+            name: None,
+            fields: checked_fields(&field_names(result)?)?,
+            defaults: Vec::new(),
+            aliases: HashMap::new(),
+            types: HashMap::new(),
+            coercions: Vec::new(),
+            optional: Vec::new(),
+        })))
+    })
+    .map_err(|e| format!("Schema resolver failed for ID {}: {}", id, e))?;
+
+    if let Some(def) = &def {
+        if let Some(resolver) = RESOLVER.lock().unwrap().as_mut() {
+            resolver.insert(def.clone());
+        }
+    }
+    Ok(def)
+}
+
+/// Schema referenced by a frame with FLAG_SCHEMA (its ID follows the header).
+pub(crate) fn frame_schema(data: &[u8]) -> Result<Arc<SchemaDef>, String> {
+    let offset = scan::header_size(data)?;
+    let id = data
+        .get(offset..offset + 4)
+        .map(|b| u32::from_le_bytes(b.try_into().unwrap()))
+        .ok_or("Buffer too small for B-FAST schema ID")?;
+    if let Some(def) = lookup(id) {
+        return Ok(def);
+    }
+    resolve(id)?.ok_or_else(|| {
+        format!(
+            "Unknown schema ID {}; register it with b_fast.register_schema()",
+            id
+        )
+    })
+}
+
+pub(crate) fn register(def: &Arc<SchemaDef>) -> PyResult<()> {
+    let mut registry = REGISTRY.write().unwrap();
+    match registry.get(&def.id) {
+        Some(existing) if existing.fields != def.fields => {
+            Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                "Schema ID {} is already registered with different fields",
+                def.id
+            )))
+        }
+        Some(_) => Ok(()),
+        None => {
+            registry.insert(def.id, def.clone());
+            Ok(())
+        }
+    }
+}
+
+/// Field names of a Pydantic model (v2 or v1), a dataclass, a TypedDict or a
+/// list of strings.
+fn field_names(source: &PyAny) -> PyResult<Vec<String>> {
+    for attr in ["model_fields", "__fields__", "__dataclass_fields__"] {
+        if let Ok(fields) = source.getattr(attr) {
+            if let Ok(fields) = fields.downcast::<PyDict>() {
+                return fields.keys().iter().map(|k| k.extract()).collect();
+            }
+        }
+    }
+    // TypedDict classes keep every key, inherited ones included, in their
+    // annotations
+    if is_typed_dict(source)? {
+        let annotations = source.getattr("__annotations__")?.downcast::<PyDict>()?;
+        return annotations.keys().iter().map(|k| k.extract()).collect();
+    }
+    if source.is_instance_of::<PyString>() {
+        return Err(PyErr::new::<pyo3::exceptions::PyTypeError, _>(
+            "BFastSchema expects a model class or a list of field names, not a string",
+        ));
+    }
+    source.iter()?.map(|name| name?.extract()).collect()
+}
+
+pub(crate) fn is_typed_dict(source: &PyAny) -> PyResult<bool> {
+    Ok(source.hasattr("__total__")? && source.hasattr("__annotations__")?)
+}
+
+/// Keys of a TypedDict that records may leave out.
+fn optional_keys(source: &PyAny) -> PyResult<Vec<String>> {
+    if !is_typed_dict(source)? {
+        return Ok(Vec::new());
+    }
+    // Python 3.8 only records whether all keys are required
+    let keys = match source.getattr("__optional_keys__") {
+        Ok(keys) => keys,
+        Err(_) if source.getattr("__total__")?.is_true()? => return Ok(Vec::new()),
+        Err(_) => source.getattr("__annotations__")?,
+    };
+    let mut keys = keys
+        .iter()?
+        .map(|key| key?.extract())
+        .collect::<PyResult<Vec<String>>>()?;
+    keys.sort();
+    Ok(keys)
+}
+
+fn checked_fields(names: &[String]) -> PyResult<Vec<&'static str>> {
+    let mut seen = BTreeSet::new();
+    for field in names {
+        if !seen.insert(field.as_str()) {
+            return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                "Duplicate field in schema: {:?}",
+                field
+            )));
+        }
+    }
+    Ok(names.iter().map(|f| intern_field(f)).collect())
+}
+
+/// Defaults declared on a Pydantic model (v2 or v1) or a dataclass.
+fn model_defaults(source: &PyAny) -> PyResult<Vec<(String, PyObject)>> {
+    let py = source.py();
+    let mut defaults = Vec::new();
+    if let Ok(fields) = source.getattr("model_fields") {
+        if let Ok(fields) = fields.downcast::<PyDict>() {
+            let kwargs = PyDict::new(py);
+            kwargs.set_item("call_default_factory", true)?;
+            for (name, info) in fields {
+                if !info.call_method0("is_required")?.is_true()? {
+                    let value = info.call_method("get_default", (), Some(kwargs))?;
+                    defaults.push((name.extract()?, value.into()));
+                }
+            }
+            return Ok(defaults);
+        }
+    }
+    if let Ok(fields) = source.getattr("__fields__") {
+        if let Ok(fields) = fields.downcast::<PyDict>() {
+            for (name, info) in fields {
+                if !info.getattr("required")?.is_true()? {
+                    defaults.push((name.extract()?, info.call_method0("get_default")?.into()));
+                }
+            }
+            return Ok(defaults);
+        }
+    }
+    if let Ok(fields) = source.getattr("__dataclass_fields__") {
+        if let Ok(fields) = fields.downcast::<PyDict>() {
+            let missing = py.import("dataclasses")?.getattr("MISSING")?;
+            for (name, field) in fields {
+                let default = field.getattr("default")?;
+                let factory = field.getattr("default_factory")?;
+                if !default.is(missing) {
+                    defaults.push((name.extract()?, default.into()));
+                } else if !factory.is(missing) {
+                    defaults.push((name.extract()?, factory.call0()?.into()));
+                }
+            }
+        }
+    }
+    Ok(defaults)
+}
+
+fn default_value<'py>(py: Python<'py>, value: &'py PyObject) -> PyResult<&'py PyAny> {
+    let value = value.as_ref(py);
+    // Every record gets its own copy of mutable defaults
+    if value.is_instance_of::<PyDict>()
+        || value.is_instance_of::<PyList>()
+        || value.is_instance_of::<PySet>()
+    {
+        return py.import("copy")?.call_method1("deepcopy", (value,));
+    }
+    Ok(value)
+}
+
+pub(crate) fn evolve_record<'py>(
+    py: Python<'py>,
+    record: &'py PyDict,
+    def: &SchemaDef,
+) -> PyResult<&'py PyDict> {
+    let fields: HashSet<&str> = def.fields.iter().copied().collect();
+    let evolved = PyDict::new(py);
+    for (key, value) in record {
+        if let Ok(key) = key.extract::<&str>() {
+            if fields.contains(key) {
+                evolved.set_item(key, value)?;
+            }
+        }
+    }
+    // Current names win over values written under an old name
+    for (key, value) in record {
+        if let Ok(key) = key.extract::<&str>() {
+            if let Some(&field) = def.aliases.get(key) {
+                if !evolved.contains(field)? {
+                    evolved.set_item(field, value)?;
+                }
+            }
+        }
+    }
+    for (field, value) in &def.defaults {
+        if !evolved.contains(*field)? {
+            evolved.set_item(*field, default_value(py, value)?)?;
+        }
+    }
+    coerce::coerce_decoded(evolved, def)?;
+    Ok(evolved)
+}
+
+/// Evolve the records of a decoded payload (the root object, or the objects
+/// of a root list) to the shape of the reader schema `def`.
+pub(crate) fn evolve(py: Python, value: PyObject, def: &SchemaDef) -> PyResult<PyObject> {
+    let root = value.as_ref(py);
+    if let Ok(record) = root.downcast::<PyDict>() {
+        return Ok(evolve_record(py, record, def)?.into());
+    }
+    if let Ok(list) = root.downcast::<PyList>() {
+        for (index, item) in list.iter().enumerate() {
+            if let Ok(record) = item.downcast::<PyDict>() {
+                list.set_item(index, evolve_record(py, record, def)?)?;
+            }
+        }
+    }
+    Ok(value)
+}
+
+#[allow(non_local_definitions)]
+#[pyclass]
+pub struct BFastSchema {
+    pub(crate) def: Arc<SchemaDef>,
+}
+
+#[allow(non_local_definitions)]
+#[pymethods]
+impl BFastSchema {
+    /// Build a schema from a Pydantic model class, a dataclass, a TypedDict or
+    /// a list of field names. Without an explicit `id`, one is derived from the fields.
+    /// `defaults` extends the model's own defaults; `aliases` maps a field to
+    /// the name it was written under by an older schema; `coerce` maps a field
+    /// to the type its values are converted to.
+    #[new]
+    #[pyo3(signature = (
+        source,
+        *,
+        id = None,
+        name = None,
+        defaults = None,
+        aliases = None,
+        coerce = None
+    ))]
+    fn new(
+        source: &PyAny,
+        id: Option<u32>,
+        name: Option<String>,
+        defaults: Option<&PyDict>,
+        aliases: Option<HashMap<String, String>>,
+        coerce: Option<&PyDict>,
+    ) -> PyResult<Self> {
+        let names = field_names(source)?;
+        let fields = checked_fields(&names)?;
+        let field = |name: &str, what: &str| {
+            fields.iter().copied().find(|f| *f == name).ok_or_else(|| {
+                PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                    "{} for unknown field {:?}",
+                    what, name
+                ))
+            })
+        };
+
+        let mut default_values = Vec::new();
+        let explicit = match defaults {
+            Some(defaults) => defaults.items().extract::<Vec<(String, PyObject)>>()?,
+            None => Vec::new(),
+        };
+        for (name, value) in model_defaults(source)?.into_iter().chain(explicit) {
+            let name = field(&name, "Default")?;
+            default_values.retain(|(f, _): &(&str, PyObject)| *f != name);
+            default_values.push((name, value));
+        }
+
+        let mut alias_map = HashMap::new();
+        for (name, old) in aliases.unwrap_or_default() {
+            let name = field(&name, "Alias")?;
+            if fields.contains(&old.as_str()) {
+                return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                    "Alias {:?} is also a field of the schema",
+                    old
+                )));
+            }
+            alias_map.insert(old, name);
+        }
+
+        let optional = optional_keys(source)?
+            .iter()
+            .map(|name| field(name, "Optional key"))
+            .collect::<PyResult<Vec<_>>>()?;
+
+        let mut coercions = Vec::new();
+        if let Some(coerce) = coerce {
+            for (name, spec) in coerce {
+                let name = field(name.extract()?, "Coercion")?;
+                coercions.push((name, coerce::parse(name, spec)?));
+            }
+        }
+
+        let id = id.unwrap_or_else(|| xxh3_64(names.join("\0").as_bytes()) as u32);
+        let name = name.or_else(|| {
+            source
+                .getattr("__name__")
+                .and_then(|n| n.extract::<String>())
+                .ok()
+        });
+        Ok(BFastSchema {
+            def: Arc::new(SchemaDef {
+                id,
+                name,
+                fields,
+                defaults: default_values,
+                aliases: alias_map,
+                types: validate::field_types(source)?,
+                coercions,
+                optional,
+            }),
+        })
+    }
+
+    #[getter]
+    fn id(&self) -> u32 {
+        self.def.id
+    }
+
+    #[getter]
+    fn name(&self) -> Option<String> {
+        self.def.name.clone()
+    }
+
+    #[getter]
+    fn fields<'py>(&self, py: Python<'py>) -> &'py PyList {
+        PyList::new(py, &self.def.fields)
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "BFastSchema(id={}, name={:?}, fields={:?})",
+            self.def.id,
+            self.def.name.as_deref().unwrap_or(""),
+            self.def.fields
+        )
+    }
+}
+
+/// Make a schema resolvable when decoding frames that reference its ID.
+#[pyfunction]
+pub fn register_schema(schema: &BFastSchema) -> PyResult<()> {
+    register(&schema.def)
+}
+
+/// Resolve schema IDs missing from the local registry by calling `resolver(id)`,
+/// which returns a BFastSchema, a list of field names or None. Answers are kept
+/// in an LRU cache of `cache_size` schemas; `None` removes the resolver.
+#[pyfunction]
+#[pyo3(signature = (resolver, *, cache_size = 256))]
+pub fn register_schema_resolver(resolver: Option<PyObject>, cache_size: usize) -> PyResult<()> {
+    if cache_size == 0 {
+        return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
+            "cache_size must be at least 1",
+        ));
+    }
+    *RESOLVER.lock().unwrap() = resolver.map(|fetch| Resolver {
+        fetch: Arc::new(fetch),
+        capacity: cache_size,
+        cache: HashMap::new(),
+        order: VecDeque::new(),
+    });
+    Ok(())
+}
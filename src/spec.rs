@@ -0,0 +1,245 @@
+// Machine-readable description of the wire format.
+//
+// `b_fast.FORMAT_VERSION`, `b_fast.TAGS` and `b_fast.spec()` come from the
+// tables below, so implementations in other languages and conformance suites
+// can check themselves against the format this build writes instead of
+// reading the encoder. dump_tokens names values through the same table.
+//
+// Layouts describe the bytes after the tag; integers are little-endian.
+
+use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyDict, PyList};
+
+use crate::columnar::{COL_BOOL, COL_DELTA, COL_DICT, COL_PLAIN, COL_RLE, COL_XOR};
+use crate::compression::{
+    CODEC_BROTLI, CODEC_LZ4, CODEC_LZ4_CHUNKS, CODEC_LZ4_FRAME, CODEC_NONE, CODEC_SNAPPY,
+    CODEC_ZSTD,
+};
+use crate::scan::{TAG_FLOAT32, TAG_INT16, TAG_INT32, TAG_INT8, TAG_NEGATIVE_INT};
+use crate::{
+    FLAG_COLUMNAR, FLAG_COMPRESSED, FLAG_METADATA, FLAG_NULL_BITMAP, FLAG_PACKED_BOOLS,
+    FLAG_SCHEMA, FLAG_SHARED_REFS, FORMAT_VERSION, TAG_BLOB_REF, TAG_BOOL_ARRAY, TAG_COLUMNAR,
+    TAG_CUSTOM, TAG_DATE, TAG_DATETIME, TAG_DATE_DAYS, TAG_DECIMAL, TAG_EXT, TAG_FINGERPRINT,
+    TAG_INT_OBJECT, TAG_NULL_BITMAP, TAG_RANGE, TAG_REF, TAG_SECTIONS, TAG_TENSOR, TAG_TIME,
+    TAG_TIMEDELTA, TAG_TIME_NANOS, TAG_TYPED_ARRAY, TAG_UUID,
+};
+
+/// Header flag bits by the name encoder options and dump_tokens use.
+pub(crate) const FLAGS: [(u8, &str); 7] = [
+    (FLAG_COMPRESSED, "compressed"),
+    (FLAG_SHARED_REFS, "dedup"),
+    (FLAG_SCHEMA, "schema"),
+    (FLAG_NULL_BITMAP, "null_bitmap"),
+    (FLAG_COLUMNAR, "columnar"),
+    (FLAG_PACKED_BOOLS, "pack_bools"),
+    (FLAG_METADATA, "metadata"),
+];
+
+/// `(tag, name, layout)` of every tag but the small ints (0x30-0x3F, 0x38
+/// excepted, and 0xB0-0xBF), which carry their value in the low nibble.
+const TAGS: [(u8, &str, &str); 36] = [
+    (0x10, "null", "nothing (None)"),
+    (0x20, "bool", "nothing (False)"),
+    (0x21, "bool", "nothing (True)"),
+    (0x38, "int64", "i64"),
+    (TAG_INT8, "int8", "i8"),
+    (TAG_INT16, "int16", "i16"),
+    (TAG_INT32, "int32", "i32"),
+    (0x40, "float", "f64"),
+    (TAG_FLOAT32, "float32", "f32"),
+    (0x50, "str", "[length (u32)][UTF-8]"),
+    (0x60, "list", "[count (u32)] then count values"),
+    (
+        TAG_COLUMNAR,
+        "columnar",
+        "[row count (u32)][column count (u16)] then per column [key ID (u32)]\
+         [encoding (u8)][encoded values]",
+    ),
+    (
+        TAG_FINGERPRINT,
+        "fingerprint",
+        "record layout fingerprint (u64); only in front of a root list",
+    ),
+    (0x70, "object", "([key ID (u32)][value])* then 0x7F"),
+    (
+        TAG_NULL_BITMAP,
+        "bitmap_object",
+        "[first key ID (u32)][field count (u16)][presence bitmap] then the \
+         present values, then ([key ID (u32)][value])* then 0x7F",
+    ),
+    (
+        TAG_INT_OBJECT,
+        "int_object",
+        "[count (u32)] then ([key][value])*, keys as int values",
+    ),
+    (
+        TAG_SECTIONS,
+        "sections",
+        "[count (u32)] then ([key ID (u32)][value length (u32)])*, then the \
+         values back to back",
+    ),
+    (0x7F, "end", "nothing; closes an object"),
+    (0x80, "bytes", "[length (u32)][bytes]"),
+    (0x90, "float_array", "[count (u32)] then count f64"),
+    (
+        TAG_TENSOR,
+        "tensor",
+        "[dtype code (u8)][bits (u8)][ndim (u8)][dim (u32)]*ndim[byte length \
+         (u32)][data]",
+    ),
+    (
+        TAG_BOOL_ARRAY,
+        "bool_array",
+        "[count (u32)] then 8 per byte, least significant bit first",
+    ),
+    (
+        TAG_TYPED_ARRAY,
+        "typed_array",
+        "[type code (u8, b/B/h/H/i/I/q/Q/f/d)][byte length (u32)][items]",
+    ),
+    (TAG_RANGE, "range", "start, stop and step as int values"),
+    (
+        TAG_BLOB_REF,
+        "blob_ref",
+        "[token length (u32)][UTF-8 token]",
+    ),
+    (
+        TAG_REF,
+        "ref",
+        "[post-order index of an earlier container (u32)]",
+    ),
+    (
+        TAG_DATETIME,
+        "datetime",
+        "[length (u32)][ISO 8601], or 0xFFFFFFFF then [epoch nanoseconds (i64)]\
+         [UTC offset in seconds (i32), i32::MIN when naive]",
+    ),
+    (TAG_DATE, "date", "[length (u32)][ISO 8601]"),
+    (TAG_TIME, "time", "[length (u32)][ISO 8601]"),
+    (TAG_UUID, "uuid", "[length (u32)][32 hex digits]"),
+    (TAG_DECIMAL, "decimal", "[length (u32)][decimal string]"),
+    (TAG_TIMEDELTA, "timedelta", "nanoseconds (i64)"),
+    (TAG_DATE_DAYS, "date", "days since 1970-01-01 (i32)"),
+    (TAG_TIME_NANOS, "time", "nanoseconds since midnight (u64)"),
+    (
+        TAG_CUSTOM,
+        "custom",
+        "[name length (u32)][module.qualname] then the state value",
+    ),
+    (TAG_EXT, "ext", "[ext ID (u8)][length (u32)][bytes]"),
+];
+
+/// Tags set aside for future extension layouts.
+const RESERVED_TAGS: std::ops::RangeInclusive<u8> = 0xE1..=0xEF;
+
+/// Name and layout of `tag`, or None when no value starts with it.
+pub(crate) fn tag(tag: u8) -> Option<(&'static str, &'static str)> {
+    if let Some(&(_, name, layout)) = TAGS.iter().find(|(t, _, _)| *t == tag) {
+        return Some((name, layout));
+    }
+    match tag & 0xF0 {
+        0x30 => Some(("int", "nothing (the low nibble is the value)")),
+        TAG_NEGATIVE_INT => Some(("int", "nothing (the low nibble minus 16 is the value)")),
+        _ => None,
+    }
+}
+
+/// Every tag byte a value can start with.
+fn tags() -> impl Iterator<Item = (u8, &'static str, &'static str)> {
+    (0..=u8::MAX).filter_map(|t| tag(t).map(|(name, layout)| (t, name, layout)))
+}
+
+/// Tag byte to value type name for every tag of the format, as `b_fast.TAGS`.
+pub(crate) fn tag_names<'py>(py: Python<'py>) -> PyResult<&'py PyDict> {
+    let dict = PyDict::new(py);
+    for (tag, name, _) in tags() {
+        dict.set_item(tag, name)?;
+    }
+    Ok(dict)
+}
+
+/// Machine-readable description of the wire format this build reads and
+/// writes: header fields, flags, value tags with their layouts, column
+/// encodings and compression codecs.
+#[pyfunction]
+pub fn spec(py: Python) -> PyResult<PyObject> {
+    let spec = PyDict::new(py);
+    spec.set_item("version", FORMAT_VERSION)?;
+    spec.set_item("magic", PyBytes::new(py, b"BF"))?;
+    spec.set_item("byte_order", "little")?;
+
+    let header = PyList::empty(py);
+    for (name, layout) in [
+        ("magic", "2 bytes, BF"),
+        ("flags", "u8, see flags"),
+        ("version", "u8"),
+        ("string_count", "u16"),
+        (
+            "length",
+            "u32, bytes after the header; absent in version 1 frames",
+        ),
+        (
+            "inflated_size",
+            "u32, size of the inflated frame; only with the compressed flag, \
+             then the compressed frame",
+        ),
+        ("schema_id", "u32, with the schema flag"),
+        (
+            "metadata",
+            "[length (u32)] then an uncompressed frame holding a dict, with the \
+             metadata flag",
+        ),
+        ("string_table", "string_count times [length (u8)][UTF-8]"),
+        ("payload", "one value; keys are string table indices"),
+    ] {
+        let field = PyDict::new(py);
+        field.set_item("name", name)?;
+        field.set_item("layout", layout)?;
+        header.append(field)?;
+    }
+    spec.set_item("header", header)?;
+
+    let flags = PyDict::new(py);
+    for (bit, name) in FLAGS {
+        flags.set_item(name, bit)?;
+    }
+    spec.set_item("flags", flags)?;
+
+    let tags_dict = PyDict::new(py);
+    for (tag, name, layout) in tags() {
+        let entry = PyDict::new(py);
+        entry.set_item("name", name)?;
+        entry.set_item("layout", layout)?;
+        tags_dict.set_item(tag, entry)?;
+    }
+    spec.set_item("tags", tags_dict)?;
+    spec.set_item("reserved_tags", RESERVED_TAGS.collect::<Vec<_>>())?;
+
+    let encodings = PyDict::new(py);
+    for (id, name) in [
+        (COL_PLAIN, "plain"),
+        (COL_RLE, "rle"),
+        (COL_BOOL, "bool"),
+        (COL_DELTA, "delta"),
+        (COL_XOR, "xor"),
+        (COL_DICT, "dict"),
+    ] {
+        encodings.set_item(id, name)?;
+    }
+    spec.set_item("column_encodings", encodings)?;
+
+    let codecs = PyDict::new(py);
+    for (id, name) in [
+        (CODEC_NONE, "none"),
+        (CODEC_LZ4, "lz4"),
+        (CODEC_ZSTD, "zstd"),
+        (CODEC_SNAPPY, "snappy"),
+        (CODEC_LZ4_CHUNKS, "lz4-chunks"),
+        (CODEC_LZ4_FRAME, "lz4-frame"),
+        (CODEC_BROTLI, "brotli"),
+    ] {
+        codecs.set_item(id, name)?;
+    }
+    spec.set_item("codecs", codecs)?;
+    Ok(spec.into())
+}
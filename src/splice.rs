@@ -0,0 +1,143 @@
+// Byte-level surgery on encoded lists.
+//
+// Frames whose payload is a list can be merged and sliced without decoding:
+// element bytes are copied as-is and only object key IDs are remapped into
+// the string table of the output frame.
+
+use pyo3::prelude::*;
+use pyo3::types::{PyAny, PyBytes};
+
+use crate::scan::{parse_frame, unpack, Frame, ScanResult, StringTable};
+
+fn value_error(message: String) -> PyErr {
+    PyErr::new::<pyo3::exceptions::PyValueError, _>(message)
+}
+
+fn list_frame<'a>(data: &'a [u8], operation: &str) -> ScanResult<Frame<'a>> {
+    let frame = parse_frame(data)?;
+    frame.require_plain(operation)?;
+    if frame.tag(frame.payload)? != 0x60 {
+        return Err(format!(
+            "{} expects payloads encoded from a list",
+            operation
+        ));
+    }
+    Ok(frame)
+}
+
+/// Copy every element of `frame`'s root list into `out`. Returns the count.
+fn append_items(frame: &Frame, table: &mut StringTable, out: &mut Vec<u8>) -> ScanResult<u32> {
+    let ids = frame
+        .strings
+        .iter()
+        .map(|s| table.intern(s))
+        .collect::<ScanResult<Vec<u32>>>()?;
+    let count = frame.u32_at(frame.payload + 1)?;
+    let start = frame.payload + 5;
+
+    // Same key IDs in both tables: the element bytes can be copied verbatim
+    if ids.iter().enumerate().all(|(i, &id)| id as usize == i) {
+        let end = frame.skip(frame.payload)?;
+        out.extend_from_slice(&frame.data[start..end]);
+    } else {
+        let mut cursor = start;
+        for _ in 0..count {
+            cursor = frame.copy_value(cursor, table, out)?;
+        }
+    }
+    Ok(count)
+}
+
+fn concat_frames(blobs: &[&[u8]], compress: Option<bool>) -> ScanResult<Vec<u8>> {
+    let mut table = StringTable::default();
+    let mut items = Vec::new();
+    let mut total: u32 = 0;
+    let mut any_compressed = false;
+
+    for (index, blob) in blobs.iter().enumerate() {
+        let (data, compressed) = unpack(blob).map_err(|e| format!("Frame {}: {}", index, e))?;
+        any_compressed |= compressed;
+        let frame = list_frame(&data, "concat").map_err(|e| format!("Frame {}: {}", index, e))?;
+        let count = append_items(&frame, &mut table, &mut items)?;
+        total = total
+            .checked_add(count)
+            .ok_or("Concatenated list exceeds 2^32 elements")?;
+    }
+
+    let mut payload = Vec::with_capacity(5 + items.len());
+    payload.push(0x60);
+    payload.extend_from_slice(&total.to_le_bytes());
+    payload.extend_from_slice(&items);
+    table.build_frame(&payload, 0, None, compress.unwrap_or(any_compressed))
+}
+
+/// Resolve Python-style `start:stop` bounds against a list of `len` elements.
+pub(crate) fn slice_bounds(
+    start: Option<isize>,
+    stop: Option<isize>,
+    len: usize,
+) -> (usize, usize) {
+    let clamp = |index: isize| {
+        if index < 0 {
+            (len as isize + index).max(0) as usize
+        } else {
+            (index as usize).min(len)
+        }
+    };
+    let start = start.map_or(0, clamp);
+    let stop = stop.map_or(len, clamp);
+    (start, stop.max(start))
+}
+
+fn slice_frame(data: &[u8], start: Option<isize>, stop: Option<isize>) -> ScanResult<Vec<u8>> {
+    let (data, compressed) = unpack(data)?;
+    let frame = list_frame(&data, "slice")?;
+    let count = frame.u32_at(frame.payload + 1)? as usize;
+    let (start, stop) = slice_bounds(start, stop, count);
+
+    let mut cursor = frame.payload + 5;
+    for _ in 0..start {
+        cursor = frame.skip(cursor)?;
+    }
+
+    // A fresh table keeps only the keys the selected elements use
+    let mut table = StringTable::default();
+    let mut payload = Vec::new();
+    payload.push(0x60);
+    payload.extend_from_slice(&((stop - start) as u32).to_le_bytes());
+    for _ in start..stop {
+        cursor = frame.copy_value(cursor, &mut table, &mut payload)?;
+    }
+    table.build_frame(&payload, frame.flags, frame.metadata, compressed)
+}
+
+/// Merge frames whose payloads are lists into one frame holding all elements.
+/// The result is compressed if any input was, unless `compress` says otherwise.
+#[pyfunction]
+#[pyo3(signature = (blobs, *, compress = None))]
+pub fn concat(py: Python, blobs: &PyAny, compress: Option<bool>) -> PyResult<PyObject> {
+    let blobs = blobs
+        .iter()?
+        .map(|blob| blob?.extract::<&[u8]>())
+        .collect::<PyResult<Vec<&[u8]>>>()?;
+    let merged = py
+        .allow_threads(|| concat_frames(&blobs, compress))
+        .map_err(value_error)?;
+    Ok(PyBytes::new(py, &merged).into())
+}
+
+/// New frame holding elements `start:stop` of an encoded list (Python slice
+/// semantics), with a string table pruned to the keys they use.
+#[pyfunction]
+#[pyo3(signature = (data, start = None, stop = None))]
+pub fn slice(
+    py: Python,
+    data: &[u8],
+    start: Option<isize>,
+    stop: Option<isize>,
+) -> PyResult<PyObject> {
+    let sliced = py
+        .allow_threads(|| slice_frame(data, start, stop))
+        .map_err(value_error)?;
+    Ok(PyBytes::new(py, &sliced).into())
+}
@@ -0,0 +1,560 @@
+// Encode-time statistics, readable without decoding the payload.
+//
+// `encode_packed(..., stats=["total"])` counts the records of the value (the
+// items of a root list, or the value itself), counts them by type name and
+// takes the count, min, max and sum of the numeric values of each named
+// field, dict key or attribute. The result is encoded as a frame of its own
+// behind the metadata frame, inside the metadata section:
+//
+//   [section length (u32)][metadata frame][statistics frame]
+//
+// Decoders skip the section as a whole and `b_fast.metadata` reads only the
+// first frame, so frames with statistics decode anywhere. Without `metadata=`
+// the metadata frame holds None. Frames rebuilt by splice and diff drop the
+// statistics, which would no longer match their records.
+//
+// Columnar encoders with `column_stats=True` add a pruning index to the
+// statistics, Parquet-style: under "columns", each record field gets the
+// number of records holding it, how many of those hold None, and the min and
+// max of the rest when they are all numbers (not bools or NaN) or all
+// strings. `may_match`, `aggregate` and `filter` read it to skip frames and
+// columns without looking at their records.
+
+use std::cmp::Ordering;
+
+use pyo3::prelude::*;
+use pyo3::types::{PyBool, PyDict, PyFloat, PyList, PyLong, PyString, PyTuple};
+
+use crate::nonfinite::NonFinite;
+use crate::scan::{float_width, int_width, parse_frame, unpack, Frame, ScanResult};
+use crate::{decode_frame, hints, BFast, DecodeOptions};
+
+/// The metadata section for a frame of `obj`: the `metadata` frame, followed
+/// by the statistics frame when `fields` or `columns` is given.
+pub(crate) fn metadata_section(
+    obj: &PyAny,
+    metadata: Option<&PyDict>,
+    fields: Option<&[String]>,
+    columns: Option<&PyDict>,
+) -> PyResult<Option<Vec<u8>>> {
+    if fields.is_none() && columns.is_none() {
+        return metadata
+            .map(|metadata| BFast::new().encode_to_vec(metadata, false))
+            .transpose();
+    }
+    let py = obj.py();
+    let mut section = match metadata {
+        Some(metadata) => BFast::new().encode_to_vec(metadata, false)?,
+        None => BFast::new().encode_to_vec(py.None().as_ref(py), false)?,
+    };
+    let stats = match fields {
+        Some(fields) => collect(obj, fields)?,
+        None => {
+            let stats = PyDict::new(py);
+            stats.set_item("records", records(obj).len())?;
+            stats
+        }
+    };
+    if let Some(columns) = columns {
+        stats.set_item("columns", columns)?;
+    }
+    section.extend_from_slice(&BFast::new().encode_to_vec(stats, false)?);
+    Ok(Some(section))
+}
+
+/// The records of `obj`: the items of a root list, or the value itself.
+fn records(obj: &PyAny) -> Vec<&PyAny> {
+    if let Ok(list) = obj.downcast::<PyList>() {
+        list.iter().collect()
+    } else if let Ok(tuple) = obj.downcast::<PyTuple>() {
+        tuple.iter().collect()
+    } else {
+        vec![obj]
+    }
+}
+
+/// Count, min, max and sum of one field's numeric values.
+#[derive(Default)]
+struct FieldStats<'py> {
+    count: usize,
+    min: Option<&'py PyAny>,
+    max: Option<&'py PyAny>,
+    sum: Option<&'py PyAny>,
+}
+
+impl<'py> FieldStats<'py> {
+    /// Take `value` of the field `name` into account; `add` is
+    /// `operator.add`.
+    fn add(&mut self, name: &str, value: &'py PyAny, add: &'py PyAny) -> PyResult<()> {
+        self.count += 1;
+        if self.min.map_or(Ok(true), |min| value.lt(min))? {
+            self.min = Some(value);
+        }
+        if self.max.map_or(Ok(true), |max| value.gt(max))? {
+            self.max = Some(value);
+        }
+        self.sum = Some(match self.sum {
+            None => value,
+            Some(sum) => add.call1((sum, value)).map_err(|e| {
+                PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                    "Cannot total field '{}': {}",
+                    name, e
+                ))
+            })?,
+        });
+        Ok(())
+    }
+
+    fn to_dict(&self, py: Python<'py>) -> PyResult<&'py PyDict> {
+        let dict = PyDict::new(py);
+        dict.set_item("count", self.count)?;
+        dict.set_item("min", self.min)?;
+        dict.set_item("max", self.max)?;
+        match self.sum {
+            Some(sum) => dict.set_item("sum", sum)?,
+            None => dict.set_item("sum", 0)?,
+        }
+        Ok(dict)
+    }
+}
+
+/// Ints, floats and Decimals, but not bools or NaN.
+fn is_number(value: &PyAny) -> PyResult<bool> {
+    if value.is_instance_of::<pyo3::types::PyBool>() {
+        return Ok(false);
+    }
+    if value.is_instance_of::<pyo3::types::PyLong>() {
+        return Ok(true);
+    }
+    if let Ok(float) = value.downcast::<pyo3::types::PyFloat>() {
+        return Ok(!float.value().is_nan());
+    }
+    if value.get_type().name()? == "Decimal" {
+        return Ok(!value.call_method0("is_nan")?.is_true()?);
+    }
+    Ok(false)
+}
+
+/// The value of `field` in `record`: a key of dicts, an attribute of objects.
+fn field<'py>(record: &'py PyAny, field: &str) -> PyResult<Option<&'py PyAny>> {
+    if let Ok(dict) = record.downcast::<PyDict>() {
+        return dict.get_item(field);
+    }
+    match record.hasattr(field)? {
+        true => record.getattr(field).map(Some),
+        false => Ok(None),
+    }
+}
+
+/// The statistics dict of `obj`.
+fn collect<'py>(obj: &'py PyAny, fields: &[String]) -> PyResult<&'py PyDict> {
+    let py = obj.py();
+    let records = records(obj);
+
+    let add = py.import("operator")?.getattr("add")?;
+    let types = PyDict::new(py);
+    let mut totals: Vec<FieldStats> = fields.iter().map(|_| FieldStats::default()).collect();
+    for record in &records {
+        let name = record.get_type().name()?;
+        let seen = match types.get_item(name)? {
+            Some(count) => count.extract::<usize>()?,
+            None => 0,
+        };
+        types.set_item(name, seen + 1)?;
+        for (name, total) in fields.iter().zip(&mut totals) {
+            if let Some(value) = field(record, name)? {
+                if is_number(value)? {
+                    total.add(name, value, add)?;
+                }
+            }
+        }
+    }
+
+    let stats = PyDict::new(py);
+    stats.set_item("records", records.len())?;
+    stats.set_item("types", types)?;
+    let by_field = PyDict::new(py);
+    for (name, total) in fields.iter().zip(&totals) {
+        by_field.set_item(name, total.to_dict(py)?)?;
+    }
+    stats.set_item("fields", by_field)?;
+    Ok(stats)
+}
+
+/// Decode the statistics of a frame encoded with `stats=`, or return None;
+/// the payload is not decoded.
+#[pyfunction]
+pub fn stats(py: Python, data: &[u8]) -> PyResult<PyObject> {
+    let (frame_data, _) = unpack(data).map_err(PyErr::new::<pyo3::exceptions::PyValueError, _>)?;
+    let frame =
+        parse_frame(&frame_data).map_err(PyErr::new::<pyo3::exceptions::PyValueError, _>)?;
+    match frame.stats {
+        Some(stats) => decode_frame(py, stats, None, DecodeOptions::default()),
+        None => Ok(py.None()),
+    }
+}
+
+/// A value a column can be bounded by. Numbers compare with each other
+/// exactly, as in Python, and strings with strings.
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum Bound<'a> {
+    Int(i64),
+    Float(f64),
+    Str(&'a str),
+}
+
+impl<'a> Bound<'a> {
+    /// The bound `value` stands for; None for values without one (bools,
+    /// NaN, ints beyond i64, other types).
+    fn of(value: &'a PyAny) -> PyResult<Option<Self>> {
+        if value.is_instance_of::<PyBool>() {
+            return Ok(None);
+        }
+        if value.is_instance_of::<PyLong>() {
+            return Ok(value.extract::<i64>().ok().map(Bound::Int));
+        }
+        if let Ok(float) = value.downcast::<PyFloat>() {
+            let float = float.value();
+            return Ok((!float.is_nan()).then_some(Bound::Float(float)));
+        }
+        if let Ok(text) = value.downcast::<PyString>() {
+            return Ok(Some(Bound::Str(text.to_str()?)));
+        }
+        Ok(None)
+    }
+
+    /// The bound of the value at `offset` of a statistics frame.
+    fn at(frame: &Frame<'a>, offset: usize) -> ScanResult<Option<Self>> {
+        Ok(match frame.tag(offset)? {
+            t if int_width(t).is_some() => Some(Bound::Int(frame.int_at(offset)?)),
+            t if float_width(t).is_some() => Some(Bound::Float(frame.float_at(offset)?)),
+            0x50 => {
+                let length = frame.u32_at(offset + 1)? as usize;
+                let bytes = frame
+                    .data
+                    .get(offset + 5..offset + 5 + length)
+                    .ok_or_else(|| "Unexpected end of buffer during parsing".to_string())?;
+                let text = std::str::from_utf8(bytes)
+                    .map_err(|e| format!("Invalid UTF-8 in string: {}", e))?;
+                Some(Bound::Str(text))
+            }
+            _ => None,
+        })
+    }
+
+    /// How `self` compares with `other`; None when they do not compare.
+    pub(crate) fn compare(self, other: Bound) -> Option<Ordering> {
+        match (self, other) {
+            (Bound::Int(a), Bound::Int(b)) => Some(a.cmp(&b)),
+            (Bound::Float(a), Bound::Float(b)) => a.partial_cmp(&b),
+            (Bound::Int(a), Bound::Float(b)) => int_float(a, b),
+            (Bound::Float(a), Bound::Int(b)) => int_float(b, a).map(Ordering::reverse),
+            (Bound::Str(a), Bound::Str(b)) => Some(a.cmp(b)),
+            _ => None,
+        }
+    }
+
+    fn into_py(self, py: Python) -> PyObject {
+        match self {
+            Bound::Int(i) => i.into_py(py),
+            Bound::Float(f) => f.into_py(py),
+            Bound::Str(s) => s.into_py(py),
+        }
+    }
+}
+
+/// `int` compared with `float` without rounding the int.
+fn int_float(int: i64, float: f64) -> Option<Ordering> {
+    if float.is_nan() {
+        return None;
+    }
+    // 2**63 is exact as a float; floats at or beyond it are out of range
+    if float >= 9_223_372_036_854_775_808.0 {
+        return Some(Ordering::Less);
+    }
+    if float < -9_223_372_036_854_775_808.0 {
+        return Some(Ordering::Greater);
+    }
+    let whole = float.trunc();
+    Some(int.cmp(&(whole as i64)).then_with(|| match float > whole {
+        true => Ordering::Less,
+        false if float < whole => Ordering::Greater,
+        false => Ordering::Equal,
+    }))
+}
+
+/// Record count, null count and bounds of one column.
+struct ColumnStats<'py> {
+    count: usize,
+    nulls: usize,
+    bounds: Option<(Bound<'py>, Bound<'py>)>,
+    // Cleared by a value without a bound, or one that does not compare with
+    // the bounds
+    ordered: bool,
+}
+
+impl<'py> ColumnStats<'py> {
+    fn new() -> Self {
+        ColumnStats {
+            count: 0,
+            nulls: 0,
+            bounds: None,
+            ordered: true,
+        }
+    }
+
+    fn add(&mut self, bound: Bound<'py>) {
+        let Some((min, max)) = self.bounds else {
+            self.bounds = Some((bound, bound));
+            return;
+        };
+        match (bound.compare(min), bound.compare(max)) {
+            (Some(below), Some(above)) => {
+                let min = if below == Ordering::Less { bound } else { min };
+                let max = if above == Ordering::Greater {
+                    bound
+                } else {
+                    max
+                };
+                self.bounds = Some((min, max));
+            }
+            _ => self.ordered = false,
+        }
+    }
+
+    fn to_dict(&self, py: Python<'py>) -> PyResult<&'py PyDict> {
+        let dict = PyDict::new(py);
+        dict.set_item("count", self.count)?;
+        dict.set_item("nulls", self.nulls)?;
+        let bounds = self.bounds.filter(|_| self.ordered);
+        dict.set_item("min", bounds.map(|(min, _)| min.into_py(py)))?;
+        dict.set_item("max", bounds.map(|(_, max)| max.into_py(py)))?;
+        Ok(dict)
+    }
+}
+
+impl BFast {
+    /// The "columns" statistics of the records of `obj`, by field name in the
+    /// order the fields are first seen.
+    pub(crate) fn column_stats<'py>(&self, obj: &'py PyAny) -> PyResult<&'py PyDict> {
+        let py = obj.py();
+        let mut columns: Vec<(&str, ColumnStats)> = Vec::new();
+        for record in records(obj) {
+            let Some(fields) = self.record_dict(record)? else {
+                continue;
+            };
+            // Floats the writer narrows to f32 are bounded by both values
+            let float32 = self.float32 || !hints::of(record.get_type())?.is_empty();
+            for (key, value) in fields {
+                let Ok(key) = key.downcast::<PyString>() else {
+                    continue;
+                };
+                let key = key.to_str()?;
+                let column = match columns.iter().position(|(name, _)| *name == key) {
+                    Some(index) => &mut columns[index].1,
+                    None => {
+                        columns.push((key, ColumnStats::new()));
+                        &mut columns.last_mut().unwrap().1
+                    }
+                };
+                column.count += 1;
+                if let Ok(float) = value.downcast::<PyFloat>() {
+                    let float = float.value();
+                    if !float.is_finite() && self.non_finite == NonFinite::Null {
+                        column.nulls += 1;
+                        continue;
+                    }
+                    let narrow = float as f32;
+                    if float32 && narrow.is_finite() {
+                        column.add(Bound::Float(narrow as f64));
+                    }
+                }
+                if value.is_none() {
+                    column.nulls += 1;
+                    continue;
+                }
+                match Bound::of(value)? {
+                    Some(bound) => column.add(bound),
+                    None => column.ordered = false,
+                }
+            }
+        }
+        let dict = PyDict::new(py);
+        for (name, column) in &columns {
+            dict.set_item(name, column.to_dict(py)?)?;
+        }
+        Ok(dict)
+    }
+}
+
+/// A comparison of `filter` and `may_match`.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Op {
+    pub(crate) fn parse(op: &str) -> PyResult<Op> {
+        Ok(match op {
+            "==" => Op::Eq,
+            "!=" => Op::Ne,
+            "<" => Op::Lt,
+            "<=" => Op::Le,
+            ">" => Op::Gt,
+            ">=" => Op::Ge,
+            _ => {
+                return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                    "Unknown filter op {:?}; expected ==, !=, <, <=, > or >=",
+                    op
+                )))
+            }
+        })
+    }
+
+    /// Whether a value ordered `ordering` against the operand matches.
+    pub(crate) fn holds(self, ordering: Ordering) -> bool {
+        match self {
+            Op::Eq => ordering == Ordering::Equal,
+            Op::Ne => ordering != Ordering::Equal,
+            Op::Lt => ordering == Ordering::Less,
+            Op::Le => ordering != Ordering::Greater,
+            Op::Gt => ordering == Ordering::Greater,
+            Op::Ge => ordering != Ordering::Less,
+        }
+    }
+}
+
+/// The operand of a predicate.
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum Operand<'a> {
+    Null,
+    Bound(Bound<'a>),
+    // NaN and other values the index does not order
+    Other,
+}
+
+impl<'a> Operand<'a> {
+    pub(crate) fn of(value: &'a PyAny) -> PyResult<Self> {
+        if value.is_none() {
+            return Ok(Operand::Null);
+        }
+        // As in Python, bools compare as 0 and 1
+        if let Ok(flag) = value.downcast::<PyBool>() {
+            return Ok(Operand::Bound(Bound::Int(flag.is_true() as i64)));
+        }
+        Ok(Bound::of(value)?.map_or(Operand::Other, Operand::Bound))
+    }
+}
+
+/// One column of the pruning index of a frame.
+pub(crate) struct Column<'a> {
+    pub count: u64,
+    pub nulls: u64,
+    pub bounds: Option<(Bound<'a>, Bound<'a>)>,
+}
+
+impl Column<'_> {
+    /// Whether a record may hold the column's field `op` `operand`: with a
+    /// None operand, `==` and `!=` test for None and orderings never match;
+    /// otherwise None and values of another kind only match `!=`.
+    pub(crate) fn may_match(&self, op: Op, operand: Operand) -> bool {
+        let values = self.count - self.nulls;
+        match operand {
+            _ if self.count == 0 => false,
+            Operand::Null => match op {
+                Op::Eq => self.nulls > 0,
+                Op::Ne => values > 0,
+                _ => false,
+            },
+            Operand::Other => true,
+            Operand::Bound(operand) => {
+                let Some((min, max)) = self.bounds else {
+                    return match op {
+                        Op::Ne => true,
+                        _ => values > 0,
+                    };
+                };
+                // The bounds cover every value, so an operand of another
+                // kind matches none of them
+                let (Some(from_min), Some(from_max)) = (operand.compare(min), operand.compare(max))
+                else {
+                    return op == Op::Ne;
+                };
+                match op {
+                    Op::Eq => from_min != Ordering::Less && from_max != Ordering::Greater,
+                    Op::Ne => {
+                        self.nulls > 0 || from_min != Ordering::Equal || from_max != Ordering::Equal
+                    }
+                    Op::Lt => from_min == Ordering::Greater,
+                    Op::Le => from_min != Ordering::Less,
+                    Op::Gt => from_max == Ordering::Less,
+                    Op::Ge => from_max != Ordering::Greater,
+                }
+            }
+        }
+    }
+}
+
+/// The pruning index entry of `field` in the statistics frame `stats`.
+/// `Ok(None)` when the frame has no index; a field no record holds gets an
+/// empty column.
+pub(crate) fn column<'a>(stats: Option<&'a [u8]>, field: &str) -> ScanResult<Option<Column<'a>>> {
+    let Some(stats) = stats else {
+        return Ok(None);
+    };
+    let frame = parse_frame(stats)?;
+    if frame.tag(frame.payload)? != 0x70 {
+        return Ok(None);
+    }
+    let Some(&(_, _, columns)) = frame
+        .object_entries(frame.payload)?
+        .iter()
+        .find(|(key, _, _)| *key == "columns")
+    else {
+        return Ok(None);
+    };
+    let mut column = Column {
+        count: 0,
+        nulls: 0,
+        bounds: None,
+    };
+    let Some(&(_, _, offset)) = frame
+        .object_entries(columns)?
+        .iter()
+        .find(|(key, _, _)| *key == field)
+    else {
+        return Ok(Some(column));
+    };
+    let (mut min, mut max) = (None, None);
+    for (key, _, value) in frame.object_entries(offset)? {
+        match key {
+            "count" => column.count = frame.int_at(value)? as u64,
+            "nulls" => column.nulls = frame.int_at(value)? as u64,
+            "min" => min = Bound::at(&frame, value)?,
+            "max" => max = Bound::at(&frame, value)?,
+            _ => {}
+        }
+    }
+    column.bounds = min.zip(max);
+    Ok(Some(column))
+}
+
+/// Whether records of a frame encoded with `column_stats=True` may match
+/// `field op value`, from its pruning index alone; True for frames without
+/// one.
+#[pyfunction]
+pub fn may_match(data: &[u8], field: &str, op: &str, value: &PyAny) -> PyResult<bool> {
+    let op = Op::parse(op)?;
+    let operand = Operand::of(value)?;
+    let (frame_data, _) = unpack(data).map_err(PyErr::new::<pyo3::exceptions::PyValueError, _>)?;
+    let frame =
+        parse_frame(&frame_data).map_err(PyErr::new::<pyo3::exceptions::PyValueError, _>)?;
+    let column =
+        column(frame.stats, field).map_err(PyErr::new::<pyo3::exceptions::PyValueError, _>)?;
+    Ok(column.is_none_or(|column| column.may_match(op, operand)))
+}
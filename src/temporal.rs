@@ -0,0 +1,97 @@
+// Calendar arithmetic for the numeric temporal encodings.
+//
+// Dates are written as days since the Unix epoch (TAG_DATE_DAYS) and naive
+// times as nanoseconds since midnight (TAG_TIME_NANOS). Datetimes written
+// with `datetime_nanos` carry their instant as i64 nanoseconds since the
+// epoch plus the UTC offset, instead of an ISO string; decoding renders them
+// back to ISO 8601 so that the regular string path (pandas for
+// sub-microsecond values, allowed_classes fallbacks) applies unchanged.
+
+/// `TAG_DATETIME` length value marking the numeric form:
+/// `[epoch nanoseconds (i64)][UTC offset in seconds (i32)]`.
+pub(crate) const DATETIME_NANOS: u32 = u32::MAX;
+
+/// Offset stored for naive datetimes.
+pub(crate) const NAIVE: i32 = i32::MIN;
+
+/// Byte length of a numeric datetime after its length marker.
+pub(crate) const DATETIME_NANOS_LEN: usize = 12;
+
+const NANOS_PER_DAY: i64 = 86_400_000_000_000;
+
+/// Days from 1970-01-01 to a proleptic Gregorian date.
+pub(crate) fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = month as i64;
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Proleptic Gregorian `(year, month, day)` of a day count from 1970-01-01.
+pub(crate) fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+/// ISO 8601 text of a day count, as `date.isoformat()` writes it.
+pub(crate) fn date_iso(days: i64) -> String {
+    let (year, month, day) = civil_from_days(days);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// ISO 8601 text of nanoseconds since midnight, as `time.isoformat()` writes
+/// it (with nine fraction digits when there are nanoseconds).
+pub(crate) fn time_iso(nanos: u64) -> String {
+    let seconds = nanos / 1_000_000_000;
+    let mut iso = format!(
+        "{:02}:{:02}:{:02}",
+        seconds / 3_600,
+        seconds / 60 % 60,
+        seconds % 60
+    );
+    let fraction = nanos % 1_000_000_000;
+    if !fraction.is_multiple_of(1_000) {
+        iso.push_str(&format!(".{:09}", fraction));
+    } else if fraction != 0 {
+        iso.push_str(&format!(".{:06}", fraction / 1_000));
+    }
+    iso
+}
+
+/// ISO 8601 text of a numeric datetime, as `datetime.isoformat()` writes it.
+pub(crate) fn iso_from_nanos(nanos: i64, offset: i32) -> String {
+    let wall = if offset == NAIVE {
+        nanos as i128
+    } else {
+        nanos as i128 + offset as i128 * 1_000_000_000
+    };
+    let days = wall.div_euclid(NANOS_PER_DAY as i128) as i64;
+    let of_day = wall.rem_euclid(NANOS_PER_DAY as i128) as u64;
+    let mut iso = format!("{}T{}", date_iso(days), time_iso(of_day));
+    if offset != NAIVE {
+        let sign = if offset < 0 { '-' } else { '+' };
+        let offset = offset.unsigned_abs();
+        iso.push_str(&format!(
+            "{}{:02}:{:02}",
+            sign,
+            offset / 3_600,
+            offset / 60 % 60
+        ));
+        if !offset.is_multiple_of(60) {
+            iso.push_str(&format!(":{:02}", offset % 60));
+        }
+    }
+    iso
+}
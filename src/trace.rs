@@ -0,0 +1,70 @@
+// Timing hooks for the encode pipeline.
+//
+// Each stage (payload serialization, string table, compression, PyBytes
+// construction) runs inside a `Span`. With a hook installed through
+// `set_trace_hook`, finishing a span calls `hook(stage, duration_ns, nbytes)`;
+// with the `tracing` feature, spans are also emitted to the `tracing` crate.
+// Encodes with `release_gil=True` report everything done without the GIL as
+// one "released" stage.
+// With neither, a span costs one atomic load.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use pyo3::prelude::*;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static HOOK: Mutex<Option<Arc<PyObject>>> = Mutex::new(None);
+
+pub(crate) struct Span {
+    stage: &'static str,
+    start: Option<Instant>,
+    #[cfg(feature = "tracing")]
+    span: tracing::span::EnteredSpan,
+}
+
+/// Start timing `stage`.
+pub(crate) fn span(stage: &'static str) -> Span {
+    Span {
+        stage,
+        start: ENABLED.load(Ordering::Relaxed).then(Instant::now),
+        #[cfg(feature = "tracing")]
+        span: tracing::debug_span!("b_fast", stage, bytes = tracing::field::Empty).entered(),
+    }
+}
+
+impl Span {
+    /// End the stage, reporting the size of what it produced.
+    pub(crate) fn finish(self, py: Python, bytes: usize) -> PyResult<()> {
+        #[cfg(feature = "tracing")]
+        self.span.record("bytes", bytes);
+        let Some(start) = self.start else {
+            return Ok(());
+        };
+        let elapsed = start.elapsed().as_nanos() as u64;
+        // Called outside the lock, so the hook may replace itself
+        let hook = HOOK.lock().unwrap().clone();
+        if let Some(hook) = hook {
+            hook.call1(py, (self.stage, elapsed, bytes))?;
+        }
+        Ok(())
+    }
+}
+
+/// Install `hook(stage, duration_ns, nbytes)`, called after every encode
+/// stage, or remove it with `None`.
+#[pyfunction]
+pub fn set_trace_hook(hook: Option<&PyAny>) -> PyResult<()> {
+    if let Some(hook) = hook {
+        if !hook.is_callable() {
+            return Err(PyErr::new::<pyo3::exceptions::PyTypeError, _>(
+                "Trace hook must be callable",
+            ));
+        }
+    }
+    let hook = hook.map(|hook| Arc::new(hook.into()));
+    ENABLED.store(hook.is_some(), Ordering::Relaxed);
+    *HOOK.lock().unwrap() = hook;
+    Ok(())
+}
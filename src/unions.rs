@@ -0,0 +1,155 @@
+// Root lists of tagged unions: records of several model classes told apart by
+// a discriminator field.
+//
+// A list mixing `Cat` and `Dog` records, where each class declares the same
+// field (`kind`) as a `Literal` of one value of its own, is written like a
+// batch per class: the field layout of each class is worked out once, and
+// each record is a custom object (0xD9) naming its class in front of its
+// fields. That class name is the record's selector. Decoders without the
+// classes in `allowed_classes` read the records as dicts, discriminator
+// included, as before; with them, each record comes back as its own class.
+//
+// Only root lists take this path, under the same conditions as the batch
+// path; lists of classes without a common discriminator are written as
+// individual objects.
+
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList, PySet, PyType};
+
+use crate::{hints, BFast, TAG_CUSTOM};
+
+/// The field telling the classes apart: declared by every class as a
+/// `Literal` of a single value, no two classes sharing it. The first such
+/// field of the first class wins.
+pub(crate) fn discriminator(classes: &[&PyType]) -> PyResult<Option<String>> {
+    let Some(first) = classes.first() else {
+        return Ok(None);
+    };
+    let hints = classes
+        .iter()
+        .map(|class| literal_fields(class))
+        .collect::<PyResult<Vec<_>>>()?;
+    'fields: for (name, _) in &hints[0] {
+        let values = PySet::empty(first.py())?;
+        for fields in &hints {
+            match fields.iter().find(|(field, _)| field == name) {
+                Some((_, value)) => values.add(value)?,
+                None => continue 'fields,
+            }
+        }
+        if values.len() == classes.len() {
+            return Ok(Some(name.clone()));
+        }
+    }
+    Ok(None)
+}
+
+/// The fields of `class` annotated with a `Literal` of one value, and that
+/// value.
+fn literal_fields(class: &PyType) -> PyResult<Vec<(String, &PyAny)>> {
+    let typing = class.py().import("typing")?;
+    let Ok(hints) = typing.call_method1("get_type_hints", (class,)) else {
+        return Ok(Vec::new());
+    };
+    let literal = typing.getattr("Literal")?;
+    let mut fields = Vec::new();
+    for (name, hint) in hints.downcast::<PyDict>()? {
+        if !typing.call_method1("get_origin", (hint,))?.is(literal) {
+            continue;
+        }
+        let args = typing.call_method1("get_args", (hint,))?;
+        // Unhashable values cannot tell classes apart
+        if args.len()? == 1 && args.get_item(0)?.hash().is_ok() {
+            fields.push((name.extract()?, args.get_item(0)?));
+        }
+    }
+    Ok(fields)
+}
+
+/// Records of one class of a union and the way they are written.
+struct Variant<'py> {
+    class: &'py PyType,
+    name: String,
+    field_names: Vec<String>,
+    field_ids: Vec<u32>,
+    writers: Vec<crate::ValueWriter>,
+    hints: Vec<hints::FieldHints>,
+}
+
+impl BFast {
+    /// Write `list` as a tagged union; fails, leaving the buffer for the
+    /// caller to rewind, when its records are not one.
+    pub(crate) fn serialize_union_batch(&mut self, list: &PyList) -> PyResult<()> {
+        let not_a_union = || {
+            PyErr::new::<pyo3::exceptions::PyValueError, _>("Not a list of tagged union records")
+        };
+        let mut groups: Vec<(&PyType, Vec<&PyAny>)> = Vec::new();
+        for item in list.iter() {
+            let class = item.get_type();
+            match groups.iter().position(|(c, _)| c.is(class)) {
+                Some(index) => groups[index].1.push(item),
+                None if item.hasattr("__dict__")? => groups.push((class, vec![item])),
+                None => return Err(not_a_union()),
+            }
+        }
+        let classes: Vec<&PyType> = groups.iter().map(|(class, _)| *class).collect();
+        if classes.len() < 2 || discriminator(&classes)?.is_none() {
+            return Err(not_a_union());
+        }
+
+        let mut variants = Vec::with_capacity(groups.len());
+        for (class, items) in groups {
+            let field_names: Vec<String> = self
+                .model_dict(items[0])?
+                .keys()
+                .iter()
+                .map(|k| k.to_string())
+                .collect();
+            let field_ids = field_names
+                .iter()
+                .map(|name| self.get_or_create_string_id_fast(name))
+                .collect();
+            let writers = self.field_writers(PyList::new(list.py(), items), &field_names)?;
+            let hints = hints::for_fields(class, &field_names)?;
+            let name = format!(
+                "{}.{}",
+                class.getattr("__module__")?,
+                class.getattr("__qualname__")?
+            );
+            variants.push(Variant {
+                class,
+                name,
+                field_names,
+                field_ids,
+                writers,
+                hints,
+            });
+        }
+
+        self.check_recursion_depth()?;
+        self.ensure_buffer_capacity(5 + list.len() * 60);
+        self.work_buffer.push(0x60);
+        self.work_buffer
+            .extend_from_slice(&(list.len() as u32).to_le_bytes());
+        for item in list.iter() {
+            self.check_buffer_limit(0)?;
+            let variant = variants
+                .iter()
+                .find(|variant| variant.class.is(item.get_type()))
+                .ok_or_else(not_a_union)?;
+            self.work_buffer.push(TAG_CUSTOM);
+            self.work_buffer
+                .extend_from_slice(&(variant.name.len() as u32).to_le_bytes());
+            self.work_buffer.extend_from_slice(variant.name.as_bytes());
+            self.serialize_batch_record(
+                item,
+                &variant.field_names,
+                &variant.field_ids,
+                &variant.writers,
+                &variant.hints,
+            )?;
+        }
+        self.decrease_recursion_depth();
+        Ok(())
+    }
+}
@@ -0,0 +1,237 @@
+// Checking records against the encoder's schema before they are written.
+//
+// `BFast(schema=..., validate=True)` refuses a payload whose records do not
+// match the schema: a field the schema does not list, a field it lists that
+// is missing and has no default (nor is an optional TypedDict key), or a
+// value whose type differs from the field's annotation on the model the
+// schema was built from. Records are the root value or the items of a root
+// list, as dicts or objects. Only annotations that name classes are checked
+// (`int`, `Optional[str]`, `list[int]` as `list`, ...); others, and schemas
+// built from field names, accept any value. Floats accept ints, and fields
+// typed as a model, dataclass or TypedDict accept dicts, which encode the
+// same way.
+
+use std::collections::HashMap;
+
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList, PyTuple, PyType};
+
+use crate::schema::SchemaDef;
+use crate::BFast;
+
+/// The classes a field's values may be instances of.
+pub(crate) struct FieldType {
+    classes: Py<PyTuple>,
+    /// The annotation as written, for error messages.
+    name: String,
+}
+
+/// The checkable field types of the model class `source`, from its type
+/// hints; empty when it has none.
+pub(crate) fn field_types(source: &PyAny) -> PyResult<HashMap<String, FieldType>> {
+    let py = source.py();
+    let mut types = HashMap::new();
+    if !source.is_instance_of::<PyType>() {
+        return Ok(types);
+    }
+    let typing = py.import("typing")?;
+    // Forward references that do not resolve leave the fields unchecked
+    let Ok(hints) = typing.call_method1("get_type_hints", (source,)) else {
+        return Ok(types);
+    };
+    for (name, hint) in hints.downcast::<PyDict>()? {
+        let mut classes = Vec::new();
+        if collect_classes(hint, &mut classes)? {
+            if classes
+                .iter()
+                .any(|class| class.is(py.get_type::<pyo3::types::PyFloat>()))
+            {
+                classes.push(py.get_type::<pyo3::types::PyLong>());
+            }
+            if classes.iter().any(|class| declares_fields(class)) {
+                classes.push(py.get_type::<PyDict>());
+            }
+            let name_of = match hint.is_instance_of::<PyType>() {
+                true => hint.getattr("__name__")?,
+                false => hint.str()?.as_ref(),
+            };
+            types.insert(
+                name.extract()?,
+                FieldType {
+                    classes: PyTuple::new(py, classes).into(),
+                    name: name_of.extract()?,
+                },
+            );
+        }
+    }
+    Ok(types)
+}
+
+/// Add the classes `hint` names to `classes`; false when it names something
+/// else (Any, Literal, a TypeVar, ...).
+fn collect_classes<'py>(hint: &'py PyAny, classes: &mut Vec<&'py PyType>) -> PyResult<bool> {
+    let py = hint.py();
+    let typing = py.import("typing")?;
+    let origin = typing.call_method1("get_origin", (hint,))?;
+    if origin.is_none() {
+        if hint.is(typing.getattr("Any")?) {
+            return Ok(false);
+        }
+        // TypedDicts refuse isinstance checks; their values are dicts
+        if crate::schema::is_typed_dict(hint)? {
+            classes.push(py.get_type::<PyDict>());
+            return Ok(true);
+        }
+        return match hint.downcast::<PyType>() {
+            Ok(class) if !class.is(py.get_type::<pyo3::types::PyAny>()) => {
+                classes.push(class);
+                Ok(true)
+            }
+            _ => Ok(false),
+        };
+    }
+    // Optional[X], Union[X, Y] and X | Y
+    let union_type = py.import("types")?.getattr("UnionType").ok();
+    if origin.is(typing.getattr("Union")?) || union_type.is_some_and(|t| origin.is(t)) {
+        for arg in typing.call_method1("get_args", (hint,))?.iter()? {
+            if !collect_classes(arg?, classes)? {
+                return Ok(false);
+            }
+        }
+        return Ok(true);
+    }
+    // list[int], Dict[str, int], ...: only the container is checked
+    match origin.downcast::<PyType>() {
+        Ok(class) => {
+            classes.push(class);
+            Ok(true)
+        }
+        Err(_) => Ok(false),
+    }
+}
+
+/// Whether `class` is a Pydantic model or a dataclass.
+pub(crate) fn declares_fields(class: &PyType) -> bool {
+    ["model_fields", "__fields__", "__dataclass_fields__"]
+        .iter()
+        .any(|attr| class.hasattr(*attr).unwrap_or(false))
+}
+
+impl BFast {
+    /// Fail when the records of `obj` do not match the encoder's schema;
+    /// does nothing unless the encoder validates.
+    pub(crate) fn validate_root(&self, obj: &PyAny) -> PyResult<()> {
+        let (true, Some(def)) = (self.validate, &self.schema) else {
+            return Ok(());
+        };
+        if let Ok(list) = obj.downcast::<PyList>() {
+            for (index, record) in list.iter().enumerate() {
+                self.check_record(record, def, Some(index))?;
+            }
+            return Ok(());
+        }
+        if let Ok(tuple) = obj.downcast::<PyTuple>() {
+            for (index, record) in tuple.iter().enumerate() {
+                self.check_record(record, def, Some(index))?;
+            }
+            return Ok(());
+        }
+        self.check_record(obj, def, None)
+    }
+
+    /// Like `validate_root` for one record added to a batch.
+    pub(crate) fn validate_added(&self, record: &PyAny) -> PyResult<()> {
+        match (self.validate, &self.schema) {
+            (true, Some(def)) => self.check_record(record, def, None),
+            _ => Ok(()),
+        }
+    }
+
+    /// Fail when `record`, item `index` of the root list if given, does not
+    /// match `def`.
+    fn check_record(&self, record: &PyAny, def: &SchemaDef, index: Option<usize>) -> PyResult<()> {
+        let Some(problem) = self.mismatch(record, def)? else {
+            return Ok(());
+        };
+        let record = match index {
+            Some(index) => format!("Record {}", index),
+            None => "Record".to_string(),
+        };
+        let schema = match &def.name {
+            Some(name) => format!("schema {}", name),
+            None => format!("schema ID {}", def.id),
+        };
+        Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+            "{} does not match {}: {}",
+            record, schema, problem
+        )))
+    }
+
+    /// What is wrong with `record`, or None when it matches `def`.
+    fn mismatch(&self, record: &PyAny, def: &SchemaDef) -> PyResult<Option<String>> {
+        let Some(fields) = self.record_fields(record)? else {
+            return Ok(Some(format!(
+                "expected a dict or an object, not {}",
+                type_name(record)
+            )));
+        };
+        let py = record.py();
+        for (key, value) in fields.iter() {
+            let key = key.str()?.to_string_lossy();
+            if !def.fields.contains(&&*key) {
+                return Ok(Some(format!("unknown field '{}'", key)));
+            }
+            let Some(expected) = def.types.get(&*key) else {
+                continue;
+            };
+            if !value.is_instance(expected.classes.as_ref(py))? {
+                return Ok(Some(format!(
+                    "field '{}' expects {}, not {}",
+                    key,
+                    expected.name,
+                    type_name(value)
+                )));
+            }
+        }
+        for field in &def.fields {
+            let may_be_missing =
+                def.defaults.iter().any(|(name, _)| name == field) || def.optional.contains(field);
+            if !fields.contains(*field)? && !may_be_missing {
+                return Ok(Some(format!("missing field '{}'", field)));
+            }
+        }
+        Ok(None)
+    }
+
+    /// The fields `record` is written with, or None when it is not a record.
+    pub(crate) fn record_fields<'py>(&self, record: &'py PyAny) -> PyResult<Option<&'py PyDict>> {
+        if let Ok(dict) = record.downcast::<PyDict>() {
+            return Ok(Some(dict));
+        }
+        if record.is_instance_of::<PyType>() {
+            return Ok(None);
+        }
+        if record.hasattr("__dict__")? {
+            return self.model_dict(record).map(Some);
+        }
+        // Dataclasses with __slots__
+        if let Ok(declared) = record.get_type().getattr("__dataclass_fields__") {
+            let fields = PyDict::new(record.py());
+            for name in declared.downcast::<PyDict>()?.keys() {
+                fields.set_item(
+                    name,
+                    record.getattr(name.downcast::<pyo3::types::PyString>()?)?,
+                )?;
+            }
+            return Ok(Some(fields));
+        }
+        Ok(None)
+    }
+}
+
+fn type_name(value: &PyAny) -> String {
+    value
+        .get_type()
+        .name()
+        .map_or_else(|_| "?".to_string(), str::to_string)
+}
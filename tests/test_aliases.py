@@ -0,0 +1,75 @@
+"""Tests for decode-time key aliases"""
+
+from dataclasses import dataclass
+
+import pytest
+
+import b_fast
+
+ALIASES = {"cid": "customer_id", "amt": "amount"}
+OLD_ORDERS = [{"id": i, "cid": i % 7, "amt": i * 2.5} for i in range(20)]
+NEW_ORDERS = [{"id": i, "customer_id": i % 7, "amount": i * 2.5} for i in range(20)]
+
+
+@dataclass
+class OldOrder:
+    id: int
+    cid: int
+
+
+@pytest.mark.parametrize("compress", [False, True])
+def test_aliases_rename_keys(compress):
+    bf = b_fast.BFast()
+    encoded = bf.encode_packed(OLD_ORDERS, compress=compress)
+
+    assert bf.decode_packed(encoded, aliases=ALIASES) == NEW_ORDERS
+
+
+def test_aliases_rename_record_fields():
+    bf = b_fast.BFast()
+    encoded = bf.encode_packed([OldOrder(1, 3), OldOrder(2, 4)], compress=False)
+
+    decoded = bf.decode_packed(encoded, aliases={"cid": "customer_id"})
+
+    assert decoded == [{"id": 1, "customer_id": 3}, {"id": 2, "customer_id": 4}]
+
+
+def test_aliases_rename_nested_keys_only():
+    bf = b_fast.BFast()
+    data = {"order": {"cid": 1}, "note": "cid"}
+    encoded = bf.encode_packed(data, compress=False)
+
+    decoded = bf.decode_packed(encoded, aliases={"cid": "customer_id"})
+
+    assert decoded == {"order": {"customer_id": 1}, "note": "cid"}
+
+
+def test_aliases_lazy():
+    bf = b_fast.BFast()
+    encoded = bf.encode_packed(OLD_ORDERS, compress=False)
+
+    decoded = bf.decode_packed(encoded, lazy=True, aliases=ALIASES)
+
+    assert decoded[3] == NEW_ORDERS[3]
+    assert list(decoded) == NEW_ORDERS
+
+
+def test_aliases_do_not_leak_into_later_decodes():
+    bf = b_fast.BFast()
+    encoded = bf.encode_packed(OLD_ORDERS, compress=False)
+
+    assert bf.decode_packed(encoded, aliases=ALIASES) == NEW_ORDERS
+    assert bf.decode_packed(encoded) == OLD_ORDERS
+
+
+def test_alias_onto_existing_key_follows_duplicate_policy():
+    bf = b_fast.BFast()
+    encoded = bf.encode_packed({"cid": 1, "customer_id": 2}, compress=False)
+
+    assert bf.decode_packed(encoded, aliases={"cid": "customer_id"}) == {
+        "customer_id": 2
+    }
+    with pytest.raises(ValueError, match="Duplicate key"):
+        bf.decode_packed(
+            encoded, aliases={"cid": "customer_id"}, duplicate_keys="error"
+        )
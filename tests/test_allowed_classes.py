@@ -0,0 +1,77 @@
+"""Tests for decode_packed(allowed_classes=...)"""
+
+import datetime
+import uuid
+from decimal import Decimal
+
+import pytest
+
+import b_fast
+
+RECORD = {
+    "at": datetime.datetime(2024, 5, 1, 12, 30),
+    "day": datetime.date(2024, 5, 1),
+    "time": datetime.time(8, 15),
+    "id": uuid.UUID("12345678-1234-5678-1234-567812345678"),
+    "price": Decimal("9.99"),
+    "name": "order",
+}
+
+
+def decode(data, **kwargs):
+    encoded = b_fast.BFast().encode_packed(data, compress=False)
+    return b_fast.BFast().decode_packed(encoded, **kwargs)
+
+
+def test_default_allows_every_class():
+    assert decode(RECORD) == RECORD
+
+
+def test_empty_allowlist_decodes_plain_data():
+    decoded = decode(RECORD, allowed_classes=[])
+
+    assert decoded == {
+        "at": "2024-05-01T12:30:00",
+        "day": "2024-05-01",
+        "time": "08:15:00",
+        "id": "12345678-1234-5678-1234-567812345678",
+        "price": "9.99",
+        "name": "order",
+    }
+
+
+def test_listed_classes_are_instantiated():
+    decoded = decode(RECORD, allowed_classes=[datetime.date, Decimal])
+
+    assert decoded["day"] == RECORD["day"]
+    assert decoded["price"] == RECORD["price"]
+    assert decoded["at"] == "2024-05-01T12:30:00"
+    assert decoded["id"] == str(RECORD["id"])
+
+
+def test_subclasses_are_not_implied():
+    # datetime is a subclass of date, but must be listed on its own
+    decoded = decode(RECORD, allowed_classes=(datetime.date,))
+
+    assert isinstance(decoded["at"], str)
+
+
+def test_allowlist_applies_to_nested_values():
+    decoded = decode([[RECORD]], allowed_classes=set())
+
+    assert decoded[0][0]["price"] == "9.99"
+
+
+def test_non_class_entries_raise():
+    with pytest.raises(TypeError, match="must contain classes"):
+        decode(RECORD, allowed_classes=["datetime"])
+
+
+def test_tensors_without_numpy_class():
+    numpy = pytest.importorskip("numpy")
+    array = numpy.arange(6, dtype=numpy.int32).reshape(2, 3)
+
+    decoded = decode(array, allowed_classes=[])
+
+    assert decoded == {"dtype": "<i4", "shape": [2, 3], "data": array.tobytes()}
+    assert isinstance(decode(array, allowed_classes=[numpy.ndarray]), numpy.ndarray)
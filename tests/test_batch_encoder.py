@@ -0,0 +1,69 @@
+"""Tests for BFastBatchEncoder"""
+
+import pytest
+
+import b_fast
+
+RECORDS = [{"id": i, "name": f"User {i}", "active": i % 2 == 0} for i in range(500)]
+
+
+class Loop:
+    def __bfast__(self):
+        return self
+
+
+def test_added_records_decode_as_a_list():
+    batch = b_fast.BFastBatchEncoder()
+    for record in RECORDS:
+        batch.add(record)
+
+    assert len(batch) == 500
+    assert b_fast.BFast().decode_packed(batch.finish()) == RECORDS
+
+
+def test_same_result_as_encode_packed():
+    batch = b_fast.BFastBatchEncoder(compress=True)
+    batch.extend(iter(RECORDS))
+
+    encoded = batch.finish()
+    packed = b_fast.BFast().encode_packed(RECORDS, compress=True)
+
+    decoder = b_fast.BFast()
+    assert decoder.decode_packed(encoded) == decoder.decode_packed(packed)
+
+
+def test_keys_share_one_string_table():
+    batch = b_fast.BFastBatchEncoder()
+    batch.extend(RECORDS)
+
+    encoded = batch.finish()
+    assert encoded.count(b"name") == 1
+
+
+def test_empty_batch():
+    assert b_fast.BFast().decode_packed(b_fast.BFastBatchEncoder().finish()) == []
+
+
+def test_failed_record_leaves_the_batch_usable():
+    batch = b_fast.BFastBatchEncoder()
+    batch.add({"id": 1})
+
+    with pytest.raises(RecursionError):
+        batch.add({"id": 2, "loop": Loop()})
+    batch.add({"id": 3})
+
+    assert len(batch) == 2
+    encoded = batch.finish()
+    assert b"loop" not in encoded
+    assert b_fast.BFast().decode_packed(encoded) == [{"id": 1}, {"id": 3}]
+
+
+def test_finished_batch_is_closed():
+    batch = b_fast.BFastBatchEncoder()
+    batch.add({"id": 1})
+    batch.finish()
+
+    with pytest.raises(ValueError, match="already finished"):
+        batch.add({"id": 2})
+    with pytest.raises(ValueError, match="already finished"):
+        batch.finish()
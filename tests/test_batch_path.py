@@ -0,0 +1,199 @@
+"""Tests for the record batch path taken by long lists of objects"""
+
+from datetime import datetime
+from typing import Any, List, Optional
+
+import pytest
+from pydantic import BaseModel
+
+import b_fast
+
+
+class User(BaseModel):
+    id: int
+    name: str
+
+
+class Event(BaseModel):
+    id: int
+    at: Optional[datetime] = None
+    tags: List[str] = []
+    extra: Any = None
+
+
+class Point:
+    def __init__(self, **fields):
+        self.__dict__.update(fields)
+
+
+USERS = [User(id=i, name=f"user{i}") for i in range(12)]
+
+
+def expected(items):
+    return [item.model_dump() if isinstance(item, User) else item for item in items]
+
+
+@pytest.mark.parametrize("position", [0, 5, 11])
+def test_mixed_lists(position):
+    items = list(USERS)
+    items.insert(position, {"other": 1})
+    bf = b_fast.BFast()
+
+    encoded = bf.encode_packed(items, compress=False)
+
+    assert bf.decode_packed(encoded) == expected(items)
+
+
+def test_same_class_with_other_fields():
+    points = [Point(x=i, y=i) for i in range(10)] + [Point(x=1, z=2)]
+    bf = b_fast.BFast()
+
+    decoded = bf.decode_packed(bf.encode_packed(points, compress=False))
+
+    assert decoded[-1] == {"x": 1, "z": 2}
+    assert decoded[0] == {"x": 0, "y": 0}
+
+
+def test_same_class_with_fewer_fields():
+    points = [Point(x=i, y=i) for i in range(10)] + [Point(x=1)]
+
+    encoded = b_fast.BFast(null_bitmap=True).encode_packed(points, compress=False)
+
+    assert b_fast.BFast().decode_packed(encoded)[-1] == {"x": 1}
+
+
+@pytest.mark.parametrize("threshold", [0, 1, 8, 1000])
+def test_threshold(threshold):
+    encoder = b_fast.BFast(batch_threshold=threshold)
+
+    few = encoder.encode_packed(USERS[:3], compress=False)
+    many = encoder.encode_packed(USERS, compress=False)
+
+    assert b_fast.BFast().decode_packed(few) == expected(USERS[:3])
+    assert b_fast.BFast().decode_packed(many) == expected(USERS)
+
+
+def test_threshold_must_not_be_negative():
+    with pytest.raises(OverflowError):
+        b_fast.BFast(batch_threshold=-1)
+
+
+def test_encoder_reused_after_failed_batch():
+    encoder = b_fast.BFast()
+    encoder.encode_packed(USERS + [1], compress=False)
+
+    encoded = encoder.encode_packed(USERS, compress=False)
+
+    assert b_fast.BFast().decode_packed(encoded) == expected(USERS)
+
+
+def test_first_record_none_does_not_hide_later_types():
+    events = [Event(id=i, at=datetime(2024, 5, i)) for i in range(1, 12)]
+    events.insert(0, Event(id=0))
+    bf = b_fast.BFast()
+
+    decoded = bf.decode_packed(bf.encode_packed(events, compress=False))
+
+    assert decoded == [event.model_dump() for event in events]
+
+
+def test_container_fields():
+    events = [Event(id=i, tags=["a", str(i)]) for i in range(12)]
+    bf = b_fast.BFast()
+
+    decoded = bf.decode_packed(bf.encode_packed(events, compress=False))
+
+    assert decoded[3]["tags"] == ["a", "3"]
+
+
+@pytest.mark.parametrize("kwargs", [{}, {"null_bitmap": True}])
+def test_types_changing_after_the_sample(kwargs):
+    events = [Event(id=i, extra=i) for i in range(40)]
+    events[30] = Event(id=30, extra=datetime(2024, 5, 1))
+    events[31] = Event(id=31, extra={"nested": [1, 2]})
+
+    encoded = b_fast.BFast(**kwargs).encode_packed(events, compress=False)
+    decoded = b_fast.BFast().decode_packed(encoded)
+
+    assert decoded == [event.model_dump() for event in events]
+
+
+def test_batches_carry_a_layout_fingerprint():
+    others = [User(id=i, name="x") for i in range(20)]
+    events = [Event(id=i) for i in range(12)]
+    bf = b_fast.BFast()
+
+    fingerprint = b_fast.fingerprint(bf.encode_packed(USERS, False))
+    other = b_fast.fingerprint(bf.encode_packed(events, False))
+
+    assert isinstance(fingerprint, int)
+    assert b_fast.fingerprint(bf.encode_packed(others, False)) == fingerprint
+    assert b_fast.fingerprint(bf.encode_packed(others, True)) == fingerprint
+    assert other not in (None, fingerprint)
+    assert b_fast.fingerprint(bf.encode_packed(expected(USERS), False)) is None
+    assert b_fast.fingerprint(bf.encode_packed(USERS[:3], False)) is None
+
+
+@pytest.mark.parametrize("lazy", [False, True])
+def test_decoding_checks_the_fingerprint(lazy):
+    bf = b_fast.BFast()
+    users = bf.encode_packed(USERS, compress=False)
+    events = bf.encode_packed([Event(id=i) for i in range(12)], compress=False)
+    fingerprint = b_fast.fingerprint(users)
+
+    decoded = bf.decode_packed(users, fingerprint=fingerprint, lazy=lazy)
+
+    assert list(decoded) == expected(USERS)
+    with pytest.raises(ValueError, match="layout fingerprint"):
+        bf.decode_packed(events, fingerprint=fingerprint, lazy=lazy)
+
+
+def test_fingerprinted_frames_stay_scannable():
+    bf = b_fast.BFast()
+
+    encoded = bf.encode_packed(USERS, compress=False)
+
+    assert b_fast.get(encoded, "[3].name") == "user3"
+    assert b_fast.dump_tokens(encoded)["tokens"][0][1] == "fingerprint"
+    assert b_fast.slice(encoded, 2, 4) == bf.encode_packed(USERS[2:4], compress=False)
+
+
+@pytest.mark.parametrize("kwargs", [{}, {"immutable": True}, {"max_depth": 4}])
+def test_frames_of_one_layout_decode_alike(kwargs):
+    events = [
+        Event(id=i, tags=["a"] * (i % 3), extra=[i] if i % 2 else "x")
+        for i in range(30)
+    ]
+    events[0] = Event(id=0, at=datetime(2024, 5, 1))
+    batched = b_fast.BFast()
+    unbatched = b_fast.BFast(batch_threshold=1000)
+    decoder = b_fast.BFast()
+
+    for batch in (events[:15], events[15:]):
+        encoded = batched.encode_packed(batch, compress=False)
+        decoded = decoder.decode_packed(encoded, **kwargs)
+        plain = unbatched.encode_packed(batch, compress=False)
+        assert decoded == decoder.decode_packed(plain, **kwargs)
+
+
+def test_records_of_another_layout_with_the_same_fingerprint():
+    bf = b_fast.BFast()
+    users = bf.encode_packed(USERS, compress=False)
+    points = [Point(x=i, y=str(i)) for i in range(12)]
+    points = bytearray(bf.encode_packed(points, compress=False))
+    start = points.index(b"\x62")
+    points[start : start + 9] = users[users.index(b"\x62") :][:9]
+
+    b_fast.BFast().decode_packed(users)
+    decoded = b_fast.BFast().decode_packed(bytes(points))
+
+    assert decoded == [{"x": i, "y": str(i)} for i in range(12)]
+
+
+def test_mapped_frames_with_a_fingerprint(tmp_path):
+    path = tmp_path / "users.bf"
+    path.write_bytes(b_fast.BFast().encode_packed(USERS, compress=False))
+
+    decoded = b_fast.BFast().decode_mmap(path, copy=True)
+
+    assert decoded.to_list() == expected(USERS)
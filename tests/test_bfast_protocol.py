@@ -0,0 +1,139 @@
+"""Tests for the __bfast__ / __bfast_restore__ protocol"""
+
+import enum
+
+import pytest
+
+import b_fast
+
+
+class Point:
+    def __init__(self, x, y):
+        self.x = x
+        self.y = y
+        self.cache = {"norm": None}
+
+    def __bfast__(self):
+        return [self.x, self.y]
+
+    @classmethod
+    def __bfast_restore__(cls, state):
+        return cls(*state)
+
+    def __eq__(self, other):
+        return isinstance(other, Point) and (self.x, self.y) == (other.x, other.y)
+
+
+class Color(enum.Enum):
+    RED = 1
+
+    def __bfast__(self):
+        return self.name
+
+
+class Nested:
+    def __bfast__(self):
+        return Point(1, 2)
+
+
+class Loop:
+    def __bfast__(self):
+        return self
+
+
+class EncodeOnly:
+    def __bfast__(self):
+        return {"kind": "encode-only"}
+
+
+def test_encodes_the_returned_structure():
+    bf = b_fast.BFast()
+
+    encoded = bf.encode_packed({"p": Point(3, 4)}, compress=False)
+
+    assert bf.decode_packed(encoded) == {"p": [3, 4]}
+
+
+def test_restores_allowed_classes():
+    bf = b_fast.BFast()
+
+    encoded = bf.encode_packed({"p": Point(3, 4)}, compress=False)
+    decoded = bf.decode_packed(encoded, allowed_classes=[Point])
+
+    assert decoded == {"p": Point(3, 4)}
+
+
+def test_other_allowed_classes_do_not_restore():
+    bf = b_fast.BFast()
+
+    encoded = bf.encode_packed(Point(3, 4), compress=False)
+    decoded = bf.decode_packed(encoded, allowed_classes=[EncodeOnly])
+
+    assert decoded == [3, 4]
+
+
+def test_class_without_restore_decodes_plain():
+    bf = b_fast.BFast()
+
+    encoded = bf.encode_packed(EncodeOnly(), compress=False)
+    decoded = bf.decode_packed(encoded, allowed_classes=[EncodeOnly])
+
+    assert decoded == {"kind": "encode-only"}
+
+
+def test_checked_before_enum_value():
+    bf = b_fast.BFast()
+
+    encoded = bf.encode_packed([Color.RED], compress=False)
+
+    assert bf.decode_packed(encoded) == ["RED"]
+
+
+def test_nested_custom_objects():
+    bf = b_fast.BFast()
+
+    encoded = bf.encode_packed(Nested(), compress=False)
+    decoded = bf.decode_packed(encoded, allowed_classes=[Point])
+
+    assert decoded == Point(1, 2)
+
+
+def test_self_reference_raises():
+    with pytest.raises(RecursionError):
+        b_fast.BFast().encode_packed(Loop(), compress=False)
+
+
+@pytest.mark.parametrize(
+    "encoder",
+    [
+        b_fast.BFast(dedup=True),
+        b_fast.BFast(columnar=True),
+        b_fast.BFast(null_bitmap=True),
+    ],
+)
+def test_encoder_options(encoder):
+    rows = [{"id": i, "at": Point(i, i)} for i in range(4)] * 2
+
+    encoded = encoder.encode_packed(rows, compress=True)
+    decoded = b_fast.BFast().decode_packed(encoded, allowed_classes=[Point])
+
+    assert decoded == rows
+    assert decoded[0]["at"] is not decoded[1]["at"]
+
+
+def test_dump_tokens_shows_class_name():
+    encoded = b_fast.BFast().encode_packed(Point(1, 2), compress=False)
+
+    tokens = b_fast.dump_tokens(encoded)["tokens"]
+
+    name = f'"{__name__}.Point"'
+    assert tokens[0] == (10, "custom", len(encoded) - 10, name)
+    assert tokens[1][0] == (15 + len(name) - 2, "list", 7, "2 items")
+
+
+def test_query_helpers_skip_custom_values():
+    encoded = b_fast.BFast().encode_packed(
+        {"p": Point(1, 2), "n": 5}, compress=False
+    )
+
+    assert b_fast.get(encoded, "n") == 5
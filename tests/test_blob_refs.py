@@ -0,0 +1,76 @@
+"""Tests for values kept outside the frame through blob_store/blob_resolver"""
+
+import pytest
+
+import b_fast
+
+REPORT = {"id": 7, "title": "Q3", "body": "x" * 5000, "scan": b"\x00" * 4000}
+
+
+class Store:
+    def __init__(self):
+        self.blobs = {}
+
+    def put(self, value):
+        token = f"blob-{len(self.blobs)}"
+        self.blobs[token] = value
+        return token
+
+
+def test_large_values_are_stored_outside_the_frame():
+    store = Store()
+    encoder = b_fast.BFast(blob_store=store.put, blob_threshold=1024)
+
+    encoded = encoder.encode_packed(REPORT, compress=False)
+
+    assert len(encoded) < 200
+    assert sorted(store.blobs.values(), key=len) == [REPORT["scan"], REPORT["body"]]
+    decoded = encoder.decode_packed(encoded, blob_resolver=store.blobs.__getitem__)
+    assert decoded == REPORT
+
+
+def test_small_values_stay_inline():
+    store = Store()
+    encoder = b_fast.BFast(blob_store=store.put, blob_threshold=10_000)
+
+    encoded = encoder.encode_packed(REPORT, compress=False)
+
+    assert store.blobs == {}
+    assert b_fast.BFast().decode_packed(encoded) == REPORT
+
+
+def test_records_of_a_batch_are_offloaded():
+    store = Store()
+    records = [{"id": i, "body": str(i) * 2000} for i in range(20)]
+    encoder = b_fast.BFast(blob_store=store.put, blob_threshold=1024, columnar=True)
+
+    encoded = encoder.encode_packed(records, compress=True)
+
+    assert len(store.blobs) == 20
+    resolve = store.blobs.__getitem__
+    assert encoder.decode_packed(encoded, blob_resolver=resolve) == records
+
+
+def test_frames_with_references_need_a_resolver():
+    encoder = b_fast.BFast(blob_store=lambda value: "s3://bucket/1", blob_threshold=0)
+    encoded = encoder.encode_packed({"body": "text"}, compress=False)
+
+    with pytest.raises(ValueError, match="'s3://bucket/1'.*blob_resolver"):
+        b_fast.BFast().decode_packed(encoded)
+
+
+def test_references_show_in_dumps():
+    encoder = b_fast.BFast(blob_store=lambda value: "s3://bucket/1", blob_threshold=0)
+    encoded = encoder.encode_packed(["text"], compress=False)
+
+    tokens = b_fast.dump_tokens(encoded)
+
+    assert "blob_ref" in repr(tokens)
+    assert "s3://bucket/1" in repr(tokens)
+
+
+def test_store_must_return_a_str_token():
+    encoder = b_fast.BFast(blob_store=lambda value: 42, blob_threshold=0)
+
+    with pytest.raises(TypeError, match="str token, got int"):
+        encoder.encode_packed({"body": "text"}, compress=False)
@@ -0,0 +1,54 @@
+"""Tests for the brotli codec (brotli feature)"""
+
+import pytest
+
+import b_fast
+
+
+def built_with_brotli():
+    try:
+        b_fast.BFast(compression="brotli")
+    except ValueError:
+        return False
+    return True
+
+
+pytestmark = pytest.mark.skipif(
+    not built_with_brotli(), reason="built without the brotli feature"
+)
+
+DATA = {"rows": [{"id": i, "text": "lorem ipsum " * 4, "n": i % 7} for i in range(500)]}
+
+
+@pytest.mark.parametrize("level", [None, 0, 5])
+def test_encoder_codec(level):
+    bf = b_fast.BFast(compression="brotli", compression_level=level)
+
+    packed = bf.encode_packed(DATA, compress=True)
+
+    assert packed[:4] == b"BC\x06\xff"
+    assert b_fast.BFast().decode_packed(packed) == DATA
+    assert bf.encode_deferred(DATA).result() == packed
+
+
+def test_smaller_than_lz4():
+    raw = b_fast.BFast().encode_packed(DATA, compress=False)
+
+    brotli = b_fast.recompress(raw, "brotli")
+
+    assert len(brotli) < len(b_fast.recompress(raw, "lz4"))
+    assert b_fast.recompress(brotli, "none") == raw
+    assert b_fast.get(brotli, "rows[3].id") == 3
+
+
+def test_rejects_bad_level():
+    with pytest.raises(ValueError, match="brotli level"):
+        b_fast.BFast(compression="brotli", compression_level=12)
+
+
+def test_truncated_stream_is_rejected():
+    raw = b_fast.BFast().encode_packed(DATA, compress=False)
+    packed = b_fast.recompress(raw, "brotli")
+
+    with pytest.raises(ValueError):
+        b_fast.BFast().decode_packed(packed[:-10])
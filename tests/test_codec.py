@@ -0,0 +1,105 @@
+"""Tests for the transport codec"""
+
+import threading
+
+import pytest
+
+import b_fast
+
+ROWS = [{"id": i, "name": f"user{i}"} for i in range(500)]
+
+
+class Point:
+    def __init__(self, x):
+        self.x = x
+
+    def __bfast__(self):
+        return [self.x]
+
+    @classmethod
+    def __bfast_restore__(cls, state):
+        return cls(*state)
+
+
+def test_media_type():
+    assert b_fast.MEDIA_TYPE == "application/x-bfast"
+    assert b_fast.BFastCodec.media_type == b_fast.MEDIA_TYPE
+    assert b_fast.BFastResponse.media_type == b_fast.MEDIA_TYPE
+
+
+def test_roundtrip():
+    codec = b_fast.BFastCodec()
+
+    encoded = codec.encode(ROWS)
+
+    assert encoded == b_fast.BFast().encode_packed(ROWS, compress=True)
+    assert codec.decode(encoded) == ROWS
+
+
+def test_methods_work_as_plain_callables():
+    serializer = b_fast.BFastCodec(compress=False).encode
+    deserializer = b_fast.BFastCodec().decode
+
+    assert deserializer(serializer({"ok": True})) == {"ok": True}
+
+
+def test_decode_options():
+    codec = b_fast.BFastCodec(allowed_classes=[Point])
+
+    decoded = codec.decode(codec.encode(Point(3)))
+
+    assert isinstance(decoded, Point)
+    assert decoded.x == 3
+
+
+def test_shared_between_threads():
+    codec = b_fast.BFastCodec()
+    results = []
+
+    def worker(n):
+        for _ in range(50):
+            results.append(codec.decode(codec.encode({"n": n, "rows": ROWS[:20]})))
+
+    threads = [threading.Thread(target=worker, args=(n,)) for n in range(4)]
+    for thread in threads:
+        thread.start()
+    for thread in threads:
+        thread.join()
+
+    assert len(results) == 200
+    assert all(r["rows"] == ROWS[:20] for r in results)
+
+
+def test_confluent_wire_prefix():
+    codec = b_fast.BFastCodec(compress=False, schema_id=42)
+
+    encoded = codec.encode(ROWS[:3])
+
+    assert encoded[:5] == b"\x00\x00\x00\x00\x2a"
+    assert encoded[5:] == b_fast.BFast().encode_packed(ROWS[:3], compress=False)
+    assert codec.decode(encoded) == ROWS[:3]
+
+
+def test_confluent_serializer_context_is_accepted():
+    codec = b_fast.BFastCodec(schema_id=7)
+
+    assert codec.decode(codec.encode({"ok": True}, None), None) == {"ok": True}
+
+
+def test_confluent_schema_id_mismatch_is_rejected():
+    encoded = b_fast.BFastCodec(schema_id=1).encode({"ok": True})
+
+    with pytest.raises(ValueError, match="schema ID 1, expected 2"):
+        b_fast.BFastCodec(schema_id=2).decode(encoded)
+
+
+def test_confluent_magic_byte_is_required():
+    encoded = b_fast.BFastCodec().encode({"ok": True})
+
+    with pytest.raises(ValueError, match="magic byte"):
+        b_fast.BFastCodec(schema_id=1).decode(encoded)
+
+
+def test_confluent_schema_id_must_fit_in_32_bits():
+    with pytest.raises(ValueError, match="32 bits"):
+        b_fast.BFastCodec(schema_id=2**32)
@@ -0,0 +1,118 @@
+"""Tests for schema coercions"""
+
+import uuid
+from dataclasses import dataclass
+from datetime import date, datetime
+from decimal import Decimal
+
+import pytest
+
+import b_fast
+
+ID = "12345678-1234-5678-1234-567812345678"
+COERCE = {"id": "uuid", "created": "datetime", "total": ("decimal", 2)}
+SCHEMA = b_fast.BFastSchema(["id", "created", "total"], id=0xC000, coerce=COERCE)
+RAW = {"id": ID, "created": "2024-05-01T12:30:00", "total": 10.5}
+COERCED = {
+    "id": uuid.UUID(ID),
+    "created": datetime(2024, 5, 1, 12, 30),
+    "total": Decimal("10.50"),
+}
+
+
+@dataclass
+class Payment:
+    id: uuid.UUID
+    created: datetime
+    total: Decimal
+
+
+@pytest.mark.parametrize("compress", [False, True])
+def test_encode_coerces(compress):
+    bf = b_fast.BFast(schema=SCHEMA)
+
+    encoded = bf.encode_packed([RAW, RAW], compress=compress)
+
+    assert b_fast.BFast().decode_packed(encoded) == [COERCED, COERCED]
+
+
+def test_decode_coerces():
+    encoded = b_fast.BFast().encode_packed([RAW], compress=False)
+
+    assert b_fast.BFast().decode_packed(encoded, schema=SCHEMA) == [COERCED]
+    assert b_fast.BFast().decode_packed(encoded) == [RAW]
+
+
+def test_typed_values_are_kept():
+    bf = b_fast.BFast(schema=SCHEMA)
+
+    encoded = bf.encode_packed(COERCED, compress=False)
+
+    assert b_fast.BFast().decode_packed(encoded) == COERCED
+
+
+@pytest.mark.parametrize(
+    "case",
+    [
+        ("decimal", 3, Decimal("3")),
+        ("decimal", "1.25", Decimal("1.25")),
+        ("decimal", 0.1, Decimal("0.1")),
+        (("decimal", 2), 3, Decimal("3.00")),
+        (("decimal", 1), Decimal("2.26"), Decimal("2.3")),
+        ("date", "2024-05-01", date(2024, 5, 1)),
+        ("uuid", None, None),
+        ("uuid", 7, 7),
+        ("decimal", True, True),
+    ],
+)
+def test_coercions(case):
+    coercion, value, expected = case
+    schema = b_fast.BFastSchema(["value"], id=0xC001, coerce={"value": coercion})
+    bf = b_fast.BFast(schema=schema)
+
+    decoded = b_fast.BFast().decode_packed(bf.encode_packed({"value": value}, False))
+
+    assert decoded == {"value": expected}
+    assert type(decoded["value"]) is type(expected)
+
+
+def test_objects_are_coerced():
+    schema = b_fast.BFastSchema(Payment, coerce=COERCE)
+    bf = b_fast.BFast(schema=schema, validate=True)
+    payment = Payment(ID, "2024-05-01T12:30:00", 10.5)
+
+    encoded = bf.encode_packed(payment, compress=False)
+
+    assert b_fast.BFast().decode_packed(encoded) == COERCED
+    assert payment.id == ID
+
+
+def test_split_frames_are_coerced():
+    bf = b_fast.BFast(schema=SCHEMA)
+
+    frames = bf.encode_packed_split([RAW] * 3, 120)
+
+    decoded = [row for frame in frames for row in b_fast.BFast().decode_packed(frame)]
+    assert decoded == [COERCED] * 3
+
+
+def test_bad_value():
+    bf = b_fast.BFast(schema=SCHEMA)
+
+    with pytest.raises(ValueError, match="Cannot coerce field 'id' to uuid"):
+        bf.encode_packed({"id": "nope"}, compress=False)
+
+
+@pytest.mark.parametrize(
+    "case",
+    [
+        ({"id": "int"}, "Unknown coercion 'int'"),
+        ({"id": ("uuid", 2)}, "Unknown coercion"),
+        ({"missing": "uuid"}, "Coercion for unknown field"),
+    ],
+)
+def test_bad_coercion(case):
+    coerce, message = case
+
+    with pytest.raises(ValueError, match=message):
+        b_fast.BFastSchema(["id"], coerce=coerce)
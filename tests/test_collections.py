@@ -0,0 +1,95 @@
+"""Tests for dict views and the collections module types"""
+
+from collections import Counter, OrderedDict, defaultdict, deque
+from typing import Deque
+
+from pydantic import BaseModel
+
+import b_fast
+
+SCORES = {"ana": 3, "bia": 5}
+
+
+class Queue(BaseModel):
+    name: str
+    pending: Deque[int]
+
+
+def test_dict_views_as_lists():
+    bf = b_fast.BFast()
+
+    keys = bf.decode_packed(bf.encode_packed(SCORES.keys(), compress=False))
+    values = bf.decode_packed(bf.encode_packed(SCORES.values(), compress=False))
+    items = bf.decode_packed(bf.encode_packed(SCORES.items(), compress=False))
+
+    assert keys == ["ana", "bia"]
+    assert values == [3, 5]
+    assert items == [["ana", 3], ["bia", 5]]
+
+
+def test_nested_views():
+    bf = b_fast.BFast()
+
+    encoded = bf.encode_packed({"names": SCORES.keys()}, compress=False)
+
+    assert bf.decode_packed(encoded) == {"names": ["ana", "bia"]}
+
+
+def test_deque_as_list():
+    bf = b_fast.BFast()
+
+    full = bf.encode_packed(deque([1, "two", None], maxlen=5), compress=False)
+    empty = bf.encode_packed(deque(), compress=False)
+
+    assert bf.decode_packed(full) == [1, "two", None]
+    assert bf.decode_packed(empty) == []
+
+
+def test_counter_as_dict():
+    counts = Counter("abracadabra")
+    bf = b_fast.BFast()
+
+    decoded = bf.decode_packed(bf.encode_packed(counts, compress=False))
+
+    assert type(decoded) is dict
+    assert decoded == dict(counts)
+
+
+def test_defaultdict_as_dict():
+    groups = defaultdict(list)
+    groups["a"].append(1)
+    groups["b"]
+    bf = b_fast.BFast()
+
+    decoded = bf.decode_packed(bf.encode_packed(groups, compress=False))
+
+    assert type(decoded) is dict
+    assert decoded == {"a": [1], "b": []}
+
+
+def test_ordered_dict_keeps_order():
+    ordered = OrderedDict([("z", 1), ("a", 2)])
+    bf = b_fast.BFast()
+
+    decoded = bf.decode_packed(bf.encode_packed(ordered, compress=False))
+
+    assert list(decoded.items()) == [("z", 1), ("a", 2)]
+
+
+def test_pydantic_fields():
+    queues = [Queue(name="jobs", pending=deque([4, 2]))] * 2
+    bf = b_fast.BFast()
+
+    decoded = bf.decode_packed(bf.encode_packed(queues, compress=False))
+
+    assert decoded == [{"name": "jobs", "pending": [4, 2]}] * 2
+
+
+def test_dedup_shares_views():
+    encoded = b_fast.BFast(dedup=True).encode_packed(
+        [SCORES.items(), SCORES.items()], compress=False
+    )
+
+    decoded = b_fast.BFast().decode_packed(encoded)
+
+    assert decoded == [[["ana", 3], ["bia", 5]]] * 2
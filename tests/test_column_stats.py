@@ -0,0 +1,203 @@
+"""Tests for column_stats=True and b_fast.may_match"""
+
+import math
+import operator
+
+import pytest
+
+import b_fast
+
+ORDERS = [
+    {
+        "id": i,
+        "status": ["active", "closed", "pending"][i % 3],
+        "total": i * 2.5,
+        "note": None if i % 4 else "gift",
+    }
+    for i in range(30)
+]
+OPS = {
+    "==": operator.eq,
+    "!=": operator.ne,
+    "<": operator.lt,
+    "<=": operator.le,
+    ">": operator.gt,
+    ">=": operator.ge,
+}
+OPERANDS = [None, 0, 29, 30, -1, 12.5, 72.5, 73.0, 2**63, "active", "a", "z", True]
+UNORDERED = {"count": 2, "nulls": 0, "min": None, "max": None}
+
+
+def matches(record, field, op, value):
+    if field not in record:
+        return False
+    try:
+        return OPS[op](record[field], value)
+    except TypeError:
+        return False
+
+
+def test_stats():
+    encoder = b_fast.BFast(columnar=True, column_stats=True)
+    encoded = encoder.encode_packed(ORDERS, compress=True)
+
+    stats = b_fast.stats(encoded)
+
+    assert stats == {
+        "records": 30,
+        "columns": {
+            "id": {"count": 30, "nulls": 0, "min": 0, "max": 29},
+            "status": {"count": 30, "nulls": 0, "min": "active", "max": "pending"},
+            "total": {"count": 30, "nulls": 0, "min": 0.0, "max": 72.5},
+            "note": {"count": 30, "nulls": 22, "min": "gift", "max": "gift"},
+        },
+    }
+    assert b_fast.BFast().decode_packed(encoded) == ORDERS
+
+
+def test_stats_next_to_field_totals():
+    encoder = b_fast.BFast(columnar=True, column_stats=True)
+
+    encoded = encoder.encode_packed(ORDERS, compress=False, stats=["id"])
+
+    stats = b_fast.stats(encoded)
+    assert stats["fields"]["id"]["sum"] == sum(range(30))
+    assert stats["columns"]["id"]["max"] == 29
+
+
+@pytest.mark.parametrize(
+    "case",
+    [
+        ([{"a": 1}, {"a": "x"}], UNORDERED),
+        ([{"a": True}, {"a": 2}], UNORDERED),
+        ([{"a": math.nan}, {"a": 2}], UNORDERED),
+        ([{"a": 2**64}, {"a": 1}], UNORDERED),
+        ([{"a": None}, {"b": 1}], {"count": 1, "nulls": 1, "min": None, "max": None}),
+        ([{"a": 3}, {"a": -0.5}], {"count": 2, "nulls": 0, "min": -0.5, "max": 3}),
+    ],
+)
+def test_unordered_columns(case):
+    records, expected = case
+    encoder = b_fast.BFast(columnar=True, column_stats=True)
+    encoded = encoder.encode_packed(records, compress=True)
+
+    columns = b_fast.stats(encoded)["columns"]
+
+    assert columns["a"] == expected
+
+
+def test_float32_bounds_cover_the_written_values():
+    encoder = b_fast.BFast(columnar=True, column_stats=True, float32=True)
+    encoded = encoder.encode_packed([{"x": 0.1}, {"x": 0.2}], compress=True)
+
+    column = b_fast.stats(encoded)["columns"]["x"]
+
+    decoded = [row["x"] for row in b_fast.BFast().decode_packed(encoded)]
+    assert column["min"] <= min(decoded) and column["max"] >= max(decoded)
+
+
+def test_non_finite_floats_written_as_null_count_as_nulls():
+    encoder = b_fast.BFast(columnar=True, column_stats=True, non_finite="null")
+    encoded = encoder.encode_packed([{"x": math.inf}, {"x": 1.0}], compress=True)
+
+    column = b_fast.stats(encoded)["columns"]["x"]
+
+    assert column == {"count": 2, "nulls": 1, "min": 1.0, "max": 1.0}
+
+
+@pytest.mark.parametrize("field", ["id", "status", "total", "note", "missing"])
+def test_may_match_never_prunes_a_match(field):
+    encoder = b_fast.BFast(columnar=True, column_stats=True)
+    for records in (ORDERS[:2], ORDERS[10:14], ORDERS):
+        encoded = encoder.encode_packed(records, compress=True)
+        for op in OPS:
+            for value in OPERANDS:
+                expected = any(matches(r, field, op, value) for r in records)
+
+                found = b_fast.may_match(encoded, field, op, value)
+
+                assert found or not expected, (len(records), field, op, value)
+
+
+@pytest.mark.parametrize(
+    "case",
+    [
+        ("status", "==", "refunded", False),
+        ("status", "==", "closed", True),
+        ("status", "<", "active", False),
+        ("id", ">", 29, False),
+        ("id", ">=", 29.0, True),
+        ("id", "==", "1", False),
+        ("total", "<", 0, False),
+        ("note", "==", None, True),
+        ("id", "==", None, False),
+        ("missing", "!=", 1, False),
+    ],
+)
+def test_may_match(case):
+    field, op, value, expected = case
+    encoder = b_fast.BFast(columnar=True, column_stats=True)
+    encoded = encoder.encode_packed(ORDERS, compress=True)
+
+    assert b_fast.may_match(encoded, field, op, value) is expected
+
+
+def test_frames_without_column_stats_may_match():
+    encoded = b_fast.BFast().encode_packed(ORDERS, compress=False)
+
+    assert b_fast.may_match(encoded, "status", "==", "refunded")
+
+
+@pytest.mark.parametrize(
+    "case",
+    [
+        ("id", "count", 30),
+        ("id", "min", 0),
+        ("total", "max", 72.5),
+        ("note", "count", 8),
+        ("missing", "count", 0),
+        ("missing", "max", None),
+    ],
+)
+def test_aggregate_reads_the_index(case):
+    field, op, expected = case
+    encoder = b_fast.BFast(columnar=True, column_stats=True)
+    encoded = encoder.encode_packed(ORDERS, compress=True)
+
+    assert b_fast.aggregate(encoded, field, op) == expected
+
+
+@pytest.mark.parametrize(
+    "case",
+    [
+        ("id", "sum", sum(range(30))),
+        ("total", "sum", sum(i * 2.5 for i in range(30))),
+    ],
+)
+def test_aggregate_beyond_the_index(case):
+    field, op, expected = case
+    encoder = b_fast.BFast(columnar=True, column_stats=True)
+    encoded = encoder.encode_packed(ORDERS, compress=True)
+
+    assert b_fast.aggregate(encoded, field, op) == expected
+
+
+def test_aggregate_beyond_the_index_checks_values():
+    encoder = b_fast.BFast(columnar=True, column_stats=True)
+    encoded = encoder.encode_packed(ORDERS, compress=True)
+
+    with pytest.raises(ValueError, match="non-numeric"):
+        b_fast.aggregate(encoded, "note", "max")
+
+
+def test_column_stats_requires_columnar():
+    with pytest.raises(ValueError, match="column_stats requires columnar=True"):
+        b_fast.BFast(column_stats=True)
+
+
+def test_unknown_op():
+    encoder = b_fast.BFast(columnar=True, column_stats=True)
+    encoded = encoder.encode_packed(ORDERS, compress=True)
+
+    with pytest.raises(ValueError, match="Unknown filter op"):
+        b_fast.may_match(encoded, "id", "=~", 1)
@@ -0,0 +1,272 @@
+"""Tests for BFast(columnar=True)"""
+
+import datetime
+import math
+from dataclasses import dataclass
+from decimal import Decimal
+
+import pytest
+
+import b_fast
+
+EXPORT = [
+    {
+        "id": i,
+        "tenant": 42,
+        "status": "active" if i % 500 else "suspended",
+        "score": i * 0.25,
+        "tags": ["a", "b"] if i % 2 else [],
+    }
+    for i in range(5000)
+]
+
+
+@dataclass
+class Event:
+    kind: str
+    at: datetime.date
+    amount: Decimal
+
+
+def test_records_roundtrip():
+    encoded = b_fast.BFast(columnar=True).encode_packed(EXPORT, compress=False)
+    decoded = b_fast.BFast().decode_packed(encoded)
+
+    assert decoded == EXPORT
+    assert list(decoded[0]) == ["id", "tenant", "status", "score", "tags"]
+
+
+def test_repeated_columns_are_run_length_encoded():
+    plain = b_fast.BFast().encode_packed(EXPORT, compress=False)
+
+    encoded = b_fast.BFast(columnar=True).encode_packed(EXPORT, compress=False)
+
+    # tenant and status collapse to a handful of runs
+    assert encoded.count(b"active") < 20
+    assert len(encoded) < len(plain) // 2
+
+
+def test_decoded_containers_are_independent():
+    rows = [{"k": 1, "items": []} for _ in range(10)]
+
+    encoded = b_fast.BFast(columnar=True).encode_packed(rows, compress=False)
+    decoded = b_fast.BFast().decode_packed(encoded)
+    decoded[0]["items"].append("x")
+
+    assert decoded[1]["items"] == []
+
+
+@pytest.mark.parametrize(
+    "data",
+    [
+        [{"a": 1}, {"b": 1}],
+        [{"a": 1, "b": 2}, {"b": 2, "a": 1}],
+        [{"a": 1}, {"a": 1, "b": 2}],
+        [{"a": 1}, 5],
+        [{"a": 1}],
+        [{}, {}],
+        {"nested": [{"x": None}, {"x": 2}, {"x": 2}]},
+    ],
+)
+def test_irregular_lists_keep_their_shape(data):
+    encoded = b_fast.BFast(columnar=True).encode_packed(data, compress=False)
+    decoded = b_fast.BFast().decode_packed(encoded)
+
+    assert decoded == data
+    if isinstance(data, list):
+        assert [list(row) for row in decoded if isinstance(row, dict)] == [
+            list(row) for row in data if isinstance(row, dict)
+        ]
+
+
+def test_model_rows():
+    events = [
+        Event("sale", datetime.date(2024, 1, i % 28 + 1), Decimal("9.90"))
+        for i in range(50)
+    ]
+
+    encoded = b_fast.BFast(columnar=True).encode_packed(events, compress=False)
+    decoded = b_fast.BFast().decode_packed(encoded)
+
+    assert decoded[3] == {
+        "kind": "sale",
+        "at": datetime.date(2024, 1, 4),
+        "amount": Decimal("9.90"),
+    }
+
+
+@pytest.mark.parametrize(
+    "kwargs", [{"dedup": True}, {"schema": b_fast.BFastSchema(["id", "tenant"])}]
+)
+def test_combined_with_other_options(kwargs):
+    encoder = b_fast.BFast(columnar=True, **kwargs)
+    encoded = encoder.encode_packed(EXPORT, compress=False)
+    decoded = b_fast.BFast().decode_packed(encoded)
+
+    assert decoded == EXPORT
+
+
+def test_compressed_roundtrip():
+    bf = b_fast.BFast(columnar=True)
+
+    encoded = bf.encode_packed(EXPORT, compress=True)
+
+    assert b_fast.BFast().decode_packed(encoded) == EXPORT
+
+
+def test_query_falls_back_to_full_decode():
+    encoder = b_fast.BFast(columnar=True)
+    encoded = encoder.encode_packed({"rows": EXPORT}, compress=False)
+
+    assert b_fast.get(encoded, "rows[500].status") == "suspended"
+
+
+def test_byte_level_rewrites_are_rejected():
+    encoded = b_fast.BFast(columnar=True).encode_packed(EXPORT, compress=False)
+
+    with pytest.raises(ValueError, match="columnar"):
+        b_fast.slice(encoded, 0, 2)
+
+
+TIMESTAMPS = [
+    {"ts": 1_700_000_000_000_000_000 + i * 1_000_000 + i % 3, "seq": i}
+    for i in range(3000)
+]
+
+
+def test_sorted_int_columns_are_delta_encoded():
+    plain = b_fast.BFast(columnar=True).encode_packed(
+        [{"ts": row["ts"], "seq": str(row["seq"])} for row in TIMESTAMPS],
+        compress=False,
+    )
+
+    encoded = b_fast.BFast(columnar=True).encode_packed(TIMESTAMPS, compress=False)
+    decoded = b_fast.BFast().decode_packed(encoded)
+
+    assert decoded == TIMESTAMPS
+    # ~2 bytes per timestamp and 1 per sequence number instead of 9 each
+    assert len(encoded) < 4 * len(TIMESTAMPS)
+    assert len(encoded) < len(plain) // 2
+
+
+@pytest.mark.parametrize(
+    "values",
+    [
+        list(range(100, 0, -1)),
+        [-(2**63), 2**63 - 1],
+        [2**63 - 1, 0, -(2**63)],
+        [5, 5, 5, 7, 7, 2**40],
+        list(range(2000)) + [3, -(2**62), 2**62],
+    ],
+)
+def test_delta_roundtrip_is_exact(values):
+    rows = [{"v": value} for value in values]
+
+    encoded = b_fast.BFast(columnar=True).encode_packed(rows, compress=False)
+    decoded = b_fast.BFast().decode_packed(encoded)
+
+    assert decoded == rows
+    assert all(type(row["v"]) is int for row in decoded)
+
+
+def test_unsorted_and_mixed_int_columns_stay_plain():
+    rows = [{"v": value} for value in [3, 1, 2, True, None, 2**70]]
+
+    encoded = b_fast.BFast(columnar=True).encode_packed(rows, compress=False)
+    decoded = b_fast.BFast().decode_packed(encoded)
+
+    assert decoded == rows
+    assert decoded[3]["v"] is True
+
+
+READINGS = [{"sensor": 7, "celsius": 21.5 + (i // 10 % 8) * 0.25} for i in range(4000)]
+
+
+def test_float_columns_are_xor_encoded():
+    encoded = b_fast.BFast(columnar=True).encode_packed(READINGS, compress=False)
+    decoded = b_fast.BFast().decode_packed(encoded)
+
+    assert decoded == READINGS
+    # about a byte per reading instead of 9
+    assert len(encoded) < 2 * len(READINGS)
+
+
+@pytest.mark.parametrize(
+    "values",
+    [
+        [0.0, -0.0, 0.0, 5e-324, -5e-324],
+        [float("inf"), float("-inf"), 1.5, 1.5, 1.5],
+        [1.7976931348623157e308, -1.7976931348623157e308, 2.2250738585072014e-308],
+        [i / 7 for i in range(500)],
+    ],
+)
+def test_xor_roundtrip_is_bit_exact(values):
+    rows = [{"v": value} for value in values]
+
+    encoded = b_fast.BFast(columnar=True).encode_packed(rows, compress=False)
+    decoded = b_fast.BFast().decode_packed(encoded)
+
+    assert [math.copysign(1, row["v"]) for row in decoded] == [
+        math.copysign(1, value) for value in values
+    ]
+    assert decoded == rows
+
+
+def test_xor_roundtrip_keeps_nan():
+    rows = [{"v": value} for value in [1.0, float("nan"), 2.0, float("nan")]]
+
+    encoded = b_fast.BFast(columnar=True).encode_packed(rows, compress=False)
+    decoded = b_fast.BFast().decode_packed(encoded)
+
+    assert math.isnan(decoded[1]["v"]) and math.isnan(decoded[3]["v"])
+    assert decoded[2]["v"] == 2.0
+
+
+
+COUNTRIES = ["BR", "PT", "AO", "MZ", None]
+# 200 cities in each half, so the second half adds new dictionary entries
+ORDERS = [
+    {"id": i, "country": COUNTRIES[i * 3 % 5], "city": f"c{i % 200 + i // 2000 * 200}"}
+    for i in range(4000)
+]
+
+
+def columns(encoded):
+    """`{name: (encoding, its last token)}` of a columnar root list"""
+    tokens = b_fast.dump_tokens(encoded)["tokens"][-1]
+    found = {}
+    for column, values in zip(tokens[::2], tokens[1::2]):
+        name, encoding = column[3].rsplit(" ", 1)
+        found[name.strip('"')] = (encoding.strip("()"), values[-1])
+    return found
+
+
+def test_low_cardinality_strings_are_dictionary_encoded():
+    unique = [dict(row, city=f"c{row['id']}") for row in ORDERS]
+    plain = b_fast.BFast(columnar=True).encode_packed(unique, compress=False)
+
+    encoded = b_fast.BFast(columnar=True).encode_packed(ORDERS, compress=False)
+    decoded = b_fast.BFast().decode_packed(encoded)
+
+    found = columns(encoded)
+    assert decoded == ORDERS
+    assert found["country"][0] == found["city"][0] == "dict"
+    assert found["country"][1][1:] == ("codes", 4000, "4000 x u8")
+    assert found["city"][1][1:] == ("codes", 8000, "4000 x u16")
+    assert columns(plain)["city"][0] == "plain"
+    assert len(encoded) < len(plain) // 2
+    assert b_fast.spec()["column_encodings"][5] == "dict"
+
+
+def test_runs_and_high_cardinality_strings_keep_their_encoding():
+    rows = [
+        {"status": "active" if i < 900 else "closed", "key": f"k{i}"}
+        for i in range(1000)
+    ]
+
+    encoded = b_fast.BFast(columnar=True).encode_packed(rows, compress=False)
+    decoded = b_fast.BFast().decode_packed(encoded)
+
+    assert decoded == rows
+    assert columns(encoded)["status"][0] == "rle"
+    assert columns(encoded)["key"][0] == "plain"
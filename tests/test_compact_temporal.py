@@ -0,0 +1,87 @@
+"""Tests for the compact date (days) and time (nanoseconds) encodings"""
+
+from datetime import date, time, timedelta, timezone
+
+import pytest
+from pydantic import BaseModel
+
+import b_fast
+
+
+class Shift(BaseModel):
+    day: date
+    start: time
+
+
+@pytest.mark.parametrize(
+    "value",
+    [
+        date(2024, 2, 29),
+        date(1970, 1, 1),
+        date(1969, 12, 31),
+        date(1, 1, 1),
+        date(9999, 12, 31),
+        time(0, 0),
+        time(8, 15, 30),
+        time(23, 59, 59, 999999),
+    ],
+)
+def test_roundtrip(value):
+    bf = b_fast.BFast()
+
+    encoded = bf.encode_packed(value, compress=False)
+    decoded = bf.decode_packed(encoded)
+
+    assert type(decoded) is type(value)
+    assert decoded == value
+
+
+def test_fixed_sizes():
+    values = [date(2024, 5, 1), time(8, 15, 0, 5)]
+    encoded = b_fast.BFast().encode_packed(values, compress=False)
+
+    tokens = b_fast.dump_tokens(encoded)
+
+    assert tokens["tokens"][1] == [
+        (15, "date", 5, '"2024-05-01"'),
+        (20, "time", 9, '"08:15:00.000005"'),
+    ]
+
+
+def test_aware_time_keeps_offset():
+    value = time(8, 15, tzinfo=timezone(timedelta(hours=2)))
+    bf = b_fast.BFast()
+
+    encoded = bf.encode_packed(value, compress=False)
+    decoded = bf.decode_packed(encoded)
+
+    assert decoded == value
+    assert decoded.utcoffset() == timedelta(hours=2)
+
+
+def test_disallowed_classes_decode_iso_strings():
+    values = [date(2024, 5, 1), time(8, 15, 0, 5)]
+    bf = b_fast.BFast()
+
+    encoded = bf.encode_packed(values, compress=False)
+    decoded = bf.decode_packed(encoded, allowed_classes=[])
+
+    assert decoded == ["2024-05-01", "08:15:00.000005"]
+
+
+@pytest.mark.parametrize("kwargs", [{}, {"columnar": True}])
+def test_pydantic_models(kwargs):
+    shifts = [Shift(day=date(2024, 5, i + 1), start=time(9, i)) for i in range(12)]
+    encoded = b_fast.BFast(**kwargs).encode_packed(shifts, compress=False)
+
+    decoded = b_fast.BFast().decode_packed(encoded)
+
+    assert decoded == [shift.model_dump() for shift in shifts]
+
+
+def test_query_helpers():
+    data = {"day": date(2024, 5, 1), "start": time(9, 0), "n": 5}
+    encoded = b_fast.BFast().encode_packed(data, compress=False)
+
+    assert b_fast.get(encoded, "n") == 5
+    assert b_fast.get(encoded, "day") == date(2024, 5, 1)
@@ -0,0 +1,239 @@
+"""Tests for b_fast.recompress"""
+
+import io
+import os
+
+import pytest
+
+import b_fast
+
+DATA = {"rows": [{"id": i, "text": "lorem ipsum " * 4, "n": i % 7} for i in range(500)]}
+NOISE = {"blob": os.urandom(4096), "tag": "random"}
+# Compressed frames start with a header and the inflated size, then the codec
+BODY = 14
+
+
+@pytest.mark.parametrize("source_compressed", [True, False])
+def test_recompress_to_zstd(source_compressed):
+    bf = b_fast.BFast()
+    encoded = bf.encode_packed(DATA, compress=source_compressed)
+
+    packed = b_fast.recompress(encoded, "zstd", level=19)
+
+    assert packed[BODY : BODY + 4] == b"\x28\xb5\x2f\xfd"
+    assert bf.decode_packed(packed) == DATA
+    assert len(packed) < len(bf.encode_packed(DATA, compress=True))
+
+
+def test_recompress_preserves_frame_bytes():
+    bf = b_fast.BFast()
+    raw = bf.encode_packed(DATA, compress=False)
+
+    zstd = b_fast.recompress(raw)
+    back = b_fast.recompress(zstd, "none")
+    lz4 = b_fast.recompress(zstd, "lz4")
+
+    assert back == raw
+    assert b_fast.recompress(lz4, "none") == raw
+
+
+def test_zstd_frames_work_with_other_decoders(tmp_path):
+    bf = b_fast.BFast()
+    packed = b_fast.recompress(bf.encode_packed(DATA, compress=True))
+    path = tmp_path / "cold.bf"
+    path.write_bytes(packed)
+
+    assert bf.decode_from(io.BytesIO(packed)) == DATA
+    assert bf.decode_mmap(path) == DATA
+    assert b_fast.get(packed, "rows[3].id") == 3
+
+
+def test_recompress_rejects_bad_settings():
+    encoded = b_fast.BFast().encode_packed(DATA, compress=False)
+
+    with pytest.raises(ValueError, match="Unknown compression"):
+        b_fast.recompress(encoded, "gzip")
+    with pytest.raises(ValueError, match="zstd level"):
+        b_fast.recompress(encoded, "zstd", level=99)
+
+
+def test_recompress_to_snappy():
+    bf = b_fast.BFast()
+    raw = bf.encode_packed(DATA, compress=False)
+
+    packed = b_fast.recompress(raw, "snappy")
+
+    assert packed[BODY : BODY + 4] == b"BC\x03\xff"
+    assert len(packed) < len(raw)
+    assert bf.decode_packed(packed) == DATA
+    assert b_fast.recompress(packed, "none") == raw
+
+
+@pytest.mark.parametrize("size", [0, 1, 59, 60, 300, 70_000])
+def test_snappy_roundtrips_strings(size):
+    bf = b_fast.BFast()
+    data = {"s": "".join(chr(97 + (i * i) % 26) for i in range(size)), "r": "ab" * size}
+
+    packed = b_fast.recompress(bf.encode_packed(data, compress=False), "snappy")
+
+    assert bf.decode_packed(packed) == data
+
+
+def test_snappy_reads_with_standard_tooling():
+    snappy = pytest.importorskip("snappy")
+    bf = b_fast.BFast(compression="snappy")
+    raw = bf.encode_packed(DATA, compress=False)
+
+    packed = b_fast.recompress(raw, "snappy")
+
+    assert snappy.uncompress(packed[BODY + 4 :]) == raw
+    assert bf.decode_packed(b"BC\x03\xff" + snappy.compress(raw)) == DATA
+
+
+@pytest.mark.parametrize(
+    "compression, prefix", [("zstd", b"\x28\xb5\x2f\xfd"), ("snappy", b"BC\x03\xff")]
+)
+def test_encoder_codec(compression, prefix):
+    bf = b_fast.BFast(compression=compression)
+
+    packed = bf.encode_packed(DATA, compress=True)
+
+    assert packed[BODY : BODY + 4] == prefix
+    assert b_fast.BFast().decode_packed(packed) == DATA
+    assert bf.encode_deferred(DATA).result() == packed
+
+
+def test_encoder_rejects_unknown_codec():
+    with pytest.raises(ValueError, match="Unknown compression"):
+        b_fast.BFast(compression="gzip")
+    with pytest.raises(ValueError, match="zstd level"):
+        b_fast.BFast(compression="zstd", compression_level=99)
+
+
+def test_truncated_snappy_is_rejected():
+    raw = b_fast.BFast().encode_packed(DATA, compress=False)
+    packed = b_fast.recompress(raw, "snappy")
+
+    with pytest.raises(ValueError):
+        b_fast.BFast().decode_packed(packed[:-10])
+
+
+@pytest.mark.parametrize("chunk_size", [1024, 4096, 16_384])
+def test_parallel_chunk_size(chunk_size):
+    bf = b_fast.BFast(parallel_threshold=1, chunk_size=chunk_size)
+    raw = bf.encode_packed(DATA, compress=False)
+
+    packed = bf.encode_packed(DATA, compress=True)
+
+    chunks = int.from_bytes(packed[BODY + 8 : BODY + 12], "little")
+    assert packed[BODY : BODY + 4] == b"BC\x04\xff"
+    assert int.from_bytes(packed[BODY + 4 : BODY + 8], "little") == len(raw)
+    assert chunks == -(-len(raw) // chunk_size)
+    assert b_fast.BFast().decode_packed(packed) == DATA
+
+
+def test_parallel_threshold():
+    raw = b_fast.BFast().encode_packed(DATA, compress=False)
+    below = b_fast.BFast(parallel_threshold=len(raw) + 1, chunk_size=1024)
+    at = b_fast.BFast(parallel_threshold=len(raw), chunk_size=1024)
+
+    assert below.encode_packed(DATA, compress=True) == b_fast.BFast().encode_packed(
+        DATA, compress=True
+    )
+    assert at.encode_packed(DATA, compress=True)[BODY : BODY + 4] == b"BC\x04\xff"
+
+
+def test_chunk_size_must_be_positive():
+    with pytest.raises(ValueError, match="chunk_size"):
+        b_fast.BFast(chunk_size=0)
+
+
+def test_unenveloped_chunks_still_decode():
+    bf = b_fast.BFast(parallel_threshold=1, chunk_size=4096)
+    packed = bf.encode_packed(DATA, compress=True)
+
+    # Encoders before the envelope wrote the chunks bare
+    legacy = packed[BODY + 4 :]
+
+    assert b_fast.BFast().decode_packed(legacy) == DATA
+    assert b_fast.recompress(legacy, "none") == b_fast.recompress(packed, "none")
+    assert b_fast.spec()["codecs"][4] == "lz4-chunks"
+
+
+def test_lz4_frame_roundtrip():
+    bf = b_fast.BFast(compression="lz4-frame")
+    raw = bf.encode_packed(DATA, compress=False)
+
+    packed = bf.encode_packed(DATA, compress=True)
+
+    assert packed[BODY : BODY + 4] == b"\x04\x22\x4d\x18"
+    assert b_fast.BFast().decode_packed(packed) == DATA
+    assert b_fast.get(packed, "rows[3].id") == 3
+    assert len(b_fast.recompress(packed, "none")) == len(raw)
+    assert b_fast.spec()["codecs"][5] == "lz4-frame"
+
+
+def test_lz4_frame_reads_with_standard_tooling():
+    lz4_frame = pytest.importorskip("lz4.frame")
+    bf = b_fast.BFast(compression="lz4-frame")
+    raw = bf.encode_packed(DATA, compress=False)
+
+    packed = b_fast.recompress(raw, "lz4-frame")
+
+    assert lz4_frame.decompress(packed) == raw
+    assert bf.decode_packed(lz4_frame.compress(raw)) == DATA
+
+
+class TrickleReader:
+    """Hands out at most `limit` bytes per read() call"""
+
+    def __init__(self, data, limit):
+        self.source = io.BytesIO(data)
+        self.limit = limit
+        self.reads = 0
+
+    def read(self, size=-1):
+        self.reads += 1
+        return self.source.read(min(size, self.limit) if size >= 0 else self.limit)
+
+
+def test_lz4_frame_decodes_while_streaming():
+    bf = b_fast.BFast(compression="lz4-frame")
+    packed = bf.encode_packed(DATA, compress=True)
+    stream = TrickleReader(packed, 1000)
+
+    assert bf.decode_from(stream) == DATA
+    assert stream.reads > len(packed) // 1000
+
+
+def test_truncated_lz4_frame_is_rejected():
+    packed = b_fast.BFast(compression="lz4-frame").encode_packed(DATA, compress=True)
+
+    with pytest.raises(ValueError):
+        b_fast.BFast().decode_packed(packed[:-10])
+    with pytest.raises(ValueError):
+        b_fast.BFast().decode_from(io.BytesIO(packed[:-10]))
+
+
+@pytest.mark.parametrize("compression", ["lz4", "lz4-frame", "zstd", "snappy"])
+def test_incompressible_frames_are_stored(compression):
+    bf = b_fast.BFast(compression=compression)
+    raw = bf.encode_packed(NOISE, compress=False)
+
+    packed = bf.encode_packed(NOISE, compress=True)
+
+    assert packed == raw
+    assert packed[2] & 0x01 == 0
+    assert bf.decode_packed(packed) == NOISE
+    assert bf.encode_deferred(NOISE).result() == raw
+
+
+def test_stored_frames_keep_log_checksums():
+    sink = io.BytesIO()
+    writer = b_fast.BFastWriter(sink, compress=True, log=True)
+    writer.append(NOISE)
+    writer.append(DATA)
+
+    reader = b_fast.BFastReader(io.BytesIO(sink.getvalue()), log=True)
+    assert list(reader) == [NOISE, DATA]
+    assert not reader.truncated
@@ -0,0 +1,85 @@
+"""Tests for BFast(computed_fields=True)"""
+
+from functools import cached_property
+
+import pytest
+from pydantic import BaseModel, computed_field
+
+import b_fast
+
+
+class Rectangle(BaseModel):
+    width: int
+    height: int
+
+    @computed_field
+    @property
+    def area(self) -> int:
+        return self.width * self.height
+
+    @property
+    def perimeter(self) -> int:
+        return 2 * (self.width + self.height)
+
+
+class Order(BaseModel):
+    id: int
+    items: list[Rectangle]
+
+    @computed_field
+    @cached_property
+    def count(self) -> int:
+        return len(self.items)
+
+
+class Broken(BaseModel):
+    id: int
+
+    @computed_field
+    @property
+    def fails(self) -> int:
+        raise RuntimeError("boom")
+
+
+def test_off_by_default():
+    bf = b_fast.BFast()
+
+    encoded = bf.encode_packed(Rectangle(width=2, height=3), compress=False)
+
+    assert bf.decode_packed(encoded) == {"width": 2, "height": 3}
+
+
+def test_matches_model_dump():
+    rect = Rectangle(width=2, height=3)
+
+    encoded = b_fast.BFast(computed_fields=True).encode_packed(rect, compress=False)
+    decoded = b_fast.BFast().decode_packed(encoded)
+
+    assert decoded == rect.model_dump()
+    assert list(decoded) == ["width", "height", "area"]
+
+
+def test_nested_models_and_cached_properties():
+    order = Order(id=1, items=[Rectangle(width=1, height=1)])
+
+    encoded = b_fast.BFast(computed_fields=True).encode_packed(order, compress=False)
+
+    assert b_fast.BFast().decode_packed(encoded) == order.model_dump()
+
+
+@pytest.mark.parametrize(
+    "kwargs", [{}, {"columnar": True}, {"null_bitmap": True}, {"dedup": True}]
+)
+def test_lists_of_models(kwargs):
+    rects = [Rectangle(width=i, height=2) for i in range(12)]
+
+    encoder = b_fast.BFast(computed_fields=True, **kwargs)
+    encoded = encoder.encode_packed(rects, compress=False)
+    decoded = b_fast.BFast().decode_packed(encoded)
+
+    assert decoded == [rect.model_dump() for rect in rects]
+
+
+def test_property_errors_propagate():
+    with pytest.raises(RuntimeError, match="boom"):
+        b_fast.BFast(computed_fields=True).encode_packed(Broken(id=1), compress=False)
@@ -0,0 +1,311 @@
+"""Tests for the bfast-lines multi-document container"""
+
+import io
+import struct
+import zlib
+
+import pytest
+
+import b_fast
+
+
+def test_writer_reader_roundtrip():
+    events = [{"event": "click", "id": i} for i in range(10)]
+    sink = io.BytesIO()
+
+    writer = b_fast.BFastWriter(sink)
+    writer.append(events[0])
+    writer.extend(events[1:])
+    assert writer.frames == 10
+
+    sink.seek(0)
+    assert list(b_fast.BFastReader(sink)) == events
+
+
+def test_append_to_existing_file(tmp_path):
+    path = tmp_path / "events.bfl"
+
+    with b_fast.BFastWriter(path, compress=True) as writer:
+        writer.append({"seq": 1})
+    with b_fast.BFastWriter(path) as writer:
+        writer.append({"seq": 2, "payload": "x" * 1000})
+
+    with b_fast.BFastReader(path) as reader:
+        assert [doc["seq"] for doc in reader] == [1, 2]
+        assert reader.frames == 2
+
+
+def test_truncated_frame_raises():
+    sink = io.BytesIO()
+    writer = b_fast.BFastWriter(sink)
+    writer.append({"ok": True})
+    writer.append({"torn": "record"})
+
+    torn = io.BytesIO(sink.getvalue()[:-3])
+    reader = b_fast.BFastReader(torn)
+    assert next(reader) == {"ok": True}
+    with pytest.raises(ValueError, match="Truncated frame"):
+        next(reader)
+
+
+def test_oversized_length_prefix_raises():
+    sink = io.BytesIO(struct.pack("<I", 0xFFFFFFFF) + b"BF")
+
+    with pytest.raises(ValueError, match="Truncated frame"):
+        next(b_fast.BFastReader(sink))
+
+
+def test_empty_container():
+    assert list(b_fast.BFastReader(io.BytesIO())) == []
+
+
+def test_log_stops_cleanly_at_torn_tail(tmp_path):
+    path = tmp_path / "events.log"
+    with b_fast.BFastWriter(path, log=True, fsync_every=2) as writer:
+        for i in range(5):
+            writer.append({"seq": i})
+
+    data = path.read_bytes()
+    path.write_bytes(data[:-4])
+
+    with b_fast.BFastReader(path, log=True) as reader:
+        assert [doc["seq"] for doc in reader] == [0, 1, 2, 3]
+        assert reader.truncated
+
+
+def test_log_detects_corruption():
+    sink = io.BytesIO()
+    writer = b_fast.BFastWriter(sink, log=True)
+    writer.append({"seq": 0})
+    writer.append({"seq": 1})
+
+    corrupted = bytearray(sink.getvalue())
+    corrupted[-2] ^= 0xFF
+
+    reader = b_fast.BFastReader(io.BytesIO(bytes(corrupted)), log=True)
+    assert list(reader) == [{"seq": 0}]
+    assert reader.truncated
+
+
+def corrupt_middle_record(sink):
+    """The log in `sink` with three records, the second failing its checksum."""
+    b_fast.BFastWriter(sink, log=True).extend([{"seq": i} for i in range(3)])
+    data = bytearray(sink.getvalue())
+    length = struct.unpack_from("<I", data, 0)[0]
+    data[8 + length + 10] ^= 0xFF
+    return bytes(data)
+
+
+def test_log_reader_raises_at_a_corrupt_record_before_others():
+    data = corrupt_middle_record(io.BytesIO())
+
+    reader = b_fast.BFastReader(io.BytesIO(data), log=True)
+
+    assert next(reader) == {"seq": 0}
+    with pytest.raises(ValueError, match="followed by more records"):
+        next(reader)
+    assert not reader.truncated
+
+
+def test_log_read_many_raises_at_a_corrupt_record_before_others():
+    data = corrupt_middle_record(io.BytesIO())
+
+    reader = b_fast.BFastReader(io.BytesIO(data), log=True)
+
+    assert reader.read_many() == [{"seq": 0}]
+    with pytest.raises(ValueError, match="followed by more records"):
+        reader.read_many()
+    assert reader.read_many() == [{"seq": 2}]
+
+
+@pytest.mark.parametrize("cut", [0, 3])
+def test_log_writer_refuses_a_corrupt_record_before_a_torn_one(tmp_path, cut):
+    path = tmp_path / "events.log"
+    data = corrupt_middle_record(io.BytesIO())
+    path.write_bytes(data[: len(data) - cut])
+
+    with pytest.raises(ValueError, match="Corrupt log record"):
+        b_fast.BFastWriter(path, log=True)
+    with pytest.raises(ValueError, match="followed by more records"):
+        list(b_fast.BFastReader(path, log=True))
+
+
+def test_log_writer_recovers_before_appending(tmp_path):
+    path = tmp_path / "events.log"
+    with b_fast.BFastWriter(path, log=True) as writer:
+        writer.append({"seq": 0})
+        writer.append({"seq": 1})
+    path.write_bytes(path.read_bytes()[:-3])
+
+    with b_fast.BFastWriter(path, log=True) as writer:
+        assert writer.recovered_bytes > 0
+        writer.append({"seq": 2})
+
+    with b_fast.BFastReader(path, log=True) as reader:
+        assert [doc["seq"] for doc in reader] == [0, 2]
+        assert not reader.truncated
+
+
+def test_log_writer_drops_a_final_record_failing_its_checksum(tmp_path):
+    path = tmp_path / "events.log"
+    with b_fast.BFastWriter(path, log=True) as writer:
+        writer.extend([{"seq": 0}, {"seq": 1}])
+    corrupted = bytearray(path.read_bytes())
+    corrupted[-2] ^= 0xFF
+    path.write_bytes(bytes(corrupted))
+
+    with b_fast.BFastWriter(path, log=True) as writer:
+        writer.append({"seq": 2})
+
+    with b_fast.BFastReader(path, log=True) as reader:
+        assert [doc["seq"] for doc in reader] == [0, 2]
+
+
+def test_log_writer_refuses_a_corrupt_record_before_others(tmp_path):
+    path = tmp_path / "events.log"
+    with b_fast.BFastWriter(path, log=True) as writer:
+        writer.extend([{"seq": i} for i in range(3)])
+    corrupted = bytearray(path.read_bytes())
+    corrupted[10] ^= 0xFF
+    path.write_bytes(bytes(corrupted))
+
+    with pytest.raises(ValueError, match="Corrupt log record at byte 0"):
+        b_fast.BFastWriter(path, log=True)
+
+    assert path.read_bytes() == bytes(corrupted)
+
+
+@pytest.mark.parametrize("compress", [False, True])
+def test_log_checksums_cover_the_written_frames(compress):
+    rows = [{"id": i, "name": "x" * 20} for i in range(5000)]
+    documents = [{"seq": 0}, {"rows": rows}]
+    sink = io.BytesIO()
+    writer = b_fast.BFastWriter(sink, log=True, compress=compress)
+    writer.extend(documents)
+
+    data = sink.getvalue()
+    offset = 0
+    while offset < len(data):
+        length, checksum = struct.unpack_from("<II", data, offset)
+        frame = data[offset + 8 : offset + 8 + length]
+        assert checksum == zlib.crc32(frame)
+        assert b_fast.BFast().decode_packed(frame) in documents
+        offset += 8 + length
+
+
+@pytest.mark.parametrize("log", [False, True])
+def test_read_many(log):
+    documents = [{"seq": i, "payload": "x" * (i * 100)} for i in range(50)]
+    sink = io.BytesIO()
+    b_fast.BFastWriter(sink, compress=True, log=log).extend(documents)
+    sink.seek(0)
+
+    reader = b_fast.BFastReader(sink, log=log)
+    first = reader.read_many(10)
+    rest = reader.read_many()
+
+    assert first == documents[:10]
+    assert rest == documents[10:]
+    assert reader.frames == 50
+    assert reader.read_many() == []
+
+
+def test_read_many_stops_at_a_corrupt_log_record():
+    sink = io.BytesIO()
+    writer = b_fast.BFastWriter(sink, log=True)
+    writer.extend([{"seq": 0}, {"seq": 1}, {"seq": 2}])
+
+    corrupted = bytearray(sink.getvalue())
+    corrupted[-2] ^= 0xFF
+
+    reader = b_fast.BFastReader(io.BytesIO(bytes(corrupted)), log=True)
+    assert reader.read_many() == [{"seq": 0}, {"seq": 1}]
+    assert reader.truncated
+
+
+
+@pytest.mark.parametrize("log", [False, True])
+def test_cursor_resumes_from_a_saved_position(tmp_path, log):
+    path = tmp_path / "events.bfl"
+    documents = [{"seq": i, "payload": "x" * i} for i in range(20)]
+    with b_fast.BFastWriter(path, compress=True, log=log) as writer:
+        writer.extend(documents)
+
+    with b_fast.BFastReader(path, log=log) as reader:
+        assert reader.remaining() == path.stat().st_size
+        first = [reader.decode_next() for _ in range(5)] + reader.read_many(3)
+        position = reader.position()
+
+    with b_fast.BFastReader(path, log=log, position=position) as reader:
+        rest = list(reader)
+        assert reader.remaining() == 0
+
+    assert first == documents[:8]
+    assert rest == documents[8:]
+
+
+def test_cursor_counts_from_where_the_source_starts():
+    source = io.BytesIO()
+    source.write(b"prelude")
+    b_fast.BFastWriter(source).extend([{"seq": 0}, {"seq": 1}])
+    source.seek(7)
+
+    reader = b_fast.BFastReader(source)
+
+    assert reader.position() == 7
+    assert reader.decode_next() == {"seq": 0}
+    assert reader.position() == source.tell()
+
+
+def test_cursor_counts_records_that_fail_to_decode():
+    valid = b_fast.BFast().encode_packed({"seq": 1}, compress=False)
+    data = struct.pack("<I", 3) + b"bad" + struct.pack("<I", len(valid)) + valid
+
+    reader = b_fast.BFastReader(io.BytesIO(data))
+
+    with pytest.raises(ValueError):
+        reader.read_many()
+    assert reader.position() == 7
+    assert reader.read_many() == [{"seq": 1}]
+    assert reader.position() == len(data)
+
+
+def test_read_many_keeps_the_records_around_a_bad_one():
+    bf = b_fast.BFast()
+    frames = [bf.encode_packed({"seq": i}, compress=False) for i in range(3)]
+    frames[1] = b"bad"
+    data = b"".join(struct.pack("<I", len(frame)) + frame for frame in frames)
+
+    reader = b_fast.BFastReader(io.BytesIO(data))
+
+    assert reader.read_many() == [{"seq": 0}]
+    assert reader.position() == 4 + len(frames[0])
+    with pytest.raises(ValueError):
+        reader.read_many()
+    assert reader.position() == 4 + len(frames[0]) + 4 + 3
+    assert reader.read_many() == [{"seq": 2}]
+    assert reader.position() == len(data)
+    assert reader.frames == 2
+
+
+def test_cursor_picks_up_appended_frames(tmp_path):
+    path = tmp_path / "events.bfl"
+    with b_fast.BFastWriter(path) as writer:
+        writer.append({"seq": 0})
+
+    with b_fast.BFastReader(path) as reader:
+        assert reader.decode_next() == {"seq": 0}
+        with pytest.raises(EOFError):
+            reader.decode_next()
+        position = reader.position()
+
+    with b_fast.BFastWriter(path) as writer:
+        writer.append({"seq": 1})
+
+    with b_fast.BFastReader(path, position=position) as reader:
+        assert reader.remaining() == path.stat().st_size - position
+        assert reader.decode_next() == {"seq": 1}
+
+
+if __name__ == "__main__":
+    pytest.main([__file__])
@@ -0,0 +1,135 @@
+"""Tests for BFast(datetime_nanos=True)"""
+
+import sys
+from datetime import datetime, timedelta, timezone
+
+import pytest
+from pydantic import BaseModel
+
+import b_fast
+
+IST = timezone(timedelta(hours=5, minutes=30))
+BRT = timezone(timedelta(hours=-3))
+
+
+class Tick(BaseModel):
+    symbol: str
+    at: datetime
+
+
+@pytest.mark.parametrize(
+    "value",
+    [
+        datetime(2024, 5, 1, 12, 30),
+        datetime(2024, 5, 1, 12, 30, 15, 123456),
+        datetime(1969, 12, 31, 23, 59, 59, 999999),
+        datetime(1700, 2, 28, 1, 2, 3),
+        datetime(2024, 2, 29, 0, 0, tzinfo=timezone.utc),
+        datetime(2024, 5, 1, 0, 15, tzinfo=IST),
+        datetime(2024, 12, 31, 22, 0, 1, 5, tzinfo=BRT),
+    ],
+)
+def test_roundtrip(value):
+    encoded = b_fast.BFast(datetime_nanos=True).encode_packed(value, compress=False)
+
+    decoded = b_fast.BFast().decode_packed(encoded)
+
+    assert decoded == value
+    assert decoded.utcoffset() == value.utcoffset()
+
+
+def test_smaller_than_iso():
+    value = [datetime(2024, 5, 1, 12, 30, 15, 123456, tzinfo=IST)]
+
+    iso = b_fast.BFast().encode_packed(value, compress=False)
+    nanos = b_fast.BFast(datetime_nanos=True).encode_packed(value, compress=False)
+
+    assert len(nanos) < len(iso)
+
+
+def test_out_of_range_raises():
+    encoder = b_fast.BFast(datetime_nanos=True)
+
+    with pytest.raises(OverflowError, match="nanosecond"):
+        encoder.encode_packed(datetime(2300, 1, 1), compress=False)
+
+
+def test_disallowed_class_decodes_iso_string():
+    value = datetime(2024, 5, 1, 0, 15, tzinfo=IST)
+    encoded = b_fast.BFast(datetime_nanos=True).encode_packed(value, compress=False)
+
+    decoded = b_fast.BFast().decode_packed(encoded, allowed_classes=[])
+
+    assert decoded == value.isoformat()
+
+
+@pytest.mark.parametrize(
+    "value, iso",
+    [
+        (datetime(2024, 5, 1, 12, 30), "2024-05-01T12:30:00.000000001"),
+        (
+            datetime(2024, 5, 1, 0, 15, tzinfo=IST),
+            "2024-05-01T00:15:00.000000001+05:30",
+        ),
+    ],
+)
+def test_nanoseconds_without_pandas_decode_iso_string(monkeypatch, value, iso):
+    encoded = bytearray(
+        b_fast.BFast(datetime_nanos=True).encode_packed(value, compress=False)
+    )
+    at = encoded.index(b"\xd1\xff\xff\xff\xff") + 5
+    nanos = int.from_bytes(encoded[at : at + 8], "little", signed=True) + 1
+    encoded[at : at + 8] = nanos.to_bytes(8, "little", signed=True)
+    monkeypatch.setitem(sys.modules, "pandas", None)
+
+    decoded = b_fast.BFast().decode_packed(bytes(encoded))
+
+    assert decoded == iso
+
+
+def test_pydantic_batch_path():
+    ticks = [Tick(symbol="X", at=datetime(2024, 5, 1, 9, i)) for i in range(12)]
+
+    encoded = b_fast.BFast(datetime_nanos=True).encode_packed(ticks, compress=False)
+
+    decoded = b_fast.BFast().decode_packed(encoded)
+
+    assert decoded[7] == {"symbol": "X", "at": datetime(2024, 5, 1, 9, 7)}
+
+
+def test_scans_and_listings():
+    data = {"at": datetime(2024, 5, 1, tzinfo=IST), "n": 5}
+    encoded = b_fast.BFast(datetime_nanos=True).encode_packed(data, compress=False)
+
+    tokens = b_fast.dump_tokens(encoded)["tokens"]
+
+    assert b_fast.get(encoded, "n") == 5
+    assert tokens[1][1][1:] == ("datetime", 17, '"2024-05-01T00:00:00+05:30"')
+    assert b_fast.BFast().decode_packed(encoded, lazy=True)["n"] == 5
+
+
+def test_pandas_timestamp_keeps_nanoseconds():
+    pandas = pytest.importorskip("pandas")
+    stamp = pandas.Timestamp("2024-05-01T12:30:00.123456789+05:30")
+    encoded = b_fast.BFast(datetime_nanos=True).encode_packed(stamp, compress=False)
+
+    decoded = b_fast.BFast().decode_packed(encoded)
+
+    assert decoded == stamp
+    assert decoded.nanosecond == 789
+
+
+def test_numpy_datetime64():
+    numpy = pytest.importorskip("numpy")
+    encoder = b_fast.BFast(datetime_nanos=True)
+
+    value = encoder.encode_packed(
+        numpy.datetime64("2024-05-01T12:30:00.000000001"), compress=False
+    )
+    missing = encoder.encode_packed(numpy.datetime64("NaT"), compress=False)
+
+    decoded = b_fast.BFast().decode_packed(value, allowed_classes=[datetime])
+    assert decoded == "2024-05-01T12:30:00.000000001"
+    assert b_fast.BFast().decode_packed(missing) is None
+    with pytest.raises(OverflowError):
+        encoder.encode_packed(numpy.datetime64("2300-01-01"), compress=False)
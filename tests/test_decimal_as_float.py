@@ -0,0 +1,66 @@
+"""Tests for BFast(decimal_as_float=True)"""
+
+import math
+from decimal import Decimal
+
+import pytest
+from pydantic import BaseModel
+
+import b_fast
+
+
+class Price(BaseModel):
+    sku: str
+    amount: Decimal
+
+
+def test_off_by_default():
+    bf = b_fast.BFast()
+
+    encoded = bf.encode_packed(Decimal("9.99"), compress=False)
+
+    assert bf.decode_packed(encoded) == Decimal("9.99")
+
+
+@pytest.mark.parametrize(
+    "text, expected",
+    [
+        ("9.99", 9.99),
+        ("9.990", 9.99),
+        ("-0.5", -0.5),
+        ("1E+3", 1000.0),
+        ("12345678.9", 12345678.9),
+        ("Infinity", math.inf),
+    ],
+)
+def test_exact_values_become_floats(text, expected):
+    encoder = b_fast.BFast(decimal_as_float=True)
+    encoded = encoder.encode_packed(Decimal(text), compress=False)
+    decoded = b_fast.BFast().decode_packed(encoded)
+
+    assert type(decoded) is float
+    assert decoded == expected
+
+
+def test_nan():
+    encoder = b_fast.BFast(decimal_as_float=True)
+    encoded = encoder.encode_packed(Decimal("NaN"), compress=False)
+
+    assert math.isnan(b_fast.BFast().decode_packed(encoded))
+
+
+@pytest.mark.parametrize(
+    "text", ["0.1000000000000000000001", "12345678901234567890.12", "sNaN"]
+)
+def test_rounding_raises(text):
+    with pytest.raises(ValueError, match="losing precision"):
+        b_fast.BFast(decimal_as_float=True).encode_packed(Decimal(text), compress=False)
+
+
+def test_pydantic_batch_path():
+    prices = [Price(sku=f"s{i}", amount=Decimal(f"{i}.25")) for i in range(12)]
+
+    encoded = b_fast.BFast(decimal_as_float=True).encode_packed(prices, compress=False)
+    decoded = b_fast.BFast().decode_packed(encoded)
+
+    assert decoded[3] == {"sku": "s3", "amount": 3.25}
@@ -1,3 +1,4 @@
+import sys
 from datetime import date, datetime, time
 from decimal import Decimal
 from uuid import uuid4
@@ -101,6 +102,21 @@ def test_decode_parallel_compression():
     assert decoded == large_data
 
 
+@pytest.mark.parametrize(
+    "options", [{}, {"null_bitmap": True}, {"columnar": True}, {"dedup": True}]
+)
+def test_decoded_keys_are_interned(options):
+    rows = [{"customer_id": i, "region": None} for i in range(20)]
+    encoded = b_fast.BFast(**options).encode_packed(rows, compress=False)
+
+    decoded = b_fast.BFast().decode_packed(encoded)
+    keys = [key for row in decoded for key in row]
+
+    assert decoded == rows
+    assert len({id(key) for key in keys}) == 2
+    assert keys[0] is sys.intern("customer_id")
+
+
 def test_decode_errors():
     bf = b_fast.BFast()
 
@@ -114,3 +130,19 @@ def test_decode_errors():
     # Invalid magic
     with pytest.raises(ValueError, match="Invalid B-FAST magic"):
         bf.decode_packed(b"XX1234", decompress=False)
+
+
+def test_decode_non_ascii_strings():
+    bf = b_fast.BFast()
+    data = ["", "naïve", "日本語", "emoji 🚀", "x" * 100_000]
+    encoded = bf.encode_packed(data, compress=False)
+    assert bf.decode_packed(encoded, decompress=False) == data
+
+
+def test_decode_invalid_utf8_string():
+    bf = b_fast.BFast()
+    encoded = bytearray(bf.encode_packed("abc", compress=False))
+    encoded[-1] = 0xFF
+
+    with pytest.raises(UnicodeDecodeError):
+        bf.decode_packed(bytes(encoded), decompress=False)
@@ -0,0 +1,124 @@
+"""Tests for the decode_packed string table and depth limits"""
+
+import io
+
+import pytest
+
+import b_fast
+
+RECORD = {"id": 1, "customer_name": "Ana", "tags": [["a"], ["b"]]}
+
+
+def test_defaults_accept_regular_frames():
+    bf = b_fast.BFast()
+
+    encoded = bf.encode_packed(RECORD, compress=False)
+
+    assert bf.decode_packed(encoded) == RECORD
+
+
+def nested(depth):
+    """A list nested `depth` levels deep."""
+    value = 0
+    for _ in range(depth):
+        value = [value]
+    return value
+
+
+def test_defaults_reject_oversized_frames():
+    many_keys = b_fast.BFast().encode_packed({f"k{i}": i for i in range(5000)}, False)
+    long_key = b_fast.BFast().encode_packed({"k" * 200: 1}, compress=False)
+    deep = b_fast.BFast().encode_packed(nested(80), compress=False)
+    bf = b_fast.BFast()
+
+    with pytest.raises(ValueError, match="more than max_keys \\(4096\\)"):
+        bf.decode_packed(many_keys)
+    with pytest.raises(ValueError, match="more than max_key_length \\(128\\)"):
+        bf.decode_packed(long_key)
+    with pytest.raises(RecursionError):
+        bf.decode_packed(deep)
+    assert bf.decode_packed(many_keys, max_keys=5000)["k0"] == 0
+    assert bf.decode_packed(long_key, max_key_length=255) == {"k" * 200: 1}
+    assert bf.decode_packed(deep, max_depth=128) == nested(80)
+
+
+def test_max_keys():
+    encoded = b_fast.BFast().encode_packed(RECORD, compress=False)
+
+    with pytest.raises(ValueError, match="3 entries, more than max_keys \\(2\\)"):
+        b_fast.BFast().decode_packed(encoded, max_keys=2)
+    assert b_fast.BFast().decode_packed(encoded, max_keys=3) == RECORD
+
+
+def test_max_keys_is_checked_before_the_table_is_read():
+    # Declares 65535 entries with none present
+    frame = b"BF\x00\x01\xff\xff"
+
+    with pytest.raises(ValueError, match="max_keys"):
+        b_fast.BFast().decode_packed(frame, max_keys=1000)
+
+
+def test_max_key_length():
+    encoded = b_fast.BFast().encode_packed(RECORD, compress=False)
+
+    with pytest.raises(ValueError, match="entry 1 is 13 bytes"):
+        b_fast.BFast().decode_packed(encoded, max_key_length=12)
+
+
+def test_max_depth():
+    encoded = b_fast.BFast().encode_packed(RECORD, compress=False)
+
+    with pytest.raises(RecursionError):
+        b_fast.BFast().decode_packed(encoded, max_depth=3)
+    assert b_fast.BFast().decode_packed(encoded, max_depth=4) == RECORD
+
+
+def test_max_depth_cannot_exceed_the_built_in_limit():
+    encoded = b_fast.BFast().encode_packed(RECORD, compress=False)
+
+    with pytest.raises(ValueError, match="max_depth cannot exceed 128"):
+        b_fast.BFast().decode_packed(encoded, max_depth=1000)
+
+
+def test_lazy_decoding_checks_the_limits():
+    encoded = b_fast.BFast().encode_packed([RECORD, RECORD], compress=False)
+
+    with pytest.raises(ValueError, match="max_keys"):
+        b_fast.BFast().decode_packed(encoded, lazy=True, max_keys=1)
+
+
+def read_from(encoded, tmp_path, **limits):
+    return b_fast.BFast().decode_from(io.BytesIO(encoded), **limits)
+
+
+def read_mmap(encoded, tmp_path, **limits):
+    path = tmp_path / "frame.bf"
+    path.write_bytes(encoded)
+    return list(b_fast.BFast().decode_mmap(path, **limits))
+
+
+def read_container(encoded, tmp_path, **limits):
+    sink = io.BytesIO()
+    b_fast.BFastWriter(sink).append(b_fast.BFast().decode_packed(encoded))
+    sink.seek(0)
+    return b_fast.BFastReader(sink, **limits).read_many()[0]
+
+
+def read_columns(encoded, tmp_path, **limits):
+    return b_fast.BFast().decode_columns(encoded, arrays=False, **limits)
+
+
+@pytest.mark.parametrize(
+    "read", [read_from, read_mmap, read_container, read_columns]
+)
+def test_every_entry_point_checks_the_limits(read, tmp_path):
+    rows = [{"id": i, "customer_name": "Ana"} for i in range(3)]
+    encoded = b_fast.BFast(columnar=read is read_columns).encode_packed(rows, True)
+
+    with pytest.raises(ValueError, match="max_keys"):
+        read(encoded, tmp_path, max_keys=1)
+    with pytest.raises(ValueError, match="max_key_length"):
+        read(encoded, tmp_path, max_key_length=4)
+    with pytest.raises(ValueError, match="max_depth cannot exceed 128"):
+        read(encoded, tmp_path, max_depth=1000)
+    assert read(encoded, tmp_path, max_keys=2)
@@ -0,0 +1,100 @@
+"""Tests for b_fast.count and b_fast.decode_range"""
+
+from dataclasses import dataclass
+
+import pytest
+
+import b_fast
+
+ORDERS = [{"id": i, "total": i * 1.5, "tags": ["a"] * (i % 3)} for i in range(100)]
+WINDOWS = [(0, 10), (40, 60), (95, 200), (-5, None), (None, 3), (60, 40), (None, None)]
+
+
+@dataclass
+class Point:
+    x: int
+    y: int
+
+
+@pytest.mark.parametrize("compress", [False, True])
+def test_count(compress):
+    bf = b_fast.BFast()
+
+    assert b_fast.count(bf.encode_packed(ORDERS, compress=compress)) == 100
+    assert b_fast.count(bf.encode_packed([], compress=compress)) == 0
+
+
+@pytest.mark.parametrize("window", WINDOWS)
+def test_decode_range(window):
+    bf = b_fast.BFast()
+    start, stop = window
+    encoded = bf.encode_packed(ORDERS, compress=True)
+
+    assert b_fast.decode_range(encoded, start, stop) == ORDERS[start:stop]
+
+
+def test_decode_range_records():
+    bf = b_fast.BFast()
+    points = [Point(i, -i) for i in range(50)]
+    encoded = bf.encode_packed(points, compress=False)
+
+    assert b_fast.count(encoded) == 50
+    assert b_fast.decode_range(encoded, 10, 12) == [
+        {"x": 10, "y": -10},
+        {"x": 11, "y": -11},
+    ]
+
+
+@pytest.mark.parametrize(
+    "options",
+    [{"dedup": True}, {"null_bitmap": True}, {"columnar": True}, {"pack_bools": True}],
+)
+def test_decode_range_other_layouts(options):
+    bf = b_fast.BFast(**options)
+    records = [{"id": i, "note": None, "ok": i % 2 == 0} for i in range(30)]
+    encoded = bf.encode_packed(records, compress=False)
+
+    assert b_fast.count(encoded) == 30
+    assert b_fast.decode_range(encoded, 5, 8) == records[5:8]
+
+
+@pytest.mark.parametrize(
+    "case",
+    [
+        ({}, True),
+        ({"dedup": True}, False),
+        ({"null_bitmap": True}, False),
+        ({"columnar": True}, False),
+        ({"pack_bools": True}, False),
+    ],
+)
+def test_decode_range_reads_past_the_window_only_when_decoding_in_full(case):
+    options, windowed = case
+    records = [{"id": i, "note": None, "ok": i % 2 == 0} for i in range(30)]
+    encoder = b_fast.BFast(**options)
+    encoded = bytearray(encoder.encode_packed(records, compress=False))
+    # Cut the end of the payload off, keeping the header length consistent
+    del encoded[-4:]
+    encoded[6:10] = (len(encoded) - 10).to_bytes(4, "little")
+
+    if windowed:
+        assert b_fast.decode_range(bytes(encoded), 5, 8) == records[5:8]
+    else:
+        with pytest.raises(ValueError):
+            b_fast.decode_range(bytes(encoded), 5, 8)
+
+
+def test_decode_range_keeps_metadata_out():
+    bf = b_fast.BFast()
+    encoded = bf.encode_packed(ORDERS, compress=False, metadata={"page": 1})
+
+    assert b_fast.count(encoded) == 100
+    assert b_fast.decode_range(encoded, 1, 2) == ORDERS[1:2]
+
+
+@pytest.mark.parametrize("function", [b_fast.count, b_fast.decode_range])
+def test_not_a_list(function):
+    encoded = b_fast.BFast().encode_packed({"id": 1}, compress=False)
+
+    with pytest.raises(ValueError, match="expects payloads encoded from a list"):
+        function(encoded)
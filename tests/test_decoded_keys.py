@@ -0,0 +1,50 @@
+"""Tests for the key objects a decoder reuses across frames"""
+
+import b_fast
+
+RECORD = {"user id": 1, "display name": "a", "tags": ["x"]}
+ROWS = [{"event id": i, "event kind": "click"} for i in range(20)]
+
+
+def test_keys_are_shared_across_frames():
+    bf = b_fast.BFast()
+    first = bf.decode_packed(bf.encode_packed(RECORD, compress=False))
+    second = bf.decode_packed(bf.encode_packed(dict(RECORD), compress=True))
+    assert first == second == RECORD
+    for a, b in zip(first, second):
+        assert a is b
+
+
+def test_keys_are_shared_by_records_and_frames():
+    bf = b_fast.BFast()
+    first = bf.decode_packed(bf.encode_packed(ROWS, compress=False))
+    second = bf.decode_packed(bf.encode_packed(ROWS, compress=False))
+    assert first == second == ROWS
+    keys = {id(key) for row in first + second for key in row}
+    assert len(keys) == 2
+
+
+def test_frames_from_another_encoder_share_keys():
+    bf = b_fast.BFast()
+    encoded = b_fast.BFast().encode_packed(RECORD, compress=False)
+    first = bf.decode_packed(encoded)
+    second = bf.decode_packed(bf.encode_packed(RECORD, compress=False))
+    assert [id(key) for key in first] == [id(key) for key in second]
+
+
+def test_lazy_and_immutable_decodes_share_keys():
+    bf = b_fast.BFast()
+    encoded = bf.encode_packed(ROWS, compress=False)
+    lazy = bf.decode_packed(encoded, lazy=True)
+    frozen = bf.decode_packed(encoded, immutable=True)
+    assert list(lazy[0]) == list(frozen[0]) == ["event id", "event kind"]
+    for a, b in zip(lazy[0], frozen[0]):
+        assert a is b
+
+
+def test_more_keys_than_are_kept_still_decode():
+    bf = b_fast.BFast()
+    for start in range(0, 5000, 100):
+        payload = {f"key {i}": i for i in range(start, start + 100)}
+        encoded = b_fast.BFast().encode_packed(payload, compress=True)
+        assert bf.decode_packed(encoded) == payload
@@ -0,0 +1,60 @@
+"""Tests for structural sharing of repeated subtrees"""
+
+import pytest
+
+import b_fast
+
+CONFIG = {"region": "us-east-1", "limits": {"cpu": 2, "memory": [512, 1024]}}
+
+
+def make_payload():
+    return {
+        "services": [{"name": f"svc-{i}", "config": dict(CONFIG)} for i in range(50)],
+        "default": dict(CONFIG),
+    }
+
+
+def test_dedup_shrinks_repeated_subtrees():
+    data = make_payload()
+    plain = b_fast.BFast().encode_packed(data, compress=False)
+    shared = b_fast.BFast(dedup=True).encode_packed(data, compress=False)
+
+    assert len(shared) < len(plain) // 2
+
+
+def test_dedup_roundtrip_copies_by_default():
+    bf = b_fast.BFast(dedup=True)
+    data = make_payload()
+
+    decoded = bf.decode_packed(bf.encode_packed(data, compress=True))
+
+    assert decoded == data
+    first, second = decoded["services"][0]["config"], decoded["services"][1]["config"]
+    assert first is not second
+    first["region"] = "changed"
+    assert second["region"] == "us-east-1"
+
+
+def test_dedup_shared_refs():
+    bf = b_fast.BFast(dedup=True)
+    data = make_payload()
+
+    decoded = bf.decode_packed(bf.encode_packed(data, compress=False), shared_refs=True)
+
+    assert decoded == data
+    assert decoded["services"][0]["config"] is decoded["services"][1]["config"]
+    assert decoded["default"] is decoded["services"][0]["config"]
+
+
+def test_dedup_nested_repeats():
+    bf = b_fast.BFast(dedup=True)
+    inner = {"a": [1, 2, 3], "b": {"c": "d"}}
+    data = [inner, {"x": inner, "y": [inner, inner]}, [[1, 2, 3], [1, 2, 3]]]
+
+    assert bf.decode_packed(bf.encode_packed(data, compress=False)) == data
+    encoded = bf.encode_packed(data, compress=False)
+    assert bf.decode_packed(encoded, shared_refs=True) == data
+
+
+if __name__ == "__main__":
+    pytest.main([__file__])
@@ -0,0 +1,124 @@
+"""Tests for b_fast.diff / b_fast.apply_patch"""
+
+import pytest
+
+import b_fast
+
+
+def apply_diff(old, new, compress=False):
+    bf = b_fast.BFast()
+    encoded = bf.encode_packed(old, compress=compress)
+    patched = b_fast.apply_patch(encoded, b_fast.diff(old, new))
+    return patched, bf.decode_packed(patched)
+
+
+def test_nested_field_update():
+    old = {"user": {"id": 1, "name": "Ana", "tags": ["a", "b"]}, "version": 1}
+    new = {"user": {"id": 1, "name": "Bia", "tags": ["a", "b"]}, "version": 2}
+
+    _, decoded = apply_diff(old, new)
+
+    assert decoded == new
+
+
+def test_added_and_removed_keys():
+    old = {"a": 1, "b": {"x": 1, "y": 2}, "gone": True}
+    new = {"a": 1, "b": {"x": 1, "z": [1, 2]}, "new_key": "hello"}
+
+    _, decoded = apply_diff(old, new)
+
+    assert decoded == new
+
+
+@pytest.mark.parametrize(
+    "old, new",
+    [
+        ([1, 2, 3], [1, 2, 3, 4, {"k": "v"}]),
+        ([1, 2, 3, 4, 5], [1, 2]),
+        ([1, 2, 3], [1, 20, 3]),
+        ([], ["first"]),
+        ([{"id": 1}, {"id": 2}], [{"id": 1, "done": True}]),
+    ],
+)
+def test_list_changes(old, new):
+    _, decoded = apply_diff(old, new)
+
+    assert decoded == new
+
+
+@pytest.mark.parametrize(
+    "old, new",
+    [(1, "one"), ({"a": 1}, [1]), (None, {"a": None}), (2.5, 2.5)],
+)
+def test_root_value_changes(old, new):
+    _, decoded = apply_diff(old, new)
+
+    assert decoded == new
+
+
+def test_unchanged_payload_gives_empty_patch():
+    data = {"items": [{"id": i, "price": i * 1.5} for i in range(20)]}
+
+    patch = b_fast.diff(data, data)
+    _, decoded = apply_diff(data, data)
+
+    assert b_fast.BFast().decode_packed(patch) == []
+    assert decoded == data
+
+
+def test_patch_is_smaller_than_snapshot():
+    old = {"rows": [{"id": i, "name": f"row-{i}", "score": i} for i in range(500)]}
+    new = {"rows": [dict(row) for row in old["rows"]]}
+    new["rows"][250]["score"] = -1
+
+    patch = b_fast.diff(old, new)
+    snapshot = b_fast.BFast().encode_packed(new, compress=False)
+
+    assert len(patch) < len(snapshot) // 50
+    _, decoded = apply_diff(old, new)
+    assert decoded == new
+
+
+def test_compressed_input_stays_compressed():
+    old = {"rows": [{"id": i, "label": "x" * 20} for i in range(100)]}
+    new = {"rows": old["rows"] + [{"id": 100, "label": "new"}]}
+
+    patched, decoded = apply_diff(old, new, compress=True)
+
+    assert patched[2] & 1  # compressed flag
+    assert decoded == new
+
+
+def test_patch_is_a_regular_frame():
+    patch = b_fast.diff({"a": 1, "b": 2}, {"a": 5})
+
+    ops = b_fast.BFast().decode_packed(patch)
+
+    assert ops == [[1, ["b"], None], [0, ["a"], 5]]
+
+
+def test_patch_for_a_different_document_fails():
+    patch = b_fast.diff({"a": {"b": 1}}, {"a": {"b": 2}})
+    encoded = b_fast.BFast().encode_packed({"x": 1}, compress=False)
+
+    with pytest.raises(ValueError, match="path not found"):
+        b_fast.apply_patch(encoded, patch)
+
+
+def test_dedup_frames_are_rejected():
+    bf = b_fast.BFast(dedup=True)
+    encoded = bf.encode_packed([{"a": 1}, {"a": 1}], compress=False)
+    patch = b_fast.diff([], [1])
+
+    with pytest.raises(ValueError, match="dedup"):
+        b_fast.apply_patch(encoded, patch)
+
+
+def test_malformed_patch_raises():
+    patch = b_fast.diff({"a": 1}, {"a": 5})
+    encoded = b_fast.BFast().encode_packed({"a": 1}, compress=False)
+    key = patch.rindex(b"\x50\x01\x00\x00\x00a")
+    malformed = patch[: key + 1] + b"\xff\xff\x00\x00" + patch[key + 5 :]
+
+    with pytest.raises(ValueError):
+        b_fast.apply_patch(encoded, malformed)
@@ -0,0 +1,97 @@
+"""Tests for dump_tokens"""
+
+import pytest
+
+import b_fast
+
+RECORD = {"id": 3, "name": "Ana", "tags": ["a", None, True, 2.5]}
+
+
+def test_header_and_strings():
+    encoded = b_fast.BFast().encode_packed(RECORD, compress=False)
+
+    listing = b_fast.dump_tokens(encoded)
+
+    assert listing["header"] == {
+        "version": 2,
+        "length": len(encoded) - 10,
+        "compressed": False,
+        "flags": [],
+        "schema_id": None,
+        "metadata_size": None,
+        "string_count": 3,
+    }
+    assert listing["strings"] == [(10, "id"), (13, "name"), (18, "tags")]
+
+
+def test_nested_tokens():
+    encoded = b_fast.BFast().encode_packed(RECORD, compress=False)
+
+    tokens = b_fast.dump_tokens(encoded)["tokens"]
+
+    assert tokens[0] == (23, "object", len(encoded) - 23, "3 fields")
+    assert tokens[1] == [
+        (24, "key", 4, '#0 "id"'),
+        (28, "int", 1, "3"),
+        (29, "key", 4, '#1 "name"'),
+        (33, "str", 8, '"Ana"'),
+        (41, "key", 4, '#2 "tags"'),
+        (45, "list", 22, "4 items"),
+        [
+            (50, "str", 6, '"a"'),
+            (56, "null", 1, "None"),
+            (57, "bool", 1, "True"),
+            (58, "float", 9, "2.5"),
+        ],
+    ]
+
+
+def test_columnar_frames_list_columns():
+    rows = [{"id": i, "status": "ok"} for i in range(100)]
+    encoded = b_fast.BFast(columnar=True).encode_packed(rows, compress=False)
+
+    listing = b_fast.dump_tokens(encoded)
+    columns = listing["tokens"][1]
+
+    assert listing["header"]["flags"] == ["columnar"]
+    assert listing["tokens"][0][3] == "100 rows, 2 columns"
+    assert [token[3] for token in columns[::2]] == ['"id" (delta)', '"status" (rle)']
+
+
+def test_compressed_offsets_refer_to_the_inflated_frame():
+    rows = [RECORD] * 50
+
+    listing = b_fast.dump_tokens(b_fast.BFast().encode_packed(rows, compress=True))
+
+    assert listing["header"]["compressed"] is True
+    assert listing["tokens"][0][:2] == (23, "list")
+
+
+def test_long_strings_are_cut_off():
+    encoded = b_fast.BFast().encode_packed("x" * 100, compress=False)
+
+    preview = b_fast.dump_tokens(encoded)["tokens"][0][3]
+
+    assert preview == '"' + "x" * 40 + '"...'
+
+
+def test_truncated_payload_ends_in_error_token():
+    encoded = b_fast.BFast().encode_packed([1, "abc", [2]], compress=False)
+
+    tokens = b_fast.dump_tokens(encoded[:-3])["tokens"]
+
+    assert tokens[1][:2] == [(15, "int", 1, "1"), (16, "str", 8, '"abc"')]
+    assert tokens[1][2][1] == "error"
+
+
+def test_trailing_bytes_are_reported():
+    encoded = b_fast.BFast().encode_packed([1], compress=False)
+
+    tokens = b_fast.dump_tokens(encoded + b"zz")["tokens"]
+
+    assert tokens[-1] == (16, "trailing", 2, "2 bytes")
+
+
+def test_truncated_header_fails():
+    with pytest.raises(ValueError, match="too small"):
+        b_fast.dump_tokens(b"BF\x00")
@@ -0,0 +1,71 @@
+"""Tests for the duplicate key policy of encoders and decode_packed"""
+
+import pytest
+
+import b_fast
+
+# 1 and "1" are different dict keys that both write the key "1"
+CLASHING = {1: "a", "x": 0, "1": "b"}
+RESOLVED = {"first": {"1": "a", "x": 0}, "last": {"1": "b", "x": 0}}
+POLICIES = ["first", "last"]
+
+
+class Lowercase(b_fast.BFast):
+    def on_key(self, key):
+        return key.lower()
+
+
+def unchecked_frame():
+    return b_fast.BFast().encode_packed(CLASHING, compress=False)
+
+
+def test_default_writes_duplicates_and_keeps_the_last():
+    assert b_fast.BFast().decode_packed(unchecked_frame()) == {"1": "b", "x": 0}
+
+
+@pytest.mark.parametrize("policy", POLICIES)
+def test_encoder_resolves_duplicates(policy):
+    encoder = b_fast.BFast(duplicate_keys=policy)
+    encoded = encoder.encode_packed(CLASHING, compress=False)
+    decoded = b_fast.BFast().decode_packed(encoded, duplicate_keys="error")
+    assert decoded == RESOLVED[policy]
+    assert list(decoded) == ["1", "x"]
+
+
+@pytest.mark.parametrize("policy", POLICIES)
+def test_decoder_resolves_duplicates(policy):
+    decoded = b_fast.BFast().decode_packed(unchecked_frame(), duplicate_keys=policy)
+    assert decoded == RESOLVED[policy]
+
+
+def test_encoder_error_policy():
+    encoder = b_fast.BFast(duplicate_keys="error")
+    with pytest.raises(ValueError, match="Duplicate key '1'"):
+        encoder.encode_packed({"nested": [CLASHING]}, compress=False)
+    assert encoder.encode_packed({"1": "a"}, compress=False)
+
+
+def test_decoder_error_policy():
+    with pytest.raises(ValueError, match="Duplicate key '1'"):
+        b_fast.BFast().decode_packed(unchecked_frame(), duplicate_keys="error")
+
+
+@pytest.mark.parametrize("options", [{"null_bitmap": True}, {"dedup": True}])
+def test_policy_applies_with_other_layouts(options):
+    encoder = b_fast.BFast(duplicate_keys="first", **options)
+    encoded = encoder.encode_packed([CLASHING], compress=True)
+    decoded = b_fast.BFast().decode_packed(encoded, duplicate_keys="error")
+    assert decoded == [RESOLVED["first"]]
+
+
+def test_keys_merged_by_a_hook():
+    encoder = Lowercase(duplicate_keys="last")
+    encoded = encoder.encode_packed({"Id": 1, "ID": 2, "name": "a"}, compress=False)
+    assert b_fast.BFast().decode_packed(encoded) == {"id": 2, "name": "a"}
+
+
+def test_unknown_policy_is_rejected():
+    with pytest.raises(ValueError, match="duplicate_keys"):
+        b_fast.BFast(duplicate_keys="merge")
+    with pytest.raises(ValueError, match="duplicate_keys"):
+        b_fast.BFast().decode_packed(unchecked_frame(), duplicate_keys="merge")
@@ -0,0 +1,109 @@
+"""Tests for encode_async"""
+
+import asyncio
+
+import pytest
+
+import b_fast
+
+ROWS = [{"id": i, "name": f"user{i}", "active": i % 2 == 0} for i in range(5000)]
+
+
+def run(coro):
+    return asyncio.run(coro)
+
+
+@pytest.mark.parametrize("compress", [False, True])
+def test_matches_encode_packed(compress):
+    async def main():
+        return await b_fast.encode_async(ROWS, compress=compress)
+
+    encoded = run(main())
+
+    assert encoded == b_fast.BFast().encode_packed(ROWS, compress=compress)
+    assert b_fast.BFast().decode_packed(encoded) == ROWS
+
+
+def test_uses_encoder_options():
+    encoder = b_fast.BFast(columnar=True)
+
+    async def main():
+        return await b_fast.encode_async(ROWS, encoder=encoder)
+
+    encoded = run(main())
+
+    assert "columnar" in b_fast.dump_tokens(encoded)["header"]["flags"]
+    assert encoder.encode_packed(ROWS, compress=False) == encoded
+
+
+def test_uses_encoder_hooks():
+    class Upper(b_fast.BFast):
+        def on_key(self, key):
+            return key.upper()
+
+    async def main():
+        return await b_fast.encode_async({"id": 1}, encoder=Upper())
+
+    encoded = run(main())
+
+    assert b_fast.BFast().decode_packed(encoded) == {"ID": 1}
+
+
+def test_loop_keeps_running():
+    ticks = []
+
+    async def ticker():
+        while True:
+            ticks.append(None)
+            await asyncio.sleep(0)
+
+    async def main():
+        task = asyncio.create_task(ticker())
+        encoded = await b_fast.encode_async(ROWS * 10, compress=True)
+        task.cancel()
+        return encoded
+
+    encoded = run(main())
+
+    assert b_fast.BFast().decode_packed(encoded) == ROWS * 10
+    assert ticks
+
+
+def test_concurrent_encodes():
+    async def main():
+        return await asyncio.gather(
+            *(b_fast.encode_async({"n": n}) for n in range(20))
+        )
+
+    results = run(main())
+
+    assert [b_fast.BFast().decode_packed(r) for r in results] == [
+        {"n": n} for n in range(20)
+    ]
+
+
+def test_errors_are_raised_on_await():
+    class Loop:
+        def __bfast__(self):
+            return self
+
+    async def main():
+        return await b_fast.encode_async(Loop())
+
+    with pytest.raises(RecursionError):
+        run(main())
+
+
+def test_cancelled_future_is_left_alone():
+    async def main():
+        future = b_fast.encode_async(ROWS)
+        future.cancel()
+        await asyncio.sleep(0.2)
+        return future.cancelled()
+
+    assert run(main())
+
+
+def test_requires_a_running_loop():
+    with pytest.raises(RuntimeError):
+        b_fast.encode_async(ROWS)
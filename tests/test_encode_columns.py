@@ -0,0 +1,179 @@
+"""Tests for BFast.encode_columns / decode_columns"""
+
+import pytest
+from pydantic import BaseModel
+
+import b_fast
+
+COLUMNS = {
+    "id": list(range(1000)),
+    "price": [i * 0.5 for i in range(1000)],
+    "active": [i % 3 == 0 for i in range(1000)],
+    "region": ["eu" if i % 2 else "us" for i in range(1000)],
+}
+
+
+class Reading(BaseModel):
+    sensor: str
+    seq: int
+    value: float
+
+
+READINGS = [Reading(sensor=f"s{i % 4}", seq=i * 7, value=i / 8) for i in range(50)]
+
+
+def test_rows_decode_as_records():
+    encoded = b_fast.BFast().encode_columns(COLUMNS)
+
+    decoded = b_fast.BFast().decode_packed(encoded)
+
+    assert len(decoded) == 1000
+    assert decoded[7] == {"id": 7, "price": 3.5, "active": False, "region": "eu"}
+
+
+def test_same_bytes_as_the_columnar_encoder():
+    records = [dict(zip(COLUMNS, row)) for row in zip(*COLUMNS.values())]
+
+    from_columns = b_fast.BFast().encode_columns(COLUMNS)
+    from_rows = b_fast.BFast(columnar=True).encode_packed(records, compress=False)
+
+    assert from_columns == from_rows
+
+
+def test_columns_roundtrip_as_lists():
+    encoded = b_fast.BFast().encode_columns(COLUMNS, compress=True)
+
+    decoded = b_fast.BFast().decode_columns(encoded, arrays=False)
+
+    assert decoded == COLUMNS
+
+
+def test_columnar_records_decode_as_columns():
+    records = [{"a": 1, "b": "x"}, {"a": 2, "b": "y"}]
+    encoded = b_fast.BFast(columnar=True).encode_packed(records, compress=False)
+
+    decoded = b_fast.BFast().decode_columns(encoded, arrays=False)
+
+    assert decoded == {"a": [1, 2], "b": ["x", "y"]}
+
+
+def test_empty_columns():
+    encoded = b_fast.BFast().encode_columns({"a": [], "b": []})
+
+    assert b_fast.BFast().decode_packed(encoded) == []
+    decoded = b_fast.BFast().decode_columns(encoded, arrays=False)
+    assert decoded == {"a": [], "b": []}
+
+
+def test_columns_of_different_lengths_are_rejected():
+    with pytest.raises(ValueError, match='"b" has 2 rows, expected 3'):
+        b_fast.BFast().encode_columns({"a": [1, 2, 3], "b": [1, 2]})
+
+
+def test_non_dict_input_is_rejected():
+    with pytest.raises(TypeError, match="dict of columns"):
+        b_fast.BFast().encode_columns([[1, 2], [3, 4]])
+
+
+def test_row_payloads_are_rejected():
+    encoded = b_fast.BFast().encode_packed([{"a": 1}], compress=False)
+
+    with pytest.raises(ValueError, match="encode_columns"):
+        b_fast.BFast().decode_columns(encoded, arrays=False)
+
+
+def test_numpy_columns_roundtrip_as_arrays():
+    np = pytest.importorskip("numpy")
+    columns = {
+        "id": np.arange(1000, dtype=np.int32),
+        "noise": np.random.default_rng(1).integers(-50, 50, 1000),
+        "price": np.linspace(0, 10, 1000),
+        "active": np.arange(1000) % 3 == 0,
+        "region": np.array(["eu", "us"] * 500, dtype=object),
+    }
+
+    encoded = b_fast.BFast().encode_columns(columns)
+    decoded = b_fast.BFast().decode_columns(encoded)
+
+    assert decoded["id"].dtype == np.int64
+    assert np.array_equal(decoded["id"], columns["id"])
+    assert np.array_equal(decoded["noise"], columns["noise"])
+    assert decoded["price"].dtype == np.float64
+    assert np.array_equal(decoded["price"], columns["price"])
+    assert decoded["active"].dtype == np.bool_
+    assert np.array_equal(decoded["active"], columns["active"])
+    assert decoded["region"] == columns["region"].tolist()
+
+
+def test_multidimensional_arrays_are_rejected():
+    np = pytest.importorskip("numpy")
+
+    with pytest.raises(ValueError, match="one-dimensional"):
+        b_fast.BFast().encode_columns({"m": np.zeros((2, 2))})
+
+
+def test_batch_frames_decode_as_columns():
+    encoded = b_fast.BFast().encode_packed(READINGS, compress=True)
+
+    decoded = b_fast.BFast().decode_columns(encoded, arrays=False)
+
+    assert decoded == {
+        "sensor": [r.sensor for r in READINGS],
+        "seq": [r.seq for r in READINGS],
+        "value": [r.value for r in READINGS],
+    }
+
+
+def test_batch_columns_of_mixed_types_stay_lists():
+    class Row:
+        def __init__(self, key):
+            self.key = key
+
+    rows = [Row(i) for i in range(10)] + [Row("x"), Row(2.5)]
+    encoded = b_fast.BFast().encode_packed(rows, compress=False)
+
+    decoded = b_fast.BFast().decode_columns(encoded, arrays=False, lazy=True)
+
+    assert decoded == {"key": list(range(10)) + ["x", 2.5]}
+
+
+@pytest.mark.parametrize("name", ["id", "price"])
+def test_numeric_columns_decode_lazily(name):
+    encoded = b_fast.BFast().encode_columns(COLUMNS)
+
+    decoded = b_fast.BFast().decode_columns(encoded, arrays=False, lazy=True)
+    column = decoded[name]
+
+    assert isinstance(column, b_fast.LazyColumn)
+    assert len(column) == 1000
+    assert column[7] == COLUMNS[name][7]
+    assert column[-1] == COLUMNS[name][-1]
+    assert column[10:20:3] == COLUMNS[name][10:20:3]
+    assert list(column) == column.to_list() == COLUMNS[name]
+    assert decoded["region"] == COLUMNS["region"]
+    with pytest.raises(IndexError):
+        column[1000]
+
+
+def test_lazy_columns_of_batch_frames():
+    encoded = b_fast.BFast().encode_packed(READINGS, compress=False)
+
+    decoded = b_fast.BFast().decode_columns(encoded, arrays=False, lazy=True)
+
+    assert decoded["seq"].dtype == "int64"
+    assert decoded["value"].dtype == "float64"
+    assert decoded["value"][9] == 9 / 8
+    assert repr(decoded["seq"]) == "LazyColumn(int64, len=50)"
+
+
+def test_batch_frames_decode_as_arrays():
+    np = pytest.importorskip("numpy")
+    encoded = b_fast.BFast().encode_packed(READINGS, compress=False)
+
+    decoded = b_fast.BFast().decode_columns(encoded)
+    lazy = b_fast.BFast().decode_columns(encoded, arrays=False, lazy=True)
+
+    assert decoded["seq"].dtype == np.int64
+    assert np.array_equal(decoded["seq"], [r.seq for r in READINGS])
+    assert np.array_equal(decoded["value"], lazy["value"].to_numpy())
+    assert decoded["sensor"] == [r.sensor for r in READINGS]
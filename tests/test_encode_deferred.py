@@ -0,0 +1,58 @@
+"""Tests for encode_deferred"""
+
+import pytest
+
+import b_fast
+
+ROWS = [{"id": i, "name": f"user{i}", "score": i * 0.5} for i in range(20000)]
+
+
+@pytest.mark.parametrize("compress", [False, True])
+def test_matches_encode_packed(compress):
+    pending = b_fast.BFast().encode_deferred(ROWS, compress=compress)
+
+    encoded = pending.result()
+
+    assert encoded == b_fast.BFast().encode_packed(ROWS, compress=compress)
+    assert pending.done()
+
+
+def test_result_is_cached():
+    pending = b_fast.BFast().encode_deferred(ROWS)
+
+    assert pending.result() is pending.result()
+
+
+def test_data_may_change_after_the_call():
+    rows = [dict(row) for row in ROWS[:100]]
+
+    pending = b_fast.BFast().encode_deferred(rows)
+    rows.clear()
+
+    assert b_fast.BFast().decode_packed(pending.result()) == ROWS[:100]
+
+
+def test_encoder_is_free_for_the_next_frame():
+    encoder = b_fast.BFast()
+
+    first = encoder.encode_deferred(ROWS)
+    second = encoder.encode_deferred({"n": 1})
+
+    assert b_fast.BFast().decode_packed(second.result(timeout=10)) == {"n": 1}
+    assert b_fast.BFast().decode_packed(first.result(timeout=10)) == ROWS
+
+
+def test_options():
+    pending = b_fast.BFast().encode_deferred(
+        {"rows": ROWS}, metadata={"producer": "api"}, sections=True
+    )
+
+    encoded = pending.result()
+
+    assert b_fast.metadata(encoded) == {"producer": "api"}
+    assert b_fast.get(encoded, "rows[3].name") == "user3"
+
+
+def test_errors_raise_immediately():
+    with pytest.raises(TypeError):
+        b_fast.BFast().encode_deferred(ROWS, sections=True)
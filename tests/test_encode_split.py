@@ -0,0 +1,53 @@
+"""Tests for BFast.encode_packed_split"""
+
+import pytest
+
+import b_fast
+
+RECORDS = [{"id": i, "name": f"User {i}", "tags": ["a"] * (i % 7)} for i in range(2000)]
+
+
+@pytest.mark.parametrize("compress", [False, True])
+def test_frames_stay_under_the_cap(compress):
+    frames = b_fast.BFast().encode_packed_split(RECORDS, 4096, compress=compress)
+
+    assert len(frames) > 1
+    assert all(len(frame) <= 4096 for frame in frames)
+    decoder = b_fast.BFast()
+    assert [r for frame in frames for r in decoder.decode_packed(frame)] == RECORDS
+
+
+def test_frames_are_filled():
+    frames = b_fast.BFast().encode_packed_split(RECORDS, 4096)
+
+    # Uncompressed frames are cut just before the record that would not fit
+    assert all(len(frame) > 4096 - 128 for frame in frames[:-1])
+
+
+def test_each_frame_has_its_own_string_table():
+    records = [{f"key{i}": i} for i in range(100)]
+
+    frames = b_fast.BFast().encode_packed_split(records, 256)
+
+    assert b"key0" not in frames[-1]
+    assert b_fast.BFast().decode_packed(frames[-1])[-1] == {"key99": 99}
+
+
+def test_small_input_is_one_frame():
+    encoder = b_fast.BFast()
+
+    frames = encoder.encode_packed_split(iter(RECORDS[:10]))
+
+    assert len(frames) == 1
+    assert encoder.decode_packed(frames[0]) == RECORDS[:10]
+
+
+def test_empty_input_has_no_frames():
+    assert b_fast.BFast().encode_packed_split([]) == []
+
+
+def test_oversized_record_is_rejected():
+    records = [{"id": 1}, {"blob": "x" * 1000}]
+
+    with pytest.raises(ValueError, match="Record 1 needs a"):
+        b_fast.BFast().encode_packed_split(records, 512)
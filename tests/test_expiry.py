@@ -0,0 +1,110 @@
+"""Tests for expires_at and b_fast.is_expired"""
+
+import io
+import time
+from datetime import datetime, timedelta, timezone
+
+import pytest
+
+import b_fast
+
+PAGE = {"title": "Home", "items": list(range(2000))}
+
+
+@pytest.mark.parametrize("compress", [False, True])
+def test_ttl_sets_expires_at(compress):
+    bf = b_fast.BFast()
+    before = time.time()
+
+    encoded = bf.encode_packed(PAGE, compress=compress, ttl=60)
+
+    expires_at = b_fast.metadata(encoded)["expires_at"]
+    assert before + 60 <= expires_at <= time.time() + 60
+    assert not b_fast.is_expired(encoded)
+    assert b_fast.is_expired(encoded, now=expires_at)
+    assert b_fast.is_expired(encoded, now=expires_at + 1)
+    assert bf.decode_packed(encoded, lazy=False) == PAGE
+
+
+def test_ttl_keeps_other_metadata():
+    bf = b_fast.BFast()
+    metadata = {"producer": "web"}
+
+    encoded = bf.encode_packed(PAGE, compress=False, metadata=metadata, ttl=0)
+
+    assert b_fast.metadata(encoded)["producer"] == "web"
+    assert metadata == {"producer": "web"}
+    assert b_fast.is_expired(encoded)
+
+
+def test_ttl_on_encode_to_and_encode_deferred():
+    bf = b_fast.BFast()
+    fp = io.BytesIO()
+
+    bf.encode_to(PAGE, fp, ttl=60)
+    deferred = bf.encode_deferred(PAGE, ttl=-0.0).result()
+
+    assert "expires_at" in b_fast.metadata(fp.getvalue())
+    assert not b_fast.is_expired(fp.getvalue())
+    assert b_fast.is_expired(deferred)
+
+
+def test_expires_at_datetime():
+    bf = b_fast.BFast()
+    expires_at = datetime(2030, 1, 1, tzinfo=timezone.utc)
+
+    encoded = bf.encode_packed(
+        PAGE, compress=False, metadata={"expires_at": expires_at}
+    )
+
+    assert not b_fast.is_expired(encoded, now=expires_at - timedelta(seconds=1))
+    assert b_fast.is_expired(encoded, now=expires_at)
+    assert b_fast.is_expired(encoded, now=expires_at.timestamp() + 1)
+
+
+def test_naive_datetimes_are_utc():
+    bf = b_fast.BFast()
+    expires_at = datetime(2030, 1, 1)
+    epoch = datetime(2030, 1, 1, tzinfo=timezone.utc).timestamp()
+
+    encoded = bf.encode_packed(
+        PAGE, compress=False, metadata={"expires_at": expires_at}
+    )
+
+    assert not b_fast.is_expired(encoded, now=epoch - 1)
+    assert b_fast.is_expired(encoded, now=epoch)
+    assert b_fast.is_expired(encoded, now=datetime(2030, 1, 1, 0, 0, 1))
+
+
+@pytest.mark.parametrize("metadata", [None, {"producer": "web"}, {"expires_at": None}])
+def test_frames_without_expires_at_never_expire(metadata):
+    bf = b_fast.BFast()
+
+    encoded = bf.encode_packed(PAGE, compress=True, metadata=metadata)
+
+    assert not b_fast.is_expired(encoded, now=float("inf"))
+
+
+@pytest.mark.parametrize("ttl", [-1, float("nan"), float("inf")])
+def test_bad_ttl(ttl):
+    bf = b_fast.BFast()
+
+    with pytest.raises(ValueError, match="ttl must be a non-negative"):
+        bf.encode_packed(PAGE, compress=False, ttl=ttl)
+
+
+def test_bad_expires_at():
+    bf = b_fast.BFast()
+    encoded = bf.encode_packed(
+        PAGE, compress=False, metadata={"expires_at": "tomorrow"}
+    )
+
+    with pytest.raises(TypeError, match="expires_at must be epoch seconds"):
+        b_fast.is_expired(encoded)
+    with pytest.raises(TypeError, match="now must be epoch seconds"):
+        b_fast.is_expired(bf.encode_packed(PAGE, compress=False, ttl=1), now="x")
+
+
+def test_not_a_frame():
+    with pytest.raises(ValueError):
+        b_fast.is_expired(b"nope")
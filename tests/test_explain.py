@@ -0,0 +1,78 @@
+"""Tests for explain"""
+
+import pytest
+
+import b_fast
+
+ROWS = [
+    {"id": i, "name": f"user-{i}", "email": None, "score": i * 0.5, "tags": ["a"]}
+    for i in range(100)
+]
+
+
+def test_categories_cover_every_byte():
+    encoded = b_fast.BFast().encode_packed(ROWS, compress=False)
+
+    report = b_fast.explain(encoded)
+
+    assert report.total == len(encoded)
+    assert report.compressed is None
+    assert sum(report.categories.values()) == len(encoded)
+
+
+def test_fields_are_summed_over_records():
+    report = b_fast.explain(ROWS)
+
+    # key ID (4) plus the value, per record
+    assert report.fields["email"] == 100 * (4 + 1)
+    assert report.fields["score"] == 100 * (4 + 9)
+    assert list(report.fields)[0] == "name"
+
+
+def test_categories_are_sorted_by_size():
+    report = b_fast.explain({"text": "x" * 1000, "n": 1, "flag": True})
+
+    assert list(report.categories)[0] == "strings"
+    assert report.categories["strings"] == 1005
+    assert report.categories["booleans"] == 1
+    assert report.fields == {"text": 1009, "n": 5, "flag": 5}
+
+
+def test_columnar_fields_are_columns():
+    encoded = b_fast.BFast(columnar=True).encode_packed(ROWS, compress=False)
+
+    report = b_fast.explain(encoded)
+
+    assert set(report.fields) == {"id", "name", "email", "score", "tags"}
+    assert sum(report.categories.values()) == len(encoded)
+
+
+def test_compressed_payload():
+    encoded = b_fast.BFast().encode_packed(ROWS, compress=True)
+
+    report = b_fast.explain(encoded)
+
+    assert report.compressed == len(encoded)
+    assert report.total == len(b_fast.BFast().encode_packed(ROWS, compress=False))
+
+
+def test_str_renders_tables():
+    text = str(b_fast.explain(ROWS))
+
+    assert text.startswith(f"{b_fast.explain(ROWS).total} bytes")
+    assert "field" in text and "type" in text
+    assert "tags" in text and "%" in text
+
+
+def test_scalar_has_no_fields():
+    report = b_fast.explain(5)
+
+    assert report.fields == {}
+    assert report.categories == {"overhead": 10, "numbers": 1}
+
+
+def test_malformed_payload_fails():
+    encoded = b_fast.BFast().encode_packed(ROWS, compress=False)
+
+    with pytest.raises(ValueError, match="end of buffer"):
+        b_fast.explain(encoded[:-10])
@@ -0,0 +1,84 @@
+"""Tests for extension types registered with b_fast.register_ext"""
+
+import struct
+
+import pytest
+
+import b_fast
+
+POINT = struct.Struct("<dd")
+
+
+class Point:
+    __slots__ = ("x", "y")
+
+    def __init__(self, x, y):
+        self.x, self.y = x, y
+
+    def __eq__(self, other):
+        return isinstance(other, Point) and (self.x, self.y) == (other.x, other.y)
+
+
+class Point3(Point):
+    __slots__ = ()
+
+
+def pack_point(point):
+    return POINT.pack(point.x, point.y)
+
+
+def unpack_point(data):
+    return Point(*POINT.unpack(data))
+
+
+b_fast.register_ext(1, Point, pack_point, unpack_point)
+
+
+def test_instances_roundtrip_through_their_extension():
+    bf = b_fast.BFast()
+    data = {"origin": Point(0.0, 0.0), "path": [Point(1.5, 2.0), Point(-1.0, 3.25)]}
+
+    encoded = bf.encode_packed(data, compress=False)
+
+    assert b"\xe0\x01\x10\x00\x00\x00" + POINT.pack(1.5, 2.0) in encoded
+    assert bf.decode_packed(encoded) == data
+
+
+def test_subclasses_use_the_extension():
+    bf = b_fast.BFast()
+
+    decoded = bf.decode_packed(bf.encode_packed([Point3(1.0, 2.0)], compress=True))
+
+    assert decoded == [Point(1.0, 2.0)]
+
+
+def test_records_with_extensions_stay_scannable():
+    records = [{"id": i, "at": Point(i, i)} for i in range(20)]
+    encoded = b_fast.BFast(columnar=True).encode_packed(records, compress=False)
+
+    assert b_fast.BFast().decode_packed(encoded) == records
+    assert b_fast.get(encoded, "[3].id") == 3
+    assert "'ext'" in repr(b_fast.dump_tokens(encoded))
+
+
+def test_unregistered_ids_are_rejected():
+    frame = b"BF\x00\x01\x00\x00" + b"\xe0\xfa\x02\x00\x00\x00ab"
+
+    with pytest.raises(ValueError, match="extension type 250"):
+        b_fast.BFast().decode_packed(frame)
+
+
+def test_encode_must_return_bytes():
+    class Broken:
+        pass
+
+    b_fast.register_ext(2, Broken, lambda obj: "text", lambda data: None)
+
+    with pytest.raises(TypeError, match="must return bytes, got str"):
+        b_fast.BFast().encode_packed([Broken()], compress=False)
+
+
+@pytest.mark.parametrize("ext_id", [-1, 256])
+def test_ext_id_must_fit_in_a_byte(ext_id):
+    with pytest.raises(ValueError, match="between 0 and 255"):
+        b_fast.register_ext(ext_id, Point, pack_point, unpack_point)
@@ -0,0 +1,102 @@
+"""Tests for per-field hints declared with Annotated"""
+
+import sys
+from dataclasses import dataclass
+from typing import List
+
+import pytest
+from pydantic import BaseModel
+
+import b_fast
+
+pytestmark = pytest.mark.skipif(
+    sys.version_info < (3, 9), reason="typing.Annotated needs Python 3.9"
+)
+
+if sys.version_info >= (3, 9):
+    from typing import Annotated
+
+    class Reading(BaseModel):
+        value: Annotated[float, b_fast.BFastF32()]
+        exact: float
+        series: Annotated[List[float], b_fast.BFastF32()]
+        payload: Annotated[bytes, b_fast.BFastRaw()]
+        other: bytes
+
+    @dataclass
+    class Sample:
+        value: Annotated[float, "unit: m", b_fast.BFastF32()]
+        exact: float
+
+
+def reading(i):
+    return Reading(
+        value=0.1 + i,
+        exact=0.1 + i,
+        series=[0.5, 0.1],
+        payload=b"p" * 100,
+        other=b"o" * 100,
+    )
+
+
+def tokens(encoded):
+    def walk(items):
+        for item in items:
+            if isinstance(item, list):
+                yield from walk(item)
+            else:
+                yield item[1]
+
+    return list(walk(b_fast.dump_tokens(encoded)["tokens"]))
+
+
+@pytest.mark.parametrize(
+    "case",
+    [
+        (1, {}),
+        (20, {}),
+        (20, {"null_bitmap": True}),
+        (20, {"columnar": True}),
+    ],
+)
+def test_hints(case):
+    count, options = case
+    blobs = []
+
+    def store(value):
+        blobs.append(value)
+        return str(len(blobs) - 1)
+
+    readings = [reading(i) for i in range(count)]
+    encoder = b_fast.BFast(blob_store=store, blob_threshold=10, **options)
+
+    encoded = encoder.encode_packed(readings, compress=False)
+
+    found = tokens(encoded)
+    assert found.count("float32") == count * 3
+    assert blobs == [b"o" * 100] * count
+    decoded = b_fast.BFast().decode_packed(
+        encoded, blob_resolver=lambda token: blobs[int(token)]
+    )
+    assert [row["payload"] for row in decoded] == [b"p" * 100] * count
+    assert decoded[0]["exact"] == 0.1
+
+
+def test_dataclass_with_other_metadata():
+    encoded = b_fast.BFast().encode_packed(Sample(0.1, 0.1), compress=False)
+
+    assert tokens(encoded).count("float32") == 1
+    assert b_fast.BFast().decode_packed(encoded)["value"] != 0.1
+
+
+def test_encoder_float32_still_applies():
+    encoder = b_fast.BFast(float32=True)
+
+    encoded = encoder.encode_packed(Sample(0.1, 0.1), compress=False)
+
+    assert tokens(encoded).count("float32") == 2
+
+
+def test_repr():
+    assert repr(b_fast.BFastF32()) == "BFastF32()"
+    assert repr(b_fast.BFastRaw()) == "BFastRaw()"
@@ -0,0 +1,141 @@
+"""Tests for b_fast.filter"""
+
+import datetime
+import math
+import operator
+
+import pytest
+
+import b_fast
+
+ORDERS = [
+    {
+        "id": i,
+        "status": ["active", "closed", "pending"][i % 3],
+        "total": i * 2.5,
+        "paid": i % 2 == 0,
+        "note": None if i % 4 else "gift",
+        "customer": {"name": f"c{i}", "tier": i % 5},
+        "placed": datetime.date(2024, 1, 1 + i),
+    }
+    for i in range(28)
+]
+OPS = {
+    "==": operator.eq,
+    "!=": operator.ne,
+    "<": operator.lt,
+    "<=": operator.le,
+    ">": operator.gt,
+    ">=": operator.ge,
+}
+ENCODERS = [
+    {},
+    {"columnar": True},
+    {"columnar": True, "column_stats": True},
+    {"dedup": True},
+    {"null_bitmap": True},
+]
+PREDICATES = [
+    ("status", "==", "active"),
+    ("status", ">", "b"),
+    ("id", "<", 10),
+    ("id", ">=", 26.5),
+    ("total", "<=", 5),
+    ("total", "==", 5),
+    ("paid", "==", True),
+    ("paid", "<", 1),
+    ("note", "==", None),
+    ("note", "!=", None),
+    ("note", "<", "z"),
+    ("customer.tier", "==", 4),
+    ("placed", "!=", "2024-01-01"),
+    ("placed", "==", 3),
+    ("missing", "!=", 1),
+    ("id", "==", math.nan),
+    ("id", "!=", math.nan),
+]
+
+
+def expected(records, field, op, value):
+    kept = []
+    for record in records:
+        found = record
+        for key in field.split("."):
+            found = found.get(key, KeyError) if isinstance(found, dict) else KeyError
+        if found is KeyError:
+            continue
+        try:
+            if OPS[op](found, value):
+                kept.append(record)
+        except TypeError:
+            pass
+    return kept
+
+
+@pytest.mark.parametrize("options", ENCODERS)
+def test_matches_python(options):
+    encoded = b_fast.BFast(**options).encode_packed(ORDERS, compress=True)
+
+    for predicate in PREDICATES:
+        result = b_fast.filter(encoded, *predicate)
+
+        decoded = b_fast.BFast().decode_packed(result)
+        assert decoded == expected(ORDERS, *predicate), (options, predicate)
+        assert b_fast.filter(encoded, *predicate, decode=True) == decoded
+
+
+@pytest.mark.parametrize("compress", [False, True])
+def test_keeps_compression_and_metadata(compress):
+    encoder = b_fast.BFast()
+    metadata = {"source": "orders"}
+    encoded = encoder.encode_packed(ORDERS, compress, metadata=metadata)
+
+    result = b_fast.filter(encoded, "status", "==", "closed")
+
+    assert b_fast.metadata(result) == metadata
+    assert bool(result[2] & 1) is compress
+    assert b_fast.count(result) == 9
+
+
+def test_copies_only_the_keys_in_use():
+    records = [{"id": 1, "a": 1}, {"id": 2, "b": 2}]
+    encoded = b_fast.BFast().encode_packed(records, compress=False)
+
+    result = b_fast.filter(encoded, "id", "==", 2)
+
+    assert b"a" not in result[10:]
+    assert b_fast.BFast().decode_packed(result) == [{"id": 2, "b": 2}]
+
+
+def test_pruned_frames_are_not_scanned():
+    encoder = b_fast.BFast(columnar=True, column_stats=True)
+    encoded = bytearray(encoder.encode_packed(ORDERS, compress=False))
+    # Damage the payload; only the header is read
+    encoded[-20:] = b"\xff" * 20
+
+    result = b_fast.filter(bytes(encoded), "status", "==", "refunded")
+
+    assert b_fast.BFast().decode_packed(result) == []
+
+
+@pytest.mark.parametrize(
+    "case",
+    [
+        ({"id": 1}, "==", "expects payloads encoded from a list"),
+        ([{"id": 1}], "~", "Unknown filter op"),
+    ],
+)
+def test_errors(case):
+    value, op, message = case
+    encoded = b_fast.BFast().encode_packed(value, compress=False)
+
+    with pytest.raises(ValueError, match=message):
+        b_fast.filter(encoded, "id", op, 1)
+
+
+@pytest.mark.parametrize("value", [2**64, datetime.date(2024, 1, 1), [1]])
+def test_unsupported_operands(value):
+    encoded = b_fast.BFast().encode_packed(ORDERS, compress=False)
+
+    with pytest.raises(ValueError, match="filter compares None, bools"):
+        b_fast.filter(encoded, "id", "==", value)
@@ -0,0 +1,103 @@
+"""Tests for flattening nested models"""
+
+from dataclasses import dataclass
+from typing import Optional
+
+import pytest
+from pydantic import BaseModel
+
+import b_fast
+
+
+class Geo(BaseModel):
+    lat: float
+
+
+class Address(BaseModel):
+    city: str
+    geo: Geo
+
+
+@dataclass
+class Item:
+    sku: str
+    qty: int
+
+
+class Order(BaseModel):
+    id: int
+    address: Optional[Address]
+    item: Item
+    meta: dict
+
+
+ORDERS = [
+    Order(
+        id=i,
+        address=Address(city="Recife", geo=Geo(lat=-8.0)),
+        item=Item("A", i),
+        meta={"source": "web"},
+    )
+    for i in range(20)
+]
+FLAT = [
+    {
+        "id": i,
+        "address.city": "Recife",
+        "address.geo": {"lat": -8.0},
+        "item.sku": "A",
+        "item.qty": i,
+        "meta": {"source": "web"},
+    }
+    for i in range(20)
+]
+FIELDS = ["id", "address.city", "address.geo", "item.sku", "item.qty", "meta"]
+SCHEMA = b_fast.BFastSchema(FIELDS, id=0xD000)
+
+
+@pytest.mark.parametrize("compress", [False, True])
+def test_columnar(compress):
+    encoder = b_fast.BFast(columnar=True, flatten=True)
+
+    encoded = encoder.encode_packed(ORDERS, compress=compress)
+
+    assert b_fast.BFast().decode_packed(encoded) == FLAT
+
+
+def test_schema():
+    encoder = b_fast.BFast(schema=SCHEMA, flatten=True, validate=True)
+
+    encoded = encoder.encode_packed(ORDERS, compress=False)
+
+    assert b_fast.BFast().decode_packed(encoded) == FLAT
+    assert b"city" not in encoded
+
+
+def test_root_record_and_none():
+    encoder = b_fast.BFast(schema=SCHEMA, flatten=True)
+    order = ORDERS[0].model_copy(update={"address": None})
+
+    encoded = encoder.encode_packed(order, compress=False)
+
+    assert b_fast.BFast().decode_packed(encoded) == {
+        "id": 0,
+        "address": None,
+        "item.sku": "A",
+        "item.qty": 0,
+        "meta": {"source": "web"},
+    }
+
+
+def test_split_frames():
+    encoder = b_fast.BFast(schema=SCHEMA, flatten=True)
+
+    frames = encoder.encode_packed_split(ORDERS, 400)
+
+    decoded = [row for frame in frames for row in b_fast.BFast().decode_packed(frame)]
+    assert len(frames) > 1
+    assert decoded == FLAT
+
+
+def test_requires_schema_or_columnar():
+    with pytest.raises(ValueError, match="flatten requires a schema or columnar"):
+        b_fast.BFast(flatten=True)
@@ -0,0 +1,67 @@
+"""Tests for BFast(float32=True)"""
+
+import struct
+
+import pytest
+
+import b_fast
+
+EMBEDDING = [i / 7 for i in range(64)]
+ROWS = [{"id": i, "score": i / 3} for i in range(20)]
+
+
+def as_f32(value):
+    return struct.unpack("<f", struct.pack("<f", value))[0]
+
+
+def test_floats_take_five_bytes():
+    wide = b_fast.BFast().encode_packed(EMBEDDING, compress=False)
+    narrow = b_fast.BFast(float32=True).encode_packed(EMBEDDING, compress=False)
+    assert len(wide) - len(narrow) == 4 * len(EMBEDDING)
+    assert b_fast.BFast().decode_packed(narrow) == [as_f32(v) for v in EMBEDDING]
+
+
+def test_default_keeps_f64():
+    bf = b_fast.BFast()
+    assert bf.decode_packed(bf.encode_packed(EMBEDDING, compress=False)) == EMBEDDING
+
+
+@pytest.mark.parametrize("value", [1e300, -1e300])
+def test_values_beyond_f32_stay_f64(value):
+    encoded = b_fast.BFast(float32=True).encode_packed([value], compress=False)
+    assert b_fast.BFast().decode_packed(encoded) == [value]
+
+
+def test_special_values():
+    encoder = b_fast.BFast(float32=True)
+    encoded = encoder.encode_packed([float("inf"), float("-inf"), 0.5], False)
+    decoded = b_fast.BFast().decode_packed(encoded)
+    assert decoded == [float("inf"), float("-inf"), 0.5]
+    encoder = b_fast.BFast(float32=True, non_finite="null")
+    encoded = encoder.encode_packed([float("nan")], compress=False)
+    decoded = b_fast.BFast().decode_packed(encoded)
+    assert decoded == [None]
+
+
+@pytest.mark.parametrize("options", [{}, {"columnar": True}, {"batch_threshold": 2}])
+def test_record_lists(options):
+    encoder = b_fast.BFast(float32=True, **options)
+    encoded = encoder.encode_packed(ROWS, compress=False)
+    expected = [{"id": i, "score": as_f32(i / 3)} for i in range(20)]
+    assert b_fast.BFast().decode_packed(encoded) == expected
+    assert b_fast.BFast().decode_packed(encoded, lazy=True)[4] == expected[4]
+
+
+def test_released_gil_path_matches():
+    payload = {"embedding": EMBEDDING}
+    encoder = b_fast.BFast(float32=True)
+    released = encoder.encode_packed(payload, compress=False, release_gil=True)
+    assert released == encoder.encode_packed(payload, compress=False)
+
+
+def test_tokens_and_queries():
+    encoder = b_fast.BFast(float32=True)
+    encoded = encoder.encode_packed({"scores": [0.5, 1.5]}, compress=False)
+    _, _, items = b_fast.dump_tokens(encoded)["tokens"][1]
+    assert items[0][1:] == ("float32", 5, "0.5")
+    assert b_fast.get(encoded, "scores[1]") == 1.5
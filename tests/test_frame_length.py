@@ -0,0 +1,129 @@
+"""Tests for the payload length in the frame header"""
+
+import io
+import struct
+
+import pytest
+
+import b_fast
+
+ROWS = [{"id": i, "name": f"user-{i}"} for i in range(50)]
+HEADER_SIZE = 10
+
+
+def version_1(frame):
+    """The same frame with the version 1 header, which has no length."""
+    return frame[:3] + b"\x01" + frame[4:6] + frame[HEADER_SIZE:]
+
+
+@pytest.mark.parametrize(
+    "options", [{}, {"schema": b_fast.BFastSchema(["id", "name"])}]
+)
+def test_header_records_the_rest_of_the_frame(options):
+    encoded = b_fast.BFast(**options).encode_packed(ROWS, compress=False)
+
+    assert encoded[3] == b_fast.FORMAT_VERSION == 2
+    assert struct.unpack_from("<I", encoded, 6)[0] == len(encoded) - HEADER_SIZE
+    assert b_fast.frame_length(encoded[:HEADER_SIZE]) == len(encoded)
+
+
+def test_frames_read_back_to_back_from_a_stream():
+    bf = b_fast.BFast()
+    frames = [bf.encode_packed(ROWS[:n], compress=False) for n in (0, 1, 50)]
+    stream = io.BytesIO(b"".join(frames))
+
+    read = []
+    while header := stream.read(HEADER_SIZE):
+        read.append(header + stream.read(b_fast.frame_length(header) - HEADER_SIZE))
+
+    assert read == frames
+
+
+@pytest.mark.parametrize("compression", ["lz4", "zstd", "snappy", "lz4-frame"])
+def test_compressed_frames_read_back_to_back_from_a_stream(compression):
+    bf = b_fast.BFast(compression=compression)
+    frames = [bf.encode_packed(ROWS[:n], compress=True) for n in (0, 20, 50)]
+    stream = io.BytesIO(b"".join(frames))
+
+    read = []
+    while header := stream.read(HEADER_SIZE):
+        read.append(header + stream.read(b_fast.frame_length(header) - HEADER_SIZE))
+
+    assert read == frames
+    assert [bf.decode_packed(frame) for frame in read] == [[], ROWS[:20], ROWS]
+
+
+def test_compressed_header_records_the_inflated_size():
+    bf = b_fast.BFast()
+    raw = bf.encode_packed(ROWS, compress=False)
+
+    packed = bf.encode_packed(ROWS, compress=True)
+
+    assert packed[2] & 1  # compressed flag
+    assert not raw[2] & 1
+    assert struct.unpack_from("<I", packed, HEADER_SIZE)[0] == len(raw)
+    assert b_fast.frame_length(packed) == len(packed) < len(raw)
+
+
+def test_inflated_size_is_checked():
+    packed = bytearray(b_fast.BFast().encode_packed(ROWS, compress=True))
+    struct.pack_into("<I", packed, HEADER_SIZE, 1)
+
+    with pytest.raises(ValueError, match="header records 1"):
+        b_fast.BFast().decode_packed(bytes(packed))
+    with pytest.raises(ValueError, match="Truncated compressed frame"):
+        b_fast.BFast().decode_packed(bytes(packed[:-1]))
+
+
+def test_lz4_frames_stream_behind_the_header():
+    bf = b_fast.BFast(compression="lz4-frame")
+    packed = bf.encode_packed(ROWS, compress=True)
+
+    assert bf.decode_from(io.BytesIO(packed)) == ROWS
+
+
+def test_metadata_and_batch_frames_count_every_section():
+    encoded = b_fast.BFast().encode_packed(ROWS, False, metadata={"trace": "t1"})
+    batch = b_fast.BFastBatchEncoder()
+    for row in ROWS:
+        batch.add(row)
+    batched = batch.finish()
+
+    assert b_fast.frame_length(encoded) == len(encoded)
+    assert b_fast.frame_length(batched) == len(batched)
+    assert b_fast.dump_tokens(encoded)["header"]["length"] == len(encoded) - HEADER_SIZE
+
+
+def test_rewritten_frames_get_their_new_length():
+    encoded = b_fast.BFast().encode_packed(ROWS, compress=False)
+
+    merged = b_fast.concat([encoded, encoded], compress=False)
+    part = b_fast.slice(encoded, 0, 5)
+
+    assert b_fast.frame_length(merged) == len(merged)
+    assert b_fast.frame_length(part) == len(part)
+
+
+def test_version_1_frames_still_decode():
+    encoder = b_fast.BFast(schema=b_fast.BFastSchema(["id", "name"]))
+    old = version_1(encoder.encode_packed(ROWS, compress=False))
+
+    assert b_fast.BFast().decode_packed(old) == ROWS
+    assert b_fast.get(old, "[3].name") == "user-3"
+    assert b_fast.dump_tokens(old)["header"]["length"] is None
+    with pytest.raises(ValueError, match="do not record their length"):
+        b_fast.frame_length(old)
+
+
+def test_unknown_versions_are_rejected():
+    frame = bytearray(b_fast.BFast().encode_packed(ROWS, compress=False))
+    frame[3] = 9
+
+    with pytest.raises(ValueError, match="Unsupported B-FAST format version 9"):
+        b_fast.BFast().decode_packed(bytes(frame))
+
+
+@pytest.mark.parametrize("header", [b"BF\x00\x02\x00\x00\x00", b"BC\x01\xff", b""])
+def test_frame_length_needs_a_whole_header(header):
+    with pytest.raises(ValueError):
+        b_fast.frame_length(header)
@@ -0,0 +1,60 @@
+"""Tests for b_fast.from_json_bytes"""
+
+import json
+
+import pytest
+
+import b_fast
+
+DOCUMENTS = [
+    b'{"id": 1, "tags": ["a", "b"], "price": 9.5, "active": true, "note": null}',
+    b'[{"id": 1, "name": "Ana"}, {"id": 2, "name": "Bo"}]',
+    b'"caf\\u00e9 \\ud83d\\ude00"',
+    b"-42",
+    b"[]",
+    b"{}",
+    b'{"big": 9223372036854775807, "small": -9223372036854775808, "exp": 1e3}',
+    b'{"nested": {"deep": [[1, [2, {"x": []}]]]}}',
+    json.dumps({f"key{i}": i for i in range(100)}).encode(),
+]
+
+
+@pytest.mark.parametrize("document", DOCUMENTS)
+def test_matches_encode_packed(document):
+    for compress in (False, True):
+        encoded = b_fast.from_json_bytes(document, compress=compress)
+
+        expected = b_fast.BFast().encode_packed(json.loads(document), compress)
+        assert encoded == expected
+        assert b_fast.BFast().decode_packed(encoded) == json.loads(document)
+
+
+@pytest.mark.parametrize(
+    "case",
+    [
+        (b'{"a": 1, "b": 2, "a": 3}', {"a": 3, "b": 2}),
+        (
+            json.dumps({f"k{i}": i for i in range(40)})[:-1].encode() + b', "k3": -1}',
+            {**{f"k{i}": i for i in range(40)}, "k3": -1},
+        ),
+    ],
+)
+def test_repeated_keys_keep_the_last_value(case):
+    document, expected = case
+
+    decoded = b_fast.BFast().decode_packed(b_fast.from_json_bytes(document))
+
+    assert decoded == expected
+    assert list(decoded) == list(expected)
+
+
+def test_integers_beyond_i64_become_floats():
+    encoded = b_fast.from_json_bytes(b"[18446744073709551615]")
+
+    assert b_fast.BFast().decode_packed(encoded) == [float(2**64 - 1)]
+
+
+@pytest.mark.parametrize("document", [b"{", b"[1,]", b"NaN", b"1e400", b"\xff"])
+def test_invalid_json(document):
+    with pytest.raises(ValueError, match="Invalid JSON"):
+        b_fast.from_json_bytes(document)
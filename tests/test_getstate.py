@@ -0,0 +1,108 @@
+"""Tests for the __getstate__/__setstate__ pickle protocol"""
+
+import threading
+
+from pydantic import BaseModel
+
+import b_fast
+
+
+class Connection:
+    def __init__(self, host, port):
+        self.host = host
+        self.port = port
+        self.lock = threading.Lock()
+        self.cache = {"resolved": "10.0.0.1"}
+
+    def __getstate__(self):
+        return {"host": self.host, "port": self.port}
+
+    def __setstate__(self, state):
+        self.__init__(state["host"], state["port"])
+
+
+class Counter:
+    def __init__(self, count):
+        self.count = count
+        self.history = list(range(count))
+
+    def __getstate__(self):
+        return {"count": self.count}
+
+
+class Plain:
+    def __init__(self):
+        self.a = 1
+
+
+class Both:
+    def __getstate__(self):
+        return "state"
+
+    def __bfast__(self):
+        return "bfast"
+
+    @classmethod
+    def __bfast_restore__(cls, value):
+        return value.upper()
+
+
+class User(BaseModel):
+    id: int
+    name: str
+
+
+def test_encodes_getstate_instead_of_dict():
+    bf = b_fast.BFast()
+
+    encoded = bf.encode_packed({"db": Connection("db.local", 5432)}, compress=False)
+    decoded = bf.decode_packed(encoded)
+
+    assert decoded == {"db": {"host": "db.local", "port": 5432}}
+
+
+def test_setstate_restores_allowed_classes():
+    bf = b_fast.BFast()
+
+    encoded = bf.encode_packed([Connection("db.local", 5432)], compress=False)
+    decoded = bf.decode_packed(encoded, allowed_classes=[Connection])
+
+    conn = decoded[0]
+    assert isinstance(conn, Connection)
+    assert (conn.host, conn.port) == ("db.local", 5432)
+    assert conn.lock.acquire(blocking=False)
+
+
+def test_without_setstate_updates_dict():
+    bf = b_fast.BFast()
+
+    encoded = bf.encode_packed(Counter(3), compress=False)
+    counter = bf.decode_packed(encoded, allowed_classes=[Counter])
+
+    assert isinstance(counter, Counter)
+    assert counter.__dict__ == {"count": 3}
+
+
+def test_default_getstate_keeps_dict_encoding():
+    bf = b_fast.BFast()
+
+    encoded = bf.encode_packed(Plain(), compress=False)
+
+    assert bf.decode_packed(encoded, allowed_classes=[Plain]) == {"a": 1}
+
+
+def test_bfast_protocol_takes_precedence():
+    bf = b_fast.BFast()
+
+    encoded = bf.encode_packed(Both(), compress=False)
+
+    assert bf.decode_packed(encoded) == "bfast"
+    assert bf.decode_packed(encoded, allowed_classes=[Both]) == "BFAST"
+
+
+def test_pydantic_models_keep_field_encoding():
+    bf = b_fast.BFast()
+
+    encoded = bf.encode_packed(User(id=1, name="Ana"), compress=False)
+
+    assert bf.decode_packed(encoded) == {"id": 1, "name": "Ana"}
@@ -0,0 +1,64 @@
+"""Tests for b_fast.hash"""
+
+import datetime
+import os
+import subprocess
+import sys
+import uuid
+from decimal import Decimal
+
+import b_fast
+
+
+class Point:
+    def __init__(self, x, y):
+        self.x = x
+        self.y = y
+
+
+def test_hash_is_16_bytes_and_deterministic():
+    data = {"id": 1, "when": datetime.date(2024, 1, 1), "ref": uuid.UUID(int=5)}
+
+    digest = b_fast.hash(data)
+
+    assert isinstance(digest, bytes)
+    assert len(digest) == 16
+    assert b_fast.hash(data) == digest
+
+
+def test_hash_ignores_dict_order():
+    a = {"name": "x", "price": Decimal("1.50"), "tags": {"b": 1, "a": 2}}
+    b = {"tags": {"a": 2, "b": 1}, "price": Decimal("1.50"), "name": "x"}
+
+    assert b_fast.hash(a) == b_fast.hash(b)
+
+
+def test_hash_of_objects_matches_dicts():
+    points = [Point(i, -i) for i in range(20)]
+
+    assert b_fast.hash(points) == b_fast.hash([{"y": -i, "x": i} for i in range(20)])
+
+
+def test_hash_distinguishes_values():
+    digests = {
+        b_fast.hash(value)
+        for value in [1, 2, "1", 1.0, [1], {"a": 1}, {"a": 2}, {"b": 1}, None, b"1"]
+    }
+
+    assert len(digests) == 10
+
+
+def test_hash_is_stable_across_processes():
+    code = "import b_fast; print(b_fast.hash({'s': {'alpha', 'beta', 'gamma'}}).hex())"
+    env = dict(os.environ, PYTHONPATH=os.pathsep.join(sys.path))
+
+    outputs = set()
+    for seed in ("1", "2", "3"):
+        env["PYTHONHASHSEED"] = seed
+        result = subprocess.run(
+            [sys.executable, "-c", code], env=env, capture_output=True, check=True
+        )
+        outputs.add(result.stdout.strip())
+
+    expected = b_fast.hash({"s": {"gamma", "beta", "alpha"}}).hex()
+    assert outputs == {expected.encode()}
@@ -0,0 +1,134 @@
+"""Tests for the encoding hooks of BFast subclasses"""
+
+from decimal import Decimal
+
+import pytest
+from pydantic import BaseModel
+
+import b_fast
+
+
+class Slotted:
+    __slots__ = ("x",)
+
+    def __init__(self, x):
+        self.x = x
+
+    def __str__(self):
+        return f"Slotted({self.x})"
+
+
+class User(BaseModel):
+    id: int
+    name: str
+
+
+class Rounding(b_fast.BFast):
+    def on_value(self, value):
+        return round(value, 1) if isinstance(value, float) else value
+
+
+class UpperKeys(b_fast.BFast):
+    def on_key(self, key):
+        return str(key).upper()
+
+
+class SlotsAsDicts(b_fast.BFast):
+    def on_unknown_type(self, obj):
+        if isinstance(obj, Slotted):
+            return {"x": obj.x}
+        return obj
+
+
+def test_on_value_replaces_nested_values():
+    data = {"a": 1.234, "b": [2.26, "x", {"c": 3.99}]}
+
+    encoded = Rounding().encode_packed(data, compress=False)
+
+    assert b_fast.BFast().decode_packed(encoded) == {
+        "a": 1.2,
+        "b": [2.3, "x", {"c": 4.0}],
+    }
+
+
+def test_on_key_renames_keys_of_dicts_and_models():
+    users = [User(id=i, name=f"u{i}") for i in range(20)]
+    encoder = UpperKeys()
+    decoder = b_fast.BFast()
+
+    nested = encoder.encode_packed({"a": {"b": 1}}, compress=False)
+    models = encoder.encode_packed(users, compress=False)
+
+    assert decoder.decode_packed(nested) == {"A": {"B": 1}}
+    assert decoder.decode_packed(models)[3] == {"ID": 3, "NAME": "u3"}
+
+
+def test_on_unknown_type_replaces_objects_without_a_form():
+    data = [Slotted(1), Slotted(Slotted(2))]
+    bf = b_fast.BFast()
+
+    hooked = SlotsAsDicts().encode_packed(data, compress=False)
+    plain = bf.encode_packed(data, compress=False)
+
+    assert bf.decode_packed(hooked) == [{"x": 1}, {"x": {"x": 2}}]
+    assert bf.decode_packed(plain) == ["Slotted(1)", "Slotted(Slotted(2))"]
+
+
+def test_unknown_type_hook_may_decline():
+    class Declining(b_fast.BFast):
+        def on_unknown_type(self, obj):
+            return obj
+
+    encoded = Declining().encode_packed([Slotted(5)], compress=False)
+
+    assert b_fast.BFast().decode_packed(encoded) == ["Slotted(5)"]
+
+
+def test_hooks_apply_to_every_encode_method(tmp_path):
+    encoder = Rounding(columnar=True, pack_bools=True)
+    records = [{"v": i / 3, "ok": True} for i in range(10)]
+    expected = [{"v": round(i / 3, 1), "ok": True} for i in range(10)]
+
+    path = tmp_path / "records.bf"
+    with open(path, "wb") as fp:
+        encoder.encode_to(records, fp)
+    pending = encoder.encode_deferred(records, compress=False)
+    frames = encoder.encode_packed_split(records, 64)
+
+    decoder = b_fast.BFast()
+    assert decoder.decode_packed(path.read_bytes()) == expected
+    assert decoder.decode_packed(pending.result()) == expected
+    assert [r for f in frames for r in decoder.decode_packed(f)] == expected
+
+
+def test_hooks_see_the_instance():
+    class Scaled(b_fast.BFast):
+        factor = 10
+
+        def on_value(self, value):
+            if isinstance(value, Decimal):
+                return int(value * self.factor)
+            return value
+
+    encoded = Scaled().encode_packed({"price": Decimal("1.5")}, compress=False)
+
+    assert b_fast.BFast().decode_packed(encoded) == {"price": 15}
+
+
+def test_hook_errors_propagate():
+    class Failing(b_fast.BFast):
+        def on_key(self, key):
+            raise KeyError(key)
+
+    with pytest.raises(KeyError):
+        Failing().encode_packed({"a": 1}, compress=False)
+
+
+def test_subclasses_without_hooks_keep_the_batch_path():
+    class Plain(b_fast.BFast):
+        pass
+
+    users = [User(id=i, name=f"u{i}") for i in range(20)]
+
+    assert b_fast.fingerprint(Plain().encode_packed(users, compress=False))
+    assert not b_fast.fingerprint(UpperKeys().encode_packed(users, compress=False))
@@ -0,0 +1,97 @@
+"""Tests for decode_packed(immutable=True)"""
+
+from types import MappingProxyType
+
+import pytest
+
+import b_fast
+
+DATA = {"users": [{"id": 1, "tags": ["a", "b"]}, {"id": 2, "tags": []}], "ok": True}
+
+
+def frozen(data, compress=False, **kwargs):
+    encoded = b_fast.BFast(**kwargs).encode_packed(data, compress=compress)
+    return b_fast.BFast().decode_packed(encoded, immutable=True)
+
+
+def test_lists_and_dicts_are_read_only():
+    decoded = frozen(DATA)
+
+    assert isinstance(decoded, MappingProxyType)
+    assert isinstance(decoded["users"], tuple)
+    assert isinstance(decoded["users"][0], MappingProxyType)
+    assert decoded["users"][0]["tags"] == ("a", "b")
+    assert decoded["users"][1]["tags"] == ()
+    assert decoded["ok"] is True
+
+
+def test_mutation_raises():
+    decoded = frozen(DATA)
+
+    with pytest.raises(TypeError):
+        decoded["ok"] = False
+    with pytest.raises(TypeError):
+        decoded["users"][0]["id"] = 3
+    with pytest.raises(AttributeError):
+        decoded["users"][0]["tags"].append("c")
+
+
+def test_default_stays_mutable():
+    encoded = b_fast.BFast().encode_packed(DATA, compress=False)
+
+    assert b_fast.BFast().decode_packed(encoded) == DATA
+
+
+@pytest.mark.parametrize(
+    "kwargs",
+    [{"columnar": True}, {"null_bitmap": True}, {"pack_bools": True}],
+)
+def test_encoder_options(kwargs):
+    rows = [{"id": i, "name": None, "flags": [True, False]} for i in range(8)]
+
+    decoded = frozen(rows, compress=True, **kwargs)
+
+    assert isinstance(decoded, tuple)
+    assert all(isinstance(row, MappingProxyType) for row in decoded)
+    assert decoded[3]["flags"] == (True, False)
+    assert [dict(row) for row in decoded] == [
+        {**row, "flags": (True, False)} for row in rows
+    ]
+
+
+def test_float_arrays_are_tuples():
+    decoded = frozen([1.5, 2.5, 3.5])
+
+    assert decoded == (1.5, 2.5, 3.5)
+
+
+def test_back_references_share_one_object():
+    tags = ["x", "y", "z"]
+
+    decoded = frozen([{"tags": tags}, {"tags": tags}], dedup=True)
+
+    assert decoded[0]["tags"] == ("x", "y", "z")
+    assert decoded[0]["tags"] is decoded[1]["tags"]
+
+
+def test_reader_schema():
+    encoded = b_fast.BFast().encode_packed(
+        [{"id": 1, "old": [2]}], compress=False
+    )
+    reader = b_fast.BFastSchema(["id", "new"], aliases={"new": "old"})
+
+    rows = b_fast.BFast().decode_packed(encoded, schema=reader, immutable=True)
+
+    assert isinstance(rows, tuple)
+    assert isinstance(rows[0], MappingProxyType)
+    assert dict(rows[0]) == {"id": 1, "new": (2,)}
+
+
+def test_lazy_elements():
+    encoded = b_fast.BFast().encode_packed(DATA["users"], compress=False)
+
+    rows = b_fast.BFast().decode_packed(encoded, lazy=True, immutable=True)
+
+    assert isinstance(rows, b_fast.LazyList)
+    assert isinstance(rows[0], MappingProxyType)
+    assert rows[0]["tags"] == ("a", "b")
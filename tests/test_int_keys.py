@@ -0,0 +1,109 @@
+"""Tests for integer-keyed dicts"""
+
+import pytest
+
+import b_fast
+
+USERS = {1001: {"name": "Ana"}, 1002: {"name": "Bia"}, -7: {"name": "Caio"}}
+
+
+def test_keys_keep_their_type():
+    bf = b_fast.BFast()
+
+    encoded = bf.encode_packed(USERS, compress=False)
+    decoded = bf.decode_packed(encoded)
+
+    assert decoded == USERS
+    assert list(decoded) == [1001, 1002, -7]
+
+
+def test_keys_bypass_the_string_table():
+    data = {i: i for i in range(100, 200)}
+    encoded = b_fast.BFast().encode_packed(data, compress=False)
+
+    listing = b_fast.dump_tokens(encoded)
+
+    assert listing["header"]["string_count"] == 0
+
+
+def test_large_and_small_keys():
+    data = {0: "zero", 7: "seven", 2**62: "big", -(2**63): "min"}
+    bf = b_fast.BFast()
+
+    encoded = bf.encode_packed(data, compress=False)
+
+    assert bf.decode_packed(encoded) == data
+
+
+def test_mixed_and_bool_keys_use_the_string_table():
+    bf = b_fast.BFast()
+
+    mixed = bf.encode_packed({1: "a", "b": 2}, compress=False)
+    boolean = bf.encode_packed({True: "yes"}, compress=False)
+    huge = bf.encode_packed({2**70: "huge"}, compress=False)
+
+    assert bf.decode_packed(mixed) == {"1": "a", "b": 2}
+    assert bf.decode_packed(boolean) == {"True": "yes"}
+    assert bf.decode_packed(huge) == {str(2**70): "huge"}
+
+
+def test_empty_dict_stays_an_object():
+    bf = b_fast.BFast()
+
+    encoded = bf.encode_packed({}, compress=False)
+
+    assert bf.decode_packed(encoded) == {}
+
+
+@pytest.mark.parametrize(
+    "kwargs", [{"dedup": True}, {"columnar": True}, {"null_bitmap": True}]
+)
+def test_encoder_options(kwargs):
+    data = [{"id": 1, "scores": {10: 0.5, 20: None}}] * 3 + [{7: "x"}, {7: "y"}]
+
+    encoded = b_fast.BFast(**kwargs).encode_packed(data, compress=False)
+
+    assert b_fast.BFast().decode_packed(encoded) == data
+
+
+def test_hash_ignores_key_order():
+    assert b_fast.hash({3: "c", 1: "a"}) == b_fast.hash({1: "a", 3: "c"})
+
+
+def test_immutable_and_lazy():
+    encoded = b_fast.BFast().encode_packed([USERS], compress=False)
+
+    frozen = b_fast.BFast().decode_packed(encoded, immutable=True)
+    lazy = b_fast.BFast().decode_packed(encoded, lazy=True)
+
+    assert frozen[0][1001]["name"] == "Ana"
+    assert lazy[0] == USERS
+
+
+def test_get_by_key():
+    encoded = b_fast.BFast().encode_packed({"users": USERS}, compress=False)
+
+    assert b_fast.get(encoded, "users.1002.name") == "Bia"
+    assert b_fast.get(encoded, "users[1001]") == {"name": "Ana"}
+    assert b_fast.get(encoded, "users.-7.name") == "Caio"
+
+
+def test_diff_and_patch():
+    old = {"users": USERS}
+    new = {"users": {**USERS, 1003: {"name": "Davi"}}}
+    encoded = b_fast.BFast().encode_packed(old, compress=False)
+
+    patched = b_fast.apply_patch(encoded, b_fast.diff(old, new))
+
+    assert b_fast.BFast().decode_packed(patched) == new
+
+
+def test_dump_tokens():
+    encoded = b_fast.BFast().encode_packed({5: True}, compress=False)
+
+    tokens = b_fast.dump_tokens(encoded)["tokens"]
+
+    assert tokens == [
+        (10, "int_object", 7, "1 entries"),
+        [(15, "key", 1, "5"), (16, "bool", 1, "True")],
+    ]
@@ -0,0 +1,42 @@
+"""Tests for the content-addressed helpers of the redis feature"""
+
+import pytest
+
+import b_fast
+
+pytestmark = pytest.mark.skipif(
+    not hasattr(b_fast, "encode_keyed"), reason="built without the redis feature"
+)
+
+USER = {"id": 7, "name": "Ana", "roles": ["admin", "dev"] * 50}
+
+
+def test_key_suffix_is_the_content_hash():
+    suffix, payload = b_fast.encode_keyed(USER)
+
+    assert suffix == b_fast.hash(USER).hex()
+    assert b_fast.BFast().decode_packed(payload) == USER
+
+
+def test_equal_values_share_a_key():
+    reordered = {"roles": USER["roles"], "name": "Ana", "id": 7}
+
+    assert b_fast.encode_keyed(reordered) == b_fast.encode_keyed(USER)
+
+
+def test_compression_keeps_the_key():
+    plain, _ = b_fast.encode_keyed(USER)
+    suffix, payload = b_fast.encode_keyed(USER, compress=True)
+
+    assert suffix == plain
+    assert payload[2] & 1  # compressed flag
+    assert b_fast.decode_keyed(f"users:{suffix}", payload) == USER
+
+
+def test_decode_checks_the_key():
+    suffix, payload = b_fast.encode_keyed(USER)
+    _, other = b_fast.encode_keyed({"id": 8})
+
+    assert b_fast.decode_keyed("users:" + suffix, payload) == USER
+    with pytest.raises(ValueError, match="does not match"):
+        b_fast.decode_keyed("users:" + suffix, other)
@@ -0,0 +1,90 @@
+"""Tests for decode_packed(lazy=True)"""
+
+import pytest
+
+import b_fast
+
+ROWS = [{"id": i, "name": f"user-{i}", "tags": ["a"] * (i % 3)} for i in range(1000)]
+
+
+def lazy(data, compress=False, **kwargs):
+    encoded = b_fast.BFast(**kwargs).encode_packed(data, compress=compress)
+    return b_fast.BFast().decode_packed(encoded, lazy=True)
+
+
+def test_elements_decode_on_access():
+    rows = lazy(ROWS)
+
+    assert isinstance(rows, b_fast.LazyList)
+    assert len(rows) == 1000
+    assert rows.decoded == 0
+    assert rows[500] == ROWS[500]
+    assert rows[-1] == ROWS[-1]
+    assert rows.decoded == 2
+
+
+def test_elements_are_cached():
+    rows = lazy(ROWS)
+
+    first = rows[7]
+    first["name"] = "changed"
+
+    assert rows[7] is first
+    assert rows[7]["name"] == "changed"
+
+
+@pytest.mark.parametrize(
+    "key", [slice(10, 20), slice(None, None, -100), slice(-5, None), slice(50, 10)]
+)
+def test_slicing(key):
+    rows = lazy(ROWS, compress=True)
+
+    assert rows[key] == ROWS[key]
+
+
+def test_iteration_and_to_list():
+    rows = lazy(ROWS)
+
+    assert list(rows) == ROWS
+    assert rows.to_list() == ROWS
+    assert rows.decoded == len(ROWS)
+
+
+def test_index_out_of_range():
+    rows = lazy([1, 2, 3])
+
+    with pytest.raises(IndexError):
+        rows[3]
+    with pytest.raises(IndexError):
+        rows[-4]
+
+
+@pytest.mark.parametrize("options", [{"null_bitmap": True}, {"schema": None}])
+def test_layouts_with_self_contained_elements(options):
+    data = [{"id": i, "email": None} for i in range(20)]
+
+    assert lazy(data, **options).to_list() == data
+
+
+@pytest.mark.parametrize(
+    "data, options",
+    [
+        ({"rows": [1, 2]}, {}),
+        ([[1, 2], [1, 2]], {"dedup": True}),
+        ([{"id": i} for i in range(20)], {"columnar": True}),
+    ],
+)
+def test_other_payloads_decode_as_usual(data, options):
+    decoded = lazy(data, **options)
+
+    assert not isinstance(decoded, b_fast.LazyList)
+    assert decoded == data
+
+
+def test_reader_schema_applies_per_element():
+    encoded = b_fast.BFast().encode_packed([{"id": 1, "old": 2}], compress=False)
+    reader = b_fast.BFastSchema(["id", "new"], aliases={"new": "old"})
+
+    rows = b_fast.BFast().decode_packed(encoded, lazy=True, schema=reader)
+
+    assert rows[0] == {"id": 1, "new": 2}
@@ -0,0 +1,127 @@
+"""Tests for encoder memory: usage, shrinking and the buffer cap"""
+
+import array
+
+import pytest
+
+import b_fast
+
+HUGE = {"rows": [{"id": i, "name": "x" * 100} for i in range(20_000)]}
+SMALL = {"id": 1, "name": "a"}
+ROWS = [{"id": i} for i in range(50)]
+PARTS = ["work_buffer", "string_table", "key_cache", "dedup_spans"]
+
+
+def test_usage_lists_every_part():
+    usage = b_fast.BFast().memory_usage()
+
+    assert set(usage) == set(PARTS) | {"string_table_entries", "total"}
+    assert usage["total"] == sum(usage[part] for part in PARTS)
+    assert usage["string_table_entries"] == 0
+
+
+def test_buffer_keeps_the_largest_payload_until_shrunk():
+    bf = b_fast.BFast(shrink_factor=None)
+    baseline = bf.memory_usage()["work_buffer"]
+
+    bf.encode_packed(HUGE, compress=True)
+    grown = bf.memory_usage()
+
+    released = bf.shrink_to_fit()
+
+    assert grown["work_buffer"] > len(str(HUGE)) // 2 > baseline
+    assert released >= grown["work_buffer"] - baseline
+    assert bf.memory_usage()["work_buffer"] <= baseline
+    assert bf.memory_usage()["total"] == grown["total"] - released
+
+
+def test_keys_survive_a_shrink():
+    bf = b_fast.BFast()
+    bf.encode_packed(SMALL, compress=False)
+
+    bf.shrink_to_fit()
+    encoded = bf.encode_packed(SMALL, compress=True)
+
+    assert bf.memory_usage()["string_table_entries"] == 2
+    assert bf.decode_packed(encoded) == SMALL
+
+
+def test_shrinking_twice_releases_nothing_more():
+    bf = b_fast.BFast(dedup=True)
+    bf.encode_packed(HUGE, compress=True)
+
+    bf.shrink_to_fit()
+
+    assert bf.shrink_to_fit() == 0
+
+
+class Row:
+    def __init__(self, i):
+        self.id = i
+        self.name = "x" * 100
+
+
+def test_payloads_within_the_cap_encode():
+    bf = b_fast.BFast(max_buffer_bytes=4096)
+
+    assert bf.decode_packed(bf.encode_packed(SMALL, compress=True)) == SMALL
+
+
+@pytest.mark.parametrize(
+    "data",
+    [
+        HUGE,
+        "x" * 1_000_000,
+        [b"y" * 1000] * 1000,
+        [Row(i) for i in range(10_000)],
+        array.array("d", range(200_000)),
+        [array.array("i", range(100))] * 1000,
+    ],
+)
+def test_payloads_over_the_cap_fail(data):
+    bf = b_fast.BFast(max_buffer_bytes=100_000)
+
+    with pytest.raises(ValueError, match="max_buffer_bytes=100000"):
+        bf.encode_packed(data, compress=True)
+
+    assert bf.memory_usage()["work_buffer"] <= 100_000
+    assert bf.decode_packed(bf.encode_packed(SMALL, compress=False)) == SMALL
+
+
+def test_released_encodes_are_capped():
+    bf = b_fast.BFast(max_buffer_bytes=100_000)
+
+    with pytest.raises(ValueError, match="max_buffer_bytes"):
+        bf.encode_packed(HUGE, True, release_gil=True)
+
+    assert bf.memory_usage()["work_buffer"] <= 100_000
+
+
+def test_buffer_shrinks_back_after_a_large_payload():
+    bf = b_fast.BFast()
+    for _ in range(10):
+        bf.encode_packed(ROWS, compress=True)
+
+    bf.encode_packed(HUGE, compress=True)
+    after_huge = bf.memory_usage()["work_buffer"]
+    for _ in range(50):
+        bf.encode_packed(ROWS, compress=True)
+
+    assert after_huge < len(bf.encode_packed(HUGE, compress=False)) // 4
+    assert bf.memory_usage()["work_buffer"] <= 16_384
+    assert bf.decode_packed(bf.encode_packed(ROWS, compress=True)) == ROWS
+
+
+def test_steady_payloads_keep_their_buffer():
+    bf = b_fast.BFast()
+    bf.encode_packed(HUGE, compress=True)
+    grown = bf.memory_usage()["work_buffer"]
+
+    bf.encode_packed(HUGE, compress=True)
+
+    assert bf.memory_usage()["work_buffer"] == grown
+
+
+def test_shrink_factor_must_be_positive():
+    with pytest.raises(ValueError, match="at least 1"):
+        b_fast.BFast(shrink_factor=0)
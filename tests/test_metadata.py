@@ -0,0 +1,85 @@
+"""Tests for the frame metadata section"""
+
+import io
+
+import pytest
+
+import b_fast
+
+META = {"producer": "billing", "schema_version": 3, "trace_id": "7f3a"}
+ROWS = [{"id": i, "name": f"user{i}"} for i in range(20)]
+
+
+def encode_with_metadata(data, compress=False, **kwargs):
+    return b_fast.BFast(**kwargs).encode_packed(data, compress=compress, metadata=META)
+
+
+@pytest.mark.parametrize("compress", [False, True])
+def test_roundtrip(compress):
+    encoded = encode_with_metadata(ROWS * 50, compress=compress)
+
+    assert b_fast.metadata(encoded) == META
+    assert b_fast.BFast().decode_packed(encoded) == ROWS * 50
+
+
+def test_absent_metadata_is_none():
+    encoded = b_fast.BFast().encode_packed(ROWS, compress=False)
+
+    assert b_fast.metadata(encoded) is None
+
+
+def test_metadata_keeps_its_own_strings():
+    encoded = encode_with_metadata({"producer": "api"})
+
+    assert b_fast.metadata(encoded) == META
+    assert b_fast.dump_tokens(encoded)["strings"][0][1] == "producer"
+
+
+def test_schema_and_metadata():
+    schema = b_fast.BFastSchema(["id", "name"])
+    encoded = encode_with_metadata(ROWS, schema=schema)
+
+    assert b_fast.metadata(encoded) == META
+    assert b_fast.BFast().decode_packed(encoded, schema=schema) == ROWS
+
+
+def test_query_helpers_skip_the_section():
+    encoded = encode_with_metadata(ROWS)
+
+    assert b_fast.get(encoded, "[3].name") == "user3"
+    assert b_fast.BFast().decode_packed(encoded, lazy=True)[5] == ROWS[5]
+    assert b_fast.metadata(b_fast.slice(encoded, 2, 4)) == META
+    assert b_fast.BFast().decode_packed(b_fast.slice(encoded, 2, 4)) == ROWS[2:4]
+
+
+def test_patches_keep_the_section():
+    encoded = encode_with_metadata({"count": 1})
+
+    patched = b_fast.apply_patch(encoded, b_fast.diff({"count": 1}, {"count": 2}))
+
+    assert b_fast.metadata(patched) == META
+    assert b_fast.BFast().decode_packed(patched) == {"count": 2}
+
+
+def test_dump_tokens_header():
+    encoded = encode_with_metadata(ROWS)
+    size = len(b_fast.BFast().encode_packed(META, compress=False))
+
+    header = b_fast.dump_tokens(encoded)["header"]
+
+    assert header["flags"] == ["metadata"]
+    assert header["metadata_size"] == size
+    assert b_fast.dump_tokens(encoded)["strings"][0] == (10 + 4 + size, "id")
+
+
+def test_encode_to():
+    sink = io.BytesIO()
+
+    b_fast.BFast().encode_to(ROWS, sink, metadata=META)
+
+    assert b_fast.metadata(sink.getvalue()) == META
+
+
+def test_metadata_must_be_a_dict():
+    with pytest.raises(TypeError):
+        b_fast.BFast().encode_packed(ROWS, compress=False, metadata=["trace"])
@@ -0,0 +1,95 @@
+"""Tests for the non_finite policy of encoders"""
+
+import math
+from decimal import Decimal
+
+import pytest
+
+import b_fast
+
+SPECIAL = [float("nan"), float("inf"), float("-inf")]
+PAYLOAD = {"score": 1.5, "ratio": float("nan"), "limit": float("inf")}
+ROWS = [{"id": i, "score": float("nan") if i % 3 else i / 2} for i in range(20)]
+
+
+def test_allow_is_the_default():
+    bf = b_fast.BFast()
+
+    decoded = bf.decode_packed(bf.encode_packed(PAYLOAD, compress=True))
+
+    assert math.isnan(decoded["ratio"]) and decoded["limit"] == math.inf
+
+
+def test_null_writes_none():
+    encoder = b_fast.BFast(non_finite="null")
+    decoder = b_fast.BFast()
+
+    record = encoder.encode_packed(PAYLOAD, compress=True)
+    values = encoder.encode_packed(SPECIAL + [0.5], compress=True)
+
+    assert decoder.decode_packed(record) == {
+        "score": 1.5,
+        "ratio": None,
+        "limit": None,
+    }
+    assert decoder.decode_packed(values) == [None, None, None, 0.5]
+
+
+@pytest.mark.parametrize("value", SPECIAL)
+def test_raise_refuses_the_value(value):
+    encoder = b_fast.BFast(non_finite="raise")
+    with pytest.raises(ValueError, match="non_finite"):
+        encoder.encode_packed({"nested": [1.0, value]}, compress=False)
+    assert encoder.encode_packed({"nested": [1.0]}, compress=False)
+
+
+@pytest.mark.parametrize("options", [{}, {"columnar": True}, {"batch_threshold": 2}])
+def test_record_lists(options):
+    encoded = b_fast.BFast(non_finite="null", **options).encode_packed(ROWS, True)
+    decoded = b_fast.BFast().decode_packed(encoded)
+    assert [row["score"] for row in decoded] == [
+        None if i % 3 else i / 2 for i in range(20)
+    ]
+    encoder = b_fast.BFast(non_finite="raise", **options)
+    with pytest.raises(ValueError, match="non_finite"):
+        encoder.encode_packed(ROWS, compress=False)
+
+
+def test_released_gil_path():
+    encoded = b_fast.BFast(non_finite="null").encode_packed(
+        PAYLOAD, compress=False, release_gil=True
+    )
+    assert b_fast.BFast().decode_packed(encoded)["ratio"] is None
+
+
+@pytest.mark.parametrize(
+    "case",
+    [
+        ({"non_finite": "null"}, [1.0, float("nan")], [1.0, None]),
+        ({"float32": True}, [1.0, 0.5], [1.0, 0.5]),
+    ],
+)
+def test_numpy_fallback_keeps_dedup_references(case):
+    numpy = pytest.importorskip("numpy")
+    options, scores, expected = case
+    repeated = {"tags": ["a", "b"], "meta": {"x": 1}}
+    data = {"scores": numpy.array(scores), "first": repeated, "second": repeated}
+
+    encoded = b_fast.BFast(dedup=True, **options).encode_packed(data, compress=True)
+    decoded = b_fast.BFast().decode_packed(encoded)
+
+    assert decoded == {"scores": expected, "first": repeated, "second": repeated}
+
+
+def test_decimal_as_float():
+    encoder = b_fast.BFast(decimal_as_float=True, non_finite="null")
+
+    encoded = encoder.encode_packed({"amount": Decimal("NaN")}, compress=True)
+    decoded = b_fast.BFast().decode_packed(encoded)
+
+    assert decoded == {"amount": None}
+
+
+def test_unknown_policy_is_rejected():
+    with pytest.raises(ValueError, match="non_finite"):
+        b_fast.BFast(non_finite="zero")
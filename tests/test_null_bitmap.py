@@ -0,0 +1,113 @@
+"""Tests for BFast(null_bitmap=True)"""
+
+from dataclasses import dataclass
+from typing import Optional
+
+import pytest
+
+import b_fast
+
+FIELDS = ["ts", "host", "cpu", "mem", "gpu", "disk", "net", "error", "region", "note"]
+SPARSE = [
+    {
+        "ts": i,
+        "host": "web-1",
+        "cpu": i * 0.5 if i % 4 == 0 else None,
+        "mem": None,
+        "gpu": None,
+        "disk": None,
+        "net": None,
+        "error": "timeout" if i % 10 == 0 else None,
+        "region": None,
+        "note": None,
+    }
+    for i in range(100)
+]
+
+
+@dataclass
+class Reading:
+    sensor: str
+    value: Optional[float] = None
+    unit: Optional[str] = None
+
+
+def test_sparse_records_roundtrip():
+    encoded = b_fast.BFast(null_bitmap=True).encode_packed(SPARSE, compress=False)
+    decoded = b_fast.BFast().decode_packed(encoded)
+
+    assert decoded == SPARSE
+    assert list(decoded[0]) == FIELDS
+
+
+def test_sparse_records_shrink_with_schema():
+    plain = b_fast.BFast(schema=b_fast.BFastSchema(FIELDS))
+    plain_size = len(plain.encode_packed(SPARSE, compress=False))
+
+    encoder = b_fast.BFast(null_bitmap=True, schema=b_fast.BFastSchema(FIELDS))
+    encoded = encoder.encode_packed(SPARSE, compress=False)
+    decoded = b_fast.BFast().decode_packed(encoded)
+
+    assert len(encoded) < plain_size // 2
+    assert decoded == SPARSE
+
+
+@pytest.mark.parametrize(
+    "data",
+    [
+        {},
+        {"a": None},
+        {"a": 1, "b": {"c": None, "d": [None, {"e": None}]}},
+        [{"x": None, "y": 2}, {"y": None, "x": 1, "z": None}],
+        [{"k": i, "opt": None if i % 2 else i} for i in range(20)],
+    ],
+)
+def test_mixed_shapes(data):
+    encoded = b_fast.BFast(null_bitmap=True).encode_packed(data, compress=False)
+    decoded = b_fast.BFast().decode_packed(encoded)
+
+    assert decoded == data
+
+
+def test_model_batches():
+    readings = [Reading("s%d" % i, value=i or None) for i in range(30)]
+
+    encoded = b_fast.BFast(null_bitmap=True).encode_packed(readings, compress=False)
+    decoded = b_fast.BFast().decode_packed(encoded)
+
+    assert decoded[0] == {"sensor": "s0", "value": None, "unit": None}
+    assert decoded[5] == {"sensor": "s5", "value": 5, "unit": None}
+
+
+def test_compressed_roundtrip():
+    bf = b_fast.BFast(null_bitmap=True)
+
+    encoded = bf.encode_packed(SPARSE, compress=True)
+
+    assert b_fast.BFast().decode_packed(encoded) == SPARSE
+
+
+def test_combined_with_dedup():
+    record = {"a": None, "b": [1, 2], "c": None}
+    data = [record, {"nested": record}, record]
+
+    encoder = b_fast.BFast(null_bitmap=True, dedup=True)
+    encoded = encoder.encode_packed(data, compress=False)
+    decoded = b_fast.BFast().decode_packed(encoded)
+
+    assert decoded == data
+
+
+def test_query_falls_back_to_full_decode():
+    encoded = b_fast.BFast(null_bitmap=True).encode_packed(SPARSE, compress=False)
+
+    assert b_fast.get(encoded, "[10].error") == "timeout"
+    assert b_fast.get(encoded, "[11].error", "missing") is None
+    assert b_fast.get(encoded, "[11].nope", "missing") == "missing"
+
+
+def test_byte_level_rewrites_are_rejected():
+    encoded = b_fast.BFast(null_bitmap=True).encode_packed(SPARSE, compress=False)
+
+    with pytest.raises(ValueError, match="null_bitmap"):
+        b_fast.slice(encoded, 0, 10)
@@ -0,0 +1,86 @@
+"""Tests for bit-packed booleans (pack_bools and columnar bool columns)"""
+
+import pytest
+
+import b_fast
+
+FLAGS = [i % 3 == 0 for i in range(1000)]
+
+
+def test_bool_list_is_packed():
+    plain = b_fast.BFast().encode_packed(FLAGS, compress=False)
+
+    encoded = b_fast.BFast(pack_bools=True).encode_packed(FLAGS, compress=False)
+    decoded = b_fast.BFast().decode_packed(encoded)
+
+    assert decoded == FLAGS
+    assert all(type(value) is bool for value in decoded)
+    assert len(encoded) < len(plain) // 7
+
+
+@pytest.mark.parametrize(
+    "data",
+    [
+        [True],
+        [False] * 9,
+        [True, 1, False],
+        [True, None],
+        [],
+        {"a": [True, False], "b": [[False, True, True]]},
+    ],
+)
+def test_mixed_and_edge_cases(data):
+    encoded = b_fast.BFast(pack_bools=True).encode_packed(data, compress=False)
+    decoded = b_fast.BFast().decode_packed(encoded)
+
+    assert decoded == data
+    assert decoded.__class__ is data.__class__
+
+
+def test_packed_list_is_independent_per_decode():
+    data = {"x": [True, True], "y": [True, True]}
+    encoded = b_fast.BFast(pack_bools=True).encode_packed(data, compress=False)
+    decoded = b_fast.BFast().decode_packed(encoded)
+    decoded["x"].append(False)
+
+    assert decoded["y"] == [True, True]
+
+
+def test_bool_columns_are_packed():
+    rows = [{"id": i, "active": i % 2 == 0, "admin": i % 7 == 0} for i in range(800)]
+    plain = b_fast.BFast(columnar=True).encode_packed(rows, compress=False)
+    unpacked = b_fast.BFast(columnar=True).encode_packed(
+        [dict(row, active=int(row["active"])) for row in rows], compress=False
+    )
+
+    encoded = b_fast.BFast(columnar=True).encode_packed(rows, compress=False)
+    decoded = b_fast.BFast().decode_packed(encoded)
+
+    assert decoded == rows
+    assert type(decoded[0]["active"]) is bool
+    assert len(plain) < len(unpacked) - 600
+
+
+def test_constant_bool_column_prefers_runs():
+    rows = [{"id": i, "deleted": False} for i in range(5000)]
+
+    encoded = b_fast.BFast(columnar=True).encode_packed(rows, compress=False)
+    decoded = b_fast.BFast().decode_packed(encoded)
+
+    assert decoded == rows
+    assert len(encoded) < 5000 * 9 + 100
+
+
+def test_query_falls_back_to_full_decode():
+    encoder = b_fast.BFast(pack_bools=True)
+    encoded = encoder.encode_packed({"flags": FLAGS}, compress=False)
+
+    assert b_fast.get(encoded, "flags[3]") is True
+    assert b_fast.get(encoded, "flags[4]") is False
+
+
+def test_byte_level_rewrites_are_rejected():
+    encoded = b_fast.BFast(pack_bools=True).encode_packed(FLAGS, compress=False)
+
+    with pytest.raises(ValueError, match="pack_bools"):
+        b_fast.slice(encoded, 0, 10)
@@ -0,0 +1,97 @@
+"""Tests for pandas scalar and column support"""
+
+import sys
+from datetime import timedelta
+
+import numpy as np
+import pytest
+
+import b_fast
+
+pd = pytest.importorskip("pandas")
+
+
+def test_timestamp_keeps_nanoseconds():
+    bf = b_fast.BFast()
+    ts = pd.Timestamp("2024-01-15 10:30:45.123456789")
+
+    decoded = bf.decode_packed(bf.encode_packed({"ts": ts}, compress=False))
+
+    assert isinstance(decoded["ts"], pd.Timestamp)
+    assert decoded["ts"] == ts
+    assert decoded["ts"].nanosecond == 789
+
+
+@pytest.mark.parametrize("tz", [None, "UTC", "Asia/Kolkata"])
+def test_timestamp_is_written_as_epoch_nanoseconds(tz):
+    ts = pd.Timestamp("2024-01-15 10:30:45.123456789", tz=tz)
+
+    encoded = b_fast.BFast().encode_packed(ts, compress=False)
+
+    assert b"\xd1\xff\xff\xff\xff" + ts.value.to_bytes(8, "little") in encoded
+    assert b_fast.BFast().decode_packed(encoded) == ts
+
+
+def test_timestamp_decodes_to_iso_string_without_pandas(monkeypatch):
+    ts = pd.Timestamp("2024-01-15 10:30:45.123456789")
+    encoded = b_fast.BFast().encode_packed(ts, compress=False)
+    monkeypatch.setitem(sys.modules, "pandas", None)
+
+    decoded = b_fast.BFast().decode_packed(encoded)
+
+    assert decoded == "2024-01-15T10:30:45.123456789"
+
+
+def test_nat_becomes_none():
+    bf = b_fast.BFast()
+    decoded = bf.decode_packed(bf.encode_packed({"ts": pd.NaT}, compress=False))
+    assert decoded == {"ts": None}
+
+
+def test_timedelta_roundtrip():
+    bf = b_fast.BFast()
+    data = {"py": timedelta(days=2, seconds=3), "pd": pd.Timedelta(nanoseconds=1500)}
+
+    decoded = bf.decode_packed(bf.encode_packed(data, compress=False))
+
+    # Plain timedeltas keep their str() form
+    assert decoded["py"] == "2 days, 0:00:03"
+    assert decoded["pd"] == pd.Timedelta(nanoseconds=1500)
+
+
+def test_numeric_series_is_typed_column():
+    bf = b_fast.BFast()
+    series = pd.Series([1, 2, 3], dtype="int32")
+
+    decoded = bf.decode_packed(bf.encode_packed({"col": series}, compress=False))
+
+    assert decoded["col"].dtype == np.int32
+    np.testing.assert_array_equal(decoded["col"], series.to_numpy())
+
+
+def test_series_index_is_dropped():
+    bf = b_fast.BFast()
+    series = pd.Series([4, 5], index=["a", "b"], name="qty")
+
+    decoded = bf.decode_packed(bf.encode_packed(series, compress=False))
+    as_dict = bf.decode_packed(bf.encode_packed(series.to_dict(), compress=False))
+
+    np.testing.assert_array_equal(decoded, [4, 5])
+    assert as_dict == {"a": 4, "b": 5}
+
+
+def test_object_series_and_index():
+    bf = b_fast.BFast()
+    data = {
+        "names": pd.Series(["a", "b", None]),
+        "when": pd.DatetimeIndex(["2024-01-01", "2024-01-02"]),
+    }
+
+    decoded = bf.decode_packed(bf.encode_packed(data, compress=False))
+
+    assert decoded["names"] == ["a", "b", None]
+    assert decoded["when"] == [pd.Timestamp("2024-01-01"), pd.Timestamp("2024-01-02")]
+
+
+if __name__ == "__main__":
+    pytest.main([__file__])
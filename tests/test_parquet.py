@@ -0,0 +1,76 @@
+"""Tests for Parquet export (parquet feature)"""
+
+import pytest
+
+import b_fast
+
+pytestmark = pytest.mark.skipif(
+    not hasattr(b_fast, "to_parquet"), reason="built without the parquet feature"
+)
+
+ROWS = [
+    {"id": i, "name": f"user{i}", "score": i * 0.5, "active": i % 2 == 0}
+    for i in range(100)
+]
+
+
+def test_writes_a_parquet_file(tmp_path):
+    path = tmp_path / "rows.parquet"
+    encoded = b_fast.BFast().encode_packed(ROWS, compress=True)
+
+    assert b_fast.to_parquet(encoded, path) == 100
+
+    data = path.read_bytes()
+    assert data[:4] == data[-4:] == b"PAR1"
+
+
+def test_columns_and_nulls(tmp_path):
+    pq = pytest.importorskip("pyarrow.parquet")
+    rows = [{"id": 1, "price": 2}, {"id": 2, "price": 2.5, "note": "x"}, {"id": 3}]
+    path = tmp_path / "rows.parquet"
+    encoded = b_fast.BFast().encode_packed(rows, compress=True)
+
+    b_fast.to_parquet(encoded, str(path))
+
+    table = pq.read_table(path)
+    assert table.column_names == ["id", "price", "note"]
+    assert table.to_pylist() == [
+        {"id": 1, "price": 2.0, "note": None},
+        {"id": 2, "price": 2.5, "note": "x"},
+        {"id": 3, "price": None, "note": None},
+    ]
+
+
+def test_roundtrip_through_pyarrow(tmp_path):
+    pq = pytest.importorskip("pyarrow.parquet")
+    path = tmp_path / "rows.parquet"
+    encoded = b_fast.BFast().encode_packed(ROWS, compress=True)
+
+    b_fast.to_parquet(encoded, path)
+
+    assert pq.read_table(path).to_pylist() == ROWS
+
+
+@pytest.mark.parametrize(
+    "rows, message",
+    [
+        ([{"id": 1, "tags": ["a"]}], "not a scalar"),
+        ([{"id": 1}, {"id": "2"}], "mixes int and str"),
+        ([{"id": 1}, [2]], "not a record"),
+        ({"id": 1}, "list of records"),
+    ],
+)
+def test_rejects_other_payloads(tmp_path, rows, message):
+    path = tmp_path / "rows.parquet"
+    encoded = b_fast.BFast().encode_packed(rows, compress=True)
+
+    with pytest.raises(ValueError, match=message):
+        b_fast.to_parquet(encoded, path)
+    assert not path.exists()
+
+
+def test_rejects_columnar_frames(tmp_path):
+    encoded = b_fast.BFast(columnar=True).encode_packed(ROWS, compress=True)
+
+    with pytest.raises(ValueError, match="columnar"):
+        b_fast.to_parquet(encoded, tmp_path / "rows.parquet")
@@ -0,0 +1,76 @@
+"""Tests for decoding floats as Decimals"""
+
+from decimal import Decimal
+
+import pytest
+
+import b_fast
+
+PRICES = {"price": 0.1, "total": 1234.5, "tiny": 1e-20, "huge": 1e300}
+ROWS = [{"id": i, "price": i + 0.1} for i in range(20)]
+
+
+def decode(data, **kwargs):
+    return b_fast.BFast().decode_packed(data, parse_float="decimal", **kwargs)
+
+
+def test_floats_come_back_as_decimals():
+    encoded = b_fast.BFast().encode_packed(PRICES, compress=False)
+    decoded = decode(encoded)
+    assert decoded == {key: Decimal(repr(value)) for key, value in PRICES.items()}
+    assert all(type(value) is Decimal for value in decoded.values())
+    assert decoded["price"] == Decimal("0.1")
+
+
+def test_decimals_stay_decimals():
+    value = {"amount": Decimal("10.005"), "rate": 0.25}
+    encoded = b_fast.BFast().encode_packed(value, compress=True)
+    assert decode(encoded) == {"amount": Decimal("10.005"), "rate": Decimal("0.25")}
+
+
+def test_decimal_as_float_round_trips_to_decimal():
+    encoder = b_fast.BFast(decimal_as_float=True)
+    encoded = encoder.encode_packed({"amount": Decimal("19.99")}, compress=False)
+    assert decode(encoded) == {"amount": Decimal("19.99")}
+
+
+def test_ints_and_special_floats():
+    encoded = b_fast.BFast().encode_packed(
+        [1, float("inf"), float("-inf"), float("nan")], compress=False
+    )
+    decoded = decode(encoded)
+    assert decoded[0] == 1 and type(decoded[0]) is int
+    assert decoded[1:3] == [Decimal("Infinity"), Decimal("-Infinity")]
+    assert decoded[3].is_nan()
+
+
+@pytest.mark.parametrize("options", [{}, {"columnar": True}, {"batch_threshold": 2}])
+def test_record_lists(options):
+    encoded = b_fast.BFast(**options).encode_packed(ROWS, compress=False)
+    decoded = decode(encoded)
+    assert [row["price"] for row in decoded] == [
+        Decimal(repr(row["price"])) for row in ROWS
+    ]
+    assert [row["id"] for row in decoded] == list(range(20))
+
+
+def test_lazy_lists():
+    encoded = b_fast.BFast().encode_packed(ROWS, compress=False)
+    decoded = decode(encoded, lazy=True)
+    assert decoded[3]["price"] == Decimal("3.1")
+
+
+def test_default_keeps_floats():
+    encoded = b_fast.BFast().encode_packed(PRICES, compress=False)
+    assert b_fast.BFast().decode_packed(encoded) == PRICES
+
+
+def test_decimal_not_allowed_keeps_floats():
+    encoded = b_fast.BFast().encode_packed(PRICES, compress=False)
+    assert decode(encoded, allowed_classes=[]) == PRICES
+
+
+def test_unknown_parse_float_is_rejected():
+    encoded = b_fast.BFast().encode_packed(PRICES, compress=False)
+    with pytest.raises(ValueError, match="parse_float"):
+        b_fast.BFast().decode_packed(encoded, parse_float="double")
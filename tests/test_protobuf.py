@@ -0,0 +1,212 @@
+"""Tests for the descriptor-driven protobuf bridge"""
+
+import pytest
+
+import b_fast
+
+# FieldDescriptorProto types and labels
+DOUBLE = 1
+INT64 = 3
+INT32 = 5
+BOOL = 8
+STRING = 9
+MESSAGE = 11
+BYTES = 12
+ENUM = 14
+SINT64 = 18
+OPTIONAL = 1
+REPEATED = 3
+
+
+def varint(value):
+    out = bytearray()
+    while value >= 0x80:
+        out.append(value & 0x7F | 0x80)
+        value >>= 7
+    out.append(value)
+    return bytes(out)
+
+
+def length_field(number, payload):
+    if isinstance(payload, str):
+        payload = payload.encode()
+    return varint(number << 3 | 2) + varint(len(payload)) + payload
+
+
+def varint_field(number, value):
+    return varint(number << 3) + varint(value)
+
+
+def field(name, number, kind, label=OPTIONAL, type_name=None):
+    out = length_field(1, name) + varint_field(3, number)
+    out += varint_field(4, label) + varint_field(5, kind)
+    if type_name:
+        out += length_field(6, type_name)
+    return out
+
+
+def message(name, *fields, nested=(), map_entry=False):
+    out = length_field(1, name)
+    out += b"".join(length_field(2, f) for f in fields)
+    out += b"".join(length_field(3, n) for n in nested)
+    if map_entry:
+        out += length_field(7, varint_field(7, 1))
+    return out
+
+
+# package shop;
+# message Order {
+#   int32 id = 1; string customer = 2; double total = 3; bool paid = 4;
+#   repeated Item items = 5; repeated int64 tags = 6;
+#   map<string, int32> stock = 7;
+#   Status status = 8; bytes note = 9; sint64 delta = 10;
+#   message Item { string sku = 1; int32 qty = 2; }
+# }
+ITEM = message("Item", field("sku", 1, STRING), field("qty", 2, INT32))
+STOCK_ENTRY = message(
+    "StockEntry", field("key", 1, STRING), field("value", 2, INT32), map_entry=True
+)
+ORDER = message(
+    "Order",
+    field("id", 1, INT32),
+    field("customer", 2, STRING),
+    field("total", 3, DOUBLE),
+    field("paid", 4, BOOL),
+    field("items", 5, MESSAGE, REPEATED, ".shop.Order.Item"),
+    field("tags", 6, INT64, REPEATED),
+    field("stock", 7, MESSAGE, REPEATED, ".shop.Order.StockEntry"),
+    field("status", 8, ENUM, type_name=".shop.Status"),
+    field("note", 9, BYTES),
+    field("delta", 10, SINT64),
+    nested=(ITEM, STOCK_ENTRY),
+)
+DESCRIPTOR = length_field(1, length_field(2, "shop") + length_field(4, ORDER))
+
+ORDER_RECORD = {
+    "id": 150,
+    "customer": "Ana",
+    "total": 19.5,
+    "paid": True,
+    "items": [{"sku": "A-1", "qty": 2}, {"sku": "B-7", "qty": -1}],
+    "tags": [1, 300, -5],
+    "stock": {"A-1": 4},
+    "status": 2,
+    "note": b"\x00\xff",
+    "delta": -3,
+}
+
+
+def test_scalar_fields_use_the_standard_wire_format():
+    data = b_fast.BFast().encode_packed({"id": 150, "customer": "Ana"}, compress=False)
+
+    encoded = b_fast.to_protobuf(data, DESCRIPTOR, "shop.Order")
+
+    assert encoded == b"\x08\x96\x01" + b"\x12\x03Ana"
+
+
+def test_round_trip_through_protobuf():
+    data = b_fast.BFast().encode_packed(ORDER_RECORD, compress=False)
+
+    encoded = b_fast.to_protobuf(data, DESCRIPTOR, "shop.Order")
+    decoded = b_fast.from_protobuf(encoded, DESCRIPTOR, ".shop.Order")
+
+    assert b_fast.BFast().decode_packed(decoded) == ORDER_RECORD
+
+
+def test_missing_fields_decode_to_defaults():
+    record = {"customer": "Ana", "paid": None}
+    data = b_fast.BFast().encode_packed(record, compress=False)
+
+    encoded = b_fast.to_protobuf(data, DESCRIPTOR, "shop.Order")
+    record = b_fast.BFast().decode_packed(
+        b_fast.from_protobuf(encoded, DESCRIPTOR, "shop.Order")
+    )
+
+    assert record == {
+        "id": 0,
+        "customer": "Ana",
+        "total": 0.0,
+        "paid": False,
+        "items": [],
+        "tags": [],
+        "stock": {},
+        "status": 0,
+        "note": b"",
+        "delta": 0,
+    }
+
+
+def test_lists_map_to_one_message_per_record():
+    records = [{"id": 1}, {"id": 2, "customer": "Bo"}]
+    data = b_fast.BFast().encode_packed(records, compress=False)
+
+    messages = b_fast.to_protobuf(data, DESCRIPTOR, "shop.Order")
+    decoded = b_fast.BFast().decode_packed(
+        b_fast.from_protobuf(messages, DESCRIPTOR, "shop.Order", compress=True)
+    )
+
+    assert messages == [b"\x08\x01", b"\x08\x02\x12\x02Bo"]
+    assert [r["id"] for r in decoded] == [1, 2]
+    assert decoded[1]["customer"] == "Bo"
+
+
+def test_unknown_field_numbers_are_skipped():
+    message_bytes = b"\x08\x07" + varint_field(99, 5) + length_field(98, "x")
+
+    record = b_fast.BFast().decode_packed(
+        b_fast.from_protobuf(message_bytes, DESCRIPTOR, "shop.Order")
+    )
+
+    assert record["id"] == 7
+
+
+def test_unpacked_repeated_scalars_are_accepted():
+    message_bytes = varint_field(6, 4) + varint_field(6, 9)
+
+    record = b_fast.BFast().decode_packed(
+        b_fast.from_protobuf(message_bytes, DESCRIPTOR, "shop.Order")
+    )
+
+    assert record["tags"] == [4, 9]
+
+
+def test_unknown_message_type_is_rejected():
+    data = b_fast.BFast().encode_packed({"id": 1}, compress=False)
+
+    with pytest.raises(ValueError, match="not in the descriptor"):
+        b_fast.to_protobuf(data, DESCRIPTOR, "shop.Invoice")
+
+
+def test_keys_outside_the_message_are_rejected():
+    data = b_fast.BFast().encode_packed({"id": 1, "coupon": "X"}, compress=False)
+
+    with pytest.raises(ValueError, match="coupon"):
+        b_fast.to_protobuf(data, DESCRIPTOR, "shop.Order")
+
+
+def test_values_outside_the_field_range_are_rejected():
+    data = b_fast.BFast().encode_packed({"id": 2**40}, compress=False)
+
+    with pytest.raises(OverflowError, match="id"):
+        b_fast.to_protobuf(data, DESCRIPTOR, "shop.Order")
+
+
+def test_truncated_messages_are_rejected():
+    with pytest.raises(ValueError, match="Truncated"):
+        b_fast.from_protobuf(b"\x12\x05Ana", DESCRIPTOR, "shop.Order")
+
+
+@pytest.mark.parametrize("entry_field", ["key", "value"])
+def test_map_entries_without_a_key_or_value_field_are_rejected(entry_field):
+    number = 1 if entry_field == "key" else 2
+    entry = message("StockEntry", field(entry_field, number, STRING), map_entry=True)
+    order = message(
+        "Order",
+        field("stock", 7, MESSAGE, REPEATED, ".shop.Order.StockEntry"),
+        nested=(entry,),
+    )
+    descriptor = length_field(1, length_field(2, "shop") + length_field(4, order))
+    data = length_field(7, length_field(number, "A-1"))
+
+    with pytest.raises(ValueError, match="StockEntry"):
+        b_fast.from_protobuf(data, descriptor, "shop.Order")
@@ -0,0 +1,70 @@
+"""Tests for Pydantic field exclusion and secret masking"""
+
+import pytest
+from pydantic import BaseModel, Field, SecretBytes, SecretStr
+
+import b_fast
+
+
+class Account(BaseModel):
+    user: str
+    password: SecretStr
+    token: SecretBytes
+    internal: int = Field(default=0, exclude=True)
+
+
+ACCOUNT = Account(user="ana", password="hunter2", token=b"\x01\x02", internal=7)
+
+
+def test_secrets_are_masked_by_default():
+    bf = b_fast.BFast()
+
+    encoded = bf.encode_packed(ACCOUNT, compress=False)
+    decoded = bf.decode_packed(encoded)
+
+    assert decoded == {"user": "ana", "password": "**********", "token": "**********"}
+    assert b"hunter2" not in b_fast.BFast().encode_packed(ACCOUNT, compress=False)
+
+
+def test_reveal_secrets():
+    encoded = b_fast.BFast(reveal_secrets=True).encode_packed(ACCOUNT, compress=False)
+    decoded = b_fast.BFast().decode_packed(encoded)
+
+    assert decoded["password"] == "hunter2"
+    assert decoded["token"] == b"\x01\x02"
+
+
+def test_excluded_fields_are_skipped():
+    bf = b_fast.BFast()
+
+    encoded = bf.encode_packed(ACCOUNT, compress=False)
+    decoded = bf.decode_packed(encoded)
+
+    assert "internal" not in decoded
+    assert set(decoded) == set(ACCOUNT.model_dump())
+
+
+def test_bare_secret_values():
+    bf = b_fast.BFast()
+
+    masked = bf.encode_packed([SecretStr("s")], compress=False)
+    revealed = b_fast.BFast(reveal_secrets=True).encode_packed(
+        [SecretStr("s")], compress=False
+    )
+
+    assert bf.decode_packed(masked) == ["**********"]
+    assert bf.decode_packed(revealed) == ["s"]
+
+
+@pytest.mark.parametrize(
+    "kwargs", [{}, {"columnar": True}, {"null_bitmap": True}, {"dedup": True}]
+)
+def test_lists_of_models(kwargs):
+    accounts = [ACCOUNT] * 12
+
+    encoded = b_fast.BFast(**kwargs).encode_packed(accounts, compress=False)
+    decoded = b_fast.BFast().decode_packed(encoded)
+
+    assert decoded == [
+        {"user": "ana", "password": "**********", "token": "**********"}
+    ] * 12
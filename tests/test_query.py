@@ -0,0 +1,130 @@
+"""Tests for queries that run directly on encoded bytes"""
+
+import datetime
+
+import pytest
+
+import b_fast
+
+ORDERS = {
+    "orders": [
+        {
+            "id": i,
+            "amount": i * 10.5,
+            "customer": {"id": f"c-{i}", "since": datetime.date(2020, 1, i + 1)},
+            "items": [{"sku": "A"}, {"sku": "B"}],
+        }
+        for i in range(12)
+    ],
+    "meta": {"page": 1},
+}
+
+
+ENCODED = b_fast.BFast().encode_packed(ORDERS, compress=False)
+
+
+def test_get_nested_field():
+    assert b_fast.get(ENCODED, "orders[3].customer.id") == "c-3"
+    assert b_fast.get(ENCODED, "orders[3].customer.since") == datetime.date(2020, 1, 4)
+    assert b_fast.get(ENCODED, "orders[11].items[1].sku") == "B"
+    assert b_fast.get(ENCODED, "meta") == {"page": 1}
+
+
+def test_get_empty_path_returns_everything():
+    assert b_fast.get(ENCODED, "") == ORDERS
+
+
+def test_get_missing_path_returns_default():
+    assert b_fast.get(ENCODED, "orders[99].id") is None
+    assert b_fast.get(ENCODED, "meta.missing", "fallback") == "fallback"
+    assert b_fast.get(ENCODED, "meta.page.deeper") is None
+    assert b_fast.get(ENCODED, "meta[0]") is None
+
+
+def test_get_root_list():
+    bf = b_fast.BFast()
+    data = bf.encode_packed([{"id": i} for i in range(20)], compress=True)
+
+    assert b_fast.get(data, "[7].id") == 7
+
+
+def test_get_compressed_and_dedup_frames():
+    data = b_fast.BFast(dedup=True).encode_packed(ORDERS, compress=True)
+
+    assert b_fast.get(data, "orders[5].items[0].sku") == "A"
+    assert b_fast.get(data, "orders[5].customer.id") == "c-5"
+
+
+@pytest.mark.parametrize("path", ["orders..id", "orders[x]", "orders[1", "orders]1["])
+def test_get_malformed_path(path):
+    with pytest.raises(ValueError):
+        b_fast.get(ENCODED, path)
+
+
+SALES = [
+    {"amount": 10, "region": "eu"},
+    {"amount": 2.5, "region": "us"},
+    {"amount": None, "region": "us"},
+    {"region": "br"},
+    {"amount": -7, "region": "eu", "extra": {"weight": 3}},
+] * 4
+
+
+def test_aggregate_ops():
+    data = b_fast.BFast().encode_packed(SALES, compress=True)
+
+    assert b_fast.aggregate(data, field="amount", op="sum") == 22.0
+    assert b_fast.aggregate(data, field="amount", op="count") == 12
+    assert b_fast.aggregate(data, field="amount", op="min") == -7
+    assert b_fast.aggregate(data, field="amount", op="max") == 10
+    assert b_fast.aggregate(data, op="count") == 20
+    assert b_fast.aggregate(data, field="extra.weight") == 12
+
+
+def test_aggregate_integer_sum_stays_exact():
+    rows = [{"n": 2**62}, {"n": 2**62}, {"n": 1}]
+    data = b_fast.BFast().encode_packed(rows, compress=False)
+
+    total = b_fast.aggregate(data, field="n")
+
+    assert total == 2**63 + 1
+    assert isinstance(total, int)
+
+
+def test_aggregate_scalars_and_empty_lists():
+    bf = b_fast.BFast()
+
+    assert b_fast.aggregate(bf.encode_packed([1, 2, 3.5], compress=False)) == 6.5
+    assert b_fast.aggregate(bf.encode_packed([], compress=False)) == 0
+    assert b_fast.aggregate(bf.encode_packed([], compress=False), op="max") is None
+
+
+@pytest.mark.parametrize(
+    "options",
+    [
+        {"columnar": True},
+        {"null_bitmap": True},
+        {"pack_bools": True},
+        {"dedup": True},
+    ],
+)
+def test_aggregate_decodes_other_layouts(options):
+    rows = [dict(row, paid=i % 2 == 0) for i, row in enumerate(SALES)]
+    data = b_fast.BFast(**options).encode_packed(rows, compress=True)
+
+    assert b_fast.aggregate(data, field="amount", op="sum") == 22.0
+    assert b_fast.aggregate(data, field="amount", op="count") == 12
+    assert b_fast.aggregate(data, field="paid") == 10
+    assert b_fast.aggregate(data, field="extra.weight", op="max") == 3
+
+
+def test_aggregate_errors():
+    bf = b_fast.BFast()
+    data = bf.encode_packed(SALES, compress=False)
+
+    with pytest.raises(ValueError, match="non-numeric"):
+        b_fast.aggregate(data, field="region")
+    with pytest.raises(ValueError, match="Unknown aggregate op"):
+        b_fast.aggregate(data, field="amount", op="avg")
+    with pytest.raises(ValueError, match="list"):
+        b_fast.aggregate(bf.encode_packed({"a": 1}, compress=False), field="a")
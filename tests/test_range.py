@@ -0,0 +1,69 @@
+"""Tests for range values"""
+
+import pytest
+
+import b_fast
+
+
+@pytest.mark.parametrize(
+    "value",
+    [range(10), range(3, 90, 7), range(10, -10, -3), range(0), range(-(2**40), 2**40)],
+)
+def test_roundtrip(value):
+    bf = b_fast.BFast()
+
+    encoded = bf.encode_packed(value, compress=False)
+    decoded = bf.decode_packed(encoded)
+
+    assert isinstance(decoded, range)
+    assert decoded == value
+
+
+def test_encoded_compactly():
+    encoded = b_fast.BFast().encode_packed(range(1000), compress=False)
+
+    assert len(encoded) <= 10 + 1 + 9 + 2
+
+
+def test_nested_in_records():
+    config = {"ports": range(8000, 8010), "retries": range(3)}
+    bf = b_fast.BFast()
+
+    encoded = bf.encode_packed([config], compress=False)
+
+    assert bf.decode_packed(encoded) == [config]
+
+
+def test_expand_to_list():
+    bf = b_fast.BFast()
+
+    encoded = bf.encode_packed(range(1, 10, 4), compress=False)
+
+    assert bf.decode_packed(encoded, ranges_as_lists=True) == [1, 5, 9]
+    assert bf.decode_packed(encoded, ranges_as_lists=True, immutable=True) == (1, 5, 9)
+
+
+def test_columnar_runs_do_not_share_expanded_lists():
+    rows = [{"id": i, "span": range(2)} for i in range(4)]
+    encoded = b_fast.BFast(columnar=True).encode_packed(rows, compress=False)
+
+    decoded = b_fast.BFast().decode_packed(encoded, ranges_as_lists=True)
+
+    decoded[0]["span"].append(2)
+    assert decoded[1]["span"] == [0, 1]
+
+
+def test_huge_bounds_fall_back_to_string():
+    bf = b_fast.BFast()
+
+    encoded = bf.encode_packed(range(2**70), compress=False)
+
+    assert bf.decode_packed(encoded) == "range(0, 1180591620717411303424)"
+
+
+def test_dump_tokens():
+    encoded = b_fast.BFast().encode_packed(range(2, 50, 3), compress=False)
+
+    tokens = b_fast.dump_tokens(encoded)["tokens"]
+
+    assert tokens == [(10, "range", 5, "range(2, 50, 3)")]
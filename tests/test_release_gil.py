@@ -0,0 +1,134 @@
+"""Tests for encode_packed(..., release_gil=True)"""
+
+import threading
+from datetime import date
+
+import pytest
+
+import b_fast
+
+ROWS = [
+    {"id": i, "name": f"user-{i}", "score": i / 3, "tags": ("a", "b"), "raw": b"\x00"}
+    for i in range(300)
+]
+
+PAYLOADS = [
+    None,
+    True,
+    -1,
+    7,
+    8,
+    2**63 - 1,
+    1.5,
+    "text",
+    b"bytes",
+    [],
+    {},
+    [1, [2, [3, None]], {"nested": {"deep": [False]}}],
+    {"a": 1, "b": {"a": 2, "c": "x"}},
+    ROWS,
+]
+
+# Values the released path leaves to the regular one
+FALLBACKS = [
+    {"when": date(2024, 1, 2)},
+    {1: "int keys"},
+    {"big": 2**70},
+    [{1, 2}],
+    {"subclass": type("Text", (str,), {})("x")},
+]
+
+
+@pytest.mark.parametrize("data", PAYLOADS)
+def test_same_bytes_as_the_regular_path(data):
+    bf = b_fast.BFast()
+
+    for compress in (False, True):
+        released = bf.encode_packed(data, compress, release_gil=True)
+
+        assert released == bf.encode_packed(data, compress)
+
+
+@pytest.mark.parametrize("data", FALLBACKS)
+def test_other_values_take_the_regular_path(data):
+    bf = b_fast.BFast()
+
+    released = bf.encode_packed(data, False, release_gil=True)
+
+    assert released == bf.encode_packed(data, False)
+
+
+@pytest.mark.parametrize("options", [{"dedup": True}, {"columnar": True}])
+def test_options_that_need_the_objects_are_kept(options):
+    bf = b_fast.BFast(**options)
+
+    released = bf.encode_packed(ROWS, False, release_gil=True)
+
+    assert released == b_fast.BFast(**options).encode_packed(ROWS, False)
+
+
+def test_metadata_is_written():
+    bf = b_fast.BFast()
+
+    frame = bf.encode_packed(ROWS, True, metadata={"trace": "t1"}, release_gil=True)
+
+    assert b_fast.metadata(frame) == {"trace": "t1"}
+    assert bf.decode_packed(frame) == bf.decode_packed(bf.encode_packed(ROWS, True))
+
+
+@pytest.mark.parametrize(
+    "data",
+    [
+        list(range(100_000)),
+        ["x" * 100] * 5000,
+        [b"y" * 1000] * 1000,
+        [{"id": i, "tags": ["a", "b"]} for i in range(20_000)],
+    ],
+)
+def test_large_lists_stop_at_the_buffer_cap(data):
+    bf = b_fast.BFast(max_buffer_bytes=100_000)
+
+    with pytest.raises(ValueError, match="max_buffer_bytes=100000"):
+        bf.encode_packed(data, True, release_gil=True)
+
+    assert bf.memory_usage()["work_buffer"] <= 100_000
+
+
+def test_lists_within_the_cap_encode_released():
+    bf = b_fast.BFast(max_buffer_bytes=100_000)
+
+    released = bf.encode_packed(ROWS, True, release_gil=True)
+
+    assert released == b_fast.BFast().encode_packed(ROWS, True)
+
+
+def test_encoders_reused_across_calls():
+    bf = b_fast.BFast()
+
+    first = bf.encode_packed({"a": [1, 2]}, False, release_gil=True)
+    second = bf.encode_packed({"b": "x", "a": 3}, False, release_gil=True)
+
+    assert bf.decode_packed(first) == {"a": [1, 2]}
+    assert bf.decode_packed(second) == {"b": "x", "a": 3}
+
+
+def test_threads_encode_concurrently():
+    results = {}
+
+    def work(n):
+        bf = b_fast.BFast()
+        rows = [dict(row, worker=n) for row in ROWS]
+        frames = [bf.encode_packed(rows, True, release_gil=True) for _ in range(5)]
+        results[n] = [bf.decode_packed(frame) for frame in frames]
+
+    threads = [threading.Thread(target=work, args=(n,)) for n in range(4)]
+    for thread in threads:
+        thread.start()
+    for thread in threads:
+        thread.join()
+
+    bf = b_fast.BFast()
+    for n, decoded in results.items():
+        rows = [dict(row, worker=n) for row in ROWS]
+        expected = bf.decode_packed(bf.encode_packed(rows, True))
+        assert decoded == [expected] * 5
@@ -0,0 +1,206 @@
+"""Tests for BFastSchema / register_schema"""
+
+from dataclasses import dataclass, field
+
+import pytest
+
+import b_fast
+
+FIELDS = ["id", "name", "email", "active"]
+ROWS = [
+    {"id": i, "name": f"user-{i}", "email": f"u{i}@example.com", "active": i % 2 == 0}
+    for i in range(50)
+]
+
+
+@dataclass
+class User:
+    id: int
+    name: str
+
+
+def test_field_list_roundtrip():
+    schema = b_fast.BFastSchema(FIELDS)
+    bf = b_fast.BFast(schema=schema)
+
+    encoded = bf.encode_packed(ROWS, compress=False)
+
+    assert b_fast.BFast().decode_packed(encoded) == ROWS
+    assert b"email" not in encoded
+
+
+def test_schema_frames_are_smaller():
+    row = ROWS[0]
+    plain = b_fast.BFast().encode_packed(row, compress=False)
+    schema = b_fast.BFast(schema=b_fast.BFastSchema(FIELDS))
+
+    encoded = schema.encode_packed(row, compress=False)
+
+    assert len(encoded) < len(plain)
+    assert b_fast.BFast().decode_packed(encoded) == row
+
+
+def test_dataclass_source():
+    schema = b_fast.BFastSchema(User)
+    bf = b_fast.BFast(schema=schema)
+
+    encoded = bf.encode_packed(User(1, "Ana"), compress=False)
+
+    assert schema.fields == ["id", "name"]
+    assert schema.name == "User"
+    assert bf.decode_packed(encoded) == {"id": 1, "name": "Ana"}
+
+
+def test_pydantic_source():
+    pydantic = pytest.importorskip("pydantic")
+
+    class Item(pydantic.BaseModel):
+        sku: str
+        qty: int
+
+    schema = b_fast.BFastSchema(Item, id=0x5EED)
+    bf = b_fast.BFast(schema=schema)
+
+    encoded = bf.encode_packed(Item(sku="a", qty=2), compress=False)
+
+    assert schema.id == 0x5EED
+    assert b_fast.BFast().decode_packed(encoded) == {"sku": "a", "qty": 2}
+
+
+def test_keys_outside_the_schema():
+    data = {"id": 1, "extra": {"nested": [1, 2]}, "name": "x", "more": None}
+    bf = b_fast.BFast(schema=b_fast.BFastSchema(["id", "name"]))
+
+    encoded = bf.encode_packed(data, compress=False)
+
+    assert b"extra" in encoded
+    assert bf.decode_packed(encoded) == data
+
+
+def test_compressed_roundtrip():
+    bf = b_fast.BFast(schema=b_fast.BFastSchema(FIELDS))
+
+    encoded = bf.encode_packed(ROWS, compress=True)
+
+    assert encoded[2] & 1  # compressed flag
+    assert b_fast.BFast().decode_packed(encoded) == ROWS
+
+
+def test_derived_id_depends_on_field_order():
+    first = b_fast.BFastSchema(["a", "b"])
+
+    assert first.id == b_fast.BFastSchema(["a", "b"]).id
+    assert first.id != b_fast.BFastSchema(["b", "a"]).id
+
+
+def test_unknown_schema_id_fails():
+    bf = b_fast.BFast(schema=b_fast.BFastSchema(["k"], id=0x1234))
+    encoded = bytearray(bf.encode_packed({"k": 1}, compress=False))
+    encoded[10:14] = (0xDEADBEEF).to_bytes(4, "little")
+
+    with pytest.raises(ValueError, match="Unknown schema ID"):
+        b_fast.BFast().decode_packed(bytes(encoded))
+
+
+def test_conflicting_registration_fails():
+    b_fast.register_schema(b_fast.BFastSchema(["x"], id=0x7777))
+    b_fast.register_schema(b_fast.BFastSchema(["x"], id=0x7777))
+
+    with pytest.raises(ValueError, match="already registered"):
+        b_fast.register_schema(b_fast.BFastSchema(["y"], id=0x7777))
+
+
+def test_duplicate_fields_fail():
+    with pytest.raises(ValueError, match="Duplicate field"):
+        b_fast.BFastSchema(["a", "b", "a"])
+
+
+def test_string_source_fails():
+    with pytest.raises(TypeError):
+        b_fast.BFastSchema("name")
+
+
+def test_byte_level_tools_resolve_schema_keys():
+    bf = b_fast.BFast(schema=b_fast.BFastSchema(FIELDS))
+    encoded = bf.encode_packed(ROWS, compress=False)
+
+    sliced = b_fast.slice(encoded, 10, 12)
+
+    assert b_fast.get(encoded, "[3].email") == "u3@example.com"
+    assert b_fast.aggregate(encoded, "id") == sum(range(50))
+    assert b_fast.BFast().decode_packed(sliced) == ROWS[10:12]
+    assert b"email" in sliced
+
+
+V1 = b_fast.BFastSchema(["id", "name", "legacy"], id=0xA001)
+V1_ROWS = [{"id": i, "name": f"n{i}", "legacy": True} for i in range(20)]
+
+
+def test_reader_schema_adds_defaults_and_drops_removed_fields():
+    encoded = b_fast.BFast(schema=V1).encode_packed(V1_ROWS, compress=False)
+    v2 = b_fast.BFastSchema(["id", "name", "tier"], id=0xA002, defaults={"tier": 1})
+
+    decoded = b_fast.BFast().decode_packed(encoded, schema=v2)
+
+    assert decoded == [{"id": i, "name": f"n{i}", "tier": 1} for i in range(20)]
+
+
+def test_reader_schema_renames_through_aliases():
+    encoded = b_fast.BFast(schema=V1).encode_packed(V1_ROWS[0], compress=False)
+    v2 = b_fast.BFastSchema(
+        ["id", "full_name"], id=0xA003, aliases={"full_name": "name"}
+    )
+
+    decoded = b_fast.BFast().decode_packed(encoded, schema=v2)
+
+    assert decoded == {"id": 0, "full_name": "n0"}
+
+
+def test_current_name_wins_over_alias():
+    encoded = b_fast.BFast().encode_packed({"new": 1, "old": 2}, compress=False)
+    reader = b_fast.BFastSchema(["new"], aliases={"new": "old"})
+
+    assert b_fast.BFast().decode_packed(encoded, schema=reader) == {"new": 1}
+
+
+@dataclass
+class UserV2:
+    id: int
+    name: str = "anonymous"
+    tags: list = field(default_factory=list)
+
+
+def test_dataclass_defaults_are_copied_per_record():
+    encoded = b_fast.BFast().encode_packed([{"id": 1}, {"id": 2}], compress=False)
+
+    decoded = b_fast.BFast().decode_packed(encoded, schema=b_fast.BFastSchema(UserV2))
+    decoded[0]["tags"].append("x")
+
+    assert decoded[1] == {"id": 2, "name": "anonymous", "tags": []}
+
+
+def test_pydantic_defaults():
+    pydantic = pytest.importorskip("pydantic")
+
+    class Item(pydantic.BaseModel):
+        sku: str
+        qty: int = 1
+
+    encoded = b_fast.BFast().encode_packed({"sku": "a"}, compress=False)
+    decoded = b_fast.BFast().decode_packed(encoded, schema=b_fast.BFastSchema(Item))
+
+    assert Item(**decoded) == Item(sku="a", qty=1)
+    assert decoded == {"sku": "a", "qty": 1}
+
+
+@pytest.mark.parametrize(
+    "kwargs, message",
+    [
+        ({"defaults": {"nope": 1}}, "unknown field"),
+        ({"aliases": {"nope": "old"}}, "unknown field"),
+        ({"aliases": {"a": "b"}}, "also a field"),
+    ],
+)
+def test_invalid_evolution_options(kwargs, message):
+    with pytest.raises(ValueError, match=message):
+        b_fast.BFastSchema(["a", "b"], **kwargs)
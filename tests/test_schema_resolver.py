@@ -0,0 +1,113 @@
+"""Tests for b_fast.register_schema_resolver"""
+
+import pytest
+
+import b_fast
+
+WRITER = b_fast.BFastSchema(["sku", "qty"], id=0xB000)
+ROW = {"sku": "a-1", "qty": 3}
+
+
+def frame_with_id(schema_id):
+    """Encode ROW, then point the frame at a schema ID nobody registered."""
+    encoded = bytearray(b_fast.BFast(schema=WRITER).encode_packed(ROW, compress=False))
+    encoded[10:14] = schema_id.to_bytes(4, "little")
+    return bytes(encoded)
+
+
+def test_resolver_is_called_once_per_id():
+    calls = []
+
+    def fetch(schema_id):
+        calls.append(schema_id)
+        return ["sku", "qty"]
+
+    b_fast.register_schema_resolver(fetch)
+    try:
+        encoded = frame_with_id(0xB001)
+        assert b_fast.BFast().decode_packed(encoded) == ROW
+        assert b_fast.BFast().decode_packed(encoded) == ROW
+        assert b_fast.get(encoded, "qty") == 3
+    finally:
+        b_fast.register_schema_resolver(None)
+
+    assert calls == [0xB001]
+
+
+def test_resolver_can_return_a_schema():
+    b_fast.register_schema_resolver(
+        lambda schema_id: b_fast.BFastSchema(["sku", "qty"], id=schema_id)
+    )
+    try:
+        assert b_fast.BFast().decode_packed(frame_with_id(0xB002)) == ROW
+    finally:
+        b_fast.register_schema_resolver(None)
+
+
+def test_least_recently_used_schema_is_evicted():
+    calls = []
+
+    def fetch(schema_id):
+        calls.append(schema_id)
+        return ["sku", "qty"]
+
+    b_fast.register_schema_resolver(fetch, cache_size=2)
+    try:
+        for schema_id in [0xB010, 0xB011, 0xB010, 0xB012, 0xB010, 0xB011]:
+            b_fast.BFast().decode_packed(frame_with_id(schema_id))
+    finally:
+        b_fast.register_schema_resolver(None)
+
+    assert calls == [0xB010, 0xB011, 0xB012, 0xB011]
+
+
+def test_local_registry_takes_precedence():
+    b_fast.register_schema_resolver(lambda schema_id: ["other", "fields"])
+    try:
+        encoded = b_fast.BFast(schema=WRITER).encode_packed(ROW, compress=False)
+        assert b_fast.BFast().decode_packed(encoded) == ROW
+    finally:
+        b_fast.register_schema_resolver(None)
+
+
+def test_unknown_to_the_resolver():
+    b_fast.register_schema_resolver(lambda schema_id: None)
+    try:
+        with pytest.raises(ValueError, match="Unknown schema ID"):
+            b_fast.BFast().decode_packed(frame_with_id(0xB020))
+    finally:
+        b_fast.register_schema_resolver(None)
+
+
+def test_resolver_errors_are_reported():
+    def fetch(schema_id):
+        raise ConnectionError("registry unavailable")
+
+    b_fast.register_schema_resolver(fetch)
+    try:
+        with pytest.raises(ValueError, match="registry unavailable"):
+            b_fast.BFast().decode_packed(frame_with_id(0xB030))
+    finally:
+        b_fast.register_schema_resolver(None)
+
+
+def test_mismatched_schema_id_is_rejected():
+    b_fast.register_schema_resolver(lambda schema_id: WRITER)
+    try:
+        with pytest.raises(ValueError, match="resolver returned schema ID"):
+            b_fast.BFast().decode_packed(frame_with_id(0xB040))
+    finally:
+        b_fast.register_schema_resolver(None)
+
+
+def test_removed_resolver_is_not_called():
+    b_fast.register_schema_resolver(lambda schema_id: ["sku", "qty"])
+    b_fast.register_schema_resolver(None)
+
+    with pytest.raises(ValueError, match="Unknown schema ID"):
+        b_fast.BFast().decode_packed(frame_with_id(0xB050))
+
+
+def test_cache_size_must_be_positive():
+    with pytest.raises(ValueError, match="cache_size"):
+        b_fast.register_schema_resolver(lambda schema_id: None, cache_size=0)
@@ -0,0 +1,96 @@
+"""Tests for frames written as named sections"""
+
+import pytest
+
+import b_fast
+
+ROWS = [{"id": i, "name": f"user{i}", "tags": ["a", "b"]} for i in range(200)]
+REPORT = {"header": {"version": 2}, "rows": ROWS, "summary": {"count": 200}}
+
+
+def encode_sections(data, compress=False, **kwargs):
+    encoder = b_fast.BFast(**kwargs)
+    return encoder.encode_packed(data, compress=compress, sections=True)
+
+
+@pytest.mark.parametrize("compress", [False, True])
+def test_roundtrip(compress):
+    decoded = b_fast.BFast().decode_packed(encode_sections(REPORT, compress=compress))
+
+    assert decoded == REPORT
+    assert list(decoded) == ["header", "rows", "summary"]
+
+
+def test_get_reads_one_section():
+    encoded = encode_sections(REPORT)
+
+    assert b_fast.get(encoded, "summary") == {"count": 200}
+    assert b_fast.get(encoded, "rows[150].name") == "user150"
+    assert b_fast.get(encoded, "missing", default=0) == 0
+
+
+def test_sections_are_indexed():
+    encoded = encode_sections(REPORT)
+
+    tokens = b_fast.dump_tokens(encoded)["tokens"]
+
+    assert tokens[0][1:] == ("sections", len(encoded) - tokens[0][0], "3 sections")
+    assert tokens[1][0][1:] == ("index", 24, "3 entries")
+    assert [t[3] for t in tokens[1] if t[1] == "section"] == [
+        '"header"',
+        '"rows"',
+        '"summary"',
+    ]
+
+
+@pytest.mark.parametrize(
+    "kwargs",
+    [
+        {"columnar": True},
+        {"null_bitmap": True},
+        {"schema": b_fast.BFastSchema(["id", "name"])},
+    ],
+)
+def test_encoder_options(kwargs):
+    encoded = encode_sections(REPORT, **kwargs)
+
+    assert b_fast.BFast().decode_packed(encoded) == REPORT
+
+
+def test_with_metadata():
+    encoded = b_fast.BFast().encode_packed(
+        REPORT, compress=True, metadata={"trace_id": "7f3a"}, sections=True
+    )
+
+    assert b_fast.metadata(encoded) == {"trace_id": "7f3a"}
+    assert b_fast.get(encoded, "header.version") == 2
+
+
+def test_empty_dict():
+    assert b_fast.BFast().decode_packed(encode_sections({})) == {}
+
+
+def test_requires_a_dict():
+    with pytest.raises(TypeError):
+        encode_sections(ROWS)
+
+
+def test_rejects_dedup():
+    with pytest.raises(ValueError):
+        encode_sections(REPORT, dedup=True)
+
+
+def test_patches_are_rejected():
+    patch = b_fast.diff({"a": 1}, {"a": 2})
+
+    with pytest.raises(ValueError, match="sections"):
+        b_fast.apply_patch(encode_sections({"a": 1}), patch)
+
+
+def test_corrupt_index_raises():
+    encoded = bytearray(encode_sections({"a": [1, 2, 3]}))
+    index = encoded.index(0x73) + 5
+    encoded[index + 4] += 1
+
+    with pytest.raises(ValueError):
+        b_fast.BFast().decode_packed(bytes(encoded))
@@ -0,0 +1,78 @@
+"""Tests for the int encodings: packed into the tag byte or sized by value"""
+
+import pytest
+
+import b_fast
+
+PACKED = [n for n in range(-16, 16) if n != 8]
+ROWS = [
+    {"id": i * 1000, "parent": -1, "delta": i % 5 - 2, "big": 2**40 + i}
+    for i in range(30)
+]
+
+
+@pytest.mark.parametrize("value", PACKED)
+def test_value_takes_one_byte(value):
+    bf = b_fast.BFast()
+    encoded = bf.encode_packed([value], compress=False)
+    assert len(encoded) == len(bf.encode_packed([], compress=False)) + 1
+    assert b_fast.BFast().decode_packed(encoded) == [value]
+
+
+@pytest.mark.parametrize(
+    "value,width",
+    [
+        (8, 1),
+        (16, 1),
+        (-17, 1),
+        (127, 1),
+        (-128, 1),
+        (128, 2),
+        (-32768, 2),
+        (32768, 4),
+        (-(2**31), 4),
+        (2**31, 8),
+        (-(2**63), 8),
+        (2**63 - 1, 8),
+    ],
+)
+def test_other_values_take_their_width(value, width):
+    bf = b_fast.BFast()
+    encoded = bf.encode_packed([value], compress=False)
+    assert len(encoded) == len(bf.encode_packed([], compress=False)) + 1 + width
+    assert b_fast.BFast().decode_packed(encoded) == [value]
+
+
+def test_tags_are_named():
+    values = [-1, -16, 100, -1000, 10**6, 10**12]
+    encoded = b_fast.BFast().encode_packed(values, compress=False)
+    _, items = b_fast.dump_tokens(encoded)["tokens"]
+    assert [(kind, size, preview) for _, kind, size, preview in items] == [
+        ("int", 1, "-1"),
+        ("int", 1, "-16"),
+        ("int8", 2, "100"),
+        ("int16", 3, "-1000"),
+        ("int32", 5, "1000000"),
+        ("int64", 9, "1000000000000"),
+    ]
+
+
+@pytest.mark.parametrize("options", [{}, {"columnar": True}, {"batch_threshold": 2}])
+def test_record_lists(options):
+    encoded = b_fast.BFast(**options).encode_packed(ROWS, compress=False)
+    assert b_fast.BFast().decode_packed(encoded) == ROWS
+    assert b_fast.BFast().decode_packed(encoded, lazy=True)[7] == ROWS[7]
+
+
+def test_released_gil_path_matches():
+    payload = {"values": PACKED + [100, -1000, 10**6, 10**12]}
+    bf = b_fast.BFast()
+    released = bf.encode_packed(payload, compress=False, release_gil=True)
+    assert released == bf.encode_packed(payload, compress=False)
+
+
+def test_int_keys_and_ranges():
+    payload = {-1: "a", 300: range(-5, 70_000, 1000)}
+    bf = b_fast.BFast()
+    decoded = bf.decode_packed(bf.encode_packed(payload, compress=False))
+    assert decoded == payload
@@ -0,0 +1,87 @@
+"""Tests for the wire format description: FORMAT_VERSION, TAGS and spec()"""
+
+import decimal
+import uuid
+from datetime import date, datetime, timedelta
+
+import b_fast
+
+SAMPLE = {
+    "none": None,
+    "flag": True,
+    "small": 3,
+    "big": 10**12,
+    "ratio": 0.5,
+    "text": "hello",
+    "blob": b"\x00\x01",
+    "items": [1, [2, 3]],
+    "when": datetime(2024, 5, 1, 12, 30),
+    "day": date(2024, 5, 1),
+    "span": timedelta(seconds=5),
+    "key": uuid.UUID(int=7),
+    "price": decimal.Decimal("1.25"),
+    "span_range": range(0, 10, 2),
+}
+
+
+def token_tags(tokens):
+    for token in tokens:
+        if isinstance(token, list):
+            yield from token_tags(token)
+        else:
+            yield token[1]
+
+
+def test_format_version_matches_the_header():
+    encoded = b_fast.BFast().encode_packed(SAMPLE, compress=False)
+
+    assert encoded[3] == b_fast.FORMAT_VERSION == b_fast.spec()["version"]
+
+
+def test_tags_name_every_value_written():
+    encoded = b_fast.BFast().encode_packed(SAMPLE, compress=False)
+
+    names = set(token_tags(b_fast.dump_tokens(encoded)["tokens"]))
+
+    # Object keys are listed as "key" tokens
+    assert names - {"key"} <= set(b_fast.TAGS.values())
+
+
+def test_tags_cover_small_ints():
+    assert b_fast.TAGS[0x38] == "int64"
+    assert {b_fast.TAGS[tag] for tag in range(0x30, 0x40) if tag != 0x38} == {"int"}
+    assert {b_fast.TAGS[tag] for tag in range(0xB0, 0xC0)} == {"int"}
+
+
+def test_spec_matches_tags():
+    spec = b_fast.spec()
+
+    assert {tag: entry["name"] for tag, entry in spec["tags"].items()} == b_fast.TAGS
+    assert all(entry["layout"] for entry in spec["tags"].values())
+    assert not set(spec["reserved_tags"]) & set(b_fast.TAGS)
+    assert spec["magic"] == b"BF"
+
+
+def test_extension_range_holds_only_extensions():
+    spec = b_fast.spec()
+
+    assert [tag for tag in b_fast.TAGS if 0xE0 <= tag <= 0xEF] == [0xE0]
+    assert b_fast.TAGS[0xE0] == "ext"
+    assert spec["reserved_tags"] == list(range(0xE1, 0xF0))
+
+
+def test_spec_flags_match_dump_tokens():
+    encoded = b_fast.BFast(columnar=True).encode_packed([SAMPLE], compress=False)
+
+    header = b_fast.dump_tokens(encoded)["header"]
+
+    flags = b_fast.spec()["flags"]
+    assert encoded[2] == sum(flags[name] for name in header["flags"])
+    assert "columnar" in header["flags"]
+
+
+def test_spec_header_fields_in_order():
+    names = [field["name"] for field in b_fast.spec()["header"]]
+
+    assert names[:4] == ["magic", "flags", "version", "string_count"]
+    assert names[-1] == "payload"
@@ -0,0 +1,115 @@
+"""Tests for byte-level operations on encoded lists"""
+
+import pytest
+
+import b_fast
+
+
+def test_concat_frames_from_different_encoders():
+    first = [{"id": i, "name": f"a{i}"} for i in range(10)]
+    second = [{"email": "x@y.z", "id": 99}, {"tags": ["t"], "name": "b"}] * 20
+    third = [1, "two", None, [3.0]]
+
+    merged = b_fast.concat(
+        [
+            b_fast.BFast().encode_packed(first, compress=False),
+            b_fast.BFast().encode_packed(second, compress=True),
+            b_fast.BFast().encode_packed(third, compress=False),
+        ]
+    )
+
+    assert merged[2] & 1  # compressed flag
+    assert b_fast.BFast().decode_packed(merged) == first + second + third
+
+
+def test_concat_frames_from_one_encoder():
+    bf = b_fast.BFast()
+    shards = [[{"shard": n, "row": i} for i in range(50)] for n in range(4)]
+
+    merged = b_fast.concat(
+        (bf.encode_packed(shard, compress=False) for shard in shards), compress=False
+    )
+
+    assert not merged[2] & 1
+    assert bf.decode_packed(merged) == [row for shard in shards for row in shard]
+
+
+def test_concat_empty():
+    assert b_fast.BFast().decode_packed(b_fast.concat([])) == []
+
+
+def test_concat_rejects_non_lists():
+    bf = b_fast.BFast()
+    blobs = [bf.encode_packed([1], compress=False), bf.encode_packed({"a": 1}, False)]
+
+    with pytest.raises(ValueError, match="Frame 1"):
+        b_fast.concat(blobs)
+
+
+ROWS = [{"id": i, "name": f"row-{i}", "tags": ["x"] * (i % 3)} for i in range(40)]
+
+
+@pytest.mark.parametrize(
+    "start, stop",
+    [
+        (0, 10),
+        (10, 20),
+        (35, None),
+        (None, 5),
+        (-5, None),
+        (-10, -2),
+        (30, 100),
+        (20, 10),
+    ],
+)
+def test_slice_matches_python_slicing(start, stop):
+    bf = b_fast.BFast()
+    data = bf.encode_packed(ROWS, compress=True)
+
+    page = b_fast.slice(data, start, stop)
+
+    assert bf.decode_packed(page) == ROWS[start:stop]
+
+
+def test_slice_prunes_string_table():
+    bf = b_fast.BFast()
+    rows = [{"a": 1}] * 10 + [{"only_in_tail": 2}] * 10
+    data = bf.encode_packed(rows, compress=False)
+
+    page = b_fast.slice(data, 0, 10)
+
+    assert b"only_in_tail" not in page
+    assert bf.decode_packed(page) == rows[:10]
+
+
+def test_slice_keeps_compression_of_input():
+    bf = b_fast.BFast()
+
+    plain = b_fast.slice(bf.encode_packed(ROWS, compress=False), 0, 30)
+    packed = b_fast.slice(bf.encode_packed(ROWS, compress=True), 0, 30)
+
+    assert not plain[2] & 1
+    assert packed[2] & 1
+    assert bf.decode_packed(plain) == bf.decode_packed(packed) == ROWS[:30]
+
+
+def frame(payload):
+    header = b"BF\x00\x02" + (0).to_bytes(2, "little")
+    return header + len(payload).to_bytes(4, "little") + payload
+
+
+TAG_OF = {name: tag for tag, name in sorted(b_fast.TAGS.items(), reverse=True)}
+MALFORMED = [
+    # A nested list cut off inside its count
+    frame(b"\x60\x01\x00\x00\x00\x60\x01"),
+    # A custom value whose state runs past the end of the frame
+    frame(b"\x60\x01\x00\x00\x00" + bytes([TAG_OF["custom"]]) + b"\xff\xff\x00\x00"),
+]
+
+
+@pytest.mark.parametrize("data", MALFORMED)
+def test_malformed_elements_raise(data):
+    with pytest.raises(ValueError, match="Unexpected end of buffer"):
+        b_fast.slice(data)
+    with pytest.raises(ValueError, match="Unexpected end of buffer"):
+        b_fast.concat([data])
@@ -0,0 +1,113 @@
+"""Tests for encode-time statistics read with b_fast.stats"""
+
+import io
+from dataclasses import dataclass
+from decimal import Decimal
+
+import pytest
+
+import b_fast
+
+ORDERS = [
+    {"id": i, "total": Decimal("10.50") * i, "qty": i % 5, "paid": i % 2 == 0}
+    for i in range(50)
+]
+
+
+@dataclass
+class Refund:
+    id: int
+    total: float
+
+
+@pytest.mark.parametrize("compress", [False, True])
+def test_stats_without_decoding(compress):
+    bf = b_fast.BFast()
+
+    encoded = bf.encode_packed(ORDERS, compress=compress, stats=["total", "qty"])
+
+    assert b_fast.stats(encoded) == {
+        "records": 50,
+        "types": {"dict": 50},
+        "fields": {
+            "total": {
+                "count": 50,
+                "min": Decimal("0.00"),
+                "max": Decimal("514.50"),
+                "sum": Decimal("12862.50"),
+            },
+            "qty": {"count": 50, "min": 0, "max": 4, "sum": 100},
+        },
+    }
+    assert bf.decode_packed(encoded) == ORDERS
+    assert b_fast.metadata(encoded) is None
+
+
+def test_stats_by_type_and_attribute():
+    records = [Refund(1, 2.5), {"id": 2, "total": 7}, Refund(3, -1.0), {"id": 4}]
+
+    encoded = b_fast.BFast().encode_packed(records, compress=False, stats=["total"])
+
+    stats = b_fast.stats(encoded)
+    assert stats["records"] == 4
+    assert stats["types"] == {"Refund": 2, "dict": 2}
+    assert stats["fields"]["total"] == {"count": 3, "min": -1.0, "max": 7, "sum": 8.5}
+
+
+def test_stats_skip_values_that_are_not_numbers():
+    records = [{"v": True}, {"v": "3"}, {"v": None}, {"v": float("nan")}, {"v": 4}]
+    bf = b_fast.BFast()
+
+    encoded = bf.encode_packed(records, compress=False, stats=["v", "missing"])
+
+    fields = b_fast.stats(encoded)["fields"]
+    assert fields["v"] == {"count": 1, "min": 4, "max": 4, "sum": 4}
+    assert fields["missing"] == {"count": 0, "min": None, "max": None, "sum": 0}
+
+
+def test_stats_beside_metadata():
+    bf = b_fast.BFast()
+
+    encoded = bf.encode_packed(
+        {"id": 7}, compress=False, metadata={"producer": "api"}, stats=[]
+    )
+
+    assert b_fast.metadata(encoded) == {"producer": "api"}
+    assert b_fast.stats(encoded) == {"records": 1, "types": {"dict": 1}, "fields": {}}
+    assert b_fast.get(encoded, "id") == 7
+    assert b_fast.dump_tokens(encoded)["strings"] == [(encoded.index(b"\x02id"), "id")]
+
+
+def test_frames_without_stats():
+    bf = b_fast.BFast()
+
+    encoded = bf.encode_packed(ORDERS, compress=False, metadata={"producer": "api"})
+
+    assert b_fast.stats(encoded) is None
+    assert b_fast.stats(bf.encode_packed(ORDERS, compress=False)) is None
+
+
+def test_stats_on_other_encode_methods():
+    bf = b_fast.BFast()
+    sink = io.BytesIO()
+
+    bf.encode_to(ORDERS, sink, stats=["qty"])
+    deferred = bf.encode_deferred(ORDERS, stats=["qty"]).result()
+
+    assert b_fast.stats(sink.getvalue())["fields"]["qty"]["sum"] == 100
+    assert b_fast.stats(deferred)["fields"]["qty"]["sum"] == 100
+
+
+def test_spliced_frames_drop_stats():
+    bf = b_fast.BFast()
+    encoded = bf.encode_packed(ORDERS, compress=False, stats=["qty"])
+
+    assert b_fast.stats(b_fast.slice(encoded, 0, 10)) is None
+    assert bf.decode_packed(b_fast.slice(encoded, 0, 10)) == ORDERS[:10]
+
+
+def test_stats_reject_fields_that_cannot_be_totalled():
+    records = [{"v": Decimal("1.5")}, {"v": 2.5}]
+
+    with pytest.raises(ValueError, match="Cannot total field 'v'"):
+        b_fast.BFast().encode_packed(records, compress=False, stats=["v"])
@@ -0,0 +1,148 @@
+"""Tests for file-like sinks and sources"""
+
+import gzip
+import io
+
+import pytest
+
+import b_fast
+
+
+def test_bytesio_roundtrip():
+    bf = b_fast.BFast()
+    data = {"items": [{"id": i, "name": f"item_{i}"} for i in range(1000)]}
+
+    sink = io.BytesIO()
+    written = bf.encode_to(data, sink, compress=True)
+    assert written == len(sink.getvalue())
+
+    sink.seek(0)
+    assert bf.decode_from(sink) == data
+
+
+def test_gzip_file_roundtrip(tmp_path):
+    """Payloads larger than one transfer chunk go through several write() calls"""
+    bf = b_fast.BFast()
+    data = {"blob": b"x" * 200_000, "values": list(range(10_000))}
+    path = tmp_path / "payload.bf.gz"
+
+    with gzip.open(path, "wb") as f:
+        bf.encode_to(data, f)
+
+    with gzip.open(path, "rb") as f:
+        assert bf.decode_from(f) == data
+
+
+class Sink:
+    def __init__(self, accepted):
+        self.accepted = accepted
+        self.data = b""
+
+    def write(self, data):
+        taken = max(0, min(self.accepted, len(data)))
+        self.data += bytes(data[:taken])
+        return self.accepted if self.accepted < 1 else taken
+
+
+def test_partial_writes_are_retried():
+    bf = b_fast.BFast()
+    data = {"values": list(range(100))}
+    sink = Sink(7)
+
+    written = bf.encode_to(data, sink, compress=False)
+
+    assert written == len(sink.data)
+    assert bf.decode_packed(sink.data) == data
+
+
+@pytest.mark.parametrize("accepted", [0, -1])
+def test_sink_that_stops_writing_raises(accepted):
+    bf = b_fast.BFast()
+
+    with pytest.raises(OSError, match=f"returned {accepted}"):
+        bf.encode_to({"id": 1}, Sink(accepted), compress=False)
+
+
+def test_non_blocking_sink_raises():
+    """A raw stream in non-blocking mode returns None when it takes nothing"""
+
+    class NonBlockingSink:
+        def write(self, data):
+            return None
+
+    bf = b_fast.BFast()
+
+    with pytest.raises(BlockingIOError, match="non-blocking"):
+        bf.encode_to({"id": 1}, NonBlockingSink(), compress=False)
+
+
+def test_read_only_source():
+    """Sources without readinto() fall back to read(size)"""
+
+    class Source:
+        def __init__(self, payload):
+            self.payload = payload
+            self.pos = 0
+
+        def read(self, size):
+            chunk = self.payload[self.pos : self.pos + size]
+            self.pos += len(chunk)
+            return chunk
+
+    bf = b_fast.BFast()
+    data = {"name": "stream", "values": [1.5, 2.5]}
+    assert bf.decode_from(Source(bf.encode_packed(data, compress=False))) == data
+
+
+def test_decode_mmap_returns_views(tmp_path):
+    bf = b_fast.BFast()
+    data = {"blob": b"\x00\x01" * 1000, "name": "snapshot", "ids": [1, 2, 3]}
+    path = tmp_path / "snapshot.bf"
+    path.write_bytes(bf.encode_packed(data, compress=False))
+
+    decoded = bf.decode_mmap(path)
+
+    assert isinstance(decoded["blob"], memoryview)
+    assert bytes(decoded["blob"]) == data["blob"]
+    assert isinstance(decoded["name"], str)
+    assert decoded["name"] == "snapshot"
+    assert decoded["ids"] == [1, 2, 3]
+
+
+@pytest.mark.parametrize("copy", [False, True])
+def test_decode_mmap_lists_are_lazy(tmp_path, copy):
+    bf = b_fast.BFast()
+    rows = [{"id": i, "name": f"row{i}", "blob": bytes([i])} for i in range(50)]
+    path = tmp_path / "rows.bf"
+    path.write_bytes(bf.encode_packed(rows, compress=False))
+
+    decoded = bf.decode_mmap(path, copy=copy)
+
+    assert isinstance(decoded, b_fast.LazyList)
+    assert decoded.decoded == 0
+    assert decoded[7]["name"] == "row7"
+    assert isinstance(decoded[7]["blob"], bytes if copy else memoryview)
+    assert decoded.decoded == 1
+    assert [dict(row, blob=bytes(row["blob"])) for row in decoded] == rows
+
+
+def test_decode_mmap_copy_and_compressed(tmp_path):
+    bf = b_fast.BFast()
+    data = {"name": "snapshot", "values": list(range(100)) * 5}
+
+    plain = tmp_path / "plain.bf"
+    plain.write_bytes(bf.encode_packed(data, compress=False))
+    assert bf.decode_mmap(plain, copy=True) == data
+
+    packed = tmp_path / "packed.bf"
+    packed.write_bytes(bf.encode_packed(data, compress=True))
+    assert packed.read_bytes()[2] & 1  # compressed flag
+    assert bf.decode_mmap(str(packed)) == data
+
+    rows = tmp_path / "rows.bf"
+    rows.write_bytes(bf.encode_packed(data["values"], compress=True))
+    assert bf.decode_mmap(rows).to_list() == data["values"]
+
+
+if __name__ == "__main__":
+    pytest.main([__file__])
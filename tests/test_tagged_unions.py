@@ -0,0 +1,105 @@
+"""Tests for lists of tagged union records"""
+
+from dataclasses import dataclass
+from typing import Literal
+
+import pytest
+from pydantic import BaseModel
+
+import b_fast
+
+
+class Cat(BaseModel):
+    kind: Literal["cat"] = "cat"
+    name: str
+    lives: int = 9
+
+
+class Dog(BaseModel):
+    kind: Literal["dog"] = "dog"
+    name: str
+    good: bool = True
+
+
+class Point(BaseModel):
+    x: int
+
+
+class Size(BaseModel):
+    width: int
+
+
+@dataclass
+class Added:
+    op: Literal[1]
+    value: int
+
+
+@dataclass
+class Removed:
+    op: Literal[2]
+    reason: str
+
+
+PETS = [Cat(name="Tom"), Dog(name="Rex"), Cat(name="Kit", lives=3)]
+PET_DICTS = [pet.model_dump() for pet in PETS]
+
+
+@pytest.mark.parametrize("case", [(PETS, False), (PETS * 200, True)])
+def test_roundtrip(case):
+    pets, compress = case
+    encoded = b_fast.BFast().encode_packed(pets, compress=compress)
+
+    decoded = b_fast.BFast().decode_packed(encoded, allowed_classes=[Cat, Dog])
+
+    assert decoded == pets
+    assert [type(pet) for pet in decoded] == [type(pet) for pet in pets]
+
+
+def test_without_allowed_classes_records_are_dicts():
+    encoded = b_fast.BFast().encode_packed(PETS, compress=False)
+
+    assert b_fast.BFast().decode_packed(encoded) == PET_DICTS
+    assert b_fast.BFast().decode_packed(encoded, allowed_classes=[Cat]) == [
+        PETS[0],
+        PET_DICTS[1],
+        PETS[2],
+    ]
+
+
+def test_dataclasses():
+    events = [Added(1, 5), Removed(2, "gone")]
+    encoded = b_fast.BFast().encode_packed(events, compress=False)
+
+    decoded = b_fast.BFast().decode_packed(encoded, allowed_classes=[Added, Removed])
+
+    assert decoded == events
+
+
+def test_null_bitmap():
+    encoder = b_fast.BFast(null_bitmap=True)
+    encoded = encoder.encode_packed(PETS, compress=False)
+
+    decoded = b_fast.BFast().decode_packed(encoded, allowed_classes=[Cat, Dog])
+
+    assert decoded == PETS
+
+
+def test_classes_without_discriminator_are_plain_objects():
+    encoded = b_fast.BFast().encode_packed([Point(x=1), Size(width=2)], False)
+
+    decoded = b_fast.BFast().decode_packed(encoded, allowed_classes=[Point, Size])
+
+    assert decoded == [{"x": 1}, {"width": 2}]
+
+
+def test_shared_literal_value_is_not_a_discriminator():
+    class Lion(BaseModel):
+        kind: Literal["cat"] = "cat"
+        name: str
+
+    encoded = b_fast.BFast().encode_packed([Cat(name="a"), Lion(name="b")], False)
+
+    decoded = b_fast.BFast().decode_packed(encoded, allowed_classes=[Cat, Lion])
+
+    assert decoded == [Cat(name="a").model_dump(), {"kind": "cat", "name": "b"}]
@@ -0,0 +1,101 @@
+"""Tests for DLPack tensor support (PyTorch, TensorFlow, NumPy)"""
+
+import ctypes
+
+import numpy as np
+import pytest
+
+import b_fast
+
+
+def test_numpy_int_array_roundtrip():
+    """Non-f64 NumPy arrays go through DLPack with dtype and shape"""
+    bf = b_fast.BFast()
+    array = np.arange(12, dtype=np.int32).reshape(3, 4)
+
+    encoded = bf.encode_packed({"features": array}, compress=False)
+    decoded = bf.decode_packed(encoded, decompress=False)
+
+    assert decoded["features"].dtype == np.int32
+    assert decoded["features"].shape == (3, 4)
+    np.testing.assert_array_equal(decoded["features"], array)
+
+
+def test_non_contiguous_view():
+    """Strided views are copied in logical (row-major) order"""
+    bf = b_fast.BFast()
+    array = np.arange(12, dtype=np.float32).reshape(3, 4).T
+
+    encoded = bf.encode_packed([array], compress=False)
+    decoded = bf.decode_packed(encoded, decompress=False)
+
+    assert decoded[0].shape == (4, 3)
+    np.testing.assert_array_equal(decoded[0], array)
+
+
+def test_torch_tensor_roundtrip():
+    torch = pytest.importorskip("torch")
+    bf = b_fast.BFast()
+    tensor = torch.tensor([[1.5, 2.5], [3.5, 4.5]], dtype=torch.float32)
+
+    encoded = bf.encode_packed({"embedding": tensor}, compress=True)
+    decoded = bf.decode_packed(encoded)
+
+    assert decoded["embedding"].dtype == np.float32
+    np.testing.assert_array_equal(decoded["embedding"], tensor.numpy())
+
+
+class DLTensor(ctypes.Structure):
+    _fields_ = [
+        ("data", ctypes.c_void_p),
+        ("device", ctypes.c_int32 * 2),
+        ("ndim", ctypes.c_int32),
+        ("dtype", ctypes.c_uint8 * 4),
+        ("shape", ctypes.POINTER(ctypes.c_int64)),
+        ("strides", ctypes.c_void_p),
+        ("byte_offset", ctypes.c_uint64),
+    ]
+
+
+class DLManagedTensor(ctypes.Structure):
+    _fields_ = [
+        ("dl_tensor", DLTensor),
+        ("manager_ctx", ctypes.c_void_p),
+        ("deleter", ctypes.c_void_p),
+    ]
+
+
+class RawTensor:
+    """A one-element float32 CPU tensor exported through a hand-built capsule"""
+
+    def __init__(self, ndim):
+        self.value = ctypes.c_float(1.5)
+        self.shape = (ctypes.c_int64 * ndim)(*[1] * ndim)
+        self.managed = DLManagedTensor()
+        tensor = self.managed.dl_tensor
+        tensor.data = ctypes.addressof(self.value)
+        tensor.device[:] = [1, 0]
+        tensor.ndim = ndim
+        tensor.dtype[:] = [2, 32, 1, 0]
+        tensor.shape = self.shape
+
+    def __dlpack_device__(self):
+        return (1, 0)
+
+    def __dlpack__(self):
+        new = ctypes.pythonapi.PyCapsule_New
+        new.restype = ctypes.py_object
+        new.argtypes = [ctypes.c_void_p, ctypes.c_char_p, ctypes.c_void_p]
+        return new(ctypes.addressof(self.managed), b"dltensor", None)
+
+
+@pytest.mark.parametrize("ndim", [256, 1000])
+def test_tensor_rank_beyond_header_raises(ndim):
+    bf = b_fast.BFast()
+
+    with pytest.raises(OverflowError, match="more than 255 dims"):
+        bf.encode_packed({"t": RawTensor(ndim)}, compress=False)
+
+
+if __name__ == "__main__":
+    pytest.main([__file__])
@@ -0,0 +1,145 @@
+"""Tests for b_fast.to_json"""
+
+import array
+import datetime
+import decimal
+import json
+import math
+import uuid
+
+import pytest
+
+import b_fast
+
+RECORD = {
+    "id": 7,
+    "name": 'Ana "Bo" café \U0001f600\n',
+    "score": 9.5,
+    "active": True,
+    "note": None,
+    "tags": ("a", "b"),
+    "nested": {"deep": [[1, [2, {"x": []}]]], 3: "three"},
+    "big": -(2**63),
+    "born": datetime.date(1990, 5, 17),
+    "seen": datetime.datetime(2024, 1, 2, 3, 4, 5, 678000),
+    "aware": datetime.datetime(2024, 1, 2, 3, 4, 5, tzinfo=datetime.timezone.utc),
+    "alarm": datetime.time(6, 30),
+    "token": uuid.UUID("12345678-1234-5678-1234-567812345678"),
+    "price": decimal.Decimal("19.90"),
+    "wait": datetime.timedelta(seconds=90),
+    "samples": array.array("h", [-2, 0, 300]),
+    "ratios": array.array("f", [0.5, 0.25]),
+    "steps": range(10, 0, -3),
+}
+ORDERS = [
+    {"id": i, "status": "open", "customer": {"name": f"c{i}", "email": f"c{i}@x"}}
+    for i in range(20)
+]
+
+
+class Point:
+    def __init__(self, x, y):
+        self.x = x
+        self.y = y
+
+    def __getstate__(self):
+        return {"x": self.x, "y": self.y}
+
+
+def plain(encoded):
+    decoded = b_fast.BFast().decode_packed(
+        encoded, allowed_classes=[], ranges_as_lists=True
+    )
+    return json.loads(json.dumps(decoded))
+
+
+@pytest.mark.parametrize(
+    "case",
+    [
+        ({}, {"compress": False}),
+        ({}, {"compress": True}),
+        ({"datetime_nanos": True}, {"compress": False}),
+        ({"float32": True}, {"compress": False}),
+        ({"schema": b_fast.BFastSchema(list(RECORD), id=0xD100)}, {"compress": False}),
+        ({}, {"compress": False, "sections": True}),
+        ({}, {"compress": False, "metadata": {"source": "cache"}}),
+    ],
+)
+def test_matches_plain_decode(case):
+    options, encode_options = case
+    encoded = b_fast.BFast(**options).encode_packed(RECORD, **encode_options)
+
+    result = b_fast.to_json(encoded)
+
+    assert json.loads(result) == plain(encoded)
+    assert result.startswith(b'{"id":7,"name":"Ana \\"Bo\\" caf\xc3\xa9 ')
+
+
+@pytest.mark.parametrize(
+    "value", [[], {}, "", 0, -1, 2**40, 0.1, [True, False] * 9, [Point(1, 2)]]
+)
+def test_values(value):
+    encoded = b_fast.BFast().encode_packed(value, compress=False)
+
+    assert json.loads(b_fast.to_json(encoded)) == plain(encoded)
+
+
+def test_batch_path_records():
+    encoded = b_fast.BFast().encode_packed(ORDERS, compress=False)
+
+    assert json.loads(b_fast.to_json(encoded)) == ORDERS
+
+
+@pytest.mark.parametrize(
+    "case",
+    [
+        (["id"], [{"id": i} for i in range(20)]),
+        (
+            ["id", "customer.email", "missing"],
+            [{"id": i, "customer": {"email": f"c{i}@x"}} for i in range(20)],
+        ),
+        (["customer"], [{"customer": order["customer"]} for order in ORDERS]),
+        ([], [{} for _ in ORDERS]),
+    ],
+)
+def test_fields(case):
+    fields, expected = case
+    encoded = b_fast.BFast().encode_packed(ORDERS, compress=True)
+
+    result = b_fast.to_json(encoded, fields=fields)
+
+    assert json.loads(result) == expected
+
+
+def test_fields_of_root_record_and_dotted_names():
+    record = {"id": 1, "address.city": "Recife", "address": {"city": "Olinda"}}
+    encoded = b_fast.BFast().encode_packed(record, compress=False)
+
+    result = b_fast.to_json(encoded, fields=["address.city"])
+
+    assert json.loads(result) == {
+        "address.city": "Recife",
+        "address": {"city": "Olinda"},
+    }
+
+
+def test_non_finite_floats_become_null():
+    encoded = b_fast.BFast().encode_packed([math.nan, math.inf, 1.5], compress=False)
+
+    assert b_fast.to_json(encoded) == b"[null,null,1.5]"
+
+
+@pytest.mark.parametrize(
+    "case",
+    [
+        ({}, {"blob": b"raw"}, "bytes values have no JSON form"),
+        ({"dedup": True}, [[1], [1]], "does not support frames encoded with dedup"),
+        ({"columnar": True}, ORDERS, "does not support frames encoded with columnar"),
+    ],
+)
+def test_errors(case):
+    options, value, message = case
+    encoded = b_fast.BFast(**options).encode_packed(value, compress=False)
+
+    with pytest.raises(ValueError, match=message):
+        b_fast.to_json(encoded)
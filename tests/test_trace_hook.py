@@ -0,0 +1,72 @@
+"""Tests for set_trace_hook"""
+
+import pytest
+
+import b_fast
+
+ROWS = [{"id": i, "name": f"user-{i}", "tags": ["a", "b"]} for i in range(200)]
+
+
+def traced(data, **kwargs):
+    calls = []
+    b_fast.set_trace_hook(lambda *args: calls.append(args))
+    try:
+        encoded = b_fast.BFast().encode_packed(data, **kwargs)
+    finally:
+        b_fast.set_trace_hook(None)
+    return encoded, calls
+
+
+def test_stages_in_order():
+    encoded, calls = traced(ROWS, compress=False)
+
+    assert [stage for stage, _, _ in calls] == ["serialize", "string_table", "pybytes"]
+    assert all(isinstance(ns, int) and ns >= 0 for _, ns, _ in calls)
+    assert calls[1][2] == calls[2][2] == len(encoded)
+    assert calls[0][2] < len(encoded)
+
+
+def test_compression_stage():
+    encoded, calls = traced(ROWS, compress=True)
+
+    assert [stage for stage, _, _ in calls] == [
+        "serialize",
+        "string_table",
+        "compress",
+        "pybytes",
+    ]
+    assert calls[2][2] == len(encoded) < calls[1][2]
+
+
+def test_removed_hook_is_not_called():
+    calls = []
+    b_fast.set_trace_hook(lambda *args: calls.append(args))
+    b_fast.set_trace_hook(None)
+
+    b_fast.BFast().encode_packed(ROWS, compress=False)
+
+    assert calls == []
+
+
+def test_hook_errors_propagate():
+    def hook(stage, duration_ns, nbytes):
+        raise RuntimeError(stage)
+
+    b_fast.set_trace_hook(hook)
+    try:
+        with pytest.raises(RuntimeError, match="serialize"):
+            b_fast.BFast().encode_packed(ROWS, compress=False)
+    finally:
+        b_fast.set_trace_hook(None)
+
+
+def test_non_callable_hook_fails():
+    with pytest.raises(TypeError, match="callable"):
+        b_fast.set_trace_hook("print")
+
+
+def test_released_stage():
+    encoded, calls = traced(ROWS, compress=True, release_gil=True)
+
+    assert [stage for stage, _, _ in calls] == ["released", "pybytes"]
+    assert calls[0][2] == calls[1][2] == len(encoded)
@@ -0,0 +1,93 @@
+"""Tests for array.array values (typed array tag)"""
+
+from array import array
+
+import pytest
+
+import b_fast
+
+
+@pytest.mark.parametrize("typecode", ["b", "B", "h", "H", "i", "I", "q", "Q"])
+def test_integer_arrays(typecode):
+    values = array(typecode, [0, 1, 2, 100, 127])
+    bf = b_fast.BFast()
+
+    encoded = bf.encode_packed(values, compress=False)
+    decoded = bf.decode_packed(encoded)
+
+    assert isinstance(decoded, array)
+    assert decoded.itemsize == values.itemsize
+    assert decoded == values
+
+
+@pytest.mark.parametrize("typecode", ["f", "d"])
+def test_float_arrays(typecode):
+    values = array(typecode, [0.5, -1.25, 3.0])
+    bf = b_fast.BFast()
+
+    encoded = bf.encode_packed(values, compress=False)
+
+    assert bf.decode_packed(encoded) == values
+
+
+def test_long_arrays_use_fixed_width_codes():
+    values = array("l", [-(2**31), 2**31 - 1])
+    bf = b_fast.BFast()
+
+    encoded = bf.encode_packed(values, compress=False)
+    decoded = bf.decode_packed(encoded)
+
+    assert decoded.tolist() == values.tolist()
+    assert decoded.typecode in ("i", "q")
+    assert decoded.itemsize == values.itemsize
+
+
+def test_items_are_stored_raw():
+    values = array("d", range(1000))
+
+    encoded = b_fast.BFast().encode_packed(values, compress=False)
+
+    assert len(encoded) < 8 * 1000 + 20
+
+
+def test_text_arrays_decode_as_strings():
+    bf = b_fast.BFast()
+
+    encoded = bf.encode_packed({"chars": array("u", "hello")}, compress=False)
+
+    assert bf.decode_packed(encoded) == {"chars": "hello"}
+
+
+def test_empty_and_nested_arrays():
+    data = {"empty": array("i"), "rows": [array("H", [1, 2])]}
+    bf = b_fast.BFast()
+
+    encoded = bf.encode_packed(data, compress=False)
+    decoded = bf.decode_packed(encoded)
+
+    assert decoded == data
+
+
+def test_allowlist_and_immutable_fall_back():
+    values = array("i", [3, 4])
+    bf = b_fast.BFast()
+
+    encoded = bf.encode_packed(values, compress=False)
+
+    assert bf.decode_packed(encoded, allowed_classes=[]) == [3, 4]
+    assert bf.decode_packed(encoded, immutable=True) == (3, 4)
+
+
+def test_dump_tokens():
+    encoded = b_fast.BFast().encode_packed(array("d", [1.0, 2.0]), compress=False)
+
+    tokens = b_fast.dump_tokens(encoded)["tokens"]
+
+    assert tokens == [(10, "typed_array", 22, "16 bytes of 'd'")]
+
+
+def test_truncated_payload_raises():
+    encoded = b_fast.BFast().encode_packed(array("q", [1, 2]), compress=False)
+
+    with pytest.raises(ValueError):
+        b_fast.BFast().decode_packed(encoded[:-3], decompress=False)
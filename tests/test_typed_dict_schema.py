@@ -0,0 +1,78 @@
+"""Tests for schemas built from TypedDicts"""
+
+from typing import List, Optional, TypedDict
+
+import pytest
+
+import b_fast
+
+
+class Tag(TypedDict):
+    name: str
+
+
+class Base(TypedDict):
+    id: int
+
+
+class Click(Base):
+    kind: str
+    tags: List[Tag]
+
+
+class Event(Click, total=False):
+    note: Optional[str]
+
+
+class Sparse(TypedDict, total=False):
+    a: int
+    b: str
+
+
+EVENTS = [{"id": i, "kind": "click", "tags": [{"name": "x"}]} for i in range(20)]
+
+
+def test_fields_from_annotations():
+    schema = b_fast.BFastSchema(Event)
+
+    assert schema.fields == ["id", "kind", "tags", "note"]
+    assert schema.name == "Event"
+
+
+@pytest.mark.parametrize("compress", [False, True])
+def test_roundtrip(compress):
+    bf = b_fast.BFast(schema=b_fast.BFastSchema(Event))
+
+    encoded = bf.encode_packed(EVENTS, compress=compress)
+
+    assert b_fast.BFast().decode_packed(encoded) == EVENTS
+    assert b"kind" not in bf.encode_packed(EVENTS, compress=False)
+
+
+def test_validate_optional_keys():
+    bf = b_fast.BFast(schema=b_fast.BFastSchema(Event), validate=True)
+
+    bf.encode_packed(EVENTS + [{**EVENTS[0], "note": None}], compress=False)
+    with pytest.raises(ValueError, match="missing field 'kind'"):
+        bf.encode_packed([{"id": 1, "tags": []}], compress=False)
+    with pytest.raises(ValueError, match="field 'note' expects"):
+        bf.encode_packed([{**EVENTS[0], "note": 3}], compress=False)
+
+
+def test_nested_typed_dict_values_are_dicts():
+    class Wrapper(TypedDict):
+        event: Event
+
+    bf = b_fast.BFast(schema=b_fast.BFastSchema(Wrapper), validate=True)
+
+    bf.encode_packed({"event": EVENTS[0]}, compress=False)
+    with pytest.raises(ValueError, match="field 'event' expects Event, not list"):
+        bf.encode_packed({"event": []}, compress=False)
+
+
+def test_total_false():
+    bf = b_fast.BFast(schema=b_fast.BFastSchema(Sparse), validate=True)
+
+    encoded = bf.encode_packed([{}, {"a": 1}, {"b": "x"}], compress=False)
+
+    assert b_fast.BFast().decode_packed(encoded) == [{}, {"a": 1}, {"b": "x"}]
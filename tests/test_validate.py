@@ -0,0 +1,116 @@
+"""Tests for encoding with validate=True"""
+
+from dataclasses import dataclass, field
+from typing import Any, List, Optional
+
+import pytest
+from pydantic import BaseModel
+
+import b_fast
+
+
+@dataclass
+class Address:
+    city: str
+
+
+@dataclass
+class Order:
+    id: int
+    total: float
+    note: Optional[str] = None
+    tags: List[str] = field(default_factory=list)
+    extra: Any = None
+    address: Optional[Address] = None
+
+
+class Customer(BaseModel):
+    id: int
+    name: str
+    vip: bool = False
+
+
+ORDER_SCHEMA = b_fast.BFastSchema(Order)
+GOOD = [
+    Order(1, 9.5),
+    {"id": 2, "total": 3},
+    {"id": 3, "total": 1.0, "note": None, "tags": ["a"], "extra": object},
+    {"id": 4, "total": 2.0, "address": {"city": "Recife"}},
+    {"id": 5, "total": 2.0, "address": Address("Natal")},
+]
+BAD = [
+    ({"id": 1}, "missing field 'total'"),
+    ({"id": "1", "total": 1.0}, "field 'id' expects int, not str"),
+    ({"id": 1, "total": 1.0, "qty": 2}, "unknown field 'qty'"),
+    ({"id": 1, "total": 1.0, "note": 3}, "field 'note' expects typing.Optional"),
+    ({"id": 1, "total": 1.0, "tags": "a"}, "field 'tags' expects typing.List"),
+    ({"id": 1, "total": None}, "field 'total' expects float, not NoneType"),
+    (7, "expected a dict or an object, not int"),
+]
+
+
+def test_matching_records_encode():
+    bf = b_fast.BFast(schema=ORDER_SCHEMA, validate=True)
+
+    encoded = bf.encode_packed(GOOD[:4], compress=False)
+
+    assert len(b_fast.BFast().decode_packed(encoded)) == 4
+
+
+@pytest.mark.parametrize("case", BAD)
+def test_mismatches_are_refused(case):
+    record, message = case
+    bf = b_fast.BFast(schema=ORDER_SCHEMA, validate=True)
+    expected = f"Record 1 does not match schema Order: {message}"
+
+    with pytest.raises(ValueError, match=expected):
+        bf.encode_packed([GOOD[0], record], compress=False)
+
+
+def test_root_record():
+    bf = b_fast.BFast(schema=ORDER_SCHEMA, validate=True)
+
+    with pytest.raises(ValueError, match="^Record does not match schema Order"):
+        bf.encode_packed({"id": 1}, compress=False)
+
+
+def test_pydantic_model():
+    bf = b_fast.BFast(schema=b_fast.BFastSchema(Customer), validate=True)
+    customers = [Customer(id=1, name="Ana"), {"id": 2, "name": "Bo"}]
+
+    bf.encode_packed(customers, compress=True)
+    with pytest.raises(ValueError, match="field 'vip' expects bool, not str"):
+        bf.encode_packed([{"id": 3, "name": "Cy", "vip": "yes"}], compress=True)
+
+
+def test_field_list_schema_checks_names_only():
+    schema = b_fast.BFastSchema(["sku", "qty"], id=0xB100)
+    bf = b_fast.BFast(schema=schema, validate=True)
+
+    bf.encode_packed([{"sku": 1, "qty": "many"}], compress=False)
+    with pytest.raises(ValueError, match="schema ID 45312: missing field 'qty'"):
+        bf.encode_packed([{"sku": "a"}], compress=False)
+
+
+def test_every_encode_path_validates():
+    bf = b_fast.BFast(schema=ORDER_SCHEMA, validate=True)
+
+    with pytest.raises(ValueError, match="missing field"):
+        bf.encode_packed([{"id": 1}], compress=False, release_gil=True)
+    with pytest.raises(ValueError, match="missing field"):
+        bf.encode_deferred([{"id": 1}])
+    with pytest.raises(ValueError, match="^Record does not match"):
+        bf.encode_packed_split([GOOD[0], {"id": 1}], 1024)
+
+
+def test_without_validate_mismatches_encode():
+    bf = b_fast.BFast(schema=ORDER_SCHEMA)
+
+    encoded = bf.encode_packed([{"id": "x"}], compress=False)
+
+    assert b_fast.BFast().decode_packed(encoded) == [{"id": "x"}]
+
+
+def test_validate_requires_a_schema():
+    with pytest.raises(ValueError, match="validate requires a schema"):
+        b_fast.BFast(validate=True)